@@ -0,0 +1,71 @@
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use recs::prelude::*;
+
+#[derive(Component)]
+struct Position {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Component)]
+struct Velocity {
+    dx: f32,
+    dy: f32,
+}
+
+const ENTITIES: usize = 10_000;
+
+fn populated_registry() -> Registry {
+    let mut registry = Registry::new();
+    for i in 0..ENTITIES {
+        let position = Position {
+            x: i as f32,
+            y: 0.0,
+        };
+        // Only half of the entities move, so the join has something to skip
+        if i % 2 == 0 {
+            registry.spawn((position, Velocity { dx: 1.0, dy: 1.0 }));
+        } else {
+            registry.spawn((position,));
+        }
+    }
+    registry
+}
+
+fn single_component(c: &mut Criterion) {
+    let mut registry = populated_registry();
+
+    c.bench_function("iter (&Position,)", |b| {
+        b.iter(|| {
+            let mut sum = 0.0;
+            for (pos,) in registry.query::<(&Position,)>() {
+                sum += pos.x;
+            }
+            black_box(sum)
+        })
+    });
+
+    c.bench_function("iter (&mut Position,)", |b| {
+        b.iter(|| {
+            for (mut pos,) in registry.query::<(&mut Position,)>() {
+                pos.y += 1.0;
+            }
+        })
+    });
+}
+
+fn joined_components(c: &mut Criterion) {
+    let mut registry = populated_registry();
+
+    c.bench_function("iter (&mut Position, &Velocity)", |b| {
+        b.iter(|| {
+            for (mut pos, vel) in registry.query::<(&mut Position, &Velocity)>() {
+                pos.x += vel.dx;
+                pos.y += vel.dy;
+            }
+        })
+    });
+}
+
+criterion_group!(benches, single_component, joined_components);
+criterion_main!(benches);
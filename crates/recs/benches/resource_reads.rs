@@ -0,0 +1,71 @@
+//! Many systems reading the same resource, as with a config resource read by
+//! every gameplay system.
+//!
+//! Resources aren't behind locks: the executor only runs systems with
+//! compatible accesses together, and shared reads are compatible, so readers
+//! dereference the resource directly. The parallel run should scale with the
+//! number of cores rather than serialize on the shared resource.
+
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use recs::{prelude::*, system::executor::ExecutorKind};
+
+#[derive(Resource)]
+struct Config {
+    scale: f32,
+}
+
+#[derive(Component)]
+struct Position {
+    x: f32,
+}
+
+const ENTITIES: usize = 10_000;
+
+/// Reads `Config` once per entity, to keep the resource hot under contention
+fn reader<const N: usize>(config: Res<Config>, query: Query<(&Position,)>) {
+    let mut sum = 0.0;
+    for (position,) in query {
+        sum += position.x * config.scale;
+    }
+    black_box(sum);
+}
+
+fn registry_with_readers(executor: ExecutorKind) -> Registry {
+    let mut registry = Registry::new();
+    registry.set_executor(executor);
+    registry.insert_resource(Config { scale: 0.5 });
+    registry.spawn_batch((0..ENTITIES).map(|i| (Position { x: i as f32 },)));
+
+    registry.add_system(reader::<0>);
+    registry.add_system(reader::<1>);
+    registry.add_system(reader::<2>);
+    registry.add_system(reader::<3>);
+    registry.add_system(reader::<4>);
+    registry.add_system(reader::<5>);
+    registry.add_system(reader::<6>);
+    registry.add_system(reader::<7>);
+    registry.add_system(reader::<8>);
+    registry.add_system(reader::<9>);
+    registry.add_system(reader::<10>);
+    registry.add_system(reader::<11>);
+    registry.add_system(reader::<12>);
+    registry.add_system(reader::<13>);
+    registry.add_system(reader::<14>);
+    registry.add_system(reader::<15>);
+    registry
+}
+
+fn shared_resource_reads(c: &mut Criterion) {
+    let mut group = c.benchmark_group("16 systems reading Res<Config>");
+    for (name, executor) in [
+        ("sequential", ExecutorKind::Sequential),
+        ("parallel", ExecutorKind::Parallel),
+    ] {
+        let mut registry = registry_with_readers(executor);
+        group.bench_function(name, |b| b.iter(|| registry.run_systems()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, shared_resource_reads);
+criterion_main!(benches);
@@ -28,7 +28,7 @@ fn main() {
 }
 
 fn movement_system(query: Query<(&mut Position, &Velocity)>) {
-    for (pos, vel) in query {
+    for (mut pos, vel) in query {
         pos.x += vel.dx;
         pos.y += vel.dy;
     }
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use recs::prelude::*;
 
 #[derive(Component)]
@@ -13,21 +15,6 @@ struct Velocity {
 }
 
 // Resources
-#[derive(Resource, Debug, Clone)]
-struct GameTime {
-    delta_time: f32,
-    total_time: f32,
-}
-
-impl Default for GameTime {
-    fn default() -> Self {
-        Self {
-            delta_time: 0.016, // ~60 FPS
-            total_time: 0.0,
-        }
-    }
-}
-
 #[derive(Resource, Debug, Clone)]
 struct GameConfig {
     gravity: f32,
@@ -52,7 +39,9 @@ struct GameStats {
 fn main() {
     let mut registry = Registry::new();
 
-    registry.init_resource::<GameTime>();
+    // The registry advances `Time` every frame; a manual clock keeps the
+    // example deterministic
+    registry.insert_resource(Time::manual(Duration::from_millis(16)));
     registry.init_resource::<GameConfig>();
     registry.init_resource::<GameStats>();
 
@@ -74,20 +63,19 @@ fn main() {
     registry.add_system(stats_system);
 
     for frame in 0..30 {
-        if let Some(time) = registry.get_resource_mut::<GameTime>() {
-            time.delta_time = 0.016 + (frame as f32 * 0.0001);
-            time.total_time += time.delta_time;
+        if let Some(time) = registry.get_resource_mut::<Time>() {
+            time.set_manual_delta(Duration::from_micros(16_000 + frame * 100));
         }
 
         registry.run_systems();
 
-        if frame % 50 == 0 {
-            if let Some(stats) = registry.get_resource::<GameStats>() {
-                println!(
-                    "Frame {}: Moved: {}, Out of bounds: {}",
-                    frame, stats.entities_moved, stats.out_of_bounds_entities
-                );
-            }
+        if frame % 50 == 0
+            && let Some(stats) = registry.get_resource::<GameStats>()
+        {
+            println!(
+                "Frame {}: Moved: {}, Out of bounds: {}",
+                frame, stats.entities_moved, stats.out_of_bounds_entities
+            );
         }
     }
 
@@ -96,41 +84,44 @@ fn main() {
     }
 }
 
-fn time_system(time: ResMut<GameTime>) {
+fn time_system(time: Res<Time>) {
     println!(
         "Game time: {:.2}s, Delta: {:.4}s",
-        time.total_time, time.delta_time
+        time.elapsed_secs(),
+        time.delta_secs()
     );
 }
 
 fn movement_system(
     query: Query<(&mut Position, &Velocity)>,
-    time: Res<GameTime>,
+    time: Res<Time>,
     config: Res<GameConfig>,
     mut stats: ResMut<GameStats>,
 ) {
     stats.entities_moved = 0;
 
-    for (pos, vel) in query {
-        let gravity_effect = config.gravity * time.delta_time;
+    let delta = time.delta_secs();
+    for (mut pos, vel) in query {
+        let gravity_effect = config.gravity * delta;
 
-        pos.x += vel.dx * time.delta_time;
-        pos.y += vel.dy * time.delta_time + gravity_effect * time.delta_time;
+        pos.x += vel.dx * delta;
+        pos.y += vel.dy * delta + gravity_effect * delta;
 
         let speed = (vel.dx * vel.dx + vel.dy * vel.dy).sqrt();
+        #[allow(clippy::needless_ifs)]
         if speed > config.max_speed {}
 
         stats.entities_moved += 1;
     }
 }
 
-fn stats_system(optional_time: OptionalRes<GameTime>, optional_stats: OptionalRes<GameStats>) {
-    if let (Some(time), Some(stats)) = (optional_time.as_ref(), optional_stats.as_ref()) {
-        if time.total_time as u32 % 2 == 0 {
-            println!(
-                "Periodic stats check - Entities moved: {}",
-                stats.entities_moved
-            );
-        }
+fn stats_system(optional_time: Option<Res<Time>>, optional_stats: Option<Res<GameStats>>) {
+    if let (Some(time), Some(stats)) = (optional_time, optional_stats)
+        && (time.elapsed_secs() as u32).is_multiple_of(2)
+    {
+        println!(
+            "Periodic stats check - Entities moved: {}",
+            stats.entities_moved
+        );
     }
 }
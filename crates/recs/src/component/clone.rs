@@ -0,0 +1,83 @@
+use std::{
+    any::{Any, TypeId},
+    collections::{HashMap, HashSet},
+};
+
+use crate::{component::Component, entity::Entity, registry::Registry};
+
+/// Maps entities of a source registry to their copies in a target registry
+pub type EntityMap = HashMap<Entity, Entity>;
+
+type CloneFn = Box<dyn Fn(&Registry, Entity, &EntityMap) -> Option<Box<dyn Any>> + Send + Sync>;
+
+/// Storage for per-component clone functions, used to clone entities or copy
+/// them into another registry. Registered through `Component::CLONE`,
+/// `Registry::register_component_clone` and
+/// `Registry::register_component_clone_with`.
+#[derive(Default)]
+pub struct ComponentCloners {
+    /// In registration order, so that copies are deterministic
+    cloners: Vec<(TypeId, CloneFn)>,
+}
+
+impl ComponentCloners {
+    /// Creates a new empty ComponentCloners
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the clone function of `C`, replacing any previous one.
+    ///
+    /// `clone` receives the map of every copied entity, so components
+    /// referring to other entities can point to their copies.
+    pub fn insert<C: Component>(
+        &mut self,
+        clone: impl Fn(&C, &EntityMap) -> C + Send + Sync + 'static,
+    ) {
+        let type_id = TypeId::of::<C>();
+        let clone_fn: CloneFn = Box::new(move |source, entity, map| {
+            let component = source.get_component::<C>(entity)?;
+            Some(Box::new(clone(component, map)))
+        });
+
+        match self.cloners.iter_mut().find(|(id, _)| *id == type_id) {
+            Some((_, existing)) => *existing = clone_fn,
+            None => self.cloners.push((type_id, clone_fn)),
+        }
+    }
+
+    /// Registers the clone function declared by `C::CLONE`, unless one is
+    /// registered already
+    pub(crate) fn register_declared<C: Component>(&mut self) {
+        if let Some(clone) = C::CLONE
+            && !self.contains::<C>()
+        {
+            self.insert(move |component: &C, _| clone(component));
+        }
+    }
+
+    /// Checks if a clone function is registered for `C`
+    pub fn contains<C: Component>(&self) -> bool {
+        self.contains_id(TypeId::of::<C>())
+    }
+
+    pub(crate) fn contains_id(&self, type_id: TypeId) -> bool {
+        self.cloners.iter().any(|(id, _)| *id == type_id)
+    }
+
+    /// Clones every component of `entity` in `source` that has a clone
+    /// function and isn't `stripped`, returning them with their types
+    pub(crate) fn clone_components(
+        &self,
+        source: &Registry,
+        entity: Entity,
+        map: &EntityMap,
+        stripped: &HashSet<TypeId>,
+    ) -> Vec<(TypeId, Box<dyn Any>)> {
+        self.cloners
+            .iter()
+            .filter(|(type_id, _)| !stripped.contains(type_id))
+            .filter_map(|(type_id, clone)| Some((*type_id, clone(source, entity, map)?)))
+            .collect()
+    }
+}
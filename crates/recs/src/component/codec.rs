@@ -0,0 +1,183 @@
+use std::{
+    any::{Any, type_name},
+    collections::BTreeMap,
+};
+
+use crate::{
+    component::{Component, ComponentTicks, sparse_set::SparseSet},
+    entity::Entity,
+    error::RecsError,
+    registry::Registry,
+};
+
+type PackFn = Box<dyn Fn(&dyn Any, &mut Vec<u8>) + Send + Sync>;
+type UnpackFn = Box<dyn Fn(&[u8]) -> Option<Box<dyn Any>> + Send + Sync>;
+type AddFn = fn(&mut Registry, Entity, Box<dyn Any>) -> Result<(), RecsError>;
+
+/// The packed bytes of one component, tagged with the name of its codec
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PackedComponent {
+    /// Name of the codec that packed the component, see `ComponentCodecs`
+    pub name: String,
+    /// The packed component data
+    pub bytes: Vec<u8>,
+}
+
+/// Type-erased pack and unpack functions of a single component type
+struct ComponentCodec {
+    pack: PackFn,
+    unpack: UnpackFn,
+    /// Returns the component of an entity
+    get: for<'r> fn(&'r Registry, Entity) -> Option<&'r dyn Any>,
+    /// Adds an unpacked component to an entity
+    add: AddFn,
+}
+
+/// The components of a sparse set packed with their codec, along with their
+/// entities and change ticks, as kept by rollback snapshots
+pub(crate) struct PackedStorage {
+    components: Vec<(Entity, ComponentTicks, Vec<u8>)>,
+}
+
+/// Storage for per-component pack/unpack functions, keyed by component type name.
+///
+/// Snapshot and replication code uses these codecs to turn components into
+/// compact byte strings (e.g. quantized transforms or delta-encoded health)
+/// instead of serializing them field by field: serde saves, incremental
+/// snapshots and rollback snapshots all store the packed form of components
+/// that have a codec. Codecs are registered through
+/// `Registry::register_component_codec`.
+#[derive(Default)]
+pub struct ComponentCodecs {
+    /// Ordered by name so that packed entities have a deterministic layout
+    codecs: BTreeMap<&'static str, ComponentCodec>,
+}
+
+impl ComponentCodecs {
+    /// Creates a new empty ComponentCodecs
+    pub fn new() -> Self {
+        Self {
+            codecs: BTreeMap::new(),
+        }
+    }
+
+    /// Registers the codec for `C`, replacing any previous one
+    pub fn insert<C: Component>(
+        &mut self,
+        pack: impl Fn(&C, &mut Vec<u8>) + Send + Sync + 'static,
+        unpack: impl Fn(&[u8]) -> Option<C> + Send + Sync + 'static,
+    ) {
+        let codec = ComponentCodec {
+            pack: Box::new(move |component, bytes| {
+                let component = component
+                    .downcast_ref::<C>()
+                    .expect("Codecs only pack their own component type");
+                pack(component, bytes)
+            }),
+            unpack: Box::new(move |bytes| Some(Box::new(unpack(bytes)?))),
+            get: |registry, entity| {
+                registry
+                    .get_component::<C>(entity)
+                    .map(|component| component as &dyn Any)
+            },
+            add: |registry, entity, component| {
+                let component = component
+                    .downcast::<C>()
+                    .expect("Codecs only unpack their own component type");
+                registry.add_component(entity, *component)
+            },
+        };
+        self.codecs.insert(type_name::<C>(), codec);
+    }
+
+    /// Checks if a codec is registered for `C`
+    pub fn contains<C: Component>(&self) -> bool {
+        self.codecs.contains_key(std::any::type_name::<C>())
+    }
+
+    /// Packs `component` with the codec of `C`, or returns None if `C` has
+    /// no codec
+    pub(crate) fn pack_value<C: Component>(&self, component: &C) -> Option<Vec<u8>> {
+        let codec = self.codecs.get(type_name::<C>())?;
+        let mut bytes = Vec::new();
+        (codec.pack)(component, &mut bytes);
+        Some(bytes)
+    }
+
+    /// Unpacks a `C` packed by `pack_value`
+    pub(crate) fn unpack_value<C: Component>(&self, bytes: &[u8]) -> Result<C, RecsError> {
+        let codec = self
+            .codecs
+            .get(type_name::<C>())
+            .ok_or_else(|| RecsError::UnknownComponentCodec(type_name::<C>().to_string()))?;
+        (codec.unpack)(bytes)
+            .and_then(|component| component.downcast::<C>().ok())
+            .map(|component| *component)
+            .ok_or(RecsError::InvalidComponentData(type_name::<C>()))
+    }
+
+    /// Packs every component of `sparse_set`, or returns None if `C` has no
+    /// codec
+    pub(crate) fn pack_storage<C: Component>(
+        &self,
+        sparse_set: &SparseSet<C>,
+    ) -> Option<PackedStorage> {
+        let components = sparse_set
+            .iter_with_entities()
+            .map(|(entity, component)| {
+                let ticks = *sparse_set
+                    .get_ticks(entity.id() as usize)
+                    .expect("Stored components have ticks");
+                Some((entity, ticks, self.pack_value(component)?))
+            })
+            .collect::<Option<_>>()?;
+        Some(PackedStorage { components })
+    }
+
+    /// Unpacks the components of `packed` into a new sparse set, keeping
+    /// their change ticks
+    pub(crate) fn unpack_storage<C: Component>(
+        &self,
+        packed: &PackedStorage,
+    ) -> Result<SparseSet<C>, RecsError> {
+        let mut sparse_set = SparseSet::new();
+        for (entity, ticks, bytes) in &packed.components {
+            let component = self.unpack_value(bytes)?;
+            sparse_set.insert_with_ticks(*entity, component, *ticks);
+        }
+        Ok(sparse_set)
+    }
+
+    /// Packs every component of `entity` that has a codec
+    pub(crate) fn pack(&self, registry: &Registry, entity: Entity) -> Vec<PackedComponent> {
+        let mut packed = Vec::new();
+        for (name, codec) in &self.codecs {
+            if let Some(component) = (codec.get)(registry, entity) {
+                let mut bytes = Vec::new();
+                (codec.pack)(component, &mut bytes);
+                packed.push(PackedComponent {
+                    name: name.to_string(),
+                    bytes,
+                });
+            }
+        }
+        packed
+    }
+
+    /// Unpacks `packed` with its codec and inserts the component on `entity`
+    pub(crate) fn unpack(
+        &self,
+        registry: &mut Registry,
+        entity: Entity,
+        packed: &PackedComponent,
+    ) -> Result<(), RecsError> {
+        let (&name, codec) = self
+            .codecs
+            .get_key_value(packed.name.as_str())
+            .ok_or_else(|| RecsError::UnknownComponentCodec(packed.name.clone()))?;
+        let component =
+            (codec.unpack)(&packed.bytes).ok_or(RecsError::InvalidComponentData(name))?;
+        (codec.add)(registry, entity, component)
+    }
+}
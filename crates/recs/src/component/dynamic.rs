@@ -0,0 +1,229 @@
+use std::alloc::Layout;
+
+use crate::{component::ComponentTicks, entity::Entity, tick::Tick};
+
+/// Identifies a component type registered at runtime with
+/// `Registry::register_dynamic_component`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ComponentId(u32);
+
+impl ComponentId {
+    /// Returns the index of the component type in registration order
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A sparse set of untyped component values, each stored as `layout.size()`
+/// bytes packed back to back.
+///
+/// Values are only guaranteed to be byte aligned, so they should be decoded
+/// with e.g. `from_le_bytes` rather than cast to typed references.
+#[derive(Clone)]
+pub(crate) struct DynamicStorage {
+    name: String,
+    layout: Layout,
+    /// Packed values, `layout.size()` bytes each
+    dense: Vec<u8>,
+    /// Entities of the values in the dense array
+    entities: Vec<Entity>,
+    /// Sparse array mapping entity ids to indices in the dense array
+    sparse: Vec<Option<usize>>,
+    ticks: Vec<ComponentTicks>,
+}
+
+impl DynamicStorage {
+    fn new(name: String, layout: Layout) -> Self {
+        Self {
+            name,
+            layout,
+            dense: Vec::new(),
+            entities: Vec::new(),
+            sparse: Vec::new(),
+            ticks: Vec::new(),
+        }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    pub(crate) fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    fn dense_index(&self, id: usize) -> Option<usize> {
+        self.sparse.get(id).copied().flatten()
+    }
+
+    fn bytes(&self, dense_index: usize) -> std::ops::Range<usize> {
+        let size = self.layout.size();
+        dense_index * size..(dense_index + 1) * size
+    }
+
+    pub(crate) fn contains(&self, id: usize) -> bool {
+        self.dense_index(id).is_some()
+    }
+
+    /// Inserts or overwrites the value of `entity`. `value` must be exactly
+    /// `layout.size()` bytes long.
+    pub(crate) fn insert(&mut self, entity: Entity, value: &[u8], tick: Tick) {
+        let id = entity.id() as usize;
+        if let Some(dense_index) = self.dense_index(id) {
+            let range = self.bytes(dense_index);
+            self.dense[range].copy_from_slice(value);
+            self.entities[dense_index] = entity;
+            self.ticks[dense_index].changed = tick;
+            return;
+        }
+
+        if id >= self.sparse.len() {
+            self.sparse.resize(id + 1, None);
+        }
+        self.sparse[id] = Some(self.entities.len());
+        self.dense.extend_from_slice(value);
+        self.entities.push(entity);
+        self.ticks.push(ComponentTicks::new(tick));
+    }
+
+    /// Removes the value of the entity with this id, moving the last value
+    /// into its place
+    pub(crate) fn remove(&mut self, id: usize) -> Option<Vec<u8>> {
+        let dense_index = self.dense_index(id)?;
+        let last_index = self.entities.len() - 1;
+        let removed = self.dense[self.bytes(dense_index)].to_vec();
+
+        if dense_index != last_index {
+            let (last, gap) = (self.bytes(last_index), self.bytes(dense_index));
+            self.dense.copy_within(last, gap.start);
+            self.entities.swap(dense_index, last_index);
+            self.ticks.swap(dense_index, last_index);
+            self.sparse[self.entities[dense_index].id() as usize] = Some(dense_index);
+        }
+        self.dense.truncate(last_index * self.layout.size());
+        self.entities.pop();
+        self.ticks.pop();
+        self.sparse[id] = None;
+
+        Some(removed)
+    }
+
+    pub(crate) fn get(&self, id: usize) -> Option<&[u8]> {
+        let dense_index = self.dense_index(id)?;
+        Some(&self.dense[self.bytes(dense_index)])
+    }
+
+    /// Returns the value of the entity with this id mutably, marking it as
+    /// changed at `tick`
+    pub(crate) fn get_mut(&mut self, id: usize, tick: Tick) -> Option<&mut [u8]> {
+        let dense_index = self.dense_index(id)?;
+        self.ticks[dense_index].changed = tick;
+        let range = self.bytes(dense_index);
+        Some(&mut self.dense[range])
+    }
+
+    pub(crate) fn ticks(&self, id: usize) -> Option<ComponentTicks> {
+        self.dense_index(id)
+            .map(|dense_index| self.ticks[dense_index])
+    }
+}
+
+/// Every dynamic component type, indexed by `ComponentId`
+#[derive(Default, Clone)]
+pub(crate) struct DynamicComponents {
+    storages: Vec<DynamicStorage>,
+}
+
+impl DynamicComponents {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clamps the change ticks of every dynamic component, see
+    /// `Tick::check_tick`
+    pub(crate) fn check_change_ticks(&mut self, this_run: Tick) {
+        for storage in &mut self.storages {
+            for ticks in &mut storage.ticks {
+                ticks.check_ticks(this_run);
+            }
+        }
+    }
+
+    pub(crate) fn register(&mut self, name: String, layout: Layout) -> ComponentId {
+        let id = ComponentId(self.storages.len() as u32);
+        self.storages.push(DynamicStorage::new(name, layout));
+        id
+    }
+
+    pub(crate) fn id(&self, name: &str) -> Option<ComponentId> {
+        self.storages
+            .iter()
+            .position(|storage| storage.name == name)
+            .map(|index| ComponentId(index as u32))
+    }
+
+    pub(crate) fn get(&self, id: ComponentId) -> Option<&DynamicStorage> {
+        self.storages.get(id.index())
+    }
+
+    pub(crate) fn get_mut(&mut self, id: ComponentId) -> Option<&mut DynamicStorage> {
+        self.storages.get_mut(id.index())
+    }
+
+    /// Returns mutable references to the storages of `ids`, in that order.
+    /// Returns None if an id is unknown or repeated.
+    pub(crate) fn get_many_mut(&mut self, ids: &[ComponentId]) -> Option<Vec<&mut DynamicStorage>> {
+        let mut storages: Vec<Option<&mut DynamicStorage>> = Vec::new();
+        storages.resize_with(ids.len(), || None);
+        for (index, storage) in self.storages.iter_mut().enumerate() {
+            let mut positions = ids.iter().enumerate().filter(|(_, id)| id.index() == index);
+            if let Some((position, _)) = positions.next() {
+                if positions.next().is_some() {
+                    return None;
+                }
+                storages[position] = Some(storage);
+            }
+        }
+        storages.into_iter().collect()
+    }
+
+    /// Removes every dynamic component of the entity with this id
+    pub(crate) fn remove_all(&mut self, id: usize) {
+        for storage in &mut self.storages {
+            storage.remove(id);
+        }
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &DynamicStorage> {
+        self.storages.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_moves_last_value_into_the_gap() {
+        let mut storage = DynamicStorage::new("Pair".into(), Layout::new::<[u16; 2]>());
+        let entities: Vec<_> = (0..3).map(|id| Entity::new(id, 0)).collect();
+        for (value, &entity) in entities.iter().enumerate() {
+            storage.insert(entity, &[value as u8; 4], Tick::new(1));
+        }
+
+        assert_eq!(storage.remove(0), Some(vec![0; 4]));
+        assert_eq!(storage.remove(0), None);
+        assert_eq!(storage.entities(), &[entities[2], entities[1]]);
+        assert_eq!(storage.get(2), Some(&[2u8; 4][..]));
+        assert_eq!(storage.get(1), Some(&[1u8; 4][..]));
+
+        storage.get_mut(2, Tick::new(5)).unwrap()[0] = 9;
+        assert_eq!(storage.get(2), Some(&[9, 2, 2, 2][..]));
+        assert_eq!(storage.ticks(2).unwrap().changed, Tick::new(5));
+        assert_eq!(storage.ticks(1).unwrap().changed, Tick::new(1));
+    }
+}
@@ -0,0 +1,49 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+use crate::{component::Component, entity::Entity, registry::Registry};
+
+/// A boxed factory that builds a component for an entity on first access
+pub type ComponentFactory<C> = Box<dyn Fn(&Registry, Entity) -> C + Send + Sync>;
+
+/// Storage for lazy component factories, keyed by component type.
+///
+/// A factory is invoked by the Registry the first time a component is
+/// requested through `get_or_init_component` on an entity that lacks it.
+#[derive(Default)]
+pub struct ComponentFactories {
+    factories: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl ComponentFactories {
+    /// Creates a new empty ComponentFactories
+    pub fn new() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// Registers the factory for `C`, replacing any previous one
+    pub fn insert<C: Component>(&mut self, factory: ComponentFactory<C>) {
+        self.factories.insert(TypeId::of::<C>(), Box::new(factory));
+    }
+
+    /// Gets the factory registered for `C` if it exists
+    pub fn get<C: Component>(&self) -> Option<&ComponentFactory<C>> {
+        self.factories
+            .get(&TypeId::of::<C>())
+            .and_then(|factory| factory.downcast_ref::<ComponentFactory<C>>())
+    }
+
+    /// Checks if a factory is registered for `C`
+    pub fn contains<C: Component>(&self) -> bool {
+        self.factories.contains_key(&TypeId::of::<C>())
+    }
+
+    /// Removes the factory registered for `C`, returning true if one existed
+    pub fn remove<C: Component>(&mut self) -> bool {
+        self.factories.remove(&TypeId::of::<C>()).is_some()
+    }
+}
@@ -0,0 +1,74 @@
+use std::any::{Any, TypeId};
+
+use crate::{
+    component::Component, entity::Entity, system::commands::Commands, type_map::TypeIdMap,
+};
+
+/// A hook run when a component is added to or removed from an entity, see
+/// `Registry::on_add` and `Registry::on_remove`
+pub type ComponentHook<C> = fn(Entity, &C, &mut Commands);
+
+/// A hook with its component type erased
+pub(crate) type ErasedHook = Box<dyn Fn(Entity, &dyn Any, &mut Commands) + Send + Sync>;
+
+/// The lifecycle hooks of the component types, by type
+#[derive(Default)]
+pub(crate) struct ComponentHooks {
+    on_add: TypeIdMap<ErasedHook>,
+    on_remove: TypeIdMap<ErasedHook>,
+}
+
+impl ComponentHooks {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the hooks `C` declares through `Component::ON_ADD` and
+    /// `Component::ON_REMOVE`, unless hooks were already set for it
+    pub(crate) fn register<C: Component>(&mut self) {
+        let type_id = TypeId::of::<C>();
+        if let Some(hook) = C::ON_ADD {
+            self.on_add.entry(type_id).or_insert_with(|| erase(hook));
+        }
+        if let Some(hook) = C::ON_REMOVE {
+            self.on_remove.entry(type_id).or_insert_with(|| erase(hook));
+        }
+    }
+
+    /// Sets the hook run when a `C` component is added
+    pub(crate) fn set_on_add<C: Component>(
+        &mut self,
+        hook: impl Fn(Entity, &C, &mut Commands) + Send + Sync + 'static,
+    ) {
+        self.on_add.insert(TypeId::of::<C>(), erase(hook));
+    }
+
+    /// Sets the hook run when a `C` component is removed
+    pub(crate) fn set_on_remove<C: Component>(
+        &mut self,
+        hook: impl Fn(Entity, &C, &mut Commands) + Send + Sync + 'static,
+    ) {
+        self.on_remove.insert(TypeId::of::<C>(), erase(hook));
+    }
+
+    /// Returns the hook run when a component of the type is added
+    pub(crate) fn on_add(&self, type_id: TypeId) -> Option<&ErasedHook> {
+        self.on_add.get(&type_id)
+    }
+
+    /// Returns the hook run when a component of the type is removed
+    pub(crate) fn on_remove(&self, type_id: TypeId) -> Option<&ErasedHook> {
+        self.on_remove.get(&type_id)
+    }
+}
+
+fn erase<C: Component>(
+    hook: impl Fn(Entity, &C, &mut Commands) + Send + Sync + 'static,
+) -> ErasedHook {
+    Box::new(move |entity, component, commands| {
+        let component = component
+            .downcast_ref::<C>()
+            .expect("Hooks are called with components of their type");
+        hook(entity, component, commands)
+    })
+}
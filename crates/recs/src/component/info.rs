@@ -0,0 +1,43 @@
+use std::{any::TypeId, collections::HashMap};
+
+use crate::component::Component;
+
+/// The readable names of the component types registered with a registry,
+/// whatever their storage
+#[derive(Default)]
+pub(crate) struct ComponentNames {
+    names: HashMap<TypeId, &'static str>,
+    /// Every registered type, in the order they were first registered
+    order: Vec<TypeId>,
+}
+
+impl ComponentNames {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the name of `C`
+    pub(crate) fn register<C: Component>(&mut self) {
+        let previous = self
+            .names
+            .insert(TypeId::of::<C>(), std::any::type_name::<C>());
+        if previous.is_none() {
+            self.order.push(TypeId::of::<C>());
+        }
+    }
+
+    /// Returns every registered component type, in registration order
+    pub(crate) fn order(&self) -> &[TypeId] {
+        &self.order
+    }
+
+    /// Returns the name of the component type, if it was registered
+    pub(crate) fn get(&self, type_id: TypeId) -> Option<&'static str> {
+        self.names.get(&type_id).copied()
+    }
+
+    /// Returns every registered component type with its name
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (TypeId, &'static str)> + '_ {
+        self.names.iter().map(|(&type_id, &name)| (type_id, name))
+    }
+}
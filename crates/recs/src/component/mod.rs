@@ -1,6 +1,22 @@
 use std::any::Any;
 
+use crate::{
+    component::{hooks::ComponentHook, required::RequiredComponent, storages::StorageCache},
+    entity::Entity,
+    tick::Tick,
+};
+
+pub mod clone;
+pub mod codec;
+pub mod dynamic;
+pub mod factory;
+pub mod hooks;
+pub(crate) mod info;
+pub mod removed;
+pub mod required;
 pub mod sparse_set;
+pub mod storages;
+pub mod table;
 
 /// A trait for types that can be used as components in the RECS system.
 ///
@@ -11,14 +27,129 @@ pub mod sparse_set;
 ///
 /// Components are pure data containers that can be attached to entities.
 /// They should not contain any behavior - that belongs in systems.
-pub trait Component: Send + Sync + 'static {}
+///
+/// The derive macro picks the storage of the component type with
+/// `#[component(storage = "table")]`, `#[component(storage = "sparse_map")]`
+/// or `#[component(storage = "sparse")]`, declares lifecycle hooks with
+/// `#[component(on_add = path)]` and `#[component(on_remove = path)]`, and
+/// required components with `#[component(requires(Transform, Velocity))]`.
+/// `#[component(clone)]` makes the component type clonable through its
+/// `Clone` impl.
+pub trait Component: Send + Sync + 'static {
+    /// Where the registry stores components of this type
+    const STORAGE: StorageType = StorageType::SparseSet;
+
+    /// Runs when a component of this type is added to an entity, see
+    /// `Registry::on_add`
+    const ON_ADD: Option<ComponentHook<Self>> = None;
+
+    /// Runs when a component of this type is removed from an entity, see
+    /// `Registry::on_remove`
+    const ON_REMOVE: Option<ComponentHook<Self>> = None;
+
+    /// Components inserted with their default value when a component of this
+    /// type is added to an entity lacking them, see `RequiredComponent`
+    const REQUIRES: &'static [RequiredComponent] = &[];
+
+    /// Clones components of this type for `Registry::clone_entity` and
+    /// `Registry::copy_entities_to`, registered along with their storage
+    const CLONE: Option<fn(&Self) -> Self> = None;
+
+    /// Remembers the id of the storage of this type, so that registries find
+    /// it without hashing the `TypeId`. Implemented by `#[derive(Component)]`;
+    /// the storages of other types are looked up by `TypeId`.
+    #[doc(hidden)]
+    fn storage_cache() -> Option<&'static StorageCache> {
+        None
+    }
+}
+
+/// Where the components of a type are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageType {
+    /// A sparse set: adding and removing the component is cheap, which suits
+    /// tags and other frequently toggled components
+    #[default]
+    SparseSet,
+    /// An archetype table, see `Registry::register_table_component`: queries
+    /// over several such components iterate contiguously, which suits hot,
+    /// rarely added or removed components
+    Table,
+    /// A sparse set whose entity index is a hash map rather than an array
+    /// covering every entity id: lookups are slower, but memory only grows
+    /// with the number of components, which suits components attached to a
+    /// handful of entities among millions
+    SparseMap,
+}
+
+/// Records when a component was added to an entity and when it was last changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComponentTicks {
+    /// The tick at which the component was inserted
+    pub added: Tick,
+    /// The tick at which the component was last mutably accessed
+    pub changed: Tick,
+}
+
+impl ComponentTicks {
+    /// Creates ticks for a component that was just inserted at `tick`
+    pub fn new(tick: Tick) -> Self {
+        Self {
+            added: tick,
+            changed: tick,
+        }
+    }
+
+    /// Returns true if the component was added after `last_run`
+    pub fn is_added(&self, last_run: Tick, this_run: Tick) -> bool {
+        self.added.is_newer_than(last_run, this_run)
+    }
+
+    /// Returns true if the component was changed after `last_run`
+    pub fn is_changed(&self, last_run: Tick, this_run: Tick) -> bool {
+        self.changed.is_newer_than(last_run, this_run)
+    }
+
+    /// Clamps both ticks to at most `MAX_CHANGE_AGE` ticks before `this_run`
+    pub fn check_ticks(&mut self, this_run: Tick) {
+        self.added.check_tick(this_run);
+        self.changed.check_tick(this_run);
+    }
+}
 
 /// Internal trait for component storage implementations.
 /// Provides a type-erased way to store and remove components.
 ///
 /// This trait is implemented by SparseSet and allows the Registry
 /// to manage components without knowing their concrete types.
-pub trait ComponentStorage: Any {
+pub trait ComponentStorage: Any + Send + Sync {
     /// Removes a component by its entity ID and returns it boxed as Any
     fn remove_by_id(&mut self, id: usize) -> Option<Box<dyn Any>>;
+
+    /// Returns the number of components stored
+    fn len(&self) -> usize;
+
+    /// Returns true if no components are stored
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the type name of the stored component
+    fn component_name(&self) -> &'static str;
+
+    /// Returns the entities that have a component in this storage
+    fn entities(&self) -> &[Entity];
+
+    /// Checks if the entity with `id` has a component in this storage
+    fn contains(&self, id: usize) -> bool;
+
+    /// Returns the position of the component of the entity with `id` in the
+    /// storage order, if it has one
+    fn dense_index_of(&self, id: usize) -> Option<usize>;
+
+    /// Swaps the components at positions `a` and `b` of the storage order
+    fn swap_dense(&mut self, a: usize, b: usize);
+
+    /// Clamps the change ticks of every component, see `Tick::check_tick`
+    fn check_change_ticks(&mut self, this_run: Tick);
 }
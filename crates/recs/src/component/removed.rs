@@ -0,0 +1,110 @@
+use std::{any::TypeId, marker::PhantomData};
+
+use crate::{component::Component, entity::Entity, tick::Tick, type_map::TypeIdMap};
+
+/// Buffers the entities whose components were removed, keyed by component type.
+///
+/// Every removal is stamped with the change tick at which it happened, so each
+/// reader only sees the removals that occurred since it last ran. The Registry
+/// prunes entries once every system has had a chance to observe them.
+#[derive(Default)]
+pub struct RemovedComponentStorage {
+    removed: TypeIdMap<Vec<(Entity, Tick)>>,
+}
+
+impl RemovedComponentStorage {
+    /// Creates a new empty RemovedComponentStorage
+    pub fn new() -> Self {
+        Self {
+            removed: TypeIdMap::default(),
+        }
+    }
+
+    /// Records that the component with `type_id` was removed from `entity` at `tick`
+    pub fn record(&mut self, type_id: TypeId, entity: Entity, tick: Tick) {
+        self.removed
+            .entry(type_id)
+            .or_default()
+            .push((entity, tick));
+    }
+
+    /// Returns every buffered removal of component `C`
+    pub fn get<C: Component>(&self) -> &[(Entity, Tick)] {
+        self.removed
+            .get(&TypeId::of::<C>())
+            .map_or(&[], |removed| removed.as_slice())
+    }
+
+    /// Drops every removal recorded before `tick`, as seen from `this_run`
+    pub fn prune_older_than(&mut self, tick: Tick, this_run: Tick) {
+        for removed in self.removed.values_mut() {
+            removed.retain(|(_, removed_at)| {
+                *removed_at == tick || removed_at.is_newer_than(tick, this_run)
+            });
+        }
+    }
+
+    /// Clears all buffered removals
+    pub fn clear(&mut self) {
+        self.removed.clear();
+    }
+}
+
+/// A system parameter that yields the entities whose `C` component was removed
+/// (directly or by destroying the entity) since the system last ran.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// #[derive(Component)]
+/// struct Collider;
+///
+/// fn cleanup_physics(removed: RemovedComponents<Collider>) {
+///     for entity in removed {
+///         println!("remove body for {:?}", entity);
+///     }
+/// }
+/// # let mut registry = Registry::new();
+/// # registry.add_system(cleanup_physics);
+/// # registry.run_systems();
+/// ```
+pub struct RemovedComponents<'a, C> {
+    removed: &'a [(Entity, Tick)],
+    last_run: Tick,
+    this_run: Tick,
+    _phantom: PhantomData<C>,
+}
+
+impl<'a, C: Component> RemovedComponents<'a, C> {
+    pub fn new(removed: &'a [(Entity, Tick)], last_run: Tick, this_run: Tick) -> Self {
+        Self {
+            removed,
+            last_run,
+            this_run,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the entities whose component was removed
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + use<'a, C> {
+        let (last_run, this_run) = (self.last_run, self.this_run);
+        self.removed
+            .iter()
+            .filter(move |(_, tick)| tick.is_newer_than(last_run, this_run))
+            .map(|(entity, _)| *entity)
+    }
+
+    /// Returns true if no removals happened since the system last ran
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+impl<'a, C: Component> IntoIterator for RemovedComponents<'a, C> {
+    type Item = Entity;
+    type IntoIter = Box<dyn Iterator<Item = Entity> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
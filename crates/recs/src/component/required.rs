@@ -0,0 +1,110 @@
+use crate::{component::Component, entity::Entity, registry::Registry};
+
+/// A component type that another component requires, declared with
+/// `#[component(requires(Transform))]` or through `Component::REQUIRES`.
+///
+/// When a component is added to an entity, the registry inserts the default
+/// value of every required component the entity still lacks once the whole
+/// bundle is in, so values given alongside in the bundle win over defaults.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// #[derive(Component, Default)]
+/// struct Transform { x: f32 }
+///
+/// #[derive(Component)]
+/// #[component(requires(Transform))]
+/// struct Sprite;
+///
+/// let mut registry = Registry::new();
+/// let entity = registry.spawn((Sprite,));
+/// assert!(registry.has_component::<Transform>(entity));
+///
+/// let placed = registry.spawn((Sprite, Transform { x: 4.0 }));
+/// assert_eq!(registry.get_component::<Transform>(placed).unwrap().x, 4.0);
+/// ```
+#[derive(Clone, Copy)]
+pub struct RequiredComponent {
+    insert: fn(&mut Registry, Entity),
+}
+
+impl RequiredComponent {
+    /// Requires `R`, inserted with its `Default` value
+    pub const fn of<R: Component + Default>() -> Self {
+        Self {
+            insert: insert_default::<R>,
+        }
+    }
+
+    /// Inserts the default value of the required component on `entity`,
+    /// unless it already has one
+    pub(crate) fn insert(&self, registry: &mut Registry, entity: Entity) {
+        (self.insert)(registry, entity)
+    }
+}
+
+impl std::fmt::Debug for RequiredComponent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RequiredComponent").finish_non_exhaustive()
+    }
+}
+
+fn insert_default<R: Component + Default>(registry: &mut Registry, entity: Entity) {
+    if registry.is_valid(entity) && !registry.has_component::<R>(entity) {
+        // The entity is valid, so adding can't fail
+        let _ = registry.add_component(entity, R::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::registry::Registry;
+
+    #[derive(crate::Component, Debug, Default, PartialEq)]
+    struct Transform(i32);
+
+    #[derive(crate::Component, Debug, Default, PartialEq)]
+    #[component(requires(Transform))]
+    struct Visibility(bool);
+
+    #[derive(crate::Component)]
+    #[component(requires(Visibility))]
+    struct Sprite;
+
+    #[test]
+    fn test_required_components_are_inserted_transitively() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn((Sprite,));
+        assert_eq!(
+            registry.get_component::<Visibility>(entity),
+            Some(&Visibility(false))
+        );
+        assert_eq!(
+            registry.get_component::<Transform>(entity),
+            Some(&Transform(0))
+        );
+
+        let other = registry.spawn((Transform(3),));
+        registry.add_component(other, Sprite).unwrap();
+        assert_eq!(
+            registry.get_component::<Transform>(other),
+            Some(&Transform(3))
+        );
+        assert!(registry.has_component::<Visibility>(other));
+    }
+
+    #[test]
+    fn test_bundle_values_win_over_defaults() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn((Sprite, (Visibility(true), Transform(7))));
+        assert_eq!(
+            registry.get_component::<Visibility>(entity),
+            Some(&Visibility(true))
+        );
+        assert_eq!(
+            registry.get_component::<Transform>(entity),
+            Some(&Transform(7))
+        );
+    }
+}
@@ -1,12 +1,20 @@
 use std::{
     any::Any,
-    mem::replace,
+    cmp::Ordering,
+    collections::HashMap,
+    fmt,
+    hash::BuildHasherDefault,
+    marker::PhantomData,
+    mem::{replace, take},
     slice::{Iter, IterMut},
 };
 
 use crate::{
-    component::{Component, ComponentStorage},
+    component::{Component, ComponentStorage, ComponentTicks, StorageType},
     entity::Entity,
+    query::Mut,
+    tick::Tick,
+    type_map::IdHasher,
 };
 
 /// A sparse set implementation for efficiently storing and accessing components.
@@ -19,51 +27,252 @@ use crate::{
 /// - O(1) component access by entity ID
 /// - Cache-friendly iteration over components
 /// - Memory efficient storage for sparse data
-#[derive(Debug)]
-pub struct SparseSet<C> {
+///
+/// The sparse array is an `I: SparseIndex`, by default picked from
+/// `C::STORAGE`, see `StorageIndex`.
+#[derive(Debug, Clone)]
+pub struct SparseSet<C, I = StorageIndex<C>> {
     /// Dense array of components, tightly packed with no gaps
     dense: Vec<C>,
     /// Parallel array of entities corresponding to components in the dense array
     pub(crate) entities: Vec<Entity>,
-    /// Sparse array mapping entity IDs to indices in the dense array
-    sparse: Vec<Option<usize>>,
+    /// Maps entity IDs to indices in the dense array
+    sparse: I,
+    /// Parallel array of change detection ticks for components in the dense array
+    ticks: Vec<ComponentTicks>,
+}
+
+/// Maps entity ids to positions in the dense array of a `SparseSet`
+pub trait SparseIndex: Default + Clone + fmt::Debug + Send + Sync + 'static {
+    /// Returns the dense position of the entity id
+    fn get(&self, id: usize) -> Option<usize>;
+
+    /// Points the entity id at a dense position
+    fn set(&mut self, id: usize, index: usize);
+
+    /// Forgets the entity id
+    fn clear(&mut self, id: usize);
+
+    /// Makes room for `additional` more ids, all lower than `id_bound`
+    fn reserve(&mut self, additional: usize, id_bound: usize);
+}
+
+/// A slot for every entity id up to the highest stored one
+#[derive(Debug, Clone, Default)]
+pub struct VecIndex(Vec<Option<usize>>);
+
+impl SparseIndex for VecIndex {
+    #[inline]
+    fn get(&self, id: usize) -> Option<usize> {
+        self.0.get(id).copied().flatten()
+    }
+
+    fn set(&mut self, id: usize, index: usize) {
+        if id >= self.0.len() {
+            self.0.resize(id + 1, None);
+        }
+        self.0[id] = Some(index);
+    }
+
+    fn clear(&mut self, id: usize) {
+        if let Some(slot) = self.0.get_mut(id) {
+            *slot = None;
+        }
+    }
+
+    fn reserve(&mut self, _additional: usize, id_bound: usize) {
+        if id_bound > self.0.len() {
+            self.0.resize(id_bound, None);
+        }
+    }
+}
+
+/// Only the stored entity ids, hashed with `IdHasher`, see
+/// `StorageType::SparseMap`
+#[derive(Debug, Clone, Default)]
+pub struct MapIndex(HashMap<usize, usize, BuildHasherDefault<IdHasher>>);
+
+impl MapIndex {
+    /// Returns the number of indexed entity ids
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if no entity id is indexed
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl SparseIndex for MapIndex {
+    #[inline]
+    fn get(&self, id: usize) -> Option<usize> {
+        self.0.get(&id).copied()
+    }
+
+    fn set(&mut self, id: usize, index: usize) {
+        self.0.insert(id, index);
+    }
+
+    fn clear(&mut self, id: usize) {
+        self.0.remove(&id);
+    }
+
+    fn reserve(&mut self, additional: usize, _id_bound: usize) {
+        self.0.reserve(additional);
+    }
+}
+
+/// The index of `SparseSet<C>`: a `MapIndex` if `C::STORAGE` is
+/// `StorageType::SparseMap`, a `VecIndex` otherwise. `C::STORAGE` is a
+/// constant, so the unused index is compiled out of every access.
+pub struct StorageIndex<C> {
+    vec: VecIndex,
+    map: MapIndex,
+    marker: PhantomData<fn() -> C>,
+}
+
+impl<C: Component> StorageIndex<C> {
+    const MAP: bool = matches!(C::STORAGE, StorageType::SparseMap);
+}
+
+impl<C> Default for StorageIndex<C> {
+    fn default() -> Self {
+        Self {
+            vec: VecIndex::default(),
+            map: MapIndex::default(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<C> Clone for StorageIndex<C> {
+    fn clone(&self) -> Self {
+        Self {
+            vec: self.vec.clone(),
+            map: self.map.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<C: Component> fmt::Debug for StorageIndex<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if Self::MAP {
+            self.map.fmt(f)
+        } else {
+            self.vec.fmt(f)
+        }
+    }
 }
 
-impl<C> SparseSet<C>
+impl<C: Component> SparseIndex for StorageIndex<C> {
+    #[inline]
+    fn get(&self, id: usize) -> Option<usize> {
+        if Self::MAP {
+            self.map.get(id)
+        } else {
+            self.vec.get(id)
+        }
+    }
+
+    fn set(&mut self, id: usize, index: usize) {
+        if Self::MAP {
+            self.map.set(id, index)
+        } else {
+            self.vec.set(id, index)
+        }
+    }
+
+    fn clear(&mut self, id: usize) {
+        if Self::MAP {
+            self.map.clear(id)
+        } else {
+            self.vec.clear(id)
+        }
+    }
+
+    fn reserve(&mut self, additional: usize, id_bound: usize) {
+        if Self::MAP {
+            self.map.reserve(additional, id_bound)
+        } else {
+            self.vec.reserve(additional, id_bound)
+        }
+    }
+}
+
+impl<C: Component, I: SparseIndex> Default for SparseSet<C, I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C, I> SparseSet<C, I>
 where
     C: Component,
+    I: SparseIndex,
 {
     /// Creates a new empty SparseSet
     pub fn new() -> Self {
         Self {
             dense: Vec::new(),
             entities: Vec::new(),
-            sparse: Vec::new(),
+            sparse: I::default(),
+            ticks: Vec::new(),
         }
     }
 
     /// Inserts or updates a component for an entity
     ///
-    /// If the entity already has this component type, it will be updated.
-    /// Otherwise, the component will be added to the end of the dense array.
-    pub fn insert(&mut self, entity: Entity, component: C) {
-        let id = entity.id() as usize;
-        if id >= self.sparse.len() {
-            self.sparse.resize(id + 1, None);
-        }
+    /// If the entity already has this component type, it will be updated and
+    /// marked as changed at `tick`. Otherwise, the component will be added to
+    /// the end of the dense array and marked as added at `tick`.
+    pub fn insert(&mut self, entity: Entity, component: C, tick: Tick) {
+        self.replace(entity, component, tick);
+    }
 
-        if let Some(&dense_index) = self.sparse.get(id).and_then(|x| x.as_ref()) {
-            if let Some(c) = self.dense.get_mut(dense_index) {
-                *c = component;
-            }
+    /// Inserts or updates a component for an entity like `insert`, returning
+    /// the component it replaced, if any
+    pub fn replace(&mut self, entity: Entity, component: C, tick: Tick) -> Option<C> {
+        let id = entity.id() as usize;
+        if let Some(dense_index) = self.sparse.get(id) {
+            let old = std::mem::replace(&mut self.dense[dense_index], component);
             self.entities[dense_index] = entity;
-            return;
+            self.ticks[dense_index].changed = tick;
+            return Some(old);
         }
 
         let new_index = self.dense.len();
         self.dense.push(component);
-        self.sparse[id] = Some(new_index);
+        self.sparse.set(id, new_index);
         self.entities.push(entity);
+        self.ticks.push(ComponentTicks::new(tick));
+        None
+    }
+
+    /// Inserts or updates a component for an entity like `insert`, giving it
+    /// `ticks` instead of marking it as added or changed now
+    pub(crate) fn insert_with_ticks(
+        &mut self,
+        entity: Entity,
+        component: C,
+        ticks: ComponentTicks,
+    ) {
+        self.insert(entity, component, ticks.changed);
+        let index = self
+            .sparse
+            .get(entity.id() as usize)
+            .expect("The component was just inserted");
+        self.ticks[index] = ticks;
+    }
+
+    /// Reserves room for `additional` more components, and grows the sparse
+    /// array to cover entity ids below `id_bound`
+    pub fn reserve(&mut self, additional: usize, id_bound: usize) {
+        self.dense.reserve(additional);
+        self.entities.reserve(additional);
+        self.ticks.reserve(additional);
+        self.sparse.reserve(additional, id_bound);
     }
 
     /// Removes a component by entity ID
@@ -74,52 +283,131 @@ where
     /// When a component is removed, the last component in the dense array
     /// is moved to fill its place, maintaining packed storage.
     pub fn remove(&mut self, id: usize) -> Option<C> {
-        let dense_index = match self.sparse.get(id) {
-            Some(Some(idx)) => *idx,
-            _ => return None,
-        };
+        let dense_index = self.sparse.get(id)?;
 
         let last_index = self.dense.len() - 1;
         let last_item = self.dense.pop().unwrap();
         let last_entity = self.entities.pop().unwrap();
+        let last_ticks = self.ticks.pop().unwrap();
 
         let removed = if dense_index != last_index {
             let replaced = replace(&mut self.dense[dense_index], last_item);
             self.entities[dense_index] = last_entity;
-            self.sparse[last_entity.id() as usize] = Some(dense_index);
+            self.ticks[dense_index] = last_ticks;
+            self.sparse.set(last_entity.id() as usize, dense_index);
             replaced
         } else {
             last_item
         };
 
-        self.sparse[id] = None;
+        self.sparse.clear(id);
 
         Some(removed)
     }
 
+    /// Checks if the entity with `id` has a component, consulting only the
+    /// sparse index
+    pub fn contains(&self, id: usize) -> bool {
+        self.sparse.get(id).is_some()
+    }
+
     /// Gets a reference to an entity's component if it exists
     pub fn get(&self, id: usize) -> Option<&C> {
-        if id >= self.sparse.len() {
-            return None;
-        }
-
-        if let Some(index) = self.sparse[id] {
-            self.dense.get(index)
-        } else {
-            None
-        }
+        self.dense.get(self.sparse.get(id)?)
     }
 
     /// Gets a mutable reference to an entity's component if it exists
     pub fn get_mut(&mut self, id: usize) -> Option<&mut C> {
-        if id >= self.sparse.len() {
-            return None;
+        self.dense.get_mut(self.sparse.get(id)?)
+    }
+
+    /// Gets a mutable reference to an entity's component and marks it as changed at `tick`
+    pub fn get_mut_with_tick(&mut self, id: usize, tick: Tick) -> Option<&mut C> {
+        let index = self.sparse.get(id)?;
+        self.ticks[index].changed = tick;
+        self.dense.get_mut(index)
+    }
+
+    /// Gets mutable access to an entity's component that marks it as changed
+    /// at `tick` only once written, see `Mut`
+    pub fn get_mut_tracked(&mut self, id: usize, tick: Tick) -> Option<Mut<'_, C>> {
+        let index = self.sparse.get(id)?;
+        Some(Mut::new(
+            &mut self.dense[index],
+            &mut self.ticks[index].changed,
+            tick,
+        ))
+    }
+
+    /// Gets the change detection ticks of an entity's component if it exists
+    pub fn get_ticks(&self, id: usize) -> Option<&ComponentTicks> {
+        let index = self.sparse.get(id)?;
+        self.ticks.get(index)
+    }
+
+    /// Returns the position of an entity's component in the dense array
+    pub(crate) fn dense_index(&self, id: usize) -> Option<usize> {
+        self.sparse.get(id)
+    }
+
+    /// Gets the component at position `index` of the dense array
+    ///
+    /// # Safety
+    /// `index` must be lower than `self.len()`.
+    pub(crate) unsafe fn get_dense_unchecked(&self, index: usize) -> &C {
+        unsafe { self.dense.get_unchecked(index) }
+    }
+
+    /// Gets the component at position `index` of the dense array mutably and
+    /// marks it as changed at `tick`
+    ///
+    /// # Safety
+    /// `index` must be lower than `self.len()`.
+    pub(crate) unsafe fn get_dense_mut_unchecked(&mut self, index: usize, tick: Tick) -> &mut C {
+        unsafe {
+            self.ticks.get_unchecked_mut(index).changed = tick;
+            self.dense.get_unchecked_mut(index)
         }
+    }
 
-        if let Some(index) = self.sparse[id] {
-            self.dense.get_mut(index)
-        } else {
-            None
+    /// Gets mutable access to the component at position `index` of the dense
+    /// array, marking it as changed at `tick` once written
+    ///
+    /// # Safety
+    /// `index` must be lower than `self.len()`.
+    pub(crate) unsafe fn get_dense_tracked_unchecked(
+        &mut self,
+        index: usize,
+        tick: Tick,
+    ) -> Mut<'_, C> {
+        unsafe {
+            Mut::new(
+                self.dense.get_unchecked_mut(index),
+                &mut self.ticks.get_unchecked_mut(index).changed,
+                tick,
+            )
+        }
+    }
+
+    /// Sorts the dense array with `compare`, keeping the entities, ticks and
+    /// sparse array consistent with it.
+    ///
+    /// Iterating the set, and queries whose smallest storage is this set, then
+    /// visit the components in sorted order until components are added or
+    /// removed. The sort is stable.
+    pub fn sort_by(&mut self, mut compare: impl FnMut(&C, &C) -> Ordering) {
+        let mut order: Vec<usize> = (0..self.dense.len()).collect();
+        order.sort_by(|&a, &b| compare(&self.dense[a], &self.dense[b]));
+
+        let mut dense: Vec<Option<C>> = take(&mut self.dense).into_iter().map(Some).collect();
+        self.dense = order
+            .iter()
+            .map(|&index| dense[index].take().expect("Each index is visited once"))
+            .collect();
+        self.entities = order.iter().map(|&index| self.entities[index]).collect();
+        self.ticks = order.iter().map(|&index| self.ticks[index]).collect();
+        for (index, entity) in self.entities.iter().enumerate() {
+            self.sparse.set(entity.id() as usize, index);
         }
     }
 
@@ -149,10 +437,47 @@ where
     }
 }
 
-impl<C: Component + 'static> ComponentStorage for SparseSet<C> {
+impl<C: Component, I: SparseIndex> ComponentStorage for SparseSet<C, I> {
     fn remove_by_id(&mut self, id: usize) -> Option<Box<dyn std::any::Any>> {
         self.remove(id).map(|c| Box::new(c) as Box<dyn Any>)
     }
+
+    fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    fn component_name(&self) -> &'static str {
+        std::any::type_name::<C>()
+    }
+
+    fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    fn contains(&self, id: usize) -> bool {
+        SparseSet::contains(self, id)
+    }
+
+    fn dense_index_of(&self, id: usize) -> Option<usize> {
+        self.dense_index(id)
+    }
+
+    fn swap_dense(&mut self, a: usize, b: usize) {
+        if a == b {
+            return;
+        }
+        self.dense.swap(a, b);
+        self.entities.swap(a, b);
+        self.ticks.swap(a, b);
+        self.sparse.set(self.entities[a].id() as usize, a);
+        self.sparse.set(self.entities[b].id() as usize, b);
+    }
+
+    fn check_change_ticks(&mut self, this_run: Tick) {
+        for ticks in &mut self.ticks {
+            ticks.check_ticks(this_run);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -176,7 +501,7 @@ mod tests {
         let mut ss = SparseSet::<Position>::new();
         let entity = create_entity(5);
 
-        ss.insert(entity, Position { x: 10, y: 20 });
+        ss.insert(entity, Position { x: 10, y: 20 }, Tick::default());
 
         let component = ss.get(5).unwrap();
         assert_eq!(component, &Position { x: 10, y: 20 });
@@ -187,6 +512,49 @@ mod tests {
         assert_eq!(ss.get(5).unwrap(), &Position { x: 99, y: 20 });
     }
 
+    #[test]
+    fn test_sparse_map_only_indexes_stored_entities() {
+        #[derive(crate::Component, Debug, PartialEq)]
+        #[component(storage = "sparse_map")]
+        struct Boss(u32);
+
+        let mut ss = SparseSet::<Boss>::new();
+        ss.insert(create_entity(3_000_000), Boss(1), Tick::default());
+        ss.insert(create_entity(7), Boss(2), Tick::default());
+        ss.insert(create_entity(42), Boss(3), Tick::default());
+        assert_eq!(ss.remove(3_000_000), Some(Boss(1)));
+
+        assert_eq!(ss.sparse.map.len(), 2);
+        assert!(ss.sparse.vec.0.is_empty());
+        assert_eq!(ss.get(42), Some(&Boss(3)));
+        assert_eq!(ss.dense_index(42), Some(0));
+        assert!(!ss.contains(3_000_000));
+
+        let mut registry = crate::registry::Registry::new();
+        let entities = registry.spawn_batch((0..100).map(|x| (Position { x, y: 0 },)));
+        registry.add_component(entities[60], Boss(9)).unwrap();
+        let bosses: Vec<i32> = registry
+            .query::<(&Position, &Boss)>()
+            .map(|(position, _)| position.x)
+            .collect();
+        assert_eq!(bosses, [60]);
+    }
+
+    #[test]
+    fn test_explicit_index_overrides_the_storage_type() {
+        let mut ss = SparseSet::<Position, MapIndex>::new();
+        ss.insert(
+            create_entity(1_000_000),
+            Position { x: 1, y: 2 },
+            Tick::default(),
+        );
+        ss.insert(create_entity(4), Position { x: 3, y: 4 }, Tick::default());
+        assert_eq!(ss.sparse.len(), 2);
+        assert_eq!(ss.remove(1_000_000), Some(Position { x: 1, y: 2 }));
+        assert_eq!(ss.dense_index(4), Some(0));
+        assert_eq!(ss.get(4), Some(&Position { x: 3, y: 4 }));
+    }
+
     #[test]
     fn test_remove_component_swap_back() {
         let mut ss = SparseSet::<Position>::new();
@@ -194,9 +562,9 @@ mod tests {
         let entity1 = create_entity(1);
         let entity2 = create_entity(2);
 
-        ss.insert(entity0, Position { x: 0, y: 0 });
-        ss.insert(entity1, Position { x: 1, y: 1 });
-        ss.insert(entity2, Position { x: 2, y: 2 });
+        ss.insert(entity0, Position { x: 0, y: 0 }, Tick::default());
+        ss.insert(entity1, Position { x: 1, y: 1 }, Tick::default());
+        ss.insert(entity2, Position { x: 2, y: 2 }, Tick::default());
 
         assert_eq!(ss.len(), 3);
 
@@ -205,6 +573,9 @@ mod tests {
 
         assert_eq!(ss.len(), 2);
         assert!(ss.get(entity1.id() as usize).is_none());
+        assert!(!ss.contains(entity1.id() as usize));
+        assert!(ss.contains(entity2.id() as usize));
+        assert!(!ss.contains(usize::MAX));
         assert_eq!(
             ss.get(entity2.id() as usize),
             Some(&Position { x: 2, y: 2 })
@@ -214,4 +585,52 @@ mod tests {
             Some(&Position { x: 0, y: 0 })
         );
     }
+
+    #[test]
+    fn test_ticks_follow_swap_back() {
+        let mut ss = SparseSet::<Position>::new();
+        ss.insert(create_entity(0), Position { x: 0, y: 0 }, Tick::new(1));
+        ss.insert(create_entity(1), Position { x: 1, y: 1 }, Tick::new(2));
+        ss.insert(create_entity(2), Position { x: 2, y: 2 }, Tick::new(3));
+
+        ss.remove(0);
+
+        assert!(ss.get_ticks(0).is_none());
+        assert_eq!(ss.get_ticks(2).unwrap().added, Tick::new(3));
+        assert_eq!(ss.get_ticks(1).unwrap().added, Tick::new(2));
+    }
+
+    #[test]
+    fn test_update_marks_changed_but_not_added() {
+        let mut ss = SparseSet::<Position>::new();
+        let entity = create_entity(0);
+        ss.insert(entity, Position { x: 0, y: 0 }, Tick::new(1));
+        ss.insert(entity, Position { x: 5, y: 5 }, Tick::new(4));
+
+        let ticks = ss.get_ticks(0).unwrap();
+        assert_eq!(ticks.added, Tick::new(1));
+        assert_eq!(ticks.changed, Tick::new(4));
+
+        ss.get_mut_with_tick(0, Tick::new(7)).unwrap().x = 6;
+        assert_eq!(ss.get_ticks(0).unwrap().changed, Tick::new(7));
+    }
+
+    #[test]
+    fn test_sort_by_repacks_entities_and_ticks() {
+        let mut ss = SparseSet::<Position>::new();
+        for (id, x) in [(0, 3), (1, 1), (2, 2)] {
+            ss.insert(create_entity(id), Position { x, y: 0 }, Tick::new(id + 1));
+        }
+
+        ss.sort_by(|a, b| a.x.cmp(&b.x));
+        let xs: Vec<i32> = ss.iter().map(|position| position.x).collect();
+        assert_eq!(xs, [1, 2, 3]);
+        let ids: Vec<u32> = ss.iter_with_entities().map(|(e, _)| e.id()).collect();
+        assert_eq!(ids, [1, 2, 0]);
+        assert_eq!(ss.get(0), Some(&Position { x: 3, y: 0 }));
+        assert_eq!(ss.get_ticks(0).unwrap().added, Tick::new(1));
+
+        ss.remove(1);
+        assert_eq!(ss.get(2), Some(&Position { x: 2, y: 0 }));
+    }
 }
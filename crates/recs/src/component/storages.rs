@@ -0,0 +1,380 @@
+use std::{
+    any::{Any, TypeId},
+    ptr::NonNull,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use crate::{
+    component::{Component, ComponentStorage, StorageType, sparse_set::SparseSet},
+    type_map::TypeIdMap,
+};
+
+/// Identifies the sparse storage of a component type in a registry. Ids of
+/// removed storages are reused by the next storages created. Dynamic
+/// component types are identified by `ComponentId` instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StorageId(u32);
+
+impl StorageId {
+    /// Returns the index of the storage in creation order
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// The `StorageId` a component type had in the last registry that looked its
+/// storage up, see `Component::storage_cache`.
+///
+/// Registries check the cached id against the type of the storage it points
+/// to, so an id cached by another registry only costs a regular lookup.
+#[doc(hidden)]
+pub struct StorageCache(AtomicU32);
+
+impl StorageCache {
+    pub const fn new() -> Self {
+        Self(AtomicU32::new(u32::MAX))
+    }
+
+    fn get(&self) -> Option<StorageId> {
+        let id = self.0.load(Ordering::Relaxed);
+        (id != u32::MAX).then_some(StorageId(id))
+    }
+
+    fn set(&self, id: StorageId) {
+        self.0.store(id.0, Ordering::Relaxed);
+    }
+}
+
+impl Default for StorageCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The sparse storages of the component types of a registry.
+///
+/// Storages live in a `Vec` indexed by their `StorageId`. Derived component
+/// types cache the id of their storage, see `Component::storage_cache`;
+/// other types map their `TypeId` to it, without any real hashing. Groups
+/// and queries keep the ids or storages they resolved. Storages are visited
+/// in id order.
+///
+/// The map owns its storages through raw pointers rather than boxes, so that
+/// queries running on several threads can resolve pointers to the storages
+/// they write from a shared borrow of the map, see `sparse_set_ptr`.
+#[derive(Default)]
+pub struct ComponentStorages {
+    /// Indexed by `StorageId`, None once the storage was removed
+    slots: Vec<Option<Slot>>,
+    ids: TypeIdMap<StorageId>,
+    /// Slots of removed storages, reused first
+    free: Vec<StorageId>,
+    /// Component types moved to archetype tables, which have no storage here
+    tables: TypeIdMap<()>,
+}
+
+/// A storage owned by `ComponentStorages`
+struct Slot {
+    /// The component type
+    type_id: TypeId,
+    /// The concrete type of the storage, checked before casting `storage`
+    storage_type: TypeId,
+    /// Created by `Box::into_raw`, and freed when the slot is emptied or the
+    /// map dropped
+    storage: NonNull<dyn ComponentStorage>,
+}
+
+impl Slot {
+    fn new(type_id: TypeId, storage: Box<dyn ComponentStorage>) -> Self {
+        Self {
+            type_id,
+            storage_type: (storage.as_ref() as &dyn Any).type_id(),
+            storage: NonNull::from(Box::leak(storage)),
+        }
+    }
+
+    fn into_box(self) -> Box<dyn ComponentStorage> {
+        // SAFETY: the pointer was created from a box, and the slot is gone
+        unsafe { Box::from_raw(self.storage.as_ptr()) }
+    }
+}
+
+// SAFETY: the slots own their storages, which are `Send + Sync`
+unsafe impl Send for ComponentStorages {}
+unsafe impl Sync for ComponentStorages {}
+
+impl Drop for ComponentStorages {
+    fn drop(&mut self) {
+        for slot in self.slots.drain(..).flatten() {
+            drop(slot.into_box());
+        }
+    }
+}
+
+impl ComponentStorages {
+    /// Creates an empty set of storages
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the id of the storage of the component type, if it exists
+    pub fn id(&self, type_id: TypeId) -> Option<StorageId> {
+        self.ids.get(&type_id).copied()
+    }
+
+    /// Returns the id of the storage of `C`, if it exists, through the id
+    /// cached by `C` when it is still valid
+    pub fn id_of<C: Component>(&self) -> Option<StorageId> {
+        let type_id = TypeId::of::<C>();
+        let cache = C::storage_cache();
+        if let Some(id) = cache.and_then(StorageCache::get)
+            && let Some(Some(slot)) = self.slots.get(id.index())
+            && slot.type_id == type_id
+        {
+            return Some(id);
+        }
+        let id = self.id(type_id)?;
+        if let Some(cache) = cache {
+            cache.set(id);
+        }
+        Some(id)
+    }
+
+    /// Returns the storage of the component type
+    pub fn get(&self, type_id: TypeId) -> Option<&dyn ComponentStorage> {
+        self.get_by_id(self.id(type_id)?)
+    }
+
+    /// Returns the storage of the component type mutably
+    pub fn get_mut(&mut self, type_id: TypeId) -> Option<&mut dyn ComponentStorage> {
+        self.get_by_id_mut(self.id(type_id)?)
+    }
+
+    /// Returns the storage with this id
+    pub fn get_by_id(&self, id: StorageId) -> Option<&dyn ComponentStorage> {
+        let slot = self.slots.get(id.index())?.as_ref()?;
+        // SAFETY: the slot owns the storage, borrowed along with `self`
+        Some(unsafe { slot.storage.as_ref() })
+    }
+
+    /// Returns the storage with this id mutably
+    pub fn get_by_id_mut(&mut self, id: StorageId) -> Option<&mut dyn ComponentStorage> {
+        let slot = self.slots.get_mut(id.index())?.as_mut()?;
+        // SAFETY: the slot owns the storage, borrowed along with `self`
+        Some(unsafe { slot.storage.as_mut() })
+    }
+
+    /// Returns the sparse set of `C`
+    pub fn sparse_set<C: Component>(&self) -> Option<&SparseSet<C>> {
+        // SAFETY: the storage is borrowed along with `self`
+        self.sparse_set_ptr::<C>().map(|ss| unsafe { &*ss })
+    }
+
+    /// Returns the sparse set of `C` mutably
+    pub fn sparse_set_mut<C: Component>(&mut self) -> Option<&mut SparseSet<C>> {
+        // SAFETY: the storage is borrowed along with `self`
+        self.sparse_set_ptr::<C>().map(|ss| unsafe { &mut *ss })
+    }
+
+    /// Returns a pointer to the sparse set of `C`, without borrowing the
+    /// storage itself. The pointer stays valid until the storage is removed
+    /// or replaced, and writing through it is sound as long as nothing else
+    /// accesses the storage meanwhile.
+    pub(crate) fn sparse_set_ptr<C: Component>(&self) -> Option<*mut SparseSet<C>> {
+        let slot = self.slots.get(self.id_of::<C>()?.index())?.as_ref()?;
+        (slot.storage_type == TypeId::of::<SparseSet<C>>())
+            .then(|| slot.storage.as_ptr().cast::<SparseSet<C>>())
+    }
+
+    /// Returns a pointer to the sparse set of `C` for a query, see
+    /// `sparse_set_ptr`, or None if no `C` was stored yet
+    ///
+    /// # Panics
+    /// If `C` is stored in archetype tables, which queries don't walk.
+    pub(crate) fn queried<C: Component>(&self) -> Option<*mut SparseSet<C>> {
+        if self.id_of::<C>().is_none()
+            && (C::STORAGE == StorageType::Table || self.tables.contains_key(&TypeId::of::<C>()))
+        {
+            panic!(
+                "{} is stored in archetype tables, which queries don't walk: iterate it with \
+                 `Registry::query_table`",
+                std::any::type_name::<C>()
+            );
+        }
+        self.sparse_set_ptr::<C>()
+    }
+
+    /// Returns the storage of `C`, creating it with `create` if it doesn't
+    /// exist
+    pub(crate) fn get_or_insert_with<C: Component>(
+        &mut self,
+        create: impl FnOnce() -> Box<dyn ComponentStorage>,
+    ) -> &mut dyn ComponentStorage {
+        let id = match self.id_of::<C>() {
+            Some(id) => id,
+            None => self.insert(TypeId::of::<C>(), create()),
+        };
+        self.get_by_id_mut(id)
+            .expect("Storage ids point to live storages")
+    }
+
+    /// Adds the storage of a component type that has none, in the first free
+    /// slot
+    fn insert(&mut self, type_id: TypeId, storage: Box<dyn ComponentStorage>) -> StorageId {
+        let slot = Slot::new(type_id, storage);
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.slots[id.index()] = Some(slot);
+                id
+            }
+            None => {
+                self.slots.push(Some(slot));
+                StorageId(self.slots.len() as u32 - 1)
+            }
+        };
+        self.ids.insert(type_id, id);
+        id
+    }
+
+    /// Removes the storage of the component type. Its id is reused by the
+    /// next storage created.
+    pub(crate) fn remove(&mut self, type_id: TypeId) -> Option<Box<dyn ComponentStorage>> {
+        let id = self.ids.remove(&type_id)?;
+        let slot = self.slots[id.index()].take()?;
+        self.free.push(id);
+        Some(slot.into_box())
+    }
+
+    /// Replaces the storage of `C`, which must exist, returning the old one.
+    /// Pointers to the old storage are left dangling.
+    pub(crate) fn replace<C: Component>(
+        &mut self,
+        storage: Box<dyn ComponentStorage>,
+    ) -> Box<dyn ComponentStorage> {
+        let id = self.id_of::<C>().expect("The storage to replace exists");
+        let slot = self.slots[id.index()]
+            .as_mut()
+            .expect("Storage ids point to live storages");
+        std::mem::replace(slot, Slot::new(TypeId::of::<C>(), storage)).into_box()
+    }
+
+    /// Removes the storage of a component type moved to archetype tables, and
+    /// remembers the type so that queries over it are rejected
+    pub(crate) fn move_to_tables(&mut self, type_id: TypeId) -> Option<Box<dyn ComponentStorage>> {
+        self.tables.insert(type_id, ());
+        self.remove(type_id)
+    }
+
+    /// Returns the number of storages
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns true if no storage exists
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Returns the component type of every storage, in id order
+    pub fn type_ids(&self) -> Vec<TypeId> {
+        self.iter().map(|(type_id, _)| type_id).collect()
+    }
+
+    /// Returns every storage with its component type, in id order
+    pub fn iter(&self) -> impl Iterator<Item = (TypeId, &dyn ComponentStorage)> {
+        self.slots.iter().flatten().map(|slot| {
+            // SAFETY: the slot owns the storage, borrowed along with `self`
+            (slot.type_id, unsafe { slot.storage.as_ref() })
+        })
+    }
+
+    /// Returns every storage mutably with its component type, in id order
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (TypeId, &mut dyn ComponentStorage)> {
+        self.slots.iter_mut().flatten().map(|slot| {
+            // SAFETY: the slot owns the storage, borrowed along with `self`
+            (slot.type_id, unsafe { slot.storage.as_mut() })
+        })
+    }
+
+    /// Returns every storage, in id order
+    pub fn values(&self) -> impl Iterator<Item = &dyn ComponentStorage> {
+        self.iter().map(|(_, storage)| storage)
+    }
+
+    /// Returns every storage mutably, in id order
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut dyn ComponentStorage> {
+        self.iter_mut().map(|(_, storage)| storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entity::Entity, tick::Tick};
+
+    struct A(u32);
+    impl Component for A {}
+
+    struct B;
+    impl Component for B {}
+
+    #[test]
+    fn test_storages_are_indexed_by_dense_ids() {
+        let mut storages = ComponentStorages::new();
+        storages.get_or_insert_with::<A>(|| Box::new(SparseSet::<A>::new()));
+        storages.get_or_insert_with::<B>(|| Box::new(SparseSet::<B>::new()));
+        let a = storages.id(TypeId::of::<A>()).unwrap();
+        assert_eq!(a.index(), 0);
+        assert_eq!(storages.id(TypeId::of::<B>()).unwrap().index(), 1);
+
+        storages
+            .sparse_set_mut::<A>()
+            .unwrap()
+            .insert(Entity::new(3, 0), A(7), Tick::new(0));
+        assert_eq!(storages.get_by_id(a).unwrap().len(), 1);
+        assert_eq!(storages.sparse_set::<A>().unwrap().get(3).unwrap().0, 7);
+
+        assert!(storages.remove(TypeId::of::<A>()).is_some());
+        assert!(storages.get_by_id(a).is_none());
+        assert_eq!(storages.type_ids(), [TypeId::of::<B>()]);
+        // The slot of the removed storage is reused
+        storages.get_or_insert_with::<A>(|| Box::new(SparseSet::<A>::new()));
+        assert_eq!(storages.id(TypeId::of::<A>()).unwrap().index(), 0);
+    }
+
+    struct Cached(u32);
+    impl Component for Cached {
+        fn storage_cache() -> Option<&'static StorageCache> {
+            static CACHE: StorageCache = StorageCache::new();
+            Some(&CACHE)
+        }
+    }
+
+    #[test]
+    fn test_cached_ids_are_checked_against_the_storage_type() {
+        let mut first = ComponentStorages::new();
+        first.get_or_insert_with::<Cached>(|| Box::new(SparseSet::<Cached>::new()));
+        let mut second = ComponentStorages::new();
+        second.get_or_insert_with::<A>(|| Box::new(SparseSet::<A>::new()));
+        second.get_or_insert_with::<Cached>(|| Box::new(SparseSet::<Cached>::new()));
+        second.sparse_set_mut::<Cached>().unwrap().insert(
+            Entity::new(0, 0),
+            Cached(5),
+            Tick::new(0),
+        );
+
+        // Each registry overwrites the id cached by the other one
+        for _ in 0..2 {
+            assert_eq!(first.id_of::<Cached>().unwrap().index(), 0);
+            assert_eq!(second.id_of::<Cached>().unwrap().index(), 1);
+        }
+        assert_eq!(second.sparse_set::<Cached>().unwrap().get(0).unwrap().0, 5);
+        assert!(first.sparse_set::<Cached>().unwrap().is_empty());
+
+        // A removed storage whose slot went to another type isn't found
+        second.remove(TypeId::of::<Cached>());
+        second.get_or_insert_with::<B>(|| Box::new(SparseSet::<B>::new()));
+        assert!(second.id_of::<Cached>().is_none());
+        assert_eq!(second.id_of::<B>().unwrap().index(), 1);
+    }
+}
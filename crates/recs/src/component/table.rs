@@ -0,0 +1,427 @@
+//! Archetype (table) storage, an alternative to sparse sets.
+//!
+//! Entities are grouped by the exact set of table components they have, and
+//! each group, or archetype, stores them in a table with one contiguous column
+//! per component type. Queries over table components walk the matching tables
+//! row by row without any per-entity lookup, at the cost of moving the entity
+//! to another table whenever a table component is added or removed.
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+use crate::{
+    component::{Component, ComponentTicks},
+    entity::Entity,
+    query::Mut,
+    tick::Tick,
+    type_map::TypeIdMap,
+};
+
+/// A type-erased column of a table
+pub(crate) trait Column: Any + Send + Sync {
+    /// Moves the value at `row` to the end of `target`, which must be a column
+    /// of the same type, filling the hole with the last value
+    fn move_row(&mut self, row: usize, target: &mut dyn Column);
+
+    /// Takes the value at `row` out, filling the hole with the last value
+    fn take_row(&mut self, row: usize) -> Box<dyn Any>;
+
+    /// Clamps the change ticks of every value, see `Tick::check_tick`
+    fn check_change_ticks(&mut self, this_run: Tick);
+}
+
+/// The values of one component type in a table, with their change ticks
+pub struct TypedColumn<C> {
+    values: Vec<C>,
+    ticks: Vec<ComponentTicks>,
+}
+
+impl<C: Component> TypedColumn<C> {
+    fn new() -> Self {
+        Self {
+            values: Vec::new(),
+            ticks: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, value: C, tick: Tick) {
+        self.values.push(value);
+        self.ticks.push(ComponentTicks::new(tick));
+    }
+
+    fn swap_remove(&mut self, row: usize) -> C {
+        self.ticks.swap_remove(row);
+        self.values.swap_remove(row)
+    }
+
+    /// Returns the value at `row`
+    ///
+    /// # Safety
+    /// `row` must be lower than the length of the column.
+    pub(crate) unsafe fn get_unchecked(&self, row: usize) -> &C {
+        unsafe { self.values.get_unchecked(row) }
+    }
+
+    /// Returns mutable access to the value at `row`, marking it as changed at
+    /// `tick` once written
+    ///
+    /// # Safety
+    /// `row` must be lower than the length of the column.
+    pub(crate) unsafe fn get_tracked_unchecked(&mut self, row: usize, tick: Tick) -> Mut<'_, C> {
+        unsafe {
+            Mut::new(
+                self.values.get_unchecked_mut(row),
+                &mut self.ticks.get_unchecked_mut(row).changed,
+                tick,
+            )
+        }
+    }
+}
+
+impl<C: Component> Column for TypedColumn<C> {
+    fn move_row(&mut self, row: usize, target: &mut dyn Column) {
+        let target = (target as &mut dyn Any)
+            .downcast_mut::<TypedColumn<C>>()
+            .expect("Moved a row between columns of different types. This is a bug in the RECS library.");
+        target.ticks.push(self.ticks.swap_remove(row));
+        target.values.push(self.values.swap_remove(row));
+    }
+
+    fn take_row(&mut self, row: usize) -> Box<dyn Any> {
+        Box::new(self.swap_remove(row))
+    }
+
+    fn check_change_ticks(&mut self, this_run: Tick) {
+        for ticks in &mut self.ticks {
+            ticks.check_ticks(this_run);
+        }
+    }
+}
+
+/// The entities of one archetype and their table components
+pub struct Table {
+    /// The component types of the archetype, sorted
+    types: Vec<TypeId>,
+    /// One column per component type, in the order of `types`
+    columns: Vec<Box<dyn Column>>,
+    /// The entity of each row
+    entities: Vec<Entity>,
+}
+
+impl Table {
+    /// Returns the entity of each row
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Returns the number of rows
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns true if the table has no rows
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Checks if the archetype has every component type of `types`
+    pub(crate) fn has_all(&self, types: &[TypeId]) -> bool {
+        types.iter().all(|ty| self.types.binary_search(ty).is_ok())
+    }
+
+    fn column_index(&self, type_id: TypeId) -> Option<usize> {
+        self.types.binary_search(&type_id).ok()
+    }
+
+    pub(crate) fn column<C: Component>(&self) -> Option<&TypedColumn<C>> {
+        let index = self.column_index(TypeId::of::<C>())?;
+        (self.columns[index].as_ref() as &dyn Any).downcast_ref()
+    }
+
+    pub(crate) fn column_mut<C: Component>(&mut self) -> Option<&mut TypedColumn<C>> {
+        let index = self.column_index(TypeId::of::<C>())?;
+        (self.columns[index].as_mut() as &mut dyn Any).downcast_mut()
+    }
+}
+
+/// Where the row of an entity is
+#[derive(Debug, Clone, Copy)]
+struct Location {
+    table: usize,
+    row: usize,
+}
+
+/// Every table of a registry, with the location of each entity stored in one
+#[derive(Default)]
+pub(crate) struct Archetypes {
+    tables: Vec<Table>,
+    /// The table of each set of component types
+    by_types: HashMap<Vec<TypeId>, usize>,
+    /// The location of the row of each entity, by entity id
+    locations: Vec<Option<Location>>,
+    /// Constructors of an empty column for each table component type
+    columns: TypeIdMap<fn() -> Box<dyn Column>>,
+}
+
+impl Archetypes {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clamps the change ticks of every table component, see
+    /// `Tick::check_tick`
+    pub(crate) fn check_change_ticks(&mut self, this_run: Tick) {
+        for table in &mut self.tables {
+            for column in &mut table.columns {
+                column.check_change_ticks(this_run);
+            }
+        }
+    }
+
+    /// Stores `C` components in tables from now on
+    pub(crate) fn register<C: Component>(&mut self) {
+        self.columns
+            .insert(TypeId::of::<C>(), || Box::new(TypedColumn::<C>::new()));
+    }
+
+    /// Drops every component of the type, leaving its columns empty
+    pub(crate) fn clear_column(&mut self, type_id: TypeId) {
+        let Some(&new_column) = self.columns.get(&type_id) else {
+            return;
+        };
+        for table in &mut self.tables {
+            if let Some(index) = table.column_index(type_id) {
+                drop(std::mem::replace(&mut table.columns[index], new_column()));
+            }
+        }
+    }
+
+    /// Checks if components of the type are stored in tables
+    pub(crate) fn is_table_component(&self, type_id: TypeId) -> bool {
+        self.columns.contains_key(&type_id)
+    }
+
+    pub(crate) fn tables(&self) -> &[Table] {
+        &self.tables
+    }
+
+    pub(crate) fn tables_mut(&mut self) -> &mut [Table] {
+        &mut self.tables
+    }
+
+    fn location(&self, id: usize) -> Option<Location> {
+        self.locations.get(id).copied().flatten()
+    }
+
+    pub(crate) fn get<C: Component>(&self, id: usize) -> Option<&C> {
+        let location = self.location(id)?;
+        let column = self.tables[location.table].column::<C>()?;
+        column.values.get(location.row)
+    }
+
+    /// Returns the `C` component of the entity with `id`, marking it as
+    /// changed at `tick`
+    pub(crate) fn get_mut<C: Component>(&mut self, id: usize, tick: Tick) -> Option<&mut C> {
+        let location = self.location(id)?;
+        let column = self.tables[location.table].column_mut::<C>()?;
+        column.ticks[location.row].changed = tick;
+        column.values.get_mut(location.row)
+    }
+
+    /// Checks if the entity with `id` has a component of the type
+    pub(crate) fn contains(&self, type_id: TypeId, id: usize) -> bool {
+        self.location(id)
+            .is_some_and(|location| self.tables[location.table].column_index(type_id).is_some())
+    }
+
+    #[cfg(any(test, feature = "serde"))]
+    pub(crate) fn ticks<C: Component>(&self, id: usize) -> Option<ComponentTicks> {
+        let location = self.location(id)?;
+        let column = self.tables[location.table].column::<C>()?;
+        column.ticks.get(location.row).copied()
+    }
+
+    /// Adds or replaces the `C` component of `entity`, moving it to the table
+    /// of its new archetype. Returns the replaced component, if any.
+    pub(crate) fn insert<C: Component>(
+        &mut self,
+        entity: Entity,
+        value: C,
+        tick: Tick,
+    ) -> Option<C> {
+        let id = entity.id() as usize;
+        let location = self.location(id);
+        if let Some(location) = location
+            && let Some(column) = self.tables[location.table].column_mut::<C>()
+        {
+            column.ticks[location.row].changed = tick;
+            return Some(std::mem::replace(&mut column.values[location.row], value));
+        }
+
+        let mut types = location.map_or_else(Vec::new, |l| self.tables[l.table].types.clone());
+        let type_id = TypeId::of::<C>();
+        let position = types.binary_search(&type_id).unwrap_err();
+        types.insert(position, type_id);
+
+        let target = self.table_for(types);
+        self.move_entity(entity, Some(target));
+        self.tables[target]
+            .column_mut::<C>()
+            .expect("The target table has a column for the inserted component")
+            .push(value, tick);
+        None
+    }
+
+    /// Removes the `C` component of `entity`, moving it to the table of its
+    /// new archetype
+    pub(crate) fn remove<C: Component>(&mut self, entity: Entity) -> Option<C> {
+        let location = self.location(entity.id() as usize)?;
+        let mut types = self.tables[location.table].types.clone();
+        let position = types.binary_search(&TypeId::of::<C>()).ok()?;
+        types.remove(position);
+
+        let target = (!types.is_empty()).then(|| self.table_for(types));
+        let (table, row) = self.move_entity(entity, target)?;
+        let column = self.tables[table]
+            .column_mut::<C>()
+            .expect("The source table has a column for the removed component");
+        Some(column.swap_remove(row))
+    }
+
+    /// Removes every table component of `entity`, returning them with their
+    /// types
+    pub(crate) fn remove_all(&mut self, entity: Entity) -> Vec<(TypeId, Box<dyn Any>)> {
+        let Some((table, row)) = self.move_entity(entity, None) else {
+            return Vec::new();
+        };
+        let table = &mut self.tables[table];
+        table
+            .types
+            .iter()
+            .zip(&mut table.columns)
+            .map(|(&type_id, column)| (type_id, column.take_row(row)))
+            .collect()
+    }
+
+    /// Returns the number of stored components of the type
+    pub(crate) fn count(&self, type_id: TypeId) -> usize {
+        self.tables
+            .iter()
+            .filter(|table| table.column_index(type_id).is_some())
+            .map(Table::len)
+            .sum()
+    }
+
+    /// Returns the table of the archetype with the sorted component `types`,
+    /// creating it if needed
+    fn table_for(&mut self, types: Vec<TypeId>) -> usize {
+        if let Some(&table) = self.by_types.get(&types) {
+            return table;
+        }
+        let columns = types.iter().map(|ty| (self.columns[ty])()).collect();
+        let table = self.tables.len();
+        self.tables.push(Table {
+            types: types.clone(),
+            columns,
+            entities: Vec::new(),
+        });
+        self.by_types.insert(types, table);
+        table
+    }
+
+    /// Moves the row of `entity` to the end of `target`, or out of every table
+    /// if `target` is None.
+    ///
+    /// Only the columns `target` has are moved. Returns the previous table and
+    /// row of the entity, where the caller must remove the values of the
+    /// other columns.
+    fn move_entity(&mut self, entity: Entity, target: Option<usize>) -> Option<(usize, usize)> {
+        let id = entity.id() as usize;
+        if id >= self.locations.len() {
+            self.locations.resize(id + 1, None);
+        }
+        let previous = self.locations[id].take();
+        if let Some(target) = target {
+            self.locations[id] = Some(Location {
+                table: target,
+                row: self.tables[target].entities.len(),
+            });
+            self.tables[target].entities.push(entity);
+        }
+        let Location { table, row } = previous?;
+
+        if let Some(target) = target {
+            let (source, target) = pair_mut(&mut self.tables, table, target);
+            for (index, type_id) in source.types.iter().enumerate() {
+                if let Some(target_index) = target.column_index(*type_id) {
+                    source.columns[index].move_row(row, target.columns[target_index].as_mut());
+                }
+            }
+        }
+
+        let source = &mut self.tables[table];
+        source.entities.swap_remove(row);
+        if let Some(moved) = source.entities.get(row) {
+            self.locations[moved.id() as usize] = Some(Location { table, row });
+        }
+        Some((table, row))
+    }
+}
+
+/// Borrows two different tables mutably at once
+fn pair_mut(tables: &mut [Table], a: usize, b: usize) -> (&mut Table, &mut Table) {
+    debug_assert_ne!(a, b);
+    if a < b {
+        let (left, right) = tables.split_at_mut(b);
+        (&mut left[a], &mut right[0])
+    } else {
+        let (left, right) = tables.split_at_mut(a);
+        (&mut right[0], &mut left[b])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct A(u32);
+    impl Component for A {}
+
+    #[derive(Debug, PartialEq)]
+    struct B(&'static str);
+    impl Component for B {}
+
+    #[test]
+    fn test_entities_move_between_tables() {
+        let mut archetypes = Archetypes::new();
+        archetypes.register::<A>();
+        archetypes.register::<B>();
+        let [e0, e1, e2] = [0, 1, 2].map(|id| Entity::new(id, 0));
+        let tick = Tick::new(1);
+
+        for (entity, value) in [(e0, 0), (e1, 1), (e2, 2)] {
+            archetypes.insert(entity, A(value), tick);
+        }
+        archetypes.insert(e0, B("zero"), tick);
+        // e2 filled the hole e0 left in the table of A alone
+        assert_eq!(archetypes.tables()[0].entities(), &[e2, e1]);
+        assert_eq!(archetypes.get::<A>(0), Some(&A(0)));
+        assert_eq!(archetypes.get::<B>(0), Some(&B("zero")));
+        assert_eq!(archetypes.get::<A>(2), Some(&A(2)));
+
+        assert_eq!(archetypes.remove::<A>(e0), Some(A(0)));
+        assert_eq!(archetypes.get::<B>(0), Some(&B("zero")));
+        assert_eq!(archetypes.tables().len(), 3);
+
+        let removed = archetypes.remove_all(e0);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].0, TypeId::of::<B>());
+        assert_eq!(removed[0].1.downcast_ref::<B>(), Some(&B("zero")));
+        assert!(archetypes.get::<B>(0).is_none());
+        assert_eq!(archetypes.remove::<A>(e0), None);
+        assert_eq!(archetypes.count(TypeId::of::<A>()), 2);
+    }
+}
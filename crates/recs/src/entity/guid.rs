@@ -0,0 +1,179 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{BuildHasher, RandomState},
+    str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{entity::Entity, error::RecsError};
+
+/// A stable 128-bit identifier for an entity.
+///
+/// Unlike `Entity`, whose id and generation are recycled and only meaningful
+/// within one registry, a GUID stays the same across runs. Save files, network
+/// sessions and editors use it to refer to "the same" entity. GUIDs are opt-in,
+/// see `Registry::assign_guid` and `Registry::set_guid`.
+///
+/// GUIDs are formatted like UUIDs, e.g. `67e55044-10b1-426f-9247-bb680e5fe0c8`,
+/// and serializable with the `serde` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntityGuid(u128);
+
+impl EntityGuid {
+    /// Creates a GUID from its raw value, e.g. one read from a scene file
+    pub const fn from_u128(value: u128) -> Self {
+        Self(value)
+    }
+
+    /// Returns the raw value of this GUID
+    pub const fn as_u128(self) -> u128 {
+        self.0
+    }
+
+    /// Generates a new random GUID in the UUID version 4 format
+    pub fn generate() -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos());
+
+        // RandomState is seeded from the OS, so hashing the same input with
+        // two fresh states yields 128 unpredictable bits
+        let high = RandomState::new().hash_one((count, nanos));
+        let low = RandomState::new().hash_one((nanos, count));
+        let value = ((high as u128) << 64) | low as u128;
+
+        // Version 4 and RFC 4122 variant bits
+        let value = (value & !(0xf << 76)) | (0x4 << 76);
+        let value = (value & !(0x3 << 62)) | (0x2 << 62);
+        Self(value)
+    }
+}
+
+impl fmt::Display for EntityGuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = self.0;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            (v >> 96) as u32,
+            (v >> 80) as u16,
+            (v >> 64) as u16,
+            (v >> 48) as u16,
+            v & 0xffff_ffff_ffff
+        )
+    }
+}
+
+impl FromStr for EntityGuid {
+    type Err = RecsError;
+
+    /// Parses a GUID in the hyphenated format produced by `Display`, or as 32
+    /// plain hex digits
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits: String = s.chars().filter(|&c| c != '-').collect();
+        let hyphens_valid = s.len() == digits.len()
+            || (s.len() == 36 && [8, 13, 18, 23].iter().all(|&i| s.as_bytes()[i] == b'-'));
+
+        if digits.len() != 32 || !hyphens_valid || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(RecsError::InvalidGuid(s.to_string()));
+        }
+
+        u128::from_str_radix(&digits, 16)
+            .map(Self)
+            .map_err(|_| RecsError::InvalidGuid(s.to_string()))
+    }
+}
+
+/// Two-way mapping between GUIDs and the entities carrying them
+#[derive(Default, Clone)]
+pub(crate) struct GuidIndex {
+    entities: HashMap<EntityGuid, Entity>,
+    /// GUIDs keyed by entity id, so destroyed entities can be unregistered
+    guids: HashMap<u32, EntityGuid>,
+}
+
+impl GuidIndex {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attaches `guid` to `entity`, replacing the entity's previous GUID.
+    /// Fails if another entity already carries `guid`.
+    pub(crate) fn insert(&mut self, entity: Entity, guid: EntityGuid) -> Result<(), RecsError> {
+        match self.entities.get(&guid) {
+            Some(&owner) if owner != entity => return Err(RecsError::DuplicateGuid(guid)),
+            Some(_) => return Ok(()),
+            None => {}
+        }
+
+        self.remove(entity.id());
+        self.entities.insert(guid, entity);
+        self.guids.insert(entity.id(), guid);
+        Ok(())
+    }
+
+    /// Detaches the GUID of the entity with `id`, returning it
+    pub(crate) fn remove(&mut self, id: u32) -> Option<EntityGuid> {
+        let guid = self.guids.remove(&id)?;
+        self.entities.remove(&guid);
+        Some(guid)
+    }
+
+    pub(crate) fn guid(&self, id: u32) -> Option<EntityGuid> {
+        self.guids.get(&id).copied()
+    }
+
+    pub(crate) fn entity(&self, guid: EntityGuid) -> Option<Entity> {
+        self.entities.get(&guid).copied()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (EntityGuid, Entity)> + '_ {
+        self.entities.iter().map(|(&guid, &entity)| (guid, entity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_guids_are_unique_v4() {
+        let a = EntityGuid::generate();
+        let b = EntityGuid::generate();
+        assert_ne!(a, b);
+        assert_eq!((a.as_u128() >> 76) & 0xf, 4);
+        assert_eq!((a.as_u128() >> 62) & 0x3, 2);
+    }
+
+    #[test]
+    fn test_guid_display_round_trip() {
+        let guid = EntityGuid::from_u128(0x67e55044_10b1_426f_9247_bb680e5fe0c8);
+        let text = guid.to_string();
+        assert_eq!(text, "67e55044-10b1-426f-9247-bb680e5fe0c8");
+        assert_eq!(text.parse::<EntityGuid>().unwrap(), guid);
+        assert_eq!(
+            "67e5504410b1426f9247bb680e5fe0c8"
+                .parse::<EntityGuid>()
+                .unwrap(),
+            guid
+        );
+
+        assert!("67e55044-10b1-426f-9247".parse::<EntityGuid>().is_err());
+        assert!(
+            "67e5504-410b1-426f-9247-bb680e5fe0c8"
+                .parse::<EntityGuid>()
+                .is_err()
+        );
+        assert!(
+            "zze55044-10b1-426f-9247-bb680e5fe0c8"
+                .parse::<EntityGuid>()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Remapping the entities referenced by components.
+//!
+//! Entities spawned from a document or copied into another registry get new
+//! ids, so components that store `Entity` handles must be rewritten to point
+//! at the new entities. Such components implement `MapEntities` and are
+//! registered with `Registry::register_map_entities`; scenes, extracted
+//! selections and `Registry::copy_entities_to` then rewrite them through an
+//! `EntityMapper` once every entity exists.
+
+use std::{any::TypeId, collections::HashMap};
+
+use crate::{component::Component, entity::Entity, registry::Registry};
+
+/// Translates the entities of a source, such as a saved document, to those of
+/// the registry being loaded into
+pub trait EntityMapper {
+    /// Returns the entity `entity` maps to, or `entity` itself if it isn't
+    /// mapped
+    fn map_entity(&mut self, entity: Entity) -> Entity;
+}
+
+impl EntityMapper for HashMap<Entity, Entity> {
+    fn map_entity(&mut self, entity: Entity) -> Entity {
+        self.get(&entity).copied().unwrap_or(entity)
+    }
+}
+
+/// A component, or part of one, referencing other entities.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use recs::entity::map::{EntityMapper, MapEntities};
+/// #[derive(Component)]
+/// struct Squad {
+///     leader: Entity,
+///     members: Vec<Entity>,
+/// }
+///
+/// impl MapEntities for Squad {
+///     fn map_entities(&mut self, mapper: &mut dyn EntityMapper) {
+///         self.leader.map_entities(mapper);
+///         self.members.map_entities(mapper);
+///     }
+/// }
+/// ```
+pub trait MapEntities {
+    /// Replaces every entity referenced by the value with its mapping
+    fn map_entities(&mut self, mapper: &mut dyn EntityMapper);
+}
+
+impl MapEntities for Entity {
+    fn map_entities(&mut self, mapper: &mut dyn EntityMapper) {
+        *self = mapper.map_entity(*self);
+    }
+}
+
+impl<T: MapEntities> MapEntities for Option<T> {
+    fn map_entities(&mut self, mapper: &mut dyn EntityMapper) {
+        if let Some(value) = self {
+            value.map_entities(mapper);
+        }
+    }
+}
+
+impl<T: MapEntities> MapEntities for Vec<T> {
+    fn map_entities(&mut self, mapper: &mut dyn EntityMapper) {
+        for value in self {
+            value.map_entities(mapper);
+        }
+    }
+}
+
+type MapEntitiesFn = fn(&mut Registry, Entity, &mut dyn EntityMapper);
+
+/// The components registered with `Registry::register_map_entities`, keyed by
+/// component type
+#[derive(Default)]
+pub(crate) struct EntityMappers {
+    mappers: HashMap<TypeId, MapEntitiesFn>,
+}
+
+impl EntityMappers {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Registry {
+    /// Makes loaded scenes and copied entities remap the entities referenced
+    /// by their `C` components, see `MapEntities`
+    pub fn register_map_entities<C: Component + MapEntities>(&mut self) {
+        self.entity_mappers
+            .mappers
+            .insert(TypeId::of::<C>(), |registry, entity, mapper| {
+                if let Some(component) = registry.get_component_mut::<C>(entity) {
+                    component.map_entities(mapper);
+                }
+            });
+    }
+
+    /// Remaps the entities referenced by the components of `entity`
+    #[cfg(feature = "serde")]
+    pub(crate) fn map_entities(&mut self, entity: Entity, mapper: &mut dyn EntityMapper) {
+        let mappers: Vec<_> = self.entity_mappers.mappers.values().copied().collect();
+        for map in mappers {
+            map(self, entity, mapper);
+        }
+    }
+
+    /// Remaps the entities referenced by the components of `entity` in
+    /// `target`, using the components registered in this registry
+    pub(crate) fn map_entities_in(
+        &self,
+        target: &mut Registry,
+        entity: Entity,
+        mapper: &mut dyn EntityMapper,
+    ) {
+        for map in self.entity_mappers.mappers.values() {
+            map(target, entity, mapper);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_references_are_mapped() {
+        let [a, b, c] = [0, 1, 2].map(|id| Entity::new(id, 1));
+        let mut map = HashMap::from([(a, b), (b, c)]);
+
+        let mut value = (Some(a), vec![a, b, c]);
+        value.0.map_entities(&mut map);
+        value.1.map_entities(&mut map);
+        assert_eq!(value, (Some(b), vec![b, c, c]));
+    }
+}
@@ -1,11 +1,18 @@
-use crate::error::RecsError;
+use crate::{component::Component, error::RecsError};
+
+pub mod guid;
+pub mod map;
+pub mod pool;
 
 /// Represents a unique entity in the RECS system.
 ///
 /// Each entity is identified by two numbers:
 /// - An ID that can be reused when entities are destroyed
 /// - A generation number that ensures old references to reused IDs are invalid
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+///
+/// Entities are ordered by ID, then by generation.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entity(u32, u32);
 
 impl Entity {
@@ -23,6 +30,88 @@ impl Entity {
     pub fn generation(&self) -> u32 {
         self.1
     }
+
+    /// Packs the entity into a single integer, with the generation in the high
+    /// 32 bits and the ID in the low 32 bits. See `from_bits`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// let mut registry = Registry::new();
+    /// let entity = registry.create_entity();
+    ///
+    /// let bits = entity.to_bits();
+    /// assert_eq!(Entity::from_bits(bits), Some(entity));
+    /// ```
+    pub fn to_bits(&self) -> u64 {
+        ((self.1 as u64) << 32) | self.0 as u64
+    }
+
+    /// Unpacks an entity packed with `to_bits`.
+    ///
+    /// Returns None if the generation is 0, which no entity ever has. Whether
+    /// the entity is still alive is up to `Registry::is_valid`.
+    pub fn from_bits(bits: u64) -> Option<Self> {
+        let generation = (bits >> 32) as u32;
+        (generation != 0).then_some(Self(bits as u32, generation))
+    }
+}
+
+/// A contiguous block of entity ids reserved with `Registry::reserve_entities`.
+///
+/// Entities created in a range take their ids from it only, and destroying
+/// them returns the ids to the range rather than to general allocation.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct EntityRange {
+    start: u32,
+    end: u32,
+}
+
+impl EntityRange {
+    /// Returns the first id of the range
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    /// Returns the id right after the last one of the range
+    pub fn end(&self) -> u32 {
+        self.end
+    }
+
+    /// Returns the number of ids in the range
+    pub fn len(&self) -> usize {
+        (self.end - self.start) as usize
+    }
+
+    /// Returns true if the range holds no ids
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Checks if the id of `entity` belongs to the range
+    pub fn contains(&self, entity: Entity) -> bool {
+        (self.start..self.end).contains(&entity.id())
+    }
+}
+
+/// A reserved range along with its ids not currently used by an entity
+#[derive(Clone)]
+struct ReservedRange {
+    range: EntityRange,
+    /// Unused ids, popped from the back so the lowest ids are used first
+    free: Vec<usize>,
+}
+
+/// A marker component for entities that are temporarily inactive.
+///
+/// Queries skip disabled entities unless filtered with `IncludeDisabled`, while
+/// their components are kept in storage so that the entity can be reactivated
+/// cheaply by removing the marker, see `Registry::set_enabled`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Disabled;
+
+impl Component for Disabled {
+    const CLONE: Option<fn(&Self) -> Self> = Some(Self::clone);
 }
 
 /// Manages entity lifecycle, including creation, destruction, and validation.
@@ -30,11 +119,26 @@ impl Entity {
 /// The EntityManager maintains:
 /// - A list of generation numbers for each entity ID
 /// - A list of freed entity IDs that can be reused
+/// - Which entity IDs are in use, to enumerate the alive entities
+/// - The reserved ranges of IDs, which keep their own free lists
+#[derive(Clone)]
 pub struct EntityManager {
     /// Generation numbers for each entity ID
     generations: Vec<u32>,
     /// List of entity IDs that can be reused
     free_list: Vec<usize>,
+    /// Whether each entity ID is used by an alive entity
+    alive: Vec<bool>,
+    /// Reserved ranges, sorted by their first ID
+    reserved: Vec<ReservedRange>,
+    /// Number of unused IDs across all reserved ranges
+    reserved_free: usize,
+}
+
+impl Default for EntityManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EntityManager {
@@ -43,6 +147,9 @@ impl EntityManager {
         Self {
             generations: Vec::new(),
             free_list: Vec::new(),
+            alive: Vec::new(),
+            reserved: Vec::new(),
+            reserved_free: 0,
         }
     }
 
@@ -51,14 +158,61 @@ impl EntityManager {
     pub fn create_entity(&mut self) -> Entity {
         if let Some(index) = self.free_list.pop() {
             let generation = self.generations[index];
+            self.alive[index] = true;
             Entity(index as u32, generation)
         } else {
             let index = self.generations.len();
             self.generations.push(1);
+            self.alive.push(true);
             Entity(index as u32, 1)
         }
     }
 
+    /// Creates a manager where exactly the entities of `alive` are alive, and
+    /// every other id slot below the largest one is free with the generation
+    /// given by `generations`, or 1 if it has none
+    #[cfg(feature = "serde")]
+    pub(crate) fn restore(
+        mut generations: Vec<u32>,
+        alive: &std::collections::HashSet<Entity>,
+    ) -> Self {
+        let len = alive.iter().map(|entity| entity.0 as usize + 1).max();
+        generations.resize(generations.len().max(len.unwrap_or(0)), 1);
+        for entity in alive {
+            generations[entity.0 as usize] = entity.1;
+        }
+        let alive_ids: std::collections::HashSet<usize> =
+            alive.iter().map(|entity| entity.0 as usize).collect();
+        // Reversed so the lowest ids are reused first
+        let free_list = (0..generations.len())
+            .rev()
+            .filter(|index| !alive_ids.contains(index))
+            .collect();
+        let alive = (0..generations.len())
+            .map(|index| alive_ids.contains(&index))
+            .collect();
+
+        Self {
+            generations,
+            free_list,
+            alive,
+            reserved: Vec::new(),
+            reserved_free: 0,
+        }
+    }
+
+    /// Returns an upper bound on the ids of the next `count` entities created
+    /// with `create_entity`
+    pub fn id_bound_after(&self, count: usize) -> usize {
+        self.generations.len() + count.saturating_sub(self.free_list.len())
+    }
+
+    /// Reserves room for `additional` more entity ids
+    pub fn reserve(&mut self, additional: usize) {
+        self.generations
+            .reserve(additional.saturating_sub(self.free_list.len()));
+    }
+
     /// Destroys an entity, making its ID available for reuse.
     /// Returns an error if the entity is invalid.
     pub fn destroy_entity(&mut self, entity: Entity) -> Result<(), RecsError> {
@@ -68,11 +222,126 @@ impl EntityManager {
 
         let index = entity.id() as usize;
         self.generations[index] += 1;
-        self.free_list.push(index);
+        self.alive[index] = false;
+        match self.reserved_range_of(index) {
+            Some(reserved) => {
+                self.reserved[reserved].free.push(index);
+                self.reserved_free += 1;
+            }
+            None => self.free_list.push(index),
+        }
 
         Ok(())
     }
 
+    /// Reserves a block of `count` contiguous entity ids that are never handed
+    /// out by `create_entity`, only by `create_entity_in`
+    pub fn reserve_range(&mut self, count: u32) -> EntityRange {
+        let start = self.generations.len() as u32;
+        let range = EntityRange {
+            start,
+            end: start + count,
+        };
+
+        self.generations.resize(range.end as usize, 1);
+        self.alive.resize(range.end as usize, false);
+        self.reserved.push(ReservedRange {
+            range,
+            free: (range.start as usize..range.end as usize).rev().collect(),
+        });
+        self.reserved_free += range.len();
+        range
+    }
+
+    /// Creates a new entity with an id taken from `range`.
+    /// Returns an error if every id of the range is in use, or if the range
+    /// was released.
+    pub fn create_entity_in(&mut self, range: EntityRange) -> Result<Entity, RecsError> {
+        let index = self
+            .reserved
+            .iter_mut()
+            .find(|reserved| reserved.range == range)
+            .and_then(|reserved| reserved.free.pop())
+            .ok_or(RecsError::EntityRangeExhausted(range))?;
+
+        self.reserved_free -= 1;
+        self.alive[index] = true;
+        Ok(Entity(index as u32, self.generations[index]))
+    }
+
+    /// Returns an iterator over every alive entity whose id belongs to `range`
+    pub fn iter_range(&self, range: EntityRange) -> impl Iterator<Item = Entity> + '_ {
+        self.iter_ids(range.start as usize..range.end as usize)
+    }
+
+    /// Ends the reservation of `range`: its unused ids go back to general
+    /// allocation, and entities still alive in it become regular entities.
+    /// Returns false if the range was not reserved.
+    pub fn release_range(&mut self, range: EntityRange) -> bool {
+        let Some(position) = self
+            .reserved
+            .iter()
+            .position(|reserved| reserved.range == range)
+        else {
+            return false;
+        };
+
+        let reserved = self.reserved.remove(position);
+        self.reserved_free -= reserved.free.len();
+        self.free_list.extend(reserved.free);
+        true
+    }
+
+    /// Returns the index of the reserved range containing the id `index`
+    fn reserved_range_of(&self, index: usize) -> Option<usize> {
+        let after = self
+            .reserved
+            .partition_point(|reserved| reserved.range.start as usize <= index);
+        after
+            .checked_sub(1)
+            .filter(|&candidate| index < self.reserved[candidate].range.end as usize)
+    }
+
+    /// Returns the number of entities currently alive
+    pub fn alive_count(&self) -> usize {
+        self.generations.len() - self.free_list.len() - self.reserved_free
+    }
+
+    /// Returns the current generation of every id slot ever allocated, as
+    /// `(id, generation)` pairs in id order. A slot's generation grows by one
+    /// each time the entity using it is destroyed.
+    pub fn slot_generations(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.generations
+            .iter()
+            .enumerate()
+            .map(|(id, &generation)| (id as u32, generation))
+    }
+
+    /// Returns the number of id slots ever allocated
+    pub fn slot_count(&self) -> usize {
+        self.generations.len()
+    }
+
+    /// Returns an iterator over every alive entity, in id order
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.iter_ids(0..self.generations.len())
+    }
+
+    /// Returns an iterator over the alive entities with an id in `ids`
+    fn iter_ids(&self, ids: std::ops::Range<usize>) -> impl Iterator<Item = Entity> + '_ {
+        let ids = ids.start.min(self.generations.len())..ids.end.min(self.generations.len());
+        ids.filter_map(|id| self.alive_entity(id as u32))
+    }
+
+    /// Returns the alive entity with `id`, if any
+    pub(crate) fn alive_entity(&self, id: u32) -> Option<Entity> {
+        let index = id as usize;
+        self.alive
+            .get(index)
+            .is_some_and(|&alive| alive)
+            .then(|| Entity(id, self.generations[index]))
+    }
+
     /// Checks if an entity reference is still valid by comparing its generation
     /// number with the current generation for that entity ID.
     pub fn is_valid(&self, entity: Entity) -> bool {
@@ -132,4 +401,68 @@ mod tests {
         assert!(result.is_err());
         matches!(result.unwrap_err(), RecsError::InvalidEntity(_));
     }
+
+    #[test]
+    fn test_iter_skips_destroyed_entities() {
+        let mut manager = EntityManager::new();
+        let e0 = manager.create_entity();
+        let e1 = manager.create_entity();
+        let e2 = manager.create_entity();
+        manager.destroy_entity(e1).unwrap();
+
+        assert_eq!(manager.iter().collect::<Vec<_>>(), vec![e0, e2]);
+        assert_eq!(manager.alive_count(), 2);
+    }
+
+    #[test]
+    fn test_reserved_range_ids_stay_in_range() {
+        let mut manager = EntityManager::new();
+        let outside = manager.create_entity();
+        let range = manager.reserve_range(3);
+        assert_eq!((range.start(), range.end()), (1, 4));
+
+        let first = manager.create_entity_in(range).unwrap();
+        let second = manager.create_entity_in(range).unwrap();
+        assert_eq!((first.id(), second.id()), (1, 2));
+        assert_eq!(manager.alive_count(), 3);
+
+        // Freed range ids are reused in the range only
+        manager.destroy_entity(first).unwrap();
+        manager.destroy_entity(outside).unwrap();
+        assert_eq!(manager.create_entity().id(), 0);
+        assert_eq!(manager.create_entity().id(), 4);
+        assert_eq!(manager.create_entity_in(range).unwrap().id(), 1);
+        assert_eq!(manager.create_entity_in(range).unwrap().id(), 3);
+        assert!(matches!(
+            manager.create_entity_in(range),
+            Err(RecsError::EntityRangeExhausted(_))
+        ));
+        assert_eq!(manager.iter_range(range).count(), 3);
+
+        // Released ids return to general allocation
+        for entity in manager.iter_range(range).collect::<Vec<_>>() {
+            manager.destroy_entity(entity).unwrap();
+        }
+        assert!(manager.release_range(range));
+        assert_eq!(manager.alive_count(), 2);
+        assert!(range.contains(manager.create_entity()));
+        assert!(manager.create_entity_in(range).is_err());
+    }
+
+    #[test]
+    fn test_entity_bits_round_trip() {
+        let entity = Entity::new(7, 3);
+        assert_eq!(entity.to_bits(), (3 << 32) | 7);
+        assert_eq!(Entity::from_bits(entity.to_bits()), Some(entity));
+        let last = Entity::new(u32::MAX, u32::MAX);
+        assert_eq!(Entity::from_bits(last.to_bits()), Some(last));
+        assert_eq!(Entity::from_bits(7), None);
+
+        let mut sorted = vec![Entity::new(2, 1), Entity::new(1, 2), Entity::new(1, 1)];
+        sorted.sort();
+        assert_eq!(
+            sorted,
+            [Entity::new(1, 1), Entity::new(1, 2), Entity::new(2, 1)]
+        );
+    }
 }
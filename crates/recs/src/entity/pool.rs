@@ -0,0 +1,194 @@
+use crate::{
+    entity::{Disabled, Entity},
+    error::RecsError,
+    registry::{Registry, bundle::ComponentBundle},
+    resource::Resource,
+};
+
+/// A bounded pool of reusable entities for high-churn objects such as bullets
+/// or particles.
+///
+/// Instead of destroying an entity, `release` disables it and keeps its
+/// components in storage. `acquire` then reactivates a pooled entity, or spawns
+/// a new one from the pool's bundle factory when the pool is empty. At most
+/// `capacity` inactive entities are kept; releasing beyond that destroys the
+/// entity for real.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use recs::entity::pool::EntityPool;
+/// #[derive(Component)]
+/// struct Bullet { speed: f32 }
+///
+/// let mut registry = Registry::new();
+/// let mut pool = EntityPool::new(64, || (Bullet { speed: 10.0 },));
+///
+/// let bullet = pool.acquire(&mut registry);
+/// pool.release(&mut registry, bullet).unwrap();
+///
+/// // The same entity is handed out again, with its components intact
+/// assert_eq!(pool.acquire(&mut registry), bullet);
+/// ```
+pub struct EntityPool<B> {
+    /// Builds the components of newly spawned pool entities
+    factory: Box<dyn Fn() -> B + Send + Sync>,
+    /// Disabled entities ready to be reused
+    inactive: Vec<Entity>,
+    /// Maximum number of inactive entities kept in the pool
+    capacity: usize,
+}
+
+impl<B: ComponentBundle + 'static> EntityPool<B> {
+    /// Creates a new empty pool keeping at most `capacity` inactive entities
+    pub fn new(capacity: usize, factory: impl Fn() -> B + Send + Sync + 'static) -> Self {
+        Self {
+            factory: Box::new(factory),
+            inactive: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Returns an active entity, reusing a pooled one if available.
+    ///
+    /// Reused entities keep the component values they had when released, so
+    /// callers are expected to reinitialize the fields they care about.
+    pub fn acquire(&mut self, registry: &mut Registry) -> Entity {
+        while let Some(entity) = self.inactive.pop() {
+            // Pooled entities may have been destroyed behind the pool's back
+            if registry.remove_component::<Disabled>(entity).is_ok() {
+                return entity;
+            }
+        }
+
+        registry.spawn((self.factory)())
+    }
+
+    /// Returns an entity to the pool, disabling it so queries skip it.
+    ///
+    /// If the pool is already full, the entity is destroyed instead.
+    pub fn release(&mut self, registry: &mut Registry, entity: Entity) -> Result<(), RecsError> {
+        // Releasing an entity twice must not hand it out twice later
+        if registry.get_component::<Disabled>(entity).is_some() {
+            return Ok(());
+        }
+
+        if self.inactive.len() >= self.capacity {
+            return registry.destroy_entity(entity);
+        }
+
+        registry.add_component(entity, Disabled)?;
+        self.inactive.push(entity);
+        Ok(())
+    }
+
+    /// Spawns disabled entities until the pool holds `count` inactive entities
+    /// (bounded by its capacity), so the first acquisitions don't allocate
+    pub fn prewarm(&mut self, registry: &mut Registry, count: usize) {
+        let target = count.min(self.capacity);
+        while self.inactive.len() < target {
+            let entity = registry.spawn((self.factory)());
+            registry.add_component(entity, Disabled).expect(
+                "Failed to disable newly created entity. This is a bug in the RECS library.",
+            );
+            self.inactive.push(entity);
+        }
+    }
+
+    /// Returns the number of inactive entities in the pool
+    pub fn len(&self) -> usize {
+        self.inactive.len()
+    }
+
+    /// Returns true if the pool holds no inactive entities
+    pub fn is_empty(&self) -> bool {
+        self.inactive.is_empty()
+    }
+
+    /// Returns the maximum number of inactive entities kept in the pool
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Destroys every inactive entity and empties the pool
+    pub fn clear(&mut self, registry: &mut Registry) {
+        for entity in self.inactive.drain(..) {
+            let _ = registry.destroy_entity(entity);
+        }
+    }
+}
+
+impl<B: ComponentBundle + Send + Sync + 'static> Resource for EntityPool<B> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::Component;
+
+    #[derive(Debug, PartialEq)]
+    struct Bullet {
+        speed: i32,
+    }
+    impl Component for Bullet {}
+
+    fn bullet_pool(capacity: usize) -> EntityPool<(Bullet,)> {
+        EntityPool::new(capacity, || (Bullet { speed: 1 },))
+    }
+
+    #[test]
+    fn test_released_entities_are_hidden_and_reused() {
+        let mut registry = Registry::new();
+        let mut pool = bullet_pool(4);
+
+        let bullet = pool.acquire(&mut registry);
+        registry.get_component_mut::<Bullet>(bullet).unwrap().speed = 7;
+        assert_eq!(registry.query::<(&Bullet,)>().count(), 1);
+
+        pool.release(&mut registry, bullet).unwrap();
+        assert_eq!(registry.query::<(&Bullet,)>().count(), 0);
+        assert!(registry.is_valid(bullet));
+        assert_eq!(pool.len(), 1);
+
+        let reused = pool.acquire(&mut registry);
+        assert_eq!(reused, bullet);
+        assert_eq!(
+            registry.get_component::<Bullet>(reused),
+            Some(&Bullet { speed: 7 })
+        );
+        assert_eq!(registry.query::<(&Bullet,)>().count(), 1);
+    }
+
+    #[test]
+    fn test_release_beyond_capacity_destroys() {
+        let mut registry = Registry::new();
+        let mut pool = bullet_pool(1);
+
+        let b1 = pool.acquire(&mut registry);
+        let b2 = pool.acquire(&mut registry);
+
+        pool.release(&mut registry, b1).unwrap();
+        pool.release(&mut registry, b2).unwrap();
+
+        assert_eq!(pool.len(), 1);
+        assert!(registry.is_valid(b1));
+        assert!(!registry.is_valid(b2));
+    }
+
+    #[test]
+    fn test_prewarm_and_destroyed_pooled_entities() {
+        let mut registry = Registry::new();
+        let mut pool = bullet_pool(8);
+
+        pool.prewarm(&mut registry, 3);
+        assert_eq!(pool.len(), 3);
+        assert_eq!(registry.query::<(&Bullet,)>().count(), 0);
+
+        registry.destroy_entity(pool.inactive[0]).unwrap();
+
+        for _ in 0..3 {
+            let entity = pool.acquire(&mut registry);
+            assert!(registry.is_valid(entity));
+        }
+        assert_eq!(registry.query::<(&Bullet,)>().count(), 3);
+    }
+}
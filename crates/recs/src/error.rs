@@ -1,6 +1,9 @@
 use std::{any::TypeId, fmt};
 
-use crate::entity::Entity;
+use crate::{
+    component::dynamic::ComponentId,
+    entity::{Entity, EntityRange, guid::EntityGuid},
+};
 
 /// Represents possible errors that can occur in the RECS system
 #[derive(Debug)]
@@ -9,6 +12,55 @@ pub enum RecsError {
     InvalidEntity(Entity),
     /// The requested component type was not found on the entity
     ComponentNotFound(TypeId),
+    /// The entity was not spawned from a prefab
+    NotAPrefabInstance(Entity),
+    /// A query expected to match exactly one entity matched none
+    NoEntities(&'static str),
+    /// A query expected to match exactly one entity matched several
+    MultipleEntities(&'static str),
+    /// The ordering constraints of these systems form a cycle
+    SystemOrderCycle(Vec<String>),
+    /// Another entity already carries this GUID
+    DuplicateGuid(EntityGuid),
+    /// The string is not a valid entity GUID
+    InvalidGuid(String),
+    /// No component codec is registered under this name
+    UnknownComponentCodec(String),
+    /// A component codec rejected the packed bytes of this component type
+    InvalidComponentData(&'static str),
+    /// A plugin with this name was already added to the registry
+    DuplicatePlugin(String),
+    /// The reserved entity range has no unused id left, or was released
+    EntityRangeExhausted(EntityRange),
+    /// Making the first entity a child of the second would form a cycle
+    HierarchyCycle(Entity, Entity),
+    /// A spawn table refers to a spawnable that isn't registered
+    UnknownSpawnable(String),
+    /// The text is not a valid spawn table, with the parser's message
+    InvalidSpawnTable(String),
+    /// The scene can't be parsed or spawned, with a description of why
+    InvalidScene(String),
+    /// No dynamic component is registered under this id
+    UnknownComponentId(ComponentId),
+    /// A dynamic component can't be registered or inserted, with a
+    /// description of why
+    InvalidDynamicComponent(String),
+    /// The group can't be created, with a description of why
+    InvalidGroup(String),
+    /// The storage of the component type can't be reordered, with a
+    /// description of why
+    UnsortableStorage(String),
+    /// The component type can't be moved to archetype tables, with a
+    /// description of why
+    UntableableStorage(String),
+    /// The entity can't be cloned, as these component types have no clone
+    /// function
+    NotClonable(Vec<&'static str>),
+    /// A time scale must be finite and not negative
+    InvalidTimeScale(f64),
+    /// A registry holding these non-send resources can't be stepped on
+    /// another thread
+    NonSendWorld(Vec<&'static str>),
 }
 
 impl fmt::Display for RecsError {
@@ -29,6 +81,107 @@ impl fmt::Display for RecsError {
                     type_id
                 )
             }
+            RecsError::NotAPrefabInstance(entity) => {
+                write!(
+                    f,
+                    "Entity was not spawned from a prefab: id={}, generation={}",
+                    entity.id(),
+                    entity.generation()
+                )
+            }
+            RecsError::NoEntities(query) => {
+                write!(f, "No entities match the query {}", query)
+            }
+            RecsError::MultipleEntities(query) => {
+                write!(
+                    f,
+                    "Multiple entities match the query {}, expected exactly one",
+                    query
+                )
+            }
+            RecsError::SystemOrderCycle(systems) => {
+                write!(
+                    f,
+                    "System ordering constraints form a cycle between: {}",
+                    systems.join(", ")
+                )
+            }
+            RecsError::DuplicateGuid(guid) => {
+                write!(f, "GUID {} is already used by another entity", guid)
+            }
+            RecsError::InvalidGuid(text) => {
+                write!(f, "Invalid entity GUID: {:?}", text)
+            }
+            RecsError::UnknownComponentCodec(name) => {
+                write!(f, "No component codec registered for {}", name)
+            }
+            RecsError::InvalidComponentData(component) => {
+                write!(f, "Packed data is not a valid {} component", component)
+            }
+            RecsError::DuplicatePlugin(name) => {
+                write!(f, "Plugin {} was already added", name)
+            }
+            RecsError::EntityRangeExhausted(range) => {
+                write!(
+                    f,
+                    "No entity id left in the reserved range {}..{}",
+                    range.start(),
+                    range.end()
+                )
+            }
+            RecsError::HierarchyCycle(child, parent) => {
+                write!(
+                    f,
+                    "Entity id={} can't become a child of entity id={}, its own descendant",
+                    child.id(),
+                    parent.id()
+                )
+            }
+            RecsError::UnknownSpawnable(name) => {
+                write!(f, "No spawnable registered under the name {}", name)
+            }
+            RecsError::InvalidSpawnTable(message) => {
+                write!(f, "Invalid spawn table: {}", message)
+            }
+            RecsError::InvalidScene(message) => write!(f, "Invalid scene: {}", message),
+            RecsError::UnknownComponentId(id) => {
+                write!(
+                    f,
+                    "No dynamic component is registered with id {}",
+                    id.index()
+                )
+            }
+            RecsError::InvalidDynamicComponent(message) => {
+                write!(f, "Invalid dynamic component: {}", message)
+            }
+            RecsError::InvalidGroup(message) => write!(f, "Invalid group: {}", message),
+            RecsError::UnsortableStorage(message) => {
+                write!(f, "Can't sort storage: {}", message)
+            }
+            RecsError::UntableableStorage(message) => {
+                write!(f, "Can't store in tables: {}", message)
+            }
+            RecsError::NotClonable(names) => {
+                write!(
+                    f,
+                    "Entity has components without a clone function: {}",
+                    names.join(", ")
+                )
+            }
+            RecsError::InvalidTimeScale(scale) => {
+                write!(
+                    f,
+                    "Invalid time scale {}: it must be finite and not negative",
+                    scale
+                )
+            }
+            RecsError::NonSendWorld(resources) => {
+                write!(
+                    f,
+                    "A world can't hold non-send resources, found: {}",
+                    resources.join(", ")
+                )
+            }
         }
     }
 }
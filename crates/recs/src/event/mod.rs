@@ -0,0 +1,535 @@
+use std::{
+    any::TypeId,
+    fmt,
+    marker::PhantomData,
+    mem::{swap, take},
+};
+
+use crate::{
+    component::Component,
+    entity::Entity,
+    event::trace::{EventAccess, EventLog},
+    resource::{Resource, ResourceStorage},
+    tick::Tick,
+    type_map::TypeIdMap,
+};
+
+pub mod trace;
+
+/// A double-buffered queue of events of type `T`, stored as a resource.
+///
+/// Events sent during a frame stay readable during the next one as well, so
+/// readers that run before the sender in the frame order still see them. The
+/// Registry swaps the buffers in `maintain()` for every event type registered
+/// with `Registry::add_event`, dropping events older than two frames.
+///
+/// Systems send events through `EventWriter` and read them through
+/// `EventReader`, which only yields the events sent since the system last ran.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// struct Damage { amount: u32 }
+///
+/// #[derive(Resource, Default)]
+/// struct TotalDamage(u32);
+///
+/// fn attack(mut damage: EventWriter<Damage>) {
+///     damage.send(Damage { amount: 5 });
+/// }
+///
+/// fn apply_damage(damage: EventReader<Damage>, mut total: ResMut<TotalDamage>) {
+///     for event in damage {
+///         total.0 += event.amount;
+///     }
+/// }
+///
+/// let mut registry = Registry::new();
+/// registry.add_event::<Damage>();
+/// registry.init_resource::<TotalDamage>();
+/// registry.add_system(attack);
+/// registry.add_system(apply_damage);
+///
+/// registry.run_systems();
+/// registry.run_systems();
+/// assert_eq!(registry.get_resource::<TotalDamage>().unwrap().0, 10);
+/// ```
+pub struct Events<T> {
+    /// Events sent during the previous frame, with the tick they were sent at
+    previous: Vec<(T, Tick)>,
+    /// Events sent during the current frame, with the tick they were sent at
+    current: Vec<(T, Tick)>,
+    /// Where sends and reads are recorded while the type is traced, see
+    /// `Registry::trace_event`
+    pub(crate) trace: Option<EventLog>,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Events<T> {
+    /// Creates a new empty event queue
+    pub fn new() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+            trace: None,
+        }
+    }
+
+    /// Sends an event, stamped with the change tick `tick`
+    pub fn send(&mut self, event: T, tick: Tick) {
+        if let Some(trace) = &self.trace {
+            trace.record(std::any::type_name::<T>(), EventAccess::Sent, tick);
+        }
+        self.current.push((event, tick));
+    }
+
+    /// Swaps the buffers, dropping the events sent before the current frame
+    pub fn update(&mut self) {
+        swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+    }
+
+    /// Returns an iterator over every retained event, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.iter_with_ticks().map(|(event, _)| event)
+    }
+
+    /// Returns an iterator over every retained event and the tick it was sent at
+    pub fn iter_with_ticks(&self) -> impl Iterator<Item = (&T, Tick)> {
+        self.previous
+            .iter()
+            .chain(&self.current)
+            .map(|(event, tick)| (event, *tick))
+    }
+
+    /// Removes and returns every retained event, oldest first
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + use<T> {
+        let previous = take(&mut self.previous);
+        let current = take(&mut self.current);
+        previous.into_iter().chain(current).map(|(event, _)| event)
+    }
+
+    /// Returns the number of retained events
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    /// Returns true if no events are retained
+    pub fn is_empty(&self) -> bool {
+        self.previous.is_empty() && self.current.is_empty()
+    }
+
+    /// Drops every retained event
+    pub fn clear(&mut self) {
+        self.previous.clear();
+        self.current.clear();
+    }
+
+    /// Returns the number of events sent during the current frame
+    pub(crate) fn current_len(&self) -> usize {
+        self.current.len()
+    }
+
+    /// Drops the events sent during the current frame beyond the first `len`
+    pub(crate) fn truncate_current(&mut self, len: usize) {
+        self.current.truncate(len);
+    }
+}
+
+impl<T: Send + Sync + 'static> Resource for Events<T> {}
+
+/// A system parameter that sends events of type `T`.
+///
+/// Panics if the event type wasn't registered with `Registry::add_event`.
+pub struct EventWriter<'a, T> {
+    events: &'a mut Events<T>,
+    /// The tick of the sending system's run
+    tick: Tick,
+}
+
+impl<'a, T> EventWriter<'a, T> {
+    pub fn new(events: &'a mut Events<T>, tick: Tick) -> Self {
+        Self { events, tick }
+    }
+
+    /// Sends an event
+    pub fn send(&mut self, event: T) {
+        self.events.send(event, self.tick);
+    }
+
+    /// Sends every event of `events`
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = T>) {
+        for event in events {
+            self.send(event);
+        }
+    }
+}
+
+/// A system parameter that reads the events of type `T` sent since the system
+/// last ran. Each reader sees every event once, as long as it runs at least
+/// once every two frames.
+///
+/// Panics if the event type wasn't registered with `Registry::add_event`.
+pub struct EventReader<'a, T> {
+    events: &'a Events<T>,
+    last_run: Tick,
+    this_run: Tick,
+}
+
+impl<'a, T> EventReader<'a, T> {
+    pub fn new(events: &'a Events<T>, last_run: Tick, this_run: Tick) -> Self {
+        Self {
+            events,
+            last_run,
+            this_run,
+        }
+    }
+
+    /// Returns an iterator over the unread events, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &'a T> + use<'a, T> {
+        let trace = &self.events.trace;
+        let this_run = self.this_run;
+        self.unread().inspect(move |_| {
+            if let Some(trace) = trace {
+                trace.record(std::any::type_name::<T>(), EventAccess::Read, this_run);
+            }
+        })
+    }
+
+    /// Returns the number of unread events
+    pub fn len(&self) -> usize {
+        self.unread().count()
+    }
+
+    /// Returns true if no events were sent since the system last ran
+    pub fn is_empty(&self) -> bool {
+        self.unread().next().is_none()
+    }
+
+    /// Returns the unread events without recording them as read when traced
+    fn unread(&self) -> impl Iterator<Item = &'a T> + use<'a, T> {
+        let (last_run, this_run) = (self.last_run, self.this_run);
+        self.events
+            .iter_with_ticks()
+            .filter(move |(_, tick)| tick.is_newer_than(last_run, this_run))
+            .map(|(event, _)| event)
+    }
+}
+
+impl<'a, T> IntoIterator for EventReader<'a, T> {
+    type Item = &'a T;
+    type IntoIter = Box<dyn Iterator<Item = &'a T> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+/// Sent whenever a `C` component is removed from an entity, including when
+/// the entity is destroyed, once registered with
+/// `Registry::add_event::<ComponentRemoved<C>>()`.
+///
+/// Unlike `RemovedComponents`, which only covers removals since the reading
+/// system last ran, removal events are buffered like any other event.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use recs::event::ComponentRemoved;
+/// #[derive(Component)]
+/// struct Shield;
+///
+/// #[derive(Resource, Default)]
+/// struct BrokenShields(Vec<Entity>);
+///
+/// fn announce(removed: EventReader<ComponentRemoved<Shield>>, mut broken: ResMut<BrokenShields>) {
+///     broken.0.extend(removed.iter().map(|event| event.entity));
+/// }
+///
+/// let mut registry = Registry::new();
+/// registry.add_event::<ComponentRemoved<Shield>>();
+/// registry.init_resource::<BrokenShields>();
+/// registry.add_system(announce);
+///
+/// let knight = registry.spawn((Shield,));
+/// registry.remove_component::<Shield>(knight).unwrap();
+/// registry.run_systems();
+/// assert_eq!(registry.get_resource::<BrokenShields>().unwrap().0, [knight]);
+/// ```
+pub struct ComponentRemoved<C> {
+    /// The entity the component was removed from
+    pub entity: Entity,
+    _marker: PhantomData<fn() -> C>,
+}
+
+impl<C> ComponentRemoved<C> {
+    /// Creates the event for the removal of a `C` component from `entity`
+    pub fn new(entity: Entity) -> Self {
+        Self {
+            entity,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<C> Clone for ComponentRemoved<C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C> Copy for ComponentRemoved<C> {}
+
+impl<C> PartialEq for ComponentRemoved<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entity == other.entity
+    }
+}
+
+impl<C> Eq for ComponentRemoved<C> {}
+
+impl<C> fmt::Debug for ComponentRemoved<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ComponentRemoved")
+            .field("entity", &self.entity)
+            .finish()
+    }
+}
+
+/// Panics with a hint to `add_event` when the `Events<T>` resource is missing
+pub(crate) fn missing_events<T>() -> ! {
+    panic!(
+        "Event type {} not found. Did you forget to call add_event?",
+        std::any::type_name::<T>()
+    )
+}
+
+/// Type-erased operations on a registered `Events<T>` resource
+struct EventType {
+    name: &'static str,
+    /// The TypeId of the `Events<T>` resource, as recorded in system accesses
+    resource: TypeId,
+    update: fn(&mut ResourceStorage),
+    len: fn(&ResourceStorage) -> usize,
+    current_len: fn(&ResourceStorage) -> usize,
+    truncate_current: fn(&mut ResourceStorage, usize),
+}
+
+/// Sends a `ComponentRemoved` event of a component type, if registered
+type RemovalSender = fn(&mut ResourceStorage, Entity, Tick);
+
+/// The event types registered with `Registry::add_event`, whose buffers the
+/// Registry swaps every frame
+#[derive(Default)]
+pub(crate) struct EventTypes {
+    types: TypeIdMap<EventType>,
+    /// Senders of the removal events of the component types, by component type
+    removals: TypeIdMap<RemovalSender>,
+    /// The sends and reads of the traced event types
+    pub(crate) log: EventLog,
+}
+
+impl EventTypes {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `T`, returning false if it already was
+    pub(crate) fn insert<T: Send + Sync + 'static>(&mut self) -> bool {
+        fn update<T: Send + Sync + 'static>(resources: &mut ResourceStorage) {
+            if let Some(events) = resources.get_mut::<Events<T>>() {
+                events.update();
+            }
+        }
+
+        fn len<T: Send + Sync + 'static>(resources: &ResourceStorage) -> usize {
+            resources.get::<Events<T>>().map_or(0, Events::len)
+        }
+
+        fn current_len<T: Send + Sync + 'static>(resources: &ResourceStorage) -> usize {
+            resources.get::<Events<T>>().map_or(0, Events::current_len)
+        }
+
+        fn truncate_current<T: Send + Sync + 'static>(resources: &mut ResourceStorage, len: usize) {
+            if let Some(events) = resources.get_mut::<Events<T>>() {
+                events.truncate_current(len);
+            }
+        }
+
+        if self.types.contains_key(&TypeId::of::<T>()) {
+            return false;
+        }
+        self.types.insert(
+            TypeId::of::<T>(),
+            EventType {
+                name: std::any::type_name::<T>(),
+                resource: TypeId::of::<Events<T>>(),
+                update: update::<T>,
+                len: len::<T>,
+                current_len: current_len::<T>,
+                truncate_current: truncate_current::<T>,
+            },
+        );
+        true
+    }
+
+    /// Records how to send `ComponentRemoved<C>` for a removal of `C` known
+    /// only by its type id
+    pub(crate) fn register_component<C: Component>(&mut self) {
+        self.removals
+            .entry(TypeId::of::<C>())
+            .or_insert(|resources, entity, tick| {
+                if let Some(events) = resources.get_mut::<Events<ComponentRemoved<C>>>() {
+                    events.send(ComponentRemoved::new(entity), tick);
+                }
+            });
+    }
+
+    /// Sends the `ComponentRemoved` event of the removal of a component of the
+    /// type from `entity`, if that event type is registered
+    pub(crate) fn send_removal(
+        &self,
+        resources: &mut ResourceStorage,
+        type_id: TypeId,
+        entity: Entity,
+        tick: Tick,
+    ) {
+        if let Some(send) = self.removals.get(&type_id) {
+            send(resources, entity, tick);
+        }
+    }
+
+    /// Swaps the buffers of every registered event type
+    pub(crate) fn update(&self, resources: &mut ResourceStorage) {
+        for event_type in self.types.values() {
+            (event_type.update)(resources);
+        }
+    }
+
+    /// Returns the name and number of retained events of every registered type
+    pub(crate) fn backlog<'a>(
+        &'a self,
+        resources: &'a ResourceStorage,
+    ) -> impl Iterator<Item = (&'static str, usize)> + 'a {
+        self.types
+            .values()
+            .map(|event_type| (event_type.name, (event_type.len)(resources)))
+    }
+
+    /// Returns the TypeId of every registered `Events<T>` resource along with
+    /// the number of events sent to it during the current frame
+    pub(crate) fn sent_this_frame<'a>(
+        &'a self,
+        resources: &'a ResourceStorage,
+    ) -> impl Iterator<Item = (TypeId, usize)> + 'a {
+        self.types
+            .values()
+            .map(|event_type| (event_type.resource, (event_type.current_len)(resources)))
+    }
+
+    /// Drops the events sent to the `Events<T>` resource with TypeId
+    /// `resource` during the current frame beyond the first `len`
+    pub(crate) fn truncate_sent(
+        &self,
+        resources: &mut ResourceStorage,
+        resource: TypeId,
+        len: usize,
+    ) {
+        if let Some(event_type) = self
+            .types
+            .values()
+            .find(|event_type| event_type.resource == resource)
+        {
+            (event_type.truncate_current)(resources, len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{registry::Registry, resource::ResMut};
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    struct Hit(u32);
+
+    #[derive(Default)]
+    struct Received(Vec<u32>);
+    impl Resource for Received {}
+
+    fn send_hit(mut hits: EventWriter<Hit>) {
+        hits.send(Hit(1));
+    }
+
+    fn receive_hits(hits: EventReader<Hit>, mut received: ResMut<Received>) {
+        received.0.extend(hits.iter().map(|hit| hit.0));
+    }
+
+    #[test]
+    fn test_events_are_double_buffered() {
+        let mut events = Events::new();
+        events.send(Hit(1), Tick::new(1));
+        events.update();
+        events.send(Hit(2), Tick::new(2));
+        assert_eq!(
+            events.iter().copied().collect::<Vec<_>>(),
+            vec![Hit(1), Hit(2)]
+        );
+
+        events.update();
+        assert_eq!(events.iter().copied().collect::<Vec<_>>(), vec![Hit(2)]);
+        events.update();
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_readers_see_each_event_once() {
+        let mut registry = Registry::new();
+        registry.add_event::<Hit>();
+        registry.init_resource::<Received>();
+        // The reader runs before the writer, so it sees the events one frame late
+        registry.add_system(receive_hits);
+        registry.add_system(send_hit);
+
+        registry.run_systems();
+        assert!(registry.get_resource::<Received>().unwrap().0.is_empty());
+
+        registry.send_event(Hit(7));
+        registry.run_systems();
+        registry.run_systems();
+        assert_eq!(
+            registry.get_resource::<Received>().unwrap().0,
+            vec![1, 7, 1]
+        );
+
+        // Events older than two frames are dropped
+        assert_eq!(registry.get_resource::<Events<Hit>>().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_removals_send_registered_events() {
+        struct Armor;
+        impl Component for Armor {}
+
+        #[derive(crate::Component)]
+        #[component(storage = "table")]
+        struct Mass;
+
+        let mut registry = Registry::new();
+        registry.add_event::<ComponentRemoved<Armor>>();
+        let knight = registry.spawn((Armor, Mass));
+        let squire = registry.spawn((Armor, Mass));
+        registry.remove_component::<Armor>(knight).unwrap();
+        // Removals of unregistered event types send nothing
+        registry.destroy_entity(squire).unwrap();
+
+        let events = registry.get_resource::<Events<ComponentRemoved<Armor>>>();
+        let entities: Vec<Entity> = events.unwrap().iter().map(|event| event.entity).collect();
+        assert_eq!(entities, [knight, squire]);
+    }
+}
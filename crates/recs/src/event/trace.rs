@@ -0,0 +1,202 @@
+//! Debug tracing of the sends and reads of selected event types, to find out
+//! which systems produce and consume an event. See `Registry::trace_event`.
+
+use std::{
+    cell::RefCell,
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::{event::Events, registry::Registry, tick::Tick};
+
+/// Whether a traced event was sent or read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventAccess {
+    Sent,
+    Read,
+}
+
+/// A single send or read of a traced event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventTraceEntry {
+    /// The type name of the event
+    pub event: &'static str,
+    pub access: EventAccess,
+    /// The name of the system that sent or read the event, or None outside
+    /// of systems
+    pub system: Option<&'static str>,
+    /// The tick the event was sent at, or the tick of the reading system's run
+    pub tick: Tick,
+}
+
+impl fmt::Display for EventTraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let access = match self.access {
+            EventAccess::Sent => "sent",
+            EventAccess::Read => "read",
+        };
+        write!(
+            f,
+            "tick {}: {} {} {}",
+            self.tick.get(),
+            self.system.unwrap_or("outside of systems"),
+            access,
+            self.event
+        )
+    }
+}
+
+/// The trace shared by the `Events<T>` resources of the traced event types,
+/// which readers may append to from several threads at once
+#[derive(Clone, Default)]
+pub(crate) struct EventLog(Arc<Mutex<Vec<EventTraceEntry>>>);
+
+impl EventLog {
+    /// Appends an entry attributed to the system running on this thread
+    pub(crate) fn record(&self, event: &'static str, access: EventAccess, tick: Tick) {
+        let system = RUNNING.with_borrow(|systems| systems.last().copied());
+        self.lock().push(EventTraceEntry {
+            event,
+            access,
+            system,
+            tick,
+        });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<EventTraceEntry>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+thread_local! {
+    /// Names of the systems running on this thread, innermost last
+    static RUNNING: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Attributes the events traced on this thread to a system while alive
+pub(crate) struct SystemScope;
+
+impl SystemScope {
+    pub(crate) fn enter(system: &'static str) -> Self {
+        RUNNING.with_borrow_mut(|systems| systems.push(system));
+        SystemScope
+    }
+}
+
+impl Drop for SystemScope {
+    /// Also runs when the system panics, so the stack stays balanced
+    fn drop(&mut self) {
+        RUNNING.with_borrow_mut(|systems| systems.pop());
+    }
+}
+
+impl Registry {
+    /// Starts or stops tracing the sends and reads of events of type `T`,
+    /// registering `T` if needed.
+    ///
+    /// While traced, every event sent, through `EventWriter`, `send_event` or
+    /// removal events, and every event yielded by an `EventReader` is
+    /// appended to the trace with the name of the system and the tick, to
+    /// debug where events go. Tracing costs a lock per event, so leave it off
+    /// outside of debugging sessions.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use recs::event::trace::EventAccess;
+    /// struct Damage(u32);
+    ///
+    /// fn attack(mut damage: EventWriter<Damage>) {
+    ///     damage.send(Damage(5));
+    /// }
+    ///
+    /// fn absorb(damage: EventReader<Damage>) {
+    ///     for _ in damage {}
+    /// }
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.trace_event::<Damage>(true);
+    /// registry.add_system(attack);
+    /// registry.add_system(absorb.after(attack));
+    /// registry.run_systems();
+    ///
+    /// let trace = registry.take_event_trace();
+    /// assert_eq!(trace[0].access, EventAccess::Sent);
+    /// assert!(trace[1].system.unwrap().ends_with("absorb"));
+    /// ```
+    pub fn trace_event<T: Send + Sync + 'static>(&mut self, enabled: bool) {
+        self.add_event::<T>();
+        let log = enabled.then(|| self.event_types.log.clone());
+        if let Some(events) = self.resources.get_mut::<Events<T>>() {
+            events.trace = log;
+        }
+    }
+
+    /// Returns the traced sends and reads recorded so far, oldest first, see
+    /// `trace_event`
+    pub fn event_trace(&self) -> Vec<EventTraceEntry> {
+        self.event_types.log.lock().clone()
+    }
+
+    /// Returns the traced sends and reads recorded so far and clears the
+    /// trace, see `trace_event`
+    pub fn take_event_trace(&mut self) -> Vec<EventTraceEntry> {
+        std::mem::take(&mut *self.event_types.log.lock())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{EventReader, EventWriter};
+
+    struct Ping;
+    struct Pong;
+
+    fn ping(mut pings: EventWriter<Ping>, mut pongs: EventWriter<Pong>) {
+        pings.send(Ping);
+        pongs.send(Pong);
+    }
+
+    fn count_pings(pings: EventReader<Ping>) {
+        // Counting doesn't read the events
+        if !pings.is_empty() {
+            for _ in pings {}
+        }
+    }
+
+    #[test]
+    fn test_only_traced_types_are_recorded() {
+        let mut registry = Registry::new();
+        registry.add_event::<Pong>();
+        registry.trace_event::<Ping>(true);
+        registry.add_system(ping);
+        registry.add_system(count_pings);
+        registry.run_systems();
+        registry.send_event(Ping);
+
+        let trace = registry.take_event_trace();
+        let accesses: Vec<_> = trace
+            .iter()
+            .map(|entry| {
+                (
+                    entry.access,
+                    entry.system.map(|system| system.ends_with("count_pings")),
+                )
+            })
+            .collect();
+        assert_eq!(
+            accesses,
+            [
+                (EventAccess::Sent, Some(false)),
+                (EventAccess::Read, Some(true)),
+                (EventAccess::Sent, None),
+            ]
+        );
+        assert!(trace.iter().all(|entry| entry.event.ends_with("Ping")));
+
+        registry.trace_event::<Ping>(false);
+        registry.run_systems();
+        assert!(registry.event_trace().is_empty());
+    }
+}
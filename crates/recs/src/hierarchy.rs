@@ -0,0 +1,258 @@
+//! Parent/child relationships between entities.
+//!
+//! A child carries a `Parent` component pointing at its parent, and the parent
+//! carries a `Children` component listing its children in insertion order. Both
+//! sides are only changed through the registry's hierarchy methods, which keep
+//! them in sync, including when entities are destroyed.
+
+use crate::{
+    component::Component,
+    entity::{
+        Entity,
+        map::{EntityMapper, MapEntities},
+    },
+    error::RecsError,
+    registry::Registry,
+};
+
+/// The parent of an entity, maintained by `Registry::set_parent`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Parent(Entity);
+
+impl Parent {
+    /// Returns the parent entity
+    pub fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+impl Component for Parent {}
+
+impl MapEntities for Parent {
+    fn map_entities(&mut self, mapper: &mut dyn EntityMapper) {
+        self.0.map_entities(mapper);
+    }
+}
+
+/// The children of an entity in insertion order, maintained by
+/// `Registry::set_parent` and `Registry::add_child`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Children(Vec<Entity>);
+
+impl std::ops::Deref for Children {
+    type Target = [Entity];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Component for Children {}
+
+impl MapEntities for Children {
+    fn map_entities(&mut self, mapper: &mut dyn EntityMapper) {
+        self.0.map_entities(mapper);
+    }
+}
+
+impl Registry {
+    /// Makes `child` a child of `parent`, detaching it from its previous
+    /// parent if it had one.
+    ///
+    /// Returns an error if either entity is invalid, or if `parent` is `child`
+    /// itself or one of its descendants.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// let mut registry = Registry::new();
+    /// let ship = registry.create_entity();
+    /// let turret = registry.create_entity();
+    ///
+    /// registry.set_parent(turret, ship).unwrap();
+    /// assert_eq!(registry.parent(turret), Some(ship));
+    /// assert_eq!(registry.children(ship), &[turret]);
+    ///
+    /// // Destroying the parent orphans its children
+    /// registry.destroy_entity(ship).unwrap();
+    /// assert_eq!(registry.parent(turret), None);
+    /// ```
+    pub fn set_parent(&mut self, child: Entity, parent: Entity) -> Result<(), RecsError> {
+        for entity in [child, parent] {
+            if !self.is_valid(entity) {
+                return Err(RecsError::InvalidEntity(entity));
+            }
+        }
+        if self.parent(child) == Some(parent) {
+            return Ok(());
+        }
+
+        let mut ancestor = Some(parent);
+        while let Some(entity) = ancestor {
+            if entity == child {
+                return Err(RecsError::HierarchyCycle(child, parent));
+            }
+            ancestor = self.parent(entity);
+        }
+
+        self.detach_from_parent(child);
+        self.add_component(child, Parent(parent))?;
+        match self.get_component_mut::<Children>(parent) {
+            Some(children) => children.0.push(child),
+            None => self.add_component(parent, Children(vec![child]))?,
+        }
+        Ok(())
+    }
+
+    /// Makes `child` a child of `parent`, see `set_parent`
+    pub fn add_child(&mut self, parent: Entity, child: Entity) -> Result<(), RecsError> {
+        self.set_parent(child, parent)
+    }
+
+    /// Detaches `child` from its parent and returns the parent, if it had one.
+    /// Returns an error if `child` is invalid.
+    pub fn remove_parent(&mut self, child: Entity) -> Result<Option<Entity>, RecsError> {
+        if !self.is_valid(child) {
+            return Err(RecsError::InvalidEntity(child));
+        }
+        Ok(self.detach_from_parent(child))
+    }
+
+    /// Detaches every entity of `children` that is a child of `parent`.
+    /// Returns an error if `parent` is invalid.
+    pub fn remove_children(
+        &mut self,
+        parent: Entity,
+        children: &[Entity],
+    ) -> Result<(), RecsError> {
+        if !self.is_valid(parent) {
+            return Err(RecsError::InvalidEntity(parent));
+        }
+        for &child in children {
+            if self.parent(child) == Some(parent) {
+                self.detach_from_parent(child);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the parent of `entity`, if it has one
+    pub fn parent(&self, entity: Entity) -> Option<Entity> {
+        self.get_component::<Parent>(entity).map(Parent::get)
+    }
+
+    /// Returns the children of `entity` in insertion order
+    pub fn children(&self, entity: Entity) -> &[Entity] {
+        self.get_component::<Children>(entity)
+            .map_or(&[], |children| &children.0)
+    }
+
+    /// Destroys `entity` along with all of its descendants
+    pub fn despawn_recursive(&mut self, entity: Entity) -> Result<(), RecsError> {
+        if !self.is_valid(entity) {
+            return Err(RecsError::InvalidEntity(entity));
+        }
+
+        let mut doomed = vec![entity];
+        let mut index = 0;
+        while let Some(&next) = doomed.get(index) {
+            doomed.extend_from_slice(self.children(next));
+            index += 1;
+        }
+        // Children first, so no parent ever points at a destroyed entity
+        for entity in doomed.into_iter().rev() {
+            self.destroy_entity(entity)?;
+        }
+        Ok(())
+    }
+
+    /// Removes `child` from its parent's children and drops its `Parent`,
+    /// returning the former parent
+    fn detach_from_parent(&mut self, child: Entity) -> Option<Entity> {
+        let parent = self.remove_component::<Parent>(child).ok()?.get();
+        if let Some(children) = self.get_component_mut::<Children>(parent) {
+            children.0.retain(|&entity| entity != child);
+            if children.is_empty() {
+                let _ = self.remove_component::<Children>(parent);
+            }
+        }
+        Some(parent)
+    }
+
+    /// Unlinks `entity` from its parent and children before it is destroyed
+    pub(crate) fn detach_hierarchy(&mut self, entity: Entity) {
+        self.detach_from_parent(entity);
+        if let Ok(children) = self.remove_component::<Children>(entity) {
+            for child in children.0 {
+                let _ = self.remove_component::<Parent>(child);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reparenting_keeps_both_sides_in_sync() {
+        let mut registry = Registry::new();
+        let [a, b, c] = [(); 3].map(|_| registry.create_entity());
+
+        registry.set_parent(c, a).unwrap();
+        registry.add_child(a, b).unwrap();
+        assert_eq!(registry.children(a), &[c, b]);
+
+        registry.set_parent(c, b).unwrap();
+        assert_eq!(registry.children(a), &[b]);
+        assert_eq!(registry.children(b), &[c]);
+        assert_eq!(registry.parent(c), Some(b));
+
+        assert!(matches!(
+            registry.set_parent(a, c),
+            Err(RecsError::HierarchyCycle(child, parent)) if child == a && parent == c
+        ));
+        assert!(registry.set_parent(a, a).is_err());
+
+        registry.remove_children(b, &[c, a]).unwrap();
+        assert_eq!(registry.parent(c), None);
+        assert!(registry.get_component::<Children>(b).is_none());
+        assert_eq!(registry.remove_parent(b).unwrap(), Some(a));
+        assert!(registry.get_component::<Children>(a).is_none());
+    }
+
+    #[test]
+    fn test_destroying_entities_unlinks_them() {
+        let mut registry = Registry::new();
+        let [root, middle, leaf, other] = [(); 4].map(|_| registry.create_entity());
+        registry.set_parent(middle, root).unwrap();
+        registry.set_parent(leaf, middle).unwrap();
+        registry.set_parent(other, root).unwrap();
+
+        registry.destroy_entity(middle).unwrap();
+        assert_eq!(registry.children(root), &[other]);
+        assert_eq!(registry.parent(leaf), None);
+
+        registry.set_parent(leaf, other).unwrap();
+        registry.despawn_recursive(root).unwrap();
+        assert!(![root, other, leaf].iter().any(|&e| registry.is_valid(e)));
+        assert_eq!(registry.entities().count(), 0);
+    }
+
+    #[test]
+    fn test_despawn_recursive_command_keeps_the_rest_of_the_tree() {
+        let mut registry = Registry::new();
+        let [root, branch, leaf, sibling] = [(); 4].map(|_| registry.create_entity());
+        registry.set_parent(branch, root).unwrap();
+        registry.set_parent(leaf, branch).unwrap();
+        registry.set_parent(sibling, root).unwrap();
+
+        registry.commands().despawn_recursive(branch);
+        registry.apply_commands();
+
+        assert!(!registry.is_valid(branch) && !registry.is_valid(leaf));
+        assert_eq!(registry.children(root), &[sibling]);
+    }
+}
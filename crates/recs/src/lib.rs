@@ -1,17 +1,48 @@
+// Lets the derives, which refer to `recs::...`, be used inside the crate
+extern crate self as recs;
+
+pub use recs_macros::Bundle;
 pub use recs_macros::Component;
+pub use recs_macros::Reflect;
 pub use recs_macros::Resource;
 
 pub mod component;
 pub mod entity;
 pub mod error;
+pub mod event;
+pub mod hierarchy;
+pub mod observer;
+pub mod plugin;
+pub mod prefab;
 pub mod query;
+pub mod reflect;
 pub mod registry;
+pub mod relationship;
 pub mod resource;
+pub mod rng;
+pub mod runner;
+#[cfg(feature = "ron")]
+pub mod scene;
+pub mod spawn_table;
 pub mod system;
+pub mod testing;
+pub mod tick;
+pub mod time;
+#[cfg(feature = "transform")]
+pub mod transform;
+pub(crate) mod type_map;
 
 pub mod prelude {
+    #[allow(deprecated)]
+    pub use crate::resource::{OptionalRes, OptionalResMut};
     pub use crate::{
-        Component, Resource, query::Query, registry::Registry, resource::OptionalRes,
-        resource::OptionalResMut, resource::Res, resource::ResMut,
+        Bundle, Component, Reflect, Resource, component::removed::RemovedComponents,
+        entity::Disabled, entity::Entity, event::EventReader, event::EventWriter, event::Events,
+        hierarchy::Children, hierarchy::Parent, plugin::Plugin, prefab::Prefab, query::Added,
+        query::Changed, query::IncludeDisabled, query::Mut, query::Query, registry::Registry,
+        resource::FromWorld, resource::Res, resource::ResMut, resource::non_send::NonSend,
+        resource::non_send::NonSendMut, system::IntoSystem, system::commands::Commands,
+        system::output::SystemOutput, system::param_set::ParamSet, system::pipe::In,
+        system::schedule::IntoSystemConfig, system::schedule::Stage, time::FixedTime, time::Time,
     };
 }
@@ -0,0 +1,396 @@
+//! Push-style reactions to events, as an alternative to polling queries.
+//!
+//! An observer is a callback subscribed to one event type, either for every
+//! entity or for a single one. Events are triggered explicitly with
+//! `Registry::trigger` and `Registry::trigger_for`, or by the registry itself
+//! when components are added, inserted or removed (`OnAdd`, `OnInsert` and
+//! `OnRemove`). Component events are delivered when commands are applied, so
+//! observers never see an entity in the middle of a bundle insertion.
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashSet,
+    marker::PhantomData,
+    ops::Deref,
+};
+
+use crate::{
+    component::Component, entity::Entity, registry::Registry, system::commands::Commands,
+    type_map::TypeIdMap,
+};
+
+/// Triggered for an entity when it gets a `C` component it didn't have
+pub struct OnAdd<C>(PhantomData<fn() -> C>);
+
+/// Triggered for an entity whenever a `C` component is inserted on it,
+/// including when it replaces a previous value
+pub struct OnInsert<C>(PhantomData<fn() -> C>);
+
+/// Triggered for an entity when its `C` component is removed, including when
+/// the entity is destroyed
+pub struct OnRemove<C>(PhantomData<fn() -> C>);
+
+macro_rules! impl_lifecycle_event {
+    ($($event:ident),+) => {$(
+        impl<C> $event<C> {
+            fn new() -> Self {
+                Self(PhantomData)
+            }
+        }
+    )+};
+}
+
+impl_lifecycle_event!(OnAdd, OnInsert, OnRemove);
+
+/// Identifies an observer, to remove it with `Registry::remove_observer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverId(u64);
+
+/// The event an observer is reacting to, passed to its callback
+pub struct Trigger<'a, E> {
+    event: &'a E,
+    entity: Option<Entity>,
+}
+
+impl<'a, E> Trigger<'a, E> {
+    /// Returns the triggered event
+    pub fn event(&self) -> &'a E {
+        self.event
+    }
+
+    /// Returns the entity the event was triggered for, if any
+    pub fn entity(&self) -> Option<Entity> {
+        self.entity
+    }
+}
+
+impl<E> Deref for Trigger<'_, E> {
+    type Target = E;
+
+    fn deref(&self) -> &Self::Target {
+        self.event
+    }
+}
+
+type ObserverFn = Box<dyn FnMut(&dyn Any, Option<Entity>, &mut Registry) + Send + Sync>;
+
+/// Triggers `OnRemove` of a component type for an entity
+type RemovalTrigger = fn(&mut Registry, Entity);
+
+struct Observer {
+    id: ObserverId,
+    /// The only entity the observer reacts to, if it is scoped to one
+    target: Option<Entity>,
+    callback: ObserverFn,
+}
+
+/// The observers of a registry, by event type
+#[derive(Default)]
+pub(crate) struct Observers {
+    by_event: TypeIdMap<Vec<Observer>>,
+    /// Triggers `OnRemove` for the component types, which are erased on some
+    /// removal paths
+    removals: TypeIdMap<(TypeId, RemovalTrigger)>,
+    /// Observers taken out to run, see `Registry::run_observers`
+    running: HashSet<ObserverId>,
+    /// Running observers that were removed meanwhile
+    removed_while_running: HashSet<ObserverId>,
+    next_id: u64,
+}
+
+impl Observers {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records how to trigger `OnRemove<C>` for a removal of `C` known only
+    /// by its type id
+    pub(crate) fn register<C: Component>(&mut self) {
+        self.removals
+            .entry(TypeId::of::<C>())
+            .or_insert((TypeId::of::<OnRemove<C>>(), |registry, entity| {
+                registry.trigger_for(OnRemove::<C>::new(), entity)
+            }));
+    }
+
+    fn observes(&self, event: TypeId) -> bool {
+        self.by_event
+            .get(&event)
+            .is_some_and(|observers| !observers.is_empty())
+    }
+}
+
+impl Registry {
+    /// Runs `observer` whenever an `E` event is triggered, for any entity or
+    /// none. Returns its id, for `remove_observer`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use recs::observer::OnAdd;
+    /// #[derive(Component)]
+    /// struct Burning;
+    ///
+    /// #[derive(Resource, Default)]
+    /// struct Alarms(u32);
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.init_resource::<Alarms>();
+    /// registry.observe::<OnAdd<Burning>>(|_trigger, registry| {
+    ///     registry.get_resource_mut::<Alarms>().unwrap().0 += 1;
+    /// });
+    ///
+    /// registry.spawn((Burning,));
+    /// registry.apply_commands();
+    /// assert_eq!(registry.get_resource::<Alarms>().unwrap().0, 1);
+    /// ```
+    pub fn observe<E: 'static>(
+        &mut self,
+        observer: impl FnMut(Trigger<E>, &mut Registry) + Send + Sync + 'static,
+    ) -> ObserverId {
+        self.add_observer(None, observer)
+    }
+
+    /// Runs `observer` whenever an `E` event is triggered for `entity`. The
+    /// observer is removed once the entity is destroyed, after observing its
+    /// `OnRemove` events.
+    pub fn observe_entity<E: 'static>(
+        &mut self,
+        entity: Entity,
+        observer: impl FnMut(Trigger<E>, &mut Registry) + Send + Sync + 'static,
+    ) -> ObserverId {
+        self.add_observer(Some(entity), observer)
+    }
+
+    fn add_observer<E: 'static>(
+        &mut self,
+        target: Option<Entity>,
+        mut observer: impl FnMut(Trigger<E>, &mut Registry) + Send + Sync + 'static,
+    ) -> ObserverId {
+        let id = ObserverId(self.observers.next_id);
+        self.observers.next_id += 1;
+        let callback: ObserverFn = Box::new(move |event, entity, registry| {
+            let event = event
+                .downcast_ref::<E>()
+                .expect("Observers are called with events of their type");
+            observer(Trigger { event, entity }, registry)
+        });
+        self.observers
+            .by_event
+            .entry(TypeId::of::<E>())
+            .or_default()
+            .push(Observer {
+                id,
+                target,
+                callback,
+            });
+        id
+    }
+
+    /// Removes an observer. Returns false if it was already removed.
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        for observers in self.observers.by_event.values_mut() {
+            if let Some(index) = observers.iter().position(|observer| observer.id == id) {
+                observers.remove(index);
+                return true;
+            }
+        }
+        // Running observers are dropped once they all ran
+        self.observers.running.contains(&id) && self.observers.removed_while_running.insert(id)
+    }
+
+    /// Runs the observers of `E` that aren't scoped to an entity.
+    ///
+    /// Observers run right away, in the order they were added. An event
+    /// triggered again by one of its own observers doesn't reach the observers
+    /// that are already running.
+    pub fn trigger<E: 'static>(&mut self, event: E) {
+        self.run_observers(&event, None);
+    }
+
+    /// Runs the observers of `E` that aren't scoped to an entity and those
+    /// scoped to `entity`
+    pub fn trigger_for<E: 'static>(&mut self, event: E, entity: Entity) {
+        self.run_observers(&event, Some(entity));
+    }
+
+    fn run_observers<E: 'static>(&mut self, event: &E, entity: Option<Entity>) {
+        let type_id = TypeId::of::<E>();
+        // Observers are taken out while they run, so they can trigger events,
+        // add observers and remove observers themselves
+        let Some(mut running) = self.observers.by_event.remove(&type_id) else {
+            return;
+        };
+        self.observers
+            .running
+            .extend(running.iter().map(|observer| observer.id));
+        for observer in &mut running {
+            if observer.target.is_none_or(|target| Some(target) == entity)
+                && !self.observers.removed_while_running.contains(&observer.id)
+            {
+                (observer.callback)(event, entity, self);
+            }
+        }
+
+        let Observers {
+            running: running_ids,
+            removed_while_running: removed,
+            ..
+        } = &mut self.observers;
+        running.retain(|observer| {
+            running_ids.remove(&observer.id);
+            !removed.remove(&observer.id)
+        });
+        let observers = self.observers.by_event.entry(type_id).or_default();
+        running.append(observers);
+        *observers = running;
+    }
+
+    /// Queues the `OnAdd` and `OnInsert` events of a `C` component inserted on
+    /// `entity`
+    pub(crate) fn queue_insert_events<C: Component>(&mut self, entity: Entity, added: bool) {
+        let mut commands = Commands::new(&mut self.command_queue, &mut self.entity_manager);
+        if added && self.observers.observes(TypeId::of::<OnAdd<C>>()) {
+            commands.trigger_for(OnAdd::<C>::new(), entity);
+        }
+        if self.observers.observes(TypeId::of::<OnInsert<C>>()) {
+            commands.trigger_for(OnInsert::<C>::new(), entity);
+        }
+    }
+
+    /// Queues the `OnRemove` event of a component of the type removed from
+    /// `entity`
+    pub(crate) fn queue_remove_event(&mut self, type_id: TypeId, entity: Entity) {
+        if let Some(&(event, trigger)) = self.observers.removals.get(&type_id)
+            && self.observers.observes(event)
+        {
+            self.command_queue
+                .push(move |registry: &mut Registry| trigger(registry, entity));
+        }
+    }
+
+    /// Queues the removal of the observers scoped to `entity`, which was
+    /// destroyed, after the events already queued for it
+    pub(crate) fn queue_observers_cleanup(&mut self, entity: Entity) {
+        let scoped = self.observers.by_event.values().flatten();
+        if scoped
+            .into_iter()
+            .any(|observer| observer.target == Some(entity))
+        {
+            self.command_queue.push(move |registry: &mut Registry| {
+                for observers in registry.observers.by_event.values_mut() {
+                    observers.retain(|observer| observer.target != Some(entity));
+                }
+            });
+        }
+    }
+}
+
+impl Commands<'_> {
+    /// Queues triggering `event`, see `Registry::trigger`
+    pub fn trigger<E: Send + 'static>(&mut self, event: E) {
+        self.add(move |registry| registry.trigger(event));
+    }
+
+    /// Queues triggering `event` for `entity`, see `Registry::trigger_for`
+    pub fn trigger_for<E: Send + 'static>(&mut self, event: E, entity: Entity) {
+        self.add(move |registry| registry.trigger_for(event, entity));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::Resource;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+    impl Component for Health {}
+
+    struct Burning;
+    impl Component for Burning {}
+
+    struct Explosion {
+        radius: u32,
+    }
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+    impl Resource for Log {}
+
+    fn log(registry: &mut Registry, line: String) {
+        registry.get_resource_mut::<Log>().unwrap().0.push(line);
+    }
+
+    fn lines(registry: &Registry) -> Vec<String> {
+        registry.get_resource::<Log>().unwrap().0.clone()
+    }
+
+    #[test]
+    fn test_component_events() {
+        let mut registry = Registry::new();
+        registry.init_resource::<Log>();
+        registry.observe::<OnAdd<Health>>(|trigger, registry| {
+            let health = registry.get_component::<Health>(trigger.entity().unwrap());
+            let line = format!("add {}", health.unwrap().0);
+            log(registry, line);
+        });
+        registry.observe::<OnInsert<Health>>(|_, registry| log(registry, "insert".into()));
+        registry.observe::<OnRemove<Health>>(|_, registry| log(registry, "remove".into()));
+
+        let entity = registry.spawn((Health(10),));
+        registry.add_component(entity, Health(5)).unwrap();
+        registry.apply_commands();
+        registry.destroy_entity(entity).unwrap();
+        registry.apply_commands();
+
+        // Events are delivered once commands are applied, so the first one
+        // already sees the replaced value
+        assert_eq!(lines(&registry), ["add 5", "insert", "insert", "remove"]);
+    }
+
+    #[test]
+    fn test_entity_scoped_observers() {
+        let mut registry = Registry::new();
+        registry.init_resource::<Log>();
+        let target = registry.spawn((Health(1),));
+        let other = registry.spawn((Health(1),));
+        registry.observe_entity::<OnAdd<Burning>>(target, |_, registry| {
+            log(registry, "target burns".into());
+        });
+        registry.observe_entity::<Explosion>(target, |trigger, registry| {
+            let line = format!("hit by {}", trigger.radius);
+            log(registry, line);
+        });
+
+        registry.add_component(other, Burning).unwrap();
+        registry.add_component(target, Burning).unwrap();
+        registry.apply_commands();
+        registry.trigger(Explosion { radius: 1 });
+        registry.trigger_for(Explosion { radius: 2 }, other);
+        registry.trigger_for(Explosion { radius: 3 }, target);
+        assert_eq!(lines(&registry), ["target burns", "hit by 3"]);
+
+        registry.destroy_entity(target).unwrap();
+        registry.apply_commands();
+        assert!(registry.observers.by_event.values().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn test_observers_can_remove_themselves() {
+        let mut registry = Registry::new();
+        registry.init_resource::<Log>();
+        let id = std::sync::Arc::new(std::sync::OnceLock::new());
+        let own_id = id.clone();
+        let observer = registry.observe::<Explosion>(move |_, registry| {
+            log(registry, "once".into());
+            assert!(registry.remove_observer(*own_id.get().unwrap()));
+        });
+        id.set(observer).unwrap();
+
+        registry.trigger(Explosion { radius: 1 });
+        registry.trigger(Explosion { radius: 1 });
+        assert_eq!(lines(&registry), ["once"]);
+        assert!(!registry.remove_observer(observer));
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{error::RecsError, registry::Registry};
+
+/// A reusable module registering its components, resources and systems as a
+/// unit, e.g. physics, input or networking.
+///
+/// Plugins are identified by `name`, which defaults to the type name: adding
+/// a plugin whose name is already registered fails with
+/// `RecsError::DuplicatePlugin`. Override `name` to allow several instances of
+/// one plugin type with different configurations.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// #[derive(Component)]
+/// struct Velocity { dy: f32 }
+///
+/// #[derive(Resource)]
+/// struct Gravity(f32);
+///
+/// fn apply_gravity(gravity: Res<Gravity>, query: Query<(&mut Velocity,)>) {
+///     for (mut velocity,) in query {
+///         velocity.dy += gravity.0;
+///     }
+/// }
+///
+/// struct PhysicsPlugin;
+///
+/// impl Plugin for PhysicsPlugin {
+///     fn build(&self, registry: &mut Registry) {
+///         registry.register_component::<Velocity>();
+///         registry.insert_resource(Gravity(-9.81));
+///         registry.add_system(apply_gravity);
+///     }
+/// }
+///
+/// let mut registry = Registry::new();
+/// registry.add_plugin(PhysicsPlugin).unwrap();
+/// assert!(registry.has_plugin::<PhysicsPlugin>());
+/// assert!(registry.add_plugin(PhysicsPlugin).is_err());
+/// ```
+pub trait Plugin: 'static {
+    /// Registers everything the plugin provides into `registry`
+    fn build(&self, registry: &mut Registry);
+
+    /// Returns the name identifying this plugin
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// The names of the plugins added to a registry, in the order they were added
+#[derive(Debug, Default)]
+pub(crate) struct Plugins {
+    names: Vec<String>,
+}
+
+impl Plugins {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.names.iter().any(|added| added == name)
+    }
+
+    /// Records `name`, or returns false if it was already recorded
+    pub(crate) fn insert(&mut self, name: &str) -> bool {
+        if self.contains(name) {
+            return false;
+        }
+        self.names.push(name.to_owned());
+        true
+    }
+
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+}
+
+impl Registry {
+    /// Adds a plugin, running its `build` method right away.
+    ///
+    /// Returns `RecsError::DuplicatePlugin` without building it if a plugin
+    /// with the same name was already added. Plugins may add the plugins they
+    /// depend on from `build`, checking `has_plugin` first.
+    pub fn add_plugin<P: Plugin>(&mut self, plugin: P) -> Result<(), RecsError> {
+        // Record the plugin before building it, so that a plugin adding
+        // itself again from `build` is reported too
+        if !self.plugins.insert(plugin.name()) {
+            return Err(RecsError::DuplicatePlugin(plugin.name().to_owned()));
+        }
+        plugin.build(self);
+        Ok(())
+    }
+
+    /// Checks if a plugin of type `P` with its default name was added
+    pub fn has_plugin<P: Plugin>(&self) -> bool {
+        self.plugins.contains(std::any::type_name::<P>())
+    }
+
+    /// Checks if a plugin named `name` was added
+    pub fn has_plugin_named(&self, name: &str) -> bool {
+        self.plugins.contains(name)
+    }
+
+    /// Returns the names of the added plugins, in the order they were added
+    pub fn plugin_names(&self) -> impl Iterator<Item = &str> {
+        self.plugins.names()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::Resource;
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+    impl Resource for Log {}
+
+    struct Input;
+
+    impl Plugin for Input {
+        fn build(&self, registry: &mut Registry) {
+            registry.init_resource::<Log>();
+            registry
+                .get_resource_mut::<Log>()
+                .unwrap()
+                .0
+                .push("input".into());
+        }
+    }
+
+    struct Player;
+
+    impl Plugin for Player {
+        fn build(&self, registry: &mut Registry) {
+            if !registry.has_plugin::<Input>() {
+                registry.add_plugin(Input).unwrap();
+            }
+            registry
+                .get_resource_mut::<Log>()
+                .unwrap()
+                .0
+                .push("player".into());
+        }
+    }
+
+    struct Channel(&'static str);
+
+    impl Plugin for Channel {
+        fn build(&self, _registry: &mut Registry) {}
+
+        fn name(&self) -> &str {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_plugin_dependencies_build_once() {
+        let mut registry = Registry::new();
+        registry.add_plugin(Player).unwrap();
+        assert!(matches!(
+            registry.add_plugin(Input),
+            Err(RecsError::DuplicatePlugin(_))
+        ));
+
+        assert_eq!(
+            registry.get_resource::<Log>().unwrap().0,
+            ["input", "player"]
+        );
+        assert_eq!(
+            registry.plugin_names().collect::<Vec<_>>(),
+            [
+                std::any::type_name::<Player>(),
+                std::any::type_name::<Input>()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_named_plugin_instances() {
+        let mut registry = Registry::new();
+        registry.add_plugin(Channel("chat")).unwrap();
+        registry.add_plugin(Channel("voice")).unwrap();
+        assert!(registry.add_plugin(Channel("chat")).is_err());
+
+        assert!(registry.has_plugin_named("voice"));
+        assert!(!registry.has_plugin::<Channel>());
+    }
+}
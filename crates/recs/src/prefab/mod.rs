@@ -0,0 +1,521 @@
+use std::{
+    any::{Any, TypeId},
+    sync::Arc,
+};
+
+use crate::{
+    component::Component,
+    entity::Entity,
+    error::RecsError,
+    registry::{Registry, bundle::ComponentBundle},
+};
+
+/// A type-erased component value stored inside a prefab.
+///
+/// Alongside the value it keeps monomorphized function pointers so that the
+/// value can be cloned into an entity or compared against a live component
+/// without knowing its concrete type.
+struct PrefabComponent {
+    type_id: TypeId,
+    type_name: &'static str,
+    value: Box<dyn Any + Send + Sync>,
+    /// Clones the stored value onto an entity
+    insert: fn(&(dyn Any + Send + Sync), &mut Registry, Entity) -> Result<(), RecsError>,
+    /// Compares the stored value with the entity's live component.
+    /// Returns None if the entity does not have the component.
+    matches: fn(&(dyn Any + Send + Sync), &Registry, Entity) -> Option<bool>,
+    /// Clones the entity's live component into a new PrefabComponent
+    extract: fn(&Registry, Entity) -> Option<PrefabComponent>,
+}
+
+impl PrefabComponent {
+    fn new<C: Component + Clone + PartialEq>(component: C) -> Self {
+        Self {
+            type_id: TypeId::of::<C>(),
+            type_name: std::any::type_name::<C>(),
+            value: Box::new(component),
+            insert: |value, registry, entity| {
+                let value = value
+                    .downcast_ref::<C>()
+                    .expect("prefab value type mismatch");
+                registry.add_component(entity, value.clone())
+            },
+            matches: |value, registry, entity| {
+                let value = value
+                    .downcast_ref::<C>()
+                    .expect("prefab value type mismatch");
+                registry
+                    .get_component::<C>(entity)
+                    .map(|live| live == value)
+            },
+            extract: |registry, entity| {
+                registry
+                    .get_component::<C>(entity)
+                    .map(|live| PrefabComponent::new(live.clone()))
+            },
+        }
+    }
+}
+
+/// A reusable template of component values that entities can be spawned from.
+///
+/// Entities spawned through `Registry::spawn_prefab` remember which prefab they
+/// came from, which allows comparing them against it later on: extracting the
+/// overridden components for compact saves, or reverting them back to the
+/// prefab's values.
+///
+/// Prefab components must be `Clone` (to be instantiated) and `PartialEq`
+/// (to be diffed against live entities).
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use std::sync::Arc;
+/// #[derive(Component, Clone, PartialEq)]
+/// struct Health(u32);
+///
+/// let goblin = Arc::new(Prefab::new().with(Health(30)));
+///
+/// let mut registry = Registry::new();
+/// let entity = registry.spawn_prefab(&goblin);
+/// registry.get_component_mut::<Health>(entity).unwrap().0 = 10;
+///
+/// let diff = registry.prefab_diff(entity).unwrap();
+/// assert!(diff.overrides.contains::<Health>());
+///
+/// registry.revert_to_prefab(entity).unwrap();
+/// assert_eq!(registry.get_component::<Health>(entity).unwrap().0, 30);
+/// ```
+///
+/// A prefab can also be a variant of a base prefab, inheriting the base's
+/// components and overriding some of them:
+/// ```rust
+/// # use recs::prelude::*;
+/// # use std::sync::Arc;
+/// #[derive(Component, Clone, PartialEq, Debug)]
+/// struct Stats { health: u32, speed: f32 }
+///
+/// #[derive(Component, Clone, PartialEq, Debug)]
+/// struct Position(f32, f32);
+///
+/// let enemy = Arc::new(
+///     Prefab::new()
+///         .with(Stats { health: 30, speed: 1.0 })
+///         .with(Position(0.0, 0.0)),
+/// );
+/// let brute = Arc::new(Prefab::variant_of(&enemy).with_override(|stats: &mut Stats| {
+///     stats.health = 80;
+/// }));
+///
+/// let mut registry = Registry::new();
+/// let entity = registry.spawn_prefab_with(&brute, (Position(4.0, 2.0),));
+/// assert_eq!(
+///     registry.get_component::<Stats>(entity),
+///     Some(&Stats { health: 80, speed: 1.0 })
+/// );
+/// assert_eq!(registry.get_component::<Position>(entity), Some(&Position(4.0, 2.0)));
+/// ```
+#[derive(Default)]
+pub struct Prefab {
+    /// The prefab this one is a variant of
+    base: Option<Arc<Prefab>>,
+    /// The prefab's own components, replacing those of the base
+    components: Vec<PrefabComponent>,
+}
+
+impl Prefab {
+    /// Creates a new empty Prefab
+    pub fn new() -> Self {
+        Self {
+            base: None,
+            components: Vec::new(),
+        }
+    }
+
+    /// Creates a variant of `base`: it has every component of the base,
+    /// except those it overrides with `with`, `insert` or `with_override`
+    pub fn variant_of(base: &Arc<Prefab>) -> Self {
+        Self {
+            base: Some(base.clone()),
+            components: Vec::new(),
+        }
+    }
+
+    /// Returns the prefab this one is a variant of
+    pub fn base(&self) -> Option<&Arc<Prefab>> {
+        self.base.as_ref()
+    }
+
+    /// Overrides some fields of the prefab's `C` component, whether it is the
+    /// prefab's own or inherited from its base.
+    ///
+    /// # Panics
+    /// Panics if neither the prefab nor its bases have a `C` component.
+    pub fn with_override<C: Component + Clone + PartialEq>(
+        mut self,
+        apply: impl FnOnce(&mut C),
+    ) -> Self {
+        let mut component = self.get::<C>().cloned().unwrap_or_else(|| {
+            panic!(
+                "Prefab has no {} component to override",
+                std::any::type_name::<C>()
+            )
+        });
+        apply(&mut component);
+        self.insert(component);
+        self
+    }
+
+    /// Adds a component value to the prefab, replacing any existing value of the same type
+    pub fn with<C: Component + Clone + PartialEq>(mut self, component: C) -> Self {
+        self.insert(component);
+        self
+    }
+
+    /// Inserts a component value into the prefab.
+    /// If a value of the same type already exists, it will be replaced.
+    pub fn insert<C: Component + Clone + PartialEq>(&mut self, component: C) {
+        self.insert_erased(PrefabComponent::new(component));
+    }
+
+    fn insert_erased(&mut self, component: PrefabComponent) {
+        match self
+            .components
+            .iter_mut()
+            .find(|c| c.type_id == component.type_id)
+        {
+            Some(existing) => *existing = component,
+            None => self.components.push(component),
+        }
+    }
+
+    /// Gets a reference to a component value stored in the prefab or
+    /// inherited from its base
+    pub fn get<C: Component>(&self) -> Option<&C> {
+        self.find(TypeId::of::<C>())
+            .and_then(|c| c.value.downcast_ref::<C>())
+    }
+
+    /// Finds the component with `type_id`, looking through the bases
+    fn find(&self, type_id: TypeId) -> Option<&PrefabComponent> {
+        self.components
+            .iter()
+            .find(|c| c.type_id == type_id)
+            .or_else(|| self.base.as_ref()?.find(type_id))
+    }
+
+    /// Returns every component of the prefab, including inherited ones, base
+    /// components first
+    fn resolved(&self) -> Vec<&PrefabComponent> {
+        let mut resolved = self
+            .base
+            .as_ref()
+            .map_or_else(Vec::new, |base| base.resolved());
+        for component in &self.components {
+            match resolved.iter_mut().find(|c| c.type_id == component.type_id) {
+                Some(inherited) => *inherited = component,
+                None => resolved.push(component),
+            }
+        }
+        resolved
+    }
+
+    /// Checks if the prefab contains a value for the given component type
+    pub fn contains<C: Component>(&self) -> bool {
+        self.contains_type(TypeId::of::<C>())
+    }
+
+    /// Checks if the prefab contains a value for the component type with `type_id`
+    pub fn contains_type(&self, type_id: TypeId) -> bool {
+        self.find(type_id).is_some()
+    }
+
+    /// Returns the type names of all components in the prefab, including
+    /// inherited ones
+    pub fn component_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.resolved().into_iter().map(|c| c.type_name)
+    }
+
+    /// Returns the number of components in the prefab, including inherited ones
+    pub fn len(&self) -> usize {
+        self.resolved().len()
+    }
+
+    /// Returns true if the prefab contains no components
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Clones every component of the prefab onto `entity`, including
+    /// inherited ones
+    pub fn insert_into(&self, registry: &mut Registry, entity: Entity) -> Result<(), RecsError> {
+        for component in self.resolved() {
+            (component.insert)(component.value.as_ref(), registry, entity)?;
+        }
+        Ok(())
+    }
+}
+
+/// Records which prefab an entity was spawned from.
+///
+/// This component is attached automatically by `Registry::spawn_prefab`.
+#[derive(Clone)]
+pub struct PrefabInstance {
+    prefab: Arc<Prefab>,
+}
+
+impl Component for PrefabInstance {
+    const CLONE: Option<fn(&Self) -> Self> = Some(Self::clone);
+}
+
+impl PrefabInstance {
+    /// Creates provenance information pointing at `prefab`
+    pub fn new(prefab: Arc<Prefab>) -> Self {
+        Self { prefab }
+    }
+
+    /// Returns the prefab the entity was spawned from
+    pub fn prefab(&self) -> &Arc<Prefab> {
+        &self.prefab
+    }
+}
+
+/// The difference between a live entity and the prefab it was spawned from.
+pub struct PrefabDiff {
+    /// Components whose live value differs from the prefab, holding the live values
+    pub overrides: Prefab,
+    /// Types of prefab components that have been removed from the entity
+    pub removed: Vec<TypeId>,
+}
+
+impl PrefabDiff {
+    /// Returns true if the entity still matches its prefab exactly
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl Registry {
+    /// Spawns a new entity with a clone of every component in `prefab`,
+    /// recording the prefab as the entity's origin.
+    pub fn spawn_prefab(&mut self, prefab: &Arc<Prefab>) -> Entity {
+        let entity = self.create_entity();
+        prefab
+            .insert_into(self, entity)
+            .and_then(|_| self.add_component(entity, PrefabInstance::new(prefab.clone())))
+            .expect(
+                "Failed to add prefab to newly created entity. This is a bug in the RECS library.",
+            );
+        entity
+    }
+
+    /// Spawns an entity from `prefab` like `spawn_prefab`, then inserts the
+    /// components of `overrides` over the prefab's values. Overridden
+    /// components show up in `prefab_diff`.
+    pub fn spawn_prefab_with<B: ComponentBundle>(
+        &mut self,
+        prefab: &Arc<Prefab>,
+        overrides: B,
+    ) -> Entity {
+        let entity = self.spawn_prefab(prefab);
+        overrides.add_to_entity(self, entity).expect(
+            "Failed to add overrides to newly spawned prefab. This is a bug in the RECS library.",
+        );
+        self.insert_required_components();
+        entity
+    }
+
+    /// Compares a live entity against the prefab it was spawned from.
+    ///
+    /// Returns an error if the entity is invalid or was not spawned from a prefab.
+    pub fn prefab_diff(&self, entity: Entity) -> Result<PrefabDiff, RecsError> {
+        let prefab = self.prefab_of(entity)?;
+
+        let mut diff = PrefabDiff {
+            overrides: Prefab::new(),
+            removed: Vec::new(),
+        };
+        for component in prefab.resolved() {
+            match (component.matches)(component.value.as_ref(), self, entity) {
+                Some(true) => {}
+                Some(false) => {
+                    if let Some(live) = (component.extract)(self, entity) {
+                        diff.overrides.insert_erased(live);
+                    }
+                }
+                None => diff.removed.push(component.type_id),
+            }
+        }
+
+        Ok(diff)
+    }
+
+    /// Resets every prefab component on the entity back to the prefab's value,
+    /// re-adding components that were removed.
+    ///
+    /// Components that already match the prefab are left untouched, so they
+    /// are not reported as changed.
+    pub fn revert_to_prefab(&mut self, entity: Entity) -> Result<(), RecsError> {
+        let prefab = self.prefab_of(entity)?;
+
+        for component in prefab.resolved() {
+            if (component.matches)(component.value.as_ref(), self, entity) != Some(true) {
+                (component.insert)(component.value.as_ref(), self, entity)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn prefab_of(&self, entity: Entity) -> Result<Arc<Prefab>, RecsError> {
+        if !self.is_valid(entity) {
+            return Err(RecsError::InvalidEntity(entity));
+        }
+
+        self.get_component::<PrefabInstance>(entity)
+            .map(|instance| instance.prefab.clone())
+            .ok_or(RecsError::NotAPrefabInstance(entity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Health(u32);
+    impl Component for Health {}
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Name(&'static str);
+    impl Component for Name {}
+
+    fn goblin() -> Arc<Prefab> {
+        Arc::new(Prefab::new().with(Health(30)).with(Name("goblin")))
+    }
+
+    #[test]
+    fn test_spawn_prefab_clones_components() {
+        let mut registry = Registry::new();
+        let prefab = goblin();
+
+        let e1 = registry.spawn_prefab(&prefab);
+        let e2 = registry.spawn_prefab(&prefab);
+
+        assert_eq!(registry.get_component::<Health>(e1), Some(&Health(30)));
+        assert_eq!(registry.get_component::<Name>(e2), Some(&Name("goblin")));
+        assert!(Arc::ptr_eq(
+            registry
+                .get_component::<PrefabInstance>(e1)
+                .unwrap()
+                .prefab(),
+            &prefab
+        ));
+    }
+
+    #[test]
+    fn test_diff_extracts_only_overrides() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn_prefab(&goblin());
+
+        assert!(registry.prefab_diff(entity).unwrap().is_empty());
+
+        registry.get_component_mut::<Health>(entity).unwrap().0 = 5;
+        registry.remove_component::<Name>(entity).unwrap();
+
+        let diff = registry.prefab_diff(entity).unwrap();
+        assert_eq!(diff.overrides.len(), 1);
+        assert_eq!(diff.overrides.get::<Health>(), Some(&Health(5)));
+        assert_eq!(diff.removed, vec![TypeId::of::<Name>()]);
+    }
+
+    #[test]
+    fn test_revert_to_prefab() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn_prefab(&goblin());
+
+        registry.get_component_mut::<Health>(entity).unwrap().0 = 5;
+        registry.remove_component::<Name>(entity).unwrap();
+
+        registry.revert_to_prefab(entity).unwrap();
+
+        assert_eq!(registry.get_component::<Health>(entity), Some(&Health(30)));
+        assert_eq!(
+            registry.get_component::<Name>(entity),
+            Some(&Name("goblin"))
+        );
+        assert!(registry.prefab_diff(entity).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_overrides_can_be_reapplied() {
+        let mut registry = Registry::new();
+        let prefab = goblin();
+        let original = registry.spawn_prefab(&prefab);
+        registry.get_component_mut::<Health>(original).unwrap().0 = 12;
+
+        let overrides = registry.prefab_diff(original).unwrap().overrides;
+
+        let restored = registry.spawn_prefab(&prefab);
+        overrides.insert_into(&mut registry, restored).unwrap();
+
+        assert_eq!(
+            registry.get_component::<Health>(restored),
+            Some(&Health(12))
+        );
+        assert_eq!(
+            registry.get_component::<Name>(restored),
+            Some(&Name("goblin"))
+        );
+    }
+
+    #[test]
+    fn test_variants_inherit_and_override() {
+        let base = goblin();
+        let chief = Arc::new(
+            Prefab::variant_of(&base)
+                .with_override(|health: &mut Health| health.0 *= 2)
+                .with(Name("chief")),
+        );
+        let elder = Arc::new(
+            Prefab::variant_of(&chief).with_override(|health: &mut Health| {
+                health.0 += 1;
+            }),
+        );
+
+        assert_eq!(elder.get::<Health>(), Some(&Health(61)));
+        assert_eq!(elder.get::<Name>(), Some(&Name("chief")));
+        assert_eq!(elder.len(), 2);
+        assert_eq!(base.get::<Health>(), Some(&Health(30)));
+        assert!(Arc::ptr_eq(elder.base().unwrap(), &chief));
+
+        let mut registry = Registry::new();
+        let entity = registry.spawn_prefab_with(&elder, (Name("Grok"),));
+        assert_eq!(registry.get_component::<Health>(entity), Some(&Health(61)));
+        let diff = registry.prefab_diff(entity).unwrap();
+        assert_eq!(diff.overrides.get::<Name>(), Some(&Name("Grok")));
+        assert_eq!(diff.overrides.len(), 1);
+
+        registry.revert_to_prefab(entity).unwrap();
+        assert_eq!(registry.get_component::<Name>(entity), Some(&Name("chief")));
+    }
+
+    #[test]
+    #[should_panic(expected = "Prefab has no")]
+    fn test_overriding_a_missing_component_panics() {
+        Prefab::new()
+            .with(Name("x"))
+            .with_override(|health: &mut Health| health.0 = 1);
+    }
+
+    #[test]
+    fn test_diff_on_plain_entity_returns_error() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn(Health(1));
+
+        assert!(matches!(
+            registry.prefab_diff(entity),
+            Err(RecsError::NotAPrefabInstance(_))
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+use std::marker::PhantomData;
+
+use crate::{
+    query::{QueryFilter, QueryParam, QueryStorage, ReadOnlyQueryParam},
+    registry::Registry,
+    tick::Tick,
+};
+
+/// An iterator over every unique combination of `K` distinct entities matching
+/// a query, created by `Query::iter_combinations` and
+/// `Query::iter_combinations_mut`.
+///
+/// Combinations are produced in lexicographic order of the matching entities.
+/// The set of matching entities is captured when the iterator is created.
+pub struct QueryCombinationIter<'s, Q: QueryStorage, F, const K: usize> {
+    registry: *mut Registry,
+    /// The storages of the queried components, or None if one is missing
+    storages: Option<Q::Storages>,
+    /// Ids of the entities matching the query and filter
+    entities: Vec<u32>,
+    /// Positions in `entities` of the current combination
+    indices: [usize; K],
+    started: bool,
+    this_run: Tick,
+    _phantom: PhantomData<(&'s mut Registry, Q, F)>,
+}
+
+impl<'s, Q: QueryStorage, F: QueryFilter, const K: usize> QueryCombinationIter<'s, Q, F, K> {
+    /// Creates an iterator over the combinations of `entities`, the ids of
+    /// the entities matching `Q` and `F`, fetched from `storages`
+    ///
+    /// # Safety
+    /// `registry` and the storages must be alive for the whole lifetime `'s`,
+    /// and no other reference to the queried components may be alive at the
+    /// same time.
+    pub(crate) unsafe fn new(
+        registry: *mut Registry,
+        storages: Option<Q::Storages>,
+        entities: Vec<u32>,
+        this_run: Tick,
+    ) -> Self {
+        Self {
+            registry,
+            storages,
+            entities,
+            indices: [0; K],
+            started: false,
+            this_run,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Moves `indices` to the next combination, returning false once exhausted
+    fn advance(&mut self) -> bool {
+        let n = self.entities.len();
+        if K == 0 || K > n {
+            return false;
+        }
+
+        if !self.started {
+            self.started = true;
+            for (i, index) in self.indices.iter_mut().enumerate() {
+                *index = i;
+            }
+            return true;
+        }
+
+        // Find the rightmost index that can still be incremented
+        let Some(i) = (0..K).rev().find(|&i| self.indices[i] < n - K + i) else {
+            self.indices = [n; K];
+            return false;
+        };
+
+        self.indices[i] += 1;
+        for j in i + 1..K {
+            self.indices[j] = self.indices[j - 1] + 1;
+        }
+        true
+    }
+
+    /// Fetches the items of the current combination
+    ///
+    /// # Safety
+    /// No items previously fetched for any of the current entities may still be
+    /// alive if `Q` hands out mutable references.
+    unsafe fn fetch_current<'f>(&self) -> Option<[<Q as QueryParam<'f>>::Item; K]>
+    where
+        Q: QueryParam<'f>,
+    {
+        let storages = self.storages?;
+        let items = self.indices.map(|index| unsafe {
+            Q::fetch(self.registry, storages, self.entities[index], self.this_run)
+        });
+        if items.iter().any(Option::is_none) {
+            return None;
+        }
+        Some(items.map(Option::unwrap))
+    }
+
+    /// Fetches the next combination, borrowing the iterator until the returned
+    /// items are dropped
+    pub fn fetch_next<'f>(&'f mut self) -> Option<[<Q as QueryParam<'f>>::Item; K]>
+    where
+        Q: QueryParam<'f>,
+    {
+        while self.advance() {
+            // SAFETY: the returned items borrow self mutably, so items from a
+            // previous combination can't be alive anymore, and the entities
+            // within a combination are always distinct
+            if let Some(items) = unsafe { self.fetch_current() } {
+                return Some(items);
+            }
+        }
+        None
+    }
+}
+
+impl<'s, Q, F, const K: usize> Iterator for QueryCombinationIter<'s, Q, F, K>
+where
+    Q: QueryParam<'s> + ReadOnlyQueryParam,
+    F: QueryFilter,
+{
+    type Item = [Q::Item; K];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.advance() {
+            // SAFETY: read-only items can coexist with any number of others
+            if let Some(items) = unsafe { self.fetch_current() } {
+                return Some(items);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{
+    component::dynamic::{ComponentId, DynamicStorage},
+    entity::{Disabled, Entity},
+    error::RecsError,
+    registry::Registry,
+};
+
+/// A query over dynamic components, built at runtime from `ComponentId`s.
+///
+/// Matches every enabled entity that has all the fetched components and none
+/// of the excluded ones, and yields the bytes of the fetched components in the
+/// order they were listed.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use recs::query::DynamicQuery;
+/// # use std::alloc::Layout;
+/// let mut registry = Registry::new();
+/// let speed = registry.register_dynamic_component("Speed", Layout::new::<u8>()).unwrap();
+/// let frozen = registry.register_dynamic_component("Frozen", Layout::new::<()>()).unwrap();
+///
+/// let runner = registry.create_entity();
+/// registry.insert_dynamic(runner, speed, &[3]).unwrap();
+/// let statue = registry.create_entity();
+/// registry.insert_dynamic(statue, speed, &[5]).unwrap();
+/// registry.insert_dynamic(statue, frozen, &[]).unwrap();
+///
+/// let query = DynamicQuery::new([speed]).without(frozen);
+/// registry
+///     .for_each_dynamic_mut(&query, |_, values| values[0][0] *= 2)
+///     .unwrap();
+///
+/// let speeds: Vec<_> = registry
+///     .query_dynamic(&query)
+///     .unwrap()
+///     .map(|(entity, values)| (entity, values[0][0]))
+///     .collect();
+/// assert_eq!(speeds, [(runner, 6)]);
+/// assert_eq!(registry.get_dynamic(statue, speed), Some(&[5][..]));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DynamicQuery {
+    fetch: Vec<ComponentId>,
+    without: Vec<ComponentId>,
+}
+
+impl DynamicQuery {
+    /// Creates a query fetching the `fetch` components. A query fetching
+    /// nothing matches every enabled entity.
+    pub fn new(fetch: impl IntoIterator<Item = ComponentId>) -> Self {
+        Self {
+            fetch: fetch.into_iter().collect(),
+            without: Vec::new(),
+        }
+    }
+
+    /// Only matches entities that don't have the `id` component
+    pub fn without(mut self, id: ComponentId) -> Self {
+        self.without.push(id);
+        self
+    }
+
+    /// Returns the fetched components, in the order their values are yielded
+    pub fn fetched(&self) -> &[ComponentId] {
+        &self.fetch
+    }
+}
+
+impl Registry {
+    /// Iterates over the entities matching `query`, with the bytes of their
+    /// fetched components. Returns `UnknownComponentId` if the query refers
+    /// to a component that isn't registered.
+    pub fn query_dynamic<'r>(
+        &'r self,
+        query: &DynamicQuery,
+    ) -> Result<impl Iterator<Item = (Entity, Vec<&'r [u8]>)> + 'r, RecsError> {
+        let fetch = self.dynamic_storages(&query.fetch)?;
+        let without = self.dynamic_storages(&query.without)?;
+
+        let candidates: Vec<Entity> = match fetch.iter().min_by_key(|s| s.entities().len()) {
+            Some(storage) => storage.entities().to_vec(),
+            None => self.entities().collect(),
+        };
+        let values = fetch.clone();
+        let matches = move |entity: &Entity| {
+            let id = entity.id() as usize;
+            fetch.iter().all(|storage| storage.contains(id))
+                && !without.iter().any(|storage| storage.contains(id))
+                && !self.has_component::<Disabled>(*entity)
+        };
+
+        Ok(candidates.into_iter().filter(matches).map(move |entity| {
+            let id = entity.id() as usize;
+            let values = values
+                .iter()
+                .map(|storage| storage.get(id).expect("matched entities have every value"))
+                .collect();
+            (entity, values)
+        }))
+    }
+
+    /// Calls `f` with every entity matching `query` and the mutable bytes of
+    /// its fetched components, marking them as changed. Returns
+    /// `InvalidDynamicComponent` if the query fetches a component twice.
+    pub fn for_each_dynamic_mut(
+        &mut self,
+        query: &DynamicQuery,
+        mut f: impl FnMut(Entity, &mut [&mut [u8]]),
+    ) -> Result<(), RecsError> {
+        let entities: Vec<Entity> = self
+            .query_dynamic(query)?
+            .map(|(entity, _)| entity)
+            .collect();
+        let tick = self.change_tick();
+        let mut storages = self.dynamic.get_many_mut(&query.fetch).ok_or_else(|| {
+            RecsError::InvalidDynamicComponent("a query fetches a component twice".to_string())
+        })?;
+
+        for entity in entities {
+            let id = entity.id() as usize;
+            let mut values: Vec<&mut [u8]> = storages
+                .iter_mut()
+                .map(|storage| {
+                    storage
+                        .get_mut(id, tick)
+                        .expect("matched entities have every value")
+                })
+                .collect();
+            f(entity, &mut values);
+        }
+        Ok(())
+    }
+
+    fn dynamic_storages(&self, ids: &[ComponentId]) -> Result<Vec<&DynamicStorage>, RecsError> {
+        ids.iter()
+            .map(|&id| {
+                self.dynamic
+                    .get(id)
+                    .ok_or(RecsError::UnknownComponentId(id))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::Layout;
+
+    #[test]
+    fn test_values_follow_fetch_order_and_skip_disabled() {
+        let mut registry = Registry::new();
+        let a = registry
+            .register_dynamic_component("A", Layout::new::<u8>())
+            .unwrap();
+        let b = registry
+            .register_dynamic_component("B", Layout::new::<u8>())
+            .unwrap();
+
+        let both = registry.create_entity();
+        registry.insert_dynamic(both, a, &[1]).unwrap();
+        registry.insert_dynamic(both, b, &[2]).unwrap();
+        let disabled = registry.create_entity();
+        registry.insert_dynamic(disabled, a, &[3]).unwrap();
+        registry.insert_dynamic(disabled, b, &[4]).unwrap();
+        registry.add_component(disabled, Disabled).unwrap();
+        let only_a = registry.create_entity();
+        registry.insert_dynamic(only_a, a, &[5]).unwrap();
+
+        let items: Vec<_> = registry
+            .query_dynamic(&DynamicQuery::new([b, a]))
+            .unwrap()
+            .map(|(entity, values)| (entity, values.concat()))
+            .collect();
+        assert_eq!(items, [(both, vec![2, 1])]);
+        assert_eq!(
+            registry
+                .query_dynamic(&DynamicQuery::new([]))
+                .unwrap()
+                .count(),
+            2
+        );
+
+        let tick = registry.change_tick();
+        registry.clear_trackers();
+        registry
+            .for_each_dynamic_mut(&DynamicQuery::new([a]).without(b), |entity, values| {
+                assert_eq!(entity, only_a);
+                values[0][0] = 7;
+            })
+            .unwrap();
+        assert_eq!(registry.get_dynamic(only_a, a), Some(&[7][..]));
+        assert_ne!(registry.dynamic_ticks(only_a, a).unwrap().changed, tick);
+
+        assert!(matches!(
+            registry.for_each_dynamic_mut(&DynamicQuery::new([a, a]), |_, _| {}),
+            Err(RecsError::InvalidDynamicComponent(_))
+        ));
+        let unregistered = Registry::new();
+        assert!(matches!(
+            unregistered
+                .query_dynamic(&DynamicQuery::new([a]))
+                .map(|iter| iter.count()),
+            Err(RecsError::UnknownComponentId(_))
+        ));
+    }
+}
@@ -0,0 +1,180 @@
+//! Entity-only queries: `(Entity,)` yields every enabled, alive entity that
+//! passes the filter, without fetching any component.
+
+use std::iter::FusedIterator;
+
+use crate::{
+    entity::{Entity, EntityManager},
+    query::{
+        ComponentStorages, QueryFilter, QueryIter, QueryParam, QueryStorage, ReadOnlyQueryParam,
+    },
+    registry::Registry,
+    system::access::Access,
+    tick::Tick,
+};
+
+impl ReadOnlyQueryParam for Entity {}
+
+impl QueryStorage for (Entity,) {
+    /// Entities aren't stored in a component storage
+    type Storages = ();
+
+    fn storages(_components: &ComponentStorages) -> Option<Self::Storages> {
+        Some(())
+    }
+
+    /// No storage bounds an entity query, which walks the entity id slots
+    /// instead
+    unsafe fn min_len(_storages: Self::Storages) -> usize {
+        usize::MAX
+    }
+
+    unsafe fn smallest_entities(_storages: Self::Storages) -> *const [Entity] {
+        &[]
+    }
+
+    unsafe fn visit_ids(
+        registry: *const Registry,
+        _storages: Self::Storages,
+        mut visit: impl FnMut(u32) -> bool,
+    ) {
+        // SAFETY: forwarded from the caller
+        let entity_manager = unsafe { &(*registry).entity_manager };
+        for id in 0..entity_manager.slot_count() as u32 {
+            if entity_manager.alive_entity(id).is_some() && !visit(id) {
+                return;
+            }
+        }
+    }
+}
+
+impl<'q> QueryParam<'q> for (Entity,) {
+    type Item = (Entity,);
+
+    fn access(access: &mut Access) {
+        access.read_entities();
+    }
+
+    unsafe fn fetch(
+        registry: *mut Registry,
+        _storages: Self::Storages,
+        entity_id: u32,
+        _this_run: Tick,
+    ) -> Option<Self::Item> {
+        unsafe {
+            (*registry)
+                .entity_manager
+                .alive_entity(entity_id)
+                .map(|entity| (entity,))
+        }
+    }
+}
+
+impl<F: QueryFilter> QueryIter<'_, (Entity,), F> {
+    /// Borrows the entity slots for a single step, since commands of the
+    /// querying system may reserve entities between two steps
+    fn entity_manager(&self) -> &EntityManager {
+        // SAFETY: the registry is alive for 'q, and commands only borrow the
+        // entity slots while reserving an entity
+        unsafe { &(*self.registry).entity_manager }
+    }
+}
+
+impl<'q, F: QueryFilter> Iterator for QueryIter<'q, (Entity,), F> {
+    type Item = (Entity,);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.exhausted {
+            return None;
+        }
+
+        // Entities reserved by commands during the walk get new slots, which
+        // aren't walked
+        if self.len == usize::MAX {
+            self.len = self.entity_manager().slot_count();
+        }
+        let slots = self.len;
+        let registry_ptr = self.registry;
+        let disabled = self.disabled;
+
+        while self.entity_index < slots {
+            let id = self.entity_index as u32;
+            self.entity_index += 1;
+
+            let Some(entity) = self.entity_manager().alive_entity(id) else {
+                continue;
+            };
+            // SAFETY: the registry, and so the `Disabled` storage, is borrowed
+            // for 'q
+            unsafe {
+                if disabled.is_some_and(|d| (*d).get(id as usize).is_some())
+                    || !F::matches(registry_ptr, self.filter, id, self.last_run, self.this_run)
+                {
+                    continue;
+                }
+            }
+            return Some((entity,));
+        }
+
+        self.exhausted = true;
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.exhausted {
+            return (0, Some(0));
+        }
+        let slots = self.len.min(self.entity_manager().slot_count());
+        (0, Some(slots.saturating_sub(self.entity_index)))
+    }
+}
+
+impl<'q, F: QueryFilter> FusedIterator for QueryIter<'q, (Entity,), F> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{component::Component, entity::Disabled, query::Query};
+
+    #[derive(Debug, PartialEq)]
+    struct Tag;
+    impl Component for Tag {}
+
+    #[test]
+    fn test_entity_query_yields_enabled_alive_entities() {
+        let mut registry = Registry::new();
+        let [a, b, c, d] = [(); 4].map(|_| registry.create_entity());
+        registry.add_component(c, Tag).unwrap();
+        registry.destroy_entity(b).unwrap();
+        registry.add_component(d, Disabled).unwrap();
+
+        let entities: Vec<Entity> = registry.query::<(Entity,)>().map(|(e,)| e).collect();
+        assert_eq!(entities, [a, c]);
+        assert_eq!(registry.entities().collect::<Vec<_>>(), [a, c, d]);
+
+        let query = Query::<(Entity,)>::new(&mut registry);
+        assert_eq!(query.get(c), Some((c,)));
+        assert_eq!(query.get(b), None);
+        assert_eq!(query.get(d), None);
+        assert_eq!(query.iter_combinations::<2>().count(), 1);
+    }
+
+    #[test]
+    fn test_commands_reserve_entities_during_the_walk() {
+        let mut registry = Registry::new();
+        let existing = registry.spawn_batch((0..3).map(|_| (Tag,)));
+        registry.add_system(
+            |mut commands: crate::system::commands::Commands, entities: Query<(Entity,)>| {
+                for (entity,) in entities.iter() {
+                    let spawned = commands.spawn((Tag,));
+                    assert_ne!(entity, spawned);
+                }
+            },
+        );
+        registry.run_systems();
+
+        let tagged: Vec<Entity> = registry.query::<(Entity,)>().map(|(e,)| e).collect();
+        assert_eq!(tagged.len(), 6);
+        assert_eq!(tagged[..3], existing);
+    }
+}
@@ -0,0 +1,251 @@
+use std::{any::TypeId, marker::PhantomData};
+
+use crate::{
+    component::{Component, sparse_set::SparseSet, storages::ComponentStorages},
+    registry::Registry,
+    system::access::Access,
+    tick::Tick,
+};
+
+/// A trait for types that restrict which entities a query yields.
+///
+/// Filters only inspect storage metadata and never hand out component data,
+/// so they are usable alongside any combination of query items.
+///
+/// This trait is implemented for `()` (no filtering), the filter types in this
+/// module and tuples of filters up to 16 elements, which match only if every
+/// member matches.
+pub trait QueryFilter {
+    /// True if the filter accepts every entity, which lets iterators skip
+    /// evaluating it altogether
+    const MATCHES_ALL: bool = false;
+
+    /// True if the query also yields entities carrying the `Disabled`
+    /// marker, which are skipped otherwise
+    const INCLUDES_DISABLED: bool = false;
+
+    /// Pointers to the storages the filter reads, resolved once along with
+    /// the storages of the query
+    type State: Copy + 'static;
+
+    /// Looks the storages up. Missing storages are kept as None, and treated
+    /// by `matches` as holding no component.
+    fn state(components: &ComponentStorages) -> Self::State;
+
+    /// Returns true if the entity with `entity_id` passes this filter
+    ///
+    /// # Safety
+    /// `registry` must point to a live Registry, `state` must have been
+    /// resolved from it, and its component storages must not be structurally
+    /// modified for the duration of the call. Filters only read the fields
+    /// they need through the pointer, since other threads may be writing the
+    /// rest of the registry.
+    unsafe fn matches(
+        registry: *const Registry,
+        state: Self::State,
+        entity_id: u32,
+        last_run: Tick,
+        this_run: Tick,
+    ) -> bool;
+
+    /// Records the component metadata this filter reads
+    fn access(access: &mut Access);
+}
+
+impl QueryFilter for () {
+    const MATCHES_ALL: bool = true;
+
+    type State = ();
+
+    fn state(_components: &ComponentStorages) -> Self::State {}
+
+    unsafe fn matches(
+        _registry: *const Registry,
+        _state: Self::State,
+        _entity_id: u32,
+        _last_run: Tick,
+        _this_run: Tick,
+    ) -> bool {
+        true
+    }
+
+    fn access(_access: &mut Access) {}
+}
+
+/// Resolves the storage of `C` for the state of a filter
+///
+/// # Panics
+/// If `C` is stored in archetype tables, see `Registry::query_table`.
+pub(crate) fn storage<C: Component>(components: &ComponentStorages) -> Option<*const SparseSet<C>> {
+    components.queried::<C>().map(|ss| ss.cast_const())
+}
+
+/// A filter that matches entities whose `C` component was mutably accessed or
+/// replaced since the querying system last ran.
+///
+/// Newly added components count as changed.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// #[derive(Component)]
+/// struct Position { x: f32 }
+///
+/// fn rebuild_spatial_hash(query: Query<(&Position,), Changed<Position>>) {
+///     for (pos,) in query {
+///         println!("moved to {}", pos.x);
+///     }
+/// }
+/// # let mut registry = Registry::new();
+/// # registry.add_system(rebuild_spatial_hash);
+/// # registry.run_systems();
+/// ```
+pub struct Changed<C>(PhantomData<C>);
+
+impl<C: Component> QueryFilter for Changed<C> {
+    type State = Option<*const SparseSet<C>>;
+
+    fn state(components: &ComponentStorages) -> Self::State {
+        storage::<C>(components)
+    }
+
+    unsafe fn matches(
+        _registry: *const Registry,
+        state: Self::State,
+        entity_id: u32,
+        last_run: Tick,
+        this_run: Tick,
+    ) -> bool {
+        // SAFETY: the storage was resolved from the live registry
+        state
+            .and_then(|ss| unsafe { (*ss).get_ticks(entity_id as usize) })
+            .is_some_and(|ticks| ticks.is_changed(last_run, this_run))
+    }
+
+    fn access(access: &mut Access) {
+        access.read_component(TypeId::of::<C>());
+    }
+}
+
+/// A filter that matches entities whose `C` component was inserted since the
+/// querying system last ran.
+///
+/// This includes components constructed lazily by a component factory.
+pub struct Added<C>(PhantomData<C>);
+
+impl<C: Component> QueryFilter for Added<C> {
+    type State = Option<*const SparseSet<C>>;
+
+    fn state(components: &ComponentStorages) -> Self::State {
+        storage::<C>(components)
+    }
+
+    unsafe fn matches(
+        _registry: *const Registry,
+        state: Self::State,
+        entity_id: u32,
+        last_run: Tick,
+        this_run: Tick,
+    ) -> bool {
+        // SAFETY: the storage was resolved from the live registry
+        state
+            .and_then(|ss| unsafe { (*ss).get_ticks(entity_id as usize) })
+            .is_some_and(|ticks| ticks.is_added(last_run, this_run))
+    }
+
+    fn access(access: &mut Access) {
+        access.read_component(TypeId::of::<C>());
+    }
+}
+
+/// A filter that lets a query yield disabled entities too, which are
+/// otherwise skipped, see `Registry::set_enabled`.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// #[derive(Component)]
+/// struct Bullet;
+///
+/// let mut registry = Registry::new();
+/// let pooled = registry.spawn((Bullet,));
+/// registry.spawn((Bullet,));
+/// registry.set_enabled(pooled, false).unwrap();
+///
+/// assert_eq!(registry.query::<(&Bullet,)>().count(), 1);
+/// assert_eq!(registry.query_filtered::<(&Bullet,), IncludeDisabled>().count(), 2);
+/// ```
+pub struct IncludeDisabled;
+
+impl QueryFilter for IncludeDisabled {
+    const MATCHES_ALL: bool = true;
+    const INCLUDES_DISABLED: bool = true;
+
+    type State = ();
+
+    fn state(_components: &ComponentStorages) -> Self::State {}
+
+    unsafe fn matches(
+        _registry: *const Registry,
+        _state: Self::State,
+        _entity_id: u32,
+        _last_run: Tick,
+        _this_run: Tick,
+    ) -> bool {
+        true
+    }
+
+    fn access(_access: &mut Access) {}
+}
+
+macro_rules! impl_filter_for_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: QueryFilter),+> QueryFilter for ($($name,)+) {
+            const MATCHES_ALL: bool = $($name::MATCHES_ALL)&&+;
+            const INCLUDES_DISABLED: bool = $($name::INCLUDES_DISABLED)||+;
+
+            type State = ($($name::State,)+);
+
+            fn state(components: &ComponentStorages) -> Self::State {
+                ($($name::state(components),)+)
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn matches(
+                registry: *const Registry,
+                state: Self::State,
+                entity_id: u32,
+                last_run: Tick,
+                this_run: Tick,
+            ) -> bool {
+                let ($($name,)+) = state;
+                unsafe { $($name::matches(registry, $name, entity_id, last_run, this_run))&&+ }
+            }
+
+            fn access(access: &mut Access) {
+                $($name::access(access);)+
+            }
+        }
+    };
+}
+
+impl_filter_for_tuple!(F0);
+impl_filter_for_tuple!(F0, F1);
+impl_filter_for_tuple!(F0, F1, F2);
+impl_filter_for_tuple!(F0, F1, F2, F3);
+impl_filter_for_tuple!(F0, F1, F2, F3, F4);
+impl_filter_for_tuple!(F0, F1, F2, F3, F4, F5);
+impl_filter_for_tuple!(F0, F1, F2, F3, F4, F5, F6);
+impl_filter_for_tuple!(F0, F1, F2, F3, F4, F5, F6, F7);
+impl_filter_for_tuple!(F0, F1, F2, F3, F4, F5, F6, F7, F8);
+impl_filter_for_tuple!(F0, F1, F2, F3, F4, F5, F6, F7, F8, F9);
+impl_filter_for_tuple!(F0, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10);
+impl_filter_for_tuple!(F0, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11);
+impl_filter_for_tuple!(F0, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12);
+impl_filter_for_tuple!(F0, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13);
+impl_filter_for_tuple!(
+    F0, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14
+);
+impl_filter_for_tuple!(
+    F0, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15
+);
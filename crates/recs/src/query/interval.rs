@@ -0,0 +1,133 @@
+use std::any::TypeId;
+
+use crate::{
+    component::{Component, sparse_set::SparseSet, storages::ComponentStorages},
+    query::{QueryFilter, filter::storage},
+    registry::Registry,
+    system::access::Access,
+    tick::Tick,
+};
+
+/// Runs the per-entity logic of `DueThisFrame` queries only every `every`
+/// frames, for far-away or low-priority entities.
+///
+/// Entities sharing an interval are staggered over the frames so that they
+/// don't all come due at once: by default each entity is offset by its id,
+/// `with_offset` pins the offset explicitly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpdateInterval {
+    every: u32,
+    offset: Option<u32>,
+}
+
+impl Component for UpdateInterval {
+    const CLONE: Option<fn(&Self) -> Self> = Some(Self::clone);
+}
+
+impl UpdateInterval {
+    /// Updates the entity once every `every` frames. An interval of 0 or 1
+    /// updates it every frame.
+    pub fn new(every: u32) -> Self {
+        Self {
+            every: every.max(1),
+            offset: None,
+        }
+    }
+
+    /// Sets the frame offset within the interval instead of deriving it from
+    /// the entity id, e.g. to update a group of entities together
+    pub fn with_offset(mut self, offset: u32) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Returns the number of frames between two updates
+    ///
+    /// Systems can scale their per-update step by it to keep simulating at the
+    /// same overall rate.
+    pub fn every(&self) -> u32 {
+        self.every
+    }
+
+    /// Returns true if the entity with `entity_id` is due at `frame`
+    pub fn is_due(&self, frame: u64, entity_id: u32) -> bool {
+        let offset = self.offset.unwrap_or(entity_id) as u64;
+        (frame + offset).is_multiple_of(self.every as u64)
+    }
+}
+
+/// A filter that matches entities due for an update this frame according to
+/// their `UpdateInterval`. Entities without an interval are due every frame.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use recs::query::{DueThisFrame, UpdateInterval};
+/// #[derive(Component)]
+/// struct Brain { decisions: u32 }
+///
+/// fn think(query: Query<(&mut Brain,), DueThisFrame>) {
+///     for (mut brain,) in query {
+///         brain.decisions += 1;
+///     }
+/// }
+///
+/// let mut registry = Registry::new();
+/// let near = registry.spawn((Brain { decisions: 0 },));
+/// let far = registry.spawn((Brain { decisions: 0 }, UpdateInterval::new(4)));
+/// registry.add_system(think);
+///
+/// for _ in 0..8 {
+///     registry.run_systems();
+/// }
+/// assert_eq!(registry.get_component::<Brain>(near).unwrap().decisions, 8);
+/// assert_eq!(registry.get_component::<Brain>(far).unwrap().decisions, 2);
+/// ```
+pub struct DueThisFrame;
+
+impl QueryFilter for DueThisFrame {
+    type State = Option<*const SparseSet<UpdateInterval>>;
+
+    fn state(components: &ComponentStorages) -> Self::State {
+        storage::<UpdateInterval>(components)
+    }
+
+    unsafe fn matches(
+        registry: *const Registry,
+        state: Self::State,
+        entity_id: u32,
+        _last_run: Tick,
+        _this_run: Tick,
+    ) -> bool {
+        // SAFETY: forwarded from the caller
+        unsafe {
+            let frame = (*registry).frame_count;
+            state
+                .and_then(|ss| (*ss).get(entity_id as usize))
+                .is_none_or(|interval| interval.is_due(frame, entity_id))
+        }
+    }
+
+    fn access(access: &mut Access) {
+        access.read_component(TypeId::of::<UpdateInterval>());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intervals_are_staggered() {
+        let interval = UpdateInterval::new(4);
+        let due_counts: Vec<usize> = (0..4)
+            .map(|frame| (0..8).filter(|&id| interval.is_due(frame, id)).count())
+            .collect();
+        // Eight entities spread evenly over the four frames of the interval
+        assert_eq!(due_counts, vec![2, 2, 2, 2]);
+
+        let grouped = UpdateInterval::new(4).with_offset(1);
+        assert!((0..8).all(|id| grouped.is_due(3, id)));
+        assert!(UpdateInterval::new(0).is_due(5, 3));
+    }
+}
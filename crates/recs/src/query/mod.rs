@@ -1,164 +1,900 @@
-use std::{any::TypeId, marker::PhantomData};
+use std::{any::TypeId, cmp::Ordering, iter::FusedIterator, marker::PhantomData};
 
 use crate::{
     component::{Component, sparse_set::SparseSet},
+    entity::{Disabled, Entity},
+    error::RecsError,
     registry::Registry,
+    system::access::Access,
+    tick::Tick,
 };
 
+pub mod combinations;
+pub mod dynamic;
+mod entity;
+pub mod filter;
+pub mod interval;
+pub mod mutation;
+#[cfg(feature = "rayon")]
+mod par_iter;
+pub mod state;
+pub mod table;
+
+pub use combinations::QueryCombinationIter;
+pub use dynamic::DynamicQuery;
+pub use filter::{Added, Changed, IncludeDisabled, QueryFilter};
+pub use interval::{DueThisFrame, UpdateInterval};
+pub use mutation::Mut;
+pub use state::QueryState;
+pub use table::{TableQueryItem, TableQueryIter, TableQueryParam};
+
+/// Resolves the storages of the components a query reads, independently of
+/// the lifetime of the query, so that they can be looked up once and reused.
+/// See `QueryState`.
+pub trait QueryStorage {
+    /// Pointers to the storages of the queried components
+    type Storages: Copy + 'static;
+
+    /// Looks the storages up, or returns None if one of them doesn't exist.
+    /// Only pointers are resolved: the map of storages is borrowed, never the
+    /// storages themselves.
+    ///
+    /// # Panics
+    /// If the query names the same component twice.
+    fn storages(components: &ComponentStorages) -> Option<Self::Storages>;
+
+    /// Returns the number of entries of the smallest storage
+    ///
+    /// # Safety
+    /// The storages must be alive.
+    unsafe fn min_len(storages: Self::Storages) -> usize;
+
+    /// Returns the entities of the smallest storage, which iterators walk
+    ///
+    /// # Safety
+    /// The storages must be alive.
+    unsafe fn smallest_entities(storages: Self::Storages) -> *const [Entity];
+
+    /// Calls `visit` with the id of every entity holding all the queried
+    /// components, until it returns false, without fetching any item. The
+    /// `Disabled` marker and filters aren't checked.
+    ///
+    /// # Safety
+    /// `registry` and the storages must be alive, and nothing may write to
+    /// the storages or the entity slots during the call.
+    unsafe fn visit_ids(
+        registry: *const Registry,
+        storages: Self::Storages,
+        visit: impl FnMut(u32) -> bool,
+    );
+    /// Returns the number of entities holding all the queried components when
+    /// it is known without a join, i.e. for a single storage
+    ///
+    /// # Safety
+    /// The storages must be alive.
+    unsafe fn exact_len(_storages: Self::Storages) -> Option<usize> {
+        None
+    }
+}
+
+pub use crate::component::storages::ComponentStorages;
+
 /// A trait for querying entities with specific component combinations.
-pub trait QueryParam<'q> {
+pub trait QueryParam<'q>: QueryStorage {
     /// The type returned by the query iterator
     type Item;
 
-    /// Creates a new iterator over entities that match this query
-    fn iter(registry: &'q mut Registry) -> QueryIter<'q, Self>
+    /// Creates a new iterator over entities that match this query and the filter `F`
+    fn iter<F: QueryFilter>(registry: &'q mut Registry) -> QueryIter<'q, Self, F>
     where
-        Self: Sized;
+        Self: Sized,
+    {
+        let storages = Self::storages(&registry.components);
+        let filter = F::state(&registry.components);
+        QueryIter::new(registry, storages, filter)
+    }
+
+    /// Fetches the items of a single entity from the resolved `storages`, or
+    /// None if it lacks any of the components. The `Disabled` marker isn't
+    /// checked.
+    ///
+    /// # Safety
+    /// `registry` and the storages must be alive for the whole lifetime `'q`,
+    /// and no other reference to the fetched components may be alive at the
+    /// same time.
+    unsafe fn fetch(
+        registry: *mut Registry,
+        storages: Self::Storages,
+        entity_id: u32,
+        this_run: Tick,
+    ) -> Option<Self::Item>;
+
+    /// Records the components this query reads and writes
+    fn access(access: &mut Access);
 }
 
+/// Marker trait for queries that only hand out shared references.
+///
+/// It is implemented for `&C` items and tuples made only of them, and is what
+/// allows `Query::get` to be called through a shared reference.
+pub trait ReadOnlyQueryParam {}
+
 /// A standalone query that can be passed to systems
-pub struct Query<'q, Q> {
-    registry: &'q mut Registry,
-    _phantom: PhantomData<Q>,
+///
+/// The optional `F` parameter restricts the yielded entities without fetching
+/// any data, e.g. `Query<(&Position,), Changed<Position>>`.
+pub struct Query<'q, Q: QueryStorage, F: QueryFilter = ()> {
+    /// Kept as a raw pointer so that read-only accessors taking `&self` can
+    /// still hand it to the fetch machinery without casting away a shared borrow
+    registry: *mut Registry,
+    /// The storages of the queried components, resolved when the query was
+    /// created, or None if one of them doesn't exist
+    storages: Option<Q::Storages>,
+    /// The storages the filter reads, resolved along with `storages`
+    filter: F::State,
+    /// The tick change detection compares against, i.e. when the querying
+    /// system last ran
+    last_run: Tick,
+    /// The tick of the current system run
+    this_run: Tick,
+    _phantom: PhantomData<(&'q mut Registry, Q, F)>,
 }
 
-impl<'q, Q> Query<'q, Q> {
+impl<'q, Q: QueryStorage, F: QueryFilter> Query<'q, Q, F> {
     pub fn new(registry: &'q mut Registry) -> Self {
+        let last_run = registry.last_change_tick();
+        let this_run = registry.change_tick();
+        let storages = Q::storages(&registry.components);
+        let filter = F::state(&registry.components);
+        Self::with_ticks(registry, storages, filter, last_run, this_run)
+    }
+
+    /// Creates a query over the already resolved `storages` and `filter`
+    /// state, whose change detection compares against `last_run` instead of
+    /// the registry's current ticks
+    pub(crate) fn with_ticks(
+        registry: *mut Registry,
+        storages: Option<Q::Storages>,
+        filter: F::State,
+        last_run: Tick,
+        this_run: Tick,
+    ) -> Self {
         Self {
             registry,
+            storages,
+            filter,
+            last_run,
+            this_run,
             _phantom: PhantomData,
         }
     }
+
+    /// Creates the iterator over the query results with this query's ticks
+    ///
+    /// # Safety
+    /// The caller must uphold the aliasing rules for the items of `Q`, see
+    /// `QueryParam::fetch`.
+    unsafe fn iter_unchecked<'s>(&self) -> QueryIter<'s, Q, F>
+    where
+        Q: QueryParam<'s>,
+    {
+        let mut iter = QueryIter::new(self.registry, self.storages, self.filter);
+        iter.last_run = self.last_run;
+        iter.this_run = self.this_run;
+        iter
+    }
+
+    /// Gets the query items of a single entity without iterating.
+    ///
+    /// Returns None if the entity is no longer valid, lacks one of the queried
+    /// components or doesn't pass the filter.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Position { x: f32 }
+    ///
+    /// #[derive(Component)]
+    /// struct Target(Entity);
+    ///
+    /// fn aim(targets: Query<(&Target,)>, positions: Query<(&Position,)>) {
+    ///     for (target,) in targets {
+    ///         if let Some((pos,)) = positions.get(target.0) {
+    ///             println!("aiming at {}", pos.x);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn get<'s>(&'s self, entity: Entity) -> Option<<Q as QueryParam<'s>>::Item>
+    where
+        Q: QueryParam<'s> + ReadOnlyQueryParam,
+    {
+        // SAFETY: read-only queries never hand out mutable references or touch
+        // change ticks, so fetching through a shared borrow is sound
+        unsafe { self.fetch_checked(entity) }
+    }
+
+    /// Gets the query items of a single entity without iterating, including
+    /// mutable items.
+    ///
+    /// Returns None if the entity is no longer valid, lacks one of the queried
+    /// components or doesn't pass the filter.
+    pub fn get_mut<'s>(&'s mut self, entity: Entity) -> Option<<Q as QueryParam<'s>>::Item>
+    where
+        Q: QueryParam<'s>,
+    {
+        // SAFETY: the returned items borrow self mutably, so no other items
+        // fetched through this query can be alive at the same time
+        unsafe { self.fetch_checked(entity) }
+    }
+
+    /// Returns an iterator over the query results without consuming the query
+    pub fn iter<'s>(&'s self) -> QueryIter<'s, Q, F>
+    where
+        Q: QueryParam<'s> + ReadOnlyQueryParam,
+    {
+        // SAFETY: read-only queries never hand out mutable references or touch
+        // change ticks, so iterating through a shared borrow is sound
+        unsafe { self.iter_unchecked() }
+    }
+
+    /// Returns an iterator over the query results, including mutable items,
+    /// without consuming the query
+    pub fn iter_mut<'s>(&'s mut self) -> QueryIter<'s, Q, F>
+    where
+        Q: QueryParam<'s>,
+    {
+        // SAFETY: the iterator borrows self mutably for its whole lifetime
+        unsafe { self.iter_unchecked() }
+    }
+
+    /// Returns true if no entity matches the query.
+    ///
+    /// Stops at the first match, without consuming the query or fetching any
+    /// item.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Enemy;
+    ///
+    /// #[derive(Resource, Default)]
+    /// struct Victories(u32);
+    ///
+    /// fn check_victory(enemies: Query<(&Enemy,)>, mut victories: ResMut<Victories>) {
+    ///     if !enemies.is_empty() {
+    ///         return;
+    ///     }
+    ///     victories.0 += 1;
+    /// }
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.init_resource::<Victories>();
+    /// registry.add_system(check_victory);
+    /// registry.run_systems();
+    /// assert_eq!(registry.get_resource::<Victories>().unwrap().0, 1);
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.count_matches(1) == 0
+    }
+
+    /// Returns the number of entities matching the query, by checking the
+    /// queried storages and the filter without fetching any item. See `len`
+    /// for a shortcut when the count is known upfront.
+    pub fn count(&self) -> usize {
+        self.count_matches(usize::MAX)
+    }
+
+    /// Returns the number of entities matching the query.
+    ///
+    /// Read from the storage length when no filter can reject entities of a
+    /// single-component query and no entity is disabled, otherwise counts like
+    /// `count`.
+    pub fn len(&self) -> usize {
+        if F::MATCHES_ALL
+            && let Some(storages) = self.storages
+            // SAFETY: the storages belong to the registry borrowed for 'q
+            && let Some(len) = unsafe { Q::exact_len(storages) }
+            && (F::INCLUDES_DISABLED || unsafe { disabled_storage(self.registry) }.is_none())
+        {
+            return len;
+        }
+        self.count()
+    }
+
+    /// Counts the entities matching the query and the filter, up to `limit`
+    fn count_matches(&self, limit: usize) -> usize {
+        let mut count = 0;
+        self.visit_matches(|_| {
+            count += 1;
+            count < limit
+        });
+        count
+    }
+
+    /// Collects the ids of the entities matching the query and the filter
+    fn matching_ids(&self) -> Vec<u32> {
+        let mut ids = Vec::new();
+        self.visit_matches(|id| {
+            ids.push(id);
+            true
+        });
+        ids
+    }
+
+    /// Calls `visit` with the id of every entity matching the query and the
+    /// filter, skipping disabled ones unless the filter includes them, until
+    /// it returns false
+    fn visit_matches(&self, mut visit: impl FnMut(u32) -> bool) {
+        let Some(storages) = self.storages else {
+            return;
+        };
+        let registry = self.registry as *const Registry;
+        // SAFETY: the storages belong to the registry borrowed for 'q, and
+        // only membership is checked: no item is fetched, so mutable items
+        // fetched through `&mut self` can't be alive meanwhile
+        unsafe {
+            let disabled = disabled_storage(registry).filter(|_| !F::INCLUDES_DISABLED);
+            Q::visit_ids(registry, storages, |id| {
+                if disabled.is_some_and(|ss| (*ss).contains(id as usize))
+                    || !F::matches(registry, self.filter, id, self.last_run, self.this_run)
+                {
+                    return true;
+                }
+                visit(id)
+            });
+        }
+    }
+
+    /// Returns the query results, including mutable items, sorted with
+    /// `compare`. The sort is stable.
+    ///
+    /// The results are collected before being sorted, so prefer
+    /// `Registry::sort_components_by` to sort a storage once for every query.
+    pub fn sort_by<'s>(
+        &'s mut self,
+        mut compare: impl FnMut(&<Q as QueryParam<'s>>::Item, &<Q as QueryParam<'s>>::Item) -> Ordering,
+    ) -> std::vec::IntoIter<<Q as QueryParam<'s>>::Item>
+    where
+        Q: QueryParam<'s>,
+        QueryIter<'s, Q, F>: Iterator<Item = <Q as QueryParam<'s>>::Item>,
+    {
+        let mut items: Vec<_> = self.iter_mut().collect();
+        items.sort_by(&mut compare);
+        items.into_iter()
+    }
+
+    /// Returns the query results, including mutable items, sorted by the key
+    /// `key` extracts from them. The sort is stable.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Priority(u32);
+    ///
+    /// #[derive(Component)]
+    /// struct Label(&'static str);
+    ///
+    /// fn run_in_order(mut query: Query<(&Priority, &Label)>) {
+    ///     for (_, label) in query.sort_by_key(|(priority, _)| priority.0) {
+    ///         println!("{}", label.0);
+    ///     }
+    /// }
+    /// # let mut registry = Registry::new();
+    /// # registry.add_system(run_in_order);
+    /// # registry.run_systems();
+    /// ```
+    pub fn sort_by_key<'s, K: Ord>(
+        &'s mut self,
+        mut key: impl FnMut(&<Q as QueryParam<'s>>::Item) -> K,
+    ) -> std::vec::IntoIter<<Q as QueryParam<'s>>::Item>
+    where
+        Q: QueryParam<'s>,
+        QueryIter<'s, Q, F>: Iterator<Item = <Q as QueryParam<'s>>::Item>,
+    {
+        self.sort_by(|a, b| key(a).cmp(&key(b)))
+    }
+
+    /// Returns the items of the only entity matching the query.
+    ///
+    /// Useful for singleton-like entities such as the camera or the player.
+    /// Returns `NoEntities` or `MultipleEntities` if the query doesn't match
+    /// exactly one entity.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Player { score: u32 }
+    ///
+    /// fn print_score(query: Query<(&Player,)>) {
+    ///     let (player,) = query.single().expect("there should be exactly one player");
+    ///     println!("score: {}", player.score);
+    /// }
+    /// # let mut registry = Registry::new();
+    /// # registry.spawn(Player { score: 0 });
+    /// # registry.add_system(print_score);
+    /// # registry.run_systems();
+    /// ```
+    pub fn single<'s>(&'s self) -> Result<<Q as QueryParam<'s>>::Item, RecsError>
+    where
+        Q: QueryParam<'s> + ReadOnlyQueryParam,
+        QueryIter<'s, Q, F>: Iterator<Item = <Q as QueryParam<'s>>::Item>,
+    {
+        Self::only_item(self.iter())
+    }
+
+    /// Returns the items, including mutable ones, of the only entity matching
+    /// the query.
+    ///
+    /// Returns `NoEntities` or `MultipleEntities` if the query doesn't match
+    /// exactly one entity.
+    pub fn single_mut<'s>(&'s mut self) -> Result<<Q as QueryParam<'s>>::Item, RecsError>
+    where
+        Q: QueryParam<'s>,
+        QueryIter<'s, Q, F>: Iterator<Item = <Q as QueryParam<'s>>::Item>,
+    {
+        Self::only_item(self.iter_mut())
+    }
+
+    /// Returns an iterator over every unique combination of `K` distinct
+    /// entities matching the query, e.g. all pairs for `K = 2`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Collider { x: f32, radius: f32 }
+    ///
+    /// fn detect_collisions(query: Query<(&Collider,)>) {
+    ///     for [(a,), (b,)] in query.iter_combinations::<2>() {
+    ///         if (a.x - b.x).abs() < a.radius + b.radius {
+    ///             println!("collision");
+    ///         }
+    ///     }
+    /// }
+    /// # let mut registry = Registry::new();
+    /// # registry.add_system(detect_collisions);
+    /// # registry.run_systems();
+    /// ```
+    pub fn iter_combinations<'s, const K: usize>(&'s self) -> QueryCombinationIter<'s, Q, F, K>
+    where
+        Q: QueryParam<'s> + ReadOnlyQueryParam,
+    {
+        // SAFETY: read-only queries never hand out mutable references, and the
+        // combination iterator borrows self for its whole lifetime
+        unsafe {
+            QueryCombinationIter::new(
+                self.registry,
+                self.storages,
+                self.matching_ids(),
+                self.this_run,
+            )
+        }
+    }
+
+    /// Returns a lending iterator over every unique combination of `K` distinct
+    /// entities matching the query, including mutable items.
+    ///
+    /// Since the same entity appears in several combinations, each combination
+    /// must be dropped before the next one is fetched through
+    /// `QueryCombinationIter::fetch_next`. The entities within one combination
+    /// are always distinct, so their mutable items never alias.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Boid { x: f32, vx: f32 }
+    ///
+    /// fn separate(mut query: Query<(&mut Boid,)>) {
+    ///     let mut pairs = query.iter_combinations_mut::<2>();
+    ///     while let Some([(mut a,), (mut b,)]) = pairs.fetch_next() {
+    ///         let push = if a.x < b.x { -0.1 } else { 0.1 };
+    ///         a.vx += push;
+    ///         b.vx -= push;
+    ///     }
+    /// }
+    /// # let mut registry = Registry::new();
+    /// # registry.add_system(separate);
+    /// # registry.run_systems();
+    /// ```
+    pub fn iter_combinations_mut<'s, const K: usize>(
+        &'s mut self,
+    ) -> QueryCombinationIter<'s, Q, F, K>
+    where
+        Q: QueryParam<'s>,
+    {
+        // SAFETY: the combination iterator borrows self mutably for its whole
+        // lifetime, and only hands out mutable items through `fetch_next`
+        unsafe {
+            QueryCombinationIter::new(
+                self.registry,
+                self.storages,
+                self.matching_ids(),
+                self.this_run,
+            )
+        }
+    }
+
+    fn only_item<I: Iterator>(mut iter: I) -> Result<I::Item, RecsError> {
+        let query_name = std::any::type_name::<Q>();
+        let item = iter.next().ok_or(RecsError::NoEntities(query_name))?;
+        if iter.next().is_some() {
+            return Err(RecsError::MultipleEntities(query_name));
+        }
+        Ok(item)
+    }
+
+    unsafe fn fetch_checked<'s>(&self, entity: Entity) -> Option<<Q as QueryParam<'s>>::Item>
+    where
+        Q: QueryParam<'s>,
+    {
+        let storages = self.storages?;
+        unsafe {
+            if !(*self.registry).entity_manager.is_valid(entity) {
+                return None;
+            }
+
+            if !F::INCLUDES_DISABLED
+                && disabled_storage(self.registry)
+                    .is_some_and(|ss| (*ss).contains(entity.id() as usize))
+            {
+                return None;
+            }
+
+            if !F::matches(
+                self.registry,
+                self.filter,
+                entity.id(),
+                self.last_run,
+                self.this_run,
+            ) {
+                return None;
+            }
+
+            Q::fetch(self.registry, storages, entity.id(), self.this_run)
+        }
+    }
+
+    /// Records the components and entity data this query accesses
+    pub(crate) fn access(access: &mut Access)
+    where
+        Q: QueryParam<'q>,
+    {
+        Q::access(access);
+        F::access(access);
+        // `get` and `get_mut` check entity liveness
+        access.read_entities();
+    }
 }
 
-impl<'q, Q: QueryParam<'q>> IntoIterator for Query<'q, Q>
+impl<'q, Q: QueryParam<'q>, F: QueryFilter> IntoIterator for Query<'q, Q, F>
 where
-    QueryIter<'q, Q>: Iterator<Item = Q::Item>,
+    QueryIter<'q, Q, F>: Iterator<Item = Q::Item>,
 {
     type Item = Q::Item;
-    type IntoIter = QueryIter<'q, Q>;
+    type IntoIter = QueryIter<'q, Q, F>;
 
     fn into_iter(self) -> Self::IntoIter {
-        Q::iter(self.registry)
+        // SAFETY: the pointer was created from a `&'q mut Registry` owned by
+        // this query, which is consumed here
+        unsafe { self.iter_unchecked() }
     }
 }
 
-/// A helper trait for query items.
-pub trait QueryItem<'q> {
+/// Returns the `Disabled` storage of the registry if any entity is disabled,
+/// borrowing only the map of storages
+///
+/// # Safety
+/// `registry` must point to a live Registry whose storages aren't being
+/// created or removed.
+unsafe fn disabled_storage(registry: *const Registry) -> Option<*const SparseSet<Disabled>> {
+    unsafe {
+        (*registry)
+            .components
+            .sparse_set_ptr::<Disabled>()
+            .map(<*mut _>::cast_const)
+            .filter(|&ss| !(*ss).is_empty())
+    }
+}
+
+/// The component behind a query item, independent of the lifetime of the
+/// query
+pub trait QueryComponent {
     type Component: Component;
+
+    /// Looks up the storage of the component
+    ///
+    /// # Panics
+    /// If the component is stored in archetype tables, see
+    /// `Registry::query_table`.
+    fn get_storage(components: &ComponentStorages) -> Option<*mut SparseSet<Self::Component>> {
+        components.queried::<Self::Component>()
+    }
+}
+
+impl<C: Component> QueryComponent for &C {
+    type Component = C;
+}
+
+impl<C: Component> QueryComponent for &mut C {
+    type Component = C;
+}
+
+/// A helper trait for query items.
+pub trait QueryItem<'q>: QueryComponent {
     type Item;
-    fn get_storage(
-        components: &mut std::collections::HashMap<
-            TypeId,
-            Box<dyn crate::component::ComponentStorage>,
-        >,
-    ) -> Option<*mut SparseSet<Self::Component>>;
+
+    /// Records whether this item reads or writes its component
+    fn access(access: &mut Access);
+
+    /// Fetches the item for `entity_id` from the storage.
+    ///
+    /// Mutable items mark the component as changed at `this_run` once written.
+    ///
+    /// # Safety
+    /// `storage` must point to a live SparseSet for the whole lifetime `'q`, and
+    /// no other reference to the same component may be alive at the same time.
     unsafe fn get_from_storage(
         storage: *mut SparseSet<Self::Component>,
         entity_id: u32,
+        this_run: Tick,
     ) -> Option<Self::Item>;
+
+    /// Fetches the item at position `index` of the storage's dense array,
+    /// skipping the sparse lookup.
+    ///
+    /// Mutable items mark the component as changed at `this_run` once written.
+    ///
+    /// # Safety
+    /// Same as `get_from_storage`, and `index` must be lower than the length
+    /// of the storage.
+    unsafe fn get_from_dense(
+        storage: *mut SparseSet<Self::Component>,
+        index: usize,
+        this_run: Tick,
+    ) -> Self::Item;
 }
 
 impl<'q, C: Component + 'static> QueryItem<'q> for &C {
-    type Component = C;
     type Item = &'q C;
 
-    fn get_storage(
-        components: &mut std::collections::HashMap<
-            TypeId,
-            Box<dyn crate::component::ComponentStorage>,
-        >,
-    ) -> Option<*mut SparseSet<Self::Component>> {
-        let type_id = TypeId::of::<C>();
-        components
-            .get_mut(&type_id)
-            .and_then(|storage| {
-                (storage.as_mut() as &mut dyn std::any::Any).downcast_mut::<SparseSet<C>>()
-            })
-            .map(|ss| ss as *mut SparseSet<C>)
-    }
-
-    unsafe fn get_from_storage(storage: *mut SparseSet<C>, entity_id: u32) -> Option<Self::Item> {
+    fn access(access: &mut Access) {
+        access.read_component(TypeId::of::<C>());
+    }
+
+    unsafe fn get_from_storage(
+        storage: *mut SparseSet<C>,
+        entity_id: u32,
+        _this_run: Tick,
+    ) -> Option<Self::Item> {
         unsafe { (*storage).get(entity_id as usize) }
     }
+
+    unsafe fn get_from_dense(
+        storage: *mut SparseSet<C>,
+        index: usize,
+        _this_run: Tick,
+    ) -> Self::Item {
+        unsafe { (*storage).get_dense_unchecked(index) }
+    }
 }
 
+impl<C: Component> ReadOnlyQueryParam for &C {}
+
+/// Mutable items are yielded as `Mut`, which only flags the component as
+/// changed when it is written
 impl<'q, C: Component + 'static> QueryItem<'q> for &mut C {
-    type Component = C;
-    type Item = &'q mut C;
-
-    fn get_storage(
-        components: &mut std::collections::HashMap<
-            TypeId,
-            Box<dyn crate::component::ComponentStorage>,
-        >,
-    ) -> Option<*mut SparseSet<Self::Component>> {
-        let type_id = TypeId::of::<C>();
-        components
-            .get_mut(&type_id)
-            .and_then(|storage| {
-                (storage.as_mut() as &mut dyn std::any::Any).downcast_mut::<SparseSet<C>>()
-            })
-            .map(|ss| ss as *mut SparseSet<C>)
-    }
-
-    unsafe fn get_from_storage(storage: *mut SparseSet<C>, entity_id: u32) -> Option<Self::Item> {
-        unsafe { (*storage).get_mut(entity_id as usize) }
+    type Item = Mut<'q, C>;
+
+    fn access(access: &mut Access) {
+        access.write_component(TypeId::of::<C>());
+    }
+
+    unsafe fn get_from_storage(
+        storage: *mut SparseSet<C>,
+        entity_id: u32,
+        this_run: Tick,
+    ) -> Option<Self::Item> {
+        unsafe { (*storage).get_mut_tracked(entity_id as usize, this_run) }
+    }
+
+    unsafe fn get_from_dense(
+        storage: *mut SparseSet<C>,
+        index: usize,
+        this_run: Tick,
+    ) -> Self::Item {
+        unsafe { (*storage).get_dense_tracked_unchecked(index, this_run) }
     }
 }
 
-pub struct QueryIter<'q, Q: QueryParam<'q>> {
-    registry: &'q mut Registry,
+pub struct QueryIter<'q, Q: QueryParam<'q>, F: QueryFilter = ()> {
+    /// Only read, field by field: items are fetched through the storages, so
+    /// several iterators over disjoint components can share the registry,
+    /// while commands reserve entities between two steps
+    registry: *const Registry,
+    /// The storages of the queried components, or None if one is missing
+    storages: Option<Q::Storages>,
+    /// The storages the filter reads
+    filter: F::State,
+    /// The entities of the smallest queried storage, walked by joins
+    entities: *const [Entity],
+    /// The `Disabled` storage, unless it is empty or the filter includes
+    /// disabled entities
+    disabled: Option<*const SparseSet<Disabled>>,
     entity_index: usize,
-    _phantom: PhantomData<Q>,
+    /// Set when the iterator can yield nothing more: at construction if one
+    /// of the queried storages is missing or empty, or once the walk ended.
+    /// The registry is borrowed for 'q, so storages can't appear meanwhile.
+    exhausted: bool,
+    /// Length of the smallest queried storage, which bounds the number of
+    /// yielded items. Fixed for the iterator's lifetime, like the storages.
+    /// Entity queries set it to the number of entity slots at the first step.
+    len: usize,
+    /// The tick at which the querying system last ran
+    last_run: Tick,
+    /// The tick of the current system run
+    this_run: Tick,
+    _phantom: PhantomData<(&'q Registry, Q, F)>,
+}
+
+/// Panics if the query `Q` names a component twice, which would hand out
+/// aliasing references to it
+fn assert_distinct<Q>(types: &mut [TypeId]) {
+    types.sort_unstable();
+    let distinct = types.windows(2).all(|pair| pair[0] != pair[1]);
+    assert!(
+        distinct,
+        "{} can't borrow the same component twice",
+        std::any::type_name::<Q>()
+    );
 }
 
 macro_rules! impl_query_for_tuple {
+    // Single-component queries iterate their storage directly, see below
+    ($name:ident) => {
+        impl_query_param_for_tuple!($name);
+    };
     ($($name:ident),+) => {
-        impl<'q, $($name: QueryItem<'q>),+> QueryParam<'q> for ($($name,)+) {
-            type Item = ($($name::Item,)+);
+        impl_query_param_for_tuple!($($name),+);
+        impl_join_iter_for_tuple!($($name),+);
+    };
+}
+
+macro_rules! impl_query_param_for_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: QueryComponent),+> QueryStorage for ($($name,)+) {
+            type Storages = ($(*mut SparseSet<$name::Component>,)+);
+
+            fn storages(components: &ComponentStorages) -> Option<Self::Storages> {
+                assert_distinct::<Self>(&mut [$(TypeId::of::<$name::Component>()),+]);
+                Some(($($name::get_storage(components)?,)+))
+            }
+
+            unsafe fn min_len(storages: Self::Storages) -> usize {
+                // SAFETY: forwarded from the caller
+                unsafe { Self::smallest_entities(storages).len() }
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn smallest_entities(storages: Self::Storages) -> *const [Entity] {
+                let ($($name,)+) = storages;
+                // SAFETY: the caller guarantees the storages are alive
+                let slices = [$(unsafe { (*$name).entities.as_slice() as *const [Entity] }),+];
+                slices
+                    .into_iter()
+                    .min_by_key(|slice| slice.len())
+                    .expect("Tuples hold at least one storage")
+            }
 
-            fn iter(registry: &'q mut Registry) -> QueryIter<'q, Self> {
-                QueryIter {
-                    registry,
-                    entity_index: 0,
-                    _phantom: PhantomData,
+            #[allow(non_snake_case)]
+            unsafe fn exact_len(storages: Self::Storages) -> Option<usize> {
+                let ($($name,)+) = storages;
+                // SAFETY: the caller guarantees the storages are alive
+                let lens = [$(unsafe { (*$name).entities.len() }),+];
+                match lens.as_slice() {
+                    &[len] => Some(len),
+                    _ => None,
+                }
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn visit_ids(
+                _registry: *const Registry,
+                storages: Self::Storages,
+                mut visit: impl FnMut(u32) -> bool,
+            ) {
+                let ($($name,)+) = storages;
+                // SAFETY: forwarded from the caller
+                unsafe {
+                    for entity in &*Self::smallest_entities(storages) {
+                        let id = entity.id();
+                        if $((*$name).get(id as usize).is_some())&&+ && !visit(id) {
+                            return;
+                        }
+                    }
                 }
             }
         }
 
-        impl<'q, $($name: QueryItem<'q>),+> Iterator for QueryIter<'q, ($($name,)+)> {
+        impl<'q, $($name: QueryItem<'q>),+> QueryParam<'q> for ($($name,)+) {
             type Item = ($($name::Item,)+);
 
-            #[allow(non_snake_case)]
-            fn next(&mut self) -> Option<Self::Item> {
+            fn access(access: &mut Access) {
+                let mut items = Access::new();
                 $(
-                    let $name = $name::get_storage(&mut self.registry.components)?;
+                    let mut item = Access::new();
+                    $name::access(&mut item);
+                    items.extend_part(&item);
                 )+
+                access.extend(&items);
+            }
 
-                // SAFETY: Raw pointers are safe because lifetimes are managed by 'q
-                // and QueryIter structure, preventing deallocation while iterator exists
+            #[allow(non_snake_case)]
+            unsafe fn fetch(
+                _registry: *mut Registry,
+                storages: Self::Storages,
+                entity_id: u32,
+                this_run: Tick,
+            ) -> Option<Self::Item> {
+                let ($($name,)+) = storages;
                 unsafe {
-                    let mut smallest_slice: Option<&[crate::entity::Entity]> = None;
+                    // Check membership first so that mutable items are only
+                    // marked changed when the whole tuple matches
                     $(
-                        let current_slice = &(*$name).entities;
-                        match smallest_slice {
-                            None => smallest_slice = Some(current_slice),
-                            Some(s) if current_slice.len() < s.len() => smallest_slice = Some(current_slice),
-                            _ => (),
-                        }
+                        (*$name).get(entity_id as usize)?;
                     )+
 
-                    let entities_to_iterate = smallest_slice.unwrap();
+                    Some(($($name::get_from_storage($name, entity_id, this_run)?,)+))
+                }
+            }
+        }
+
+        impl<$($name: ReadOnlyQueryParam),+> ReadOnlyQueryParam for ($($name,)+) {}
+    };
+}
+
+/// Joins the storages of a multi-component query by walking the smallest one
+/// and looking the other components up by entity id
+macro_rules! impl_join_iter_for_tuple {
+    ($($name:ident),+) => {
+        impl<'q, F: QueryFilter, $($name: QueryItem<'q>),+> Iterator for QueryIter<'q, ($($name,)+), F> {
+            type Item = ($($name::Item,)+);
+
+            #[allow(non_snake_case)]
+            fn next(&mut self) -> Option<Self::Item> {
+                if self.exhausted {
+                    return None;
+                }
+
+                let ($($name,)+) = self.storages?;
+                let registry_ptr = self.registry;
 
-                    while self.entity_index < entities_to_iterate.len() {
-                        let entity = entities_to_iterate[self.entity_index];
+                // SAFETY: the storages, the walked entities and the `Disabled`
+                // storage were resolved at construction from the registry
+                // borrowed for 'q, so none of them can be reallocated meanwhile
+                unsafe {
+                    while self.entity_index < self.len {
+                        let entity = (*self.entities)[self.entity_index];
                         self.entity_index += 1;
                         let id = entity.id();
 
+                        // Check membership and filters before fetching so that
+                        // mutable items are only marked changed when yielded
+                        if $((*$name).get(id as usize).is_none())||+
+                            || self.disabled.is_some_and(|d| (*d).get(id as usize).is_some())
+                            || !F::matches(registry_ptr, self.filter, id, self.last_run, self.this_run)
+                        {
+                            continue;
+                        }
+
                         if let ($(Some($name),)+) = (
                             $(
-                                $name::get_from_storage($name, id),
+                                $name::get_from_storage($name, id, self.this_run),
                             )+
                         ) {
                             return Some(($($name,)+));
@@ -166,12 +902,142 @@ macro_rules! impl_query_for_tuple {
                     }
                 }
 
+                self.exhausted = true;
                 None
             }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (0, Some(self.remaining()))
+            }
         }
+
+        impl<'q, F: QueryFilter, $($name: QueryItem<'q>),+> FusedIterator for QueryIter<'q, ($($name,)+), F> {}
     };
 }
 
+impl<'q, Q: QueryParam<'q>, F: QueryFilter> QueryIter<'q, Q, F> {
+    /// Creates an iterator over the already resolved `storages`, with the
+    /// registry's current ticks.
+    ///
+    /// Everything the walk needs besides the items is resolved here once,
+    /// rather than on every step.
+    ///
+    /// `registry` must stay alive for 'q, with the storages left in place.
+    pub(crate) fn new(
+        registry: *const Registry,
+        storages: Option<Q::Storages>,
+        filter: F::State,
+    ) -> Self {
+        // SAFETY: the storages belong to the registry, alive for 'q
+        let len = storages.map_or(0, |storages| unsafe { Q::min_len(storages) });
+        let entities = storages.map_or(&[] as *const [Entity], |storages| unsafe {
+            Q::smallest_entities(storages)
+        });
+        let disabled = unsafe { disabled_storage(registry) }.filter(|_| !F::INCLUDES_DISABLED);
+        QueryIter {
+            // SAFETY: the ticks are plain values, read without borrowing
+            last_run: unsafe { (*registry).last_change_tick },
+            this_run: unsafe { (*registry).change_tick },
+            registry,
+            storages,
+            filter,
+            entities,
+            disabled,
+            entity_index: 0,
+            exhausted: len == 0,
+            len,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'q, Q: QueryParam<'q>, F: QueryFilter> QueryIter<'q, Q, F> {
+    /// Returns the number of storage entries not visited yet
+    fn remaining(&self) -> usize {
+        if self.exhausted {
+            0
+        } else {
+            self.len - self.entity_index
+        }
+    }
+}
+
+/// Single-component queries need none of the join machinery: every entry of
+/// the storage holds the component, so the dense array is walked directly
+/// without any sparse lookup. Only the `Disabled` marker and the filter can
+/// still reject an entity.
+impl<'q, F: QueryFilter, Q0: QueryItem<'q>> Iterator for QueryIter<'q, (Q0,), F> {
+    type Item = (Q0::Item,);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.exhausted {
+            return None;
+        }
+
+        let (storage,) = self.storages?;
+
+        let registry_ptr = self.registry;
+        let disabled = self.disabled;
+
+        // SAFETY: the storage lives as long as the registry borrowed for 'q, and
+        // each dense index is yielded at most once
+        unsafe {
+            while self.entity_index < self.len {
+                let index = self.entity_index;
+                self.entity_index += 1;
+
+                if disabled.is_some() || !F::MATCHES_ALL {
+                    let id = (&(*storage).entities)[index].id();
+                    if disabled.is_some_and(|d| (*d).get(id as usize).is_some())
+                        || !F::matches(registry_ptr, self.filter, id, self.last_run, self.this_run)
+                    {
+                        continue;
+                    }
+                }
+
+                return Some((Q0::get_from_dense(storage, index, self.this_run),));
+            }
+        }
+
+        self.exhausted = true;
+        None
+    }
+
+    /// Exact when no filter can reject entities: only the disabled entities
+    /// among the entries not visited yet are subtracted
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining();
+        if !F::MATCHES_ALL || remaining == 0 {
+            return (0, Some(remaining));
+        }
+
+        // SAFETY: the storages live as long as the registry borrowed for 'q
+        let disabled = match unsafe {
+            (
+                self.disabled.map(|disabled| &*disabled),
+                self.storages.map(|(storage,)| &*storage),
+            )
+        } {
+            (Some(disabled), Some(storage)) => disabled
+                .entities
+                .iter()
+                .filter_map(|entity| storage.dense_index(entity.id() as usize))
+                .filter(|&index| index >= self.entity_index)
+                .count(),
+            _ => 0,
+        };
+
+        let exact = remaining - disabled;
+        (exact, Some(exact))
+    }
+}
+
+impl<'q, F: QueryFilter, Q0: QueryItem<'q>> FusedIterator for QueryIter<'q, (Q0,), F> {}
+
+/// Unfiltered single-component queries know exactly how many entities are
+/// left, see `size_hint`
+impl<'q, Q0: QueryItem<'q>> ExactSizeIterator for QueryIter<'q, (Q0,), ()> {}
+
 impl_query_for_tuple!(Q0);
 impl_query_for_tuple!(Q0, Q1);
 impl_query_for_tuple!(Q0, Q1, Q2);
@@ -300,7 +1166,7 @@ mod tests {
         let mut registry = Registry::new();
         let entity = registry.spawn((Position { x: 1.0, y: 1.0 },));
 
-        for (pos,) in registry.query::<(&mut Position,)>() {
+        for (mut pos,) in registry.query::<(&mut Position,)>() {
             pos.x = 100.0;
         }
 
@@ -308,6 +1174,36 @@ mod tests {
         assert_eq!(changed_pos.x, 100.0);
     }
 
+    #[test]
+    fn test_single_component_fast_path_respects_disabled_and_filters() {
+        let mut registry = Registry::new();
+        registry.spawn((Position { x: 1.0, y: 0.0 },));
+        let e2 = registry.spawn((Position { x: 2.0, y: 0.0 },));
+        let e3 = registry.spawn((Position { x: 3.0, y: 0.0 },));
+        registry.add_component(e2, crate::entity::Disabled).unwrap();
+        registry.clear_trackers();
+
+        // Mutable items are still marked changed, and disabled entities skipped
+        for (mut pos,) in registry.query::<(&mut Position,)>() {
+            pos.y = 1.0;
+        }
+        let mut changed: Vec<f32> = registry
+            .query_filtered::<(&Position,), Changed<Position>>()
+            .map(|(pos,)| pos.x)
+            .collect();
+        changed.sort_by(f32::total_cmp);
+        assert_eq!(changed, vec![1.0, 3.0]);
+        assert_eq!(registry.get_component::<Position>(e2).unwrap().y, 0.0);
+
+        registry.clear_trackers();
+        registry.get_component_mut::<Position>(e3).unwrap().x = 4.0;
+        let changed: Vec<f32> = registry
+            .query_filtered::<(&Position,), Changed<Position>>()
+            .map(|(pos,)| pos.x)
+            .collect();
+        assert_eq!(changed, vec![4.0]);
+    }
+
     #[test]
     fn test_query_multiple_components() {
         let mut registry = Registry::new();
@@ -332,7 +1228,7 @@ mod tests {
         let mut registry = Registry::new();
         let entity = registry.spawn((Position { x: 1.0, y: 1.0 }, Velocity { dx: 5.0, dy: 0.0 }));
 
-        for (pos, vel) in registry.query::<(&mut Position, &Velocity)>() {
+        for (mut pos, vel) in registry.query::<(&mut Position, &Velocity)>() {
             pos.x += vel.dx;
         }
 
@@ -340,6 +1236,17 @@ mod tests {
         assert_eq!(changed_pos.x, 6.0);
     }
 
+    #[test]
+    #[should_panic(expected = "can't borrow the same component twice")]
+    fn test_query_rejects_duplicate_components() {
+        let mut registry = Registry::new();
+        registry.spawn((Position { x: 1.0, y: 1.0 },));
+
+        for (mut pos, other) in registry.query::<(&mut Position, &Position)>() {
+            pos.x = other.y;
+        }
+    }
+
     #[test]
     fn test_query_iterates_over_smallest_set() {
         let mut registry = Registry::new();
@@ -376,4 +1283,269 @@ mod tests {
         }
         assert_eq!(count, 0);
     }
+
+    #[test]
+    fn test_query_over_missing_or_empty_storage_is_exhausted_upfront() {
+        let mut registry = Registry::new();
+        registry.spawn((Position { x: 1.0, y: 1.0 },));
+
+        // Velocity was never registered
+        let mut iter = registry.query::<(&Position, &Velocity)>();
+        assert!(iter.exhausted);
+        assert!(iter.next().is_none());
+
+        // PlayerTag is registered but empty
+        let entity = registry.spawn((PlayerTag,));
+        registry.remove_component::<PlayerTag>(entity).unwrap();
+        assert!(registry.query::<(&PlayerTag,)>().exhausted);
+        assert!(registry.query::<(&Position, &PlayerTag)>().exhausted);
+
+        let mut iter = registry.query::<(&Position,)>();
+        assert!(!iter.exhausted);
+        assert!(iter.next().is_some());
+        assert!(iter.next().is_none());
+        assert!(iter.exhausted);
+    }
+
+    #[test]
+    fn test_query_size_hints() {
+        let mut registry = Registry::new();
+        let mut entities = Vec::new();
+        for i in 0..5 {
+            entities.push(registry.spawn((Position {
+                x: i as f32,
+                y: 0.0,
+            },)));
+        }
+        registry.spawn((Position { x: 9.0, y: 0.0 }, PlayerTag));
+        registry
+            .add_component(entities[1], crate::entity::Disabled)
+            .unwrap();
+        registry
+            .add_component(entities[3], crate::entity::Disabled)
+            .unwrap();
+
+        let mut iter = registry.query::<(&Position,)>();
+        assert_eq!(iter.len(), 4);
+        iter.next();
+        iter.next();
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.by_ref().count(), 2);
+        assert_eq!(iter.len(), 0);
+        assert!(iter.next().is_none());
+
+        assert_eq!(
+            registry.query::<(&Position, &PlayerTag)>().size_hint(),
+            (0, Some(1))
+        );
+        assert_eq!(
+            registry
+                .query_filtered::<(&Position,), Changed<Position>>()
+                .size_hint(),
+            (0, Some(6))
+        );
+        assert_eq!(registry.query::<(&Velocity,)>().size_hint(), (0, Some(0)));
+    }
+
+    #[test]
+    fn test_include_disabled_filter() {
+        let mut registry = Registry::new();
+        let enabled = registry.spawn((Position { x: 1.0, y: 0.0 }, PlayerTag));
+        let disabled = registry.spawn((Position { x: 2.0, y: 0.0 }, PlayerTag));
+        registry.set_enabled(disabled, false).unwrap();
+        assert!(!registry.is_enabled(disabled));
+
+        assert_eq!(registry.query::<(&Position,)>().count(), 1);
+        assert_eq!(registry.query::<(&Position, &PlayerTag)>().count(), 1);
+        assert_eq!(registry.query::<(Entity,)>().count(), 1);
+
+        let mut iter = registry.query_filtered::<(&Position,), IncludeDisabled>();
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+        assert_eq!(iter.by_ref().count(), 2);
+        assert_eq!(
+            registry
+                .query_filtered::<(&Position, &PlayerTag), (IncludeDisabled, Changed<Position>)>()
+                .count(),
+            2
+        );
+        let mut entities: Vec<Entity> = registry
+            .query_filtered::<(Entity,), IncludeDisabled>()
+            .map(|(entity,)| entity)
+            .collect();
+        entities.sort_by_key(|entity| entity.id());
+        assert_eq!(entities, [enabled, disabled]);
+
+        registry.set_enabled(disabled, true).unwrap();
+        registry.set_enabled(disabled, true).unwrap();
+        assert!(registry.is_enabled(disabled));
+        assert_eq!(registry.query::<(&Position,)>().count(), 2);
+
+        registry.destroy_entity(disabled).unwrap();
+        assert!(registry.set_enabled(disabled, false).is_err());
+    }
+
+    #[test]
+    fn test_query_get_single_entity() {
+        let mut registry = Registry::new();
+        let e1 = registry.spawn((Position { x: 1.0, y: 1.0 }, Velocity { dx: 1.0, dy: 0.0 }));
+        let e2 = registry.spawn((Position { x: 2.0, y: 2.0 },));
+
+        let query = Query::<(&Position, &Velocity)>::new(&mut registry);
+        let (pos, vel) = query.get(e1).unwrap();
+        assert_eq!(pos.x, 1.0);
+        assert_eq!(vel.dx, 1.0);
+        assert!(query.get(e2).is_none());
+    }
+
+    #[test]
+    fn test_query_get_mut_single_entity() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn((Position { x: 1.0, y: 1.0 }, Velocity { dx: 5.0, dy: 0.0 }));
+
+        let mut query = Query::<(&mut Position, &Velocity)>::new(&mut registry);
+        let (mut pos, vel) = query.get_mut(entity).unwrap();
+        pos.x += vel.dx;
+
+        assert_eq!(registry.get_component::<Position>(entity).unwrap().x, 6.0);
+    }
+
+    #[test]
+    fn test_query_get_rejects_stale_entity() {
+        let mut registry = Registry::new();
+        let stale = registry.spawn((Position { x: 1.0, y: 1.0 },));
+        registry.destroy_entity(stale).unwrap();
+        let reused = registry.spawn((Position { x: 2.0, y: 2.0 },));
+        assert_eq!(stale.id(), reused.id());
+
+        let query = Query::<(&Position,)>::new(&mut registry);
+        assert!(query.get(stale).is_none());
+        assert_eq!(query.get(reused).unwrap().0.x, 2.0);
+    }
+
+    #[test]
+    fn test_query_len_and_is_empty() {
+        let mut registry = Registry::new();
+        registry.spawn((Position { x: 1.0, y: 1.0 }, PlayerTag));
+        registry.spawn((Position { x: 2.0, y: 2.0 },));
+        let disabled = registry.spawn((Position { x: 3.0, y: 3.0 },));
+        registry.set_enabled(disabled, false).unwrap();
+
+        let mut query = Query::<(&mut Position,)>::new(&mut registry);
+        assert!(!query.is_empty());
+        assert_eq!(query.len(), 2);
+        assert_eq!(query.count(), 2);
+        assert_eq!(query.iter_mut().count(), 2);
+
+        let query = Query::<(&Position, &PlayerTag)>::new(&mut registry);
+        assert_eq!((query.len(), query.count()), (1, 1));
+
+        let query = Query::<(&Velocity,)>::new(&mut registry);
+        assert!(query.is_empty());
+        assert_eq!(query.len(), 0);
+
+        let query = Query::<(Entity,)>::new(&mut registry);
+        assert_eq!((query.len(), query.count()), (2, 2));
+        let query = Query::<(&mut Position,), IncludeDisabled>::new(&mut registry);
+        assert_eq!((query.len(), query.count()), (3, 3));
+    }
+
+    #[test]
+    fn test_query_single() {
+        let mut registry = Registry::new();
+        registry.spawn((Position { x: 1.0, y: 1.0 }, PlayerTag));
+        registry.spawn((Position { x: 2.0, y: 2.0 },));
+
+        let query = Query::<(&Position, &PlayerTag)>::new(&mut registry);
+        let (pos, _) = query.single().unwrap();
+        assert_eq!(pos.x, 1.0);
+
+        let query = Query::<(&Position,)>::new(&mut registry);
+        assert!(matches!(
+            query.single(),
+            Err(RecsError::MultipleEntities(_))
+        ));
+
+        let query = Query::<(&Velocity,)>::new(&mut registry);
+        assert!(matches!(query.single(), Err(RecsError::NoEntities(_))));
+    }
+
+    #[test]
+    fn test_query_single_mut() {
+        let mut registry = Registry::new();
+        let player = registry.spawn((Position { x: 1.0, y: 1.0 }, PlayerTag));
+
+        let mut query = Query::<(&mut Position, &PlayerTag)>::new(&mut registry);
+        query.single_mut().unwrap().0.x = 10.0;
+        query.single_mut().unwrap().0.y = 20.0;
+
+        assert_eq!(
+            registry.get_component::<Position>(player),
+            Some(&Position { x: 10.0, y: 20.0 })
+        );
+    }
+
+    #[test]
+    fn test_query_iter_combinations() {
+        let mut registry = Registry::new();
+        for x in 0..4 {
+            registry.spawn((Position {
+                x: x as f32,
+                y: 0.0,
+            },));
+        }
+        registry.spawn((Velocity { dx: 0.0, dy: 0.0 },));
+
+        let query = Query::<(&Position,)>::new(&mut registry);
+        let pairs: Vec<(f32, f32)> = query
+            .iter_combinations::<2>()
+            .map(|[(a,), (b,)]| (a.x, b.x))
+            .collect();
+
+        assert_eq!(pairs.len(), 6);
+        assert!(pairs.iter().all(|(a, b)| a != b));
+        assert_eq!(query.iter_combinations::<3>().count(), 4);
+        assert_eq!(query.iter_combinations::<5>().count(), 0);
+    }
+
+    #[test]
+    fn test_query_iter_combinations_mut() {
+        let mut registry = Registry::new();
+        for _ in 0..3 {
+            registry.spawn((Position { x: 0.0, y: 0.0 },));
+        }
+
+        let mut query = Query::<(&mut Position,)>::new(&mut registry);
+        let mut pairs = query.iter_combinations_mut::<2>();
+        while let Some([(mut a,), (mut b,)]) = pairs.fetch_next() {
+            a.x += 1.0;
+            b.x += 1.0;
+        }
+
+        // Every entity takes part in two of the three pairs
+        for (pos,) in registry.query::<(&Position,)>() {
+            assert_eq!(pos.x, 2.0);
+        }
+    }
+
+    #[test]
+    fn test_query_sort_by_key() {
+        let mut registry = Registry::new();
+        for x in [2.0, 0.0, 1.0] {
+            registry.spawn((Position { x, y: 0.0 }, Velocity { dx: x, dy: 0.0 }));
+        }
+
+        let mut query = Query::<(&Position, &mut Velocity)>::new(&mut registry);
+        let mut order = Vec::new();
+        for (position, mut velocity) in query.sort_by_key(|(position, _)| position.x as i32) {
+            velocity.dy = order.len() as f32;
+            order.push(position.x);
+        }
+        assert_eq!(order, [0.0, 1.0, 2.0]);
+
+        let dys: Vec<f32> = query
+            .sort_by(|(a, _), (b, _)| b.x.total_cmp(&a.x))
+            .map(|(_, velocity)| velocity.dy)
+            .collect();
+        assert_eq!(dys, [2.0, 1.0, 0.0]);
+    }
 }
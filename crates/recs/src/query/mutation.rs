@@ -0,0 +1,124 @@
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+use crate::tick::Tick;
+
+/// Mutable access to a component fetched by a query, flagging it as changed
+/// only when it is actually written.
+///
+/// Queries yield `Mut` for `&mut C` items. Reading through it leaves the
+/// change tick alone; dereferencing it mutably marks the component as changed
+/// at the tick of the current run, so `Changed<C>` filters only see real
+/// writes.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// #[derive(Component)]
+/// struct Health(u32);
+///
+/// fn clamp_health(query: Query<(&mut Health,)>) {
+///     for (mut health,) in query {
+///         // Only writes, and so only flags, the entities above the cap
+///         if health.0 > 100 {
+///             health.0 = 100;
+///         }
+///     }
+/// }
+/// # let mut registry = Registry::new();
+/// # registry.spawn((Health(150),));
+/// # registry.add_system(clamp_health);
+/// # registry.run_systems();
+/// ```
+pub struct Mut<'a, T: ?Sized> {
+    value: &'a mut T,
+    changed: &'a mut Tick,
+    this_run: Tick,
+}
+
+impl<'a, T: ?Sized> Mut<'a, T> {
+    /// Creates a pointer to `value` that sets `changed` to `this_run` on
+    /// mutable access
+    pub fn new(value: &'a mut T, changed: &'a mut Tick, this_run: Tick) -> Self {
+        Self {
+            value,
+            changed,
+            this_run,
+        }
+    }
+
+    /// Marks the value as changed without writing it
+    pub fn set_changed(&mut self) {
+        *self.changed = self.this_run;
+    }
+
+    /// Returns the value mutably without marking it as changed, e.g. for
+    /// bookkeeping that change detection shouldn't react to
+    pub fn bypass_change_detection(&mut self) -> &mut T {
+        self.value
+    }
+
+    /// Converts into a plain mutable reference, marking the value as changed
+    pub fn into_inner(mut self) -> &'a mut T {
+        self.set_changed();
+        self.value
+    }
+
+    /// Creates a new pointer mutably borrowing from this one, for passing the
+    /// value to helpers without giving up the original pointer
+    pub fn reborrow(&mut self) -> Mut<'_, T> {
+        Mut::new(self.value, self.changed, self.this_run)
+    }
+}
+
+impl<T: ?Sized> Deref for Mut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<T: ?Sized> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.set_changed();
+        self.value
+    }
+}
+
+impl<T: ?Sized> AsRef<T> for Mut<'_, T> {
+    fn as_ref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Mut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_mutable_access_flags_changes() {
+        let mut value = 1;
+        let mut changed = Tick::new(1);
+        {
+            let mut ptr = Mut::new(&mut value, &mut changed, Tick::new(5));
+            assert_eq!(*ptr, 1);
+            *ptr.bypass_change_detection() = 2;
+        }
+        assert_eq!((value, changed), (2, Tick::new(1)));
+
+        {
+            let mut ptr = Mut::new(&mut value, &mut changed, Tick::new(5));
+            *ptr.reborrow() += 1;
+        }
+        assert_eq!((value, changed), (3, Tick::new(5)));
+    }
+}
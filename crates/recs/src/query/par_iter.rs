@@ -0,0 +1,172 @@
+use rayon::prelude::*;
+
+use crate::{
+    query::{Query, QueryFilter, QueryParam, QueryStorage, ReadOnlyQueryParam},
+    registry::Registry,
+};
+
+/// Minimum number of entities processed by a single rayon task, so tiny
+/// chunks don't drown the actual work in scheduling overhead
+const MIN_BATCH_SIZE: usize = 256;
+
+/// A registry pointer that can be shared with the rayon thread pool
+#[derive(Clone, Copy)]
+struct RegistryPtr(*mut Registry);
+
+// SAFETY: parallel queries only fetch items of distinct entities from each
+// thread, and components are required to be Send + Sync
+unsafe impl Send for RegistryPtr {}
+unsafe impl Sync for RegistryPtr {}
+
+impl RegistryPtr {
+    /// Accessing the pointer through a method makes closures capture the whole
+    /// wrapper instead of the raw, non-Send field
+    fn get(self) -> *mut Registry {
+        self.0
+    }
+}
+
+/// The storage pointers of a query, shared with the rayon thread pool like
+/// `RegistryPtr`
+#[derive(Clone, Copy)]
+struct StoragesPtr<S>(S);
+
+// SAFETY: see `RegistryPtr`
+unsafe impl<S> Send for StoragesPtr<S> {}
+unsafe impl<S> Sync for StoragesPtr<S> {}
+
+impl<S: Copy> StoragesPtr<S> {
+    fn get(self) -> S {
+        self.0
+    }
+}
+
+impl<'q, Q: QueryStorage, F: QueryFilter> Query<'q, Q, F> {
+    /// Returns a parallel iterator over the query results, processed in
+    /// chunks on the rayon thread pool.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use rayon::prelude::*;
+    /// #[derive(Component)]
+    /// struct Mass(f32);
+    ///
+    /// fn total_mass(query: Query<(&Mass,)>) {
+    ///     let total: f32 = query.par_iter().map(|(mass,)| mass.0).sum();
+    ///     println!("total mass: {}", total);
+    /// }
+    /// # let mut registry = Registry::new();
+    /// # registry.add_system(total_mass);
+    /// # registry.run_systems();
+    /// ```
+    pub fn par_iter<'s>(&'s self) -> impl ParallelIterator<Item = <Q as QueryParam<'s>>::Item>
+    where
+        Q: QueryParam<'s> + ReadOnlyQueryParam,
+        <Q as QueryParam<'s>>::Item: Send,
+    {
+        // SAFETY: read-only queries never hand out mutable references or touch
+        // change ticks, so iterating through a shared borrow is sound
+        unsafe { self.par_iter_unchecked() }
+    }
+
+    /// Returns a parallel iterator over the query results, including mutable
+    /// items, processed in chunks on the rayon thread pool
+    pub fn par_iter_mut<'s>(
+        &'s mut self,
+    ) -> impl ParallelIterator<Item = <Q as QueryParam<'s>>::Item>
+    where
+        Q: QueryParam<'s>,
+        <Q as QueryParam<'s>>::Item: Send,
+    {
+        // SAFETY: the iterator borrows self mutably for its whole lifetime, and
+        // every entity is yielded at most once
+        unsafe { self.par_iter_unchecked() }
+    }
+
+    /// Calls `f` on the items of every matching entity in parallel
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Position { x: f32 }
+    ///
+    /// #[derive(Component)]
+    /// struct Velocity { dx: f32 }
+    ///
+    /// fn movement(mut query: Query<(&mut Position, &Velocity)>) {
+    ///     query.par_for_each(|(mut pos, vel)| pos.x += vel.dx);
+    /// }
+    /// # let mut registry = Registry::new();
+    /// # registry.add_system(movement);
+    /// # registry.run_systems();
+    /// ```
+    pub fn par_for_each<'s>(&'s mut self, f: impl Fn(<Q as QueryParam<'s>>::Item) + Send + Sync)
+    where
+        Q: QueryParam<'s>,
+        <Q as QueryParam<'s>>::Item: Send,
+    {
+        self.par_iter_mut().for_each(f);
+    }
+
+    /// # Safety
+    /// No other reference to the queried components may be alive while the
+    /// returned iterator is used.
+    unsafe fn par_iter_unchecked<'s>(
+        &self,
+    ) -> impl ParallelIterator<Item = <Q as QueryParam<'s>>::Item> + use<'s, Q, F>
+    where
+        Q: QueryParam<'s>,
+        <Q as QueryParam<'s>>::Item: Send,
+    {
+        // Ids come from the smallest storage, already checked against the
+        // other storages, the Disabled marker and the filter
+        let ids = self.matching_ids();
+
+        let this_run = self.this_run;
+        let registry = RegistryPtr(self.registry);
+        let storages = self.storages.map(StoragesPtr);
+        ids.into_par_iter()
+            .with_min_len(MIN_BATCH_SIZE)
+            // SAFETY: every id is unique, so items of one entity are only ever
+            // fetched by a single thread
+            .filter_map(move |id| unsafe {
+                Q::fetch(registry.get(), storages?.get(), id, this_run)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::Component;
+
+    struct Value(u64);
+    impl Component for Value {}
+
+    struct Doubled;
+    impl Component for Doubled {}
+
+    #[test]
+    fn test_par_iter_matches_sequential() {
+        let mut registry = Registry::new();
+        for i in 0..2_000 {
+            let entity = registry.spawn((Value(i),));
+            if i % 2 == 0 {
+                registry.add_component(entity, Doubled).unwrap();
+            }
+        }
+
+        let query = Query::<(&Value,)>::new(&mut registry);
+        let total: u64 = query.par_iter().map(|(v,)| v.0).sum();
+        assert_eq!(total, (0..2_000).sum::<u64>());
+
+        let mut query = Query::<(&mut Value, &Doubled)>::new(&mut registry);
+        query.par_for_each(|(mut value, _)| value.0 *= 2);
+
+        let total: u64 = registry.query::<(&Value,)>().map(|(v,)| v.0).sum();
+        let expected: u64 = (0..2_000).map(|i| if i % 2 == 0 { i * 2 } else { i }).sum();
+        assert_eq!(total, expected);
+    }
+}
@@ -0,0 +1,134 @@
+use std::marker::PhantomData;
+
+use crate::{
+    query::{Query, QueryFilter, QueryStorage},
+    registry::Registry,
+};
+
+/// The storages of a query and its filter, resolved once and reused across
+/// queries.
+///
+/// Looking storages up finds the id of every queried component's storage and
+/// downcasts it. A `QueryState` keeps the result and only resolves again when
+/// the registry's storages changed, e.g. when a new component type got its
+/// storage. Systems keep one for each of their `Query` parameters.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use recs::query::QueryState;
+/// #[derive(Component)]
+/// struct Position(f32);
+///
+/// let mut registry = Registry::new();
+/// registry.spawn(Position(1.0));
+///
+/// let mut state = QueryState::<(&mut Position,)>::new();
+/// for _ in 0..3 {
+///     for (mut position,) in state.query(&mut registry) {
+///         position.0 *= 2.0;
+///     }
+/// }
+/// let mut query = state.query(&mut registry);
+/// let (position,) = query.single_mut().unwrap();
+/// assert_eq!(position.0, 8.0);
+/// ```
+pub struct QueryState<Q: QueryStorage, F: QueryFilter = ()> {
+    /// The storage version of the registry the storages were resolved in, see
+    /// `Registry::storage_version`
+    version: Option<u64>,
+    storages: Option<Q::Storages>,
+    /// The storages the filter reads, None until first resolved
+    filter: Option<F::State>,
+    _marker: PhantomData<fn() -> F>,
+}
+
+// SAFETY: the storage pointers are only dereferenced by the queries created
+// from the state, which borrow the registry they point into
+unsafe impl<Q: QueryStorage, F: QueryFilter> Send for QueryState<Q, F> {}
+unsafe impl<Q: QueryStorage, F: QueryFilter> Sync for QueryState<Q, F> {}
+
+impl<Q: QueryStorage, F: QueryFilter> Default for QueryState<Q, F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Q: QueryStorage, F: QueryFilter> QueryState<Q, F> {
+    /// Creates a state that resolves its storages on first use
+    pub fn new() -> Self {
+        Self {
+            version: None,
+            storages: None,
+            filter: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a query over `registry` with the cached storages
+    pub fn query<'q>(&mut self, registry: &'q mut Registry) -> Query<'q, Q, F> {
+        let last_run = registry.last_change_tick();
+        let this_run = registry.change_tick();
+        // SAFETY: the registry is borrowed mutably
+        let (storages, filter) = unsafe { self.storages(registry) };
+        Query::with_ticks(registry, storages, filter, last_run, this_run)
+    }
+
+    /// Returns the storages of the queried components and of the filter,
+    /// resolving them again if the storages of the registry changed since
+    /// they were cached
+    ///
+    /// # Safety
+    /// `registry` must point to a live Registry whose storages aren't being
+    /// created or replaced concurrently.
+    pub(crate) unsafe fn storages(
+        &mut self,
+        registry: *mut Registry,
+    ) -> (Option<Q::Storages>, F::State) {
+        let version = unsafe { (*registry).storage_version };
+        if self.version != Some(version) {
+            let components = unsafe { &(*registry).components };
+            self.storages = Q::storages(components);
+            self.filter = Some(F::state(components));
+            self.version = Some(version);
+        }
+        let filter = self.filter.expect("The filter state was just resolved");
+        (self.storages, filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::Component;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    impl Component for Position {}
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+    impl Component for Velocity {}
+
+    #[test]
+    fn test_state_revalidates_when_storages_change() {
+        let mut registry = Registry::new();
+        let mut state = QueryState::<(&Position, &Velocity)>::new();
+        registry.spawn(Position(0));
+        assert_eq!(state.query(&mut registry).iter().count(), 0);
+
+        // The storage of `Velocity` didn't exist when the state was resolved
+        let entity = registry.spawn((Position(1), Velocity(2)));
+        let items: Vec<_> = state.query(&mut registry).into_iter().collect();
+        assert_eq!(items, [(&Position(1), &Velocity(2))]);
+
+        // A state resolved in another registry doesn't reuse its storages
+        let mut other = Registry::new();
+        other.spawn((Position(3), Velocity(4)));
+        let items: Vec<_> = state.query(&mut other).into_iter().collect();
+        assert_eq!(items, [(&Position(3), &Velocity(4))]);
+
+        registry.destroy_entity(entity).unwrap();
+        assert_eq!(state.query(&mut registry).iter().count(), 0);
+    }
+}
@@ -0,0 +1,290 @@
+use std::{any::TypeId, iter::FusedIterator, marker::PhantomData};
+
+use crate::{
+    component::{
+        Component,
+        sparse_set::SparseSet,
+        table::{Table, TypedColumn},
+    },
+    entity::Disabled,
+    query::Mut,
+    registry::Registry,
+    tick::Tick,
+};
+
+/// A shared or mutable reference to a table component, fetched by a table
+/// query
+pub trait TableQueryItem<'q> {
+    type Component: Component;
+    type Item;
+
+    /// Fetches the item at `row` of the column.
+    ///
+    /// Mutable items mark the component as changed at `this_run` once written.
+    ///
+    /// # Safety
+    /// `column` must point to a live column for the whole lifetime `'q`, no
+    /// other reference to the same value may be alive at the same time, and
+    /// `row` must be lower than the length of the column.
+    unsafe fn fetch(
+        column: *mut TypedColumn<Self::Component>,
+        row: usize,
+        this_run: Tick,
+    ) -> Self::Item;
+}
+
+impl<'q, C: Component> TableQueryItem<'q> for &C {
+    type Component = C;
+    type Item = &'q C;
+
+    unsafe fn fetch(column: *mut TypedColumn<C>, row: usize, _this_run: Tick) -> Self::Item {
+        unsafe { (*column).get_unchecked(row) }
+    }
+}
+
+impl<'q, C: Component> TableQueryItem<'q> for &mut C {
+    type Component = C;
+    type Item = Mut<'q, C>;
+
+    unsafe fn fetch(column: *mut TypedColumn<C>, row: usize, this_run: Tick) -> Self::Item {
+        unsafe { (*column).get_tracked_unchecked(row, this_run) }
+    }
+}
+
+/// A tuple of `TableQueryItem`s, iterated with `Registry::query_table`
+pub trait TableQueryParam<'q> {
+    /// The type returned by the query iterator
+    type Item;
+    /// The columns of the queried components in one table
+    type Columns: Copy;
+
+    /// Returns the queried component types, in query order
+    fn types() -> Vec<TypeId>;
+
+    /// Returns the columns of the queried components in `table`
+    ///
+    /// # Safety
+    /// `table` must point to a live table that has every queried component.
+    unsafe fn columns(table: *mut Table) -> Self::Columns;
+
+    /// Fetches the items at `row` of the columns
+    ///
+    /// # Safety
+    /// Same as `TableQueryItem::fetch`, for every column.
+    unsafe fn fetch(columns: Self::Columns, row: usize, this_run: Tick) -> Self::Item;
+}
+
+macro_rules! impl_table_query_for_tuple {
+    ($($name:ident),+) => {
+        impl<'q, $($name: TableQueryItem<'q>),+> TableQueryParam<'q> for ($($name,)+) {
+            type Item = ($($name::Item,)+);
+            type Columns = ($(*mut TypedColumn<$name::Component>,)+);
+
+            fn types() -> Vec<TypeId> {
+                vec![$(TypeId::of::<$name::Component>()),+]
+            }
+
+            unsafe fn columns(table: *mut Table) -> Self::Columns {
+                ($(
+                    unsafe { (*table).column_mut::<$name::Component>() }
+                        .expect("Matched tables have every queried component")
+                        as *mut TypedColumn<$name::Component>,
+                )+)
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn fetch(columns: Self::Columns, row: usize, this_run: Tick) -> Self::Item {
+                let ($($name,)+) = columns;
+                ($(unsafe { $name::fetch($name, row, this_run) },)+)
+            }
+        }
+    };
+}
+
+impl_table_query_for_tuple!(Q0);
+impl_table_query_for_tuple!(Q0, Q1);
+impl_table_query_for_tuple!(Q0, Q1, Q2);
+impl_table_query_for_tuple!(Q0, Q1, Q2, Q3);
+impl_table_query_for_tuple!(Q0, Q1, Q2, Q3, Q4);
+impl_table_query_for_tuple!(Q0, Q1, Q2, Q3, Q4, Q5);
+impl_table_query_for_tuple!(Q0, Q1, Q2, Q3, Q4, Q5, Q6);
+impl_table_query_for_tuple!(Q0, Q1, Q2, Q3, Q4, Q5, Q6, Q7);
+
+/// Iterator over the table components matching a `TableQueryParam`, walking
+/// the rows of every matching table in turn
+pub struct TableQueryIter<'q, Q: TableQueryParam<'q>> {
+    /// The tables having every queried component
+    tables: Vec<*mut Table>,
+    /// Index of the next table to walk
+    next_table: usize,
+    /// The columns and number of rows of the table being walked
+    current: Option<(*mut Table, Q::Columns, usize)>,
+    row: usize,
+    disabled: Option<&'q SparseSet<Disabled>>,
+    this_run: Tick,
+    _marker: PhantomData<&'q mut Registry>,
+}
+
+impl<'q, Q: TableQueryParam<'q>> Iterator for TableQueryIter<'q, Q> {
+    type Item = Q::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((table, columns, len)) = self.current
+                && self.row < len
+            {
+                let row = self.row;
+                self.row += 1;
+                // SAFETY: the registry is borrowed mutably for 'q, and `row`
+                // is in bounds
+                let entity = unsafe { (*table).entities()[row] };
+                if self
+                    .disabled
+                    .is_some_and(|disabled| disabled.get(entity.id() as usize).is_some())
+                {
+                    continue;
+                }
+                // SAFETY: every row is fetched once, and the queried
+                // component types are distinct
+                return Some(unsafe { Q::fetch(columns, row, self.this_run) });
+            }
+
+            let &table = self.tables.get(self.next_table)?;
+            self.next_table += 1;
+            self.row = 0;
+            // SAFETY: matched tables have every queried component
+            self.current = Some(unsafe { (table, Q::columns(table), (*table).len()) });
+        }
+    }
+}
+
+impl<'q, Q: TableQueryParam<'q>> FusedIterator for TableQueryIter<'q, Q> {}
+
+impl Registry {
+    /// Iterates over the enabled entities having every table component of
+    /// `Q`, see `register_table_component`.
+    ///
+    /// Each matching table is walked contiguously, so this is the fastest way
+    /// to iterate several components at once. Components that aren't stored
+    /// in tables never match.
+    ///
+    /// # Panics
+    /// Panics if `Q` queries the same component type twice.
+    pub fn query_table<'q, Q: TableQueryParam<'q>>(&'q mut self) -> TableQueryIter<'q, Q> {
+        let types = Q::types();
+        let mut distinct = types.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(
+            distinct.len(),
+            types.len(),
+            "A table query can't fetch the same component twice"
+        );
+
+        let this_run = self.change_tick();
+        let disabled = self
+            .disabled_storage()
+            .map(|disabled| disabled as *const SparseSet<Disabled>);
+        let tables = self
+            .archetypes
+            .tables_mut()
+            .iter_mut()
+            .filter(|table| table.has_all(&types) && !table.is_empty())
+            .map(|table| table as *mut Table)
+            .collect();
+
+        TableQueryIter {
+            tables,
+            next_table: 0,
+            current: None,
+            row: 0,
+            // SAFETY: the `Disabled` storage isn't a table, so it isn't
+            // touched by the iteration, and the registry stays borrowed for 'q
+            disabled: disabled.map(|disabled| unsafe { &*disabled }),
+            this_run,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Entity;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    impl Component for Position {}
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+    impl Component for Velocity {}
+
+    #[derive(Debug, PartialEq)]
+    struct Sparse;
+    impl Component for Sparse {}
+
+    fn world() -> (Registry, [Entity; 3]) {
+        let mut registry = Registry::new();
+        registry.register_table_component::<Position>().unwrap();
+        registry.register_table_component::<Velocity>().unwrap();
+        let still = registry.spawn((Position(0),));
+        let moving = registry.spawn((Position(10), Velocity(1)));
+        let tagged = registry.spawn((Position(20), Velocity(2), Sparse));
+        (registry, [still, moving, tagged])
+    }
+
+    #[test]
+    fn test_query_table_walks_matching_tables() {
+        let (mut registry, [still, moving, tagged]) = world();
+
+        for (mut position, velocity) in registry.query_table::<(&mut Position, &Velocity)>() {
+            position.0 += velocity.0;
+        }
+        assert_eq!(
+            registry.get_component::<Position>(still),
+            Some(&Position(0))
+        );
+        assert_eq!(
+            registry.get_component::<Position>(moving),
+            Some(&Position(11))
+        );
+        assert_eq!(
+            registry.get_component::<Position>(tagged),
+            Some(&Position(22))
+        );
+        assert_eq!(registry.query_table::<(&Position,)>().count(), 3);
+        // Sparse-set components aren't stored in tables
+        assert_eq!(registry.query_table::<(&Sparse,)>().count(), 0);
+
+        registry.add_component(moving, Disabled).unwrap();
+        assert_eq!(registry.query_table::<(&Velocity,)>().count(), 1);
+    }
+
+    #[test]
+    fn test_query_table_marks_changed() {
+        let (mut registry, [still, moving, tagged]) = world();
+        registry.maintain();
+        let this_run = registry.change_tick();
+
+        // Only written components are marked as changed
+        for (mut velocity, position) in registry.query_table::<(&mut Velocity, &Position)>() {
+            if position.0 == 10 {
+                velocity.0 += 1;
+            }
+        }
+        let ticks = |id: Entity| registry.archetypes.ticks::<Velocity>(id.id() as usize);
+        assert_eq!(ticks(moving).unwrap().changed, this_run);
+        assert_ne!(ticks(tagged).unwrap().changed, this_run);
+        assert!(ticks(still).is_none());
+        let position = registry.archetypes.ticks::<Position>(moving.id() as usize);
+        assert_ne!(position.unwrap().changed, this_run);
+    }
+
+    #[test]
+    #[should_panic(expected = "same component twice")]
+    fn test_query_table_rejects_aliasing() {
+        let (mut registry, _) = world();
+        registry.query_table::<(&mut Position, &Position)>().count();
+    }
+}
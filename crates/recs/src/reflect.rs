@@ -0,0 +1,395 @@
+//! Runtime access to components and their fields by name.
+//!
+//! Editors and scripting layers don't know component types at compile time.
+//! Components that implement `Reflect`, usually through `#[derive(Reflect)]`,
+//! and are registered with `Registry::register_reflect` can be looked up by
+//! name, and their fields read and written through `dyn Reflect`. Leaf values
+//! such as numbers, booleans and strings convert to and from `ReflectValue`.
+
+use std::{any::Any, collections::BTreeMap};
+
+use crate::{component::Component, entity::Entity, registry::Registry};
+
+/// A value whose fields can be enumerated and accessed by name at runtime
+pub trait Reflect: Any + Send + Sync {
+    /// Returns the names of the fields in declaration order, or nothing for
+    /// leaf values
+    fn field_names(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Returns the field called `name`
+    fn field(&self, _name: &str) -> Option<&dyn Reflect> {
+        None
+    }
+
+    /// Returns the field called `name` mutably
+    fn field_mut(&mut self, _name: &str) -> Option<&mut dyn Reflect> {
+        None
+    }
+
+    /// Returns the value of a leaf, if it is one
+    fn value(&self) -> Option<ReflectValue> {
+        None
+    }
+
+    /// Sets the value of a leaf from `value`, converting it if needed.
+    /// Returns false if the value isn't a leaf or can't hold `value`.
+    fn set_value(&mut self, _value: ReflectValue) -> bool {
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl dyn Reflect {
+    /// Returns the value as a `T`, if it is one
+    pub fn downcast_ref<T: Reflect>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+
+    /// Returns the value as a mutable `T`, if it is one
+    pub fn downcast_mut<T: Reflect>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut()
+    }
+
+    /// Returns the nested field at a dot-separated path such as
+    /// `"stats.health"`. The empty path is the value itself.
+    pub fn path(&self, path: &str) -> Option<&dyn Reflect> {
+        path.split('.')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |value, name| value.field(name))
+    }
+
+    /// Returns the nested field at a dot-separated path mutably, see `path`
+    pub fn path_mut(&mut self, path: &str) -> Option<&mut dyn Reflect> {
+        path.split('.')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |value, name| value.field_mut(name))
+    }
+}
+
+/// The value of a reflected leaf, such as a number or a string. Serialized
+/// as the bare value.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum ReflectValue {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+}
+
+impl ReflectValue {
+    fn to_i64(&self) -> Option<i64> {
+        match *self {
+            ReflectValue::Int(value) => Some(value),
+            ReflectValue::UInt(value) => value.try_into().ok(),
+            _ => None,
+        }
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        match *self {
+            ReflectValue::Int(value) => Some(value as f64),
+            ReflectValue::UInt(value) => Some(value as f64),
+            ReflectValue::Float(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_reflect_leaf {
+    ($($ty:ty => |$value:ident| $get:expr, $set:expr;)*) => {$(
+        impl Reflect for $ty {
+            fn value(&self) -> Option<ReflectValue> {
+                let $value = self;
+                Some($get)
+            }
+
+            fn set_value(&mut self, $value: ReflectValue) -> bool {
+                match $set {
+                    Some(value) => {
+                        *self = value;
+                        true
+                    }
+                    None => false,
+                }
+            }
+
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+
+            fn as_any_mut(&mut self) -> &mut dyn Any {
+                self
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_reflect_int {
+    ($($signed:ty),*; $($unsigned:ty),*) => {
+        impl_reflect_leaf! {
+            $($signed => |value| ReflectValue::Int(*value as i64),
+                value.to_i64().and_then(|value| value.try_into().ok());)*
+            $($unsigned => |value| ReflectValue::UInt(*value as u64),
+                value.to_i64().and_then(|value| value.try_into().ok()).or(match value {
+                    ReflectValue::UInt(value) => value.try_into().ok(),
+                    _ => None,
+                });)*
+        }
+    };
+}
+
+impl_reflect_int!(i8, i16, i32, i64, isize; u8, u16, u32, u64, usize);
+
+impl_reflect_leaf! {
+    bool => |value| ReflectValue::Bool(*value), match value {
+        ReflectValue::Bool(value) => Some(value),
+        _ => None,
+    };
+    f32 => |value| ReflectValue::Float(*value as f64), value.to_f64().map(|value| value as f32);
+    f64 => |value| ReflectValue::Float(*value), value.to_f64();
+    String => |value| ReflectValue::String(value.clone()), match value {
+        ReflectValue::String(value) => Some(value),
+        _ => None,
+    };
+}
+
+impl Reflect for Entity {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+type GetFn = for<'r> fn(&'r Registry, Entity) -> Option<&'r dyn Reflect>;
+type GetMutFn = for<'r> fn(&'r mut Registry, Entity) -> Option<&'r mut dyn Reflect>;
+
+/// Type-erased accessors of a reflected component type
+#[derive(Clone, Copy)]
+struct ReflectedComponent {
+    get: GetFn,
+    get_mut: GetMutFn,
+}
+
+/// The component types registered for reflection, keyed by name
+#[derive(Default)]
+pub(crate) struct ReflectedComponents {
+    components: BTreeMap<&'static str, ReflectedComponent>,
+}
+
+impl ReflectedComponents {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks if a component type is registered under `name`
+    #[cfg(feature = "ron")]
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.components.contains_key(name)
+    }
+}
+
+impl Registry {
+    /// Makes `C` accessible by name through `get_component_dyn`, under its
+    /// type name without the module path
+    pub fn register_reflect<C: Component + Reflect>(&mut self) {
+        let name = std::any::type_name::<C>();
+        let short = name.split('<').next().unwrap_or(name);
+        self.register_reflect_as::<C>(short.rsplit("::").next().unwrap_or(short));
+    }
+
+    /// Makes `C` accessible by name through `get_component_dyn`, under `name`
+    pub fn register_reflect_as<C: Component + Reflect>(&mut self, name: &'static str) {
+        let component = ReflectedComponent {
+            get: |registry, entity| {
+                registry
+                    .get_component::<C>(entity)
+                    .map(|component| component as &dyn Reflect)
+            },
+            get_mut: |registry, entity| {
+                registry
+                    .get_component_mut::<C>(entity)
+                    .map(|component| component as &mut dyn Reflect)
+            },
+        };
+        self.reflected.components.insert(name, component);
+    }
+
+    /// Returns the names of the components registered for reflection, sorted
+    pub fn reflected_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.reflected.components.keys().copied()
+    }
+
+    /// Returns the component registered under `name` of `entity`
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use recs::reflect::ReflectValue;
+    /// #[derive(Component, Reflect)]
+    /// struct Position { x: f32, y: f32 }
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.register_reflect::<Position>();
+    /// let entity = registry.spawn(Position { x: 1.0, y: 2.0 });
+    ///
+    /// let position = registry.get_component_dyn(entity, "Position").unwrap();
+    /// assert_eq!(position.field_names(), ["x", "y"]);
+    /// assert_eq!(position.path("y").unwrap().value(), Some(ReflectValue::Float(2.0)));
+    ///
+    /// let position = registry.get_component_dyn_mut(entity, "Position").unwrap();
+    /// position.path_mut("x").unwrap().set_value(ReflectValue::Int(5));
+    /// assert_eq!(registry.get_component::<Position>(entity).unwrap().x, 5.0);
+    /// ```
+    pub fn get_component_dyn(&self, entity: Entity, name: &str) -> Option<&dyn Reflect> {
+        let component = self.reflected.components.get(name)?;
+        (component.get)(self, entity)
+    }
+
+    /// Returns the component registered under `name` of `entity` mutably,
+    /// marking it as changed
+    pub fn get_component_dyn_mut(
+        &mut self,
+        entity: Entity,
+        name: &str,
+    ) -> Option<&mut dyn Reflect> {
+        let component = *self.reflected.components.get(name)?;
+        (component.get_mut)(self, entity)
+    }
+
+    /// Returns every reflected component of `entity` with its name, sorted by
+    /// name
+    pub fn reflect_components(&self, entity: Entity) -> Vec<(&'static str, &dyn Reflect)> {
+        self.reflected
+            .components
+            .iter()
+            .filter_map(|(&name, component)| Some((name, (component.get)(self, entity)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Reflect;
+
+    #[derive(Debug, PartialEq, Reflect)]
+    struct Stats {
+        health: u32,
+        speed: f32,
+    }
+
+    #[derive(Debug, PartialEq, Reflect)]
+    struct Unit {
+        name: String,
+        stats: Stats,
+        alive: bool,
+    }
+    impl Component for Unit {}
+
+    #[derive(Debug, PartialEq, Reflect)]
+    struct Velocity(f32, f32);
+    impl Component for Velocity {}
+
+    #[test]
+    fn test_nested_fields_by_path() {
+        let mut registry = Registry::new();
+        registry.register_reflect::<Unit>();
+        registry.register_reflect_as::<Velocity>("vel");
+        let entity = registry.spawn((
+            Unit {
+                name: "scout".into(),
+                stats: Stats {
+                    health: 10,
+                    speed: 2.5,
+                },
+                alive: true,
+            },
+            Velocity(1.0, -1.0),
+        ));
+        assert_eq!(
+            registry.reflected_names().collect::<Vec<_>>(),
+            ["Unit", "vel"]
+        );
+
+        let names: Vec<_> = registry
+            .reflect_components(entity)
+            .into_iter()
+            .map(|(name, value)| (name, value.field_names()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("Unit", &["name", "stats", "alive"][..]),
+                ("vel", &["0", "1"][..])
+            ]
+        );
+
+        let unit = registry.get_component_dyn(entity, "Unit").unwrap();
+        assert_eq!(
+            unit.path("stats.health").unwrap().value(),
+            Some(ReflectValue::UInt(10))
+        );
+        assert_eq!(
+            unit.path("stats")
+                .unwrap()
+                .downcast_ref::<Stats>()
+                .unwrap()
+                .speed,
+            2.5
+        );
+        assert!(unit.path("stats.mana").is_none());
+        assert!(unit.path("stats").unwrap().value().is_none());
+
+        let tick = registry.change_tick();
+        registry.clear_trackers();
+        let unit = registry.get_component_dyn_mut(entity, "Unit").unwrap();
+        assert!(
+            unit.path_mut("stats.health")
+                .unwrap()
+                .set_value(ReflectValue::Int(40))
+        );
+        assert!(
+            !unit
+                .path_mut("stats.health")
+                .unwrap()
+                .set_value(ReflectValue::Int(-1))
+        );
+        assert!(
+            !unit
+                .path_mut("alive")
+                .unwrap()
+                .set_value(ReflectValue::UInt(0))
+        );
+        assert!(
+            unit.path_mut("name")
+                .unwrap()
+                .set_value(ReflectValue::String("ace".into()))
+        );
+        registry
+            .get_component_dyn_mut(entity, "vel")
+            .unwrap()
+            .path_mut("1")
+            .unwrap()
+            .set_value(ReflectValue::Float(3.0));
+
+        let unit = registry.get_component::<Unit>(entity).unwrap();
+        assert_eq!((unit.stats.health, unit.name.as_str()), (40, "ace"));
+        assert_eq!(
+            registry.get_component::<Velocity>(entity),
+            Some(&Velocity(1.0, 3.0))
+        );
+        assert!(registry.get_component_dyn(entity, "Missing").is_none());
+        assert_ne!(registry.change_tick(), tick);
+    }
+}
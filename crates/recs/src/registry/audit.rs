@@ -0,0 +1,192 @@
+//! Reports on the resource usage recorded by `resource::audit`, to prune
+//! over-broad resource declarations.
+
+use std::{collections::BTreeMap, fmt};
+
+use crate::{
+    registry::{Registry, validate::stage_key},
+    resource::audit::{ResourceUsage, ResourceUse, UsageLog},
+    system::schedule::Stage,
+};
+
+/// A resource access of a system, as listed by `ResourceAudit`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceAuditEntry {
+    /// The stage the system belongs to
+    pub stage: Stage,
+    /// The name of the system
+    pub system: String,
+    /// The type name of the resource
+    pub resource: &'static str,
+    /// What the system declared and used
+    pub usage: ResourceUsage,
+}
+
+/// The resource accesses of every system that ran, returned by
+/// `Registry::resource_audit`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourceAudit {
+    /// Every recorded access, by stage, then system, then resource
+    pub entries: Vec<ResourceAuditEntry>,
+}
+
+impl ResourceAudit {
+    /// Returns the accesses declared broader than they were used, i.e. the
+    /// `ResMut` that could be `Res` and the parameters that could be dropped
+    pub fn over_broad(&self) -> impl Iterator<Item = &ResourceAuditEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.usage.is_over_broad())
+    }
+}
+
+impl fmt::Display for ResourceAudit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut over_broad = self.over_broad().peekable();
+        if over_broad.peek().is_none() {
+            return write!(f, "Every resource access is used as declared");
+        }
+        for entry in over_broad {
+            let suggestion = match entry.usage.used {
+                ResourceUse::Unused => "never used it",
+                _ => "only read it",
+            };
+            writeln!(
+                f,
+                "{:?}: system {} declares {:?} access to {} but {}",
+                entry.stage, entry.system, entry.usage.declared, entry.resource, suggestion
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Registry {
+    /// Reports which resources each system declared access to through `Res`,
+    /// `ResMut` and their optional variants, against what it actually did with
+    /// them over every run so far.
+    ///
+    /// Usage is only recorded in debug builds, so the report is empty in
+    /// release builds. Guards used on other threads than the system's own,
+    /// e.g. in `par_for_each` closures, aren't seen.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use recs::resource::audit::ResourceUse;
+    /// #[derive(Resource)]
+    /// struct Score(u32);
+    ///
+    /// fn show_score(score: ResMut<Score>) {
+    ///     println!("{}", score.0);
+    /// }
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.insert_resource(Score(0));
+    /// registry.add_system(show_score);
+    /// registry.run_systems();
+    ///
+    /// let audit = registry.resource_audit();
+    /// let entry = audit.over_broad().next().unwrap();
+    /// assert_eq!(entry.usage.declared, ResourceUse::Write);
+    /// assert_eq!(entry.usage.used, ResourceUse::Read);
+    /// ```
+    pub fn resource_audit(&self) -> ResourceAudit {
+        let mut stages: Vec<&Stage> = self.schedules.keys().collect();
+        stages.sort_by(|a, b| stage_key(a).cmp(&stage_key(b)));
+
+        let mut entries = Vec::new();
+        for stage in stages {
+            let mut systems = BTreeMap::new();
+            for system in self.schedules[stage].systems() {
+                systems
+                    .entry(system.name())
+                    .or_insert_with(UsageLog::default)
+                    .merge(system.resource_usage());
+            }
+
+            for (system, log) in systems {
+                entries.extend(log.iter().map(|(resource, usage)| ResourceAuditEntry {
+                    stage: stage.clone(),
+                    system: system.clone().into_owned(),
+                    resource,
+                    usage,
+                }));
+            }
+        }
+
+        ResourceAudit { entries }
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+    use crate::{
+        resource::{Res, ResMut, Resource},
+        system::executor::ExecutorKind,
+    };
+
+    #[derive(Default)]
+    struct Score(u32);
+    impl Resource for Score {}
+
+    #[derive(Default)]
+    struct Level(u32);
+    impl Resource for Level {}
+
+    fn tally(mut score: ResMut<Score>, level: Option<ResMut<Level>>) {
+        if score.0 > 2 {
+            score.0 += level.map_or(0, |level| level.0);
+        }
+        score.0 += 1;
+    }
+
+    fn peek(score: ResMut<Score>, _level: Res<Level>) {
+        let _ = score.0;
+    }
+
+    fn usage_of(audit: &ResourceAudit, system: &str, resource: &str) -> ResourceUsage {
+        audit
+            .entries
+            .iter()
+            .find(|entry| entry.system.ends_with(system) && entry.resource.ends_with(resource))
+            .unwrap()
+            .usage
+    }
+
+    #[test]
+    fn test_audit_compares_declared_and_used_access() {
+        for executor in [ExecutorKind::Sequential, ExecutorKind::Parallel] {
+            let mut registry = Registry::new();
+            registry.set_executor(executor);
+            registry.init_resource::<Score>();
+            registry.init_resource::<Level>();
+            registry.add_system(tally);
+            registry.add_system(peek);
+
+            registry.run_systems();
+            let audit = registry.resource_audit();
+            assert_eq!(audit.entries.len(), 4);
+            assert_eq!(
+                usage_of(&audit, "tally", "Level"),
+                ResourceUsage {
+                    declared: ResourceUse::Write,
+                    used: ResourceUse::Unused
+                }
+            );
+            assert_eq!(usage_of(&audit, "peek", "Score").used, ResourceUse::Read);
+            assert_eq!(usage_of(&audit, "peek", "Level").used, ResourceUse::Unused);
+            assert_eq!(audit.over_broad().count(), 3);
+
+            // Usage accumulates over runs
+            for _ in 0..3 {
+                registry.run_systems();
+            }
+            let audit = registry.resource_audit();
+            assert_eq!(usage_of(&audit, "tally", "Level").used, ResourceUse::Read);
+            assert_eq!(usage_of(&audit, "tally", "Score").used, ResourceUse::Write);
+            assert!(audit.to_string().contains("only read it"));
+        }
+    }
+}
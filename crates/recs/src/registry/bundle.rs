@@ -1,27 +1,101 @@
-use crate::{component::Component, entity::Entity, error::RecsError, registry::Registry};
+use crate::{entity::Entity, error::RecsError, registry::Registry};
 
 /// A trait for types that can be added as a bundle of components to an entity.
 ///
-/// This trait is automatically implemented for tuples of components up to 32 elements.
+/// This trait is automatically implemented for single components and for tuples
+/// of up to 32 bundles, so tuples nest: `((Position, Velocity), Player)` adds
+/// all three components. Structs can derive it with `#[derive(Bundle)]`, and
+/// their bundle-typed fields are flattened the same way.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// #[derive(Component)]
+/// struct Position { x: f32 }
+///
+/// #[derive(Component)]
+/// struct Rotation(f32);
+///
+/// #[derive(Component)]
+/// struct Player;
+///
+/// #[derive(Bundle)]
+/// struct TransformBundle {
+///     position: Position,
+///     rotation: Rotation,
+/// }
+///
+/// #[derive(Bundle)]
+/// struct PlayerBundle {
+///     transform: TransformBundle,
+///     player: Player,
+/// }
+///
+/// let mut registry = Registry::new();
+/// let entity = registry.spawn(PlayerBundle {
+///     transform: TransformBundle { position: Position { x: 1.0 }, rotation: Rotation(0.0) },
+///     player: Player,
+/// });
+/// assert!(registry.get_component::<Rotation>(entity).is_some());
+///
+/// let transform = registry.remove_bundle::<TransformBundle>(entity).unwrap().unwrap();
+/// assert_eq!(transform.position.x, 1.0);
+/// ```
 pub trait ComponentBundle {
     /// Adds all components in the bundle to the given entity.
+    ///
+    /// Components required by the bundle's components are inserted by the
+    /// registry methods that add bundles, such as `Registry::spawn`, once the
+    /// whole bundle is in.
     fn add_to_entity(self, registry: &mut Registry, entity: Entity) -> Result<(), RecsError>;
+
+    /// Removes every component type of the bundle from the given entity.
+    ///
+    /// Returns the removed components as a bundle if the entity had all of
+    /// them, or None otherwise. The components it did have are removed anyway.
+    fn remove_from_entity(registry: &mut Registry, entity: Entity) -> Option<Self>
+    where
+        Self: Sized;
+
+    /// Prepares the storages of the bundle's component types for `additional`
+    /// more entities, ahead of a `Registry::spawn_batch`.
+    ///
+    /// Does nothing by default, which only costs batch spawns their speedup.
+    fn reserve(registry: &mut Registry, additional: usize)
+    where
+        Self: Sized,
+    {
+        let _ = (registry, additional);
+    }
 }
 
 macro_rules! impl_bundle_for_tuple {
     ($($name:ident),+) => {
         impl<$($name),+> ComponentBundle for ($($name,)+)
         where
-            $($name: Component + 'static),+
+            $($name: ComponentBundle),+
         {
             #[allow(non_snake_case)]
             fn add_to_entity(self, registry: &mut Registry, entity: Entity) -> Result<(), RecsError> {
                 let ($($name,)+) = self;
                 $(
-                    registry.add_component(entity, $name)?;
+                    $name.add_to_entity(registry, entity)?;
                 )+
                 Ok(())
             }
+
+            #[allow(non_snake_case)]
+            fn remove_from_entity(registry: &mut Registry, entity: Entity) -> Option<Self> {
+                // Every member is removed before checking that all were present
+                let ($($name,)+) = ($($name::remove_from_entity(registry, entity),)+);
+                Some(($($name?,)+))
+            }
+
+            fn reserve(registry: &mut Registry, additional: usize) {
+                $(
+                    $name::reserve(registry, additional);
+                )+
+            }
         }
     };
 }
@@ -105,3 +179,96 @@ impl_bundle_for_tuple!(
     C0, C1, C2, C3, C4, C5, C6, C7, C8, C9, C10, C11, C12, C13, C14, C15, C16, C17, C18, C19, C20,
     C21, C22, C23, C24, C25, C26, C27, C28, C29, C30, C31
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::Component;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    impl Component for Position {}
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+    impl Component for Velocity {}
+
+    #[derive(Debug, PartialEq)]
+    struct Player;
+    impl Component for Player {}
+
+    struct MotionBundle {
+        position: Position,
+        velocity: Velocity,
+    }
+
+    impl ComponentBundle for MotionBundle {
+        fn add_to_entity(self, registry: &mut Registry, entity: Entity) -> Result<(), RecsError> {
+            (self.position, self.velocity).add_to_entity(registry, entity)
+        }
+
+        fn remove_from_entity(registry: &mut Registry, entity: Entity) -> Option<Self> {
+            let (position, velocity) =
+                <(Position, Velocity)>::remove_from_entity(registry, entity)?;
+            Some(Self { position, velocity })
+        }
+    }
+
+    #[test]
+    fn test_nested_bundles_flatten() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn((
+            MotionBundle {
+                position: Position(1),
+                velocity: Velocity(2),
+            },
+            (Player,),
+        ));
+
+        assert_eq!(
+            registry.get_component::<Position>(entity),
+            Some(&Position(1))
+        );
+        assert_eq!(
+            registry.get_component::<Velocity>(entity),
+            Some(&Velocity(2))
+        );
+        assert_eq!(registry.get_component::<Player>(entity), Some(&Player));
+    }
+
+    #[test]
+    fn test_remove_bundle() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn((
+            MotionBundle {
+                position: Position(1),
+                velocity: Velocity(2),
+            },
+            Player,
+        ));
+
+        let (motion, player) = registry
+            .remove_bundle::<(MotionBundle, Player)>(entity)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (motion.position, motion.velocity),
+            (Position(1), Velocity(2))
+        );
+        assert_eq!(player, Player);
+        assert!(registry.get_component::<Position>(entity).is_none());
+
+        // Partial bundles are still removed, but can't be returned
+        registry.add_component(entity, Position(3)).unwrap();
+        assert!(
+            registry
+                .remove_bundle::<MotionBundle>(entity)
+                .unwrap()
+                .is_none()
+        );
+        assert!(registry.get_component::<Position>(entity).is_none());
+
+        registry.destroy_entity(entity).unwrap();
+        assert!(registry.remove_bundle::<(Player,)>(entity).is_err());
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    any::TypeId,
+    marker::PhantomData,
+    ptr::{addr_of, addr_of_mut},
+};
+
+use crate::{
+    component::{ComponentTicks, removed::RemovedComponentStorage},
+    query::ComponentStorages,
+    registry::Registry,
+    resource::Resource,
+    system::{access::Access, commands::Commands},
+    tick::Tick,
+};
+
+/// A handle on a registry shared by the systems running at the same time,
+/// through which system parameters fetch their data.
+///
+/// Unlike a `&mut Registry`, the cell is `Copy` and can be handed to several
+/// threads. Every accessor goes through the single field or resource it
+/// reads or writes, so parameters fetching disjoint data never create
+/// overlapping borrows. Whether two accesses overlap is decided by the access
+/// sets of the systems: they are validated when systems are added (see
+/// `Registry::add_system`), and the executor only runs systems with compatible
+/// access sets together. A cell restricted to an access set with `with_access`
+/// checks in debug builds that each resource it hands out belongs to the set,
+/// which catches parameters touching data they didn't declare.
+///
+/// Like the ones of a raw pointer, the references handed out by the accessors
+/// aren't tied to the cell: callers pick their lifetime `'a` and must keep the
+/// registry alive and untouched by conflicting accesses for as long.
+#[derive(Clone, Copy)]
+pub struct UnsafeRegistryCell<'w> {
+    registry: *mut Registry,
+    /// The data the holder may access, or None for the whole registry
+    access: Option<&'w Access>,
+    _marker: PhantomData<&'w Registry>,
+}
+
+// SAFETY: the cell is only a pointer, and its accessors require the caller to
+// uphold the access sets that keep threads from touching the same data
+unsafe impl Send for UnsafeRegistryCell<'_> {}
+unsafe impl Sync for UnsafeRegistryCell<'_> {}
+
+impl<'w> UnsafeRegistryCell<'w> {
+    /// Creates a cell with access to the whole `registry`, borrowed for as
+    /// long as the cell is used
+    pub fn new(registry: &'w mut Registry) -> Self {
+        Self {
+            registry,
+            access: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Restricts the cell to the data of `access`
+    pub fn with_access(self, access: &'w Access) -> Self {
+        Self {
+            access: Some(access),
+            ..self
+        }
+    }
+
+    /// Returns the access set the cell is restricted to, or None if it may
+    /// access the whole registry
+    pub fn access(self) -> Option<&'w Access> {
+        self.access
+    }
+
+    /// Returns the raw pointer to the registry
+    pub fn as_ptr(self) -> *mut Registry {
+        self.registry
+    }
+
+    /// Returns the cell with a caller-chosen lifetime, for parameters that
+    /// keep it to fetch their data later
+    ///
+    /// # Safety
+    /// The registry and the access set of the cell must outlive the returned
+    /// cell.
+    pub(crate) unsafe fn with_lifetime<'a>(self) -> UnsafeRegistryCell<'a> {
+        UnsafeRegistryCell {
+            registry: self.registry,
+            access: self
+                .access
+                .map(|access| unsafe { &*(access as *const Access) }),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns an exclusive reference to the whole registry
+    ///
+    /// # Safety
+    /// Nothing else may access the registry while the reference is alive.
+    pub unsafe fn registry_mut<'a>(self) -> &'a mut Registry {
+        debug_assert!(
+            self.access.is_none_or(Access::is_exclusive),
+            "Only exclusive systems can borrow the whole registry mutably"
+        );
+        unsafe { &mut *self.registry }
+    }
+
+    /// Gets a resource and its change ticks, if it exists
+    ///
+    /// # Safety
+    /// Nothing may write the resource while the reference is alive.
+    pub unsafe fn get_resource_with_ticks<'a, R: Resource>(
+        self,
+    ) -> Option<(&'a R, ComponentTicks)> {
+        self.check_resource::<R>(false);
+        unsafe { (*self.registry).resources.get_with_ticks::<R>() }
+    }
+
+    /// Gets a mutable reference to a resource, if it exists, without marking
+    /// it as changed
+    ///
+    /// # Safety
+    /// Nothing else may access the resource while the reference is alive.
+    pub unsafe fn get_resource_mut<'a, R: Resource>(self) -> Option<&'a mut R> {
+        self.check_resource::<R>(true);
+        unsafe { (*self.registry).resources.get_unchecked_mut::<R>() }
+    }
+
+    /// Gets a mutable reference to a resource, if it exists, marking it as
+    /// changed at `tick`
+    ///
+    /// # Safety
+    /// Nothing else may access the resource while the reference is alive.
+    pub unsafe fn get_resource_mut_with_tick<'a, R: Resource>(
+        self,
+        tick: Tick,
+    ) -> Option<&'a mut R> {
+        self.check_resource::<R>(true);
+        unsafe {
+            (*self.registry)
+                .resources
+                .get_unchecked_mut_with_tick::<R>(tick)
+        }
+    }
+
+    /// Gets a non-send resource, if it exists
+    ///
+    /// # Safety
+    /// Must be called on the thread the registry was created on, and nothing
+    /// may write the resource while the reference is alive.
+    pub unsafe fn get_non_send<'a, R: 'static>(self) -> Option<&'a R> {
+        self.check_resource::<R>(false);
+        unsafe { (*self.registry).non_send.get::<R>() }
+    }
+
+    /// Gets a mutable reference to a non-send resource, if it exists
+    ///
+    /// # Safety
+    /// Must be called on the thread the registry was created on, and nothing
+    /// else may access the resource while the reference is alive.
+    pub unsafe fn get_non_send_mut<'a, R: 'static>(self) -> Option<&'a mut R> {
+        self.check_resource::<R>(true);
+        unsafe { (*addr_of_mut!((*self.registry).non_send)).get_mut::<R>() }
+    }
+
+    /// Returns a command buffer recording into the registry's queue
+    ///
+    /// # Safety
+    /// No other thread may use the command queue or read the entities of the
+    /// registry while the buffer is alive. On this thread, they may be used
+    /// between calls to the buffer.
+    pub unsafe fn commands<'a>(self) -> Commands<'a> {
+        debug_assert!(
+            self.access
+                .is_none_or(|access| access.is_exclusive() || access.uses_commands()),
+            "Commands were fetched without declaring them"
+        );
+        unsafe {
+            Commands::from_raw(
+                addr_of_mut!((*self.registry).command_queue),
+                addr_of_mut!((*self.registry).entity_manager),
+            )
+        }
+    }
+
+    /// Returns the log of removed components
+    ///
+    /// # Safety
+    /// Nothing may record removals while the reference is alive, which only
+    /// happens between systems.
+    pub unsafe fn removed_components<'a>(self) -> &'a RemovedComponentStorage {
+        unsafe { &*addr_of!((*self.registry).removed_components) }
+    }
+
+    /// Returns the map of component storages, to resolve pointers to the
+    /// storages of queries. The storages themselves aren't borrowed, see
+    /// `ComponentStorages::sparse_set_ptr`.
+    ///
+    /// # Safety
+    /// No storage may be created, removed or replaced while the reference is
+    /// alive.
+    pub(crate) unsafe fn components<'a>(self) -> &'a ComponentStorages {
+        unsafe { &*addr_of!((*self.registry).components) }
+    }
+
+    /// Checks, in debug builds, that the access set of the cell covers
+    /// reading, or writing if `write` is set, the resource `R`
+    fn check_resource<R: 'static>(self, write: bool) {
+        let Some(access) = self.access else {
+            return;
+        };
+        let type_id = TypeId::of::<R>();
+        debug_assert!(
+            access.is_exclusive()
+                || access.writes_resource(type_id)
+                || (!write && access.reads_resource(type_id)),
+            "Resource {} was {} without declaring it in the system's access",
+            std::any::type_name::<R>(),
+            if write { "written" } else { "read" }
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Score(u32);
+    impl Resource for Score {}
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Lives(u32);
+    impl Resource for Lives {}
+
+    #[test]
+    fn test_disjoint_resources_borrowed_together() {
+        let mut registry = Registry::new();
+        registry.insert_resource(Score(1));
+        registry.insert_resource(Lives(3));
+
+        let mut access = Access::new();
+        access.write_resource(TypeId::of::<Score>());
+        access.read_resource(TypeId::of::<Lives>());
+        let cell = UnsafeRegistryCell::new(&mut registry);
+        let restricted = cell.with_access(&access);
+        // SAFETY: the two references point to different resources
+        unsafe {
+            let score = restricted
+                .get_resource_mut_with_tick::<Score>(Tick::new(5))
+                .unwrap();
+            let (lives, _) = restricted.get_resource_with_ticks::<Lives>().unwrap();
+            score.0 += lives.0;
+        }
+
+        assert_eq!(registry.get_resource::<Score>(), Some(&Score(4)));
+        assert_eq!(
+            registry.resource_ticks::<Score>().unwrap().changed,
+            Tick::new(5)
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "without declaring it")]
+    fn test_undeclared_resource_is_rejected() {
+        let mut registry = Registry::new();
+        registry.insert_resource(Score(1));
+        let mut access = Access::new();
+        access.read_resource(TypeId::of::<Score>());
+
+        let cell = UnsafeRegistryCell::new(&mut registry).with_access(&access);
+        // SAFETY: nothing else accesses the registry
+        let _ = unsafe { cell.get_resource_mut::<Score>() };
+    }
+}
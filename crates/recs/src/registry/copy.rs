@@ -0,0 +1,329 @@
+use std::{any::TypeId, collections::HashSet};
+
+use crate::{
+    component::{Component, clone::EntityMap},
+    entity::Entity,
+    error::RecsError,
+    hierarchy::{Children, Parent},
+    registry::Registry,
+};
+
+type EntityPredicate = Box<dyn Fn(&Registry, Entity) -> bool>;
+
+/// Selects which entities `Registry::copy_entities_to` copies, and which of
+/// their components are left behind.
+///
+/// An empty filter copies every entity with all of its clonable components.
+#[derive(Default)]
+pub struct CopyFilter {
+    predicates: Vec<EntityPredicate>,
+    stripped: HashSet<TypeId>,
+}
+
+impl CopyFilter {
+    /// Creates a filter copying every entity
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only copies entities that have a `C` component
+    pub fn with<C: Component>(self) -> Self {
+        self.matching(|registry, entity| registry.get_component::<C>(entity).is_some())
+    }
+
+    /// Only copies entities that don't have a `C` component
+    pub fn without<C: Component>(self) -> Self {
+        self.matching(|registry, entity| registry.get_component::<C>(entity).is_none())
+    }
+
+    /// Only copies entities for which `predicate` returns true
+    pub fn matching(mut self, predicate: impl Fn(&Registry, Entity) -> bool + 'static) -> Self {
+        self.predicates.push(Box::new(predicate));
+        self
+    }
+
+    /// Leaves `C` components out of the copies
+    pub fn strip<C: Component>(mut self) -> Self {
+        self.stripped.insert(TypeId::of::<C>());
+        self
+    }
+
+    fn matches(&self, registry: &Registry, entity: Entity) -> bool {
+        self.predicates
+            .iter()
+            .all(|predicate| predicate(registry, entity))
+    }
+}
+
+impl Registry {
+    /// Registers `C` as clonable, so that `clone_entity` and
+    /// `copy_entities_to` copy it. Component types declaring
+    /// `#[component(clone)]` are registered automatically.
+    pub fn register_component_clone<C: Component + Clone>(&mut self) {
+        self.cloners.insert(|component: &C, _| component.clone());
+    }
+
+    /// Registers a custom clone function for `C`, which receives the map from
+    /// source entities to their copies so that entity references can be
+    /// remapped
+    pub fn register_component_clone_with<C: Component>(
+        &mut self,
+        clone: impl Fn(&C, &EntityMap) -> C + Send + Sync + 'static,
+    ) {
+        self.cloners.insert(clone);
+    }
+
+    /// Checks if a clone function is registered for the component type
+    pub fn has_component_clone<C: Component>(&self) -> bool {
+        self.cloners.contains::<C>()
+    }
+
+    /// Copies the entities passing `filter` into `target`, e.g. to populate an
+    /// editor preview world or a replay.
+    ///
+    /// Copies get fresh ids in `target`. Only components with a clone function
+    /// registered in this registry are copied, minus those stripped by the
+    /// filter. Components registered with `register_map_entities` in this
+    /// registry are then remapped to point at the copies, so their clone
+    /// function shouldn't remap them too. Returns the map from each copied
+    /// entity to its copy.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use recs::registry::copy::CopyFilter;
+    /// #[derive(Component, Clone)]
+    /// struct Position { x: f32 }
+    ///
+    /// #[derive(Component, Clone)]
+    /// struct AiState;
+    ///
+    /// let mut world = Registry::new();
+    /// world.register_component_clone::<Position>();
+    /// world.register_component_clone::<AiState>();
+    /// let enemy = world.spawn((Position { x: 4.0 }, AiState));
+    ///
+    /// let mut preview = Registry::new();
+    /// let map = world.copy_entities_to(&mut preview, &CopyFilter::new().strip::<AiState>());
+    ///
+    /// assert_eq!(preview.get_component::<Position>(map[&enemy]).unwrap().x, 4.0);
+    /// assert!(preview.get_component::<AiState>(map[&enemy]).is_none());
+    /// ```
+    pub fn copy_entities_to(&self, target: &mut Registry, filter: &CopyFilter) -> EntityMap {
+        let copied: Vec<(Entity, Entity)> = self
+            .entities()
+            .filter(|&entity| filter.matches(self, entity))
+            .map(|entity| (entity, target.create_entity()))
+            .collect();
+        let mut map: EntityMap = copied.iter().copied().collect();
+
+        // Every copy exists before any component is cloned, so that clone
+        // functions can remap references to any copied entity
+        for &(entity, copy) in &copied {
+            let components = self
+                .cloners
+                .clone_components(self, entity, &map, &filter.stripped);
+            self.insert_components_into(target, copy, components);
+        }
+        target.insert_required_components();
+        for &(_, copy) in &copied {
+            self.map_entities_in(target, copy, &mut map);
+        }
+        map
+    }
+
+    /// Spawns a copy of `entity` with a clone of each of its components, e.g.
+    /// to duplicate a selection in an editor, and returns the copy.
+    ///
+    /// Every component of the entity needs a clone function, see
+    /// `#[component(clone)]` and `register_component_clone`; otherwise
+    /// `NotClonable` lists the component types lacking one and nothing is
+    /// spawned. The copy is left out of the hierarchy, so `Parent` and
+    /// `Children` are skipped, and gets no GUID. Returns `InvalidEntity` if
+    /// the entity is invalid.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component, Clone)]
+    /// #[component(clone)]
+    /// struct Sprite(&'static str);
+    ///
+    /// #[derive(Component)]
+    /// struct Selected;
+    ///
+    /// let mut registry = Registry::new();
+    /// let tree = registry.spawn((Sprite("tree"),));
+    /// let copy = registry.clone_entity(tree).unwrap();
+    /// assert_eq!(registry.get_component::<Sprite>(copy).unwrap().0, "tree");
+    ///
+    /// registry.add_component(tree, Selected).unwrap();
+    /// assert!(registry.clone_entity(tree).is_err());
+    /// ```
+    pub fn clone_entity(&mut self, entity: Entity) -> Result<Entity, RecsError> {
+        if !self.is_valid(entity) {
+            return Err(RecsError::InvalidEntity(entity));
+        }
+        let skipped: HashSet<TypeId> = [TypeId::of::<Parent>(), TypeId::of::<Children>()].into();
+        let mut missing: Vec<&'static str> = self
+            .component_names
+            .iter()
+            .filter(|&(type_id, _)| {
+                !skipped.contains(&type_id)
+                    && !self.cloners.contains_id(type_id)
+                    && self.has_component_id(type_id, entity)
+            })
+            .map(|(_, name)| name)
+            .collect();
+        if !missing.is_empty() {
+            missing.sort_unstable();
+            return Err(RecsError::NotClonable(missing));
+        }
+
+        let components = self
+            .cloners
+            .clone_components(self, entity, &EntityMap::new(), &skipped);
+        let copy = self.create_entity();
+        self.insert_components(copy, components);
+        self.insert_required_components();
+        Ok(copy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::map::{EntityMapper, MapEntities};
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Name(&'static str);
+    impl Component for Name {}
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Target(Entity);
+    impl Component for Target {}
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Hidden;
+    impl Component for Hidden {}
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct NotClonable;
+    impl Component for NotClonable {}
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Followers(Vec<Entity>);
+    impl Component for Followers {}
+    impl MapEntities for Followers {
+        fn map_entities(&mut self, mapper: &mut dyn EntityMapper) {
+            self.0.map_entities(mapper);
+        }
+    }
+
+    fn world() -> Registry {
+        let mut registry = Registry::new();
+        registry.register_component_clone::<Name>();
+        registry.register_component_clone::<Hidden>();
+        registry.register_component_clone_with(|target: &Target, map| {
+            Target(map.get(&target.0).copied().unwrap_or(target.0))
+        });
+        registry
+    }
+
+    #[test]
+    fn test_copy_remaps_entity_references() {
+        let mut source = world();
+        let hunter = source.create_entity();
+        let prey = source.spawn((Name("prey"), NotClonable));
+        source.add_component(hunter, Target(prey)).unwrap();
+
+        let mut target = Registry::new();
+        target.spawn((Name("already there"),));
+        let map = source.copy_entities_to(&mut target, &CopyFilter::new());
+
+        assert_eq!(map.len(), 2);
+        assert_ne!(map[&prey], prey);
+        assert_eq!(
+            target.get_component::<Target>(map[&hunter]),
+            Some(&Target(map[&prey]))
+        );
+        assert_eq!(
+            target.get_component::<Name>(map[&prey]),
+            Some(&Name("prey"))
+        );
+        assert!(target.get_component::<NotClonable>(map[&prey]).is_none());
+    }
+
+    #[test]
+    fn test_copy_maps_registered_components() {
+        let mut source = world();
+        source.register_component_clone::<Followers>();
+        source.register_map_entities::<Followers>();
+        let follower = source.spawn((Name("follower"),));
+        let leader = source.spawn((Followers(vec![follower]),));
+
+        let mut target = Registry::new();
+        target.spawn((Name("already there"),));
+        let map = source.copy_entities_to(&mut target, &CopyFilter::new());
+        assert_eq!(
+            target.get_component::<Followers>(map[&leader]),
+            Some(&Followers(vec![map[&follower]]))
+        );
+    }
+
+    #[test]
+    fn test_copy_filter_selects_and_strips() {
+        let mut source = world();
+        source.spawn((Name("a"), Hidden));
+        source.spawn((Name("b"),));
+        source.spawn((Hidden,));
+
+        let mut target = Registry::new();
+        let filter = CopyFilter::new()
+            .with::<Name>()
+            .matching(|registry, entity| registry.get_component::<Name>(entity) != Some(&Name("b")))
+            .strip::<Hidden>();
+        let map = source.copy_entities_to(&mut target, &filter);
+
+        assert_eq!(map.len(), 1);
+        assert_eq!(target.query::<(&Name,)>().count(), 1);
+        assert_eq!(target.query::<(&Hidden,)>().count(), 0);
+
+        let mut target = Registry::new();
+        source.copy_entities_to(&mut target, &CopyFilter::new().without::<Hidden>());
+        assert_eq!(target.query::<(&Name,)>().count(), 1);
+    }
+
+    #[test]
+    fn test_clone_entity_copies_every_component() {
+        #[derive(crate::Component, Debug, Clone, PartialEq)]
+        #[component(storage = "table", clone)]
+        struct Position(i32);
+
+        let mut registry = world();
+        let parent = registry.spawn((Name("parent"),));
+        let entity = registry.spawn((Name("tree"), Position(3)));
+        registry.set_parent(entity, parent).unwrap();
+
+        let copy = registry.clone_entity(entity).unwrap();
+        assert_ne!(copy, entity);
+        assert_eq!(registry.get_component::<Name>(copy), Some(&Name("tree")));
+        assert_eq!(registry.get_component::<Position>(copy), Some(&Position(3)));
+        assert_eq!(registry.parent(copy), None);
+        assert_eq!(registry.children(parent), &[entity]);
+    }
+
+    #[test]
+    fn test_clone_entity_lists_non_clonable_components() {
+        let mut registry = world();
+        let entity = registry.spawn((Name("tree"), NotClonable));
+        let count = registry.entities().count();
+
+        let Err(RecsError::NotClonable(names)) = registry.clone_entity(entity) else {
+            panic!("expected NotClonable");
+        };
+        assert_eq!(names.len(), 1);
+        assert!(names[0].ends_with("NotClonable"));
+        assert_eq!(registry.entities().count(), count);
+    }
+}
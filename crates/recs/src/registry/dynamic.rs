@@ -0,0 +1,180 @@
+//! Components whose schema is only known at runtime, such as those defined by
+//! mods.
+//!
+//! A dynamic component type is registered with a name and a memory layout and
+//! is identified by the returned `ComponentId`. Its values are untyped bytes,
+//! `layout.size()` long, stored next to but separately from the typed
+//! components. They are removed when their entity is destroyed and can be
+//! iterated with a `DynamicQuery`.
+
+use std::alloc::Layout;
+
+use crate::{
+    component::{
+        ComponentTicks,
+        dynamic::{ComponentId, DynamicStorage},
+    },
+    entity::Entity,
+    error::RecsError,
+    registry::Registry,
+};
+
+impl Registry {
+    /// Registers a component type called `name` whose values are
+    /// `layout.size()` bytes, and returns its id.
+    ///
+    /// Registering a name again returns the existing id if the layout is the
+    /// same, and `InvalidDynamicComponent` otherwise.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use std::alloc::Layout;
+    /// let mut registry = Registry::new();
+    /// let health = registry
+    ///     .register_dynamic_component("mod::Health", Layout::new::<u32>())
+    ///     .unwrap();
+    ///
+    /// let entity = registry.create_entity();
+    /// registry.insert_dynamic(entity, health, &40u32.to_le_bytes()).unwrap();
+    /// registry.get_dynamic_mut(entity, health).unwrap()[0] += 2;
+    ///
+    /// let bytes = registry.get_dynamic(entity, health).unwrap();
+    /// assert_eq!(u32::from_le_bytes(bytes.try_into().unwrap()), 42);
+    /// assert_eq!(registry.dynamic_component_id("mod::Health"), Some(health));
+    /// ```
+    pub fn register_dynamic_component(
+        &mut self,
+        name: impl Into<String>,
+        layout: Layout,
+    ) -> Result<ComponentId, RecsError> {
+        let name = name.into();
+        match self.dynamic.id(&name) {
+            Some(id) if self.dynamic_component_layout(id) == Some(layout) => Ok(id),
+            Some(_) => Err(RecsError::InvalidDynamicComponent(format!(
+                "{name} is already registered with another layout"
+            ))),
+            None => Ok(self.dynamic.register(name, layout)),
+        }
+    }
+
+    /// Returns the id of the dynamic component registered as `name`
+    pub fn dynamic_component_id(&self, name: &str) -> Option<ComponentId> {
+        self.dynamic.id(name)
+    }
+
+    /// Returns the name a dynamic component was registered with
+    pub fn dynamic_component_name(&self, id: ComponentId) -> Option<&str> {
+        self.dynamic.get(id).map(DynamicStorage::name)
+    }
+
+    /// Returns the layout a dynamic component was registered with
+    pub fn dynamic_component_layout(&self, id: ComponentId) -> Option<Layout> {
+        self.dynamic.get(id).map(DynamicStorage::layout)
+    }
+
+    /// Inserts or overwrites the `id` component of `entity` with `value`,
+    /// which must be exactly as long as the component's layout
+    pub fn insert_dynamic(
+        &mut self,
+        entity: Entity,
+        id: ComponentId,
+        value: &[u8],
+    ) -> Result<(), RecsError> {
+        if !self.entity_manager.is_valid(entity) {
+            return Err(RecsError::InvalidEntity(entity));
+        }
+        let tick = self.change_tick();
+        let storage = self
+            .dynamic
+            .get_mut(id)
+            .ok_or(RecsError::UnknownComponentId(id))?;
+        if value.len() != storage.layout().size() {
+            return Err(RecsError::InvalidDynamicComponent(format!(
+                "{} values are {} bytes, got {}",
+                storage.name(),
+                storage.layout().size(),
+                value.len()
+            )));
+        }
+        storage.insert(entity, value, tick);
+        Ok(())
+    }
+
+    /// Returns the bytes of the `id` component of `entity`
+    pub fn get_dynamic(&self, entity: Entity, id: ComponentId) -> Option<&[u8]> {
+        if !self.entity_manager.is_valid(entity) {
+            return None;
+        }
+        self.dynamic.get(id)?.get(entity.id() as usize)
+    }
+
+    /// Returns the bytes of the `id` component of `entity` mutably, marking
+    /// it as changed
+    pub fn get_dynamic_mut(&mut self, entity: Entity, id: ComponentId) -> Option<&mut [u8]> {
+        if !self.entity_manager.is_valid(entity) {
+            return None;
+        }
+        let tick = self.change_tick();
+        self.dynamic
+            .get_mut(id)?
+            .get_mut(entity.id() as usize, tick)
+    }
+
+    /// Returns the change ticks of the `id` component of `entity`
+    pub fn dynamic_ticks(&self, entity: Entity, id: ComponentId) -> Option<ComponentTicks> {
+        if !self.entity_manager.is_valid(entity) {
+            return None;
+        }
+        self.dynamic.get(id)?.ticks(entity.id() as usize)
+    }
+
+    /// Removes the `id` component of `entity`, returning its bytes
+    pub fn remove_dynamic(&mut self, entity: Entity, id: ComponentId) -> Option<Vec<u8>> {
+        if !self.entity_manager.is_valid(entity) {
+            return None;
+        }
+        self.dynamic.get_mut(id)?.remove(entity.id() as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_are_checked_and_dropped_with_their_entity() {
+        let mut registry = Registry::new();
+        let pair = Layout::new::<[u16; 2]>();
+        let id = registry.register_dynamic_component("Pair", pair).unwrap();
+        assert_eq!(
+            registry.register_dynamic_component("Pair", pair).unwrap(),
+            id
+        );
+        assert!(matches!(
+            registry.register_dynamic_component("Pair", Layout::new::<u8>()),
+            Err(RecsError::InvalidDynamicComponent(_))
+        ));
+        assert_eq!(registry.dynamic_component_name(id), Some("Pair"));
+        assert_eq!(registry.dynamic_component_layout(id), Some(pair));
+
+        let entity = registry.create_entity();
+        assert!(matches!(
+            registry.insert_dynamic(entity, id, &[1, 2, 3]),
+            Err(RecsError::InvalidDynamicComponent(_))
+        ));
+        registry.insert_dynamic(entity, id, &[1, 2, 3, 4]).unwrap();
+        assert_eq!(registry.get_dynamic(entity, id), Some(&[1, 2, 3, 4][..]));
+        assert!(registry.dynamic_ticks(entity, id).is_some());
+
+        registry.destroy_entity(entity).unwrap();
+        let reused = registry.create_entity();
+        assert_eq!(reused.id(), entity.id());
+        assert_eq!(registry.get_dynamic(reused, id), None);
+        assert_eq!(registry.remove_dynamic(reused, id), None);
+        assert!(matches!(
+            registry.insert_dynamic(entity, id, &[0; 4]),
+            Err(RecsError::InvalidEntity(_))
+        ));
+    }
+}
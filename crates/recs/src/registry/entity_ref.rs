@@ -0,0 +1,195 @@
+use crate::{
+    component::Component,
+    entity::Entity,
+    error::RecsError,
+    registry::{Registry, bundle::ComponentBundle},
+};
+
+/// A read-only view of a single entity, returned by `Registry::entity`.
+///
+/// The entity is validated once when the view is created, so its accessors
+/// go straight to the component storages.
+#[derive(Clone, Copy)]
+pub struct EntityRef<'r> {
+    registry: &'r Registry,
+    entity: Entity,
+}
+
+impl<'r> EntityRef<'r> {
+    /// Returns the viewed entity
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    /// Returns the entity's `C` component, if it has one
+    pub fn get<C: Component>(&self) -> Option<&'r C> {
+        self.registry.get_unchecked(self.entity)
+    }
+
+    /// Checks if the entity has a `C` component
+    pub fn contains<C: Component>(&self) -> bool {
+        self.get::<C>().is_some()
+    }
+}
+
+/// A mutable view of a single entity, returned by `Registry::entity_mut`.
+///
+/// The entity is validated once when the view is created, and stays valid
+/// for as long as the view borrows the registry, unless it is despawned
+/// through the view, which consumes it.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// #[derive(Component)]
+/// struct Health(u32);
+///
+/// #[derive(Component)]
+/// struct Dead;
+///
+/// let mut registry = Registry::new();
+/// let entity = registry.spawn(Health(10));
+///
+/// let mut view = registry.entity_mut(entity).unwrap();
+/// view.get_mut::<Health>().unwrap().0 = 0;
+/// if view.get::<Health>().unwrap().0 == 0 {
+///     view.remove::<Health>();
+///     view.insert(Dead);
+/// }
+///
+/// assert!(registry.entity(entity).unwrap().contains::<Dead>());
+/// ```
+pub struct EntityMut<'r> {
+    registry: &'r mut Registry,
+    entity: Entity,
+}
+
+impl<'r> EntityMut<'r> {
+    /// Returns the viewed entity
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    /// Returns the entity's `C` component, if it has one
+    pub fn get<C: Component>(&self) -> Option<&C> {
+        self.registry.get_unchecked(self.entity)
+    }
+
+    /// Returns the entity's `C` component mutably, marking it as changed
+    pub fn get_mut<C: Component>(&mut self) -> Option<&mut C> {
+        self.registry.get_mut_unchecked(self.entity)
+    }
+
+    /// Checks if the entity has a `C` component
+    pub fn contains<C: Component>(&self) -> bool {
+        self.get::<C>().is_some()
+    }
+
+    /// Adds (or replaces) a component of the entity
+    pub fn insert<C: Component>(&mut self, component: C) -> &mut Self {
+        self.registry.insert_unchecked(self.entity, component);
+        self.registry.insert_required_components();
+        self
+    }
+
+    /// Adds every component of `bundle` to the entity
+    pub fn insert_bundle<B: ComponentBundle>(&mut self, bundle: B) -> &mut Self {
+        bundle
+            .add_to_entity(self.registry, self.entity)
+            .expect("entity views always refer to a valid entity");
+        self.registry.insert_required_components();
+        self
+    }
+
+    /// Removes the entity's `C` component and returns it, if it had one
+    pub fn remove<C: Component>(&mut self) -> Option<C> {
+        self.registry.remove_unchecked(self.entity)
+    }
+
+    /// Returns a read-only view of the entity
+    pub fn as_ref(&self) -> EntityRef<'_> {
+        EntityRef {
+            registry: self.registry,
+            entity: self.entity,
+        }
+    }
+
+    /// Destroys the entity and all of its components
+    pub fn despawn(self) {
+        self.registry
+            .destroy_entity(self.entity)
+            .expect("entity views always refer to a valid entity");
+    }
+}
+
+impl Registry {
+    /// Returns a read-only view of `entity`, or an error if it is invalid
+    pub fn entity(&self, entity: Entity) -> Result<EntityRef<'_>, RecsError> {
+        if !self.is_valid(entity) {
+            return Err(RecsError::InvalidEntity(entity));
+        }
+        Ok(EntityRef {
+            registry: self,
+            entity,
+        })
+    }
+
+    /// Returns a mutable view of `entity`, or an error if it is invalid
+    pub fn entity_mut(&mut self, entity: Entity) -> Result<EntityMut<'_>, RecsError> {
+        if !self.is_valid(entity) {
+            return Err(RecsError::InvalidEntity(entity));
+        }
+        Ok(EntityMut {
+            registry: self,
+            entity,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::removed::RemovedComponents;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    impl Component for Position {}
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+    impl Component for Velocity {}
+
+    #[test]
+    fn test_entity_views() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn(Position(1));
+
+        let mut view = registry.entity_mut(entity).unwrap();
+        view.insert(Velocity(2)).insert(Position(3));
+        assert_eq!(view.get_mut::<Position>(), Some(&mut Position(3)));
+        assert_eq!(view.remove::<Velocity>(), Some(Velocity(2)));
+        assert_eq!(view.remove::<Velocity>(), None);
+        assert!(!view.as_ref().contains::<Velocity>());
+
+        let view = registry.entity(entity).unwrap();
+        assert_eq!(view.id(), entity);
+        assert_eq!(view.get::<Position>(), Some(&Position(3)));
+        assert!(!view.contains::<Velocity>());
+
+        let removed: Vec<_> = RemovedComponents::<Velocity>::new(
+            registry.removed_components.get::<Velocity>(),
+            Default::default(),
+            registry.change_tick(),
+        )
+        .iter()
+        .collect();
+        assert_eq!(removed, vec![entity]);
+
+        registry.entity_mut(entity).unwrap().despawn();
+        assert!(matches!(
+            registry.entity(entity),
+            Err(RecsError::InvalidEntity(_))
+        ));
+        assert!(registry.entity_mut(entity).is_err());
+    }
+}
@@ -0,0 +1,557 @@
+//! Saving and loading a selection of entities through serde.
+//!
+//! Unlike `Registry::serialize`, which writes the whole registry, a
+//! `SceneBuilder` picks the entities to save, such as those marked
+//! `Persistent`, and which of their serializable components to include. The
+//! result is written in the scene format, with entity ids as scene-local ids:
+//!
+//! ```text
+//! (
+//!     entities: [
+//!         (id: 3, parent: None, components: { "Chest": (gold: 10) }),
+//!         (id: 5, parent: Some(3), components: { "Lock": (broken: false) }),
+//!     ],
+//! )
+//! ```
+//!
+//! `Registry::spawn_extracted` loads it from any serde format, spawning fresh
+//! entities as `Registry::spawn_scene` does for RON scenes.
+
+use std::{any::TypeId, collections::HashSet, fmt};
+
+use serde::{
+    Deserializer, Serialize, Serializer,
+    de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, SerializeStruct},
+};
+
+use crate::{
+    component::Component,
+    entity::Entity,
+    registry::{
+        Registry,
+        serialize::{ComponentsSeed, EntityMap, SerializableComponent},
+    },
+};
+
+/// Selects the entities and component types of an `ExtractedScene`.
+///
+/// Entities are written in the order they were selected. Every serializable
+/// component is included unless the builder allows or denies types.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use recs::registry::extract::SceneBuilder;
+/// #[derive(Component, serde::Serialize, serde::Deserialize)]
+/// struct Persistent;
+///
+/// #[derive(Component, serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+/// struct Gold(u32);
+///
+/// let mut registry = Registry::new();
+/// registry.register_serializable_as::<Persistent>("Persistent");
+/// registry.register_serializable_as::<Gold>("Gold");
+/// registry.spawn((Persistent, Gold(10)));
+/// registry.spawn(Gold(99));
+///
+/// let scene = SceneBuilder::new(&registry).extract_with::<Persistent>().build();
+/// let saved = serde_json::to_string(&scene).unwrap();
+///
+/// let mut loaded = Registry::new();
+/// loaded.register_serializable_as::<Persistent>("Persistent");
+/// loaded.register_serializable_as::<Gold>("Gold");
+/// let spawned = loaded
+///     .spawn_extracted(&mut serde_json::Deserializer::from_str(&saved))
+///     .unwrap();
+/// assert_eq!(spawned.len(), 1);
+/// let (_, chest) = spawned.iter().next().unwrap();
+/// assert_eq!(loaded.get_component::<Gold>(chest), Some(&Gold(10)));
+/// ```
+pub struct SceneBuilder<'r> {
+    registry: &'r Registry,
+    entities: Vec<Entity>,
+    selected: HashSet<Entity>,
+    allowed: Option<HashSet<TypeId>>,
+    denied: HashSet<TypeId>,
+}
+
+impl<'r> SceneBuilder<'r> {
+    /// Creates a builder selecting no entities from `registry`
+    pub fn new(registry: &'r Registry) -> Self {
+        Self {
+            registry,
+            entities: Vec::new(),
+            selected: HashSet::new(),
+            allowed: None,
+            denied: HashSet::new(),
+        }
+    }
+
+    /// Selects `entities`, skipping invalid and already selected ones
+    pub fn extract_entities(mut self, entities: impl IntoIterator<Item = Entity>) -> Self {
+        for entity in entities {
+            if self.registry.is_valid(entity) && self.selected.insert(entity) {
+                self.entities.push(entity);
+            }
+        }
+        self
+    }
+
+    /// Selects every entity that has a `C` component
+    pub fn extract_with<C: Component>(self) -> Self {
+        self.extract_matching(|registry, entity| registry.get_component::<C>(entity).is_some())
+    }
+
+    /// Selects every entity for which `predicate` returns true
+    pub fn extract_matching(self, predicate: impl Fn(&Registry, Entity) -> bool) -> Self {
+        let registry = self.registry;
+        let entities: Vec<Entity> = registry
+            .entities()
+            .filter(|&entity| predicate(registry, entity))
+            .collect();
+        self.extract_entities(entities)
+    }
+
+    /// Only includes the component types allowed this way
+    pub fn allow<C: Component>(mut self) -> Self {
+        self.allowed
+            .get_or_insert_with(HashSet::new)
+            .insert(TypeId::of::<C>());
+        self
+    }
+
+    /// Leaves `C` components out
+    pub fn deny<C: Component>(mut self) -> Self {
+        self.denied.insert(TypeId::of::<C>());
+        self
+    }
+
+    /// Returns the selected entities with their chosen components, ready to
+    /// be serialized
+    pub fn build(self) -> ExtractedScene<'r> {
+        let components = self
+            .registry
+            .serializable
+            .components
+            .iter()
+            .filter(|(_, component)| {
+                self.allowed
+                    .as_ref()
+                    .is_none_or(|allowed| allowed.contains(&component.type_id))
+                    && !self.denied.contains(&component.type_id)
+            })
+            .map(|(&name, &component)| (name, component))
+            .collect();
+        ExtractedScene {
+            registry: self.registry,
+            entities: self.entities,
+            selected: self.selected,
+            components,
+        }
+    }
+}
+
+/// A selection of entities and components of a registry, serialized in the
+/// scene format. Built with a `SceneBuilder`.
+pub struct ExtractedScene<'r> {
+    registry: &'r Registry,
+    entities: Vec<Entity>,
+    selected: HashSet<Entity>,
+    components: Vec<(&'static str, SerializableComponent)>,
+}
+
+impl ExtractedScene<'_> {
+    /// Returns the selected entities, in the order they are written
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+}
+
+impl Serialize for ExtractedScene<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut scene = serializer.serialize_struct("Scene", 1)?;
+        scene.serialize_field("entities", &EntitiesDoc(self))?;
+        scene.end()
+    }
+}
+
+struct EntitiesDoc<'a, 'r>(&'a ExtractedScene<'r>);
+
+impl Serialize for EntitiesDoc<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let scene = self.0;
+        serializer.collect_seq(
+            scene
+                .entities
+                .iter()
+                .map(|&entity| EntityDoc(scene, entity)),
+        )
+    }
+}
+
+struct EntityDoc<'a, 'r>(&'a ExtractedScene<'r>, Entity);
+
+impl Serialize for EntityDoc<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Self(scene, entity) = *self;
+        // Parents left out of the selection are dropped
+        let parent = scene
+            .registry
+            .parent(entity)
+            .filter(|parent| scene.selected.contains(parent))
+            .map(|parent| parent.id());
+
+        let mut doc = serializer.serialize_struct("SceneEntity", 3)?;
+        doc.serialize_field("id", &entity.id())?;
+        doc.serialize_field("parent", &parent)?;
+        doc.serialize_field("components", &ComponentsDoc(scene, entity))?;
+        doc.end()
+    }
+}
+
+struct ComponentsDoc<'a, 'r>(&'a ExtractedScene<'r>, Entity);
+
+impl Serialize for ComponentsDoc<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Self(scene, entity) = *self;
+        let mut map = serializer.serialize_map(None)?;
+        for (name, component) in &scene.components {
+            if let Some(value) = (component.serialize)(scene.registry, entity) {
+                map.serialize_entry(name, &value)?;
+            }
+        }
+        map.end()
+    }
+}
+
+impl Registry {
+    /// Spawns fresh entities for every entity of a document written from an
+    /// `ExtractedScene`, sets their parents, remaps the entities referenced by
+    /// their components and returns the entity spawned for each saved id.
+    ///
+    /// Components must be registered with `register_serializable` under the
+    /// name they were saved with. Nothing is left spawned if the document is
+    /// invalid.
+    pub fn spawn_extracted<'de, D: Deserializer<'de>>(
+        &mut self,
+        deserializer: D,
+    ) -> Result<EntityMap, D::Error> {
+        let mut loaded = LoadedScene::default();
+        let result = deserializer
+            .deserialize_struct(
+                "Scene",
+                &["entities"],
+                SceneVisitor {
+                    registry: self,
+                    loaded: &mut loaded,
+                },
+            )
+            .and_then(|()| self.link_extracted(&mut loaded).map_err(de::Error::custom));
+
+        if result.is_err() {
+            for (_, entity) in loaded.map.iter() {
+                let _ = self.destroy_entity(entity);
+            }
+        }
+        result.map(|()| loaded.map)
+    }
+
+    /// Sets the parents of the entities spawned for a document and remaps the
+    /// entities referenced by their components
+    fn link_extracted(&mut self, loaded: &mut LoadedScene) -> Result<(), String> {
+        for &(entity, parent) in &loaded.parents {
+            let parent = loaded
+                .map
+                .get(parent)
+                .ok_or_else(|| format!("parent {parent} is not an entity of the scene"))?;
+            self.set_parent(entity, parent)
+                .map_err(|error| error.to_string())?;
+        }
+        let entities: Vec<Entity> = loaded.map.iter().map(|(_, entity)| entity).collect();
+        for entity in entities {
+            self.map_entities(entity, &mut loaded.map);
+        }
+        Ok(())
+    }
+}
+
+/// What has been read of a document so far
+#[derive(Default)]
+struct LoadedScene {
+    map: EntityMap,
+    /// Spawned entities with the saved id of their parent
+    parents: Vec<(Entity, u32)>,
+}
+
+struct SceneVisitor<'a> {
+    registry: &'a mut Registry,
+    loaded: &'a mut LoadedScene,
+}
+
+impl<'de> Visitor<'de> for SceneVisitor<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a scene")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        seq.next_element_seed(EntitiesSeed {
+            registry: self.registry,
+            loaded: self.loaded,
+        })?
+        .ok_or_else(|| de::Error::invalid_length(0, &"a scene"))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "entities" {
+                map.next_value_seed(EntitiesSeed {
+                    registry: &mut *self.registry,
+                    loaded: &mut *self.loaded,
+                })?;
+            } else {
+                map.next_value::<de::IgnoredAny>()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct EntitiesSeed<'a> {
+    registry: &'a mut Registry,
+    loaded: &'a mut LoadedScene,
+}
+
+impl<'de> DeserializeSeed<'de> for EntitiesSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for EntitiesSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of entities")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while seq
+            .next_element_seed(EntitySeed {
+                registry: &mut *self.registry,
+                loaded: &mut *self.loaded,
+            })?
+            .is_some()
+        {}
+        Ok(())
+    }
+}
+
+struct EntitySeed<'a> {
+    registry: &'a mut Registry,
+    loaded: &'a mut LoadedScene,
+}
+
+impl EntitySeed<'_> {
+    /// Spawns the entity for the saved id `id`
+    fn spawn<E: de::Error>(&mut self, id: u32) -> Result<Entity, E> {
+        if self.loaded.map.get(id).is_some() {
+            return Err(E::custom(format!("entity id {id} is used twice")));
+        }
+        let entity = self.registry.create_entity();
+        self.loaded.map.insert(id, entity);
+        Ok(entity)
+    }
+
+    fn finish(self, entity: Entity, parent: Option<u32>) {
+        if let Some(parent) = parent {
+            self.loaded.parents.push((entity, parent));
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for EntitySeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_struct("SceneEntity", &["id", "parent", "components"], self)
+    }
+}
+
+impl<'de> Visitor<'de> for EntitySeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a scene entity")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<(), A::Error> {
+        let id = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let parent = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let entity = self.spawn(id)?;
+        seq.next_element_seed(ComponentsSeed {
+            registry: &mut *self.registry,
+            entity,
+        })?
+        .ok_or_else(|| de::Error::invalid_length(2, &"a scene entity"))?;
+        self.finish(entity, parent);
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<(), A::Error> {
+        let (mut entity, mut parent) = (None, None);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "id" => entity = Some(self.spawn(map.next_value()?)?),
+                "parent" => parent = map.next_value()?,
+                "components" => {
+                    let Some(entity) = entity else {
+                        return Err(de::Error::custom("`components` must come after `id`"));
+                    };
+                    map.next_value_seed(ComponentsSeed {
+                        registry: &mut *self.registry,
+                        entity,
+                    })?;
+                }
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+        let entity = entity.ok_or_else(|| de::Error::missing_field("id"))?;
+        self.finish(entity, parent);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::map::{EntityMapper, MapEntities};
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Persistent;
+    impl Component for Persistent {}
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Name(String);
+    impl Component for Name {}
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Cache(u32);
+    impl Component for Cache {}
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Owner(Entity);
+    impl Component for Owner {}
+    impl MapEntities for Owner {
+        fn map_entities(&mut self, mapper: &mut dyn EntityMapper) {
+            self.0.map_entities(mapper);
+        }
+    }
+
+    fn new_registry() -> Registry {
+        let mut registry = Registry::new();
+        registry.register_serializable_as::<Persistent>("Persistent");
+        registry.register_serializable_as::<Name>("Name");
+        registry.register_serializable_as::<Cache>("Cache");
+        registry.register_serializable_as::<Owner>("Owner");
+        registry.register_map_entities::<Owner>();
+        registry
+    }
+
+    #[test]
+    fn test_only_selected_entities_and_components_are_saved() {
+        let mut registry = new_registry();
+        let house = registry.spawn((Persistent, Name("house".into()), Cache(1)));
+        let chest = registry.spawn((Persistent, Owner(house)));
+        let particle = registry.spawn((Name("smoke".into()), Cache(2)));
+        registry.set_parent(chest, house).unwrap();
+        registry.set_parent(particle, house).unwrap();
+
+        let scene = SceneBuilder::new(&registry)
+            .extract_with::<Persistent>()
+            .extract_entities([house])
+            .deny::<Cache>()
+            .build();
+        assert_eq!(scene.entities(), &[house, chest]);
+        let saved = serde_json::to_string(&scene).unwrap();
+        assert!(!saved.contains("Cache") && !saved.contains("smoke"));
+
+        let mut loaded = new_registry();
+        loaded.spawn(Name("existing".into()));
+        let map = loaded
+            .spawn_extracted(&mut serde_json::Deserializer::from_str(&saved))
+            .unwrap();
+        let (new_house, new_chest) = (map.get(house.id()).unwrap(), map.get(chest.id()).unwrap());
+        assert_eq!(loaded.entities().count(), 3);
+        assert_eq!(
+            loaded.get_component::<Name>(new_house),
+            Some(&Name("house".into()))
+        );
+        assert!(loaded.get_component::<Cache>(new_house).is_none());
+        assert_eq!(
+            loaded.get_component::<Owner>(new_chest),
+            Some(&Owner(new_house))
+        );
+        assert_eq!(loaded.children(new_house), &[new_chest]);
+
+        let names_only = SceneBuilder::new(&registry)
+            .extract_matching(|registry, entity| registry.parent(entity).is_some())
+            .allow::<Name>()
+            .build();
+        let saved = serde_json::to_string(&names_only).unwrap();
+        assert_eq!(
+            saved,
+            r#"{"entities":[{"id":1,"parent":null,"components":{}},{"id":2,"parent":null,"components":{"Name":"smoke"}}]}"#
+        );
+    }
+
+    #[test]
+    fn test_invalid_documents_spawn_nothing() {
+        let mut registry = new_registry();
+        let invalid = [
+            r#"{"entities":[{"id":0},{"id":0}]}"#,
+            r#"{"entities":[{"id":0,"parent":4}]}"#,
+            r#"{"entities":[{"id":0,"parent":1},{"id":1,"parent":0}]}"#,
+            r#"{"entities":[{"id":0},{"id":1,"components":{"Velocity":1}}]}"#,
+        ];
+        for text in invalid {
+            let result = registry.spawn_extracted(&mut serde_json::Deserializer::from_str(text));
+            assert!(result.is_err(), "{text}");
+            assert_eq!(registry.entities().count(), 0, "{text}");
+        }
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn test_ron_output_is_a_scene() {
+        let mut registry = new_registry();
+        let house = registry.spawn((Persistent, Name("house".into())));
+        let chest = registry.spawn((Persistent, Owner(house)));
+        registry.set_parent(chest, house).unwrap();
+
+        let scene = SceneBuilder::new(&registry)
+            .extract_with::<Persistent>()
+            .build();
+        let saved = ron::to_string(&scene).unwrap();
+        let scene = crate::scene::Scene::from_ron(&saved).unwrap();
+
+        let map = registry.spawn_scene(&scene).unwrap();
+        let (new_house, new_chest) = (map.get(house.id()).unwrap(), map.get(chest.id()).unwrap());
+        assert_eq!(
+            registry.get_component::<Owner>(new_chest),
+            Some(&Owner(new_house))
+        );
+        assert_eq!(registry.parent(new_chest), Some(new_house));
+    }
+}
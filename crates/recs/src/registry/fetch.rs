@@ -0,0 +1,314 @@
+use std::any::TypeId;
+
+use crate::{component::Component, entity::Entity, registry::Registry};
+
+/// A tuple of component types fetched together with
+/// `Registry::get_components`.
+///
+/// Implemented for tuples of up to 16 component types.
+pub trait ComponentSet {
+    /// Shared references to the components of one entity
+    type Refs<'r>;
+
+    /// Fetches the components of `entity`, which must be valid, or None if it
+    /// lacks one of them
+    fn fetch(registry: &Registry, entity: Entity) -> Option<Self::Refs<'_>>;
+}
+
+macro_rules! impl_component_set_for_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: Component),+> ComponentSet for ($($name,)+) {
+            type Refs<'r> = ($(&'r $name,)+);
+
+            fn fetch(registry: &Registry, entity: Entity) -> Option<Self::Refs<'_>> {
+                Some(($(registry.get_unchecked::<$name>(entity)?,)+))
+            }
+        }
+    };
+}
+
+impl_component_set_for_tuple!(C0);
+impl_component_set_for_tuple!(C0, C1);
+impl_component_set_for_tuple!(C0, C1, C2);
+impl_component_set_for_tuple!(C0, C1, C2, C3);
+impl_component_set_for_tuple!(C0, C1, C2, C3, C4);
+impl_component_set_for_tuple!(C0, C1, C2, C3, C4, C5);
+impl_component_set_for_tuple!(C0, C1, C2, C3, C4, C5, C6);
+impl_component_set_for_tuple!(C0, C1, C2, C3, C4, C5, C6, C7);
+impl_component_set_for_tuple!(C0, C1, C2, C3, C4, C5, C6, C7, C8);
+impl_component_set_for_tuple!(C0, C1, C2, C3, C4, C5, C6, C7, C8, C9);
+impl_component_set_for_tuple!(C0, C1, C2, C3, C4, C5, C6, C7, C8, C9, C10);
+impl_component_set_for_tuple!(C0, C1, C2, C3, C4, C5, C6, C7, C8, C9, C10, C11);
+impl_component_set_for_tuple!(C0, C1, C2, C3, C4, C5, C6, C7, C8, C9, C10, C11, C12);
+impl_component_set_for_tuple!(C0, C1, C2, C3, C4, C5, C6, C7, C8, C9, C10, C11, C12, C13);
+impl_component_set_for_tuple!(
+    C0, C1, C2, C3, C4, C5, C6, C7, C8, C9, C10, C11, C12, C13, C14
+);
+impl_component_set_for_tuple!(
+    C0, C1, C2, C3, C4, C5, C6, C7, C8, C9, C10, C11, C12, C13, C14, C15
+);
+
+/// A shared or mutable reference to a component, fetched by
+/// `Registry::get_components_mut`
+pub trait ComponentRef {
+    type Component: Component;
+    type Ref<'r>;
+
+    /// Fetches the component of `entity`, which must be valid and have it.
+    ///
+    /// Mutable references mark the component as changed.
+    ///
+    /// # Safety
+    /// `registry` must point to a live Registry for the whole lifetime `'r`,
+    /// and no other reference to the same component may be alive at the same
+    /// time.
+    unsafe fn fetch<'r>(registry: *mut Registry, entity: Entity) -> Option<Self::Ref<'r>>;
+}
+
+impl<C: Component> ComponentRef for &C {
+    type Component = C;
+    type Ref<'r> = &'r C;
+
+    unsafe fn fetch<'r>(registry: *mut Registry, entity: Entity) -> Option<Self::Ref<'r>> {
+        unsafe { (*registry).get_unchecked(entity) }
+    }
+}
+
+impl<C: Component> ComponentRef for &mut C {
+    type Component = C;
+    type Ref<'r> = &'r mut C;
+
+    unsafe fn fetch<'r>(registry: *mut Registry, entity: Entity) -> Option<Self::Ref<'r>> {
+        unsafe { (*registry).get_mut_unchecked(entity) }
+    }
+}
+
+/// A tuple of `ComponentRef`s fetched together with
+/// `Registry::get_components_mut`.
+///
+/// Implemented for tuples of up to 16 references.
+pub trait ComponentRefSet {
+    /// The references to the components of one entity
+    type Refs<'r>;
+
+    /// Returns the referenced component types, in tuple order
+    fn type_ids() -> Vec<TypeId>;
+
+    /// Fetches the components of `entity`, which must be valid, or None if it
+    /// lacks one of them
+    ///
+    /// # Safety
+    /// Same as `ComponentRef::fetch`, and the component types must be
+    /// distinct.
+    unsafe fn fetch<'r>(registry: *mut Registry, entity: Entity) -> Option<Self::Refs<'r>>;
+}
+
+macro_rules! impl_component_ref_set_for_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: ComponentRef),+> ComponentRefSet for ($($name,)+) {
+            type Refs<'r> = ($($name::Ref<'r>,)+);
+
+            fn type_ids() -> Vec<TypeId> {
+                vec![$(TypeId::of::<$name::Component>()),+]
+            }
+
+            unsafe fn fetch<'r>(registry: *mut Registry, entity: Entity) -> Option<Self::Refs<'r>> {
+                unsafe {
+                    // Check membership first so that mutable references only
+                    // mark their component changed when the whole tuple matches
+                    if $(!(*registry).has_component_id(TypeId::of::<$name::Component>(), entity))||+ {
+                        return None;
+                    }
+                    Some(($($name::fetch(registry, entity)?,)+))
+                }
+            }
+        }
+    };
+}
+
+impl_component_ref_set_for_tuple!(R0);
+impl_component_ref_set_for_tuple!(R0, R1);
+impl_component_ref_set_for_tuple!(R0, R1, R2);
+impl_component_ref_set_for_tuple!(R0, R1, R2, R3);
+impl_component_ref_set_for_tuple!(R0, R1, R2, R3, R4);
+impl_component_ref_set_for_tuple!(R0, R1, R2, R3, R4, R5);
+impl_component_ref_set_for_tuple!(R0, R1, R2, R3, R4, R5, R6);
+impl_component_ref_set_for_tuple!(R0, R1, R2, R3, R4, R5, R6, R7);
+impl_component_ref_set_for_tuple!(R0, R1, R2, R3, R4, R5, R6, R7, R8);
+impl_component_ref_set_for_tuple!(R0, R1, R2, R3, R4, R5, R6, R7, R8, R9);
+impl_component_ref_set_for_tuple!(R0, R1, R2, R3, R4, R5, R6, R7, R8, R9, R10);
+impl_component_ref_set_for_tuple!(R0, R1, R2, R3, R4, R5, R6, R7, R8, R9, R10, R11);
+impl_component_ref_set_for_tuple!(R0, R1, R2, R3, R4, R5, R6, R7, R8, R9, R10, R11, R12);
+impl_component_ref_set_for_tuple!(R0, R1, R2, R3, R4, R5, R6, R7, R8, R9, R10, R11, R12, R13);
+impl_component_ref_set_for_tuple!(
+    R0, R1, R2, R3, R4, R5, R6, R7, R8, R9, R10, R11, R12, R13, R14
+);
+impl_component_ref_set_for_tuple!(
+    R0, R1, R2, R3, R4, R5, R6, R7, R8, R9, R10, R11, R12, R13, R14, R15
+);
+
+impl Registry {
+    /// Gets several components of `entity` at once, checking its validity
+    /// only once.
+    ///
+    /// Returns None if the entity is invalid or lacks one of the components.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Position { x: f32 }
+    ///
+    /// #[derive(Component)]
+    /// struct Velocity { dx: f32 }
+    ///
+    /// let mut registry = Registry::new();
+    /// let entity = registry.spawn((Position { x: 1.0 }, Velocity { dx: 2.0 }));
+    ///
+    /// let (position, velocity) = registry
+    ///     .get_components::<(Position, Velocity)>(entity)
+    ///     .unwrap();
+    /// assert_eq!(position.x + velocity.dx, 3.0);
+    /// ```
+    pub fn get_components<S: ComponentSet>(&self, entity: Entity) -> Option<S::Refs<'_>> {
+        if !self.entity_manager.is_valid(entity) {
+            return None;
+        }
+        S::fetch(self, entity)
+    }
+
+    /// Gets shared and mutable references to several components of `entity`
+    /// at once, e.g. `(&mut Position, &Velocity)`.
+    ///
+    /// Returns None if the entity is invalid or lacks one of the components,
+    /// in which case nothing is marked as changed.
+    ///
+    /// # Panics
+    /// Panics if `S` references the same component type twice.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Position { x: f32 }
+    ///
+    /// #[derive(Component)]
+    /// struct Velocity { dx: f32 }
+    ///
+    /// let mut registry = Registry::new();
+    /// let entity = registry.spawn((Position { x: 1.0 }, Velocity { dx: 2.0 }));
+    ///
+    /// let (position, velocity) = registry
+    ///     .get_components_mut::<(&mut Position, &Velocity)>(entity)
+    ///     .unwrap();
+    /// position.x += velocity.dx;
+    /// assert_eq!(registry.get_component::<Position>(entity).unwrap().x, 3.0);
+    /// ```
+    pub fn get_components_mut<S: ComponentRefSet>(
+        &mut self,
+        entity: Entity,
+    ) -> Option<S::Refs<'_>> {
+        let types = S::type_ids();
+        let mut distinct = types.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(
+            distinct.len(),
+            types.len(),
+            "get_components_mut can't borrow the same component twice"
+        );
+
+        if !self.entity_manager.is_valid(entity) {
+            return None;
+        }
+        // SAFETY: the registry is borrowed mutably for the lifetime of the
+        // references, and the component types are distinct
+        unsafe { S::fetch(self, entity) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::Changed;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+    impl Component for Health {}
+
+    #[derive(Debug, PartialEq)]
+    struct Armor(u32);
+    impl Component for Armor {}
+
+    #[derive(Debug, PartialEq)]
+    struct Position;
+    impl Component for Position {}
+
+    #[derive(Debug, PartialEq)]
+    struct Stacked(u32);
+    impl Component for Stacked {
+        const STORAGE: crate::component::StorageType = crate::component::StorageType::Table;
+    }
+
+    #[test]
+    fn test_get_components() {
+        let mut registry = Registry::new();
+        let full = registry.spawn((Health(3), Armor(1), Stacked(2)));
+        let bare = registry.spawn((Health(5),));
+
+        assert_eq!(
+            registry.get_components::<(Health, Stacked, Armor)>(full),
+            Some((&Health(3), &Stacked(2), &Armor(1)))
+        );
+        assert_eq!(
+            registry.get_components::<(Health,)>(bare),
+            Some((&Health(5),))
+        );
+        assert_eq!(registry.get_components::<(Health, Armor)>(bare), None);
+
+        registry.destroy_entity(full).unwrap();
+        assert_eq!(registry.get_components::<(Health,)>(full), None);
+    }
+
+    #[test]
+    fn test_get_components_mut() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn((Health(3), Armor(1), Stacked(2)));
+        registry.clear_trackers();
+
+        let (health, stacked, armor) = registry
+            .get_components_mut::<(&mut Health, &mut Stacked, &Armor)>(entity)
+            .unwrap();
+        health.0 += armor.0;
+        stacked.0 += armor.0;
+        assert_eq!(
+            registry.get_components::<(Health, Stacked)>(entity),
+            Some((&Health(4), &Stacked(3)))
+        );
+        let changed = |registry: &mut Registry| {
+            registry
+                .query_filtered::<(&Health,), Changed<Health>>()
+                .count()
+                + registry
+                    .query_filtered::<(&Armor,), Changed<Armor>>()
+                    .count()
+        };
+        assert_eq!(changed(&mut registry), 1);
+
+        registry.clear_trackers();
+        assert!(
+            registry
+                .get_components_mut::<(&mut Health, &mut Position)>(entity)
+                .is_none()
+        );
+        assert_eq!(changed(&mut registry), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "same component twice")]
+    fn test_get_components_mut_rejects_aliasing() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn((Health(3),));
+        registry.get_components_mut::<(&mut Health, &Health)>(entity);
+    }
+}
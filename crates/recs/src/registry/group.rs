@@ -0,0 +1,471 @@
+//! EnTT-style owning groups.
+//!
+//! A group owns the storages of a set of component types and keeps them
+//! sorted: the entities having every owned component come first, in the same
+//! order in each storage. Iterating the group is then a straight walk over the
+//! packed prefix of every storage, without any sparse lookup. Each owned
+//! storage is reordered as components are added and removed, so a component
+//! type can only be owned by a single group.
+
+use std::any::TypeId;
+
+use crate::{
+    component::{Component, ComponentStorage, sparse_set::SparseSet, storages::StorageId},
+    entity::{Disabled, Entity},
+    error::RecsError,
+    query::ComponentStorages,
+    registry::Registry,
+    tick::Tick,
+    type_map::TypeIdMap,
+};
+
+/// The storages owned by a group, and how many entities it has
+struct OwningGroup {
+    types: Vec<TypeId>,
+    /// The ids of the owned storages, in the order of `types`
+    storages: Vec<StorageId>,
+    /// Number of entities packed at the front of every owned storage
+    len: usize,
+}
+
+impl OwningGroup {
+    /// Moves the entity with `id` into the packed prefix if it has every owned
+    /// component
+    fn join(&mut self, components: &mut ComponentStorages, id: usize) {
+        for (index, &storage) in self.storages.iter().enumerate() {
+            match owned(components, storage).dense_index_of(id) {
+                Some(position) if index == 0 && position < self.len => return,
+                Some(_) => {}
+                None => return,
+            }
+        }
+        for &storage in &self.storages {
+            let storage = owned_mut(components, storage);
+            let position = storage
+                .dense_index_of(id)
+                .expect("The entity has every component");
+            storage.swap_dense(position, self.len);
+        }
+        self.len += 1;
+    }
+
+    /// Moves the entity with `id` out of the packed prefix if it's in it
+    fn leave(&mut self, components: &mut ComponentStorages, id: usize) {
+        let in_group = owned(components, self.storages[0])
+            .dense_index_of(id)
+            .is_some_and(|position| position < self.len);
+        if !in_group {
+            return;
+        }
+        self.len -= 1;
+        for &storage in &self.storages {
+            let storage = owned_mut(components, storage);
+            let position = storage
+                .dense_index_of(id)
+                .expect("The entity has every component");
+            storage.swap_dense(position, self.len);
+        }
+    }
+
+    /// Packs every entity having all the owned components from scratch
+    fn rebuild(&mut self, components: &mut ComponentStorages) {
+        self.len = 0;
+        let entities = owned(components, self.storages[0]).entities().to_vec();
+        for entity in entities {
+            self.join(components, entity.id() as usize);
+        }
+    }
+}
+
+fn owned(components: &ComponentStorages, id: StorageId) -> &dyn ComponentStorage {
+    components.get_by_id(id).expect("Owned storages exist")
+}
+
+fn owned_mut(components: &mut ComponentStorages, id: StorageId) -> &mut dyn ComponentStorage {
+    components.get_by_id_mut(id).expect("Owned storages exist")
+}
+
+/// The groups of a registry
+#[derive(Default)]
+pub(crate) struct Groups {
+    groups: Vec<OwningGroup>,
+    /// The group owning each component type
+    owners: TypeIdMap<usize>,
+}
+
+impl Groups {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// A tuple of component types owned by a group
+pub trait GroupComponents {
+    /// Shared references to the components of one entity
+    type Refs<'g>;
+    /// Mutable references to the components of one entity
+    type Muts<'g>;
+    /// Pointers to the owned storages
+    type Storages: Copy + 'static;
+
+    /// Returns the owned component types, in tuple order
+    fn type_ids() -> Vec<TypeId>;
+
+    /// Returns the owned storages, creating the missing ones
+    fn storages(registry: &mut Registry) -> Self::Storages;
+
+    /// Returns the entity at position `index` of the owned storages
+    ///
+    /// # Safety
+    /// The storages must be alive, and `index` lower than the group length.
+    unsafe fn entity(storages: Self::Storages, index: usize) -> Entity;
+
+    /// Returns the position of the entity with `id` in the owned storages
+    ///
+    /// # Safety
+    /// The storages must be alive.
+    unsafe fn index_of(storages: Self::Storages, id: usize) -> Option<usize>;
+
+    /// Returns the components at position `index` of the owned storages
+    ///
+    /// # Safety
+    /// Same as `entity`, and no mutable reference to the components may be
+    /// alive for `'g`.
+    unsafe fn get<'g>(storages: Self::Storages, index: usize) -> Self::Refs<'g>;
+
+    /// Returns the components at position `index` of the owned storages
+    /// mutably, marking them as changed at `tick`
+    ///
+    /// # Safety
+    /// Same as `entity`, and no other reference to the components may be
+    /// alive for `'g`.
+    unsafe fn get_mut<'g>(storages: Self::Storages, index: usize, tick: Tick) -> Self::Muts<'g>;
+}
+
+macro_rules! impl_group_components {
+    ($first:ident $(, $name:ident)*) => {
+        impl<$first: Component $(, $name: Component)*> GroupComponents for ($first, $($name,)*) {
+            type Refs<'g> = (&'g $first, $(&'g $name,)*);
+            type Muts<'g> = (&'g mut $first, $(&'g mut $name,)*);
+            type Storages = (*mut SparseSet<$first>, $(*mut SparseSet<$name>,)*);
+
+            fn type_ids() -> Vec<TypeId> {
+                vec![TypeId::of::<$first>() $(, TypeId::of::<$name>())*]
+            }
+
+            fn storages(registry: &mut Registry) -> Self::Storages {
+                (registry.sparse_set_ptr::<$first>(), $(registry.sparse_set_ptr::<$name>(),)*)
+            }
+
+            unsafe fn entity(storages: Self::Storages, index: usize) -> Entity {
+                unsafe { (&(*storages.0).entities)[index] }
+            }
+
+            unsafe fn index_of(storages: Self::Storages, id: usize) -> Option<usize> {
+                unsafe { (*storages.0).dense_index(id) }
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn get<'g>(storages: Self::Storages, index: usize) -> Self::Refs<'g> {
+                let ($first, $($name,)*) = storages;
+                unsafe {
+                    (
+                        (*$first).get_dense_unchecked(index),
+                        $((*$name).get_dense_unchecked(index),)*
+                    )
+                }
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn get_mut<'g>(storages: Self::Storages, index: usize, tick: Tick) -> Self::Muts<'g> {
+                let ($first, $($name,)*) = storages;
+                unsafe {
+                    (
+                        (*$first).get_dense_mut_unchecked(index, tick),
+                        $((*$name).get_dense_mut_unchecked(index, tick),)*
+                    )
+                }
+            }
+        }
+    };
+}
+
+impl_group_components!(A);
+impl_group_components!(A, B);
+impl_group_components!(A, B, C);
+impl_group_components!(A, B, C, D);
+impl_group_components!(A, B, C, D, E);
+impl_group_components!(A, B, C, D, E, F);
+impl_group_components!(A, B, C, D, E, F, G);
+impl_group_components!(A, B, C, D, E, F, G, H);
+
+/// The entities of an owning group, see `Registry::group`
+pub struct Group<'r, G: GroupComponents> {
+    registry: &'r mut Registry,
+    storages: G::Storages,
+    len: usize,
+}
+
+impl<G: GroupComponents> Group<'_, G> {
+    /// Returns the number of entities in the group, including disabled ones
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no entity has every owned component
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Checks if `entity` is in the group
+    pub fn contains(&self, entity: Entity) -> bool {
+        // SAFETY: the group borrows the registry, and the position is checked
+        // against the group length before use
+        unsafe { G::index_of(self.storages, entity.id() as usize) }.is_some_and(|index| {
+            index < self.len && unsafe { G::entity(self.storages, index) } == entity
+        })
+    }
+
+    /// Returns an iterator over the components of the enabled entities of the
+    /// group
+    pub fn iter(&self) -> impl Iterator<Item = G::Refs<'_>> + '_ {
+        let storages = self.storages;
+        // SAFETY: the group borrows the registry, and only hands out shared
+        // references through a shared borrow
+        self.enabled_indices()
+            .map(move |index| unsafe { G::get(storages, index) })
+    }
+
+    /// Returns an iterator over the components of the enabled entities of the
+    /// group, marking them as changed
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = G::Muts<'_>> + '_ {
+        let tick = self.registry.change_tick();
+        let storages = self.storages;
+        // SAFETY: the group is borrowed mutably, and each position is visited
+        // once
+        self.enabled_indices()
+            .map(move |index| unsafe { G::get_mut(storages, index, tick) })
+    }
+
+    /// Returns the positions of the enabled entities of the group
+    fn enabled_indices(&self) -> impl Iterator<Item = usize> + 'static {
+        let storages = self.storages;
+        let disabled = self
+            .registry
+            .disabled_storage()
+            .filter(|disabled| !disabled.is_empty())
+            .map(|disabled| disabled as *const SparseSet<Disabled>);
+        (0..self.len).filter(move |&index| {
+            disabled.is_none_or(|disabled| {
+                // SAFETY: the `Disabled` storage can't be owned by a group, so
+                // it outlives the iteration untouched, and `index` is lower
+                // than the group length
+                unsafe {
+                    let entity = G::entity(storages, index);
+                    (*disabled).get(entity.id() as usize).is_none()
+                }
+            })
+        })
+    }
+}
+
+impl Registry {
+    /// Returns the owning group of the component types `G`, creating it the
+    /// first time.
+    ///
+    /// The group keeps the entities having every component of `G` packed at
+    /// the front of the storages, in the same order, so iterating it is a
+    /// straight walk over the storages without sparse lookups. In exchange,
+    /// adding and removing owned components is slightly slower, and each
+    /// component type can be owned by a single group. Returns `InvalidGroup`
+    /// if a component of `G` is owned by another group, stored in tables, or
+    /// listed twice, or if `G` contains the `Disabled` marker.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Position(f32);
+    ///
+    /// #[derive(Component)]
+    /// struct Velocity(f32);
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.spawn((Position(0.0), Velocity(1.0)));
+    /// registry.spawn((Position(5.0),));
+    ///
+    /// let mut group = registry.group::<(Position, Velocity)>().unwrap();
+    /// assert_eq!(group.len(), 1);
+    /// for (position, velocity) in group.iter_mut() {
+    ///     position.0 += velocity.0;
+    /// }
+    /// ```
+    pub fn group<G: GroupComponents>(&mut self) -> Result<Group<'_, G>, RecsError> {
+        let types = G::type_ids();
+        // Creates the missing storages before the group packs them
+        let storages = G::storages(self);
+        let group = match self.groups.owners.get(&types[0]) {
+            Some(&group) if self.groups.groups[group].types == types => group,
+            _ => self.create_group(types)?,
+        };
+        Ok(Group {
+            len: self.groups.groups[group].len,
+            registry: self,
+            storages,
+        })
+    }
+
+    fn create_group(&mut self, types: Vec<TypeId>) -> Result<usize, RecsError> {
+        for (index, type_id) in types.iter().enumerate() {
+            let name = || self.component_name(*type_id).unwrap_or("A component");
+            if types[..index].contains(type_id) {
+                return Err(RecsError::InvalidGroup(format!(
+                    "{} is listed twice",
+                    name()
+                )));
+            }
+            if self.groups.owners.contains_key(type_id) {
+                return Err(RecsError::InvalidGroup(format!(
+                    "{} is already owned by another group",
+                    name()
+                )));
+            }
+            if *type_id == TypeId::of::<Disabled>() {
+                return Err(RecsError::InvalidGroup(
+                    "the Disabled marker can't be owned".to_string(),
+                ));
+            }
+            if self.archetypes.is_table_component(*type_id) {
+                return Err(RecsError::InvalidGroup(format!(
+                    "{} is stored in tables",
+                    name()
+                )));
+            }
+        }
+        Ok(self.insert_group(types))
+    }
+
+    /// Creates a group owning `types`, whose storages must exist
+    fn insert_group(&mut self, types: Vec<TypeId>) -> usize {
+        let group = self.groups.groups.len();
+        let mut storages = Vec::with_capacity(types.len());
+        for type_id in &types {
+            self.groups.owners.insert(*type_id, group);
+            storages.push(self.components.id(*type_id).expect("Owned storages exist"));
+        }
+        let mut owning = OwningGroup {
+            types,
+            storages,
+            len: 0,
+        };
+        owning.rebuild(&mut self.components);
+        self.groups.groups.push(owning);
+        group
+    }
+
+    /// Returns the storage of `C`, creating it if needed
+    fn sparse_set_ptr<C: Component>(&mut self) -> *mut SparseSet<C> {
+        let storage = self.storage_or_insert::<C>();
+        (storage as &mut dyn std::any::Any)
+            .downcast_mut::<SparseSet<C>>()
+            .expect("Component storages are sparse sets of their type")
+    }
+
+    /// Checks if the storage of `type_id` is owned by a group
+    pub(super) fn is_group_owned(&self, type_id: TypeId) -> bool {
+        self.groups.owners.contains_key(&type_id)
+    }
+
+    /// Moves `entity` into the group owning `type_id`, if any, now that it
+    /// got such a component
+    pub(super) fn join_group(&mut self, type_id: TypeId, entity: Entity) {
+        if let Some(&group) = self.groups.owners.get(&type_id) {
+            self.groups.groups[group].join(&mut self.components, entity.id() as usize);
+        }
+    }
+
+    /// Moves `entity` out of the group owning `type_id`, if any, before its
+    /// component is removed
+    pub(super) fn leave_group(&mut self, type_id: TypeId, entity: Entity) {
+        if let Some(&group) = self.groups.owners.get(&type_id) {
+            self.groups.groups[group].leave(&mut self.components, entity.id() as usize);
+        }
+    }
+
+    /// Moves `entity` out of every group, before its components are removed
+    pub(crate) fn leave_groups(&mut self, entity: Entity) {
+        for group in &mut self.groups.groups {
+            group.leave(&mut self.components, entity.id() as usize);
+        }
+    }
+
+    /// Packs every group again, after storages were replaced or edited
+    /// without going through the group hooks
+    pub(super) fn rebuild_groups(&mut self) {
+        for group in &mut self.groups.groups {
+            group.rebuild(&mut self.components);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    impl Component for Position {}
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+    impl Component for Velocity {}
+
+    fn positions(registry: &mut Registry) -> Vec<i32> {
+        let group = registry.group::<(Position, Velocity)>().unwrap();
+        let mut positions: Vec<i32> = group.iter().map(|(position, _)| position.0).collect();
+        positions.sort_unstable();
+        positions
+    }
+
+    #[test]
+    fn test_group_tracks_inserts_and_removals() {
+        let mut registry = Registry::new();
+        let a = registry.spawn((Position(0), Velocity(1)));
+        let b = registry.spawn((Position(10),));
+        let c = registry.spawn((Velocity(3), Position(20)));
+        assert_eq!(positions(&mut registry), [0, 20]);
+
+        registry.add_component(b, Velocity(2)).unwrap();
+        registry.remove_component::<Position>(a).unwrap();
+        registry.add_component(a, Position(30)).unwrap();
+        registry.destroy_entity(c).unwrap();
+        assert_eq!(positions(&mut registry), [10, 30]);
+
+        for (position, velocity) in registry.group::<(Position, Velocity)>().unwrap().iter_mut() {
+            position.0 += velocity.0;
+        }
+        assert_eq!(registry.get_component::<Position>(a), Some(&Position(31)));
+        assert_eq!(registry.get_component::<Position>(b), Some(&Position(12)));
+
+        // Every group member sits at the same position in both storages
+        let position_entities = registry.entities_with::<Position>()[..2].to_vec();
+        assert_eq!(
+            &registry.entities_with::<Velocity>()[..2],
+            position_entities
+        );
+
+        registry.add_component(b, Disabled).unwrap();
+        let group = registry.group::<(Position, Velocity)>().unwrap();
+        assert_eq!((group.len(), group.iter().count()), (2, 1));
+        assert!(group.contains(a) && group.contains(b));
+    }
+
+    #[test]
+    fn test_components_are_owned_by_one_group() {
+        let mut registry = Registry::new();
+        registry.group::<(Position, Velocity)>().unwrap();
+        assert!(registry.group::<(Position,)>().is_err());
+        assert!(registry.group::<(Velocity, Position)>().is_err());
+        assert!(registry.group::<(Velocity, Velocity)>().is_err());
+    }
+}
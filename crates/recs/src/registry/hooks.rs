@@ -0,0 +1,176 @@
+use std::any::{Any, TypeId};
+
+use crate::{component::Component, entity::Entity, registry::Registry, system::commands::Commands};
+
+impl Registry {
+    /// Runs `hook` whenever a `C` component is added to an entity, replacing
+    /// the hook set before or declared with `#[component(on_add = path)]`.
+    ///
+    /// The hook gets the new component and a command buffer, whose commands
+    /// are applied with the registry's other commands (see
+    /// `apply_commands`). Replacing a component runs the `on_remove` hook for
+    /// the old value, then this one for the new value.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Body { mass: f32 }
+    ///
+    /// #[derive(Component)]
+    /// struct PhysicsHandle(u32);
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.on_add::<Body>(|entity, _body, commands| {
+    ///     commands.insert(entity, PhysicsHandle(7));
+    /// });
+    ///
+    /// let entity = registry.spawn((Body { mass: 1.0 },));
+    /// registry.apply_commands();
+    /// assert_eq!(registry.get_component::<PhysicsHandle>(entity).unwrap().0, 7);
+    /// ```
+    pub fn on_add<C: Component>(
+        &mut self,
+        hook: impl Fn(Entity, &C, &mut Commands) + Send + Sync + 'static,
+    ) {
+        self.hooks.set_on_add(hook);
+    }
+
+    /// Runs `hook` whenever a `C` component is removed from an entity,
+    /// including when the entity is destroyed, replacing the hook set before
+    /// or declared with `#[component(on_remove = path)]`.
+    ///
+    /// The hook gets the removed component right before it is returned or
+    /// dropped, and a command buffer like `on_add` hooks. It also runs for
+    /// the old value of a replaced component, for components moved to another
+    /// registry, and for those `restore` and `load` drop. When the entity is
+    /// being destroyed, it is no longer valid by the time the hook runs.
+    pub fn on_remove<C: Component>(
+        &mut self,
+        hook: impl Fn(Entity, &C, &mut Commands) + Send + Sync + 'static,
+    ) {
+        self.hooks.set_on_remove(hook);
+    }
+
+    /// Runs the `on_add` hook of `C` for the component `entity` just got
+    pub(super) fn run_add_hook<C: Component>(&mut self, entity: Entity) {
+        let type_id = TypeId::of::<C>();
+        let Some(hook) = self.hooks.on_add(type_id) else {
+            return;
+        };
+        let id = entity.id() as usize;
+        let component = if self.archetypes.is_table_component(type_id) {
+            self.archetypes.get::<C>(id)
+        } else {
+            self.components.sparse_set::<C>().and_then(|ss| ss.get(id))
+        };
+        if let Some(component) = component {
+            let mut commands = Commands::new(&mut self.command_queue, &mut self.entity_manager);
+            hook(entity, component, &mut commands);
+        }
+    }
+
+    /// Runs the `on_remove` hook of the component type for `component`, just
+    /// removed from `entity`
+    pub(super) fn run_remove_hook(&mut self, type_id: TypeId, entity: Entity, component: &dyn Any) {
+        if let Some(hook) = self.hooks.on_remove(type_id) {
+            let mut commands = Commands::new(&mut self.command_queue, &mut self.entity_manager);
+            hook(entity, component, &mut commands);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::Resource;
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+    impl Resource for Log {}
+
+    fn log(commands: &mut Commands, line: String) {
+        commands.add(move |registry: &mut Registry| {
+            registry.get_resource_mut::<Log>().unwrap().0.push(line);
+        });
+    }
+
+    #[derive(crate::Component)]
+    #[component(on_add = body_added, on_remove = body_removed)]
+    struct Body(u32);
+
+    fn body_added(_entity: Entity, body: &Body, commands: &mut Commands) {
+        log(commands, format!("add {}", body.0));
+    }
+
+    fn body_removed(_entity: Entity, body: &Body, commands: &mut Commands) {
+        log(commands, format!("remove {}", body.0));
+    }
+
+    #[derive(crate::Component)]
+    #[component(storage = "table")]
+    struct Texture(u32);
+
+    #[test]
+    fn test_declared_hooks_run_on_add_and_remove() {
+        let mut registry = Registry::new();
+        registry.init_resource::<Log>();
+        let first = registry.spawn((Body(1),));
+        let second = registry.spawn((Body(2),));
+        // Replacing a component removes the old value and adds the new one
+        registry.add_component(first, Body(3)).unwrap();
+        registry.remove_component::<Body>(first).unwrap();
+        registry.destroy_entity(second).unwrap();
+        registry.apply_commands();
+
+        assert_eq!(
+            registry.get_resource::<Log>().unwrap().0,
+            [
+                "add 1", "add 2", "remove 1", "add 3", "remove 3", "remove 2"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_insert_batch_runs_hooks_for_replaced_components() {
+        let mut registry = Registry::new();
+        registry.init_resource::<Log>();
+        let first = registry.spawn((Body(1),));
+        let second = registry.create_entity();
+        let mut next = 1;
+        registry
+            .insert_batch([first, second, first], || {
+                next += 1;
+                Body(next)
+            })
+            .unwrap();
+        registry.apply_commands();
+
+        assert_eq!(
+            registry.get_resource::<Log>().unwrap().0,
+            ["add 1", "remove 1", "add 2", "add 3", "remove 2", "add 4"]
+        );
+        assert_eq!(registry.get_component::<Body>(first).unwrap().0, 4);
+    }
+
+    #[test]
+    fn test_registered_hooks_see_table_components() {
+        let mut registry = Registry::new();
+        registry.init_resource::<Log>();
+        registry.on_add::<Texture>(|_, texture, commands| {
+            log(commands, format!("upload {}", texture.0));
+        });
+        registry.on_remove::<Texture>(|_, texture, commands| {
+            log(commands, format!("free {}", texture.0));
+        });
+        let entity = registry.spawn((Texture(4),));
+        registry.spawn((Texture(5),));
+        registry.destroy_entity(entity).unwrap();
+        registry.apply_commands();
+
+        assert_eq!(
+            registry.get_resource::<Log>().unwrap().0,
+            ["upload 4", "upload 5", "free 4"]
+        );
+    }
+}
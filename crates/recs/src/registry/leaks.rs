@@ -0,0 +1,224 @@
+//! Diagnostics for entity id churn and entities left without components.
+
+use std::collections::HashMap;
+
+use crate::{entity::Entity, registry::Registry};
+
+/// Settings of the empty-entity leak detector, see `Registry::set_leak_detector`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeakDetector {
+    /// Number of frames an entity must stay without components before it is
+    /// reported as leaked
+    pub min_frames: u64,
+    /// Destroys leaked entities automatically at the end of each frame
+    pub reclaim: bool,
+}
+
+impl Default for LeakDetector {
+    fn default() -> Self {
+        Self {
+            min_frames: 60,
+            reclaim: false,
+        }
+    }
+}
+
+/// An entity id slot that was reused many times
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChurnHotspot {
+    /// The id of the slot
+    pub id: u32,
+    /// The current generation of the slot, i.e. one more than the number of
+    /// entities destroyed in it
+    pub generation: u32,
+}
+
+/// Tracks since when each alive entity has had no components
+#[derive(Default)]
+pub(crate) struct EmptyEntities {
+    detector: Option<LeakDetector>,
+    /// The frame at which each empty entity was first seen without components
+    empty_since: HashMap<Entity, u64>,
+}
+
+impl EmptyEntities {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Registry {
+    /// Enables the leak detector, which tracks the entities that stay without
+    /// any component for `detector.min_frames` frames, or disables it with None.
+    ///
+    /// Tracking scans every entity and component at the end of each frame, so
+    /// it is meant for debugging sessions. Entities used as bare handles, e.g.
+    /// only carrying a GUID, are reported as well.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use recs::registry::leaks::LeakDetector;
+    /// #[derive(Component)]
+    /// struct Bullet;
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.set_leak_detector(Some(LeakDetector { min_frames: 2, reclaim: false }));
+    /// let bullet = registry.spawn(Bullet);
+    /// registry.remove_component::<Bullet>(bullet).unwrap();
+    ///
+    /// for _ in 0..3 {
+    ///     registry.run_systems();
+    /// }
+    /// assert_eq!(registry.leaked_empties(), vec![bullet]);
+    /// assert_eq!(registry.reclaim_leaked_empties(), 1);
+    /// assert!(!registry.is_valid(bullet));
+    /// ```
+    pub fn set_leak_detector(&mut self, detector: Option<LeakDetector>) {
+        self.empty_entities.detector = detector;
+        if detector.is_none() {
+            self.empty_entities.empty_since.clear();
+        }
+    }
+
+    /// Returns the entities that have had no components for at least the
+    /// leak detector's `min_frames`, in id order. Always empty while the
+    /// detector is disabled.
+    pub fn leaked_empties(&self) -> Vec<Entity> {
+        let Some(detector) = self.empty_entities.detector else {
+            return Vec::new();
+        };
+
+        let mut leaked: Vec<Entity> = self
+            .empty_entities
+            .empty_since
+            .iter()
+            .filter(|&(_, &since)| self.frame_count - since >= detector.min_frames)
+            .map(|(&entity, _)| entity)
+            .collect();
+        leaked.sort_by_key(|entity| entity.id());
+        leaked
+    }
+
+    /// Destroys the entities returned by `leaked_empties` and returns how many
+    /// there were
+    pub fn reclaim_leaked_empties(&mut self) -> usize {
+        let leaked = self.leaked_empties();
+        for &entity in &leaked {
+            self.empty_entities.empty_since.remove(&entity);
+            let _ = self.destroy_entity(entity);
+        }
+        leaked.len()
+    }
+
+    /// Returns the id slots whose generation reached `min_generation`, most
+    /// reused first.
+    ///
+    /// A high generation means entities are created and destroyed in that
+    /// slot over and over, which usually points to objects that should be
+    /// pooled, see `EntityPool`.
+    pub fn churn_hotspots(&self, min_generation: u32) -> Vec<ChurnHotspot> {
+        let mut hotspots: Vec<ChurnHotspot> = self
+            .entity_manager
+            .slot_generations()
+            .filter(|&(_, generation)| generation >= min_generation)
+            .map(|(id, generation)| ChurnHotspot { id, generation })
+            .collect();
+        hotspots.sort_by(|a, b| b.generation.cmp(&a.generation).then(a.id.cmp(&b.id)));
+        hotspots
+    }
+
+    /// Records which entities are empty at the end of a frame, and reclaims
+    /// the leaked ones if the detector is set to
+    pub(crate) fn track_empty_entities(&mut self) {
+        let Some(detector) = self.empty_entities.detector else {
+            return;
+        };
+
+        let mut occupied = vec![false; self.entity_manager.slot_count()];
+        let typed = self.components.values().map(|storage| storage.entities());
+        let dynamic = self.dynamic.iter().map(|storage| storage.entities());
+        let tables = self
+            .archetypes
+            .tables()
+            .iter()
+            .map(|table| table.entities());
+        for entity in typed.chain(dynamic).chain(tables).flatten() {
+            occupied[entity.id() as usize] = true;
+        }
+
+        let frame = self.frame_count;
+        let previous = std::mem::take(&mut self.empty_entities.empty_since);
+        self.empty_entities.empty_since = self
+            .entity_manager
+            .iter()
+            .filter(|entity| !occupied[entity.id() as usize])
+            .map(|entity| (entity, previous.get(&entity).copied().unwrap_or(frame)))
+            .collect();
+
+        if detector.reclaim {
+            self.reclaim_leaked_empties();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::Component;
+
+    struct Tag;
+    impl Component for Tag {}
+
+    #[test]
+    fn test_leak_detector_tracks_entities_empty_for_long() {
+        let mut registry = Registry::new();
+        registry.set_leak_detector(Some(LeakDetector {
+            min_frames: 3,
+            reclaim: true,
+        }));
+        let kept = registry.spawn(Tag);
+        let empty = registry.create_entity();
+        let refilled = registry.create_entity();
+
+        for _ in 0..2 {
+            registry.run_systems();
+        }
+        assert!(registry.leaked_empties().is_empty());
+
+        // Getting a component resets the count
+        registry.add_component(refilled, Tag).unwrap();
+        registry.run_systems();
+        registry.remove_component::<Tag>(refilled).unwrap();
+
+        registry.run_systems();
+        assert!(!registry.is_valid(empty));
+        assert!(registry.is_valid(refilled) && registry.is_valid(kept));
+
+        registry.set_leak_detector(None);
+        for _ in 0..5 {
+            registry.run_systems();
+        }
+        assert!(registry.is_valid(refilled));
+    }
+
+    #[test]
+    fn test_churn_hotspots() {
+        let mut registry = Registry::new();
+        let stable = registry.create_entity();
+        for _ in 0..5 {
+            let entity = registry.create_entity();
+            registry.destroy_entity(entity).unwrap();
+        }
+
+        assert_eq!(
+            registry.churn_hotspots(3),
+            vec![ChurnHotspot {
+                id: 1,
+                generation: 6
+            }]
+        );
+        assert_eq!(registry.churn_hotspots(1).len(), 2);
+        assert_eq!(registry.churn_hotspots(1)[1].id, stable.id());
+    }
+}
@@ -1,18 +1,79 @@
 use std::{
     any::{Any, TypeId},
     collections::HashMap,
+    ops::{Deref, DerefMut, Range},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
 };
 
+pub mod audit;
 pub mod bundle;
+pub mod cell;
+pub mod copy;
+pub mod dynamic;
+pub mod entity_ref;
+#[cfg(feature = "serde")]
+pub mod extract;
+pub mod fetch;
+pub mod group;
+pub mod hooks;
+pub mod leaks;
+pub mod rollback;
+#[cfg(feature = "serde")]
+pub mod savepoint;
+#[cfg(feature = "serde")]
+pub mod serialize;
+pub mod sort;
+pub mod split;
+pub mod stats;
+pub mod table;
+pub mod teardown;
+pub mod transfer;
+pub mod validate;
 
 use crate::{
-    component::{Component, ComponentStorage, sparse_set::SparseSet},
-    entity::{Entity, EntityManager},
+    component::{
+        Component, ComponentStorage, ComponentTicks, StorageType,
+        clone::ComponentCloners,
+        codec::{ComponentCodecs, PackedComponent},
+        dynamic::DynamicComponents,
+        factory::{ComponentFactories, ComponentFactory},
+        hooks::ComponentHooks,
+        info::ComponentNames,
+        removed::{RemovedComponentStorage, RemovedComponents},
+        required::RequiredComponent,
+        sparse_set::SparseSet,
+        storages::ComponentStorages,
+        table::Archetypes,
+    },
+    entity::{
+        Disabled, Entity, EntityManager, EntityRange,
+        guid::{EntityGuid, GuidIndex},
+        map::EntityMappers,
+    },
     error::RecsError,
-    query::{QueryIter, QueryParam},
-    registry::bundle::ComponentBundle,
-    resource::{Resource, ResourceStorage},
-    system::{BoxedSystem, IntoSystem},
+    event::{EventTypes, Events, missing_events},
+    observer::Observers,
+    plugin::Plugins,
+    query::{Mut, QueryFilter, QueryIter, QueryParam},
+    reflect::ReflectedComponents,
+    registry::{
+        bundle::ComponentBundle, cell::UnsafeRegistryCell, group::Groups, leaks::EmptyEntities,
+        rollback::RollbackTypes, teardown::TeardownPolicy, transfer::ComponentMovers,
+    },
+    relationship::Relations,
+    resource::{FromWorld, Resource, ResourceStorage, non_send::NonSendResources},
+    system::{
+        BoxedSystem,
+        commands::{CommandError, CommandErrorPolicy, CommandErrors, CommandQueue, Commands},
+        error::{self as system_error, SystemError, SystemErrorHandler},
+        executor::{self, ExecutorKind},
+        output::LatestOutput,
+        quota::EmissionTracker,
+        schedule::{IntoSystemConfig, Schedule, Stage},
+    },
+    tick::{CHECK_TICK_THRESHOLD, Tick},
+    time::{FixedTime, Time},
 };
 
 /// The main registry that manages all entities and their components in the RECS system.
@@ -25,13 +86,101 @@ use crate::{
 /// - Running systems that operate on entities
 pub struct Registry {
     /// Manages entity creation, destruction and validation
-    entity_manager: EntityManager,
-    /// Stores components for all entities, organized by component type
-    pub(crate) components: HashMap<TypeId, Box<dyn ComponentStorage>>,
+    pub(crate) entity_manager: EntityManager,
+    /// Stores components for all entities, organized by component type, in
+    /// the order their storage was created
+    pub(crate) components: ComponentStorages,
+    /// Readable names of the registered component types
+    component_names: ComponentNames,
+    /// Lifecycle hooks of the component types, see `on_add` and `on_remove`
+    hooks: ComponentHooks,
+    /// Callbacks reacting to triggered events, see `observe`
+    pub(crate) observers: Observers,
+    /// Entities whose newly added components require others, inserted once
+    /// the current bundle is in, see `insert_required_components`
+    pending_required: Vec<(Entity, &'static [RequiredComponent])>,
+    /// Changes whenever a storage is created, replaced or removed, see
+    /// `storage_version`
+    pub(crate) storage_version: u64,
+    /// Tables of the components registered with `register_table_component`
+    pub(crate) archetypes: Archetypes,
+    /// Owning groups, which keep the storages they own sorted
+    groups: Groups,
     /// Stores resources (singleton data) accessible by systems
     pub(crate) resources: ResourceStorage,
-    /// List of systems to be executed
-    systems: Vec<BoxedSystem>,
+    /// Stores the resources that must stay on the thread that created the
+    /// registry
+    pub(crate) non_send: NonSendResources,
+    /// Systems to be executed, grouped by stage
+    schedules: HashMap<Stage, Schedule>,
+    /// Set once the `Startup` schedule has run
+    startup_done: bool,
+    /// Factories used to lazily construct components on first access
+    factories: ComponentFactories,
+    /// Entities whose components were removed, buffered for change detection
+    pub(crate) removed_components: RemovedComponentStorage,
+    /// Deferred operations recorded through `Commands`
+    pub(crate) command_queue: CommandQueue,
+    /// The current change tick, stamped onto every component write
+    pub(crate) change_tick: Tick,
+    /// The tick that change detection compares against. Inside a system this is
+    /// the tick at which that system last ran.
+    pub(crate) last_change_tick: Tick,
+    /// The change tick at which `maintain()` last ran
+    last_maintain_tick: Tick,
+    /// The change tick at which stored ticks were last clamped
+    last_check_tick: Tick,
+    /// How `run_systems()` executes the registered systems
+    executor: ExecutorKind,
+    /// Stable GUIDs attached to entities
+    guids: GuidIndex,
+    /// Pack/unpack functions used to snapshot components
+    codecs: ComponentCodecs,
+    /// Clone functions used to copy entities into another registry
+    cloners: ComponentCloners,
+    /// Insert functions used to move entities into another registry
+    movers: ComponentMovers,
+    /// Number of completed `maintain()` calls, i.e. of elapsed frames
+    pub(crate) frame_count: u64,
+    /// Event types whose buffers are swapped every frame
+    pub(crate) event_types: EventTypes,
+    /// Errors of applied commands, handled according to their policy
+    pub(crate) command_errors: CommandErrors,
+    /// Handles the errors returned by fallible systems
+    system_error_handler: SystemErrorHandler,
+    /// How components and resources are destroyed when the registry is dropped
+    teardown: TeardownPolicy,
+    /// Names of the added plugins
+    pub(crate) plugins: Plugins,
+    /// Commands and events emitted per system, and the quotas they are held to
+    pub(crate) emissions: EmissionTracker,
+    /// Entities without components, tracked by the leak detector
+    empty_entities: EmptyEntities,
+    /// Reverse indexes of the relationships between entities
+    pub(crate) relations: Relations,
+    /// Component types accessible by name through reflection
+    pub(crate) reflected: ReflectedComponents,
+    /// Storages of the component types registered at runtime
+    pub(crate) dynamic: DynamicComponents,
+    /// Components whose entity references are remapped on load and copy
+    pub(crate) entity_mappers: EntityMappers,
+    /// Component and resource types included in snapshots
+    rollback: RollbackTypes,
+    /// Component types saved by `serialize`
+    #[cfg(feature = "serde")]
+    serializable: serialize::SerializableComponents,
+}
+
+/// Returns a storage version no registry has used yet
+fn next_storage_version() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Registry {
@@ -39,29 +188,248 @@ impl Registry {
     pub fn new() -> Self {
         Self {
             entity_manager: EntityManager::new(),
-            components: HashMap::new(),
+            components: ComponentStorages::new(),
+            component_names: ComponentNames::new(),
+            hooks: ComponentHooks::new(),
+            observers: Observers::new(),
+            pending_required: Vec::new(),
+            storage_version: next_storage_version(),
+            archetypes: Archetypes::new(),
+            groups: Groups::new(),
             resources: ResourceStorage::new(),
-            systems: Vec::new(),
+            non_send: NonSendResources::new(),
+            schedules: HashMap::new(),
+            startup_done: false,
+            factories: ComponentFactories::new(),
+            removed_components: RemovedComponentStorage::new(),
+            command_queue: CommandQueue::new(),
+            change_tick: Tick::new(1),
+            last_change_tick: Tick::default(),
+            last_maintain_tick: Tick::default(),
+            last_check_tick: Tick::default(),
+            executor: ExecutorKind::default(),
+            guids: GuidIndex::new(),
+            codecs: ComponentCodecs::new(),
+            cloners: ComponentCloners::new(),
+            movers: ComponentMovers::new(),
+            frame_count: 0,
+            event_types: EventTypes::new(),
+            command_errors: CommandErrors::new(),
+            system_error_handler: system_error::log,
+            teardown: TeardownPolicy::default(),
+            plugins: Plugins::new(),
+            emissions: EmissionTracker::new(),
+            empty_entities: EmptyEntities::new(),
+            relations: Relations::new(),
+            reflected: ReflectedComponents::new(),
+            dynamic: DynamicComponents::new(),
+            entity_mappers: EntityMappers::new(),
+            rollback: RollbackTypes::new(),
+            #[cfg(feature = "serde")]
+            serializable: serialize::SerializableComponents::new(),
         }
     }
 
     /// Registers a new component type in the registry.
     /// This is automatically called when adding components, but can be called
     /// manually to pre-allocate storage for a component type.
+    ///
+    /// The storage follows `Component::STORAGE`, except that a type owned by a
+    /// group keeps its sparse set, see `register_table_component`.
     pub fn register_component<C: Component + 'static>(&mut self) {
-        let type_id = TypeId::of::<C>();
-        if !self.components.contains_key(&type_id) {
-            self.components
-                .insert(type_id, Box::new(SparseSet::<C>::new()));
+        match C::STORAGE {
+            StorageType::SparseSet | StorageType::SparseMap => {
+                self.storage_or_insert::<C>();
+            }
+            StorageType::Table => {
+                if self.register_table_component::<C>().is_err() {
+                    self.storage_or_insert::<C>();
+                }
+            }
         }
     }
 
+    /// Returns the storage of `C`, creating it if this is the first time the
+    /// component type is used
+    fn storage_or_insert<C: Component + 'static>(&mut self) -> &mut dyn ComponentStorage {
+        self.components.get_or_insert_with::<C>(|| {
+            self.component_names.register::<C>();
+            self.movers.register::<C>();
+            self.cloners.register_declared::<C>();
+            self.hooks.register::<C>();
+            self.observers.register::<C>();
+            self.event_types.register_component::<C>();
+            self.storage_version = next_storage_version();
+            Box::new(SparseSet::<C>::new())
+        })
+    }
+
+    /// Returns the version of the component storages, which changes whenever
+    /// a storage is created, replaced or removed. Versions are unique across
+    /// registries, so storages cached along with the version, as `QueryState`
+    /// does, can be reused for as long as it stays the same.
+    pub fn storage_version(&self) -> u64 {
+        self.storage_version
+    }
+
+    /// Invalidates the storages cached with the current storage version
+    fn storages_changed(&mut self) {
+        self.storage_version = next_storage_version();
+    }
+
+    /// Returns the current change tick.
+    /// Component writes made now are stamped with this tick.
+    pub fn change_tick(&self) -> Tick {
+        self.change_tick
+    }
+
+    /// Returns the tick that change detection currently compares against.
+    /// While a system is running, this is the tick at which it last ran.
+    pub fn last_change_tick(&self) -> Tick {
+        self.last_change_tick
+    }
+
     /// Creates a new entity without any components.
     /// Use `spawn()` if you want to create an entity with components.
     pub fn create_entity(&mut self) -> Entity {
         self.entity_manager.create_entity()
     }
 
+    /// Reserves a block of `count` contiguous entity ids, e.g. for a streamed
+    /// world chunk.
+    ///
+    /// Entities spawned with `spawn_in` take their ids from the range only, so
+    /// they stay packed together instead of interleaving with ids allocated by
+    /// gameplay, and the whole chunk can be unloaded with
+    /// `release_entity_range`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Tree;
+    ///
+    /// let mut registry = Registry::new();
+    /// let chunk = registry.reserve_entities(64);
+    /// for _ in 0..10 {
+    ///     registry.spawn_in(chunk, (Tree,)).unwrap();
+    /// }
+    /// let player = registry.create_entity();
+    /// assert!(!chunk.contains(player));
+    ///
+    /// // Unloading the chunk destroys every entity spawned in it
+    /// assert_eq!(registry.release_entity_range(chunk), 10);
+    /// assert_eq!(registry.query::<(&Tree,)>().count(), 0);
+    /// ```
+    pub fn reserve_entities(&mut self, count: u32) -> EntityRange {
+        self.entity_manager.reserve_range(count)
+    }
+
+    /// Creates a new entity without any components, with an id taken from
+    /// `range`. Returns an error if every id of the range is in use.
+    pub fn create_entity_in(&mut self, range: EntityRange) -> Result<Entity, RecsError> {
+        self.entity_manager.create_entity_in(range)
+    }
+
+    /// Spawns an entity with the components of `bundle`, with an id taken from
+    /// `range`. Returns an error if every id of the range is in use.
+    pub fn spawn_in<B: ComponentBundle>(
+        &mut self,
+        range: EntityRange,
+        bundle: B,
+    ) -> Result<Entity, RecsError> {
+        let entity = self.create_entity_in(range)?;
+        bundle.add_to_entity(self, entity)?;
+        self.insert_required_components();
+        Ok(entity)
+    }
+
+    /// Returns an iterator over every alive entity with an id in `range`
+    pub fn entities_in(&self, range: EntityRange) -> impl Iterator<Item = Entity> + '_ {
+        self.entity_manager.iter_range(range)
+    }
+
+    /// Destroys every entity alive in `range` and ends its reservation, giving
+    /// its ids back to general allocation. Returns the number of destroyed
+    /// entities.
+    pub fn release_entity_range(&mut self, range: EntityRange) -> usize {
+        let doomed: Vec<Entity> = self.entity_manager.iter_range(range).collect();
+        for &entity in &doomed {
+            self.destroy_entity(entity)
+                .expect("Failed to destroy an alive entity. This is a bug in the RECS library.");
+        }
+        self.entity_manager.release_range(range);
+        doomed.len()
+    }
+
+    /// Checks if an entity is still alive in this registry
+    pub fn is_valid(&self, entity: Entity) -> bool {
+        self.entity_manager.is_valid(entity)
+    }
+
+    /// Returns an iterator over every alive entity, in id order.
+    ///
+    /// Disabled entities are included, unlike with `query::<(Entity,)>()`,
+    /// which yields the enabled entities and accepts filters.
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entity_manager.iter()
+    }
+
+    /// Returns the entities that have a `C` component, in storage order
+    pub(crate) fn entities_with<C: Component>(&self) -> &[Entity] {
+        self.components
+            .get(TypeId::of::<C>())
+            .map_or(&[], |storage| storage.entities())
+    }
+
+    /// Returns the storage of the `Disabled` marker, if any entity was ever disabled
+    pub(crate) fn disabled_storage(&self) -> Option<&SparseSet<Disabled>> {
+        self.components.sparse_set::<Disabled>()
+    }
+
+    /// Checks if the entity with `id` carries the `Disabled` marker
+    pub(crate) fn is_disabled_id(&self, id: u32) -> bool {
+        self.disabled_storage()
+            .is_some_and(|ss| ss.contains(id as usize))
+    }
+
+    /// Enables or disables `entity` by removing or adding the `Disabled`
+    /// marker. Queries skip disabled entities, which keep their components.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Npc;
+    ///
+    /// let mut registry = Registry::new();
+    /// let npc = registry.spawn((Npc,));
+    /// registry.set_enabled(npc, false).unwrap();
+    /// assert_eq!(registry.query::<(&Npc,)>().count(), 0);
+    ///
+    /// registry.set_enabled(npc, true).unwrap();
+    /// assert!(registry.is_enabled(npc));
+    /// assert_eq!(registry.query::<(&Npc,)>().count(), 1);
+    /// ```
+    pub fn set_enabled(&mut self, entity: Entity, enabled: bool) -> Result<(), RecsError> {
+        if !self.is_valid(entity) {
+            return Err(RecsError::InvalidEntity(entity));
+        }
+        if enabled == self.is_enabled(entity) {
+            return Ok(());
+        }
+        if enabled {
+            self.remove_component::<Disabled>(entity).map(|_| ())
+        } else {
+            self.add_component(entity, Disabled)
+        }
+    }
+
+    /// Checks if `entity` is alive and doesn't carry the `Disabled` marker
+    pub fn is_enabled(&self, entity: Entity) -> bool {
+        self.is_valid(entity) && !self.is_disabled_id(entity.id())
+    }
+
     pub fn add_component<C: Component + 'static>(
         &mut self,
         entity: Entity,
@@ -71,17 +439,61 @@ impl Registry {
             return Err(RecsError::InvalidEntity(entity));
         }
 
-        let type_id = TypeId::of::<C>();
-        let storage = self
-            .components
-            .entry(type_id)
-            .or_insert_with(|| Box::new(SparseSet::<C>::new()));
+        self.insert_unchecked(entity, component);
+        self.insert_required_components();
+        Ok(())
+    }
 
-        if let Some(ss) = (storage.as_mut() as &mut dyn Any).downcast_mut::<SparseSet<C>>() {
-            ss.insert(entity, component);
+    /// Adds or replaces a component of `entity`, which must be valid
+    fn insert_unchecked<C: Component + 'static>(&mut self, entity: Entity, component: C) {
+        let tick = self.change_tick;
+        if C::STORAGE == StorageType::Table {
+            // A group owning the type keeps it in its sparse set
+            let _ = self.register_table_component::<C>();
         }
+        // The sparse set is found through the cached storage id, see
+        // `Component::storage_cache`
+        let replaced = match self.components.sparse_set_mut::<C>() {
+            Some(ss) => {
+                let replaced = ss.replace(entity, component, tick);
+                self.join_group(TypeId::of::<C>(), entity);
+                replaced
+            }
+            None if self.archetypes.is_table_component(TypeId::of::<C>()) => {
+                self.archetypes.insert(entity, component, tick)
+            }
+            None => {
+                let storage = self.storage_or_insert::<C>();
+                let replaced = (storage as &mut dyn Any)
+                    .downcast_mut::<SparseSet<C>>()
+                    .and_then(|ss| ss.replace(entity, component, tick));
+                self.join_group(TypeId::of::<C>(), entity);
+                replaced
+            }
+        };
+        let added = replaced.is_none();
+        match replaced {
+            // Replacing runs the remove hook of the old value, then the add
+            // hook of the new one
+            Some(old) => self.run_remove_hook(TypeId::of::<C>(), entity, &old),
+            None if !C::REQUIRES.is_empty() => {
+                self.pending_required.push((entity, C::REQUIRES));
+            }
+            None => {}
+        }
+        self.run_add_hook::<C>(entity);
+        self.queue_insert_events::<C>(entity, added);
+    }
 
-        Ok(())
+    /// Inserts the defaults of the components required by the components
+    /// added since the last call, see `Component::REQUIRES`. Bundles insert
+    /// their components first so that their values win over the defaults.
+    pub(crate) fn insert_required_components(&mut self) {
+        while let Some((entity, required)) = self.pending_required.pop() {
+            for component in required {
+                component.insert(self, entity);
+            }
+        }
     }
 
     pub fn get_component<C: Component + 'static>(&self, entity: Entity) -> Option<&C> {
@@ -89,13 +501,49 @@ impl Registry {
             return None;
         }
 
-        let type_id = TypeId::of::<C>();
-        if let Some(sparse_set) = self.components.get(&type_id) {
-            if let Some(ss) = (sparse_set.as_ref() as &dyn Any).downcast_ref::<SparseSet<C>>() {
-                return ss.get(entity.id() as usize);
-            }
+        self.get_unchecked(entity)
+    }
+
+    /// Checks if `entity` has a `C` component, without fetching it
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Frozen;
+    ///
+    /// let mut registry = Registry::new();
+    /// let entity = registry.spawn((Frozen,));
+    /// assert!(registry.has_component::<Frozen>(entity));
+    ///
+    /// registry.remove_component::<Frozen>(entity).unwrap();
+    /// assert!(!registry.has_component::<Frozen>(entity));
+    /// ```
+    pub fn has_component<C: Component>(&self, entity: Entity) -> bool {
+        self.entity_manager.is_valid(entity) && self.has_component_id(TypeId::of::<C>(), entity)
+    }
+
+    /// Checks if `entity`, which must be valid, has a component of the type
+    fn has_component_id(&self, type_id: TypeId, entity: Entity) -> bool {
+        contains_component(&self.components, &self.archetypes, type_id, entity)
+    }
+
+    /// Returns the `C` component of `entity`, which must be valid
+    fn get_unchecked<C: Component + 'static>(&self, entity: Entity) -> Option<&C> {
+        component(&self.components, &self.archetypes, entity)
+    }
+
+    /// Returns the change ticks of the `C` component of `entity`, which must
+    /// be valid
+    #[cfg(feature = "serde")]
+    fn ticks_unchecked<C: Component + 'static>(
+        &self,
+        entity: Entity,
+    ) -> Option<crate::component::ComponentTicks> {
+        match self.components.sparse_set::<C>() {
+            Some(ss) => ss.get_ticks(entity.id() as usize).copied(),
+            None => self.archetypes.ticks::<C>(entity.id() as usize),
         }
-        None
     }
 
     pub fn get_component_mut<C: Component + 'static>(&mut self, entity: Entity) -> Option<&mut C> {
@@ -103,27 +551,107 @@ impl Registry {
             return None;
         }
 
-        let type_id = TypeId::of::<C>();
-        if let Some(sparse_set) = self.components.get_mut(&type_id) {
-            if let Some(ss) = (sparse_set.as_mut() as &mut dyn Any).downcast_mut::<SparseSet<C>>() {
-                return ss.get_mut(entity.id() as usize);
-            }
-        }
-        None
+        self.get_mut_unchecked(entity)
+    }
+
+    /// Returns the `C` component of `entity`, which must be valid, marking it
+    /// as changed
+    fn get_mut_unchecked<C: Component + 'static>(&mut self, entity: Entity) -> Option<&mut C> {
+        component_mut(
+            &mut self.components,
+            &mut self.archetypes,
+            entity,
+            self.change_tick,
+        )
     }
 
     pub fn destroy_entity(&mut self, entity: Entity) -> Result<(), RecsError> {
+        if !self.entity_manager.is_valid(entity) {
+            return Err(RecsError::InvalidEntity(entity));
+        }
+        self.detach_hierarchy(entity);
+        self.detach_relations(entity);
         self.entity_manager.destroy_entity(entity)?;
+        self.leave_groups(entity);
 
         let id = entity.id() as usize;
 
-        for (_type_id, storage) in self.components.iter_mut() {
-            storage.remove_by_id(id);
+        let mut removed = Vec::new();
+        for (type_id, storage) in self.components.iter_mut() {
+            if let Some(component) = storage.remove_by_id(id) {
+                removed.push((type_id, component));
+            }
+        }
+        removed.extend(self.archetypes.remove_all(entity));
+        for (type_id, component) in removed {
+            self.component_removed(type_id, entity, component.as_ref());
         }
+        self.queue_observers_cleanup(entity);
+        self.dynamic.remove_all(id);
+        self.guids.remove(entity.id());
 
         Ok(())
     }
 
+    /// Returns the GUID of `entity`, generating and attaching a new one if it
+    /// has none yet
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// let mut registry = Registry::new();
+    /// let entity = registry.create_entity();
+    ///
+    /// let guid = registry.assign_guid(entity).unwrap();
+    /// assert_eq!(registry.entity_by_guid(guid), Some(entity));
+    /// ```
+    pub fn assign_guid(&mut self, entity: Entity) -> Result<EntityGuid, RecsError> {
+        if let Some(guid) = self.guid(entity) {
+            return Ok(guid);
+        }
+
+        let guid = EntityGuid::generate();
+        self.set_guid(entity, guid)?;
+        Ok(guid)
+    }
+
+    /// Attaches a known GUID to `entity`, e.g. one read from a save file,
+    /// replacing any GUID the entity had before.
+    ///
+    /// Returns `DuplicateGuid` if another entity already carries `guid`.
+    pub fn set_guid(&mut self, entity: Entity, guid: EntityGuid) -> Result<(), RecsError> {
+        if !self.is_valid(entity) {
+            return Err(RecsError::InvalidEntity(entity));
+        }
+        self.guids.insert(entity, guid)
+    }
+
+    /// Detaches the GUID of `entity`, returning it
+    pub fn remove_guid(&mut self, entity: Entity) -> Option<EntityGuid> {
+        if !self.is_valid(entity) {
+            return None;
+        }
+        self.guids.remove(entity.id())
+    }
+
+    /// Returns the GUID of `entity`, if it has one
+    pub fn guid(&self, entity: Entity) -> Option<EntityGuid> {
+        if !self.is_valid(entity) {
+            return None;
+        }
+        self.guids.guid(entity.id())
+    }
+
+    /// Looks up the entity carrying `guid`
+    pub fn entity_by_guid(&self, guid: EntityGuid) -> Option<Entity> {
+        self.guids.entity(guid)
+    }
+
+    /// Returns an iterator over every (GUID, entity) pair, in no particular order
+    pub fn guids(&self) -> impl Iterator<Item = (EntityGuid, Entity)> + '_ {
+        self.guids.iter()
+    }
+
     pub fn remove_component<C: Component + 'static>(
         &mut self,
         entity: Entity,
@@ -132,64 +660,771 @@ impl Registry {
             return Err(RecsError::InvalidEntity(entity));
         }
 
+        self.remove_unchecked(entity)
+            .ok_or(RecsError::ComponentNotFound(TypeId::of::<C>()))
+    }
+
+    /// Removes the `C` component of `entity`, which must be valid
+    fn remove_unchecked<C: Component + 'static>(&mut self, entity: Entity) -> Option<C> {
         let type_id = TypeId::of::<C>();
-        let storage = self.components.get_mut(&type_id);
+        let removed = if self.archetypes.is_table_component(type_id) {
+            self.archetypes.remove(entity)?
+        } else {
+            self.leave_group(type_id, entity);
+            let ss = self.components.sparse_set_mut::<C>()?;
+            ss.remove(entity.id() as usize)?
+        };
+        self.component_removed(type_id, entity, &removed);
+        Some(removed)
+    }
 
-        if let Some(storage) = storage {
-            if let Some(ss) = (storage.as_mut() as &mut dyn Any).downcast_mut::<SparseSet<C>>() {
-                return ss
-                    .remove(entity.id() as usize)
-                    .ok_or(RecsError::ComponentNotFound(type_id));
-            }
+    /// Records the removal of `component` from `entity` for change detection
+    /// and removal events, and runs its hooks and observers
+    fn component_removed(&mut self, type_id: TypeId, entity: Entity, component: &dyn Any) {
+        self.removed_components
+            .record(type_id, entity, self.change_tick);
+        self.event_types
+            .send_removal(&mut self.resources, type_id, entity, self.change_tick);
+        self.run_remove_hook(type_id, entity, component);
+        self.queue_remove_event(type_id, entity);
+    }
+
+    /// Removes every component type of the bundle `B` from `entity`.
+    ///
+    /// Returns the removed components as a `B` if the entity had all of them,
+    /// or None otherwise, in which case the components it had are still
+    /// removed. Returns an error if the entity is invalid.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Position { x: f32 }
+    ///
+    /// #[derive(Component)]
+    /// struct Velocity { dx: f32 }
+    ///
+    /// let mut registry = Registry::new();
+    /// let entity = registry.spawn((Position { x: 0.0 }, Velocity { dx: 1.0 }));
+    ///
+    /// let (_, velocity) = registry
+    ///     .remove_bundle::<(Position, Velocity)>(entity)
+    ///     .unwrap()
+    ///     .unwrap();
+    /// assert_eq!(velocity.dx, 1.0);
+    /// assert!(registry.get_component::<Position>(entity).is_none());
+    /// ```
+    pub fn remove_bundle<B: ComponentBundle>(
+        &mut self,
+        entity: Entity,
+    ) -> Result<Option<B>, RecsError> {
+        if !self.entity_manager.is_valid(entity) {
+            return Err(RecsError::InvalidEntity(entity));
+        }
+        Ok(B::remove_from_entity(self, entity))
+    }
+
+    /// Registers a factory that lazily constructs `C` the first time it is
+    /// requested on an entity through `get_or_init_component`.
+    ///
+    /// The factory receives read access to the registry and the target entity,
+    /// so it can build the component from the entity's other components.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Radius(f32);
+    ///
+    /// #[derive(Component)]
+    /// struct CachedArea(f32);
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.register_component_factory(|registry, entity| {
+    ///     let radius = registry.get_component::<Radius>(entity).map_or(0.0, |r| r.0);
+    ///     CachedArea(std::f32::consts::PI * radius * radius)
+    /// });
+    ///
+    /// let entity = registry.spawn(Radius(2.0));
+    /// let area = registry.get_or_init_component::<CachedArea>(entity).unwrap();
+    /// # assert!((area.0 - 12.566).abs() < 0.01);
+    /// ```
+    pub fn register_component_factory<C, F>(&mut self, factory: F)
+    where
+        C: Component,
+        F: Fn(&Registry, Entity) -> C + Send + Sync + 'static,
+    {
+        self.factories
+            .insert::<C>(Box::new(factory) as ComponentFactory<C>);
+    }
+
+    /// Checks if a factory is registered for the component type
+    pub fn has_component_factory<C: Component>(&self) -> bool {
+        self.factories.contains::<C>()
+    }
+
+    /// Registers the functions packing `C` into bytes and back, used by
+    /// `pack_entity` and `unpack_component`. Serde saves, `SnapshotWriter`
+    /// chunks and rollback snapshots also store `C` packed, so the registry
+    /// loading them needs the same codec.
+    ///
+    /// `unpack` returns None if the bytes are malformed. Codecs are identified
+    /// by the type name of `C`, so packed data should be unpacked by a build of
+    /// the same program.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component, Debug, PartialEq)]
+    /// struct Health(u32);
+    ///
+    /// let mut registry = Registry::new();
+    /// // Health never exceeds 65535, so two bytes are enough
+    /// registry.register_component_codec(
+    ///     |health: &Health, bytes: &mut Vec<u8>| {
+    ///         bytes.extend_from_slice(&(health.0 as u16).to_le_bytes())
+    ///     },
+    ///     |bytes| Some(Health(u16::from_le_bytes(bytes.try_into().ok()?) as u32)),
+    /// );
+    ///
+    /// let source = registry.spawn(Health(80));
+    /// let packed = registry.pack_entity(source).unwrap();
+    /// assert_eq!(packed[0].bytes.len(), 2);
+    ///
+    /// let copy = registry.create_entity();
+    /// registry.unpack_component(copy, &packed[0]).unwrap();
+    /// assert_eq!(registry.get_component::<Health>(copy), Some(&Health(80)));
+    /// ```
+    pub fn register_component_codec<C, P, U>(&mut self, pack: P, unpack: U)
+    where
+        C: Component,
+        P: Fn(&C, &mut Vec<u8>) + Send + Sync + 'static,
+        U: Fn(&[u8]) -> Option<C> + Send + Sync + 'static,
+    {
+        self.codecs.insert(pack, unpack);
+    }
+
+    /// Checks if a codec is registered for the component type
+    pub fn has_component_codec<C: Component>(&self) -> bool {
+        self.codecs.contains::<C>()
+    }
+
+    /// Packs every component of `entity` that has a registered codec, ordered
+    /// by codec name. Components without a codec are skipped.
+    pub fn pack_entity(&self, entity: Entity) -> Result<Vec<PackedComponent>, RecsError> {
+        if !self.is_valid(entity) {
+            return Err(RecsError::InvalidEntity(entity));
+        }
+        Ok(self.codecs.pack(self, entity))
+    }
+
+    /// Unpacks a component packed by `pack_entity` and inserts it on `entity`.
+    ///
+    /// Returns `UnknownComponentCodec` if no codec with the packed name is
+    /// registered, and `InvalidComponentData` if the codec rejects the bytes.
+    pub fn unpack_component(
+        &mut self,
+        entity: Entity,
+        packed: &PackedComponent,
+    ) -> Result<(), RecsError> {
+        // Codecs insert through the registry, so they are moved out meanwhile
+        let codecs = std::mem::take(&mut self.codecs);
+        let result = codecs.unpack(self, entity, packed);
+        self.codecs = codecs;
+        result
+    }
+
+    /// Constructs `C` on the entity with its registered factory if the entity
+    /// doesn't have the component yet. Returns true if the component was created.
+    ///
+    /// Created components are inserted like any other, so change detection
+    /// reports them as added.
+    ///
+    /// Returns `ComponentNotFound` if the component is missing and no factory
+    /// is registered for it.
+    pub fn init_component<C: Component>(&mut self, entity: Entity) -> Result<bool, RecsError> {
+        if !self.entity_manager.is_valid(entity) {
+            return Err(RecsError::InvalidEntity(entity));
+        }
+
+        if self.has_component::<C>(entity) {
+            return Ok(false);
+        }
+
+        let factory = self
+            .factories
+            .get::<C>()
+            .ok_or(RecsError::ComponentNotFound(TypeId::of::<C>()))?;
+        let component = factory(self, entity);
+        self.add_component(entity, component)?;
+
+        Ok(true)
+    }
+
+    /// Gets a reference to the entity's component, constructing it with the
+    /// registered factory first if it doesn't exist yet.
+    pub fn get_or_init_component<C: Component>(&mut self, entity: Entity) -> Result<&C, RecsError> {
+        self.init_component::<C>(entity)?;
+        self.get_component::<C>(entity)
+            .ok_or(RecsError::ComponentNotFound(TypeId::of::<C>()))
+    }
+
+    /// Gets a mutable reference to the entity's component, constructing it with
+    /// the registered factory first if it doesn't exist yet.
+    pub fn get_or_init_component_mut<C: Component>(
+        &mut self,
+        entity: Entity,
+    ) -> Result<&mut C, RecsError> {
+        self.init_component::<C>(entity)?;
+        self.get_component_mut::<C>(entity)
+            .ok_or(RecsError::ComponentNotFound(TypeId::of::<C>()))
+    }
+
+    /// Gets a mutable reference to the entity's component, inserting the value
+    /// returned by `f` first if it doesn't exist yet.
+    pub fn get_or_insert_component_with<C: Component>(
+        &mut self,
+        entity: Entity,
+        f: impl FnOnce() -> C,
+    ) -> Result<&mut C, RecsError> {
+        if !self.entity_manager.is_valid(entity) {
+            return Err(RecsError::InvalidEntity(entity));
+        }
+
+        if !self.has_component::<C>(entity) {
+            self.add_component(entity, f())?;
         }
 
-        Err(RecsError::ComponentNotFound(type_id))
+        self.get_component_mut::<C>(entity)
+            .ok_or(RecsError::ComponentNotFound(TypeId::of::<C>()))
     }
 
     pub fn query<'q, Q: QueryParam<'q>>(&'q mut self) -> QueryIter<'q, Q> {
         Q::iter(self)
     }
 
+    /// Queries entities matching `Q` that also pass the filter `F`.
+    ///
+    /// Outside of systems, change detection filters compare against
+    /// `last_change_tick()`.
+    pub fn query_filtered<'q, Q: QueryParam<'q>, F: QueryFilter>(
+        &'q mut self,
+    ) -> QueryIter<'q, Q, F> {
+        Q::iter::<F>(self)
+    }
+
     pub fn spawn<B: ComponentBundle>(&mut self, bundle: B) -> Entity {
         let entity = self.create_entity();
         bundle.add_to_entity(self, entity).expect(
             "Failed to add bundle to newly created entity. This is a bug in the RECS library.",
         );
+        self.insert_required_components();
         entity
     }
 
-    /// Adds a system to the registry
-    pub fn add_system<S, Params>(&mut self, system: S)
+    /// Spawns an entity for every bundle of `bundles` and returns them, in
+    /// order.
+    ///
+    /// Faster than calling `spawn` in a loop when spawning many entities of the
+    /// same shape: the storages of the bundle's components are grown once for
+    /// the whole batch, based on the iterator's size hint, instead of
+    /// reallocating as entities come in.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Particle { life: f32 }
+    ///
+    /// let mut registry = Registry::new();
+    /// let particles = registry.spawn_batch((0..1000).map(|_| (Particle { life: 1.0 },)));
+    /// assert_eq!(particles.len(), 1000);
+    /// assert_eq!(registry.query::<(&Particle,)>().count(), 1000);
+    /// ```
+    pub fn spawn_batch<B, I>(&mut self, bundles: I) -> Vec<Entity>
     where
-        S: IntoSystem<Params>,
-        S::System: 'static,
+        B: ComponentBundle,
+        I: IntoIterator<Item = B>,
     {
-        self.systems.push(Box::new(system.into_system()));
+        let bundles = bundles.into_iter();
+        let additional = bundles.size_hint().0;
+        B::reserve(self, additional);
+        self.entity_manager.reserve(additional);
+
+        let mut entities = Vec::with_capacity(additional);
+        for bundle in bundles {
+            entities.push(self.spawn(bundle));
+        }
+        entities
+    }
+
+    /// Adds or replaces a `C` component, made by `component`, on every entity
+    /// of `entities`, e.g. to tag the matches of a query.
+    ///
+    /// Faster than calling `add_component` in a loop: the entities are
+    /// validated upfront and the sparse set of `C` is grown once for the whole
+    /// batch. Hooks, observers and required components behave
+    /// as with `add_component`. Returns `InvalidEntity` if any entity is
+    /// invalid, in which case none gets the component.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Health(u32);
+    ///
+    /// #[derive(Component)]
+    /// struct PendingDelete;
+    ///
+    /// let mut registry = Registry::new();
+    /// let entities = registry.spawn_batch((0..10).map(|i| (Health(i),)));
+    /// let dead: Vec<Entity> = entities
+    ///     .into_iter()
+    ///     .filter(|&entity| registry.get_component::<Health>(entity).unwrap().0 < 3)
+    ///     .collect();
+    ///
+    /// registry.insert_batch(dead, || PendingDelete).unwrap();
+    /// assert_eq!(registry.query::<(&PendingDelete,)>().count(), 3);
+    /// ```
+    pub fn insert_batch<C, I>(
+        &mut self,
+        entities: I,
+        mut component: impl FnMut() -> C,
+    ) -> Result<(), RecsError>
+    where
+        C: Component,
+        I: IntoIterator<Item = Entity>,
+    {
+        let entities: Vec<Entity> = entities.into_iter().collect();
+        if let Some(&entity) = entities
+            .iter()
+            .find(|&&entity| !self.entity_manager.is_valid(entity))
+        {
+            return Err(RecsError::InvalidEntity(entity));
+        }
+        if C::STORAGE == StorageType::Table {
+            // A group owning the type keeps it in its sparse set
+            let _ = self.register_table_component::<C>();
+        }
+        let type_id = TypeId::of::<C>();
+        if self.archetypes.is_table_component(type_id) {
+            // Each entity moves to the table of its new archetype on its own
+            for &entity in &entities {
+                self.insert_unchecked(entity, component());
+            }
+            self.insert_required_components();
+            return Ok(());
+        }
+
+        let tick = self.change_tick;
+        let id_bound = entities
+            .iter()
+            .map(|entity| entity.id() as usize + 1)
+            .max()
+            .unwrap_or(0);
+        let storage = self.storage_or_insert::<C>();
+        let ss = (storage as &mut dyn Any)
+            .downcast_mut::<SparseSet<C>>()
+            .expect("Component storages are sparse sets of their type");
+        ss.reserve(entities.len(), id_bound);
+        for entity in entities {
+            // Each entity runs its hooks before the next one is inserted, so
+            // an entity listed twice behaves as two `add_component` calls
+            let replaced = self
+                .components
+                .sparse_set_mut::<C>()
+                .expect("The storage was created above")
+                .replace(entity, component(), tick);
+            self.join_group(type_id, entity);
+            let added = replaced.is_none();
+            // Replacing runs the remove hook of the old value, then the add
+            // hook of the new one, as in `insert_unchecked`
+            match replaced {
+                Some(old) => self.run_remove_hook(type_id, entity, &old),
+                None if !C::REQUIRES.is_empty() => {
+                    self.pending_required.push((entity, C::REQUIRES));
+                }
+                None => {}
+            }
+            self.run_add_hook::<C>(entity);
+            self.queue_insert_events::<C>(entity, added);
+        }
+        self.insert_required_components();
+        Ok(())
+    }
+
+    /// Adds a system to the `Update` schedule.
+    ///
+    /// Systems run in insertion order unless constrained with `before`/`after`
+    /// from `IntoSystemConfig`, e.g. `movement.after(input)`.
+    ///
+    /// # Panics
+    /// Panics if two parameters of the system access the same component or
+    /// resource with one of them writing it, such as `Query<(&mut Position,)>`
+    /// alongside `Query<(&Position,)>`.
+    pub fn add_system<Params>(&mut self, system: impl IntoSystemConfig<Params>) {
+        self.add_system_to(Stage::Update, system);
     }
 
-    /// Runs all registered systems in order
+    /// Adds a system to the schedule of `stage`, creating it if needed.
+    ///
+    /// Ordering constraints only apply between systems of the same schedule.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// fn spawn_level() {}
+    /// fn gameplay() {}
+    /// fn cleanup() {}
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.add_system_to(Stage::Startup, spawn_level);
+    /// registry.add_system_to(Stage::Update, gameplay);
+    /// registry.add_system_to(Stage::PostUpdate, cleanup);
+    ///
+    /// // Runs spawn_level (first frame only), gameplay, then cleanup
+    /// registry.run_systems();
+    /// ```
+    pub fn add_system_to<Params>(&mut self, stage: Stage, system: impl IntoSystemConfig<Params>) {
+        self.schedules.entry(stage).or_default().add_system(system);
+    }
+
+    /// Returns the entities whose `C` component was removed since the last
+    /// change tick (see `last_change_tick()`).
+    pub fn removed<C: Component>(&self) -> impl Iterator<Item = Entity> + '_ {
+        RemovedComponents::<C>::new(
+            self.removed_components.get::<C>(),
+            self.last_change_tick,
+            self.change_tick,
+        )
+        .iter()
+    }
+
+    /// Runs one frame: updates the `Time` resource, runs the `Startup` schedule
+    /// on the first call only, then the
+    /// `PreUpdate`, `FixedUpdate` (zero or more times, see `FixedTime`),
+    /// `Update` and `PostUpdate` schedules, then end-of-frame maintenance (see
+    /// `maintain()`)
+    ///
+    /// With `ExecutorKind::Parallel`, consecutive systems whose accesses don't
+    /// conflict run concurrently.
+    ///
+    /// # Panics
+    /// Panics if the systems' ordering constraints form a cycle.
     pub fn run_systems(&mut self) {
-        // We need to be careful here because we're borrowing self mutably
-        // We'll use raw pointers to work around the borrow checker
-        let registry_ptr = self as *mut Registry;
-
-        for system in &mut self.systems {
-            // Safety: We know the registry is valid for the duration of this call
-            // and we're not storing the reference anywhere
-            unsafe {
-                system.run(&mut *registry_ptr);
+        self.insert_resource_if_absent(Time::default());
+        if let Some(time) = self.resources.get_mut::<Time>() {
+            time.update(Instant::now());
+        }
+
+        if !self.startup_done {
+            self.startup_done = true;
+            self.run_schedule(Stage::Startup);
+        }
+
+        self.run_schedule(Stage::PreUpdate);
+        self.run_fixed_update();
+        self.run_schedule(Stage::Update);
+        self.run_schedule(Stage::PostUpdate);
+
+        self.maintain();
+    }
+
+    /// Adds the frame's `Time::delta` to `FixedTime` and runs the
+    /// `FixedUpdate` schedule once per whole step
+    fn run_fixed_update(&mut self) {
+        if !self.schedules.contains_key(&Stage::FixedUpdate) {
+            return;
+        }
+
+        let delta = self
+            .resources
+            .get::<Time>()
+            .map_or(Default::default(), Time::delta);
+        self.insert_resource_if_absent(FixedTime::default());
+        let steps = {
+            let fixed = self.resources.get_mut::<FixedTime>().unwrap();
+            fixed.accumulate(delta);
+            fixed.expend()
+        };
+        for _ in 0..steps {
+            self.run_schedule(Stage::FixedUpdate);
+        }
+    }
+
+    /// Runs the systems of a single schedule, without end-of-frame maintenance.
+    /// Does nothing if no system was added to `stage`.
+    ///
+    /// Custom stages only run through this method.
+    ///
+    /// # Panics
+    /// Panics if the systems' ordering constraints form a cycle.
+    pub fn run_schedule(&mut self, stage: Stage) {
+        // The schedule is taken out while it runs, so systems added by its
+        // commands don't invalidate it. The guard puts it back even if a
+        // system panics.
+        let Some(schedule) = self.schedules.remove(&stage) else {
+            return;
+        };
+        let mut guard = ScheduleGuard {
+            registry: self,
+            stage,
+            schedule,
+        };
+
+        if let Err(error) = guard.schedule.build() {
+            panic!("{}", error);
+        }
+        guard.registry.run_schedule_systems(&mut guard.schedule);
+    }
+
+    /// Runs the systems of a built schedule with the registry's executor
+    fn run_schedule_systems(&mut self, schedule: &mut Schedule) {
+        match self.executor {
+            ExecutorKind::Sequential => {
+                for index in 0..schedule.len() {
+                    if schedule.should_run(index, self) {
+                        self.run_system_exclusive(schedule.system_mut(index));
+                    }
+                }
+            }
+            ExecutorKind::Parallel => {
+                for batch in schedule.batches() {
+                    let running: Vec<bool> = batch
+                        .clone()
+                        .map(|index| schedule.should_run(index, self))
+                        .collect();
+                    match running.iter().filter(|&&run| run).count() {
+                        0 => {}
+                        1 => {
+                            let index = batch.start + running.iter().position(|&run| run).unwrap();
+                            self.run_system_exclusive(schedule.system_mut(index));
+                        }
+                        _ => self.run_batch(schedule, batch, &running),
+                    }
+                }
             }
         }
     }
 
+    /// Runs a single system with exclusive access to the registry
+    fn run_system_exclusive(&mut self, system: &mut BoxedSystem) {
+        let this_run = self.change_tick;
+        let previous_last_change_tick =
+            std::mem::replace(&mut self.last_change_tick, system.last_run());
+
+        let before = self.emission_snapshot();
+        system.run((), self);
+        self.record_emissions(std::iter::once(&*system), &before);
+        system.set_last_run(this_run);
+        system.apply_deferred(self);
+        self.apply_commands();
+
+        self.last_change_tick = previous_last_change_tick;
+        self.change_tick = this_run.next();
+    }
+
+    /// Runs the systems of a batch with compatible accesses concurrently,
+    /// skipping those whose `running` flag is unset. They all share the same
+    /// change tick, and their commands are applied afterwards.
+    fn run_batch(&mut self, schedule: &mut Schedule, batch: Range<usize>, running: &[bool]) {
+        let this_run = self.change_tick;
+        let before = self.emission_snapshot();
+
+        let systems = || running.iter().copied();
+        // Safety: systems of a batch have pairwise compatible accesses, and
+        // the schedule they belong to is not part of the registry while running
+        unsafe {
+            executor::run_concurrently(
+                UnsafeRegistryCell::new(self),
+                schedule
+                    .systems_mut(batch.clone())
+                    .zip(systems())
+                    .filter_map(|(system, run)| run.then_some(system)),
+                this_run,
+            )
+        };
+        let ran: Vec<&BoxedSystem> = schedule
+            .systems_range(batch.clone())
+            .zip(systems())
+            .filter_map(|(system, run)| run.then_some(system))
+            .collect();
+        self.record_emissions(ran.into_iter(), &before);
+
+        for (system, _) in schedule
+            .systems_mut(batch)
+            .zip(systems())
+            .filter(|&(_, run)| run)
+        {
+            system.set_last_run(this_run);
+            system.apply_deferred(self);
+        }
+        self.apply_commands();
+        self.change_tick = this_run.next();
+    }
+
+    /// Sets how `run_systems()` executes the registered systems
+    pub fn set_executor(&mut self, executor: ExecutorKind) {
+        self.executor = executor;
+    }
+
+    /// Returns how `run_systems()` executes the registered systems
+    pub fn executor(&self) -> ExecutorKind {
+        self.executor
+    }
+
+    /// Performs end-of-frame bookkeeping in a defined order:
+    ///
+    /// 1. Applies pending commands, so their effects are tracked this frame
+    /// 2. Swaps the buffers of every registered event type (see `Events`)
+    /// 3. Flushes removal records that every system has already observed
+    /// 4. Advances change ticks (see `clear_trackers()`)
+    /// 5. Advances the frame counter (see `frame_count()`)
+    /// 6. Tracks entities without components, if the leak detector is enabled
+    ///    (see `set_leak_detector()`)
+    ///
+    /// `run_systems()` calls this automatically. Embedders that drive the
+    /// registry manually should call it once per frame instead of performing
+    /// the individual steps themselves.
+    pub fn maintain(&mut self) {
+        self.apply_commands();
+        self.event_types.update(&mut self.resources);
+        self.emissions.end_frame();
+
+        // Every system has run at least once since the previous maintenance,
+        // so removals recorded before it have been observed by all of them
+        self.removed_components
+            .prune_older_than(self.last_maintain_tick, self.change_tick);
+
+        self.clear_trackers();
+        self.last_maintain_tick = self.change_tick;
+        self.frame_count += 1;
+        self.track_empty_entities();
+
+        if self
+            .change_tick
+            .get()
+            .wrapping_sub(self.last_check_tick.get())
+            >= CHECK_TICK_THRESHOLD
+        {
+            self.check_change_ticks();
+        }
+    }
+
+    /// Clamps every stored change tick to at most `MAX_CHANGE_AGE` ticks
+    /// before the current one, so that change detection keeps working once
+    /// the tick counter wraps around.
+    ///
+    /// `maintain()` calls this every `CHECK_TICK_THRESHOLD` ticks, which is
+    /// often enough for registries running their systems through it.
+    pub fn check_change_ticks(&mut self) {
+        let this_run = self.change_tick;
+        for storage in self.components.values_mut() {
+            storage.check_change_ticks(this_run);
+        }
+        self.archetypes.check_change_ticks(this_run);
+        self.dynamic.check_change_ticks(this_run);
+        self.resources.check_change_ticks(this_run);
+        for schedule in self.schedules.values_mut() {
+            schedule.check_change_ticks(this_run);
+        }
+        self.last_change_tick.check_tick(this_run);
+        self.last_maintain_tick.check_tick(this_run);
+        self.last_check_tick = this_run;
+    }
+
+    /// Returns the number of frames completed so far, i.e. how many times
+    /// `maintain()` has run
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Advances change detection so that changes and removals made so far are
+    /// no longer reported outside of systems (e.g. by `query_filtered` with
+    /// `Changed<T>` or by `removed::<T>()`).
+    pub fn clear_trackers(&mut self) {
+        self.last_change_tick = self.change_tick;
+        self.change_tick = self.change_tick.next();
+    }
+
+    /// Returns a command buffer for deferred operations on this registry.
+    /// The recorded commands are applied by the next `apply_commands()` call.
+    pub fn commands(&mut self) -> Commands<'_> {
+        Commands::new(&mut self.command_queue, &mut self.entity_manager)
+    }
+
+    /// Applies all pending commands recorded through `Commands`.
+    ///
+    /// This is called automatically after each system in `run_systems()`, but
+    /// can be used as an explicit sync point when driving the registry manually.
+    pub fn apply_commands(&mut self) {
+        // Commands may record further commands, so drain until nothing is left
+        while !self.command_queue.is_empty() {
+            let mut queue = std::mem::take(&mut self.command_queue);
+            queue.apply(self);
+        }
+    }
+
+    /// Sets how commands that fail while being applied are handled.
+    /// Defaults to `CommandErrorPolicy::Log`.
+    pub fn set_command_error_policy(&mut self, policy: CommandErrorPolicy) {
+        self.command_errors.policy = policy;
+    }
+
+    /// Returns how commands that fail while being applied are handled
+    pub fn command_error_policy(&self) -> CommandErrorPolicy {
+        self.command_errors.policy
+    }
+
+    /// Returns and clears the command errors stored under the
+    /// `CommandErrorPolicy::Collect` policy
+    pub fn take_command_errors(&mut self) -> Vec<CommandError> {
+        std::mem::take(&mut self.command_errors.errors)
+    }
+
+    /// Sets the handler of errors returned by fallible systems, i.e. systems
+    /// returning `Result<(), RecsError>` (or `Result<(), anyhow::Error>` with
+    /// the `anyhow` feature). Errors are handled once the system finishes,
+    /// like its commands. Defaults to `system::error::log`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use recs::error::RecsError;
+    /// use recs::system::error::{self, SystemErrors};
+    ///
+    /// #[derive(Resource)]
+    /// struct LevelPath(&'static str);
+    ///
+    /// fn load_level(path: Res<LevelPath>) -> Result<(), RecsError> {
+    ///     Err(RecsError::InvalidScene(format!("{} not found", path.0)))
+    /// }
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.insert_resource(LevelPath("levels/1.ron"));
+    /// registry.set_system_error_handler(error::collect);
+    /// registry.add_system(load_level);
+    /// registry.run_systems();
+    /// assert_eq!(registry.get_resource::<SystemErrors>().unwrap().0.len(), 1);
+    /// ```
+    pub fn set_system_error_handler(&mut self, handler: SystemErrorHandler) {
+        self.system_error_handler = handler;
+    }
+
+    /// Hands an error returned by a system to the system error handler
+    pub(crate) fn handle_system_error(&mut self, error: SystemError) {
+        let handler = self.system_error_handler;
+        handler(self, error);
+    }
+
     /// Clears all systems from the registry
     pub fn clear_systems(&mut self) {
-        self.systems.clear();
+        self.schedules.clear();
     }
 
-    /// Returns the number of registered systems
+    /// Returns the number of registered systems across all schedules
     pub fn system_count(&self) -> usize {
-        self.systems.len()
+        self.schedules.values().map(Schedule::len).sum()
     }
 
     /// Inserts a resource into the registry.
@@ -209,7 +1444,7 @@ impl Registry {
     /// # assert!(registry.has_resource::<GameSettings>());
     /// ```
     pub fn insert_resource<R: Resource>(&mut self, resource: R) {
-        self.resources.insert(resource);
+        self.resources.insert_with_tick(resource, self.change_tick);
     }
 
     /// Gets a reference to a resource if it exists
@@ -246,7 +1481,15 @@ impl Registry {
     /// # assert_eq!(registry.get_resource::<GameSettings>().unwrap().volume, 0.9);
     /// ```
     pub fn get_resource_mut<R: Resource>(&mut self) -> Option<&mut R> {
-        self.resources.get_mut::<R>()
+        self.resources.get_mut_with_tick::<R>(self.change_tick)
+    }
+
+    /// Returns when resource `R` was added and last changed, if it exists.
+    ///
+    /// Resources are marked as changed when inserted or mutably accessed,
+    /// through `get_resource_mut` or a `ResMut` system parameter.
+    pub fn resource_ticks<R: Resource>(&self) -> Option<crate::component::ComponentTicks> {
+        self.resources.get_ticks::<R>()
     }
 
     /// Removes a resource from the registry and returns it
@@ -284,7 +1527,23 @@ impl Registry {
         self.resources.contains::<R>()
     }
 
-    /// Inserts a resource with a default value if it doesn't exist
+    /// Returns an iterator over every resource as `(TypeId, type name, value)`,
+    /// see `ResourceStorage::iter`
+    pub fn iter_resources(&self) -> impl Iterator<Item = (TypeId, &'static str, &dyn Any)> + '_ {
+        self.resources.iter()
+    }
+
+    /// Returns an iterator over every resource with mutable access to its
+    /// value, see `ResourceStorage::iter_mut`. Values written through the
+    /// returned `Mut` are marked as changed, like through `get_resource_mut`.
+    pub fn iter_resources_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (TypeId, &'static str, Mut<'_, dyn Any>)> + '_ {
+        self.resources.iter_mut(self.change_tick)
+    }
+
+    /// Inserts a resource created with `FromWorld` if it doesn't exist, i.e.
+    /// its default value for `Default` resources
     ///
     /// # Example
     /// ```rust
@@ -295,23 +1554,296 @@ impl Registry {
     /// registry.init_resource::<GameSettings>();
     /// # assert!(registry.has_resource::<GameSettings>());
     /// ```
-    pub fn init_resource<R: Resource + Default>(&mut self) {
+    pub fn init_resource<R: Resource + FromWorld>(&mut self) {
         if !self.has_resource::<R>() {
-            self.insert_resource(R::default());
+            let resource = R::from_world(self);
+            self.insert_resource(resource);
+        }
+    }
+
+    /// Registers the event type `T`, inserting its `Events<T>` resource and
+    /// swapping its buffers in every `maintain()`. Does nothing if `T` is
+    /// already registered.
+    pub fn add_event<T: Send + Sync + 'static>(&mut self) {
+        if self.event_types.insert::<T>() {
+            self.insert_resource_if_absent(Events::<T>::new());
+        }
+    }
+
+    /// Sends an event of type `T` from outside of systems.
+    ///
+    /// Panics if `T` wasn't registered with `add_event`.
+    pub fn send_event<T: Send + Sync + 'static>(&mut self, event: T) {
+        let tick = self.change_tick;
+        self.resources
+            .get_mut::<Events<T>>()
+            .unwrap_or_else(|| missing_events::<T>())
+            .send(event, tick);
+    }
+
+    /// Returns the latest value returned by a system whose return type is `T`,
+    /// see `SystemOutput`
+    pub fn system_output<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.resources
+            .get::<LatestOutput<T>>()
+            .map(|output| &output.0)
+    }
+
+    /// Inserts a resource only if none of the same type exists yet.
+    /// Returns true if the resource was inserted.
+    pub fn insert_resource_if_absent<R: Resource>(&mut self, resource: R) -> bool {
+        if self.has_resource::<R>() {
+            return false;
+        }
+        self.insert_resource(resource);
+        true
+    }
+
+    /// Temporarily replaces resource `R` with `value` while `f` runs, then
+    /// restores the original resource, or removes `R` if there was none.
+    ///
+    /// The original is restored even if `f` panics. It keeps its change
+    /// ticks, unless systems ran meanwhile and saw the override, in which
+    /// case it is marked as changed so that they notice it came back.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::{Registry, Resource};
+    /// #[derive(Resource, Debug, PartialEq)]
+    /// struct Difficulty(u8);
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.insert_resource(Difficulty(1));
+    ///
+    /// registry.with_resource_override(Difficulty(3), |registry| {
+    ///     assert_eq!(registry.get_resource::<Difficulty>(), Some(&Difficulty(3)));
+    ///     registry.run_systems();
+    /// });
+    ///
+    /// assert_eq!(registry.get_resource::<Difficulty>(), Some(&Difficulty(1)));
+    /// ```
+    pub fn with_resource_override<R: Resource, T>(
+        &mut self,
+        value: R,
+        f: impl FnOnce(&mut Registry) -> T,
+    ) -> T {
+        let original = self.resources.remove_with_ticks::<R>();
+        self.insert_resource(value);
+
+        let tick = self.change_tick;
+        let mut guard = ResourceOverrideGuard {
+            registry: self,
+            original,
+            tick,
+        };
+        f(&mut guard)
+    }
+
+    /// Takes resource `R` out of the registry while `f` runs with mutable
+    /// access to both, then puts it back.
+    ///
+    /// Inside `f`, the registry doesn't have `R`: systems reading it panic,
+    /// and an `R` inserted meanwhile is replaced when the resource is put
+    /// back. The resource is put back even if `f` panics, with its change
+    /// ticks, marked as changed only if `f` accessed it mutably.
+    ///
+    /// # Panics
+    /// Panics if the registry has no `R`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Mesh(&'static str);
+    ///
+    /// #[derive(Resource, Default)]
+    /// struct MeshCache(Vec<&'static str>);
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.init_resource::<MeshCache>();
+    /// registry.spawn((Mesh("cube"),));
+    ///
+    /// registry.resource_scope(|registry, mut cache: Mut<MeshCache>| {
+    ///     for (mesh,) in registry.query::<(&Mesh,)>() {
+    ///         cache.0.push(mesh.0);
+    ///     }
+    /// });
+    /// assert_eq!(registry.get_resource::<MeshCache>().unwrap().0, ["cube"]);
+    /// ```
+    pub fn resource_scope<R: Resource, T>(
+        &mut self,
+        f: impl FnOnce(&mut Registry, Mut<'_, R>) -> T,
+    ) -> T {
+        let resource = self.resources.remove_with_ticks::<R>().unwrap_or_else(|| {
+            panic!(
+                "Resource {} not found. Did you forget to insert it?",
+                std::any::type_name::<R>()
+            )
+        });
+
+        let tick = self.change_tick;
+        let mut guard = ResourceScopeGuard {
+            registry: self,
+            resource: Some(resource),
+        };
+        let (resource, ticks) = guard
+            .resource
+            .as_mut()
+            .expect("The resource is put back on drop only");
+        f(guard.registry, Mut::new(resource, &mut ticks.changed, tick))
+    }
+}
+
+/// Puts a resource taken by `Registry::resource_scope` back when dropped
+struct ResourceScopeGuard<'a, R: Resource> {
+    registry: &'a mut Registry,
+    /// The resource and its change ticks
+    resource: Option<(R, ComponentTicks)>,
+}
+
+impl<R: Resource> Drop for ResourceScopeGuard<'_, R> {
+    fn drop(&mut self) {
+        if let Some((resource, ticks)) = self.resource.take() {
+            self.registry.resources.insert_with_ticks(resource, ticks);
+        }
+    }
+}
+
+/// Puts a schedule taken out to run back into the registry when dropped,
+/// along with the systems added to its stage meanwhile, see
+/// `Registry::run_schedule`
+struct ScheduleGuard<'a> {
+    registry: &'a mut Registry,
+    stage: Stage,
+    schedule: Schedule,
+}
+
+impl Drop for ScheduleGuard<'_> {
+    fn drop(&mut self) {
+        let mut schedule = std::mem::take(&mut self.schedule);
+        if let Some(added) = self.registry.schedules.remove(&self.stage) {
+            schedule.append(added);
+        }
+        self.registry.schedules.insert(self.stage.clone(), schedule);
+    }
+}
+
+/// Restores an overridden resource when dropped, see `Registry::with_resource_override`
+struct ResourceOverrideGuard<'a, R: Resource> {
+    registry: &'a mut Registry,
+    /// The overridden resource and its change ticks
+    original: Option<(R, ComponentTicks)>,
+    /// The change tick when the override started
+    tick: Tick,
+}
+
+impl<R: Resource> Deref for ResourceOverrideGuard<'_, R> {
+    type Target = Registry;
+
+    fn deref(&self) -> &Self::Target {
+        self.registry
+    }
+}
+
+impl<R: Resource> DerefMut for ResourceOverrideGuard<'_, R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.registry
+    }
+}
+
+impl<R: Resource> Drop for ResourceOverrideGuard<'_, R> {
+    fn drop(&mut self) {
+        match self.original.take() {
+            Some((original, mut ticks)) => {
+                if self.registry.change_tick != self.tick {
+                    ticks.changed = self.registry.change_tick;
+                }
+                self.registry.resources.insert_with_ticks(original, ticks);
+            }
+            None => {
+                self.registry.remove_resource::<R>();
+            }
         }
     }
 }
 
+/// Returns the `C` component of `entity` from the storages. Takes the
+/// storages rather than the registry so that the component view of
+/// `Registry::split` can borrow them alone.
+fn component<'a, C: Component>(
+    components: &'a ComponentStorages,
+    archetypes: &'a Archetypes,
+    entity: Entity,
+) -> Option<&'a C> {
+    match components.sparse_set::<C>() {
+        Some(ss) => ss.get(entity.id() as usize),
+        None => archetypes.get(entity.id() as usize),
+    }
+}
+
+/// Checks if `entity` has a component of the type in the storages, see
+/// `component`
+fn contains_component(
+    components: &ComponentStorages,
+    archetypes: &Archetypes,
+    type_id: TypeId,
+    entity: Entity,
+) -> bool {
+    let id = entity.id() as usize;
+    match components.get(type_id) {
+        Some(storage) => storage.contains(id),
+        None => archetypes.contains(type_id, id),
+    }
+}
+
+/// Returns the `C` component of `entity` from the storages, marking it as
+/// changed at `tick`, see `component`
+fn component_mut<'a, C: Component>(
+    components: &'a mut ComponentStorages,
+    archetypes: &'a mut Archetypes,
+    entity: Entity,
+    tick: Tick,
+) -> Option<&'a mut C> {
+    match components.sparse_set_mut::<C>() {
+        Some(ss) => ss.get_mut_with_tick(entity.id() as usize, tick),
+        None => archetypes.get_mut(entity.id() as usize, tick),
+    }
+}
+
 /// Implementation for spawning single components
 impl<C: Component + 'static> ComponentBundle for C {
     fn add_to_entity(self, registry: &mut Registry, entity: Entity) -> Result<(), RecsError> {
-        registry.add_component(entity, self)
+        if !registry.entity_manager.is_valid(entity) {
+            return Err(RecsError::InvalidEntity(entity));
+        }
+        // Required components are inserted by the caller once the whole
+        // bundle is in
+        registry.insert_unchecked(entity, self);
+        Ok(())
+    }
+
+    fn remove_from_entity(registry: &mut Registry, entity: Entity) -> Option<Self> {
+        registry.remove_component::<C>(entity).ok()
+    }
+
+    fn reserve(registry: &mut Registry, additional: usize) {
+        if C::STORAGE == StorageType::Table
+            || registry.archetypes.is_table_component(TypeId::of::<C>())
+        {
+            return;
+        }
+        let id_bound = registry.entity_manager.id_bound_after(additional);
+        let storage = registry.storage_or_insert::<C>();
+        if let Some(ss) = (storage as &mut dyn Any).downcast_mut::<SparseSet<C>>() {
+            ss.reserve(additional, id_bound);
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::resource::{Res, ResMut};
 
     #[derive(Debug, PartialEq)]
     struct Position {
@@ -346,6 +1878,48 @@ mod tests {
         assert_eq!(vel, &Velocity { dx: -1 });
     }
 
+    #[test]
+    fn test_spawn_batch_reuses_freed_ids() {
+        let mut registry = Registry::new();
+        let freed = registry.spawn(Position { x: 0 });
+        registry.destroy_entity(freed).unwrap();
+
+        let entities =
+            registry.spawn_batch((0..100).map(|i| (Position { x: i }, Velocity { dx: -i })));
+        assert_eq!(entities.len(), 100);
+        assert_eq!(entities[0].id(), freed.id());
+        assert_eq!(entities[99].id(), 99);
+        for (i, &entity) in entities.iter().enumerate() {
+            let i = i as i32;
+            assert_eq!(registry.get_component(entity), Some(&Position { x: i }));
+            assert_eq!(registry.get_component(entity), Some(&Velocity { dx: -i }));
+        }
+    }
+
+    #[test]
+    fn test_insert_batch_adds_and_replaces() {
+        let mut registry = Registry::new();
+        let entities = registry.spawn_batch((0..5).map(|x| (Position { x },)));
+        registry
+            .add_component(entities[0], Velocity { dx: 9 })
+            .unwrap();
+
+        registry
+            .insert_batch(entities.iter().copied(), || Velocity { dx: 1 })
+            .unwrap();
+        for &entity in &entities {
+            assert_eq!(registry.get_component(entity), Some(&Velocity { dx: 1 }));
+        }
+
+        registry.destroy_entity(entities[4]).unwrap();
+        let result = registry.insert_batch(entities[2..].iter().copied(), || Position { x: 0 });
+        assert!(matches!(result, Err(RecsError::InvalidEntity(_))));
+        assert_eq!(
+            registry.get_component(entities[2]),
+            Some(&Position { x: 2 })
+        );
+    }
+
     #[test]
     fn test_destroy_entity_removes_all_components() {
         let mut registry = Registry::new();
@@ -387,4 +1961,438 @@ mod tests {
 
         assert_eq!(registry.get_resource::<GameTime>().unwrap().time, 1.0);
     }
+
+    #[derive(Default)]
+    struct Log(Vec<&'static str>);
+
+    impl Resource for Log {}
+
+    fn log_startup(mut log: ResMut<Log>) {
+        log.0.push("startup");
+    }
+
+    fn log_update(mut log: ResMut<Log>) {
+        log.0.push("update");
+    }
+
+    fn log_post_update(mut log: ResMut<Log>) {
+        log.0.push("post_update");
+    }
+
+    fn log_custom(mut log: ResMut<Log>) {
+        log.0.push("custom");
+    }
+
+    #[test]
+    fn test_stages_run_in_order() {
+        let mut registry = Registry::new();
+        registry.init_resource::<Log>();
+        registry.add_system_to(Stage::PostUpdate, log_post_update);
+        registry.add_system(log_update);
+        registry.add_system_to(Stage::Startup, log_startup);
+        registry.add_system_to(Stage::custom("editor"), log_custom);
+
+        registry.run_systems();
+        registry.run_systems();
+        assert_eq!(
+            registry.get_resource::<Log>().unwrap().0,
+            ["startup", "update", "post_update", "update", "post_update"]
+        );
+        assert_eq!(registry.system_count(), 4);
+
+        registry.get_resource_mut::<Log>().unwrap().0.clear();
+        registry.run_schedule(Stage::custom("editor"));
+        assert_eq!(registry.get_resource::<Log>().unwrap().0, ["custom"]);
+    }
+
+    #[test]
+    fn test_old_changes_are_not_reported_after_the_tick_wraps() {
+        use crate::{query::Changed, tick::MAX_CHANGE_AGE};
+
+        let mut registry = Registry::new();
+        registry.spawn((Position { x: 1 },));
+
+        // Maintenance clamps the ticks once they grew old enough
+        registry.change_tick = Tick::new(MAX_CHANGE_AGE + 100);
+        registry.maintain();
+
+        // The counter then wraps around, past the tick of the spawn
+        registry.last_change_tick = Tick::new(u32::MAX);
+        registry.change_tick = Tick::new(4);
+        let changed = registry
+            .query_filtered::<(&Position,), Changed<Position>>()
+            .count();
+        assert_eq!(changed, 0);
+    }
+
+    #[test]
+    fn test_schedule_is_kept_when_a_system_panics() {
+        let mut registry = Registry::new();
+        registry.init_resource::<Log>();
+        registry.add_system(log_update);
+        registry.add_system(|| panic!("boom"));
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            registry.run_schedule(Stage::Update);
+        }));
+        assert!(result.is_err());
+        assert_eq!(registry.system_count(), 2);
+
+        registry.get_resource_mut::<Log>().unwrap().0.clear();
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            registry.run_schedule(Stage::Update);
+        }));
+        assert_eq!(registry.get_resource::<Log>().unwrap().0, ["update"]);
+    }
+
+    #[test]
+    fn test_systems_added_while_running_are_kept() {
+        let mut registry = Registry::new();
+        registry.init_resource::<Log>();
+        registry.add_system_to(Stage::Startup, |mut commands: Commands| {
+            commands.add(|registry| registry.add_system_to(Stage::Startup, log_startup));
+            commands.add(|registry| registry.add_system(log_update));
+        });
+
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Log>().unwrap().0, ["update"]);
+        assert_eq!(registry.system_count(), 3);
+    }
+
+    #[test]
+    fn test_entity_guids() {
+        let mut registry = Registry::new();
+        let e1 = registry.create_entity();
+        let e2 = registry.create_entity();
+
+        let guid = registry.assign_guid(e1).unwrap();
+        assert_eq!(registry.assign_guid(e1).unwrap(), guid);
+        assert_eq!(registry.guid(e1), Some(guid));
+        assert!(matches!(
+            registry.set_guid(e2, guid),
+            Err(RecsError::DuplicateGuid(g)) if g == guid
+        ));
+
+        let loaded = EntityGuid::from_u128(42);
+        registry.set_guid(e2, loaded).unwrap();
+        assert_eq!(registry.entity_by_guid(loaded), Some(e2));
+        assert_eq!(registry.guids().count(), 2);
+
+        // Recycled ids don't inherit the GUID of the destroyed entity
+        registry.destroy_entity(e1).unwrap();
+        assert_eq!(registry.entity_by_guid(guid), None);
+        let e3 = registry.create_entity();
+        assert_eq!(e3.id(), e1.id());
+        assert_eq!(registry.guid(e3), None);
+        assert_eq!(registry.guid(e1), None);
+
+        assert_eq!(registry.remove_guid(e2), Some(loaded));
+        assert_eq!(registry.entity_by_guid(loaded), None);
+    }
+
+    #[test]
+    fn test_fixed_update_runs_once_per_step() {
+        use std::time::Duration;
+
+        fn fixed_step(mut log: ResMut<Log>) {
+            log.0.push("fixed");
+        }
+
+        let mut registry = Registry::new();
+        registry.init_resource::<Log>();
+        registry.insert_resource(Time::manual(Duration::from_millis(25)));
+        registry.insert_resource(FixedTime::new(Duration::from_millis(10)));
+        registry.add_system_to(Stage::FixedUpdate, fixed_step);
+        registry.add_system(log_update);
+
+        registry.run_systems();
+        assert_eq!(
+            registry.get_resource::<Log>().unwrap().0,
+            vec!["fixed", "fixed", "update"]
+        );
+
+        // The 5 ms left over complete a step together with the next frame
+        registry.get_resource_mut::<Log>().unwrap().0.clear();
+        registry.run_systems();
+        assert_eq!(
+            registry.get_resource::<Log>().unwrap().0,
+            vec!["fixed", "fixed", "fixed", "update"]
+        );
+
+        // Pausing time pauses the fixed schedule as well
+        registry
+            .get_resource_mut::<Time>()
+            .unwrap()
+            .set_scale(0.0)
+            .unwrap();
+        registry.get_resource_mut::<Log>().unwrap().0.clear();
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Log>().unwrap().0, vec!["update"]);
+    }
+
+    #[test]
+    fn test_insert_resource_if_absent() {
+        let mut registry = Registry::new();
+
+        assert!(registry.insert_resource_if_absent(GameTime { time: 1.0 }));
+        assert!(!registry.insert_resource_if_absent(GameTime { time: 2.0 }));
+        assert_eq!(registry.get_resource::<GameTime>().unwrap().time, 1.0);
+    }
+
+    #[test]
+    fn test_with_resource_override_restores_original() {
+        let mut registry = Registry::new();
+        registry.insert_resource(GameTime { time: 1.0 });
+
+        let seen = registry.with_resource_override(GameTime { time: 5.0 }, |registry| {
+            registry.get_resource::<GameTime>().unwrap().time
+        });
+        assert_eq!(seen, 5.0);
+        assert_eq!(registry.get_resource::<GameTime>().unwrap().time, 1.0);
+
+        // Without an original, the override is removed afterwards
+        registry.remove_resource::<GameTime>();
+        registry.with_resource_override(GameTime { time: 5.0 }, |_| {});
+        assert!(!registry.has_resource::<GameTime>());
+    }
+
+    #[test]
+    fn test_with_resource_override_restores_on_panic() {
+        let mut registry = Registry::new();
+        registry.insert_resource(GameTime { time: 1.0 });
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            registry.with_resource_override(GameTime { time: 5.0 }, |_| panic!("boom"));
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(registry.get_resource::<GameTime>().unwrap().time, 1.0);
+    }
+
+    #[test]
+    fn test_resource_scope_puts_the_resource_back() {
+        let mut registry = Registry::new();
+        registry.insert_resource(GameTime { time: 1.0 });
+        registry.spawn((Position { x: 2 },));
+
+        let total = registry.resource_scope(|registry, mut time: Mut<GameTime>| {
+            assert!(!registry.has_resource::<GameTime>());
+            for (position,) in registry.query::<(&Position,)>() {
+                time.time += position.x as f32;
+            }
+            time.time
+        });
+        assert_eq!(total, 3.0);
+        assert_eq!(registry.get_resource::<GameTime>().unwrap().time, 3.0);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            registry.resource_scope(|_, _: Mut<GameTime>| panic!("boom"));
+        }));
+        assert!(result.is_err());
+        assert!(registry.has_resource::<GameTime>());
+    }
+
+    #[test]
+    fn test_scoped_resources_keep_their_change_ticks() {
+        #[derive(Default)]
+        struct Seen(Vec<(bool, bool)>);
+        impl Resource for Seen {}
+
+        fn watch(time: Res<GameTime>, mut seen: ResMut<Seen>) {
+            seen.0.push((time.is_added(), time.is_changed()));
+        }
+
+        let mut registry = Registry::new();
+        registry.insert_resource(GameTime { time: 1.0 });
+        registry.init_resource::<Seen>();
+        registry.add_system(watch);
+        registry.run_systems();
+        registry.run_systems();
+
+        // Reading the resource in a scope or overriding it while nothing runs
+        // leaves it untouched
+        registry.resource_scope(|_, time: Mut<GameTime>| assert_eq!(time.time, 1.0));
+        registry.with_resource_override(GameTime { time: 5.0 }, |_| {});
+        registry.run_systems();
+        registry.resource_scope(|_, mut time: Mut<GameTime>| time.time = 2.0);
+        registry.run_systems();
+        registry.with_resource_override(GameTime { time: 5.0 }, Registry::run_systems);
+        registry.run_systems();
+
+        assert_eq!(
+            registry.get_resource::<Seen>().unwrap().0,
+            [
+                (true, true),
+                (false, false),
+                (false, false),
+                (false, true),
+                (true, true),
+                (false, true)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_component_codecs() {
+        let mut registry = Registry::new();
+        // Positions are quantized to a single byte
+        registry.register_component_codec(
+            |pos: &Position, bytes: &mut Vec<u8>| bytes.push(pos.x as u8),
+            |bytes| {
+                Some(Position {
+                    x: *bytes.first()? as i32,
+                })
+            },
+        );
+        assert!(registry.has_component_codec::<Position>());
+        assert!(!registry.has_component_codec::<Velocity>());
+
+        let source = registry.spawn((Position { x: 7 }, Velocity { dx: 1 }));
+        let packed = registry.pack_entity(source).unwrap();
+        assert_eq!(packed.len(), 1);
+        assert_eq!(packed[0].bytes, vec![7]);
+
+        let target = registry.create_entity();
+        registry.unpack_component(target, &packed[0]).unwrap();
+        assert_eq!(
+            registry.get_component::<Position>(target),
+            Some(&Position { x: 7 })
+        );
+
+        let empty = PackedComponent {
+            bytes: Vec::new(),
+            ..packed[0].clone()
+        };
+        assert!(matches!(
+            registry.unpack_component(target, &empty),
+            Err(RecsError::InvalidComponentData(_))
+        ));
+        let unknown = PackedComponent {
+            name: "Unknown".to_string(),
+            bytes: vec![0],
+        };
+        assert!(matches!(
+            registry.unpack_component(target, &unknown),
+            Err(RecsError::UnknownComponentCodec(_))
+        ));
+
+        registry.destroy_entity(source).unwrap();
+        assert!(registry.pack_entity(source).is_err());
+    }
+
+    #[test]
+    fn test_component_factory_runs_once() {
+        let mut registry = Registry::new();
+        registry.register_component_factory(|registry, entity| Velocity {
+            dx: registry
+                .get_component::<Position>(entity)
+                .map_or(0, |p| p.x * 2),
+        });
+        let entity = registry.spawn(Position { x: 4 });
+
+        assert!(registry.get_component::<Velocity>(entity).is_none());
+        assert_eq!(
+            registry.get_or_init_component::<Velocity>(entity).unwrap(),
+            &Velocity { dx: 8 }
+        );
+
+        registry.get_component_mut::<Velocity>(entity).unwrap().dx = 1;
+        assert!(!registry.init_component::<Velocity>(entity).unwrap());
+        assert_eq!(
+            registry.get_or_init_component::<Velocity>(entity).unwrap(),
+            &Velocity { dx: 1 }
+        );
+    }
+
+    #[test]
+    fn test_init_component_without_factory_returns_error() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn(Position { x: 4 });
+
+        assert!(matches!(
+            registry.get_or_init_component::<Velocity>(entity),
+            Err(RecsError::ComponentNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_get_or_insert_component_with() {
+        let mut registry = Registry::new();
+        let entity = registry.create_entity();
+
+        registry
+            .get_or_insert_component_with(entity, || Position { x: 1 })
+            .unwrap()
+            .x += 1;
+        registry
+            .get_or_insert_component_with(entity, || Position { x: 100 })
+            .unwrap()
+            .x += 1;
+
+        assert_eq!(
+            registry.get_component::<Position>(entity),
+            Some(&Position { x: 3 })
+        );
+    }
+
+    #[test]
+    fn test_removed_tracks_remove_and_destroy() {
+        let mut registry = Registry::new();
+        let e1 = registry.spawn((Position { x: 1 }, Velocity { dx: 1 }));
+        let e2 = registry.spawn(Position { x: 2 });
+
+        registry.remove_component::<Velocity>(e1).unwrap();
+        registry.destroy_entity(e2).unwrap();
+
+        assert_eq!(registry.removed::<Velocity>().collect::<Vec<_>>(), vec![e1]);
+        assert_eq!(registry.removed::<Position>().collect::<Vec<_>>(), vec![e2]);
+    }
+
+    #[test]
+    fn test_clear_trackers_resets_change_detection() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn(Position { x: 1 });
+        registry.remove_component::<Position>(entity).unwrap();
+        registry.add_component(entity, Velocity { dx: 1 }).unwrap();
+
+        assert_eq!(
+            registry
+                .query_filtered::<(&Velocity,), crate::query::Changed<Velocity>>()
+                .count(),
+            1
+        );
+        assert_eq!(registry.removed::<Position>().count(), 1);
+
+        registry.clear_trackers();
+
+        assert_eq!(
+            registry
+                .query_filtered::<(&Velocity,), crate::query::Changed<Velocity>>()
+                .count(),
+            0
+        );
+        assert_eq!(registry.removed::<Position>().count(), 0);
+
+        registry.get_component_mut::<Velocity>(entity).unwrap().dx = 2;
+        assert_eq!(
+            registry
+                .query_filtered::<(&Velocity,), crate::query::Changed<Velocity>>()
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_maintain_applies_commands_and_flushes_removals() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn(Position { x: 1 });
+
+        registry.commands().remove::<Position>(entity);
+        registry.maintain();
+        assert!(registry.get_component::<Position>(entity).is_none());
+        assert_eq!(registry.removed_components.get::<Position>().len(), 1);
+
+        registry.maintain();
+        assert!(registry.removed_components.get::<Position>().is_empty());
+    }
 }
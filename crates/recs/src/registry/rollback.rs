@@ -0,0 +1,366 @@
+//! In-memory snapshots of a registry for rollback netcode.
+//!
+//! `Registry::snapshot` clones the storages of the component and resource
+//! types registered with `register_rollback` and `register_rollback_resource`,
+//! along with the entity allocator, GUIDs and dynamic components. Components
+//! with a codec (see `Registry::register_component_codec`) are kept packed
+//! instead, and go through the codec again when restored.
+//! `Registry::restore` puts them back, so a client can rewind to a confirmed
+//! frame and resimulate from there.
+
+use std::any::{Any, TypeId};
+
+use crate::{
+    component::{
+        Component, ComponentStorage, codec::PackedStorage, dynamic::DynamicComponents,
+        sparse_set::SparseSet,
+    },
+    entity::{Entity, EntityManager, guid::GuidIndex},
+    hierarchy::{Children, Parent},
+    registry::Registry,
+    resource::Resource,
+};
+
+type Stored = Box<dyn Any + Send + Sync>;
+
+/// How one component or resource type is cloned into and out of a snapshot
+struct RollbackType {
+    type_id: TypeId,
+    snapshot: fn(&Registry) -> Option<Stored>,
+    restore: fn(&mut Registry, Option<&Stored>),
+}
+
+/// The component and resource types included in snapshots, in registration
+/// order
+pub(crate) struct RollbackTypes {
+    components: Vec<RollbackType>,
+    resources: Vec<RollbackType>,
+}
+
+impl RollbackTypes {
+    /// Creates the set of rollback types, which always includes the hierarchy
+    pub(crate) fn new() -> Self {
+        let mut types = Self {
+            components: Vec::new(),
+            resources: Vec::new(),
+        };
+        types.insert_component::<Parent>();
+        types.insert_component::<Children>();
+        types
+    }
+
+    fn insert_component<C: Component + Clone>(&mut self) {
+        let type_id = TypeId::of::<C>();
+        if self.components.iter().any(|ty| ty.type_id == type_id) {
+            return;
+        }
+        self.components.push(RollbackType {
+            type_id,
+            snapshot: |registry| {
+                let sparse_set = registry.components.sparse_set::<C>()?;
+                match registry.codecs.pack_storage(sparse_set) {
+                    Some(packed) => Some(Box::new(packed)),
+                    None => Some(Box::new(sparse_set.clone())),
+                }
+            },
+            restore: |registry, stored| {
+                let sparse_set =
+                    match stored.and_then(|stored| stored.downcast_ref::<PackedStorage>()) {
+                        Some(packed) => registry
+                            .codecs
+                            .unpack_storage(packed)
+                            .unwrap_or_else(|error| panic!("Can't restore a snapshot: {error}")),
+                        None => stored
+                            .and_then(|stored| stored.downcast_ref::<SparseSet<C>>())
+                            .cloned()
+                            .unwrap_or_default(),
+                    };
+                registry.storage_or_insert::<C>();
+                let replaced = registry.components.replace::<C>(Box::new(sparse_set));
+                registry.storages_changed();
+                registry.run_restore_hooks::<C>(replaced);
+            },
+        });
+    }
+
+    fn insert_resource<R: Resource + Clone>(&mut self) {
+        let type_id = TypeId::of::<R>();
+        if self.resources.iter().any(|ty| ty.type_id == type_id) {
+            return;
+        }
+        self.resources.push(RollbackType {
+            type_id,
+            snapshot: |registry| {
+                let resource = registry.get_resource::<R>()?;
+                Some(Box::new(resource.clone()))
+            },
+            restore: |registry, stored| match stored.and_then(|stored| stored.downcast_ref::<R>()) {
+                Some(resource) => registry.insert_resource(resource.clone()),
+                None => {
+                    registry.remove_resource::<R>();
+                }
+            },
+        });
+    }
+}
+
+/// A copy of the state of a registry, taken with `Registry::snapshot`
+pub struct WorldSnapshot {
+    entity_manager: EntityManager,
+    guids: GuidIndex,
+    dynamic: DynamicComponents,
+    /// Cloned storages of the rollback components, in registration order
+    components: Vec<Option<Stored>>,
+    /// Cloned rollback resources, in registration order
+    resources: Vec<Option<Stored>>,
+}
+
+impl Registry {
+    /// Includes `C` in snapshots. `Parent` and `Children` are always included.
+    pub fn register_rollback<C: Component + Clone>(&mut self) {
+        self.rollback.insert_component::<C>();
+    }
+
+    /// Includes the resource `R` in snapshots
+    pub fn register_rollback_resource<R: Resource + Clone>(&mut self) {
+        self.rollback.insert_resource::<R>();
+    }
+
+    /// Clones the rollback components and resources, the entity allocator,
+    /// GUIDs and dynamic components
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component, Clone)]
+    /// struct Position(i32);
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.register_rollback::<Position>();
+    /// let player = registry.spawn(Position(0));
+    ///
+    /// let confirmed = registry.snapshot();
+    /// registry.get_component_mut::<Position>(player).unwrap().0 = 5;
+    /// let projectile = registry.spawn(Position(1));
+    ///
+    /// registry.restore(&confirmed);
+    /// assert_eq!(registry.get_component::<Position>(player).unwrap().0, 0);
+    /// assert!(!registry.is_valid(projectile));
+    /// ```
+    pub fn snapshot(&self) -> WorldSnapshot {
+        let snapshot_all =
+            |types: &[RollbackType]| types.iter().map(|ty| (ty.snapshot)(self)).collect();
+        WorldSnapshot {
+            entity_manager: self.entity_manager.clone(),
+            guids: self.guids.clone(),
+            dynamic: self.dynamic.clone(),
+            components: snapshot_all(&self.rollback.components),
+            resources: snapshot_all(&self.rollback.resources),
+        }
+    }
+
+    /// Rewinds the registry to `snapshot`, which can be restored again later.
+    ///
+    /// Entities created since are destroyed and destroyed ones come back with
+    /// their rollback components. Components of other types are removed from
+    /// the entities that aren't alive in the snapshot, and kept as they are on
+    /// the others. Relationship indexes are rebuilt from the components.
+    ///
+    /// Every rollback component is replaced: the `on_remove` hooks run for the
+    /// current values and the `on_add` hooks for the restored ones, and the
+    /// `on_remove` hooks for the other components removed. Removals aren't
+    /// recorded for `RemovedComponents` or removal events.
+    ///
+    /// # Panics
+    /// Panics if a codec rejects the bytes it packed when the snapshot was
+    /// taken.
+    pub fn restore(&mut self, snapshot: &WorldSnapshot) {
+        self.entity_manager = snapshot.entity_manager.clone();
+        self.guids = snapshot.guids.clone();
+        self.dynamic = snapshot.dynamic.clone();
+
+        // Types registered after the snapshot was taken have nothing stored
+        let restores: Vec<_> = self
+            .rollback
+            .components
+            .iter()
+            .map(|ty| ty.restore)
+            .collect();
+        for (index, restore) in restores.into_iter().enumerate() {
+            restore(
+                self,
+                snapshot.components.get(index).and_then(Option::as_ref),
+            );
+        }
+        let restores: Vec<_> = self
+            .rollback
+            .resources
+            .iter()
+            .map(|ty| ty.restore)
+            .collect();
+        for (index, restore) in restores.into_iter().enumerate() {
+            restore(self, snapshot.resources.get(index).and_then(Option::as_ref));
+        }
+
+        let mut removed = Vec::new();
+        for (type_id, storage) in self.components.iter_mut() {
+            if self
+                .rollback
+                .components
+                .iter()
+                .any(|ty| ty.type_id == type_id)
+            {
+                continue;
+            }
+            let dead: Vec<Entity> = storage
+                .entities()
+                .iter()
+                .filter(|&&entity| !self.entity_manager.is_valid(entity))
+                .copied()
+                .collect();
+            for entity in dead {
+                if let Some(component) = storage.remove_by_id(entity.id() as usize) {
+                    removed.push((type_id, entity, component));
+                }
+            }
+        }
+        for (type_id, entity, component) in removed {
+            self.run_remove_hook(type_id, entity, component.as_ref());
+        }
+        self.rebuild_relations();
+        self.rebuild_groups();
+    }
+}
+
+impl Registry {
+    /// Runs the `on_remove` hooks of the `C` components of `replaced`, then
+    /// the `on_add` hooks of the `C` components now stored
+    fn run_restore_hooks<C: Component>(&mut self, replaced: Box<dyn ComponentStorage>) {
+        let type_id = TypeId::of::<C>();
+        if let Ok(replaced) = (replaced as Box<dyn Any>).downcast::<SparseSet<C>>() {
+            for (&entity, component) in replaced.entities.iter().zip(replaced.iter()) {
+                self.run_remove_hook(type_id, entity, component);
+            }
+        }
+        let restored = self
+            .components
+            .sparse_set::<C>()
+            .map_or_else(Vec::new, |ss| ss.entities.clone());
+        for entity in restored {
+            self.run_add_hook::<C>(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entity::Entity, relationship::Relationship};
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Health(u32);
+    impl Component for Health {}
+
+    #[derive(Debug, PartialEq)]
+    struct Label(&'static str);
+    impl Component for Label {}
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Targets(Entity);
+    impl Component for Targets {}
+    impl Relationship for Targets {
+        fn target(&self) -> Entity {
+            self.0
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Frame(u32);
+    impl Resource for Frame {}
+
+    #[test]
+    fn test_restore_rewinds_entities_components_and_resources() {
+        let mut registry = Registry::new();
+        registry.register_rollback::<Health>();
+        registry.register_rollback::<Targets>();
+        registry.register_rollback_resource::<Frame>();
+        registry.insert_resource(Frame(1));
+
+        let kept = registry.spawn((Health(10), Label("kept")));
+        let doomed = registry.spawn(Health(3));
+        registry.relate(doomed, Targets(kept)).unwrap();
+        let snapshot = registry.snapshot();
+
+        for _ in 0..2 {
+            registry.destroy_entity(doomed).unwrap();
+            registry.get_component_mut::<Health>(kept).unwrap().0 = 1;
+            let spawned = registry.spawn((Health(7), Label("new")));
+            registry.set_parent(spawned, kept).unwrap();
+            registry.relate(spawned, Targets(kept)).unwrap();
+            registry.insert_resource(Frame(2));
+
+            registry.restore(&snapshot);
+            assert_eq!(registry.get_component::<Health>(kept), Some(&Health(10)));
+            assert_eq!(registry.get_component::<Label>(kept), Some(&Label("kept")));
+            assert_eq!(registry.get_component::<Health>(doomed), Some(&Health(3)));
+            assert!(!registry.is_valid(spawned));
+            assert_eq!(registry.children(kept), &[] as &[Entity]);
+            assert_eq!(registry.related_to::<Targets>(kept), &[doomed]);
+            assert_eq!(registry.get_resource::<Frame>(), Some(&Frame(1)));
+            assert_eq!(registry.entities().count(), 2);
+            // The spawned entity reused the id of the destroyed one, and its
+            // components that aren't rolled back went away with it
+            assert_eq!(spawned.id(), doomed.id());
+            assert!(registry.get_component::<Label>(doomed).is_none());
+        }
+    }
+
+    #[test]
+    fn test_restore_runs_hooks_of_replaced_and_removed_components() {
+        use std::sync::{Arc, Mutex};
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut registry = Registry::new();
+        registry.register_rollback::<Health>();
+        let added = log.clone();
+        registry.on_add::<Health>(move |_, health, _| {
+            added.lock().unwrap().push(format!("add {}", health.0))
+        });
+        let removed = log.clone();
+        registry.on_remove::<Health>(move |_, health, _| {
+            removed.lock().unwrap().push(format!("remove {}", health.0))
+        });
+        let dropped = log.clone();
+        registry.on_remove::<Label>(move |_, label, _| {
+            dropped.lock().unwrap().push(format!("remove {}", label.0))
+        });
+
+        registry.spawn(Health(10));
+        let snapshot = registry.snapshot();
+        registry.spawn((Health(7), Label("new")));
+        log.lock().unwrap().clear();
+
+        registry.restore(&snapshot);
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["remove 10", "remove 7", "add 10", "remove new"]
+        );
+    }
+
+    #[test]
+    fn test_components_with_a_codec_are_snapshot_packed() {
+        let mut registry = Registry::new();
+        registry.register_rollback::<Health>();
+        // Health is stored in tens
+        registry.register_component_codec(
+            |health: &Health, bytes: &mut Vec<u8>| bytes.push((health.0 / 10) as u8),
+            |bytes| Some(Health(*bytes.first()? as u32 * 10)),
+        );
+        let entity = registry.spawn(Health(57));
+        let snapshot = registry.snapshot();
+        registry.get_component_mut::<Health>(entity).unwrap().0 = 1;
+
+        registry.restore(&snapshot);
+        assert_eq!(registry.get_component::<Health>(entity), Some(&Health(50)));
+    }
+}
@@ -0,0 +1,494 @@
+//! Saving registries incrementally, a chunk per frame.
+//!
+//! Serializing a large registry in one go can stall a frame for a long time.
+//! A `SnapshotWriter` instead writes a bounded number of entities per call, in
+//! id order, and keeps running over the following frames while the game goes
+//! on. Change ticks tell it which entities changed after they were written, so
+//! it writes those again, and the last chunk it writes leaves the loaded
+//! registry exactly as the saved one was at that moment.
+//!
+//! Every chunk is a separate serde document. A `SnapshotLoader` applies the
+//! chunks in the order they were written.
+
+use std::{collections::HashMap, fmt};
+
+use serde::{
+    Deserializer, Serialize, Serializer,
+    de::{self, MapAccess, SeqAccess, Visitor},
+    ser::SerializeStruct,
+};
+
+use crate::{
+    entity::{Entity, EntityManager},
+    registry::{
+        Registry,
+        serialize::{EntitiesSeed, EntityDoc, LoadedWorld},
+    },
+    tick::Tick,
+};
+
+/// How far a `SnapshotWriter` has come
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotProgress {
+    /// More chunks are needed. Holds how many entities were written so far,
+    /// including rewrites.
+    Pending(usize),
+    /// The last chunk was written
+    Complete,
+}
+
+/// What a `SnapshotWriter` remembers of an entity it wrote
+struct WrittenEntity {
+    generation: u32,
+    /// The change tick of the chunk that wrote the entity
+    tick: Tick,
+    /// How many serializable components the entity had, to notice removals
+    components: usize,
+}
+
+/// Writes a registry as a series of chunks over several frames, see the
+/// module documentation.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use recs::registry::savepoint::{SnapshotLoader, SnapshotProgress, SnapshotWriter};
+/// #[derive(Component, serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+/// struct Health(u32);
+///
+/// let mut registry = Registry::new();
+/// registry.register_serializable_as::<Health>("Health");
+/// let entities: Vec<_> = (0..10).map(|i| registry.spawn(Health(i))).collect();
+///
+/// let mut writer = SnapshotWriter::new(4);
+/// let mut chunks = Vec::new();
+/// loop {
+///     let mut chunk = Vec::new();
+///     let progress = writer
+///         .write_chunk(&mut registry, &mut serde_json::Serializer::new(&mut chunk))
+///         .unwrap();
+///     chunks.push(chunk);
+///     if progress == SnapshotProgress::Complete {
+///         break;
+///     }
+///     // The game keeps running between chunks
+///     registry.get_component_mut::<Health>(entities[0]).unwrap().0 = 100;
+/// }
+///
+/// let mut loaded = Registry::new();
+/// loaded.register_serializable_as::<Health>("Health");
+/// let mut loader = SnapshotLoader::new();
+/// for chunk in &chunks {
+///     loader
+///         .load_chunk(&mut loaded, &mut serde_json::Deserializer::from_slice(chunk))
+///         .unwrap();
+/// }
+/// assert!(loader.is_complete());
+/// assert_eq!(loaded.get_component::<Health>(entities[0]), Some(&Health(100)));
+/// assert_eq!(loaded.entities().count(), 10);
+/// ```
+pub struct SnapshotWriter {
+    /// The most entities written per chunk
+    chunk_size: usize,
+    /// The id from which entities haven't been visited yet
+    cursor: u32,
+    written: HashMap<u32, WrittenEntity>,
+    /// Entities written in total, including rewrites
+    total: usize,
+    complete: bool,
+}
+
+impl SnapshotWriter {
+    /// Creates a writer writing at most `chunk_size` entities per chunk
+    pub fn new(chunk_size: usize) -> Self {
+        Self {
+            chunk_size: chunk_size.max(1),
+            cursor: 0,
+            written: HashMap::new(),
+            total: 0,
+            complete: false,
+        }
+    }
+
+    /// Returns true once the last chunk was written
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Writes the next chunk to `serializer`.
+    ///
+    /// The chunk holds the entities destroyed since they were written, the
+    /// entities created or changed behind the writer's position, then entities
+    /// not visited yet, up to the chunk size in total. Once nothing is left,
+    /// the chunk also holds the generations of every id slot and the writer is
+    /// complete; calling it again writes empty chunks.
+    ///
+    /// Advances the registry's change tick so that later changes are told
+    /// apart from those already written.
+    pub fn write_chunk<S: Serializer>(
+        &mut self,
+        registry: &mut Registry,
+        serializer: S,
+    ) -> Result<SnapshotProgress, S::Error> {
+        let tick = registry.change_tick;
+        registry.change_tick = tick.next();
+        let registry = &*registry;
+
+        let mut despawned = Vec::new();
+        let mut entities = Vec::new();
+        if !self.complete {
+            self.written.retain(|&id, written| {
+                let entity = Entity::new(id, written.generation);
+                let alive = registry.is_valid(entity);
+                if !alive {
+                    despawned.push(entity);
+                }
+                alive
+            });
+
+            for entity in registry.entity_manager.iter() {
+                if entity.id() >= self.cursor || entities.len() == self.chunk_size {
+                    break;
+                }
+                if self.is_stale(registry, entity) {
+                    entities.push(entity);
+                }
+            }
+            for entity in registry.entity_manager.iter() {
+                if entities.len() == self.chunk_size {
+                    break;
+                }
+                if entity.id() >= self.cursor {
+                    entities.push(entity);
+                    self.cursor = entity.id() + 1;
+                }
+            }
+            if entities.len() < self.chunk_size {
+                self.cursor = registry.entity_manager.slot_count() as u32;
+            }
+        }
+
+        for &entity in &entities {
+            let components = registry
+                .serializable
+                .components
+                .values()
+                .filter(|component| (component.ticks)(registry, entity).is_some())
+                .count();
+            let written = WrittenEntity {
+                generation: entity.generation(),
+                tick,
+                components,
+            };
+            self.written.insert(entity.id(), written);
+        }
+        self.total += entities.len();
+
+        let complete = self.complete || entities.len() < self.chunk_size;
+        let generations = (complete && !self.complete).then(|| {
+            registry
+                .entity_manager
+                .slot_generations()
+                .map(|(_, generation)| generation)
+                .collect::<Vec<_>>()
+        });
+
+        let mut chunk = serializer.serialize_struct("SnapshotChunk", 3)?;
+        chunk.serialize_field("despawned", &despawned)?;
+        chunk.serialize_field(
+            "entities",
+            &EntityList {
+                registry,
+                entities: &entities,
+            },
+        )?;
+        chunk.serialize_field("generations", &generations)?;
+        chunk.end()?;
+
+        self.complete = complete;
+        Ok(if complete {
+            SnapshotProgress::Complete
+        } else {
+            SnapshotProgress::Pending(self.total)
+        })
+    }
+
+    /// Checks if `entity`, which is behind the cursor, was never written or
+    /// changed since it was
+    fn is_stale(&self, registry: &Registry, entity: Entity) -> bool {
+        let Some(written) = self
+            .written
+            .get(&entity.id())
+            .filter(|written| written.generation == entity.generation())
+        else {
+            return true;
+        };
+
+        let mut components = 0;
+        for component in registry.serializable.components.values() {
+            if let Some(ticks) = (component.ticks)(registry, entity) {
+                if ticks
+                    .changed
+                    .is_newer_than(written.tick, registry.change_tick)
+                {
+                    return true;
+                }
+                components += 1;
+            }
+        }
+        components != written.components
+    }
+}
+
+struct EntityList<'r> {
+    registry: &'r Registry,
+    entities: &'r [Entity],
+}
+
+impl Serialize for EntityList<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            self.entities
+                .iter()
+                .map(|&entity| EntityDoc(self.registry, entity)),
+        )
+    }
+}
+
+/// Applies the chunks written by a `SnapshotWriter` to a registry.
+///
+/// The first chunk replaces every entity of the registry. Until the last chunk
+/// is loaded, the registry holds components of entities it doesn't consider
+/// alive yet, so it shouldn't be used in between.
+#[derive(Default)]
+pub struct SnapshotLoader {
+    loaded: LoadedWorld,
+    started: bool,
+    complete: bool,
+}
+
+impl SnapshotLoader {
+    /// Creates a loader that hasn't loaded any chunk yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true once the last chunk was loaded
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Loads the next chunk from `deserializer`. Returns true if it was the
+    /// last one.
+    ///
+    /// Components must be registered with `register_serializable` under the
+    /// name they were saved with.
+    pub fn load_chunk<'de, D: Deserializer<'de>>(
+        &mut self,
+        registry: &mut Registry,
+        deserializer: D,
+    ) -> Result<bool, D::Error> {
+        if !self.started {
+            let entities: Vec<Entity> = registry.entities().collect();
+            for entity in entities {
+                let _ = registry.destroy_entity(entity);
+            }
+            self.started = true;
+        }
+
+        let generations = deserializer.deserialize_struct(
+            "SnapshotChunk",
+            &["despawned", "entities", "generations"],
+            ChunkVisitor {
+                registry: &mut *registry,
+                loaded: &mut self.loaded,
+            },
+        )?;
+
+        if let Some(generations) = generations {
+            registry.entity_manager = EntityManager::restore(generations, &self.loaded.alive);
+            self.complete = true;
+        }
+        Ok(self.complete)
+    }
+}
+
+struct ChunkVisitor<'a> {
+    registry: &'a mut Registry,
+    loaded: &'a mut LoadedWorld,
+}
+
+impl ChunkVisitor<'_> {
+    /// Removes every component of the destroyed entities, running their
+    /// `on_remove` hooks
+    fn despawn(&mut self, despawned: Vec<Entity>) {
+        for entity in despawned {
+            self.loaded.alive.remove(&entity);
+            self.registry.leave_groups(entity);
+            let mut removed = Vec::new();
+            for (type_id, storage) in self.registry.components.iter_mut() {
+                if let Some(component) = storage.remove_by_id(entity.id() as usize) {
+                    removed.push((type_id, component));
+                }
+            }
+            for (type_id, component) in removed {
+                self.registry
+                    .run_remove_hook(type_id, entity, component.as_ref());
+            }
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for ChunkVisitor<'_> {
+    type Value = Option<Vec<u32>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a snapshot chunk")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let despawned = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        self.despawn(despawned);
+        seq.next_element_seed(EntitiesSeed {
+            registry: &mut *self.registry,
+            loaded: &mut *self.loaded,
+        })?
+        .ok_or_else(|| de::Error::invalid_length(1, &"a snapshot chunk"))?;
+        seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(2, &"a snapshot chunk"))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut generations = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "despawned" => {
+                    let despawned = map.next_value()?;
+                    self.despawn(despawned);
+                }
+                "entities" => map.next_value_seed(EntitiesSeed {
+                    registry: &mut *self.registry,
+                    loaded: &mut *self.loaded,
+                })?,
+                "generations" => generations = map.next_value()?,
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(generations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::Component;
+
+    #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Health(u32);
+    impl Component for Health {}
+
+    #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Name(String);
+    impl Component for Name {}
+
+    fn new_registry() -> Registry {
+        let mut registry = Registry::new();
+        registry.register_serializable_as::<Health>("Health");
+        registry.register_serializable_as::<Name>("Name");
+        registry
+    }
+
+    fn write_chunk(writer: &mut SnapshotWriter, registry: &mut Registry) -> (Vec<u8>, bool) {
+        let mut chunk = Vec::new();
+        let progress = writer
+            .write_chunk(registry, &mut serde_json::Serializer::new(&mut chunk))
+            .unwrap();
+        (chunk, progress == SnapshotProgress::Complete)
+    }
+
+    #[test]
+    fn test_changes_between_chunks_end_up_in_the_snapshot() {
+        let mut registry = new_registry();
+        let entities: Vec<_> = (0..6).map(|i| registry.spawn(Health(i))).collect();
+        let mut writer = SnapshotWriter::new(2);
+        let mut chunks = Vec::new();
+
+        chunks.push(write_chunk(&mut writer, &mut registry).0);
+        // Changed, lost a component, destroyed and replaced behind the cursor
+        registry.get_component_mut::<Health>(entities[0]).unwrap().0 = 50;
+        registry
+            .add_component(entities[0], Name("hero".into()))
+            .unwrap();
+        registry.destroy_entity(entities[1]).unwrap();
+        let replacement = registry.spawn(Health(7));
+        assert_eq!(replacement.id(), entities[1].id());
+
+        chunks.push(write_chunk(&mut writer, &mut registry).0);
+        registry.remove_component::<Name>(entities[0]).unwrap();
+        registry.destroy_entity(entities[5]).unwrap();
+        loop {
+            let (chunk, complete) = write_chunk(&mut writer, &mut registry);
+            chunks.push(chunk);
+            if complete {
+                break;
+            }
+        }
+        assert!(writer.is_complete());
+
+        let mut loaded = new_registry();
+        loaded.spawn(Name("stale".into()));
+        let mut loader = SnapshotLoader::new();
+        for chunk in &chunks {
+            let complete = loader
+                .load_chunk(
+                    &mut loaded,
+                    &mut serde_json::Deserializer::from_slice(chunk),
+                )
+                .unwrap();
+            assert_eq!(complete, std::ptr::eq(chunk, chunks.last().unwrap()));
+        }
+
+        assert_eq!(
+            loaded.entities().collect::<Vec<_>>(),
+            registry.entities().collect::<Vec<_>>()
+        );
+        for entity in registry.entities() {
+            assert_eq!(
+                loaded.get_component::<Health>(entity),
+                registry.get_component::<Health>(entity)
+            );
+            assert!(loaded.get_component::<Name>(entity).is_none());
+        }
+        assert!(!loaded.is_valid(entities[1]) && !loaded.is_valid(entities[5]));
+    }
+
+    #[test]
+    fn test_unchanged_entities_are_written_once() {
+        let mut registry = new_registry();
+        for i in 0..5 {
+            registry.spawn(Health(i));
+        }
+        let mut writer = SnapshotWriter::new(2);
+        let mut progress = Vec::new();
+        while !writer.is_complete() {
+            let mut chunk = Vec::new();
+            progress.push(
+                writer
+                    .write_chunk(&mut registry, &mut serde_json::Serializer::new(&mut chunk))
+                    .unwrap(),
+            );
+        }
+        assert_eq!(
+            progress,
+            [
+                SnapshotProgress::Pending(2),
+                SnapshotProgress::Pending(4),
+                SnapshotProgress::Complete
+            ]
+        );
+    }
+}
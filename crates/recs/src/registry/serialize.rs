@@ -0,0 +1,684 @@
+//! Saving and loading whole registries through serde.
+//!
+//! Components opt in with `Registry::register_serializable`. A saved registry
+//! is a self-describing document listing the generation of every id slot and,
+//! for every alive entity, its id, generation and serializable components keyed
+//! by name:
+//!
+//! ```text
+//! (
+//!     generations: [1, 2, 1],
+//!     entities: [
+//!         (id: 0, generation: 1, components: { "Position": (x: 1.0, y: 2.0) }),
+//!         (id: 2, generation: 1, components: {}),
+//!     ],
+//! )
+//! ```
+//!
+//! Components that also have a codec (see `Registry::register_component_codec`)
+//! are saved as the list of bytes it packs instead of their serde value.
+
+use std::{
+    any::TypeId,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+};
+
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, SerializeStruct},
+};
+
+use crate::{
+    component::{Component, ComponentTicks},
+    entity::{Entity, EntityManager, map::EntityMapper},
+    registry::Registry,
+};
+
+type SerializeFn = for<'r> fn(&'r Registry, Entity) -> Option<SavedComponent<'r>>;
+type DeserializeFn = fn(
+    &mut Registry,
+    Entity,
+    &mut dyn erased_serde::Deserializer<'_>,
+) -> Result<(), erased_serde::Error>;
+
+/// Type-erased serde functions of a single component type
+#[derive(Clone, Copy)]
+pub(super) struct SerializableComponent {
+    pub(super) type_id: TypeId,
+    pub(super) serialize: SerializeFn,
+    /// Reads the serde value of the component, as written in scenes
+    #[cfg(feature = "ron")]
+    deserialize: DeserializeFn,
+    /// Reads the component as `serialize` wrote it, packed or not
+    load: DeserializeFn,
+    /// Removes the component from an entity
+    remove: fn(&mut Registry, Entity),
+    /// Returns the change ticks of the component of an entity
+    pub(super) ticks: fn(&Registry, Entity) -> Option<ComponentTicks>,
+}
+
+/// How a component is saved: packed by its codec if it has one, or as its
+/// serde value
+pub(super) enum SavedComponent<'r> {
+    Value(&'r dyn erased_serde::Serialize),
+    Packed(Vec<u8>),
+}
+
+impl Serialize for SavedComponent<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            SavedComponent::Value(value) => value.serialize(serializer),
+            SavedComponent::Packed(bytes) => bytes.serialize(serializer),
+        }
+    }
+}
+
+/// The component types saved by `Registry::serialize`, keyed by the name they
+/// are saved under
+#[derive(Default)]
+pub(crate) struct SerializableComponents {
+    /// Ordered by name so that saved entities have a deterministic layout
+    pub(super) components: BTreeMap<&'static str, SerializableComponent>,
+}
+
+/// Maps the entity ids used in a document, such as a scene, to the entities
+/// spawned for them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntityMap {
+    entities: HashMap<u32, Entity>,
+}
+
+impl EntityMap {
+    /// Creates an empty map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps the document id `id` to `entity`
+    pub fn insert(&mut self, id: u32, entity: Entity) {
+        self.entities.insert(id, entity);
+    }
+
+    /// Returns the entity spawned for the document id `id`
+    pub fn get(&self, id: u32) -> Option<Entity> {
+        self.entities.get(&id).copied()
+    }
+
+    /// Returns the entity spawned for the id of `entity`, or `entity` itself
+    /// if the document has no entity with that id
+    pub fn map(&self, entity: Entity) -> Entity {
+        self.get(entity.id()).unwrap_or(entity)
+    }
+
+    /// Returns the number of mapped ids
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Checks if no id is mapped
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Returns the mapped `(id, entity)` pairs in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (u32, Entity)> + '_ {
+        self.entities.iter().map(|(&id, &entity)| (id, entity))
+    }
+}
+
+impl EntityMapper for EntityMap {
+    /// Maps entities by id, ignoring their generation
+    fn map_entity(&mut self, entity: Entity) -> Entity {
+        self.map(entity)
+    }
+}
+
+impl SerializableComponents {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Registry {
+    /// Makes `C` part of the documents written by `serialize`, saved under its
+    /// type name
+    pub fn register_serializable<C>(&mut self)
+    where
+        C: Component + Serialize + for<'de> Deserialize<'de> + 'static,
+    {
+        self.register_serializable_as::<C>(std::any::type_name::<C>());
+    }
+
+    /// Makes `C` part of the documents written by `serialize`, saved under
+    /// `name`. Unlike type names, a chosen name survives moving or renaming
+    /// the type, which keeps old saves loadable.
+    pub fn register_serializable_as<C>(&mut self, name: &'static str)
+    where
+        C: Component + Serialize + for<'de> Deserialize<'de> + 'static,
+    {
+        let component = SerializableComponent {
+            type_id: TypeId::of::<C>(),
+            serialize: |registry, entity| {
+                let component = registry.get_component::<C>(entity)?;
+                Some(match registry.codecs.pack_value(component) {
+                    Some(bytes) => SavedComponent::Packed(bytes),
+                    None => SavedComponent::Value(component),
+                })
+            },
+            #[cfg(feature = "ron")]
+            deserialize: deserialize_value::<C>,
+            load: |registry, entity, deserializer| {
+                if !registry.codecs.contains::<C>() {
+                    return deserialize_value::<C>(registry, entity, deserializer);
+                }
+                let bytes: Vec<u8> = erased_serde::deserialize(deserializer)?;
+                let component: C = registry
+                    .codecs
+                    .unpack_value(&bytes)
+                    .map_err(de::Error::custom)?;
+                insert_loaded(registry, entity, component);
+                Ok(())
+            },
+            remove: |registry, entity| {
+                registry.remove_unchecked::<C>(entity);
+            },
+            ticks: |registry, entity| registry.ticks_unchecked::<C>(entity),
+        };
+        self.serializable.components.insert(name, component);
+    }
+
+    /// Inserts the component saved under `name` on `entity`, reading its
+    /// value from `deserializer`. Returns a description of the failure if no
+    /// component is registered under `name` or the value is invalid.
+    #[cfg(feature = "ron")]
+    pub(crate) fn deserialize_component(
+        &mut self,
+        entity: Entity,
+        name: &str,
+        deserializer: &mut dyn erased_serde::Deserializer<'_>,
+    ) -> Result<(), String> {
+        let component = self
+            .serializable
+            .components
+            .get(name)
+            .ok_or_else(|| format!("no serializable component named {name}"))?;
+        (component.deserialize)(self, entity, deserializer)
+            .map_err(|error| format!("invalid {name}: {error}"))
+    }
+
+    /// Writes every alive entity with its id, generation and serializable
+    /// components, along with the generations of freed ids, to `serializer`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component, serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    /// struct Health(u32);
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.register_serializable_as::<Health>("Health");
+    /// let dead = registry.create_entity();
+    /// registry.destroy_entity(dead).unwrap();
+    /// let hero = registry.spawn(Health(7));
+    ///
+    /// let mut saved = Vec::new();
+    /// registry.serialize(&mut serde_json::Serializer::new(&mut saved)).unwrap();
+    ///
+    /// let mut loaded = Registry::new();
+    /// loaded.register_serializable_as::<Health>("Health");
+    /// loaded.deserialize(&mut serde_json::Deserializer::from_slice(&saved)).unwrap();
+    /// assert_eq!(loaded.get_component::<Health>(hero), Some(&Health(7)));
+    /// assert!(!loaded.is_valid(dead));
+    /// ```
+    pub fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let generations: Vec<u32> = self
+            .entity_manager
+            .slot_generations()
+            .map(|(_, generation)| generation)
+            .collect();
+
+        let mut world = serializer.serialize_struct("World", 2)?;
+        world.serialize_field("generations", &generations)?;
+        world.serialize_field("entities", &EntitiesDoc(self))?;
+        world.end()
+    }
+
+    /// Replaces every entity of the registry with those of a document written
+    /// by `serialize`, restoring their ids, generations and components.
+    ///
+    /// Components must be registered with `register_serializable` under the
+    /// name they were saved with. Reserved entity ranges are released, and
+    /// relationships loaded this way are not indexed by `related_to`. If the
+    /// document is invalid the registry is left partially loaded.
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        &mut self,
+        deserializer: D,
+    ) -> Result<(), D::Error> {
+        let entities: Vec<Entity> = self.entities().collect();
+        for entity in entities {
+            let _ = self.destroy_entity(entity);
+        }
+
+        let mut loaded = LoadedWorld::default();
+        deserializer.deserialize_struct(
+            "World",
+            &["generations", "entities"],
+            WorldVisitor {
+                registry: self,
+                loaded: &mut loaded,
+            },
+        )?;
+
+        self.entity_manager = EntityManager::restore(loaded.generations, &loaded.alive);
+        Ok(())
+    }
+}
+
+/// Reads the serde value of a `C` and inserts it on `entity`
+fn deserialize_value<C>(
+    registry: &mut Registry,
+    entity: Entity,
+    deserializer: &mut dyn erased_serde::Deserializer<'_>,
+) -> Result<(), erased_serde::Error>
+where
+    C: Component + for<'de> Deserialize<'de>,
+{
+    let component: C = erased_serde::deserialize(deserializer)?;
+    insert_loaded(registry, entity, component);
+    Ok(())
+}
+
+/// Inserts a loaded component on an entity, which may not be alive yet
+fn insert_loaded<C: Component>(registry: &mut Registry, entity: Entity, component: C) {
+    registry.insert_unchecked(entity, component);
+    registry.insert_required_components();
+}
+
+struct EntitiesDoc<'r>(&'r Registry);
+
+impl Serialize for EntitiesDoc<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.entities().map(|entity| EntityDoc(self.0, entity)))
+    }
+}
+
+pub(super) struct EntityDoc<'r>(pub(super) &'r Registry, pub(super) Entity);
+
+impl Serialize for EntityDoc<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut entity = serializer.serialize_struct("Entity", 3)?;
+        entity.serialize_field("id", &self.1.id())?;
+        entity.serialize_field("generation", &self.1.generation())?;
+        entity.serialize_field("components", &ComponentsDoc(self.0, self.1))?;
+        entity.end()
+    }
+}
+
+struct ComponentsDoc<'r>(&'r Registry, Entity);
+
+impl Serialize for ComponentsDoc<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Self(registry, entity) = *self;
+        let mut map = serializer.serialize_map(None)?;
+        for (name, component) in &registry.serializable.components {
+            if let Some(value) = (component.serialize)(registry, entity) {
+                map.serialize_entry(name, &value)?;
+            }
+        }
+        map.end()
+    }
+}
+
+/// What has been read of a document so far
+#[derive(Default)]
+pub(super) struct LoadedWorld {
+    pub(super) generations: Vec<u32>,
+    pub(super) alive: HashSet<Entity>,
+}
+
+struct WorldVisitor<'a> {
+    registry: &'a mut Registry,
+    loaded: &'a mut LoadedWorld,
+}
+
+impl<'de> Visitor<'de> for WorldVisitor<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a saved registry")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        self.loaded.generations = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        seq.next_element_seed(EntitiesSeed {
+            registry: self.registry,
+            loaded: self.loaded,
+        })?
+        .ok_or_else(|| de::Error::invalid_length(1, &"a saved registry"))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "generations" => self.loaded.generations = map.next_value()?,
+                "entities" => map.next_value_seed(EntitiesSeed {
+                    registry: self.registry,
+                    loaded: self.loaded,
+                })?,
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(super) struct EntitiesSeed<'a> {
+    pub(super) registry: &'a mut Registry,
+    pub(super) loaded: &'a mut LoadedWorld,
+}
+
+impl<'de> DeserializeSeed<'de> for EntitiesSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for EntitiesSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of entities")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(entity) = seq.next_element_seed(EntitySeed {
+            registry: &mut *self.registry,
+        })? {
+            self.loaded.alive.insert(entity);
+        }
+        Ok(())
+    }
+}
+
+struct EntitySeed<'a> {
+    registry: &'a mut Registry,
+}
+
+impl<'de> DeserializeSeed<'de> for EntitySeed<'_> {
+    type Value = Entity;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Entity, D::Error> {
+        deserializer.deserialize_struct("Entity", &["id", "generation", "components"], self)
+    }
+}
+
+impl<'de> Visitor<'de> for EntitySeed<'_> {
+    type Value = Entity;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a saved entity")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Entity, A::Error> {
+        let id = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let generation = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let entity = Entity::new(id, generation);
+        seq.next_element_seed(ComponentsSeed {
+            registry: self.registry,
+            entity,
+        })?
+        .ok_or_else(|| de::Error::invalid_length(2, &"a saved entity"))?;
+        Ok(entity)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Entity, A::Error> {
+        let (mut id, mut generation) = (None, None);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "id" => id = Some(map.next_value()?),
+                "generation" => generation = Some(map.next_value()?),
+                "components" => {
+                    let (Some(id), Some(generation)) = (id, generation) else {
+                        return Err(de::Error::custom(
+                            "`components` must come after `id` and `generation`",
+                        ));
+                    };
+                    map.next_value_seed(ComponentsSeed {
+                        registry: &mut *self.registry,
+                        entity: Entity::new(id, generation),
+                    })?;
+                }
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+        let id = id.ok_or_else(|| de::Error::missing_field("id"))?;
+        let generation = generation.ok_or_else(|| de::Error::missing_field("generation"))?;
+        Ok(Entity::new(id, generation))
+    }
+}
+
+pub(super) struct ComponentsSeed<'a> {
+    pub(super) registry: &'a mut Registry,
+    pub(super) entity: Entity,
+}
+
+impl<'de> DeserializeSeed<'de> for ComponentsSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ComponentsSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map of components by name")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        // The document lists every serializable component of the entity, so
+        // those it had before loading are dropped
+        let components: Vec<_> = self
+            .registry
+            .serializable
+            .components
+            .values()
+            .copied()
+            .collect();
+        for component in components {
+            (component.remove)(self.registry, self.entity);
+        }
+
+        while let Some(name) = map.next_key::<String>()? {
+            let component = *self
+                .registry
+                .serializable
+                .components
+                .get(name.as_str())
+                .ok_or_else(|| {
+                    de::Error::custom(format!("no serializable component named {name}"))
+                })?;
+            map.next_value_seed(ComponentSeed {
+                registry: &mut *self.registry,
+                entity: self.entity,
+                load: component.load,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+struct ComponentSeed<'a> {
+    registry: &'a mut Registry,
+    entity: Entity,
+    load: DeserializeFn,
+}
+
+impl<'de> DeserializeSeed<'de> for ComponentSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        let mut erased = <dyn erased_serde::Deserializer>::erase(deserializer);
+        (self.load)(self.registry, self.entity, &mut erased).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Position {
+        x: f32,
+        y: f32,
+    }
+    impl Component for Position {}
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    enum Team {
+        Red,
+        Blue { captain: bool },
+    }
+    impl Component for Team {}
+
+    /// Not registered, so left out of saves
+    struct Scratch;
+    impl Component for Scratch {}
+
+    fn new_registry() -> Registry {
+        let mut registry = Registry::new();
+        registry.register_serializable_as::<Position>("Position");
+        registry.register_serializable::<Team>();
+        registry
+    }
+
+    #[test]
+    fn test_round_trip_keeps_ids_generations_and_components() {
+        let mut registry = new_registry();
+        let a = registry.spawn((Position { x: 1.0, y: 2.0 }, Team::Red));
+        let recycled = registry.create_entity();
+        registry.destroy_entity(recycled).unwrap();
+        let b = registry.spawn((Team::Blue { captain: true }, Scratch));
+        let freed = registry.create_entity();
+        registry.destroy_entity(freed).unwrap();
+        assert_eq!(b.id(), recycled.id());
+
+        let saved = serde_json::to_string(&Saved(&registry)).unwrap();
+        assert!(saved.contains(r#""Position":{"x":1.0,"y":2.0}"#));
+
+        let mut loaded = new_registry();
+        let stale = loaded.spawn(Team::Red);
+        loaded.destroy_entity(stale).unwrap();
+        loaded.spawn(Scratch);
+        loaded
+            .deserialize(&mut serde_json::Deserializer::from_str(&saved))
+            .unwrap();
+
+        assert_eq!(loaded.entities().collect::<Vec<_>>(), [a, b]);
+        assert_eq!(
+            loaded.get_component::<Position>(a),
+            Some(&Position { x: 1.0, y: 2.0 })
+        );
+        assert_eq!(
+            loaded.get_component::<Team>(b),
+            Some(&Team::Blue { captain: true })
+        );
+        assert!(loaded.get_component::<Scratch>(b).is_none());
+        assert!(!loaded.is_valid(recycled) && !loaded.is_valid(freed));
+
+        // Freed ids are reused with their saved generation
+        let reused = loaded.create_entity();
+        assert_eq!(
+            (reused.id(), reused.generation()),
+            (freed.id(), freed.generation() + 1)
+        );
+        assert_eq!(serde_json::to_string(&Saved(&registry)).unwrap(), saved);
+    }
+
+    #[test]
+    fn test_invalid_documents() {
+        let mut registry = new_registry();
+        let unknown = r#"{"generations":[1],"entities":[{"id":0,"generation":1,"components":{"Velocity":[]}}]}"#;
+        let error = registry
+            .deserialize(&mut serde_json::Deserializer::from_str(unknown))
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("no serializable component named Velocity")
+        );
+
+        let out_of_order = r#"{"entities":[{"components":{},"id":0,"generation":1}]}"#;
+        assert!(
+            registry
+                .deserialize(&mut serde_json::Deserializer::from_str(out_of_order))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_components_with_a_codec_are_saved_packed() {
+        fn register_codec(registry: &mut Registry) {
+            // Positions are rounded to whole units
+            registry.register_component_codec(
+                |pos: &Position, bytes: &mut Vec<u8>| bytes.extend([pos.x as u8, pos.y as u8]),
+                |bytes| match bytes {
+                    &[x, y] => Some(Position {
+                        x: x as f32,
+                        y: y as f32,
+                    }),
+                    _ => None,
+                },
+            );
+        }
+
+        let mut registry = new_registry();
+        register_codec(&mut registry);
+        let entity = registry.spawn((Position { x: 3.7, y: 4.0 }, Team::Red));
+        let saved = serde_json::to_string(&Saved(&registry)).unwrap();
+        assert!(saved.contains(r#""Position":[3,4]"#));
+        assert!(saved.contains(r#"Team":"Red""#));
+
+        let mut loaded = new_registry();
+        register_codec(&mut loaded);
+        loaded
+            .deserialize(&mut serde_json::Deserializer::from_str(&saved))
+            .unwrap();
+        assert_eq!(
+            loaded.get_component::<Position>(entity),
+            Some(&Position { x: 3.0, y: 4.0 })
+        );
+        assert_eq!(loaded.get_component::<Team>(entity), Some(&Team::Red));
+
+        let truncated = saved.replace("[3,4]", "[3]");
+        let error = loaded
+            .deserialize(&mut serde_json::Deserializer::from_str(&truncated))
+            .unwrap_err();
+        assert!(error.to_string().contains("not a valid"));
+    }
+
+    struct Saved<'r>(&'r Registry);
+
+    impl Serialize for Saved<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.0.serialize(serializer)
+        }
+    }
+}
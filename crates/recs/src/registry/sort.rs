@@ -0,0 +1,90 @@
+use std::{any::TypeId, cmp::Ordering};
+
+use crate::{component::Component, error::RecsError, registry::Registry};
+
+impl Registry {
+    /// Sorts the storage of `C` with `compare`, see `SparseSet::sort_by`.
+    ///
+    /// Queries whose smallest storage is the one of `C` then visit entities in
+    /// that order, until `C` components are added or removed. Returns
+    /// `UnsortableStorage` if `C` is stored in tables or owned by a group,
+    /// which keeps its own order.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Depth(i32);
+    ///
+    /// let mut registry = Registry::new();
+    /// for depth in [3, 1, 2] {
+    ///     registry.spawn((Depth(depth),));
+    /// }
+    ///
+    /// registry.sort_components_by::<Depth>(|a, b| a.0.cmp(&b.0)).unwrap();
+    /// let depths: Vec<i32> = registry.query::<(&Depth,)>().map(|(d,)| d.0).collect();
+    /// assert_eq!(depths, [1, 2, 3]);
+    /// ```
+    pub fn sort_components_by<C: Component>(
+        &mut self,
+        compare: impl FnMut(&C, &C) -> Ordering,
+    ) -> Result<(), RecsError> {
+        let type_id = TypeId::of::<C>();
+        let name = std::any::type_name::<C>();
+        if self.archetypes.is_table_component(type_id) {
+            return Err(RecsError::UnsortableStorage(format!(
+                "{name} is stored in tables"
+            )));
+        }
+        if self.is_group_owned(type_id) {
+            return Err(RecsError::UnsortableStorage(format!(
+                "{name} is owned by a group"
+            )));
+        }
+        if self.components.id(type_id).is_none() {
+            return Ok(());
+        }
+        self.components
+            .sparse_set_mut::<C>()
+            .expect("Component storages are sparse sets of their type")
+            .sort_by(compare);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Layer(u8);
+    impl Component for Layer {}
+
+    #[derive(Debug, PartialEq)]
+    struct Sprite;
+    impl Component for Sprite {}
+
+    #[test]
+    fn test_sorted_storage_drives_query_order() {
+        let mut registry = Registry::new();
+        let entities: Vec<_> = [2, 0, 1]
+            .map(|layer| registry.spawn((Layer(layer), Sprite)))
+            .into();
+
+        registry
+            .sort_components_by::<Layer>(|a, b| b.0.cmp(&a.0))
+            .unwrap();
+        let layers: Vec<u8> = registry.query::<(&Layer,)>().map(|(l,)| l.0).collect();
+        assert_eq!(layers, [2, 1, 0]);
+        assert_eq!(
+            registry.get_component::<Layer>(entities[1]),
+            Some(&Layer(0))
+        );
+
+        registry.group::<(Sprite,)>().unwrap();
+        assert!(matches!(
+            registry.sort_components_by::<Sprite>(|_, _| Ordering::Equal),
+            Err(RecsError::UnsortableStorage(_))
+        ));
+    }
+}
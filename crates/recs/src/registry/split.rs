@@ -0,0 +1,207 @@
+use std::{
+    any::TypeId,
+    ptr::{addr_of, addr_of_mut},
+};
+
+use crate::{
+    component::{Component, table::Archetypes},
+    entity::{Entity, EntityManager},
+    query::{ComponentStorages, QueryFilter, QueryIter, QueryParam},
+    registry::{Registry, cell::UnsafeRegistryCell, component, component_mut, contains_component},
+    resource::Resource,
+};
+
+impl Registry {
+    /// Splits the registry into a view over its entities and components and
+    /// a view over its resources, which can be borrowed at the same time.
+    ///
+    /// Neither view can add or remove anything: spawning, despawning or
+    /// inserting components may send events or run hooks that touch both
+    /// halves, so they stay on the registry itself.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Position(f32);
+    ///
+    /// #[derive(Resource, Default)]
+    /// struct Extracted(Vec<f32>);
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.spawn((Position(1.0),));
+    /// registry.spawn((Position(2.0),));
+    /// registry.init_resource::<Extracted>();
+    ///
+    /// let (mut components, mut resources) = registry.split();
+    /// let extracted = resources.get_resource_mut::<Extracted>().unwrap();
+    /// for (position,) in components.query::<(&Position,)>() {
+    ///     extracted.0.push(position.0);
+    /// }
+    /// assert_eq!(registry.get_resource::<Extracted>().unwrap().0.len(), 2);
+    /// ```
+    pub fn split(&mut self) -> (RegistryComponents<'_>, RegistryResources<'_>) {
+        let cell = UnsafeRegistryCell::new(self);
+        (RegistryComponents { cell }, RegistryResources { cell })
+    }
+}
+
+/// The entities and components of a registry, borrowed alongside its
+/// resources, see `Registry::split`
+pub struct RegistryComponents<'a> {
+    cell: UnsafeRegistryCell<'a>,
+}
+
+impl RegistryComponents<'_> {
+    /// Borrows the fields of the registry this view reads. The resource view
+    /// only touches the resources, and this view borrows fields mutably only
+    /// through `&mut self`, so the whole registry is never borrowed.
+    fn fields(&self) -> (&EntityManager, &ComponentStorages, &Archetypes) {
+        let registry = self.cell.as_ptr();
+        // SAFETY: see above
+        unsafe {
+            (
+                &*addr_of!((*registry).entity_manager),
+                &*addr_of!((*registry).components),
+                &*addr_of!((*registry).archetypes),
+            )
+        }
+    }
+
+    /// Checks if an entity is still alive in the registry
+    pub fn is_valid(&self, entity: Entity) -> bool {
+        self.fields().0.is_valid(entity)
+    }
+
+    /// Returns an iterator over every alive entity, in id order
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.fields().0.iter()
+    }
+
+    /// Returns the `C` component of `entity`, if it has one
+    pub fn get_component<C: Component>(&self, entity: Entity) -> Option<&C> {
+        let (entity_manager, components, archetypes) = self.fields();
+        if !entity_manager.is_valid(entity) {
+            return None;
+        }
+        component(components, archetypes, entity)
+    }
+
+    /// Checks if `entity` has a `C` component
+    pub fn has_component<C: Component>(&self, entity: Entity) -> bool {
+        let (entity_manager, components, archetypes) = self.fields();
+        entity_manager.is_valid(entity)
+            && contains_component(components, archetypes, TypeId::of::<C>(), entity)
+    }
+
+    /// Returns the `C` component of `entity`, if it has one, marking it as
+    /// changed
+    pub fn get_component_mut<C: Component>(&mut self, entity: Entity) -> Option<&mut C> {
+        if !self.is_valid(entity) {
+            return None;
+        }
+        let registry = self.cell.as_ptr();
+        // SAFETY: only the component storages are borrowed mutably, which
+        // the resource view never touches, for as long as `self` is
+        unsafe {
+            let tick = (*registry).change_tick;
+            component_mut(
+                &mut *addr_of_mut!((*registry).components),
+                &mut *addr_of_mut!((*registry).archetypes),
+                entity,
+                tick,
+            )
+        }
+    }
+
+    /// Queries entities matching `Q`, like `Registry::query`
+    pub fn query<'q, Q: QueryParam<'q>>(&'q mut self) -> QueryIter<'q, Q> {
+        self.query_filtered::<Q, ()>()
+    }
+
+    /// Queries entities matching `Q` that also pass the filter `F`, like
+    /// `Registry::query_filtered`
+    pub fn query_filtered<'q, Q: QueryParam<'q>, F: QueryFilter>(
+        &'q mut self,
+    ) -> QueryIter<'q, Q, F> {
+        // SAFETY: the map of storages is only borrowed to resolve the
+        // queried storages, and the view is borrowed mutably for 'q
+        let components = unsafe { self.cell.components() };
+        let storages = Q::storages(components);
+        let filter = F::state(components);
+        QueryIter::new(self.cell.as_ptr(), storages, filter)
+    }
+}
+
+/// The resources of a registry, borrowed alongside its entities and
+/// components, see `Registry::split`
+pub struct RegistryResources<'a> {
+    cell: UnsafeRegistryCell<'a>,
+}
+
+impl RegistryResources<'_> {
+    /// Gets a reference to resource `R`, if it exists
+    pub fn get_resource<R: Resource>(&self) -> Option<&R> {
+        // SAFETY: resources are only written through `&mut self`
+        unsafe { self.cell.get_resource_with_ticks::<R>() }.map(|(resource, _)| resource)
+    }
+
+    /// Gets a mutable reference to resource `R`, if it exists, marking it as
+    /// changed
+    pub fn get_resource_mut<R: Resource>(&mut self) -> Option<&mut R> {
+        // SAFETY: the view is borrowed mutably for as long as the resource,
+        // and the component view never touches resources
+        unsafe {
+            let tick = (*self.cell.as_ptr()).change_tick;
+            self.cell.get_resource_mut_with_tick::<R>(tick)
+        }
+    }
+
+    /// Checks if resource `R` exists
+    pub fn has_resource<R: Resource>(&self) -> bool {
+        self.get_resource::<R>().is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    impl Component for Position {}
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Gravity(i32);
+    impl Resource for Gravity {}
+
+    #[test]
+    fn test_split_borrows_components_and_resources_together() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn((Position(10),));
+        registry.insert_resource(Gravity(-1));
+        registry.clear_trackers();
+
+        let (mut components, mut resources) = registry.split();
+        let gravity = resources.get_resource_mut::<Gravity>().unwrap();
+        for (mut position,) in components.query::<(&mut Position,)>() {
+            position.0 += gravity.0;
+        }
+        gravity.0 = -2;
+        components.get_component_mut::<Position>(entity).unwrap().0 *= 2;
+        assert!(components.has_component::<Position>(entity));
+        assert!(resources.has_resource::<Gravity>());
+
+        assert_eq!(
+            registry.get_component::<Position>(entity),
+            Some(&Position(18))
+        );
+        assert_eq!(registry.get_resource::<Gravity>(), Some(&Gravity(-2)));
+        assert!(
+            registry
+                .resource_ticks::<Gravity>()
+                .unwrap()
+                .is_changed(registry.last_change_tick(), registry.change_tick())
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use std::{any::TypeId, collections::BTreeMap};
+
+use crate::{component::Component, registry::Registry};
+
+/// A snapshot of the shape of a registry, returned by `Registry::stats()`.
+///
+/// Maps are keyed by type name and ordered, so two snapshots of registries with
+/// the same shape compare (and serialize) equal. Serializable with the `serde`
+/// feature.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// #[derive(Component)]
+/// struct Position { x: f32 }
+///
+/// let mut registry = Registry::new();
+/// registry.spawn(Position { x: 0.0 });
+/// registry.spawn(Position { x: 1.0 });
+///
+/// let stats = registry.stats();
+/// assert_eq!(stats.entities, 2);
+/// assert_eq!(stats.component_count::<Position>(), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RegistryStats {
+    /// Number of alive entities, including disabled ones
+    pub entities: usize,
+    /// Number of alive entities carrying the `Disabled` marker
+    pub disabled_entities: usize,
+    /// Number of stored components per registered component type name
+    pub components: BTreeMap<&'static str, usize>,
+    /// Type names of the stored resources, sorted
+    pub resources: Vec<&'static str>,
+    /// Number of registered systems across all schedules
+    pub systems: usize,
+    /// Number of commands waiting to be applied
+    pub pending_commands: usize,
+    /// Number of retained events per registered event type name
+    pub events: BTreeMap<&'static str, usize>,
+}
+
+impl RegistryStats {
+    /// Returns the number of stored `C` components, or 0 if there are none
+    pub fn component_count<C: 'static>(&self) -> usize {
+        self.components
+            .get(std::any::type_name::<C>())
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns the number of retained events of type `T`, or 0 if there are none
+    pub fn event_count<T: 'static>(&self) -> usize {
+        self.events
+            .get(std::any::type_name::<T>())
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns true if a resource of type `R` is stored
+    pub fn has_resource<R: 'static>(&self) -> bool {
+        self.resources.contains(&std::any::type_name::<R>())
+    }
+}
+
+impl Registry {
+    /// Returns the number of alive entities, including disabled ones
+    pub fn entity_count(&self) -> usize {
+        self.entity_manager.alive_count()
+    }
+
+    /// Returns the number of stored `C` components
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Health(u32);
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.spawn((Health(10),));
+    /// registry.create_entity();
+    ///
+    /// assert_eq!(registry.entity_count(), 2);
+    /// assert_eq!(registry.component_count::<Health>(), 1);
+    /// ```
+    pub fn component_count<C: Component>(&self) -> usize {
+        self.component_count_by_id(TypeId::of::<C>())
+    }
+
+    /// Returns the readable name of a registered component type, i.e. one
+    /// whose storage was created
+    pub fn component_name(&self, type_id: TypeId) -> Option<&'static str> {
+        self.component_names.get(type_id)
+    }
+
+    fn component_count_by_id(&self, type_id: TypeId) -> usize {
+        match self.components.get(type_id) {
+            Some(storage) => storage.len(),
+            None => self.archetypes.count(type_id),
+        }
+    }
+
+    /// Takes a snapshot of the number of entities, components, resources and
+    /// systems in this registry
+    pub fn stats(&self) -> RegistryStats {
+        let components = self
+            .component_names
+            .iter()
+            .map(|(type_id, name)| (name, self.component_count_by_id(type_id)))
+            .collect();
+
+        let mut resources: Vec<_> = self.resources.names().collect();
+        resources.sort_unstable();
+
+        RegistryStats {
+            entities: self.entity_count(),
+            disabled_entities: self.disabled_storage().map_or(0, |ss| ss.len()),
+            components,
+            resources,
+            systems: self.system_count(),
+            pending_commands: self.command_queue.len(),
+            events: self.event_types.backlog(&self.resources).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{component::Component, entity::Disabled, resource::Resource};
+
+    struct Position;
+    impl Component for Position {}
+
+    struct Velocity;
+    impl Component for Velocity {}
+
+    #[derive(Default)]
+    struct Gravity;
+    impl Resource for Gravity {}
+
+    fn noop() {}
+
+    #[test]
+    fn test_stats_snapshot() {
+        let mut registry = Registry::new();
+        let e1 = registry.spawn((Position, Velocity));
+        let e2 = registry.spawn((Position,));
+        let e3 = registry.spawn((Position,));
+        registry.destroy_entity(e3).unwrap();
+        registry.add_component(e2, Disabled).unwrap();
+        registry.init_resource::<Gravity>();
+        registry.add_system(noop);
+        registry.commands().despawn(e1);
+        registry.add_event::<u32>();
+        registry.send_event(3u32);
+
+        let stats = registry.stats();
+        assert_eq!(stats.entities, 2);
+        assert_eq!(stats.disabled_entities, 1);
+        assert_eq!(stats.component_count::<Position>(), 2);
+        assert_eq!(stats.component_count::<Velocity>(), 1);
+        assert_eq!(stats.component_count::<Gravity>(), 0);
+        assert!(stats.has_resource::<Gravity>());
+        assert_eq!(stats.systems, 1);
+        assert_eq!(stats.pending_commands, 1);
+        assert_eq!(stats.event_count::<u32>(), 1);
+
+        registry.apply_commands();
+        assert_eq!(registry.stats().entities, 1);
+        assert_ne!(registry.stats(), stats);
+    }
+
+    #[test]
+    fn test_counts_cover_every_registered_type() {
+        let mut registry = Registry::new();
+        registry.register_component::<Velocity>();
+        registry.register_table_component::<Position>().unwrap();
+        registry.spawn((Position,));
+        registry.spawn((Position,));
+
+        assert_eq!(registry.entity_count(), 2);
+        assert_eq!(registry.component_count::<Position>(), 2);
+        assert_eq!(registry.component_count::<Velocity>(), 0);
+        assert_eq!(
+            registry.component_name(TypeId::of::<Velocity>()),
+            Some(std::any::type_name::<Velocity>())
+        );
+        assert_eq!(registry.component_name(TypeId::of::<Gravity>()), None);
+
+        let stats = registry.stats();
+        assert_eq!(stats.components.len(), 2);
+        assert_eq!(stats.component_count::<Velocity>(), 0);
+        assert_eq!(stats.component_count::<Position>(), 2);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_stats_serialize() {
+        let mut registry = Registry::new();
+        registry.spawn((Position,));
+
+        let json = serde_json::to_value(registry.stats()).unwrap();
+        assert_eq!(json["entities"], 1);
+        assert_eq!(json["components"][std::any::type_name::<Position>()], 1);
+    }
+}
@@ -0,0 +1,182 @@
+use std::any::{Any, TypeId};
+
+use crate::{
+    component::{Component, ComponentStorage, sparse_set::SparseSet},
+    error::RecsError,
+    registry::Registry,
+};
+
+impl Registry {
+    /// Stores `C` components in archetype tables instead of a sparse set.
+    ///
+    /// Tables suit components that are rarely added or removed but iterated
+    /// together in large numbers: `query_table` walks the rows of every
+    /// matching table contiguously, while adding or removing a table component
+    /// moves the entity's row to another table. Components already stored are
+    /// moved into the tables. Component types declared with
+    /// `#[component(storage = "table")]` are registered automatically.
+    ///
+    /// Returns `UntableableStorage` if a group owns the storage of `C`, since
+    /// the group packs that sparse set; a declared table type owned by a group
+    /// keeps its sparse set.
+    ///
+    /// Table components are accessed through the usual component methods, but
+    /// only `query_table` iterates them: `query`, `Query` and the `Changed`
+    /// and `Added` filters panic on a table component. Snapshots, scenes and
+    /// relationships don't support table components.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Position(f32);
+    ///
+    /// #[derive(Component)]
+    /// struct Velocity(f32);
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.register_table_component::<Position>().unwrap();
+    /// registry.register_table_component::<Velocity>().unwrap();
+    /// let moving = registry.spawn((Position(0.0), Velocity(2.0)));
+    /// registry.spawn((Position(5.0),));
+    ///
+    /// for (mut position, velocity) in registry.query_table::<(&mut Position, &Velocity)>() {
+    ///     position.0 += velocity.0;
+    /// }
+    /// assert_eq!(registry.get_component::<Position>(moving).unwrap().0, 2.0);
+    /// ```
+    pub fn register_table_component<C: Component>(&mut self) -> Result<(), RecsError> {
+        let type_id = TypeId::of::<C>();
+        if self.archetypes.is_table_component(type_id) {
+            return Ok(());
+        }
+        if self.is_group_owned(type_id) {
+            return Err(RecsError::UntableableStorage(format!(
+                "{} is owned by a group",
+                std::any::type_name::<C>()
+            )));
+        }
+        self.archetypes.register::<C>();
+        self.component_names.register::<C>();
+        self.movers.register::<C>();
+        self.cloners.register_declared::<C>();
+        self.hooks.register::<C>();
+        self.observers.register::<C>();
+        self.event_types.register_component::<C>();
+
+        let Some(storage) = self.components.move_to_tables(type_id) else {
+            return Ok(());
+        };
+        self.storages_changed();
+        let mut sparse_set = (storage as Box<dyn Any>)
+            .downcast::<SparseSet<C>>()
+            .expect("Component storages are sparse sets of their type");
+        let tick = self.change_tick;
+        for entity in sparse_set.entities().to_vec() {
+            if let Some(component) = sparse_set.remove(entity.id() as usize) {
+                self.archetypes.insert(entity, component, tick);
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks if `C` components are stored in archetype tables, see
+    /// `register_table_component`
+    pub fn is_table_component<C: Component>(&self) -> bool {
+        self.archetypes.is_table_component(TypeId::of::<C>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Entity;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    impl Component for Position {}
+
+    #[derive(Debug, PartialEq)]
+    struct Frozen;
+    impl Component for Frozen {}
+
+    #[derive(crate::Component)]
+    #[component(storage = "table")]
+    struct Mass(u32);
+
+    #[derive(crate::Component)]
+    #[component(storage = "sparse")]
+    struct Tag;
+
+    #[test]
+    fn test_table_components_behave_like_components() {
+        let mut registry = Registry::new();
+        let early = registry.spawn((Position(1), Frozen));
+        registry.register_table_component::<Position>().unwrap();
+        registry.register_table_component::<Frozen>().unwrap();
+        assert_eq!(
+            registry.get_component::<Position>(early),
+            Some(&Position(1))
+        );
+        assert_eq!(registry.stats().component_count::<Position>(), 1);
+
+        let late = registry.spawn((Position(2),));
+        registry.get_component_mut::<Position>(late).unwrap().0 = 3;
+        assert_eq!(registry.remove_component::<Frozen>(early).unwrap(), Frozen);
+        assert_eq!(
+            registry.get_component::<Position>(early),
+            Some(&Position(1))
+        );
+        assert_eq!(registry.get_component::<Position>(late), Some(&Position(3)));
+
+        registry.destroy_entity(early).unwrap();
+        let entities: Vec<Entity> = registry.removed::<Position>().collect();
+        assert_eq!(entities, [early]);
+        assert!(registry.get_component::<Position>(early).is_none());
+        assert_eq!(registry.stats().component_count::<Position>(), 1);
+    }
+
+    #[test]
+    fn test_declared_storage_is_honored() {
+        assert_eq!(Mass::STORAGE, crate::component::StorageType::Table);
+        let mut registry = Registry::new();
+        registry.spawn_batch((0..3).map(|mass| (Mass(mass), Tag)));
+        assert!(registry.is_table_component::<Mass>());
+        assert!(!registry.is_table_component::<Tag>());
+
+        let total: u32 = registry
+            .query_table::<(&Mass,)>()
+            .map(|(mass,)| mass.0)
+            .sum();
+        assert_eq!(total, 3);
+        assert_eq!(registry.query::<(&Tag,)>().count(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "is stored in archetype tables")]
+    fn test_queries_reject_table_components() {
+        let mut registry = Registry::new();
+        registry.spawn_batch((0..2).map(|mass| (Mass(mass),)));
+        registry.add_system(|masses: crate::query::Query<(&Mass,)>| {
+            assert_eq!(masses.iter().count(), 2);
+        });
+        registry.run_systems();
+    }
+
+    #[test]
+    fn test_group_owned_types_cant_move_to_tables() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn((Position(1), Frozen));
+        registry.group::<(Position, Frozen)>().unwrap();
+        assert!(matches!(
+            registry.register_table_component::<Position>(),
+            Err(RecsError::UntableableStorage(_))
+        ));
+        assert!(!registry.is_table_component::<Position>());
+        assert_eq!(registry.group::<(Position, Frozen)>().unwrap().len(), 1);
+        assert_eq!(
+            registry.get_component::<Position>(entity),
+            Some(&Position(1))
+        );
+    }
+}
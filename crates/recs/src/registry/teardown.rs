@@ -0,0 +1,245 @@
+use std::any::TypeId;
+
+use crate::registry::Registry;
+
+/// The order in which a group of storages is dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropOrder {
+    /// Storages created first are dropped first
+    Registration,
+    /// Storages created last are dropped first, like local variables
+    #[default]
+    ReverseRegistration,
+}
+
+impl DropOrder {
+    /// Sorts `order`, given in registration order, into this drop order
+    pub(crate) fn arrange(self, mut order: Vec<TypeId>) -> Vec<TypeId> {
+        if self == DropOrder::ReverseRegistration {
+            order.reverse();
+        }
+        order
+    }
+}
+
+/// Whether resources are dropped before or after the components
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResourceTeardown {
+    /// Resources are dropped before any component storage
+    BeforeComponents,
+    /// Resources outlive every component, e.g. so that a device resource is
+    /// still alive while the buffers stored in components are released
+    #[default]
+    AfterComponents,
+}
+
+/// Defines how a `Registry` destroys its data when it is dropped.
+///
+/// Teardown always happens in three steps:
+/// 1. Pending commands are dropped without being applied.
+/// 2. Components and resources are dropped, each group in its `DropOrder`,
+///    resources before or after components per `resources`. Components are
+///    dropped type by type, sparse-set and table types alike, by when each
+///    type was first registered. Within one type, components are dropped in
+///    storage order, which is unrelated to entity ids. Non-send resources are
+///    dropped right after the other resources, in the same order, and leaked
+///    if the registry is dropped on another thread than the one that created
+///    it. Dynamic components are plain bytes, with nothing to drop.
+/// 3. The remaining state (systems, events bookkeeping, ...) is dropped.
+///
+/// Teardown doesn't run `on_remove` hooks or observers: the commands they
+/// queue could never be applied. Destroy the entities before dropping the
+/// registry to run them.
+///
+/// Set it with `Registry::set_teardown_policy`.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use recs::registry::teardown::{DropOrder, ResourceTeardown, TeardownPolicy};
+/// let mut registry = Registry::new();
+/// registry.set_teardown_policy(TeardownPolicy {
+///     components: DropOrder::Registration,
+///     resources: DropOrder::ReverseRegistration,
+///     resource_teardown: ResourceTeardown::AfterComponents,
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TeardownPolicy {
+    /// The order component storages are dropped in, by when each component
+    /// type was first registered
+    pub components: DropOrder,
+    /// The order resources are dropped in, by when each was inserted
+    pub resources: DropOrder,
+    /// Whether resources are dropped before or after the components
+    pub resource_teardown: ResourceTeardown,
+}
+
+impl Registry {
+    /// Sets how the registry destroys its components and resources when it
+    /// is dropped. Defaults to reverse registration order for both, with
+    /// resources dropped after components.
+    pub fn set_teardown_policy(&mut self, policy: TeardownPolicy) {
+        self.teardown = policy;
+    }
+
+    /// Returns how the registry destroys its data when it is dropped
+    pub fn teardown_policy(&self) -> TeardownPolicy {
+        self.teardown
+    }
+
+    fn drop_components(&mut self) {
+        let order = self.component_names.order().to_vec();
+        for type_id in self.teardown.components.arrange(order) {
+            drop(self.components.remove(type_id));
+            self.archetypes.clear_column(type_id);
+        }
+        // Storages created without a typed registration, if any
+        let order = self.components.type_ids();
+        for type_id in self.teardown.components.arrange(order) {
+            drop(self.components.remove(type_id));
+        }
+    }
+
+    fn drop_resources(&mut self) {
+        let order = self.teardown.resources;
+        self.resources.clear_in(order);
+        self.non_send.clear_in(order);
+    }
+}
+
+impl Drop for Registry {
+    fn drop(&mut self) {
+        drop(std::mem::take(&mut self.command_queue));
+
+        match self.teardown.resource_teardown {
+            ResourceTeardown::BeforeComponents => {
+                self.drop_resources();
+                self.drop_components();
+            }
+            ResourceTeardown::AfterComponents => {
+                self.drop_components();
+                self.drop_resources();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{component::Component, resource::Resource};
+
+    type DropLog = Arc<Mutex<Vec<&'static str>>>;
+
+    struct Logged<const N: usize>(&'static str, DropLog);
+
+    impl<const N: usize> Drop for Logged<N> {
+        fn drop(&mut self) {
+            self.1.lock().unwrap().push(self.0);
+        }
+    }
+
+    impl<const N: usize> Component for Logged<N> {}
+    impl<const N: usize> Resource for Logged<N> {}
+
+    /// A table component
+    struct Tabled(&'static str, DropLog);
+
+    impl Drop for Tabled {
+        fn drop(&mut self) {
+            self.1.lock().unwrap().push(self.0);
+        }
+    }
+
+    impl Component for Tabled {
+        const STORAGE: crate::component::StorageType = crate::component::StorageType::Table;
+    }
+
+    fn populate(registry: &mut Registry, log: &DropLog) {
+        registry.insert_resource(Logged::<0>("resource a", log.clone()));
+        registry.spawn(Logged::<1>("component a", log.clone()));
+        registry.insert_resource(Logged::<2>("resource b", log.clone()));
+        registry.spawn(Logged::<3>("component b", log.clone()));
+    }
+
+    #[test]
+    fn test_default_teardown_order() {
+        let log = DropLog::default();
+        let mut registry = Registry::new();
+        populate(&mut registry, &log);
+        drop(registry);
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["component b", "component a", "resource b", "resource a"]
+        );
+    }
+
+    #[test]
+    fn test_configured_teardown_order() {
+        let log = DropLog::default();
+        let mut registry = Registry::new();
+        registry.set_teardown_policy(TeardownPolicy {
+            components: DropOrder::Registration,
+            resources: DropOrder::Registration,
+            resource_teardown: ResourceTeardown::BeforeComponents,
+        });
+        populate(&mut registry, &log);
+        drop(registry);
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["resource a", "resource b", "component a", "component b"]
+        );
+    }
+
+    #[test]
+    fn test_pending_commands_are_dropped_unapplied() {
+        let log = DropLog::default();
+        let mut registry = Registry::new();
+        registry.spawn(Logged::<1>("component", log.clone()));
+        registry
+            .commands()
+            .spawn((Logged::<3>("queued component", log.clone()),));
+        drop(registry);
+
+        assert_eq!(*log.lock().unwrap(), vec!["queued component", "component"]);
+    }
+
+    #[test]
+    fn test_tables_and_non_send_resources_follow_the_policy() {
+        let log = DropLog::default();
+        let mut registry = Registry::new();
+        registry.set_teardown_policy(TeardownPolicy {
+            components: DropOrder::Registration,
+            resources: DropOrder::ReverseRegistration,
+            resource_teardown: ResourceTeardown::BeforeComponents,
+        });
+        registry.spawn(Logged::<1>("component a", log.clone()));
+        registry.spawn(Tabled("table component", log.clone()));
+        registry.spawn(Logged::<3>("component b", log.clone()));
+        registry.insert_non_send_resource(Logged::<0>("non-send a", log.clone()));
+        registry.insert_resource(Logged::<2>("resource", log.clone()));
+        registry.insert_non_send_resource(Logged::<4>("non-send b", log.clone()));
+
+        // Teardown doesn't run hooks
+        let hooked = log.clone();
+        registry.on_remove::<Logged<1>>(move |_, _, _| hooked.lock().unwrap().push("hook"));
+        drop(registry);
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "resource",
+                "non-send b",
+                "non-send a",
+                "component a",
+                "table component",
+                "component b",
+            ]
+        );
+    }
+}
@@ -0,0 +1,348 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+use crate::{
+    component::{Component, clone::EntityMap},
+    entity::{Entity, map::MapEntities},
+    error::RecsError,
+    hierarchy::{Children, Parent},
+    registry::Registry,
+};
+
+type InsertFn = fn(&mut Registry, Entity, Box<dyn Any>);
+
+/// Type-erased insertion of every component type used in a registry, so its
+/// components can be moved into another registry
+#[derive(Default)]
+pub(crate) struct ComponentMovers {
+    inserters: HashMap<TypeId, InsertFn>,
+}
+
+impl ComponentMovers {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records how to insert `C` components
+    pub(crate) fn register<C: Component>(&mut self) {
+        self.inserters.insert(TypeId::of::<C>(), insert_boxed::<C>);
+    }
+
+    fn get(&self, type_id: TypeId) -> Option<InsertFn> {
+        self.inserters.get(&type_id).copied()
+    }
+}
+
+fn insert_boxed<C: Component>(registry: &mut Registry, entity: Entity, component: Box<dyn Any>) {
+    if let Ok(component) = component.downcast::<C>() {
+        registry.insert_unchecked(entity, *component);
+    }
+}
+
+impl Registry {
+    /// Moves `entity` with all of its components into `target`, e.g. to hand a
+    /// player over to the registry of another zone, and returns its handle in
+    /// `target`.
+    ///
+    /// Components are moved rather than cloned, so they don't need a clone
+    /// function. The entity leaves this registry as if destroyed, running the
+    /// remove hooks of its components, which then run their add hooks in
+    /// `target`. Its GUID moves along, while its hierarchy and relations are
+    /// detached and its dynamic components dropped, since they refer to data
+    /// of this registry.
+    ///
+    /// Returns `InvalidEntity` if the entity is invalid, or `DuplicateGuid` if
+    /// another entity of `target` carries its GUID, in which case nothing is
+    /// moved.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Component)]
+    /// struct Player { name: String }
+    ///
+    /// let mut forest = Registry::new();
+    /// let mut castle = Registry::new();
+    /// let player = forest.spawn((Player { name: "Ayla".into() },));
+    ///
+    /// let moved = forest.move_entity(&mut castle, player).unwrap();
+    /// assert!(!forest.is_valid(player));
+    /// assert_eq!(castle.get_component::<Player>(moved).unwrap().name, "Ayla");
+    /// ```
+    pub fn move_entity(
+        &mut self,
+        target: &mut Registry,
+        entity: Entity,
+    ) -> Result<Entity, RecsError> {
+        if !self.entity_manager.is_valid(entity) {
+            return Err(RecsError::InvalidEntity(entity));
+        }
+        let guid = self.guid(entity);
+        if let Some(guid) = guid.filter(|&guid| target.entity_by_guid(guid).is_some()) {
+            return Err(RecsError::DuplicateGuid(guid));
+        }
+
+        self.detach_hierarchy(entity);
+        self.detach_relations(entity);
+        let components = self.take_components(entity);
+        self.destroy_entity(entity)?;
+
+        let moved = target.create_entity();
+        self.insert_components_into(target, moved, components);
+        target.insert_required_components();
+        if let Some(guid) = guid {
+            target.set_guid(moved, guid)?;
+        }
+        Ok(moved)
+    }
+
+    /// Moves every entity of `other` into this registry, e.g. to splice in a
+    /// level streamed in on another thread, and returns the map from each
+    /// entity of `other` to its new handle.
+    ///
+    /// Components are moved along with their entities, running their remove
+    /// hooks in `other` and their add hooks here. The hierarchy is kept, and
+    /// components registered with `register_map_entities` in `other` are
+    /// remapped to point at the new entities. GUIDs move along, unless an
+    /// entity of this registry already carries them. `resources` selects which
+    /// resources of `other` move too. Relations, dynamic components, non-send
+    /// resources and systems are left behind.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use recs::registry::transfer::MergeResources;
+    /// #[derive(Component)]
+    /// struct Tile(u32);
+    ///
+    /// #[derive(Resource)]
+    /// struct LevelName(&'static str);
+    ///
+    /// let mut world = Registry::new();
+    /// world.spawn((Tile(0),));
+    ///
+    /// let mut level = Registry::new();
+    /// let tile = level.spawn((Tile(1),));
+    /// level.insert_resource(LevelName("caves"));
+    ///
+    /// let map = world.merge(level, MergeResources::Missing);
+    /// assert_eq!(world.get_component::<Tile>(map[&tile]).unwrap().0, 1);
+    /// assert_eq!(world.query::<(&Tile,)>().count(), 2);
+    /// assert_eq!(world.get_resource::<LevelName>().unwrap().0, "caves");
+    /// ```
+    pub fn merge(&mut self, mut other: Registry, resources: MergeResources) -> EntityMap {
+        let entities: Vec<(Entity, Entity)> = other
+            .entities()
+            .map(|entity| (entity, self.create_entity()))
+            .collect();
+        let mut map: EntityMap = entities.iter().copied().collect();
+
+        for &(entity, merged) in &entities {
+            let components = other.take_components(entity);
+            other.insert_components_into(self, merged, components);
+            if let Some(guid) = other.guid(entity)
+                && self.entity_by_guid(guid).is_none()
+            {
+                let _ = self.set_guid(merged, guid);
+            }
+        }
+        // Every entity exists before references are remapped
+        for &(_, merged) in &entities {
+            other.map_entities_in(self, merged, &mut map);
+            if let Some(parent) = self.get_component_mut::<Parent>(merged) {
+                parent.map_entities(&mut map);
+            }
+            if let Some(children) = self.get_component_mut::<Children>(merged) {
+                children.map_entities(&mut map);
+            }
+        }
+        self.insert_required_components();
+
+        if resources != MergeResources::None {
+            let moved = std::mem::take(&mut other.resources);
+            let replace = resources == MergeResources::All;
+            self.resources.merge(moved, replace, self.change_tick);
+        }
+        map
+    }
+
+    /// Removes every component of `entity` from its storages, running their
+    /// remove hooks and recording the removals like `remove_component`. The
+    /// entity leaves its groups first, so that they don't count it anymore.
+    fn take_components(&mut self, entity: Entity) -> Vec<(TypeId, Box<dyn Any>)> {
+        self.leave_groups(entity);
+        let id = entity.id() as usize;
+        let mut components = Vec::new();
+        for (type_id, storage) in self.components.iter_mut() {
+            if let Some(component) = storage.remove_by_id(id) {
+                components.push((type_id, component));
+            }
+        }
+        components.extend(self.archetypes.remove_all(entity));
+        for (type_id, component) in &components {
+            self.component_removed(*type_id, entity, component.as_ref());
+        }
+        components
+    }
+
+    /// Inserts `components`, taken from this registry, on `entity` of
+    /// `target`, leaving required components pending
+    pub(crate) fn insert_components_into(
+        &self,
+        target: &mut Registry,
+        entity: Entity,
+        components: Vec<(TypeId, Box<dyn Any>)>,
+    ) {
+        for (type_id, component) in components {
+            let insert = self
+                .movers
+                .get(type_id)
+                .expect("Stored component types are registered");
+            insert(target, entity, component);
+        }
+    }
+
+    /// Inserts `components`, taken or cloned from this registry, on
+    /// `entity`, leaving required components pending
+    pub(crate) fn insert_components(
+        &mut self,
+        entity: Entity,
+        components: Vec<(TypeId, Box<dyn Any>)>,
+    ) {
+        let inserts: Vec<(InsertFn, Box<dyn Any>)> = components
+            .into_iter()
+            .map(|(type_id, component)| {
+                let insert = self
+                    .movers
+                    .get(type_id)
+                    .expect("Stored component types are registered");
+                (insert, component)
+            })
+            .collect();
+        for (insert, component) in inserts {
+            insert(self, entity, component);
+        }
+    }
+}
+
+/// Which resources `Registry::merge` moves along with the entities
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeResources {
+    /// Leaves every resource behind
+    #[default]
+    None,
+    /// Moves the resources this registry doesn't have yet
+    Missing,
+    /// Moves every resource, replacing those of this registry, including
+    /// event buffers
+    All,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::guid::EntityGuid;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+    impl Component for Health {}
+
+    #[derive(crate::Component, Debug, PartialEq)]
+    #[component(storage = "table")]
+    struct Position(i32);
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity(u32);
+    impl Component for Velocity {}
+
+    #[test]
+    fn test_move_entity_relocates_every_component() {
+        let mut source = Registry::new();
+        let mut target = Registry::new();
+        target.spawn((Health(1),));
+        let entity = source.spawn((Health(10), Position(3)));
+        let guid = source.assign_guid(entity).unwrap();
+        let child = source.spawn((Health(5),));
+        source.set_parent(child, entity).unwrap();
+
+        let moved = source.move_entity(&mut target, entity).unwrap();
+        assert!(!source.is_valid(entity));
+        assert_eq!(source.removed::<Health>().collect::<Vec<_>>(), [entity]);
+        assert_eq!(source.parent(child), None);
+        assert_eq!(target.get_component::<Health>(moved), Some(&Health(10)));
+        assert_eq!(target.get_component::<Position>(moved), Some(&Position(3)));
+        assert_eq!(target.entity_by_guid(guid), Some(moved));
+        assert!(source.move_entity(&mut target, entity).is_err());
+    }
+
+    #[test]
+    fn test_merge_remaps_hierarchy_and_references() {
+        #[derive(Debug, PartialEq)]
+        struct Target(Entity);
+        impl Component for Target {}
+        impl MapEntities for Target {
+            fn map_entities(&mut self, mapper: &mut dyn crate::entity::map::EntityMapper) {
+                self.0.map_entities(mapper);
+            }
+        }
+
+        #[derive(Debug, PartialEq)]
+        struct Seed(u64);
+        impl crate::resource::Resource for Seed {}
+
+        let mut world = Registry::new();
+        world.spawn((Health(1),));
+        world.insert_resource(Seed(1));
+
+        let mut level = Registry::new();
+        level.register_map_entities::<Target>();
+        let parent = level.spawn((Health(10),));
+        let child = level.spawn((Position(2), Target(parent)));
+        level.set_parent(child, parent).unwrap();
+        level.insert_resource(Seed(2));
+
+        let map = world.merge(level, MergeResources::All);
+        let (parent, child) = (map[&parent], map[&child]);
+        assert_eq!(world.get_component::<Health>(parent), Some(&Health(10)));
+        assert_eq!(world.get_component::<Position>(child), Some(&Position(2)));
+        assert_eq!(world.get_component::<Target>(child), Some(&Target(parent)));
+        assert_eq!(world.parent(child), Some(parent));
+        assert_eq!(world.children(parent), &[child]);
+        assert_eq!(world.get_resource::<Seed>(), Some(&Seed(2)));
+    }
+
+    #[test]
+    fn test_move_entity_leaves_groups() {
+        let mut source = Registry::new();
+        let mut target = Registry::new();
+        let entity = source.spawn((Health(1), Velocity(2)));
+        source.spawn((Health(3),));
+        let kept = source.spawn((Health(4), Velocity(5)));
+        source.group::<(Health, Velocity)>().unwrap();
+
+        source.move_entity(&mut target, entity).unwrap();
+        let group = source.group::<(Health, Velocity)>().unwrap();
+        let healths: Vec<u32> = group.iter().map(|(health, _)| health.0).collect();
+        assert_eq!(healths, [4]);
+        assert!(source.is_valid(kept));
+    }
+
+    #[test]
+    fn test_move_entity_rejects_duplicate_guids() {
+        let mut source = Registry::new();
+        let mut target = Registry::new();
+        let guid = EntityGuid::generate();
+        let entity = source.spawn((Health(10),));
+        source.set_guid(entity, guid).unwrap();
+        let existing = target.create_entity();
+        target.set_guid(existing, guid).unwrap();
+
+        assert!(matches!(
+            source.move_entity(&mut target, entity),
+            Err(RecsError::DuplicateGuid(_))
+        ));
+        assert_eq!(source.get_component::<Health>(entity), Some(&Health(10)));
+    }
+}
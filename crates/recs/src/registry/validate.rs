@@ -0,0 +1,464 @@
+use std::fmt;
+
+#[cfg(feature = "ron")]
+use crate::scene::Scene;
+use crate::{
+    entity::Entity,
+    hierarchy::{Children, Parent},
+    registry::Registry,
+    system::schedule::Stage,
+};
+
+/// A system that needs a resource the registry doesn't hold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingResource {
+    /// The stage the system is registered in
+    pub stage: Stage,
+    /// The name of the system
+    pub system: String,
+    /// The type name of the missing resource
+    pub resource: &'static str,
+}
+
+/// Two systems of one stage that conflict over some data without being
+/// ordered relative to each other, see `Schedule::ambiguities`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ambiguity {
+    /// The stage both systems are registered in
+    pub stage: Stage,
+    /// The names of the two systems, in current execution order
+    pub systems: (String, String),
+}
+
+/// Which side of a parent/child link is dangling
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HierarchyLink {
+    /// The entity's `Parent` points at a dead entity, or at one whose
+    /// `Children` doesn't list it
+    Parent,
+    /// The entity's `Children` lists a dead entity, or one whose `Parent`
+    /// points elsewhere
+    Child,
+}
+
+/// A hierarchy link the other side doesn't agree with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DanglingLink {
+    /// The entity holding the link
+    pub entity: Entity,
+    /// The entity the link points at
+    pub target: Entity,
+    pub link: HierarchyLink,
+}
+
+/// A component of a scene that the registry can't spawn or patch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownSceneComponent {
+    /// The name the scene was validated under
+    pub scene: String,
+    /// The scene-local id of the entity using the component
+    pub entity: u32,
+    /// The component name, which isn't registered as serializable or, for
+    /// patches, for reflection
+    pub component: String,
+}
+
+/// The problems found by `Registry::validate()`, ordered by stage.
+///
+/// Displays as a human-readable list of every problem.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ValidationReport {
+    /// Stages whose ordering constraints form a cycle, with the systems involved
+    pub cycles: Vec<(Stage, Vec<String>)>,
+    /// Systems whose `Res`, `ResMut` or event parameters would panic
+    pub missing_resources: Vec<MissingResource>,
+    /// Conflicting systems whose relative order is left to insertion order
+    pub ambiguities: Vec<Ambiguity>,
+    /// Parent/child links whose two sides disagree, by entity
+    pub dangling_links: Vec<DanglingLink>,
+    /// Components of the scenes passed to `Registry::validate_scenes` that
+    /// aren't registered
+    pub unknown_scene_components: Vec<UnknownSceneComponent>,
+}
+
+impl ValidationReport {
+    /// Returns true if no cycles, missing resources, dangling links or
+    /// unknown scene components were found.
+    ///
+    /// Ambiguities are only warnings: the order they leave open is still
+    /// deterministic.
+    pub fn is_ok(&self) -> bool {
+        self.cycles.is_empty()
+            && self.missing_resources.is_empty()
+            && self.dangling_links.is_empty()
+            && self.unknown_scene_components.is_empty()
+    }
+
+    /// Returns true if nothing at all was found, ambiguities included
+    pub fn is_clean(&self) -> bool {
+        self.is_ok() && self.ambiguities.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_clean() {
+            return write!(f, "No problems found");
+        }
+        for (stage, systems) in &self.cycles {
+            writeln!(
+                f,
+                "{:?}: ordering constraints form a cycle between: {}",
+                stage,
+                systems.join(", ")
+            )?;
+        }
+        for missing in &self.missing_resources {
+            writeln!(
+                f,
+                "{:?}: system {} requires missing resource {}",
+                missing.stage, missing.system, missing.resource
+            )?;
+        }
+        for dangling in &self.dangling_links {
+            let link = match dangling.link {
+                HierarchyLink::Parent => "parent",
+                HierarchyLink::Child => "child",
+            };
+            writeln!(
+                f,
+                "entity {:?} has dangling {} link to {:?}",
+                dangling.entity, link, dangling.target
+            )?;
+        }
+        for unknown in &self.unknown_scene_components {
+            writeln!(
+                f,
+                "scene {}: entity {} uses unregistered component {}",
+                unknown.scene, unknown.entity, unknown.component
+            )?;
+        }
+        for ambiguity in &self.ambiguities {
+            writeln!(
+                f,
+                "{:?}: systems {} and {} conflict but are not ordered",
+                ambiguity.stage, ambiguity.systems.0, ambiguity.systems.1
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Orders stages as they run in a frame, custom stages last by name
+pub(crate) fn stage_key(stage: &Stage) -> (u8, &str) {
+    match stage {
+        Stage::Startup => (0, ""),
+        Stage::PreUpdate => (1, ""),
+        Stage::FixedUpdate => (2, ""),
+        Stage::Update => (3, ""),
+        Stage::PostUpdate => (4, ""),
+        Stage::Custom(name) => (5, name),
+    }
+}
+
+impl Registry {
+    /// Checks the registered systems for problems that would otherwise only
+    /// show up mid-session: ordering cycles, resources required by `Res`,
+    /// `ResMut`, `EventReader` or `EventWriter` parameters that don't exist,
+    /// ambiguities, and parent/child links whose two sides disagree.
+    ///
+    /// Meant to be called once at boot, after every system, resource and
+    /// event type was registered. Resources inserted later, e.g. by startup
+    /// commands, are reported as missing.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Resource)]
+    /// struct Score(u32);
+    ///
+    /// fn show_score(score: Res<Score>) {
+    ///     println!("{}", score.0);
+    /// }
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.add_system(show_score);
+    ///
+    /// let report = registry.validate();
+    /// assert!(!report.is_ok());
+    /// assert_eq!(report.missing_resources[0].resource, std::any::type_name::<Score>());
+    ///
+    /// registry.insert_resource(Score(0));
+    /// assert!(registry.validate().is_ok());
+    /// ```
+    pub fn validate(&mut self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+
+        let mut stages: Vec<Stage> = self.schedules.keys().cloned().collect();
+        stages.sort_by(|a, b| stage_key(a).cmp(&stage_key(b)));
+
+        for stage in stages {
+            let schedule = self.schedules.get_mut(&stage).unwrap();
+
+            match schedule.ambiguities() {
+                Ok(ambiguities) => {
+                    report
+                        .ambiguities
+                        .extend(ambiguities.into_iter().map(|systems| Ambiguity {
+                            stage: stage.clone(),
+                            systems,
+                        }))
+                }
+                Err(crate::error::RecsError::SystemOrderCycle(systems)) => {
+                    report.cycles.push((stage.clone(), systems))
+                }
+                Err(_) => unreachable!("resolving the system order only fails on cycles"),
+            }
+
+            for system in schedule.systems() {
+                for (resource, type_id) in system.access().required_resources() {
+                    if !self.resources.contains_id(type_id) && !self.non_send.contains_id(type_id) {
+                        report.missing_resources.push(MissingResource {
+                            stage: stage.clone(),
+                            system: system.name().into_owned(),
+                            resource,
+                        });
+                    }
+                }
+            }
+        }
+
+        report.dangling_links = self.dangling_links();
+        report
+    }
+
+    /// Validates the registry like `validate`, and checks that every
+    /// component of the named `scenes` is registered as serializable, and
+    /// every patched one for reflection.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use recs::scene::Scene;
+    /// let scene = Scene::from_ron(r#"(entities: [(id: 0, components: { "Door": () })])"#).unwrap();
+    ///
+    /// let mut registry = Registry::new();
+    /// let report = registry.validate_scenes([("level", &scene)]);
+    /// assert_eq!(report.unknown_scene_components[0].component, "Door");
+    /// ```
+    #[cfg(feature = "ron")]
+    pub fn validate_scenes<'a>(
+        &mut self,
+        scenes: impl IntoIterator<Item = (&'a str, &'a Scene)>,
+    ) -> ValidationReport {
+        let mut report = self.validate();
+        for (name, scene) in scenes {
+            let unknown = |entity: u32, component: &str| UnknownSceneComponent {
+                scene: name.to_string(),
+                entity,
+                component: component.to_string(),
+            };
+            for entity in &scene.entities {
+                for component in entity.components.keys() {
+                    if !self
+                        .serializable
+                        .components
+                        .contains_key(component.as_str())
+                    {
+                        report
+                            .unknown_scene_components
+                            .push(unknown(entity.id, component));
+                    }
+                }
+            }
+            for patch in &scene.patches {
+                for key in patch.fields.keys() {
+                    let component = key.split('.').next().unwrap_or(key);
+                    if !self.reflected.contains(component) {
+                        report
+                            .unknown_scene_components
+                            .push(unknown(patch.id, component));
+                    }
+                }
+            }
+        }
+        report
+    }
+
+    /// Returns the parent/child links whose two sides disagree
+    fn dangling_links(&self) -> Vec<DanglingLink> {
+        let mut dangling = Vec::new();
+        for entity in self.entities() {
+            if let Some(parent) = self.get_component::<Parent>(entity).map(Parent::get)
+                && !self.children(parent).contains(&entity)
+            {
+                dangling.push(DanglingLink {
+                    entity,
+                    target: parent,
+                    link: HierarchyLink::Parent,
+                });
+            }
+            for &child in self
+                .get_component::<Children>(entity)
+                .map_or(&[][..], |c| c)
+            {
+                if self.parent(child) != Some(entity) {
+                    dangling.push(DanglingLink {
+                        entity,
+                        target: child,
+                        link: HierarchyLink::Child,
+                    });
+                }
+            }
+        }
+        dangling
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        event::{EventReader, EventWriter},
+        resource::{Res, ResMut, Resource},
+        system::schedule::IntoSystemConfig,
+    };
+
+    struct Score(u32);
+    impl Resource for Score {}
+
+    struct Hit;
+
+    fn count_hits(mut score: ResMut<Score>, hits: EventReader<Hit>) {
+        score.0 += hits.len() as u32;
+    }
+    fn send_hits(mut hits: EventWriter<Hit>) {
+        hits.send(Hit);
+    }
+    fn show_score(_score: Res<Score>) {}
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let mut registry = Registry::new();
+        registry.add_system(count_hits);
+        registry.add_system(show_score);
+        registry.add_system_to(Stage::PostUpdate, send_hits.after(show_score));
+        registry.add_system_to(Stage::PostUpdate, show_score.after(send_hits));
+
+        let report = registry.validate();
+        assert!(!report.is_ok());
+        assert_eq!(report.cycles.len(), 1);
+        assert_eq!(report.cycles[0].0, Stage::PostUpdate);
+
+        // Both Update systems miss Score, count_hits misses the Hit events
+        let missing: Vec<(&str, &str)> = report
+            .missing_resources
+            .iter()
+            .filter(|missing| missing.stage == Stage::Update)
+            .map(|missing| {
+                (
+                    missing.system.rsplit("::").next().unwrap(),
+                    missing.resource.rsplit("::").next().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            missing,
+            [
+                ("count_hits", "Hit>"),
+                ("count_hits", "Score"),
+                ("show_score", "Score")
+            ]
+        );
+
+        assert_eq!(report.ambiguities.len(), 1);
+        assert_eq!(report.ambiguities[0].stage, Stage::Update);
+        assert!(report.to_string().contains("conflict but are not ordered"));
+    }
+
+    #[test]
+    fn test_validate_passes_once_fixed() {
+        let mut registry = Registry::new();
+        registry.insert_resource(Score(0));
+        registry.add_event::<Hit>();
+        registry.add_system(count_hits);
+        registry.add_system(show_score.after(count_hits));
+        registry.add_system(send_hits.before(count_hits));
+
+        let report = registry.validate();
+        assert!(report.is_clean(), "{report}");
+    }
+
+    #[test]
+    fn test_validate_reports_dangling_hierarchy_links() {
+        let mut registry = Registry::new();
+        let parent = registry.create_entity();
+        let child = registry.create_entity();
+        let orphan = registry.create_entity();
+        registry.set_parent(child, parent).unwrap();
+        registry.set_parent(orphan, parent).unwrap();
+        assert!(registry.validate().dangling_links.is_empty());
+
+        // Raw component edits bypass the hierarchy methods
+        registry.remove_component::<Parent>(orphan).unwrap();
+        let children = registry.get_component::<Children>(parent).unwrap().clone();
+        registry.add_component(child, children).unwrap();
+
+        let report = registry.validate();
+        assert!(!report.is_ok());
+        assert_eq!(
+            report.dangling_links,
+            [
+                DanglingLink {
+                    entity: parent,
+                    target: orphan,
+                    link: HierarchyLink::Child,
+                },
+                DanglingLink {
+                    entity: child,
+                    target: child,
+                    link: HierarchyLink::Child,
+                },
+                DanglingLink {
+                    entity: child,
+                    target: orphan,
+                    link: HierarchyLink::Child,
+                },
+            ]
+        );
+        assert!(report.to_string().contains("dangling child link"));
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn test_validate_scenes_reports_unregistered_components() {
+        #[derive(serde::Serialize, serde::Deserialize, crate::Reflect)]
+        struct Door {
+            locked: bool,
+        }
+        impl crate::component::Component for Door {}
+
+        let scene = Scene::from_ron(
+            r#"(
+                entities: [(id: 0, components: { "Door": (locked: true), "Key": () })],
+                patches: [(id: 0, fields: { "Door.locked": false })],
+            )"#,
+        )
+        .unwrap();
+        let mut registry = Registry::new();
+        registry.register_serializable_as::<Door>("Door");
+
+        let report = registry.validate_scenes([("vault", &scene)]);
+        let unknown: Vec<&str> = report
+            .unknown_scene_components
+            .iter()
+            .map(|unknown| unknown.component.as_str())
+            .collect();
+        assert_eq!(unknown, ["Key", "Door"]);
+        assert_eq!(report.unknown_scene_components[0].scene, "vault");
+
+        registry.register_reflect_as::<Door>("Door");
+        let report = registry.validate_scenes([("vault", &scene)]);
+        assert_eq!(report.unknown_scene_components.len(), 1);
+    }
+}
@@ -0,0 +1,241 @@
+//! Cleanup of relationships pointing at destroyed entities.
+//!
+//! `Registry::relate` keeps relations consistent when endpoints are destroyed,
+//! but relationship components added directly, cloned from another registry or
+//! restored from a snapshot can still point at dead entities.
+//! `Registry::collect_dangling_relations` finds those and handles them
+//! according to the registry's `DanglingPolicy`.
+
+use crate::{
+    entity::Entity,
+    plugin::Plugin,
+    registry::Registry,
+    system::{
+        IntoSystem, System,
+        schedule::{IntoSystemConfig, Stage},
+    },
+    tick::Tick,
+};
+
+/// The label of the system added by `DanglingRelationsPlugin`
+pub const COLLECT_DANGLING_RELATIONS: &str = "collect_dangling_relations";
+
+/// What to do with a relationship whose target was destroyed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DanglingPolicy {
+    /// Remove the relationship component from its holder
+    #[default]
+    Clear,
+    /// Destroy the entity holding the relationship
+    DespawnHolder,
+    /// Leave the relationship in place and send a `DanglingRelation` event.
+    /// It is reported again on every pass until it is handled.
+    Report,
+}
+
+/// A relationship whose target is no longer alive, also sent as an event under
+/// `DanglingPolicy::Report`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DanglingRelation {
+    /// The entity holding the relationship component
+    pub holder: Entity,
+    /// The dead entity it points at
+    pub target: Entity,
+    /// The name of the relationship type
+    pub relationship: &'static str,
+}
+
+impl Registry {
+    /// Sets what `collect_dangling_relations` does with the relations it
+    /// finds. `Report` registers the `DanglingRelation` event.
+    pub fn set_dangling_policy(&mut self, policy: DanglingPolicy) {
+        if policy == DanglingPolicy::Report {
+            self.add_event::<DanglingRelation>();
+        }
+        self.relations.policy = policy;
+    }
+
+    /// Returns what `collect_dangling_relations` does with the relations it
+    /// finds
+    pub fn dangling_policy(&self) -> DanglingPolicy {
+        self.relations.policy
+    }
+
+    /// Finds the relationships pointing at destroyed entities, handles them
+    /// according to the `DanglingPolicy` and returns them.
+    ///
+    /// Only relationship types used with `relate` or registered with
+    /// `register_relationship` are checked.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use recs::relationship::Relationship;
+    /// #[derive(Component)]
+    /// struct OwnedBy(Entity);
+    ///
+    /// impl Relationship for OwnedBy {
+    ///     fn target(&self) -> Entity {
+    ///         self.0
+    ///     }
+    /// }
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.register_relationship::<OwnedBy>();
+    /// let owner = registry.create_entity();
+    /// let sword = registry.spawn(OwnedBy(owner));
+    ///
+    /// // Added directly, so the registry doesn't clean it up on destroy
+    /// registry.destroy_entity(owner).unwrap();
+    /// assert!(registry.get_component::<OwnedBy>(sword).is_some());
+    ///
+    /// let dangling = registry.collect_dangling_relations();
+    /// assert_eq!(dangling[0].holder, sword);
+    /// assert!(registry.get_component::<OwnedBy>(sword).is_none());
+    /// ```
+    pub fn collect_dangling_relations(&mut self) -> Vec<DanglingRelation> {
+        let found: Vec<_> = self
+            .relations
+            .indexes
+            .iter()
+            .flat_map(|(&type_id, index)| {
+                (index.find_dangling)(self)
+                    .into_iter()
+                    .map(move |(holder, target)| {
+                        let relation = DanglingRelation {
+                            holder,
+                            target,
+                            relationship: index.name,
+                        };
+                        (type_id, index.remove, relation)
+                    })
+            })
+            .collect();
+
+        let mut dangling = Vec::with_capacity(found.len());
+        for (type_id, remove, relation) in found {
+            match self.relations.policy {
+                DanglingPolicy::Clear => {
+                    if let Some(index) = self.relations.indexes.get_mut(&type_id) {
+                        index.unlink(relation.holder);
+                    }
+                    remove(self, relation.holder);
+                }
+                DanglingPolicy::DespawnHolder => {
+                    // The holder may already be gone if it held several
+                    let _ = self.destroy_entity(relation.holder);
+                }
+                DanglingPolicy::Report => self.send_event(relation),
+            }
+            dangling.push(relation);
+        }
+        dangling
+    }
+}
+
+/// The system added by `DanglingRelationsPlugin`, running
+/// `collect_dangling_relations`
+#[derive(Default)]
+pub struct CollectDanglingRelations {
+    last_run: Tick,
+}
+
+impl System for CollectDanglingRelations {
+    type In = ();
+    type Out = ();
+
+    fn run(&mut self, _input: (), registry: &mut Registry) {
+        registry.collect_dangling_relations();
+    }
+
+    fn last_run(&self) -> Tick {
+        self.last_run
+    }
+
+    fn set_last_run(&mut self, tick: Tick) {
+        self.last_run = tick;
+    }
+}
+
+impl IntoSystem<()> for CollectDanglingRelations {
+    type System = Self;
+
+    fn into_system(self) -> Self::System {
+        self
+    }
+}
+
+/// Sets the dangling relation policy and collects dangling relations in
+/// `PostUpdate` every frame. The system is labelled
+/// `COLLECT_DANGLING_RELATIONS`.
+#[derive(Default)]
+pub struct DanglingRelationsPlugin {
+    pub policy: DanglingPolicy,
+}
+
+impl Plugin for DanglingRelationsPlugin {
+    fn build(&self, registry: &mut Registry) {
+        registry.set_dangling_policy(self.policy);
+        registry.add_system_to(
+            Stage::PostUpdate,
+            CollectDanglingRelations::default().label(COLLECT_DANGLING_RELATIONS),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{component::Component, event::Events, relationship::Relationship};
+
+    struct Targets(Entity);
+    impl Component for Targets {}
+    impl Relationship for Targets {
+        fn target(&self) -> Entity {
+            self.0
+        }
+    }
+
+    /// Spawns a holder targeting a destroyed entity, bypassing `relate`
+    fn dangling_holder(registry: &mut Registry) -> (Entity, Entity) {
+        let target = registry.create_entity();
+        let holder = registry.spawn(Targets(target));
+        registry.destroy_entity(target).unwrap();
+        (holder, target)
+    }
+
+    #[test]
+    fn test_policies() {
+        let mut registry = Registry::new();
+        let alive = registry.create_entity();
+        let healthy = registry.create_entity();
+        registry.relate(healthy, Targets(alive)).unwrap();
+
+        let (holder, _) = dangling_holder(&mut registry);
+        assert_eq!(registry.collect_dangling_relations().len(), 1);
+        assert!(registry.get_component::<Targets>(holder).is_none());
+        assert!(registry.collect_dangling_relations().is_empty());
+        assert_eq!(registry.related_to::<Targets>(alive), &[healthy]);
+
+        registry.set_dangling_policy(DanglingPolicy::DespawnHolder);
+        let (holder, _) = dangling_holder(&mut registry);
+        registry.collect_dangling_relations();
+        assert!(!registry.is_valid(holder));
+        assert!(registry.is_valid(healthy));
+
+        registry
+            .add_plugin(DanglingRelationsPlugin {
+                policy: DanglingPolicy::Report,
+            })
+            .unwrap();
+        let (holder, target) = dangling_holder(&mut registry);
+        registry.run_systems();
+        let events = registry.get_resource::<Events<DanglingRelation>>().unwrap();
+        let reported: Vec<_> = events
+            .iter()
+            .map(|event| (event.holder, event.target))
+            .collect();
+        assert_eq!(reported, [(holder, target)]);
+        assert!(registry.get_component::<Targets>(holder).is_some());
+    }
+}
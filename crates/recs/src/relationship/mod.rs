@@ -0,0 +1,304 @@
+//! Typed relationships between entities with reverse lookups.
+//!
+//! A relationship is a component on the source entity that points at a target
+//! entity, such as `Targets(Entity)` or `OwnedBy(Entity)`. When it is set
+//! through `Registry::relate`, the registry also indexes it by target, so the
+//! sources pointing at an entity can be found without scanning. Relations are
+//! removed automatically when either endpoint is destroyed.
+
+use std::{any::TypeId, collections::HashMap};
+
+use crate::{component::Component, entity::Entity, error::RecsError, registry::Registry};
+
+pub mod dangling;
+
+use dangling::DanglingPolicy;
+
+/// A component pointing from the entity holding it to another entity.
+///
+/// Relationships must be changed through `Registry::relate` and
+/// `Registry::unrelate` to keep the reverse index in sync; adding or removing
+/// the component directly bypasses it.
+pub trait Relationship: Component + 'static {
+    /// Returns the entity this relationship points at
+    fn target(&self) -> Entity;
+}
+
+/// The reverse index of one relationship type
+struct RelationIndex {
+    /// Sources of the relationship, keyed by their target, in insertion order
+    sources: HashMap<Entity, Vec<Entity>>,
+    /// Target of every source
+    targets: HashMap<Entity, Entity>,
+    /// Removes the relationship component from a source
+    remove: fn(&mut Registry, Entity),
+    /// Finds the holders of the relationship whose target is dead, along with
+    /// that target
+    find_dangling: fn(&Registry) -> Vec<(Entity, Entity)>,
+    /// Lists every holder of the relationship along with its target
+    links: fn(&Registry) -> Vec<(Entity, Entity)>,
+    /// The name of the relationship type
+    name: &'static str,
+}
+
+impl RelationIndex {
+    fn new<R: Relationship>() -> Self {
+        Self {
+            sources: HashMap::new(),
+            targets: HashMap::new(),
+            remove: |registry, source| {
+                let _ = registry.remove_component::<R>(source);
+            },
+            find_dangling: |registry| {
+                registry
+                    .entities_with::<R>()
+                    .iter()
+                    .filter_map(|&holder| {
+                        let target = registry.get_component::<R>(holder)?.target();
+                        (!registry.is_valid(target)).then_some((holder, target))
+                    })
+                    .collect()
+            },
+            links: |registry| {
+                registry
+                    .entities_with::<R>()
+                    .iter()
+                    .filter_map(|&holder| {
+                        Some((holder, registry.get_component::<R>(holder)?.target()))
+                    })
+                    .collect()
+            },
+            name: std::any::type_name::<R>(),
+        }
+    }
+
+    /// Forgets the relationship of `source`, returning its target
+    fn unlink(&mut self, source: Entity) -> Option<Entity> {
+        let target = self.targets.remove(&source)?;
+        if let Some(sources) = self.sources.get_mut(&target) {
+            sources.retain(|&entity| entity != source);
+            if sources.is_empty() {
+                self.sources.remove(&target);
+            }
+        }
+        Some(target)
+    }
+}
+
+/// Reverse indexes of every relationship type used with `Registry::relate`
+#[derive(Default)]
+pub(crate) struct Relations {
+    indexes: HashMap<TypeId, RelationIndex>,
+    /// What `collect_dangling_relations` does with the relations it finds
+    policy: DanglingPolicy,
+}
+
+impl Relations {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the index of `R`, creating it on first use
+    fn index_mut<R: Relationship>(&mut self) -> &mut RelationIndex {
+        self.indexes
+            .entry(TypeId::of::<R>())
+            .or_insert_with(RelationIndex::new::<R>)
+    }
+}
+
+impl Registry {
+    /// Sets the `R` relationship of `source`, replacing the previous one.
+    ///
+    /// Returns an error if `source` or the relationship's target is invalid.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use recs::relationship::Relationship;
+    /// #[derive(Component)]
+    /// struct Targets(Entity);
+    ///
+    /// impl Relationship for Targets {
+    ///     fn target(&self) -> Entity {
+    ///         self.0
+    ///     }
+    /// }
+    ///
+    /// let mut registry = Registry::new();
+    /// let [archer, knight, dragon] = [(); 3].map(|_| registry.create_entity());
+    ///
+    /// registry.relate(archer, Targets(dragon)).unwrap();
+    /// registry.relate(knight, Targets(dragon)).unwrap();
+    /// assert_eq!(registry.related_to::<Targets>(dragon), &[archer, knight]);
+    ///
+    /// // Destroying the target removes the relationship from its sources
+    /// registry.destroy_entity(dragon).unwrap();
+    /// assert!(registry.get_component::<Targets>(archer).is_none());
+    /// ```
+    pub fn relate<R: Relationship>(
+        &mut self,
+        source: Entity,
+        relation: R,
+    ) -> Result<(), RecsError> {
+        let target = relation.target();
+        for entity in [source, target] {
+            if !self.is_valid(entity) {
+                return Err(RecsError::InvalidEntity(entity));
+            }
+        }
+
+        let index = self.relations.index_mut::<R>();
+        index.unlink(source);
+        index.targets.insert(source, target);
+        index.sources.entry(target).or_default().push(source);
+        self.add_component(source, relation)
+    }
+
+    /// Removes the `R` relationship of `source` and returns it, if it had one.
+    /// Returns an error if `source` is invalid.
+    pub fn unrelate<R: Relationship>(&mut self, source: Entity) -> Result<Option<R>, RecsError> {
+        if !self.is_valid(source) {
+            return Err(RecsError::InvalidEntity(source));
+        }
+        let Some(index) = self.relations.indexes.get_mut(&TypeId::of::<R>()) else {
+            return Ok(None);
+        };
+        if index.unlink(source).is_none() {
+            return Ok(None);
+        }
+        Ok(self.remove_component::<R>(source).ok())
+    }
+
+    /// Registers the relationship type `R` without relating any entities, so
+    /// `collect_dangling_relations` checks it even if its components are only
+    /// ever added directly, e.g. by cloning or loading snapshots
+    pub fn register_relationship<R: Relationship>(&mut self) {
+        self.relations.index_mut::<R>();
+    }
+
+    /// Returns the entity the `R` relationship of `source` points at, if it
+    /// has one
+    pub fn relation_target<R: Relationship>(&self, source: Entity) -> Option<Entity> {
+        self.get_component::<R>(source).map(R::target)
+    }
+
+    /// Returns the entities whose `R` relationship points at `target`, in the
+    /// order they were related
+    pub fn related_to<R: Relationship>(&self, target: Entity) -> &[Entity] {
+        self.relations
+            .indexes
+            .get(&TypeId::of::<R>())
+            .and_then(|index| index.sources.get(&target))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Drops every relationship from or to `entity` before it is destroyed
+    pub(crate) fn detach_relations(&mut self, entity: Entity) {
+        let mut orphaned = Vec::new();
+        for index in self.relations.indexes.values_mut() {
+            index.unlink(entity);
+            if let Some(sources) = index.sources.remove(&entity) {
+                for &source in &sources {
+                    index.targets.remove(&source);
+                }
+                orphaned.push((index.remove, sources));
+            }
+        }
+        for (remove, sources) in orphaned {
+            for source in sources {
+                remove(self, source);
+            }
+        }
+    }
+
+    /// Rebuilds every reverse index from the relationship components, after
+    /// they were replaced wholesale. Sources are then ordered as in storage.
+    pub(crate) fn rebuild_relations(&mut self) {
+        let links: Vec<_> = self
+            .relations
+            .indexes
+            .values()
+            .map(|index| (index.links)(self))
+            .collect();
+        for (index, links) in self.relations.indexes.values_mut().zip(links) {
+            index.sources.clear();
+            index.targets.clear();
+            for (source, target) in links {
+                index.sources.entry(target).or_default().push(source);
+                index.targets.insert(source, target);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Targets(Entity);
+    impl Component for Targets {}
+    impl Relationship for Targets {
+        fn target(&self) -> Entity {
+            self.0
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct OwnedBy(Entity);
+    impl Component for OwnedBy {}
+    impl Relationship for OwnedBy {
+        fn target(&self) -> Entity {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_relating_keeps_the_reverse_index_in_sync() {
+        let mut registry = Registry::new();
+        let [a, b, x, y] = [(); 4].map(|_| registry.create_entity());
+
+        registry.relate(a, Targets(x)).unwrap();
+        registry.relate(b, Targets(x)).unwrap();
+        registry.relate(a, OwnedBy(x)).unwrap();
+        assert_eq!(registry.related_to::<Targets>(x), &[a, b]);
+        assert_eq!(registry.related_to::<OwnedBy>(x), &[a]);
+
+        registry.relate(a, Targets(y)).unwrap();
+        assert_eq!(registry.related_to::<Targets>(x), &[b]);
+        assert_eq!(registry.related_to::<Targets>(y), &[a]);
+        assert_eq!(registry.relation_target::<Targets>(a), Some(y));
+
+        assert_eq!(registry.unrelate::<Targets>(b).unwrap(), Some(Targets(x)));
+        assert_eq!(registry.unrelate::<Targets>(b).unwrap(), None);
+        assert!(registry.related_to::<Targets>(x).is_empty());
+
+        registry.destroy_entity(y).unwrap();
+        assert!(matches!(
+            registry.relate(b, Targets(y)),
+            Err(RecsError::InvalidEntity(entity)) if entity == y
+        ));
+    }
+
+    #[test]
+    fn test_destroying_either_endpoint_cleans_up() {
+        let mut registry = Registry::new();
+        let [a, b, x] = [(); 3].map(|_| registry.create_entity());
+        registry.relate(a, Targets(x)).unwrap();
+        registry.relate(b, Targets(x)).unwrap();
+        registry.relate(x, OwnedBy(a)).unwrap();
+
+        registry.destroy_entity(a).unwrap();
+        assert_eq!(registry.related_to::<Targets>(x), &[b]);
+        assert!(registry.get_component::<OwnedBy>(x).is_none());
+        assert_eq!(registry.relation_target::<OwnedBy>(x), None);
+
+        registry.destroy_entity(x).unwrap();
+        assert!(registry.get_component::<Targets>(b).is_none());
+
+        // The index doesn't resurrect relations for reused ids
+        let reused = registry.create_entity();
+        assert!(registry.related_to::<Targets>(reused).is_empty());
+        assert!(registry.unrelate::<Targets>(b).unwrap().is_none());
+    }
+}
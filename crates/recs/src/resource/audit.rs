@@ -0,0 +1,119 @@
+//! Records which resources systems actually read and write, in debug builds.
+//!
+//! A system declares its resource access through its parameters, and the
+//! parallel executor keeps systems with conflicting declarations apart. A
+//! `ResMut` that is only ever read still blocks every other system using the
+//! resource, so `Registry::resource_audit` compares what each system declared
+//! with what its guards were used for.
+
+use std::{any::TypeId, cell::RefCell, collections::HashMap};
+
+use crate::resource::Resource;
+
+/// How a system accesses a resource, from least to most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+pub enum ResourceUse {
+    /// Not accessed at all
+    #[default]
+    Unused,
+    /// Only read
+    Read,
+    /// Written, and possibly read
+    Write,
+}
+
+/// What a system declared for a resource against what it did with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceUsage {
+    /// The access requested by the system's parameters
+    pub declared: ResourceUse,
+    /// The access the system's guards were actually used for, over every run
+    pub used: ResourceUse,
+}
+
+impl ResourceUsage {
+    /// Returns true if the system declared more access than it used
+    pub fn is_over_broad(&self) -> bool {
+        self.used < self.declared
+    }
+}
+
+/// The resources a system declared and used, by resource TypeId
+#[derive(Debug, Clone, Default)]
+pub struct UsageLog {
+    resources: HashMap<TypeId, (&'static str, ResourceUsage)>,
+}
+
+impl UsageLog {
+    fn entry(&mut self, type_id: TypeId, name: &'static str) -> &mut ResourceUsage {
+        &mut self
+            .resources
+            .entry(type_id)
+            .or_insert((
+                name,
+                ResourceUsage {
+                    declared: ResourceUse::Unused,
+                    used: ResourceUse::Unused,
+                },
+            ))
+            .1
+    }
+
+    /// Adds the usage recorded in `other` to this log
+    pub(crate) fn merge(&mut self, other: UsageLog) {
+        for (type_id, (name, usage)) in other.resources {
+            let entry = self.entry(type_id, name);
+            entry.declared = entry.declared.max(usage.declared);
+            entry.used = entry.used.max(usage.used);
+        }
+    }
+
+    /// Returns the recorded usage as `(type name, usage)` pairs, by name
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&'static str, ResourceUsage)> + '_ {
+        let mut resources: Vec<_> = self.resources.values().copied().collect();
+        resources.sort_by_key(|(name, _)| *name);
+        resources.into_iter()
+    }
+}
+
+thread_local! {
+    /// Logs of the systems running on this thread, innermost last
+    static RECORDING: RefCell<Vec<UsageLog>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Starts recording the resource usage of a system run on this thread
+pub(crate) fn begin() {
+    if cfg!(debug_assertions) {
+        RECORDING.with_borrow_mut(|logs| logs.push(UsageLog::default()));
+    }
+}
+
+/// Stops recording the system run started with the last `begin` and returns
+/// its resource usage
+pub(crate) fn end() -> UsageLog {
+    if cfg!(debug_assertions) {
+        RECORDING.with_borrow_mut(|logs| logs.pop().unwrap_or_default())
+    } else {
+        UsageLog::default()
+    }
+}
+
+fn with_log<R: Resource>(record: impl FnOnce(&mut ResourceUsage)) {
+    if cfg!(debug_assertions) {
+        RECORDING.with_borrow_mut(|logs| {
+            if let Some(log) = logs.last_mut() {
+                record(log.entry(TypeId::of::<R>(), std::any::type_name::<R>()));
+            }
+        });
+    }
+}
+
+/// Records that the running system requested `access` to resource `R`
+pub(crate) fn declare<R: Resource>(access: ResourceUse) {
+    with_log::<R>(|usage| usage.declared = usage.declared.max(access));
+}
+
+/// Records that the running system used a guard of resource `R` for `access`
+pub(crate) fn record<R: Resource>(access: ResourceUse) {
+    with_log::<R>(|usage| usage.used = usage.used.max(access));
+}
@@ -1,8 +1,21 @@
 use std::{
     any::{Any, TypeId},
-    collections::HashMap,
+    cell::UnsafeCell,
 };
 
+use crate::{
+    component::ComponentTicks,
+    query::Mut,
+    registry::{Registry, teardown::DropOrder},
+    resource::audit::{ResourceUse, record},
+    system::SystemTicks,
+    tick::Tick,
+    type_map::TypeIdMap,
+};
+
+pub mod audit;
+pub mod non_send;
+
 /// A trait for types that can be used as resources in the RECS system.
 ///
 /// Resources are singleton data that can be accessed by systems.
@@ -14,53 +27,238 @@ use std::{
 /// - 'static: Have a static lifetime
 pub trait Resource: Send + Sync + 'static {}
 
+/// Creates a value from the contents of a registry, used by
+/// `Registry::init_resource`.
+///
+/// It is implemented for every `Default` type. Implement it by hand for
+/// resources built from other resources or from the registry.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// #[derive(Resource)]
+/// struct Settings { pool_size: usize }
+///
+/// #[derive(Resource)]
+/// struct BufferPool(Vec<Vec<u8>>);
+///
+/// impl FromWorld for BufferPool {
+///     fn from_world(registry: &mut Registry) -> Self {
+///         let size = registry.get_resource::<Settings>().map_or(1, |s| s.pool_size);
+///         BufferPool(vec![Vec::new(); size])
+///     }
+/// }
+///
+/// let mut registry = Registry::new();
+/// registry.insert_resource(Settings { pool_size: 4 });
+/// registry.init_resource::<BufferPool>();
+/// assert_eq!(registry.get_resource::<BufferPool>().unwrap().0.len(), 4);
+/// ```
+pub trait FromWorld {
+    /// Creates the value from `registry`
+    fn from_world(registry: &mut Registry) -> Self;
+}
+
+impl<T: Default> FromWorld for T {
+    fn from_world(_registry: &mut Registry) -> Self {
+        T::default()
+    }
+}
+
+/// A stored resource
+struct ResourceData {
+    /// The type name of the resource, for diagnostics
+    name: &'static str,
+    /// The value and ticks are written through shared references by systems
+    /// running concurrently, see `ResourceStorage::get_unchecked_mut`
+    value: UnsafeCell<Box<dyn Any + Send + Sync>>,
+    ticks: UnsafeCell<ComponentTicks>,
+}
+
+// SAFETY: the cells are only written through a shared reference by
+// `get_unchecked_mut`, whose callers guarantee that nothing else accesses the
+// resource meanwhile
+unsafe impl Sync for ResourceData {}
+
+impl ResourceData {
+    fn value(&self) -> &(dyn Any + Send + Sync) {
+        // SAFETY: see `get_unchecked_mut`
+        unsafe { &**self.value.get() }
+    }
+
+    fn ticks(&self) -> ComponentTicks {
+        // SAFETY: see `get_unchecked_mut`
+        unsafe { *self.ticks.get() }
+    }
+}
+
 /// Storage for resources in the ECS system.
 ///
 /// Resources are stored in a type-erased HashMap and can be accessed
 /// by their TypeId. Only one instance of each resource type can exist.
+/// Like components, each resource records when it was added and last changed.
 #[derive(Default)]
 pub struct ResourceStorage {
-    resources: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    resources: TypeIdMap<ResourceData>,
+    /// Types of the stored resources, in insertion order
+    order: Vec<TypeId>,
 }
 
 impl ResourceStorage {
     /// Creates a new empty ResourceStorage
     pub fn new() -> Self {
         Self {
-            resources: HashMap::new(),
+            resources: TypeIdMap::default(),
+            order: Vec::new(),
         }
     }
 
     /// Inserts a resource into the storage.
     /// If a resource of the same type already exists, it will be replaced.
     pub fn insert<R: Resource>(&mut self, resource: R) {
+        self.insert_with_tick(resource, Tick::default());
+    }
+
+    /// Inserts a resource like `insert`, stamped as added and changed at
+    /// `tick`. Replacing a resource only marks it as changed.
+    pub fn insert_with_tick<R: Resource>(&mut self, resource: R, tick: Tick) {
+        let added = self
+            .resources
+            .get(&TypeId::of::<R>())
+            .map_or(tick, |previous| previous.ticks().added);
+        self.insert_with_ticks(
+            resource,
+            ComponentTicks {
+                added,
+                changed: tick,
+            },
+        );
+    }
+
+    /// Inserts a resource with the given `ticks`, e.g. to put back one taken
+    /// out with `remove_with_ticks` without it reading as added or changed
+    pub(crate) fn insert_with_ticks<R: Resource>(&mut self, resource: R, ticks: ComponentTicks) {
         let type_id = TypeId::of::<R>();
-        self.resources.insert(type_id, Box::new(resource));
+        let previous = self.resources.insert(
+            type_id,
+            ResourceData {
+                name: std::any::type_name::<R>(),
+                value: UnsafeCell::new(Box::new(resource)),
+                ticks: UnsafeCell::new(ticks),
+            },
+        );
+        if previous.is_none() {
+            self.order.push(type_id);
+        }
+    }
+
+    /// Moves the resources of `other` into this storage, stamped as changed
+    /// at `tick`. Existing resources are only replaced if `replace` is set.
+    pub(crate) fn merge(&mut self, mut other: ResourceStorage, replace: bool, tick: Tick) {
+        for type_id in other.order {
+            let Some(mut data) = other.resources.remove(&type_id) else {
+                continue;
+            };
+            let previous = self.resources.get(&type_id);
+            if previous.is_some() && !replace {
+                continue;
+            }
+            let added = previous.map_or(tick, |previous| previous.ticks().added);
+            *data.ticks.get_mut() = ComponentTicks {
+                added,
+                changed: tick,
+            };
+            if self.resources.insert(type_id, data).is_none() {
+                self.order.push(type_id);
+            }
+        }
     }
 
     /// Gets a reference to a resource if it exists
     pub fn get<R: Resource>(&self) -> Option<&R> {
-        let type_id = TypeId::of::<R>();
-        self.resources
-            .get(&type_id)
-            .and_then(|resource| resource.downcast_ref::<R>())
+        self.get_with_ticks::<R>().map(|(resource, _)| resource)
     }
 
-    /// Gets a mutable reference to a resource if it exists
+    /// Gets a reference to a resource and its change ticks if it exists
+    pub fn get_with_ticks<R: Resource>(&self) -> Option<(&R, ComponentTicks)> {
+        let data = self.resources.get(&TypeId::of::<R>())?;
+        let resource = data.value().downcast_ref::<R>()?;
+        Some((resource, data.ticks()))
+    }
+
+    /// Gets a mutable reference to a resource if it exists, without marking
+    /// it as changed
     pub fn get_mut<R: Resource>(&mut self) -> Option<&mut R> {
         let type_id = TypeId::of::<R>();
         self.resources
             .get_mut(&type_id)
-            .and_then(|resource| resource.downcast_mut::<R>())
+            .and_then(|data| data.value.get_mut().downcast_mut::<R>())
+    }
+
+    /// Gets a mutable reference to a resource if it exists, marking it as
+    /// changed at `tick`
+    pub fn get_mut_with_tick<R: Resource>(&mut self, tick: Tick) -> Option<&mut R> {
+        let data = self.resources.get_mut(&TypeId::of::<R>())?;
+        let resource = data.value.get_mut().downcast_mut::<R>()?;
+        data.ticks.get_mut().changed = tick;
+        Some(resource)
+    }
+
+    /// Gets a mutable reference to a resource through a shared reference to
+    /// the storage, without marking it as changed.
+    ///
+    /// Lets systems running concurrently fetch the distinct resources they
+    /// write without any of them borrowing the whole storage mutably.
+    ///
+    /// # Safety
+    /// Nothing else may access the resource while the returned reference is
+    /// alive, and no resource may be inserted or removed meanwhile.
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) unsafe fn get_unchecked_mut<R: Resource>(&self) -> Option<&mut R> {
+        let data = self.resources.get(&TypeId::of::<R>())?;
+        // SAFETY: the caller guarantees exclusive access to the resource
+        unsafe { (*data.value.get()).downcast_mut::<R>() }
+    }
+
+    /// Gets a mutable reference to a resource like `get_unchecked_mut`,
+    /// marking it as changed at `tick`
+    ///
+    /// # Safety
+    /// Same as `get_unchecked_mut`.
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) unsafe fn get_unchecked_mut_with_tick<R: Resource>(
+        &self,
+        tick: Tick,
+    ) -> Option<&mut R> {
+        let data = self.resources.get(&TypeId::of::<R>())?;
+        // SAFETY: the caller guarantees exclusive access to the resource
+        unsafe {
+            let resource = (*data.value.get()).downcast_mut::<R>()?;
+            (*data.ticks.get()).changed = tick;
+            Some(resource)
+        }
+    }
+
+    /// Returns when the resource was added and last changed, if it exists
+    pub fn get_ticks<R: Resource>(&self) -> Option<ComponentTicks> {
+        self.resources
+            .get(&TypeId::of::<R>())
+            .map(|data| data.ticks())
     }
 
     /// Removes a resource from storage and returns it
     pub fn remove<R: Resource>(&mut self) -> Option<R> {
+        self.remove_with_ticks::<R>().map(|(resource, _)| resource)
+    }
+
+    /// Removes a resource from storage and returns it with its change ticks
+    pub(crate) fn remove_with_ticks<R: Resource>(&mut self) -> Option<(R, ComponentTicks)> {
         let type_id = TypeId::of::<R>();
-        self.resources
-            .remove(&type_id)
-            .and_then(|resource| resource.downcast::<R>().ok())
-            .map(|boxed| *boxed)
+        self.order.retain(|&id| id != type_id);
+        let data = self.resources.remove(&type_id)?;
+        let ticks = data.ticks.into_inner();
+        let resource = data.value.into_inner().downcast::<R>().ok()?;
+        Some((*resource, ticks))
     }
 
     /// Checks if a resource of the given type exists
@@ -69,6 +267,11 @@ impl ResourceStorage {
         self.resources.contains_key(&type_id)
     }
 
+    /// Checks if a resource with the given TypeId exists
+    pub fn contains_id(&self, type_id: TypeId) -> bool {
+        self.resources.contains_key(&type_id)
+    }
+
     /// Returns the number of resources stored
     pub fn len(&self) -> usize {
         self.resources.len()
@@ -79,20 +282,155 @@ impl ResourceStorage {
         self.resources.is_empty()
     }
 
+    /// Returns an iterator over the type names of all stored resources
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.resources.values().map(|data| data.name)
+    }
+
+    /// Returns an iterator over every resource as `(TypeId, type name, value)`,
+    /// in no particular order.
+    ///
+    /// Lets tooling such as inspectors or serializers enumerate resources
+    /// without knowing their types, downcasting the values it understands.
+    pub fn iter(&self) -> impl Iterator<Item = (TypeId, &'static str, &dyn Any)> + '_ {
+        self.resources
+            .iter()
+            .map(|(type_id, data)| (*type_id, data.name, data.value() as &dyn Any))
+    }
+
+    /// Returns an iterator over every resource with mutable access to its value.
+    ///
+    /// Resources can be modified in place but not replaced or removed, since a
+    /// `&mut dyn Any` can't change the type of the value behind it. Each value
+    /// is marked as changed at `tick` once it is accessed mutably.
+    pub fn iter_mut(
+        &mut self,
+        tick: Tick,
+    ) -> impl Iterator<Item = (TypeId, &'static str, Mut<'_, dyn Any>)> + '_ {
+        self.resources.iter_mut().map(move |(type_id, data)| {
+            let value = &mut **data.value.get_mut() as &mut dyn Any;
+            (
+                *type_id,
+                data.name,
+                Mut::new(value, &mut data.ticks.get_mut().changed, tick),
+            )
+        })
+    }
+
     /// Clears all resources from storage
     pub fn clear(&mut self) {
         self.resources.clear();
+        self.order.clear();
+    }
+
+    /// Clamps the change ticks of every resource, see `Tick::check_tick`
+    pub(crate) fn check_change_ticks(&mut self, this_run: Tick) {
+        for data in self.resources.values_mut() {
+            data.ticks.get_mut().check_ticks(this_run);
+        }
+    }
+
+    /// Clears all resources from storage, dropping them in `order` of insertion
+    pub fn clear_in(&mut self, order: DropOrder) {
+        let types = std::mem::take(&mut self.order);
+        for type_id in order.arrange(types) {
+            drop(self.resources.remove(&type_id));
+        }
     }
 }
 
 /// A system parameter that provides read-only access to a resource
 pub struct Res<'a, R: Resource> {
     resource: &'a R,
+    ticks: ComponentTicks,
+    system: SystemTicks,
 }
 
 impl<'a, R: Resource> Res<'a, R> {
+    /// Creates a guard that reports the resource as neither added nor changed
     pub fn new(resource: &'a R) -> Self {
-        Self { resource }
+        let ticks = SystemTicks {
+            last_run: Tick::default(),
+            this_run: Tick::default(),
+        };
+        Self::with_ticks(resource, ComponentTicks::new(Tick::default()), ticks)
+    }
+
+    /// Creates a guard whose change detection compares the resource `ticks`
+    /// against the `system` run
+    pub fn with_ticks(resource: &'a R, ticks: ComponentTicks, system: SystemTicks) -> Self {
+        Self {
+            resource,
+            ticks,
+            system,
+        }
+    }
+
+    /// Returns true if the resource was added since the system last ran
+    pub fn is_added(&self) -> bool {
+        self.ticks
+            .is_added(self.system.last_run, self.system.this_run)
+    }
+
+    /// Returns true if the resource was added or mutably accessed since the
+    /// system last ran.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Resource)]
+    /// struct Config { scale: f32 }
+    ///
+    /// fn rebuild_layout(config: Res<Config>) {
+    ///     if !config.is_changed() {
+    ///         return;
+    ///     }
+    ///     // Expensive work depending on config.scale
+    /// }
+    /// # let mut registry = Registry::new();
+    /// # registry.insert_resource(Config { scale: 1.0 });
+    /// # registry.add_system(rebuild_layout);
+    /// # registry.run_systems();
+    /// ```
+    pub fn is_changed(&self) -> bool {
+        self.ticks
+            .is_changed(self.system.last_run, self.system.this_run)
+    }
+
+    /// Projects the guard into a part of the resource, so helpers can accept a
+    /// narrow view instead of the whole resource.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use recs::resource::MappedRes;
+    /// struct Audio { volume: f32 }
+    ///
+    /// #[derive(Resource)]
+    /// struct Settings { audio: Audio }
+    ///
+    /// fn apply_volume(audio: MappedRes<Audio>) -> f32 {
+    ///     audio.volume
+    /// }
+    ///
+    /// fn audio_system(settings: Res<Settings>) {
+    ///     let volume = apply_volume(settings.map(|s| &s.audio));
+    /// #   assert_eq!(volume, 0.5);
+    /// }
+    /// # let mut registry = Registry::new();
+    /// # registry.insert_resource(Settings { audio: Audio { volume: 0.5 } });
+    /// # registry.add_system(audio_system);
+    /// # registry.run_systems();
+    /// ```
+    pub fn map<T: ?Sized>(self, f: impl FnOnce(&R) -> &T) -> MappedRes<'a, T> {
+        record::<R>(ResourceUse::Read);
+        MappedRes::new(f(self.resource))
+    }
+
+    /// Creates a new guard borrowing from this one, for passing the resource
+    /// to helpers without giving up the original guard
+    pub fn reborrow(&self) -> Res<'_, R> {
+        Res::with_ticks(self.resource, self.ticks, self.system)
     }
 }
 
@@ -100,6 +438,7 @@ impl<'a, R: Resource> std::ops::Deref for Res<'a, R> {
     type Target = R;
 
     fn deref(&self) -> &Self::Target {
+        record::<R>(ResourceUse::Read);
         self.resource
     }
 }
@@ -113,27 +452,111 @@ impl<'a, R: Resource> ResMut<'a, R> {
     pub fn new(resource: &'a mut R) -> Self {
         Self { resource }
     }
+
+    /// Projects the guard into a part of the resource, keeping mutable access
+    /// only to that part
+    pub fn map<T: ?Sized>(self, f: impl FnOnce(&mut R) -> &mut T) -> MappedResMut<'a, T> {
+        record::<R>(ResourceUse::Write);
+        MappedResMut::new(f(self.resource))
+    }
+
+    /// Creates a new guard mutably borrowing from this one, for passing the
+    /// resource to helpers without giving up the original guard
+    pub fn reborrow(&mut self) -> ResMut<'_, R> {
+        ResMut::new(self.resource)
+    }
 }
 
 impl<'a, R: Resource> std::ops::Deref for ResMut<'a, R> {
     type Target = R;
 
     fn deref(&self) -> &Self::Target {
+        record::<R>(ResourceUse::Read);
         self.resource
     }
 }
 
 impl<'a, R: Resource> std::ops::DerefMut for ResMut<'a, R> {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        record::<R>(ResourceUse::Write);
         self.resource
     }
 }
 
+/// A read-only view into part of a resource, produced by `Res::map`
+pub struct MappedRes<'a, T: ?Sized> {
+    value: &'a T,
+}
+
+impl<'a, T: ?Sized> MappedRes<'a, T> {
+    pub fn new(value: &'a T) -> Self {
+        Self { value }
+    }
+
+    /// Projects the view further into a part of the value
+    pub fn map<U: ?Sized>(self, f: impl FnOnce(&T) -> &U) -> MappedRes<'a, U> {
+        MappedRes::new(f(self.value))
+    }
+
+    /// Creates a new view borrowing from this one
+    pub fn reborrow(&self) -> MappedRes<'_, T> {
+        MappedRes::new(self.value)
+    }
+}
+
+impl<'a, T: ?Sized> std::ops::Deref for MappedRes<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+/// A mutable view into part of a resource, produced by `ResMut::map`
+pub struct MappedResMut<'a, T: ?Sized> {
+    value: &'a mut T,
+}
+
+impl<'a, T: ?Sized> MappedResMut<'a, T> {
+    pub fn new(value: &'a mut T) -> Self {
+        Self { value }
+    }
+
+    /// Projects the view further into a part of the value
+    pub fn map<U: ?Sized>(self, f: impl FnOnce(&mut T) -> &mut U) -> MappedResMut<'a, U> {
+        MappedResMut::new(f(self.value))
+    }
+
+    /// Creates a new view mutably borrowing from this one
+    pub fn reborrow(&mut self) -> MappedResMut<'_, T> {
+        MappedResMut::new(self.value)
+    }
+}
+
+impl<'a, T: ?Sized> std::ops::Deref for MappedResMut<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<'a, T: ?Sized> std::ops::DerefMut for MappedResMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.value
+    }
+}
+
 /// A system parameter that provides optional read-only access to a resource
+///
+/// Deprecated in favour of `Option<Res<R>>`; this wrapper is kept for
+/// compatibility.
+#[deprecated(note = "use `Option<Res<R>>` instead")]
 pub struct OptionalRes<'a, R: Resource> {
     resource: Option<&'a R>,
 }
 
+#[allow(deprecated)]
 impl<'a, R: Resource> OptionalRes<'a, R> {
     pub fn new(resource: Option<&'a R>) -> Self {
         Self { resource }
@@ -147,24 +570,109 @@ impl<'a, R: Resource> OptionalRes<'a, R> {
         self.resource.is_none()
     }
 
-    pub fn as_ref(&self) -> Option<&'a R> {
+    pub fn as_ref(&self) -> Option<&R> {
+        record::<R>(ResourceUse::Read);
         self.resource
     }
+
+    /// Maps the resource with `f`, if it exists
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// #[derive(Resource, Default)]
+    /// struct Gravity(f32);
+    ///
+    /// fn fall(gravity: OptionalRes<Gravity>) {
+    ///     let g = gravity.map(|gravity| gravity.0).unwrap_or(9.81);
+    /// #   assert_eq!(g, 9.81);
+    /// }
+    /// # let mut registry = Registry::new();
+    /// # registry.add_system(fall);
+    /// # registry.run_systems();
+    /// ```
+    pub fn map<U>(&self, f: impl FnOnce(&R) -> U) -> Option<U> {
+        self.as_ref().map(f)
+    }
+
+    /// Returns `default` if the resource doesn't exist, or maps it with `f`
+    pub fn map_or<U>(&self, default: U, f: impl FnOnce(&R) -> U) -> U {
+        self.as_ref().map_or(default, f)
+    }
+
+    /// Returns None if the resource doesn't exist, or calls `f` with it
+    pub fn and_then<U>(&self, f: impl FnOnce(&R) -> Option<U>) -> Option<U> {
+        self.as_ref().and_then(f)
+    }
+
+    /// Returns true if the resource exists and matches `f`
+    pub fn is_some_and(&self, f: impl FnOnce(&R) -> bool) -> bool {
+        self.as_ref().is_some_and(f)
+    }
+
+    /// Returns a clone of the resource, if it exists
+    pub fn cloned(&self) -> Option<R>
+    where
+        R: Clone,
+    {
+        self.as_ref().cloned()
+    }
+
+    /// Returns a clone of the resource, or its default if it doesn't exist
+    pub fn unwrap_or_default(&self) -> R
+    where
+        R: Clone + Default,
+    {
+        self.cloned().unwrap_or_default()
+    }
+
+    /// Converts into a `Res` guard, if the resource exists
+    pub fn into_res(self) -> Option<Res<'a, R>> {
+        self.resource.map(Res::new)
+    }
+}
+
+#[allow(deprecated)]
+impl<'a, R: Resource> IntoIterator for OptionalRes<'a, R> {
+    type Item = &'a R;
+    type IntoIter = std::option::IntoIter<&'a R>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        record::<R>(ResourceUse::Read);
+        self.resource.into_iter()
+    }
 }
 
+#[allow(deprecated)]
+impl<'s, R: Resource> IntoIterator for &'s OptionalRes<'_, R> {
+    type Item = &'s R;
+    type IntoIter = std::option::IntoIter<&'s R>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_ref().into_iter()
+    }
+}
+
+#[allow(deprecated)]
 impl<'a, R: Resource> std::ops::Deref for OptionalRes<'a, R> {
     type Target = Option<&'a R>;
 
     fn deref(&self) -> &Self::Target {
+        record::<R>(ResourceUse::Read);
         &self.resource
     }
 }
 
 /// A system parameter that provides optional mutable access to a resource
+///
+/// Deprecated in favour of `Option<ResMut<R>>`; this wrapper is kept for
+/// compatibility.
+#[deprecated(note = "use `Option<ResMut<R>>` instead")]
 pub struct OptionalResMut<'a, R: Resource> {
     resource: Option<&'a mut R>,
 }
 
+#[allow(deprecated)]
 impl<'a, R: Resource> OptionalResMut<'a, R> {
     pub fn new(resource: Option<&'a mut R>) -> Self {
         Self { resource }
@@ -179,20 +687,93 @@ impl<'a, R: Resource> OptionalResMut<'a, R> {
     }
 
     pub fn as_mut(&mut self) -> Option<&mut R> {
-        self.resource.as_mut().map(|r| &mut **r)
+        record::<R>(ResourceUse::Write);
+        self.resource.as_deref_mut()
+    }
+
+    /// Returns a shared reference to the resource, if it exists
+    pub fn as_ref(&self) -> Option<&R> {
+        record::<R>(ResourceUse::Read);
+        self.resource.as_deref()
+    }
+
+    /// Maps the resource with `f`, if it exists
+    pub fn map<U>(self, f: impl FnOnce(&'a mut R) -> U) -> Option<U> {
+        record::<R>(ResourceUse::Write);
+        self.resource.map(f)
+    }
+
+    /// Returns `default` if the resource doesn't exist, or maps it with `f`
+    pub fn map_or<U>(self, default: U, f: impl FnOnce(&'a mut R) -> U) -> U {
+        self.map(f).unwrap_or(default)
+    }
+
+    /// Returns None if the resource doesn't exist, or calls `f` with it
+    pub fn and_then<U>(self, f: impl FnOnce(&'a mut R) -> Option<U>) -> Option<U> {
+        self.map(f).flatten()
+    }
+
+    /// Returns true if the resource exists and matches `f`
+    pub fn is_some_and(&self, f: impl FnOnce(&R) -> bool) -> bool {
+        self.as_ref().is_some_and(f)
+    }
+
+    /// Returns a clone of the resource, if it exists
+    pub fn cloned(&self) -> Option<R>
+    where
+        R: Clone,
+    {
+        self.as_ref().cloned()
+    }
+
+    /// Returns a clone of the resource, or its default if it doesn't exist
+    pub fn unwrap_or_default(&self) -> R
+    where
+        R: Clone + Default,
+    {
+        self.cloned().unwrap_or_default()
+    }
+
+    /// Converts into a `ResMut` guard, if the resource exists
+    pub fn into_res_mut(self) -> Option<ResMut<'a, R>> {
+        self.resource.map(ResMut::new)
+    }
+}
+
+#[allow(deprecated)]
+impl<'a, R: Resource> IntoIterator for OptionalResMut<'a, R> {
+    type Item = &'a mut R;
+    type IntoIter = std::option::IntoIter<&'a mut R>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map(|resource| resource).into_iter()
     }
 }
 
+#[allow(deprecated)]
+impl<'s, 'a, R: Resource> IntoIterator for &'s mut OptionalResMut<'a, R> {
+    type Item = &'s mut R;
+    type IntoIter = std::option::IntoIter<&'s mut R>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_mut().into_iter()
+    }
+}
+
+#[allow(deprecated)]
 impl<'a, R: Resource> std::ops::Deref for OptionalResMut<'a, R> {
     type Target = Option<&'a mut R>;
 
     fn deref(&self) -> &Self::Target {
+        record::<R>(ResourceUse::Read);
         &self.resource
     }
 }
 
+#[allow(deprecated)]
 impl<'a, R: Resource> std::ops::DerefMut for OptionalResMut<'a, R> {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        record::<R>(ResourceUse::Write);
         &mut self.resource
     }
 }
@@ -208,7 +789,7 @@ mod tests {
     }
     impl Resource for GameConfig {}
 
-    #[derive(Debug, PartialEq, Default)]
+    #[derive(Debug, Clone, PartialEq, Default)]
     struct Score(u32);
     impl Resource for Score {}
 
@@ -318,6 +899,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_optional_res_wrapper() {
         let config = GameConfig {
             speed: 1.0,
@@ -337,6 +919,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_optional_res_mut_wrapper() {
         let mut config = GameConfig {
             speed: 1.0,
@@ -356,4 +939,124 @@ mod tests {
         assert!(opt_res_mut_none.is_none());
         assert!(opt_res_mut_none.as_mut().is_none());
     }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_optional_res_combinators() {
+        let mut score = Score(3);
+
+        let some = OptionalRes::new(Some(&score));
+        assert_eq!(some.map(|s| s.0 * 2), Some(6));
+        assert_eq!(some.and_then(|s| s.0.checked_sub(4)), None);
+        assert!(some.is_some_and(|s| s.0 == 3));
+        assert_eq!(some.unwrap_or_default(), Score(3));
+        assert_eq!((&some).into_iter().count(), 1);
+        assert_eq!(some.into_res().map(|s| s.0), Some(3));
+
+        let none: OptionalRes<Score> = OptionalRes::new(None);
+        assert_eq!(none.map_or(0, |s| s.0), 0);
+        assert_eq!(none.unwrap_or_default(), Score(0));
+        assert_eq!(none.into_iter().count(), 0);
+
+        let mut some_mut = OptionalResMut::new(Some(&mut score));
+        for s in &mut some_mut {
+            s.0 += 1;
+        }
+        assert_eq!(some_mut.cloned(), Some(Score(4)));
+        assert_eq!(some_mut.map(|s| std::mem::take(&mut s.0)), Some(4));
+        assert_eq!(score, Score(0));
+
+        let none_mut: OptionalResMut<Score> = OptionalResMut::new(None);
+        assert!(!none_mut.is_some_and(|_| true));
+        assert_eq!(none_mut.and_then(|s| Some(s.0)), None);
+    }
+
+    #[test]
+    fn test_res_map_and_reborrow() {
+        let config = GameConfig {
+            speed: 2.0,
+            paused: false,
+        };
+
+        let res = Res::new(&config);
+        let speed = res.reborrow().map(|c| &c.speed);
+        assert_eq!(*speed, 2.0);
+        assert!(!res.paused);
+    }
+
+    #[test]
+    fn test_resmut_map_and_reborrow() {
+        let mut config = GameConfig {
+            speed: 2.0,
+            paused: false,
+        };
+
+        fn pause(mut paused: MappedResMut<bool>) {
+            *paused = true;
+        }
+
+        let mut res = ResMut::new(&mut config);
+        pause(res.reborrow().map(|c| &mut c.paused));
+        res.speed = 3.0;
+
+        let mut speed = res.map(|c| &mut c.speed);
+        *speed.reborrow() *= 2.0;
+
+        assert!(config.paused);
+        assert_eq!(config.speed, 6.0);
+    }
+
+    #[test]
+    fn test_type_erased_iteration() {
+        let mut storage = ResourceStorage::new();
+        storage.insert(Score(3));
+        storage.insert(GameConfig {
+            speed: 1.0,
+            paused: false,
+        });
+
+        let mut names: Vec<_> = storage.iter().map(|(_, name, _)| name).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                std::any::type_name::<GameConfig>(),
+                std::any::type_name::<Score>()
+            ]
+        );
+
+        for (type_id, _, mut resource) in storage.iter_mut(Tick::new(7)) {
+            if type_id == TypeId::of::<Score>() {
+                resource.downcast_mut::<Score>().unwrap().0 += 1;
+            }
+        }
+        assert_eq!(storage.get::<Score>(), Some(&Score(4)));
+        // Only the written resource is marked as changed
+        assert_eq!(storage.get_ticks::<Score>().unwrap().changed, Tick::new(7));
+        assert_eq!(
+            storage.get_ticks::<GameConfig>().unwrap().changed,
+            Tick::default()
+        );
+    }
+
+    #[test]
+    fn test_init_resource_from_world() {
+        struct Doubled(u32);
+        impl Resource for Doubled {}
+        impl FromWorld for Doubled {
+            fn from_world(registry: &mut Registry) -> Self {
+                Doubled(registry.get_resource::<Score>().map_or(0, |s| s.0 * 2))
+            }
+        }
+
+        let mut registry = Registry::new();
+        registry.init_resource::<Score>();
+        registry.get_resource_mut::<Score>().unwrap().0 = 21;
+        registry.init_resource::<Doubled>();
+        assert_eq!(registry.get_resource::<Doubled>().unwrap().0, 42);
+
+        // Existing resources are kept
+        registry.init_resource::<Score>();
+        assert_eq!(registry.get_resource::<Score>(), Some(&Score(21)));
+    }
 }
@@ -0,0 +1,259 @@
+//! Resources that can't be sent to or shared with other threads, such as
+//! window handles or audio contexts.
+//!
+//! They are stored apart from regular resources and can only be accessed from
+//! the thread that created the registry, which the executor honors by running
+//! the systems using them on the thread that runs the schedule. A registry
+//! holding some can't be handed to a `WorldCoordinator`, which steps worlds on
+//! pool threads: `add_world` rejects it.
+
+use std::{
+    any::{Any, TypeId},
+    thread::{self, ThreadId},
+};
+
+use crate::{
+    registry::{Registry, teardown::DropOrder},
+    type_map::TypeIdMap,
+};
+
+/// Storage for the non-`Send` resources of a registry, see
+/// `Registry::insert_non_send_resource`
+pub struct NonSendResources {
+    /// The thread the resources belong to
+    owner: ThreadId,
+    resources: TypeIdMap<(&'static str, Box<dyn Any>)>,
+    /// Insertion order of the resources, used when tearing down
+    order: Vec<TypeId>,
+}
+
+// SAFETY: the resources are only ever accessed, and dropped, on the owner
+// thread, every accessor checks it
+unsafe impl Send for NonSendResources {}
+unsafe impl Sync for NonSendResources {}
+
+impl Default for NonSendResources {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NonSendResources {
+    /// Creates a storage owned by the current thread
+    pub fn new() -> Self {
+        Self {
+            owner: thread::current().id(),
+            resources: TypeIdMap::default(),
+            order: Vec::new(),
+        }
+    }
+
+    /// Panics if the current thread isn't the owner thread
+    fn check_thread(&self, name: &str) {
+        assert!(
+            thread::current().id() == self.owner,
+            "Non-send resource {} accessed from a thread other than the one that created the registry",
+            name
+        );
+    }
+
+    /// Inserts a resource, replacing the one of the same type if any
+    pub fn insert<R: 'static>(&mut self, resource: R) {
+        let name = std::any::type_name::<R>();
+        self.check_thread(name);
+        let previous = self
+            .resources
+            .insert(TypeId::of::<R>(), (name, Box::new(resource)));
+        if previous.is_none() {
+            self.order.push(TypeId::of::<R>());
+        }
+    }
+
+    /// Gets a reference to a resource if it exists
+    pub fn get<R: 'static>(&self) -> Option<&R> {
+        self.check_thread(std::any::type_name::<R>());
+        self.resources
+            .get(&TypeId::of::<R>())
+            .and_then(|(_, resource)| resource.downcast_ref())
+    }
+
+    /// Gets a mutable reference to a resource if it exists
+    pub fn get_mut<R: 'static>(&mut self) -> Option<&mut R> {
+        self.check_thread(std::any::type_name::<R>());
+        self.resources
+            .get_mut(&TypeId::of::<R>())
+            .and_then(|(_, resource)| resource.downcast_mut())
+    }
+
+    /// Removes a resource from storage and returns it
+    pub fn remove<R: 'static>(&mut self) -> Option<R> {
+        self.check_thread(std::any::type_name::<R>());
+        self.order.retain(|&type_id| type_id != TypeId::of::<R>());
+        self.resources
+            .remove(&TypeId::of::<R>())
+            .and_then(|(_, resource)| resource.downcast().ok())
+            .map(|boxed| *boxed)
+    }
+
+    /// Checks if a resource with the given TypeId exists. Allowed from any
+    /// thread, since the resource itself isn't touched.
+    pub fn contains_id(&self, type_id: TypeId) -> bool {
+        self.resources.contains_key(&type_id)
+    }
+
+    /// Returns true if no resource is stored
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+
+    /// Returns the type names of the stored resources
+    pub fn names(&self) -> Vec<&'static str> {
+        self.resources.values().map(|(name, _)| *name).collect()
+    }
+}
+
+impl NonSendResources {
+    /// Drops every resource in `order` of insertion, or leaks them when
+    /// called on another thread than the owner
+    pub(crate) fn clear_in(&mut self, order: DropOrder) {
+        let types = std::mem::take(&mut self.order);
+        if thread::current().id() != self.owner {
+            std::mem::forget(std::mem::take(&mut self.resources));
+            return;
+        }
+        for type_id in order.arrange(types) {
+            drop(self.resources.remove(&type_id));
+        }
+    }
+}
+
+impl Drop for NonSendResources {
+    /// Dropping the resources on another thread would be unsound, so they are
+    /// leaked instead
+    fn drop(&mut self) {
+        if thread::current().id() != self.owner {
+            std::mem::forget(std::mem::take(&mut self.resources));
+        }
+    }
+}
+
+impl Registry {
+    /// Inserts a resource that isn't `Send` or `Sync`, replacing the one of
+    /// the same type if any.
+    ///
+    /// Non-send resources can only be accessed from the thread that created
+    /// the registry, through these methods or the `NonSend` and `NonSendMut`
+    /// system parameters. The parallel executor runs the systems using them
+    /// on the thread running the schedule, which must be that thread.
+    ///
+    /// # Panics
+    /// Panics if called from another thread than the one that created the
+    /// registry, as do the other non-send resource methods.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// use std::rc::Rc;
+    ///
+    /// struct AudioContext(Rc<u32>);
+    ///
+    /// fn play(audio: NonSend<AudioContext>) {
+    ///     assert_eq!(*audio.0, 44_100);
+    /// }
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.insert_non_send_resource(AudioContext(Rc::new(44_100)));
+    /// registry.add_system(play);
+    /// registry.run_systems();
+    /// ```
+    pub fn insert_non_send_resource<R: 'static>(&mut self, resource: R) {
+        self.non_send.insert(resource);
+    }
+
+    /// Gets a reference to a non-send resource if it exists
+    pub fn get_non_send_resource<R: 'static>(&self) -> Option<&R> {
+        self.non_send.get::<R>()
+    }
+
+    /// Gets a mutable reference to a non-send resource if it exists
+    pub fn get_non_send_resource_mut<R: 'static>(&mut self) -> Option<&mut R> {
+        self.non_send.get_mut::<R>()
+    }
+
+    /// Removes a non-send resource from the registry and returns it
+    pub fn remove_non_send_resource<R: 'static>(&mut self) -> Option<R> {
+        self.non_send.remove::<R>()
+    }
+
+    /// Checks if a non-send resource of the given type exists
+    pub fn has_non_send_resource<R: 'static>(&self) -> bool {
+        self.non_send.contains_id(TypeId::of::<R>())
+    }
+}
+
+/// A system parameter that provides read-only access to a non-`Send`
+/// resource. Systems using it always run on the thread running the schedule.
+pub struct NonSend<'a, R: 'static> {
+    resource: &'a R,
+}
+
+impl<'a, R: 'static> NonSend<'a, R> {
+    pub fn new(resource: &'a R) -> Self {
+        Self { resource }
+    }
+}
+
+impl<R: 'static> std::ops::Deref for NonSend<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        self.resource
+    }
+}
+
+/// A system parameter that provides mutable access to a non-`Send` resource.
+/// Systems using it always run on the thread running the schedule.
+pub struct NonSendMut<'a, R: 'static> {
+    resource: &'a mut R,
+}
+
+impl<'a, R: 'static> NonSendMut<'a, R> {
+    pub fn new(resource: &'a mut R) -> Self {
+        Self { resource }
+    }
+}
+
+impl<R: 'static> std::ops::Deref for NonSendMut<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        self.resource
+    }
+}
+
+impl<R: 'static> std::ops::DerefMut for NonSendMut<'_, R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.resource
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn test_non_send_resources_stay_on_their_thread() {
+        let mut storage = NonSendResources::new();
+        storage.insert(Rc::new(5));
+        assert_eq!(**storage.get::<Rc<i32>>().unwrap(), 5);
+        assert!(storage.contains_id(TypeId::of::<Rc<i32>>()));
+
+        thread::scope(|scope| {
+            let access = scope.spawn(|| storage.get::<Rc<i32>>().is_some());
+            assert!(access.join().is_err());
+        });
+        assert_eq!(storage.remove::<Rc<i32>>().map(|rc| *rc), Some(5));
+    }
+}
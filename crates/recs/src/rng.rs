@@ -0,0 +1,104 @@
+//! A small seedable random number generator resource.
+
+use std::{
+    hash::{BuildHasher, RandomState},
+    ops::RangeInclusive,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::resource::Resource;
+
+/// A fast, non-cryptographic random number generator (SplitMix64).
+///
+/// Stored as a resource so systems share one stream of numbers, which keeps
+/// runs reproducible when the generator is seeded.
+///
+/// # Example
+/// ```rust
+/// # use recs::rng::Rng;
+/// let mut a = Rng::seeded(42);
+/// let mut b = Rng::seeded(42);
+/// assert_eq!(a.next_u64(), b.next_u64());
+///
+/// let roll = a.range(1..=6);
+/// assert!((1..=6).contains(&roll));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a generator that yields the same numbers for the same seed
+    pub fn seeded(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Creates a generator seeded from the OS and the current time
+    pub fn from_entropy() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos());
+        Self::seeded(RandomState::new().hash_one(nanos))
+    }
+
+    /// Returns the next random 64-bit number
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a random number below `bound`, or 0 if `bound` is 0
+    pub fn below(&mut self, bound: u64) -> u64 {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+
+    /// Returns a random number in `range`, or its start if it is empty
+    pub fn range(&mut self, range: RangeInclusive<u32>) -> u32 {
+        let (start, end) = range.into_inner();
+        if end <= start {
+            return start;
+        }
+        start + self.below((end - start) as u64 + 1) as u32
+    }
+
+    /// Returns a random number in `[0, 1)`
+    pub fn f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns true with probability `p`
+    pub fn chance(&mut self, p: f64) -> bool {
+        self.f64() < p
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::from_entropy()
+    }
+}
+
+impl Resource for Rng {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranges_stay_in_bounds_and_cover_them() {
+        let mut rng = Rng::seeded(7);
+        let mut seen = [false; 4];
+        for _ in 0..1000 {
+            let value = rng.range(2..=5);
+            seen[value as usize - 2] = true;
+            assert!((0.0..1.0).contains(&rng.f64()));
+        }
+        assert!(seen.iter().all(|&seen| seen));
+        assert_eq!(rng.range(3..=3), 3);
+        assert_eq!(rng.below(0), 0);
+    }
+}
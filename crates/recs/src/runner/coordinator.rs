@@ -0,0 +1,382 @@
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+    time::{Duration, Instant},
+};
+
+use crate::{error::RecsError, registry::Registry, runner::AppExit};
+
+/// Identifies a world added to a `WorldCoordinator`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WorldId(u64);
+
+/// Whether a world is still being stepped
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum WorldStatus {
+    /// The world is stepped on every `WorldCoordinator::step`
+    #[default]
+    Running,
+    /// A system inserted `AppExit`
+    Exited,
+    /// A system panicked with this message. The world is no longer stepped,
+    /// but stays available for inspection.
+    Panicked(String),
+}
+
+/// Timings and state of a world, updated on every step
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WorldDiagnostics {
+    pub status: WorldStatus,
+    /// Number of steps run
+    pub steps: u64,
+    /// Duration of the latest step
+    pub last_step: Duration,
+    /// Duration of the longest step
+    pub max_step: Duration,
+    /// Total duration of every step
+    pub total: Duration,
+    /// Number of steps that took longer than the world's budget
+    pub over_budget: u64,
+}
+
+impl WorldDiagnostics {
+    /// Returns the average duration of a step
+    pub fn average_step(&self) -> Duration {
+        if self.steps == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.steps as u32
+        }
+    }
+}
+
+struct World {
+    id: WorldId,
+    name: String,
+    registry: Registry,
+    budget: Option<Duration>,
+    diagnostics: WorldDiagnostics,
+}
+
+impl World {
+    /// Runs the systems of the world once, unless it stopped
+    fn step(&mut self) {
+        if self.diagnostics.status != WorldStatus::Running {
+            return;
+        }
+        if !self.registry.non_send.is_empty() {
+            self.diagnostics.status = WorldStatus::Panicked(
+                RecsError::NonSendWorld(self.registry.non_send.names()).to_string(),
+            );
+            return;
+        }
+
+        let start = Instant::now();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| self.registry.run_systems()));
+        let elapsed = start.elapsed();
+
+        let diagnostics = &mut self.diagnostics;
+        diagnostics.steps += 1;
+        diagnostics.last_step = elapsed;
+        diagnostics.max_step = diagnostics.max_step.max(elapsed);
+        diagnostics.total += elapsed;
+        if self.budget.is_some_and(|budget| elapsed > budget) {
+            diagnostics.over_budget += 1;
+        }
+        diagnostics.status = match result {
+            Err(payload) => WorldStatus::Panicked(panic_message(payload)),
+            Ok(()) if self.registry.has_resource::<AppExit>() => WorldStatus::Exited,
+            Ok(()) => WorldStatus::Running,
+        };
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Steps many independent registries, such as the match instances of a game
+/// server, across a shared pool of threads.
+///
+/// Every `step` runs the systems of each running world once, in parallel. A
+/// world that panics is isolated: it is marked as `Panicked` and no longer
+/// stepped, while the other worlds keep going. Steps are timed against an
+/// optional per-world budget, see `WorldDiagnostics`.
+///
+/// Uses the rayon thread pool when the `rayon` feature is enabled, and scoped
+/// threads otherwise.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use recs::runner::{AppExit, coordinator::{WorldCoordinator, WorldStatus}};
+/// # use std::time::Duration;
+/// #[derive(Resource, Default)]
+/// struct Round(u32);
+///
+/// fn play(mut round: ResMut<Round>, mut commands: Commands) {
+///     round.0 += 1;
+///     if round.0 == 3 {
+///         commands.insert_resource(AppExit);
+///     }
+/// }
+///
+/// let mut coordinator = WorldCoordinator::new();
+/// let matches: Vec<_> = (0..4)
+///     .map(|i| {
+///         let mut registry = Registry::new();
+///         registry.init_resource::<Round>();
+///         registry.add_system(play);
+///         let id = coordinator.add_world(format!("match-{i}"), registry).unwrap();
+///         coordinator.set_budget(id, Some(Duration::from_millis(5)));
+///         id
+///     })
+///     .collect();
+///
+/// while !coordinator.is_idle() {
+///     coordinator.step();
+/// }
+/// for id in matches {
+///     assert_eq!(coordinator.diagnostics(id).unwrap().status, WorldStatus::Exited);
+///     assert_eq!(coordinator.world(id).unwrap().get_resource::<Round>().unwrap().0, 3);
+/// }
+/// ```
+#[derive(Default)]
+pub struct WorldCoordinator {
+    worlds: Vec<World>,
+    next_id: u64,
+}
+
+impl WorldCoordinator {
+    /// Creates a coordinator without worlds
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a world to step, returning its id.
+    ///
+    /// Worlds are stepped on pool threads, where non-send resources can't be
+    /// accessed or dropped, so a registry holding any is rejected with
+    /// `RecsError::NonSendWorld`. A world that gets some later through
+    /// `world_mut` is stopped on its next step with a `Panicked` status
+    /// instead of running its systems.
+    pub fn add_world(
+        &mut self,
+        name: impl Into<String>,
+        registry: Registry,
+    ) -> Result<WorldId, RecsError> {
+        if !registry.non_send.is_empty() {
+            return Err(RecsError::NonSendWorld(registry.non_send.names()));
+        }
+        let id = WorldId(self.next_id);
+        self.next_id += 1;
+        self.worlds.push(World {
+            id,
+            name: name.into(),
+            registry,
+            budget: None,
+            diagnostics: WorldDiagnostics::default(),
+        });
+        Ok(id)
+    }
+
+    /// Removes a world, returning its registry
+    pub fn remove_world(&mut self, id: WorldId) -> Option<Registry> {
+        let index = self.worlds.iter().position(|world| world.id == id)?;
+        Some(self.worlds.remove(index).registry)
+    }
+
+    /// Sets how long a step of the world should take at most. Longer steps
+    /// are counted in `WorldDiagnostics::over_budget`.
+    pub fn set_budget(&mut self, id: WorldId, budget: Option<Duration>) {
+        if let Some(world) = self.get_mut(id) {
+            world.budget = budget;
+        }
+    }
+
+    /// Returns the registry of a world
+    pub fn world(&self, id: WorldId) -> Option<&Registry> {
+        self.get(id).map(|world| &world.registry)
+    }
+
+    /// Returns the registry of a world mutably, e.g. to feed it input
+    pub fn world_mut(&mut self, id: WorldId) -> Option<&mut Registry> {
+        self.get_mut(id).map(|world| &mut world.registry)
+    }
+
+    /// Returns the name a world was added with
+    pub fn name(&self, id: WorldId) -> Option<&str> {
+        self.get(id).map(|world| world.name.as_str())
+    }
+
+    /// Returns the diagnostics of a world
+    pub fn diagnostics(&self, id: WorldId) -> Option<&WorldDiagnostics> {
+        self.get(id).map(|world| &world.diagnostics)
+    }
+
+    /// Returns the ids of every world in the order they were added
+    pub fn ids(&self) -> impl Iterator<Item = WorldId> + '_ {
+        self.worlds.iter().map(|world| world.id)
+    }
+
+    /// Returns the number of worlds
+    pub fn len(&self) -> usize {
+        self.worlds.len()
+    }
+
+    /// Checks if the coordinator has no worlds
+    pub fn is_empty(&self) -> bool {
+        self.worlds.is_empty()
+    }
+
+    /// Returns true if no world is running anymore
+    pub fn is_idle(&self) -> bool {
+        self.worlds
+            .iter()
+            .all(|world| world.diagnostics.status != WorldStatus::Running)
+    }
+
+    /// Runs the systems of every running world once, in parallel
+    pub fn step(&mut self) {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            self.worlds.par_iter_mut().for_each(World::step);
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+            let per_thread = self.worlds.len().div_ceil(threads).max(1);
+            std::thread::scope(|scope| {
+                for worlds in self.worlds.chunks_mut(per_thread) {
+                    scope.spawn(|| worlds.iter_mut().for_each(World::step));
+                }
+            });
+        }
+    }
+
+    fn get(&self, id: WorldId) -> Option<&World> {
+        self.worlds.iter().find(|world| world.id == id)
+    }
+
+    fn get_mut(&mut self, id: WorldId) -> Option<&mut World> {
+        self.worlds.iter_mut().find(|world| world.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{ResMut, Resource};
+
+    #[derive(Default)]
+    struct Steps(u32);
+    impl Resource for Steps {}
+
+    fn count(mut steps: ResMut<Steps>) {
+        steps.0 += 1;
+    }
+
+    fn panic_on_second_step(steps: ResMut<Steps>) {
+        if steps.0 == 2 {
+            panic!("desync in step {}", steps.0);
+        }
+    }
+
+    fn slow(_steps: ResMut<Steps>) {
+        std::thread::sleep(Duration::from_millis(2));
+    }
+
+    fn world(extra: Option<fn(ResMut<Steps>)>) -> Registry {
+        let mut registry = Registry::new();
+        registry.init_resource::<Steps>();
+        registry.add_system(count);
+        if let Some(extra) = extra {
+            registry.add_system(extra);
+        }
+        registry
+    }
+
+    #[test]
+    fn test_panics_are_isolated_and_budgets_tracked() {
+        let mut coordinator = WorldCoordinator::new();
+        let healthy = coordinator.add_world("healthy", world(None)).unwrap();
+        let crashing = coordinator
+            .add_world("crashing", world(Some(panic_on_second_step)))
+            .unwrap();
+        let sluggish = coordinator
+            .add_world("sluggish", world(Some(slow)))
+            .unwrap();
+        coordinator.set_budget(sluggish, Some(Duration::from_millis(1)));
+        coordinator.set_budget(healthy, Some(Duration::from_secs(10)));
+
+        for _ in 0..4 {
+            coordinator.step();
+        }
+
+        let steps = |id| {
+            coordinator
+                .world(id)
+                .unwrap()
+                .get_resource::<Steps>()
+                .unwrap()
+                .0
+        };
+        assert_eq!(steps(healthy), 4);
+        assert_eq!(steps(sluggish), 4);
+        assert_eq!(steps(crashing), 2);
+        assert_eq!(
+            coordinator.diagnostics(crashing).unwrap().status,
+            WorldStatus::Panicked("desync in step 2".to_string())
+        );
+        assert_eq!(coordinator.diagnostics(crashing).unwrap().steps, 2);
+
+        let sluggish_diagnostics = coordinator.diagnostics(sluggish).unwrap();
+        assert_eq!(sluggish_diagnostics.over_budget, 4);
+        assert!(sluggish_diagnostics.average_step() >= Duration::from_millis(2));
+        assert_eq!(coordinator.diagnostics(healthy).unwrap().over_budget, 0);
+        assert!(!coordinator.is_idle());
+
+        assert_eq!(coordinator.name(crashing), Some("crashing"));
+        coordinator.remove_world(crashing).unwrap();
+        assert_eq!(coordinator.ids().collect::<Vec<_>>(), [healthy, sluggish]);
+        assert!(coordinator.world(crashing).is_none());
+    }
+
+    #[test]
+    fn test_worlds_with_non_send_resources_are_rejected() {
+        let mut coordinator = WorldCoordinator::new();
+        let mut registry = world(None);
+        registry.insert_non_send_resource(std::rc::Rc::new(1));
+        assert!(matches!(
+            coordinator.add_world("local", registry),
+            Err(RecsError::NonSendWorld(_))
+        ));
+        assert!(coordinator.is_empty());
+
+        let id = coordinator.add_world("late", world(None)).unwrap();
+        coordinator
+            .world_mut(id)
+            .unwrap()
+            .insert_non_send_resource(std::rc::Rc::new(1));
+        coordinator.step();
+        assert!(matches!(
+            coordinator.diagnostics(id).unwrap().status,
+            WorldStatus::Panicked(_)
+        ));
+        assert_eq!(
+            coordinator
+                .world(id)
+                .unwrap()
+                .get_resource::<Steps>()
+                .unwrap()
+                .0,
+            0
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use crate::resource::Resource;
+
+pub mod coordinator;
+pub mod server;
+
+/// Requests that the runner driving the registry stops after the current tick.
+///
+/// Systems request an exit by inserting this resource, e.g. through
+/// `Commands::insert_resource(AppExit)`. Code outside of the registry, such as
+/// signal handlers, uses an `ExitHandle` instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AppExit;
+
+impl Resource for AppExit {}
+
+/// A cloneable, thread-safe handle for requesting a runner to stop.
+///
+/// Requesting an exit only sets an atomic flag, so it is safe to call from
+/// signal handlers and other threads.
+#[derive(Debug, Clone, Default)]
+pub struct ExitHandle {
+    requested: Arc<AtomicBool>,
+}
+
+impl ExitHandle {
+    /// Creates a new handle with no exit requested
+    pub fn new() -> Self {
+        Self {
+            requested: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Requests the runner to stop after the current tick
+    pub fn request_exit(&self) {
+        self.requested.store(true, Ordering::Release);
+    }
+
+    /// Returns true if an exit was requested through this handle or a clone of it
+    pub fn is_exit_requested(&self) -> bool {
+        self.requested.load(Ordering::Acquire)
+    }
+}
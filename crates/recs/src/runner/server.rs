@@ -0,0 +1,309 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    registry::Registry,
+    resource::Resource,
+    runner::{AppExit, ExitHandle},
+};
+
+/// A per-tick hook run by the `ServerRunner`
+pub type TickHook = Box<dyn FnMut(&mut Registry)>;
+
+/// How the `ServerRunner` waits for the next tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitStrategy {
+    /// Sleeps the thread until the next tick. Cheapest, but the OS scheduler
+    /// may wake the thread late.
+    Sleep,
+    /// Busy-waits until the next tick. Most precise, but keeps a core busy.
+    Spin,
+    /// Sleeps until `spin_for` before the next tick, then busy-waits the rest
+    Hybrid { spin_for: Duration },
+}
+
+impl Default for WaitStrategy {
+    fn default() -> Self {
+        WaitStrategy::Hybrid {
+            spin_for: Duration::from_millis(1),
+        }
+    }
+}
+
+/// What the `ServerRunner` does when it falls behind its tick rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatchUpPolicy {
+    /// Runs a single tick and drops every other missed tick
+    Skip,
+    /// Runs up to `max_ticks` missed ticks back to back, then drops the rest
+    Burst { max_ticks: u32 },
+}
+
+impl Default for CatchUpPolicy {
+    fn default() -> Self {
+        CatchUpPolicy::Burst { max_ticks: 5 }
+    }
+}
+
+/// Information about the current server tick, kept up to date by the
+/// `ServerRunner` as a resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ServerTick {
+    /// Number of ticks run so far, including the current one
+    pub tick: u64,
+    /// The fixed duration of a tick
+    pub delta: Duration,
+    /// Number of ticks dropped so far by the catch-up policy
+    pub dropped: u64,
+}
+
+impl Resource for ServerTick {}
+
+/// A runner for headless dedicated servers that runs the registry's systems at
+/// a fixed tick rate.
+///
+/// Each tick runs the receive hooks, then `Registry::run_systems()`, then the
+/// send hooks, so network input is visible to every system and the state sent
+/// out reflects the whole tick. The runner stops once a system inserts the
+/// `AppExit` resource or an exit is requested through an `ExitHandle`.
+///
+/// # Example
+/// ```rust,no_run
+/// # use recs::prelude::*;
+/// # use recs::runner::server::{ServerRunner, WaitStrategy};
+/// let mut registry = Registry::new();
+/// let mut runner = ServerRunner::new(60)
+///     .with_wait_strategy(WaitStrategy::Sleep)
+///     .on_receive(|_registry| { /* read packets into resources */ })
+///     .on_send(|_registry| { /* broadcast snapshots */ });
+///
+/// // Hand the exit handle to a signal handler for graceful shutdown
+/// let exit = runner.exit_handle();
+/// # exit.request_exit();
+///
+/// runner.run(&mut registry);
+/// ```
+pub struct ServerRunner {
+    tick_period: Duration,
+    wait_strategy: WaitStrategy,
+    catch_up: CatchUpPolicy,
+    exit: ExitHandle,
+    receive_hooks: Vec<TickHook>,
+    send_hooks: Vec<TickHook>,
+    tick: u64,
+    dropped: u64,
+}
+
+impl ServerRunner {
+    /// Creates a runner ticking `tick_rate` times per second
+    ///
+    /// # Panics
+    /// Panics if `tick_rate` is zero.
+    pub fn new(tick_rate: u32) -> Self {
+        assert!(tick_rate > 0, "Server tick rate must be greater than zero");
+        Self::with_tick_period(Duration::from_secs(1) / tick_rate)
+    }
+
+    /// Creates a runner with an explicit duration per tick
+    pub fn with_tick_period(tick_period: Duration) -> Self {
+        Self {
+            tick_period,
+            wait_strategy: WaitStrategy::default(),
+            catch_up: CatchUpPolicy::default(),
+            exit: ExitHandle::new(),
+            receive_hooks: Vec::new(),
+            send_hooks: Vec::new(),
+            tick: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Sets how the runner waits between ticks
+    pub fn with_wait_strategy(mut self, wait_strategy: WaitStrategy) -> Self {
+        self.wait_strategy = wait_strategy;
+        self
+    }
+
+    /// Sets what the runner does when it falls behind
+    pub fn with_catch_up(mut self, catch_up: CatchUpPolicy) -> Self {
+        self.catch_up = catch_up;
+        self
+    }
+
+    /// Adds a hook run at the start of every tick, before any system
+    pub fn on_receive(mut self, hook: impl FnMut(&mut Registry) + 'static) -> Self {
+        self.receive_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Adds a hook run at the end of every tick, after every system
+    pub fn on_send(mut self, hook: impl FnMut(&mut Registry) + 'static) -> Self {
+        self.send_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Returns a handle that stops the runner, e.g. from a signal handler
+    pub fn exit_handle(&self) -> ExitHandle {
+        self.exit.clone()
+    }
+
+    /// Returns the duration of a single tick
+    pub fn tick_period(&self) -> Duration {
+        self.tick_period
+    }
+
+    /// Runs a single tick immediately, regardless of the tick rate
+    pub fn tick(&mut self, registry: &mut Registry) {
+        self.tick += 1;
+        registry.insert_resource(ServerTick {
+            tick: self.tick,
+            delta: self.tick_period,
+            dropped: self.dropped,
+        });
+
+        for hook in &mut self.receive_hooks {
+            hook(registry);
+        }
+        registry.run_systems();
+        for hook in &mut self.send_hooks {
+            hook(registry);
+        }
+    }
+
+    /// Returns true once a system or an `ExitHandle` requested the runner to stop
+    pub fn should_exit(&self, registry: &Registry) -> bool {
+        self.exit.is_exit_requested() || registry.has_resource::<AppExit>()
+    }
+
+    /// Runs ticks at the configured rate until an exit is requested
+    pub fn run(&mut self, registry: &mut Registry) {
+        let mut next_tick = Instant::now();
+
+        while !self.should_exit(registry) {
+            let now = Instant::now();
+            if now < next_tick {
+                self.wait_until(next_tick);
+                continue;
+            }
+
+            let behind = now - next_tick;
+            let due = (behind.as_nanos() / self.tick_period.as_nanos().max(1)) as u64 + 1;
+            let to_run = self.ticks_to_run(due);
+
+            for _ in 0..to_run {
+                self.tick(registry);
+                if self.should_exit(registry) {
+                    return;
+                }
+            }
+
+            if to_run < due {
+                // Resynchronize instead of trying to make up for dropped ticks
+                self.dropped += due - to_run;
+                next_tick = now + self.tick_period;
+            } else {
+                next_tick += self.tick_period * to_run as u32;
+            }
+        }
+    }
+
+    /// Returns how many of `due` ticks to run back to back under the catch-up policy
+    fn ticks_to_run(&self, due: u64) -> u64 {
+        match self.catch_up {
+            CatchUpPolicy::Skip => due.min(1),
+            CatchUpPolicy::Burst { max_ticks } => due.min(max_ticks.max(1) as u64),
+        }
+    }
+
+    fn wait_until(&self, deadline: Instant) {
+        let spin_for = match self.wait_strategy {
+            WaitStrategy::Sleep => Duration::ZERO,
+            WaitStrategy::Spin => Duration::MAX,
+            WaitStrategy::Hybrid { spin_for } => spin_for,
+        };
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining > spin_for {
+            thread::sleep(remaining - spin_for);
+        }
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::{resource::ResMut, system::commands::Commands};
+
+    #[derive(Default)]
+    struct TickCount(u64);
+    impl Resource for TickCount {}
+
+    fn count_and_exit(mut count: ResMut<TickCount>, mut commands: Commands) {
+        count.0 += 1;
+        if count.0 == 5 {
+            commands.insert_resource(AppExit);
+        }
+    }
+
+    #[test]
+    fn test_runs_until_app_exit() {
+        let mut registry = Registry::new();
+        registry.init_resource::<TickCount>();
+        registry.add_system(count_and_exit);
+
+        let received = Rc::new(Cell::new(0));
+        let sent = Rc::new(Cell::new(0));
+        let mut runner = ServerRunner::new(1000)
+            .with_wait_strategy(WaitStrategy::Spin)
+            .on_receive({
+                let received = received.clone();
+                move |_| received.set(received.get() + 1)
+            })
+            .on_send({
+                let sent = sent.clone();
+                move |registry| {
+                    // Send hooks observe the state after every system ran
+                    let ticks = registry.get_resource::<TickCount>().unwrap().0;
+                    sent.set(ticks);
+                }
+            });
+
+        runner.run(&mut registry);
+
+        assert_eq!(registry.get_resource::<TickCount>().unwrap().0, 5);
+        assert_eq!(received.get(), 5);
+        assert_eq!(sent.get(), 5);
+        assert_eq!(registry.get_resource::<ServerTick>().unwrap().tick, 5);
+    }
+
+    #[test]
+    fn test_exit_handle_stops_runner() {
+        let mut registry = Registry::new();
+        registry.init_resource::<TickCount>();
+        registry.add_system(count_and_exit);
+
+        let mut runner = ServerRunner::new(1000);
+        runner.exit_handle().request_exit();
+        runner.run(&mut registry);
+
+        assert_eq!(registry.get_resource::<TickCount>().unwrap().0, 0);
+    }
+
+    #[test]
+    fn test_catch_up_policy() {
+        let skip = ServerRunner::new(60).with_catch_up(CatchUpPolicy::Skip);
+        assert_eq!(skip.ticks_to_run(1), 1);
+        assert_eq!(skip.ticks_to_run(10), 1);
+
+        let burst = ServerRunner::new(60).with_catch_up(CatchUpPolicy::Burst { max_ticks: 3 });
+        assert_eq!(burst.ticks_to_run(2), 2);
+        assert_eq!(burst.ticks_to_run(10), 3);
+    }
+}
@@ -0,0 +1,446 @@
+//! Human-editable scenes spawned into a live registry.
+//!
+//! A scene lists entities with scene-local ids, an optional parent and
+//! component values keyed by the name they were registered under with
+//! `Registry::register_serializable_as`. Scenes are written in RON:
+//!
+//! ```text
+//! (
+//!     entities: [
+//!         (id: 0, components: { "Door": (locked: true) }),
+//!         (id: 1, parent: Some(0), components: { "Key": (opens: (0, 0)) }),
+//!     ],
+//! )
+//! ```
+//!
+//! Every spawn creates fresh entities. Entities referenced inside component
+//! values use scene-local ids (the generation is ignored) and are remapped to
+//! the spawned entities for components registered with
+//! `Registry::register_map_entities`.
+//!
+//! A scene can also patch single fields of its entities' components, through
+//! the names registered with `Registry::register_reflect_as`. Patches make
+//! variants of a base scene, such as the night version of a level, cheap to
+//! write: the variant only lists the entities it adds, the components it
+//! replaces and the fields it changes, and is resolved against the base when
+//! spawned with `Registry::spawn_scene_variant`.
+//!
+//! ```text
+//! (
+//!     entities: [(id: 2, components: { "Lamp": (lit: true) })],
+//!     patches: [(id: 0, fields: { "Door.locked": false })],
+//! )
+//! ```
+
+use std::collections::{BTreeMap, HashSet};
+
+use ron::value::RawValue;
+
+use crate::{
+    error::RecsError,
+    reflect::ReflectValue,
+    registry::{Registry, serialize::EntityMap},
+};
+
+/// An entity of a `Scene`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SceneEntity {
+    /// The scene-local id, referenced by `parent` and by component values
+    pub id: u32,
+    /// The scene-local id of the parent
+    #[serde(default)]
+    pub parent: Option<u32>,
+    /// Component values in RON, keyed by the name they were registered under
+    #[serde(default)]
+    pub components: BTreeMap<String, Box<RawValue>>,
+}
+
+/// Field changes of an entity of a `Scene`, applied once its components are
+/// inserted
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ScenePatch {
+    /// The scene-local id of the patched entity
+    pub id: u32,
+    /// New values keyed by a component name registered with
+    /// `Registry::register_reflect_as` followed by a field path, such as
+    /// `"Unit.stats.health"`
+    pub fields: BTreeMap<String, ReflectValue>,
+}
+
+/// A list of entities and their components that can be spawned any number of
+/// times, see the module documentation
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct Scene {
+    pub entities: Vec<SceneEntity>,
+    /// Applied in order after every component is inserted
+    #[serde(default)]
+    pub patches: Vec<ScenePatch>,
+}
+
+impl Scene {
+    /// Parses a scene from RON. Component values are only checked when the
+    /// scene is spawned.
+    pub fn from_ron(text: &str) -> Result<Self, RecsError> {
+        ron::from_str(text).map_err(|error| RecsError::InvalidScene(error.to_string()))
+    }
+
+    /// Returns this scene extended by `variant`: the entities of `variant`
+    /// whose id is also used here take its components over the base ones and
+    /// its parent if it has one, its other entities are added, and its
+    /// patches are applied after the base ones.
+    pub fn with_variant(&self, variant: &Scene) -> Scene {
+        let mut scene = self.clone();
+        for entity in &variant.entities {
+            match scene.entities.iter_mut().find(|base| base.id == entity.id) {
+                Some(base) => {
+                    base.parent = entity.parent.or(base.parent);
+                    base.components.extend(entity.components.clone());
+                }
+                None => scene.entities.push(entity.clone()),
+            }
+        }
+        scene.patches.extend(variant.patches.iter().cloned());
+        scene
+    }
+}
+
+impl Registry {
+    /// Spawns fresh entities for every entity of `scene`, sets their parents
+    /// and returns the entity spawned for each scene-local id.
+    ///
+    /// Returns `InvalidScene` without leaving anything spawned if ids repeat,
+    /// a parent or patched entity isn't part of the scene, the parents form a
+    /// cycle, a component is unknown or its value invalid, or a patched field
+    /// doesn't exist or can't hold its value.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use recs::entity::map::{EntityMapper, MapEntities};
+    /// # use recs::scene::Scene;
+    /// #[derive(Component, serde::Serialize, serde::Deserialize)]
+    /// struct Door { locked: bool }
+    ///
+    /// #[derive(Component, serde::Serialize, serde::Deserialize)]
+    /// struct Key { opens: Entity }
+    ///
+    /// impl MapEntities for Key {
+    ///     fn map_entities(&mut self, mapper: &mut dyn EntityMapper) {
+    ///         self.opens.map_entities(mapper);
+    ///     }
+    /// }
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.register_serializable_as::<Door>("Door");
+    /// registry.register_serializable_as::<Key>("Key");
+    /// registry.register_map_entities::<Key>();
+    ///
+    /// let scene = Scene::from_ron(r#"(
+    ///     entities: [
+    ///         (id: 7, components: { "Door": (locked: true) }),
+    ///         (id: 8, parent: Some(7), components: { "Key": (opens: (7, 0)) }),
+    ///     ],
+    /// )"#).unwrap();
+    ///
+    /// registry.create_entity();
+    /// let spawned = registry.spawn_scene(&scene).unwrap();
+    /// let (door, key) = (spawned.get(7).unwrap(), spawned.get(8).unwrap());
+    /// assert!(registry.get_component::<Door>(door).unwrap().locked);
+    /// assert_eq!(registry.get_component::<Key>(key).unwrap().opens, door);
+    /// assert_eq!(registry.parent(key), Some(door));
+    /// ```
+    pub fn spawn_scene(&mut self, scene: &Scene) -> Result<EntityMap, RecsError> {
+        let mut ids = HashSet::new();
+        for entity in &scene.entities {
+            if !ids.insert(entity.id) {
+                return Err(RecsError::InvalidScene(format!(
+                    "entity id {} is used twice",
+                    entity.id
+                )));
+            }
+        }
+        if let Some(parent) = scene
+            .entities
+            .iter()
+            .filter_map(|entity| entity.parent)
+            .find(|parent| !ids.contains(parent))
+        {
+            return Err(RecsError::InvalidScene(format!(
+                "parent {parent} is not an entity of the scene"
+            )));
+        }
+        if let Some(patch) = scene.patches.iter().find(|patch| !ids.contains(&patch.id)) {
+            return Err(RecsError::InvalidScene(format!(
+                "patched entity {} is not an entity of the scene",
+                patch.id
+            )));
+        }
+
+        let mut map = EntityMap::new();
+        for entity in &scene.entities {
+            map.insert(entity.id, self.create_entity());
+        }
+        let result = self.fill_scene(scene, &mut map);
+        if result.is_err() {
+            for (_, entity) in map.iter() {
+                let _ = self.destroy_entity(entity);
+            }
+        }
+        result.map(|_| map)
+    }
+
+    /// Spawns `base` extended by `variant`, see `Scene::with_variant`. The
+    /// variant's patches apply to the components of both scenes.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use recs::scene::Scene;
+    /// #[derive(Component, Reflect, serde::Serialize, serde::Deserialize)]
+    /// struct Lamp { lit: bool, range: f32 }
+    ///
+    /// let mut registry = Registry::new();
+    /// registry.register_serializable_as::<Lamp>("Lamp");
+    /// registry.register_reflect_as::<Lamp>("Lamp");
+    ///
+    /// let level = Scene::from_ron(r#"(entities: [
+    ///     (id: 0, components: { "Lamp": (lit: false, range: 4.0) }),
+    /// ])"#).unwrap();
+    /// let night = Scene::from_ron(r#"(
+    ///     entities: [(id: 1, components: { "Lamp": (lit: true, range: 2.0) })],
+    ///     patches: [(id: 0, fields: { "Lamp.lit": true })],
+    /// )"#).unwrap();
+    ///
+    /// let spawned = registry.spawn_scene_variant(&level, &night).unwrap();
+    /// let lamp = registry.get_component::<Lamp>(spawned.get(0).unwrap()).unwrap();
+    /// assert!(lamp.lit);
+    /// assert_eq!(lamp.range, 4.0);
+    /// assert!(spawned.get(1).is_some());
+    /// ```
+    pub fn spawn_scene_variant(
+        &mut self,
+        base: &Scene,
+        variant: &Scene,
+    ) -> Result<EntityMap, RecsError> {
+        self.spawn_scene(&base.with_variant(variant))
+    }
+
+    /// Inserts the components, applies the patches and sets the parents of
+    /// the entities spawned for `scene`
+    fn fill_scene(&mut self, scene: &Scene, map: &mut EntityMap) -> Result<(), RecsError> {
+        for scene_entity in &scene.entities {
+            let entity = map.get(scene_entity.id).expect("every entity is mapped");
+            for (name, value) in &scene_entity.components {
+                let mut deserializer = ron::Deserializer::from_str(value.get_ron())
+                    .map_err(|error| RecsError::InvalidScene(error.to_string()))?;
+                self.deserialize_component(
+                    entity,
+                    name,
+                    &mut <dyn erased_serde::Deserializer>::erase(&mut deserializer),
+                )
+                .map_err(|error| {
+                    RecsError::InvalidScene(format!("entity {}: {error}", scene_entity.id))
+                })?;
+            }
+        }
+
+        for patch in &scene.patches {
+            let entity = map.get(patch.id).expect("every entity is mapped");
+            for (key, value) in &patch.fields {
+                let (component, path) = key.split_once('.').unwrap_or((key, ""));
+                let patched = self
+                    .get_component_dyn_mut(entity, component)
+                    .and_then(|component| component.path_mut(path))
+                    .is_some_and(|field| field.set_value(value.clone()));
+                if !patched {
+                    return Err(RecsError::InvalidScene(format!(
+                        "entity {}: can't set {key} to {value:?}",
+                        patch.id
+                    )));
+                }
+            }
+        }
+
+        for scene_entity in &scene.entities {
+            let entity = map.get(scene_entity.id).expect("every entity is mapped");
+            if let Some(parent) = scene_entity.parent.and_then(|parent| map.get(parent)) {
+                self.set_parent(entity, parent).map_err(|_| {
+                    RecsError::InvalidScene(format!(
+                        "the parent of entity {} is its own descendant",
+                        scene_entity.id
+                    ))
+                })?;
+            }
+            self.map_entities(entity, map);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        component::Component,
+        entity::{
+            Entity,
+            map::{EntityMapper, MapEntities},
+        },
+    };
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    enum Shape {
+        Circle(f32),
+        Rect { w: f32, h: f32 },
+    }
+    impl Component for Shape {}
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Follows(Entity);
+    impl Component for Follows {}
+    impl MapEntities for Follows {
+        fn map_entities(&mut self, mapper: &mut dyn EntityMapper) {
+            self.0.map_entities(mapper);
+        }
+    }
+
+    #[derive(Debug, PartialEq, crate::Reflect, serde::Serialize, serde::Deserialize)]
+    struct Light {
+        lit: bool,
+        range: f32,
+    }
+    impl Component for Light {}
+
+    fn new_registry() -> Registry {
+        let mut registry = Registry::new();
+        registry.register_serializable_as::<Shape>("Shape");
+        registry.register_serializable_as::<Follows>("Follows");
+        registry.register_map_entities::<Follows>();
+        registry.register_serializable_as::<Light>("Light");
+        registry.register_reflect_as::<Light>("Light");
+        registry
+    }
+
+    #[test]
+    fn test_each_spawn_creates_fresh_remapped_entities() {
+        let scene = Scene::from_ron(
+            r#"(entities: [
+                (id: 0, components: { "Shape": Rect(w: 2.0, h: 1.0) }),
+                (id: 1, parent: Some(0), components: {
+                    "Shape": Circle(0.5),
+                    "Follows": ((0, 0)),
+                }),
+            ])"#,
+        )
+        .unwrap();
+
+        let mut registry = new_registry();
+        let first = registry.spawn_scene(&scene).unwrap();
+        let second = registry.spawn_scene(&scene).unwrap();
+        assert_eq!(registry.entities().count(), 4);
+
+        for map in [&first, &second] {
+            let (root, child) = (map.get(0).unwrap(), map.get(1).unwrap());
+            assert_eq!(
+                registry.get_component::<Shape>(root),
+                Some(&Shape::Rect { w: 2.0, h: 1.0 })
+            );
+            assert_eq!(
+                registry.get_component::<Follows>(child),
+                Some(&Follows(root))
+            );
+            assert_eq!(registry.children(root), &[child]);
+        }
+        assert_ne!(first.get(0), second.get(0));
+    }
+
+    #[test]
+    fn test_invalid_scenes_spawn_nothing() {
+        let mut registry = new_registry();
+        let invalid = [
+            r#"(entities: [(id: 0), (id: 0)])"#,
+            r#"(entities: [(id: 0, parent: Some(3))])"#,
+            r#"(entities: [(id: 0, parent: Some(1)), (id: 1, parent: Some(0))])"#,
+            r#"(entities: [(id: 0, components: { "Velocity": (1.0) })])"#,
+            r#"(entities: [(id: 0), (id: 1, components: { "Shape": Square })])"#,
+        ];
+        for text in invalid {
+            let scene = Scene::from_ron(text).unwrap();
+            assert!(
+                matches!(
+                    registry.spawn_scene(&scene),
+                    Err(RecsError::InvalidScene(_))
+                ),
+                "{text}"
+            );
+            assert_eq!(registry.entities().count(), 0, "{text}");
+        }
+        assert!(matches!(
+            Scene::from_ron("(entities: 3)"),
+            Err(RecsError::InvalidScene(_))
+        ));
+    }
+
+    #[test]
+    fn test_variants_add_replace_and_patch_base_entities() {
+        let base = Scene::from_ron(
+            r#"(entities: [
+                (id: 0, components: { "Light": (lit: false, range: 4.0), "Shape": Circle(1.0) }),
+                (id: 1, parent: Some(0), components: { "Light": (lit: false, range: 1.0) }),
+            ])"#,
+        )
+        .unwrap();
+        let night = Scene::from_ron(
+            r#"(
+                entities: [
+                    (id: 1, components: { "Shape": Circle(2.0) }),
+                    (id: 2, components: { "Light": (lit: true, range: 8.0) }),
+                ],
+                patches: [(id: 0, fields: { "Light.lit": true, "Light.range": 6 })],
+            )"#,
+        )
+        .unwrap();
+
+        let mut registry = new_registry();
+        let map = registry.spawn_scene_variant(&base, &night).unwrap();
+        let (root, child) = (map.get(0).unwrap(), map.get(1).unwrap());
+        assert_eq!(
+            registry.get_component::<Light>(root),
+            Some(&Light {
+                lit: true,
+                range: 6.0
+            })
+        );
+        assert_eq!(
+            registry.get_component::<Shape>(child),
+            Some(&Shape::Circle(2.0))
+        );
+        assert!(!registry.get_component::<Light>(child).unwrap().lit);
+        assert_eq!(registry.parent(child), Some(root));
+        assert!(
+            registry
+                .get_component::<Light>(map.get(2).unwrap())
+                .unwrap()
+                .lit
+        );
+
+        let invalid = [
+            r#"(entities: [], patches: [(id: 5, fields: {})])"#,
+            r#"(entities: [], patches: [(id: 0, fields: { "Light.color": 1 })])"#,
+            r#"(entities: [], patches: [(id: 1, fields: { "Light.lit": 3.5 })])"#,
+            r#"(entities: [], patches: [(id: 1, fields: { "Shape.radius": 1 })])"#,
+        ];
+        let count = registry.entities().count();
+        for text in invalid {
+            let variant = Scene::from_ron(text).unwrap();
+            assert!(
+                matches!(
+                    registry.spawn_scene_variant(&base, &variant),
+                    Err(RecsError::InvalidScene(_))
+                ),
+                "{text}"
+            );
+            assert_eq!(registry.entities().count(), count, "{text}");
+        }
+    }
+}
@@ -0,0 +1,412 @@
+//! Data-driven weighted random spawning.
+//!
+//! A `SpawnTable` describes what to spawn as weighted entries, each naming a
+//! spawnable, a nested table, or nothing. Names are resolved against a
+//! `Spawnables` resource mapping them to prefabs or bundle factories, so tables
+//! can live in data files (see `SpawnTable::from_ron` with the `ron` feature)
+//! while the components stay in code.
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    entity::Entity,
+    error::RecsError,
+    prefab::{Prefab, PrefabInstance},
+    registry::bundle::ComponentBundle,
+    resource::Resource,
+    rng::Rng,
+    system::commands::Commands,
+};
+
+/// An inclusive range of counts, rolled uniformly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Count {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl Count {
+    /// Always `count`
+    pub fn exactly(count: u32) -> Self {
+        Self {
+            min: count,
+            max: count,
+        }
+    }
+
+    /// Anywhere from `min` to `max`, both included
+    pub fn between(min: u32, max: u32) -> Self {
+        Self { min, max }
+    }
+
+    fn roll(&self, rng: &mut Rng) -> u32 {
+        rng.range(self.min..=self.max)
+    }
+}
+
+impl Default for Count {
+    fn default() -> Self {
+        Self::exactly(1)
+    }
+}
+
+/// What an entry of a `SpawnTable` spawns
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpawnTarget {
+    /// The spawnable registered under this name in `Spawnables`
+    Spawnable(String),
+    /// The result of rolling a nested table
+    Table(SpawnTable),
+    /// Nothing, to give a table a chance of spawning less
+    Nothing,
+}
+
+/// A weighted entry of a `SpawnTable`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpawnEntry {
+    /// Relative chance of the entry being picked by a roll
+    #[cfg_attr(feature = "serde", serde(default = "default_weight"))]
+    pub weight: u32,
+    /// How many times the target is spawned when the entry is picked
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub count: Count,
+    pub spawn: SpawnTarget,
+}
+
+#[cfg(feature = "serde")]
+fn default_weight() -> u32 {
+    1
+}
+
+impl SpawnEntry {
+    /// Creates an entry spawning `target` once, with weight 1
+    pub fn new(target: SpawnTarget) -> Self {
+        Self {
+            weight: 1,
+            count: Count::default(),
+            spawn: target,
+        }
+    }
+
+    /// Sets the relative chance of the entry being picked
+    pub fn weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Sets how many times the target is spawned when the entry is picked
+    pub fn count(mut self, count: Count) -> Self {
+        self.count = count;
+        self
+    }
+}
+
+/// Weighted random spawning rules.
+///
+/// Resolving the table rolls `rolls` times; each roll picks one entry with a
+/// chance proportional to its weight, and spawns the entry's target `count`
+/// times. Nested tables are resolved the same way.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use recs::{rng::Rng, spawn_table::*};
+/// #[derive(Component)]
+/// struct Goblin;
+///
+/// #[derive(Component)]
+/// struct Chest;
+///
+/// let table = SpawnTable::new(Count::between(2, 4))
+///     .with(SpawnEntry::new(SpawnTarget::Spawnable("goblin".into())).weight(3))
+///     .with(SpawnEntry::new(SpawnTarget::Spawnable("chest".into())).weight(1));
+/// let spawnables = Spawnables::new()
+///     .with_bundle("goblin", || (Goblin,))
+///     .with_bundle("chest", || (Chest,));
+///
+/// let mut registry = Registry::new();
+/// let mut rng = Rng::seeded(1);
+/// let spawned = table.spawn(&spawnables, &mut rng, &mut registry.commands()).unwrap();
+/// registry.apply_commands();
+///
+/// assert!((2..=4).contains(&spawned.len()));
+/// let goblins = registry.query::<(&Goblin,)>().count();
+/// assert_eq!(goblins + registry.query::<(&Chest,)>().count(), spawned.len());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpawnTable {
+    /// How many entries are picked
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rolls: Count,
+    pub entries: Vec<SpawnEntry>,
+}
+
+impl SpawnTable {
+    /// Creates an empty table picking `rolls` entries
+    pub fn new(rolls: Count) -> Self {
+        Self {
+            rolls,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds an entry
+    pub fn with(mut self, entry: SpawnEntry) -> Self {
+        self.entries.push(entry);
+        self
+    }
+
+    /// Parses a table from RON.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::spawn_table::*;
+    /// let table = SpawnTable::from_ron(r#"(
+    ///     rolls: (min: 1, max: 2),
+    ///     entries: [
+    ///         (weight: 5, spawn: Spawnable("goblin"), count: (min: 2, max: 3)),
+    ///         (weight: 1, spawn: Table((entries: [(spawn: Spawnable("chest"))]))),
+    ///         (weight: 4, spawn: Nothing),
+    ///     ],
+    /// )"#).unwrap();
+    /// assert_eq!(table.entries.len(), 3);
+    /// ```
+    #[cfg(feature = "ron")]
+    pub fn from_ron(text: &str) -> Result<Self, RecsError> {
+        ron::from_str(text).map_err(|error| RecsError::InvalidSpawnTable(error.to_string()))
+    }
+
+    /// Rolls the table and returns the names of the spawnables to spawn, in
+    /// order, without spawning anything
+    pub fn roll(&self, rng: &mut Rng) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.roll_into(rng, &mut names);
+        names
+    }
+
+    fn roll_into<'t>(&'t self, rng: &mut Rng, names: &mut Vec<&'t str>) {
+        let total: u64 = self.entries.iter().map(|entry| entry.weight as u64).sum();
+        if total == 0 {
+            return;
+        }
+
+        for _ in 0..self.rolls.roll(rng) {
+            let mut pick = rng.below(total);
+            let entry = self
+                .entries
+                .iter()
+                .find(|entry| {
+                    let hit = pick < entry.weight as u64;
+                    pick = pick.saturating_sub(entry.weight as u64);
+                    hit
+                })
+                .expect("the pick is below the total weight");
+
+            for _ in 0..entry.count.roll(rng) {
+                match &entry.spawn {
+                    SpawnTarget::Spawnable(name) => names.push(name),
+                    SpawnTarget::Table(table) => table.roll_into(rng, names),
+                    SpawnTarget::Nothing => {}
+                }
+            }
+        }
+    }
+
+    /// Rolls the table and queues spawning the result through `commands`,
+    /// returning the reserved entities.
+    ///
+    /// Returns `UnknownSpawnable` without spawning anything if the roll names a
+    /// spawnable missing from `spawnables`.
+    pub fn spawn(
+        &self,
+        spawnables: &Spawnables,
+        rng: &mut Rng,
+        commands: &mut Commands,
+    ) -> Result<Vec<Entity>, RecsError> {
+        let spawners = self
+            .roll(rng)
+            .into_iter()
+            .map(|name| {
+                spawnables
+                    .spawners
+                    .get(name)
+                    .ok_or_else(|| RecsError::UnknownSpawnable(name.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(spawners
+            .into_iter()
+            .map(|spawner| spawner.spawn(commands))
+            .collect())
+    }
+}
+
+/// How a named spawnable is spawned
+#[derive(Clone)]
+enum Spawner {
+    Prefab(Arc<Prefab>),
+    Bundle(Arc<dyn Fn(&mut Commands) -> Entity + Send + Sync>),
+}
+
+impl Spawner {
+    fn spawn(&self, commands: &mut Commands) -> Entity {
+        match self {
+            Spawner::Prefab(prefab) => {
+                let entity = commands.spawn_empty();
+                let prefab = prefab.clone();
+                commands.add(move |registry| {
+                    let result = prefab.insert_into(registry, entity).and_then(|_| {
+                        registry.add_component(entity, PrefabInstance::new(prefab.clone()))
+                    });
+                    registry.command_errors.report("spawn_prefab", result);
+                });
+                entity
+            }
+            Spawner::Bundle(spawn) => spawn(commands),
+        }
+    }
+}
+
+/// The prefabs and bundle factories that `SpawnTable` entries refer to by
+/// name. Usually stored as a resource.
+#[derive(Clone, Default)]
+pub struct Spawnables {
+    spawners: HashMap<String, Spawner>,
+}
+
+impl Spawnables {
+    /// Creates an empty set of spawnables
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `prefab` under `name`, replacing any previous spawnable
+    pub fn with_prefab(mut self, name: impl Into<String>, prefab: Arc<Prefab>) -> Self {
+        self.spawners.insert(name.into(), Spawner::Prefab(prefab));
+        self
+    }
+
+    /// Registers a bundle factory under `name`, replacing any previous spawnable
+    pub fn with_bundle<B: ComponentBundle + Send + 'static>(
+        mut self,
+        name: impl Into<String>,
+        factory: impl Fn() -> B + Send + Sync + 'static,
+    ) -> Self {
+        let spawn = move |commands: &mut Commands| commands.spawn(factory());
+        self.spawners
+            .insert(name.into(), Spawner::Bundle(Arc::new(spawn)));
+        self
+    }
+
+    /// Checks if a spawnable is registered under `name`
+    pub fn contains(&self, name: &str) -> bool {
+        self.spawners.contains_key(name)
+    }
+}
+
+impl Resource for Spawnables {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        component::Component,
+        registry::Registry,
+        resource::{Res, ResMut},
+    };
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Goblin;
+    impl Component for Goblin {}
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Loot(u32);
+    impl Component for Loot {}
+
+    struct Wave(SpawnTable);
+    impl Resource for Wave {}
+
+    fn spawnable(name: &str) -> SpawnTarget {
+        SpawnTarget::Spawnable(name.to_string())
+    }
+
+    #[test]
+    fn test_roll_respects_weights_counts_and_nesting() {
+        let loot = SpawnTable::new(Count::exactly(1))
+            .with(SpawnEntry::new(spawnable("gold")))
+            .with(SpawnEntry::new(SpawnTarget::Nothing).weight(0));
+        let table = SpawnTable::new(Count::exactly(50))
+            .with(SpawnEntry::new(spawnable("goblin")).count(Count::between(2, 3)))
+            .with(SpawnEntry::new(SpawnTarget::Table(loot)).weight(3))
+            .with(SpawnEntry::new(spawnable("never")).weight(0));
+
+        let mut rng = Rng::seeded(3);
+        let names = table.roll(&mut rng);
+        let goblins = names.iter().filter(|&&name| name == "goblin").count();
+        let gold = names.iter().filter(|&&name| name == "gold").count();
+        assert_eq!(goblins + gold, names.len());
+        assert!(gold > 20 && goblins >= 2 * (50 - gold));
+        assert_eq!(table.roll(&mut Rng::seeded(3)), names);
+        assert!(SpawnTable::default().roll(&mut rng).is_empty());
+    }
+
+    #[test]
+    fn test_spawn_from_a_system() {
+        fn spawn_wave(
+            wave: Res<Wave>,
+            spawnables: Res<Spawnables>,
+            mut rng: ResMut<Rng>,
+            mut commands: Commands,
+        ) {
+            wave.0.spawn(&spawnables, &mut rng, &mut commands).unwrap();
+        }
+
+        let chest = Arc::new(Prefab::new().with(Loot(10)));
+        let mut registry = Registry::new();
+        registry.insert_resource(Rng::seeded(0));
+        registry.insert_resource(
+            Spawnables::new()
+                .with_bundle("goblin", || (Goblin,))
+                .with_prefab("chest", chest),
+        );
+        registry.insert_resource(Wave(
+            SpawnTable::new(Count::exactly(2))
+                .with(SpawnEntry::new(spawnable("goblin")).count(Count::exactly(3)))
+                .with(SpawnEntry::new(spawnable("chest"))),
+        ));
+        registry.add_system(spawn_wave);
+        registry.run_systems();
+
+        let goblins = registry.query::<(&Goblin,)>().count();
+        let chests = registry.query::<(&Loot, &PrefabInstance)>().count();
+        assert_eq!(goblins + chests * 3, 6);
+
+        let unknown = SpawnTable::new(Count::exactly(1)).with(SpawnEntry::new(spawnable("boss")));
+        let result = unknown.spawn(
+            &Spawnables::new(),
+            &mut Rng::seeded(0),
+            &mut registry.commands(),
+        );
+        assert!(matches!(result, Err(RecsError::UnknownSpawnable(name)) if name == "boss"));
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn test_ron_round_trip() {
+        let table = SpawnTable::new(Count::between(1, 2))
+            .with(SpawnEntry::new(spawnable("goblin")).weight(4))
+            .with(SpawnEntry::new(SpawnTarget::Table(
+                SpawnTable::new(Count::exactly(1)).with(SpawnEntry::new(SpawnTarget::Nothing)),
+            )));
+        let text = ron::to_string(&table).unwrap();
+        assert_eq!(SpawnTable::from_ron(&text).unwrap(), table);
+        assert!(matches!(
+            SpawnTable::from_ron("(entries: 3)"),
+            Err(RecsError::InvalidSpawnTable(_))
+        ));
+    }
+}
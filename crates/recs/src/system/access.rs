@@ -0,0 +1,225 @@
+use std::{
+    any::TypeId,
+    collections::{BTreeMap, HashSet},
+};
+
+/// The set of registry data a system reads and writes, derived from its
+/// `SystemParam`s.
+///
+/// The parallel executor only runs two systems at the same time if their
+/// accesses are compatible, i.e. neither writes anything the other one touches.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Access {
+    component_reads: HashSet<TypeId>,
+    component_writes: HashSet<TypeId>,
+    resource_reads: HashSet<TypeId>,
+    resource_writes: HashSet<TypeId>,
+    /// Deferred commands share the registry's queue and reserve entities,
+    /// which conflicts with any other entity access
+    commands: bool,
+    /// Reads entity liveness, e.g. through `Query::get`
+    entities: bool,
+    /// The system may touch anything and must run on its own
+    exclusive: bool,
+    /// The system uses non-send resources, so it must run on the thread
+    /// running the schedule
+    main_thread: bool,
+    /// Type names of the resources the system can't run without, ordered by
+    /// name for stable reports. Not considered for compatibility.
+    required_resources: BTreeMap<&'static str, TypeId>,
+    /// Parts of a single parameter conflict with each other, e.g. a query
+    /// naming a component twice with one of them writing it
+    conflicts_with_itself: bool,
+}
+
+impl Access {
+    /// Creates an empty Access that conflicts with nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an Access that conflicts with every other system
+    pub fn exclusive() -> Self {
+        Self {
+            exclusive: true,
+            ..Self::default()
+        }
+    }
+
+    /// Records a read of component `type_id`
+    pub fn read_component(&mut self, type_id: TypeId) {
+        self.component_reads.insert(type_id);
+    }
+
+    /// Records a write of component `type_id`
+    pub fn write_component(&mut self, type_id: TypeId) {
+        self.component_writes.insert(type_id);
+    }
+
+    /// Records a read of resource `type_id`
+    pub fn read_resource(&mut self, type_id: TypeId) {
+        self.resource_reads.insert(type_id);
+    }
+
+    /// Records a write of resource `type_id`
+    pub fn write_resource(&mut self, type_id: TypeId) {
+        self.resource_writes.insert(type_id);
+    }
+
+    /// Records that the system panics if resource `R` doesn't exist
+    pub fn require_resource<R: 'static>(&mut self) {
+        self.required_resources
+            .insert(std::any::type_name::<R>(), TypeId::of::<R>());
+    }
+
+    /// Returns the resources the system can't run without, as
+    /// `(type name, TypeId)` pairs ordered by name
+    pub fn required_resources(&self) -> impl Iterator<Item = (&'static str, TypeId)> + '_ {
+        self.required_resources
+            .iter()
+            .map(|(name, type_id)| (*name, *type_id))
+    }
+
+    /// Records the use of deferred commands
+    pub fn write_commands(&mut self) {
+        self.commands = true;
+    }
+
+    /// Records a read of entity liveness
+    pub fn read_entities(&mut self) {
+        self.entities = true;
+    }
+
+    /// Marks the system as having to run on the thread running the schedule
+    pub fn require_main_thread(&mut self) {
+        self.main_thread = true;
+    }
+
+    /// Returns true if the system must run on the thread running the schedule
+    pub fn requires_main_thread(&self) -> bool {
+        self.main_thread
+    }
+
+    /// Marks the system as requiring exclusive access to the registry
+    pub fn set_exclusive(&mut self) {
+        self.exclusive = true;
+    }
+
+    /// Returns true if the system records deferred commands
+    pub fn uses_commands(&self) -> bool {
+        self.commands
+    }
+
+    /// Returns true if the system reads or writes resource `type_id`
+    pub fn reads_resource(&self, type_id: TypeId) -> bool {
+        self.resource_reads.contains(&type_id) || self.resource_writes.contains(&type_id)
+    }
+
+    /// Returns true if the system writes resource `type_id`
+    pub fn writes_resource(&self, type_id: TypeId) -> bool {
+        self.resource_writes.contains(&type_id)
+    }
+
+    /// Returns true if the system requires exclusive access to the registry
+    pub fn is_exclusive(&self) -> bool {
+        self.exclusive
+    }
+
+    /// Merges every access of `other` into this one
+    pub fn extend(&mut self, other: &Access) {
+        self.component_reads.extend(&other.component_reads);
+        self.component_writes.extend(&other.component_writes);
+        self.resource_reads.extend(&other.resource_reads);
+        self.resource_writes.extend(&other.resource_writes);
+        self.commands |= other.commands;
+        self.entities |= other.entities;
+        self.exclusive |= other.exclusive;
+        self.main_thread |= other.main_thread;
+        self.required_resources.extend(&other.required_resources);
+        self.conflicts_with_itself |= other.conflicts_with_itself;
+    }
+
+    /// Merges `other`, the access of another part of the same parameter such
+    /// as an item of a query, recording a conflict if either part writes data
+    /// the other one accesses
+    pub fn extend_part(&mut self, other: &Access) {
+        self.conflicts_with_itself |= self.conflicts_over_data(other);
+        self.extend(other);
+    }
+
+    /// Returns true if parts of a single parameter conflict with each other,
+    /// see `extend_part`
+    pub fn conflicts_with_itself(&self) -> bool {
+        self.conflicts_with_itself
+    }
+
+    /// Returns true if systems with these accesses can safely run concurrently
+    pub fn is_compatible(&self, other: &Access) -> bool {
+        if self.exclusive || other.exclusive {
+            return false;
+        }
+
+        // Only one system of a batch runs on the thread running the schedule
+        if self.main_thread && other.main_thread {
+            return false;
+        }
+
+        // Reserving entities mutates the entity manager, which every query
+        // over entity handles and every other command buffer relies on
+        if (self.commands && (other.commands || other.entities))
+            || (other.commands && self.entities)
+        {
+            return false;
+        }
+
+        !self.conflicts_over_data(other)
+    }
+
+    /// Returns true if one of the accesses writes a component or resource the
+    /// other one reads or writes. Exclusive access and commands are ignored.
+    pub fn conflicts_over_data(&self, other: &Access) -> bool {
+        !(self.component_writes.is_disjoint(&other.component_writes)
+            && self.component_writes.is_disjoint(&other.component_reads)
+            && other.component_writes.is_disjoint(&self.component_reads)
+            && self.resource_writes.is_disjoint(&other.resource_writes)
+            && self.resource_writes.is_disjoint(&other.resource_reads)
+            && other.resource_writes.is_disjoint(&self.resource_reads))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct A;
+    struct B;
+
+    #[test]
+    fn test_access_compatibility() {
+        let mut read_a = Access::new();
+        read_a.read_component(TypeId::of::<A>());
+        let mut write_a = Access::new();
+        write_a.write_component(TypeId::of::<A>());
+        let mut write_b = Access::new();
+        write_b.write_component(TypeId::of::<B>());
+
+        assert!(read_a.is_compatible(&read_a.clone()));
+        assert!(write_a.is_compatible(&write_b));
+        assert!(!read_a.is_compatible(&write_a));
+        assert!(!write_a.is_compatible(&write_a.clone()));
+        assert!(!Access::exclusive().is_compatible(&Access::new()));
+
+        let mut commands = Access::new();
+        commands.write_commands();
+        let mut entities = read_a.clone();
+        entities.read_entities();
+        assert!(commands.is_compatible(&read_a));
+        assert!(!commands.is_compatible(&entities));
+        assert!(!commands.is_compatible(&commands.clone()));
+
+        let mut main_thread = read_a.clone();
+        main_thread.require_main_thread();
+        assert!(main_thread.is_compatible(&read_a));
+        assert!(!main_thread.is_compatible(&main_thread.clone()));
+    }
+}
@@ -0,0 +1,416 @@
+use std::{fmt, marker::PhantomData};
+
+use crate::{
+    component::Component,
+    entity::{Entity, EntityManager},
+    error::RecsError,
+    registry::{Registry, bundle::ComponentBundle},
+    resource::Resource,
+};
+
+/// A deferred operation on the registry
+pub type Command = Box<dyn FnOnce(&mut Registry) + Send>;
+
+/// A buffer of deferred registry operations.
+///
+/// Commands are recorded while systems run and applied by the Registry after
+/// each system, or explicitly through `Registry::apply_commands`.
+#[derive(Default)]
+pub struct CommandQueue {
+    commands: Vec<Command>,
+    /// The position of every queued spawn command with the entity it reserved
+    spawns: Vec<(usize, Entity)>,
+}
+
+impl CommandQueue {
+    /// Creates a new empty CommandQueue
+    pub fn new() -> Self {
+        Self {
+            commands: Vec::new(),
+            spawns: Vec::new(),
+        }
+    }
+
+    /// Pushes a command to the end of the queue
+    pub fn push(&mut self, command: impl FnOnce(&mut Registry) + Send + 'static) {
+        self.commands.push(Box::new(command));
+    }
+
+    /// Pushes a command filling `entity`, which was reserved for it
+    fn push_spawn(&mut self, entity: Entity, command: impl FnOnce(&mut Registry) + Send + 'static) {
+        self.spawns.push((self.commands.len(), entity));
+        self.push(command);
+    }
+
+    /// Applies every queued command to the registry in insertion order,
+    /// leaving the queue empty
+    pub fn apply(&mut self, registry: &mut Registry) {
+        self.spawns.clear();
+        for command in self.commands.drain(..) {
+            command(registry);
+        }
+    }
+
+    /// Drops the queued commands beyond the first `len`, returning the
+    /// entities reserved by the dropped spawn commands
+    pub(crate) fn truncate(&mut self, len: usize) -> Vec<Entity> {
+        self.commands.truncate(len);
+        let kept = self.spawns.partition_point(|&(index, _)| index < len);
+        self.spawns
+            .drain(kept..)
+            .map(|(_, entity)| entity)
+            .collect()
+    }
+
+    /// Returns the number of queued commands
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Returns true if no commands are queued
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+}
+
+/// What happens when a deferred command fails while being applied, e.g.
+/// because its target entity was destroyed in the meantime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommandErrorPolicy {
+    /// Drops the error
+    Ignore,
+    /// Prints the error to stderr
+    #[default]
+    Log,
+    /// Stores the error, to be retrieved with `Registry::take_command_errors`
+    Collect,
+    /// Panics with the error
+    Panic,
+}
+
+/// A deferred command that failed while being applied
+#[derive(Debug)]
+pub struct CommandError {
+    /// The name of the failed `Commands` method, e.g. `"insert"`
+    pub command: &'static str,
+    /// Why the command failed
+    pub error: RecsError,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Command `{}` failed: {}", self.command, self.error)
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// Handles the errors of applied commands according to a `CommandErrorPolicy`
+#[derive(Default)]
+pub(crate) struct CommandErrors {
+    pub(crate) policy: CommandErrorPolicy,
+    /// Errors stored under the `Collect` policy
+    pub(crate) errors: Vec<CommandError>,
+}
+
+impl CommandErrors {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles the result of the command named `command`
+    pub(crate) fn report<T>(&mut self, command: &'static str, result: Result<T, RecsError>) {
+        let Err(error) = result else {
+            return;
+        };
+
+        let error = CommandError { command, error };
+        match self.policy {
+            CommandErrorPolicy::Ignore => {}
+            CommandErrorPolicy::Log => eprintln!("{}", error),
+            CommandErrorPolicy::Collect => self.errors.push(error),
+            CommandErrorPolicy::Panic => panic!("{}", error),
+        }
+    }
+}
+
+/// A system parameter for spawning and despawning entities and changing their
+/// components from inside systems.
+///
+/// Operations are recorded into the registry's command queue and applied after
+/// the system finishes, so they never conflict with the queries a system holds.
+/// Spawned entities are reserved immediately, which means their handles can be
+/// used right away, but their components only become visible once the commands
+/// are applied.
+///
+/// Commands check their target entity when they are applied, so they never
+/// touch an entity that reused the id of a destroyed one. Failures are handled
+/// according to the registry's `CommandErrorPolicy`.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// #[derive(Component)]
+/// struct Bullet;
+///
+/// #[derive(Component)]
+/// struct Damage(u32);
+///
+/// fn fire(mut commands: Commands) {
+///     let bullet = commands.spawn((Bullet,));
+///     commands.insert(bullet, Damage(10));
+/// }
+///
+/// let mut registry = Registry::new();
+/// registry.add_system(fire);
+/// registry.run_systems();
+/// # assert_eq!(registry.query::<(&Bullet, &Damage)>().count(), 1);
+/// ```
+pub struct Commands<'a> {
+    /// Pointers rather than references: the command buffers and the queries
+    /// of a system interleave their accesses to the queue and the entity
+    /// slots, each borrowing them only for the duration of a call
+    queue: *mut CommandQueue,
+    entities: *mut EntityManager,
+    _marker: PhantomData<&'a mut CommandQueue>,
+}
+
+// SAFETY: the buffer acts as the exclusive references it was created from
+unsafe impl Send for Commands<'_> {}
+unsafe impl Sync for Commands<'_> {}
+
+impl<'a> Commands<'a> {
+    pub fn new(queue: &'a mut CommandQueue, entities: &'a mut EntityManager) -> Self {
+        // SAFETY: both are borrowed exclusively for 'a
+        unsafe { Self::from_raw(queue, entities) }
+    }
+
+    /// Creates a buffer recording into `queue` and reserving entities from
+    /// `entities`
+    ///
+    /// # Safety
+    /// Both must stay alive for 'a, and nothing may access them during a call
+    /// to a method of the buffer.
+    pub(crate) unsafe fn from_raw(queue: *mut CommandQueue, entities: *mut EntityManager) -> Self {
+        Self {
+            queue,
+            entities,
+            _marker: PhantomData,
+        }
+    }
+
+    fn queue(&mut self) -> &mut CommandQueue {
+        // SAFETY: see `from_raw`
+        unsafe { &mut *self.queue }
+    }
+
+    fn entities(&mut self) -> &mut EntityManager {
+        // SAFETY: see `from_raw`
+        unsafe { &mut *self.entities }
+    }
+
+    /// Reserves a new entity and queues the insertion of `bundle` onto it
+    pub fn spawn<B: ComponentBundle + Send + 'static>(&mut self, bundle: B) -> Entity {
+        let entity = self.entities().create_entity();
+        self.queue().push_spawn(entity, move |registry| {
+            let result = bundle.add_to_entity(registry, entity);
+            registry.insert_required_components();
+            registry.command_errors.report("spawn", result);
+        });
+        entity
+    }
+
+    /// Reserves a new entity without any components
+    pub fn spawn_empty(&mut self) -> Entity {
+        self.entities().create_entity()
+    }
+
+    /// Queues the destruction of an entity and all of its components
+    pub fn despawn(&mut self, entity: Entity) {
+        self.queue().push(move |registry| {
+            let result = registry.destroy_entity(entity);
+            registry.command_errors.report("despawn", result);
+        });
+    }
+
+    /// Queues the destruction of an entity along with all of its descendants,
+    /// see `Registry::despawn_recursive`
+    pub fn despawn_recursive(&mut self, entity: Entity) {
+        self.queue().push(move |registry| {
+            let result = registry.despawn_recursive(entity);
+            registry.command_errors.report("despawn_recursive", result);
+        });
+    }
+
+    /// Queues adding (or replacing) a component on an entity
+    pub fn insert<C: Component>(&mut self, entity: Entity, component: C) {
+        self.queue().push(move |registry| {
+            let result = registry.add_component(entity, component);
+            registry.command_errors.report("insert", result);
+        });
+    }
+
+    /// Queues adding a bundle of components to an existing entity
+    pub fn insert_bundle<B: ComponentBundle + Send + 'static>(
+        &mut self,
+        entity: Entity,
+        bundle: B,
+    ) {
+        self.queue().push(move |registry| {
+            let result = bundle.add_to_entity(registry, entity);
+            registry.insert_required_components();
+            registry.command_errors.report("insert_bundle", result);
+        });
+    }
+
+    /// Queues removing a component from an entity
+    pub fn remove<C: Component>(&mut self, entity: Entity) {
+        self.queue().push(move |registry| {
+            let result = registry.remove_component::<C>(entity);
+            registry.command_errors.report("remove", result);
+        });
+    }
+
+    /// Queues removing every component type of the bundle `B` from an entity
+    pub fn remove_bundle<B: ComponentBundle + 'static>(&mut self, entity: Entity) {
+        self.queue().push(move |registry| {
+            let result = registry.remove_bundle::<B>(entity);
+            registry.command_errors.report("remove_bundle", result);
+        });
+    }
+
+    /// Queues constructing `C` on an entity with its registered component
+    /// factory if the entity doesn't have it yet
+    pub fn init_component<C: Component>(&mut self, entity: Entity) {
+        self.queue().push(move |registry| {
+            let result = registry.init_component::<C>(entity);
+            registry.command_errors.report("init_component", result);
+        });
+    }
+
+    /// Queues inserting (or replacing) a resource
+    pub fn insert_resource<R: Resource>(&mut self, resource: R) {
+        self.queue()
+            .push(move |registry| registry.insert_resource(resource));
+    }
+
+    /// Queues removing a resource
+    pub fn remove_resource<R: Resource>(&mut self) {
+        self.queue().push(|registry| {
+            registry.remove_resource::<R>();
+        });
+    }
+
+    /// Queues an arbitrary operation on the registry
+    pub fn add(&mut self, command: impl FnOnce(&mut Registry) + Send + 'static) {
+        self.queue().push(command);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::Query;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(i32);
+    impl Component for Health {}
+
+    #[derive(Debug, PartialEq)]
+    struct Marker;
+    impl Component for Marker {}
+
+    fn spawn_system(mut commands: Commands) {
+        let entity = commands.spawn((Health(10),));
+        commands.insert(entity, Marker);
+    }
+
+    fn heal_and_spawn_system(query: Query<(&mut Health,)>, mut commands: Commands) {
+        for (mut health,) in query {
+            health.0 += 1;
+            commands.spawn((Marker,));
+        }
+    }
+
+    #[test]
+    fn test_commands_applied_after_system() {
+        let mut registry = Registry::new();
+        registry.add_system(spawn_system);
+
+        registry.run_systems();
+        registry.run_systems();
+
+        assert_eq!(registry.query::<(&Health, &Marker)>().count(), 2);
+    }
+
+    #[test]
+    fn test_commands_alongside_query() {
+        let mut registry = Registry::new();
+        registry.spawn(Health(1));
+        registry.spawn(Health(2));
+        registry.add_system(heal_and_spawn_system);
+
+        registry.run_systems();
+
+        assert_eq!(registry.query::<(&Marker,)>().count(), 2);
+        let total: i32 = registry.query::<(&Health,)>().map(|(h,)| h.0).sum();
+        assert_eq!(total, 5);
+    }
+
+    #[test]
+    fn test_registry_commands_deferred_until_apply() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn(Health(1));
+
+        let mut commands = registry.commands();
+        commands.remove::<Health>(entity);
+        let spawned = commands.spawn((Marker,));
+
+        assert!(registry.get_component::<Health>(entity).is_some());
+        assert!(registry.get_component::<Marker>(spawned).is_none());
+
+        registry.apply_commands();
+
+        assert!(registry.get_component::<Health>(entity).is_none());
+        assert_eq!(registry.get_component::<Marker>(spawned), Some(&Marker));
+    }
+
+    #[test]
+    fn test_commands_can_queue_more_commands() {
+        let mut registry = Registry::new();
+        let entity = registry.spawn(Health(1));
+
+        registry.commands().add(move |registry| {
+            registry.commands().despawn(entity);
+        });
+        registry.apply_commands();
+
+        assert!(!registry.is_valid(entity));
+    }
+
+    #[test]
+    fn test_command_on_reused_entity_slot_is_reported() {
+        let mut registry = Registry::new();
+        registry.set_command_error_policy(CommandErrorPolicy::Collect);
+        let stale = registry.spawn(Health(1));
+
+        registry.commands().insert(stale, Marker);
+        registry.destroy_entity(stale).unwrap();
+        let reused = registry.spawn(Health(2));
+        assert_eq!(reused.id(), stale.id());
+        registry.apply_commands();
+
+        // The entity that took over the id is left untouched
+        assert!(registry.get_component::<Marker>(reused).is_none());
+        let errors = registry.take_command_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].command, "insert");
+        assert!(matches!(errors[0].error, RecsError::InvalidEntity(e) if e == stale));
+        assert!(registry.take_command_errors().is_empty());
+
+        registry.set_command_error_policy(CommandErrorPolicy::Ignore);
+        registry.commands().despawn(stale);
+        registry.apply_commands();
+        assert!(registry.take_command_errors().is_empty());
+    }
+}
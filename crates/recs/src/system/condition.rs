@@ -0,0 +1,147 @@
+use crate::{registry::Registry, resource::Resource, system::SystemTicks};
+
+/// A predicate deciding whether a system runs, see `IntoSystemConfig::run_if`.
+///
+/// Implemented for closures taking the registry and the ticks of the run
+/// being considered, whose `last_run` is the tick of the system's previous
+/// run. Conditions only get shared access to the registry, so they can't
+/// change what they observe.
+pub trait Condition: Send + 'static {
+    /// Returns true if the system should run
+    fn evaluate(&mut self, registry: &Registry, ticks: SystemTicks) -> bool;
+}
+
+impl<F> Condition for F
+where
+    F: FnMut(&Registry, SystemTicks) -> bool + Send + 'static,
+{
+    fn evaluate(&mut self, registry: &Registry, ticks: SystemTicks) -> bool {
+        self(registry, ticks)
+    }
+}
+
+/// A boxed run condition, as stored alongside its system
+pub type BoxedCondition = Box<dyn Condition>;
+
+/// Runs the system only if resource `R` exists
+pub fn resource_exists<R: Resource>() -> impl Condition {
+    |registry: &Registry, _ticks: SystemTicks| registry.has_resource::<R>()
+}
+
+/// Runs the system only if resource `R` was inserted since the system last ran
+pub fn resource_added<R: Resource>() -> impl Condition {
+    |registry: &Registry, ticks: SystemTicks| {
+        registry
+            .resource_ticks::<R>()
+            .is_some_and(|resource| resource.is_added(ticks.last_run, ticks.this_run))
+    }
+}
+
+/// Runs the system only if resource `R` was inserted or mutably accessed since
+/// the system last ran. Never runs it while `R` doesn't exist.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use recs::system::condition::resource_changed;
+/// #[derive(Resource)]
+/// struct Config { scale: f32 }
+///
+/// #[derive(Resource, Default)]
+/// struct Rebuilds(u32);
+///
+/// fn rebuild_layout(_config: Res<Config>, mut rebuilds: ResMut<Rebuilds>) {
+///     rebuilds.0 += 1;
+/// }
+///
+/// let mut registry = Registry::new();
+/// registry.insert_resource(Config { scale: 1.0 });
+/// registry.init_resource::<Rebuilds>();
+/// registry.add_system(rebuild_layout.run_if(resource_changed::<Config>()));
+///
+/// registry.run_systems();
+/// registry.run_systems();
+/// assert_eq!(registry.get_resource::<Rebuilds>().unwrap().0, 1);
+///
+/// registry.get_resource_mut::<Config>().unwrap().scale = 2.0;
+/// registry.run_systems();
+/// assert_eq!(registry.get_resource::<Rebuilds>().unwrap().0, 2);
+/// ```
+pub fn resource_changed<R: Resource>() -> impl Condition {
+    |registry: &Registry, ticks: SystemTicks| {
+        registry
+            .resource_ticks::<R>()
+            .is_some_and(|resource| resource.is_changed(ticks.last_run, ticks.this_run))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        resource::{Res, ResMut},
+        system::{executor::ExecutorKind, schedule::IntoSystemConfig},
+    };
+
+    struct Config(u32);
+    impl Resource for Config {}
+
+    #[derive(Default)]
+    struct Seen(Vec<u32>);
+    impl Resource for Seen {}
+
+    fn record(config: Res<Config>, mut seen: ResMut<Seen>) {
+        assert!(config.is_changed());
+        seen.0.push(config.0);
+    }
+
+    fn world(executor: ExecutorKind) -> Registry {
+        let mut registry = Registry::new();
+        registry.set_executor(executor);
+        registry.init_resource::<Seen>();
+        registry.add_system(record.run_if(resource_changed::<Config>()));
+        registry
+    }
+
+    #[test]
+    fn test_resource_changed_skips_unchanged_resources() {
+        for executor in [ExecutorKind::Sequential, ExecutorKind::Parallel] {
+            let mut registry = world(executor);
+            registry.run_systems();
+            registry.insert_resource(Config(1));
+            registry.run_systems();
+            registry.run_systems();
+            registry.get_resource_mut::<Config>().unwrap().0 = 2;
+            registry.run_systems();
+            registry.run_systems();
+
+            assert_eq!(registry.get_resource::<Seen>().unwrap().0, [1, 2]);
+        }
+    }
+
+    #[test]
+    fn test_conditions_are_combined() {
+        fn count(mut seen: ResMut<Seen>) {
+            seen.0.push(0);
+        }
+        fn unrelated() {}
+
+        let mut registry = Registry::new();
+        registry.set_executor(ExecutorKind::Parallel);
+        registry.init_resource::<Seen>();
+        registry.insert_resource(Config(0));
+        registry.add_system(
+            count
+                .run_if(resource_exists::<Config>())
+                .run_if(resource_added::<Config>()),
+        );
+        registry.add_system(unrelated);
+        registry.run_systems();
+        registry.run_systems();
+        registry.remove_resource::<Config>();
+        registry.insert_resource(Config(0));
+        registry.run_systems();
+
+        assert_eq!(registry.get_resource::<Seen>().unwrap().0.len(), 2);
+    }
+}
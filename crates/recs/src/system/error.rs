@@ -0,0 +1,133 @@
+use std::{any::Any, borrow::Cow, fmt};
+
+use crate::{error::RecsError, registry::Registry, resource::Resource};
+
+/// The error type of fallible systems, which return `Result<(), RecsError>`,
+/// or `Result<(), anyhow::Error>` with the `anyhow` feature
+pub type BoxedError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Handles the errors returned by fallible systems, see
+/// `Registry::set_system_error_handler`
+pub type SystemErrorHandler = fn(&mut Registry, SystemError);
+
+/// An error returned by a system
+#[derive(Debug)]
+pub struct SystemError {
+    /// The name of the failed system
+    pub system: Cow<'static, str>,
+    /// The error it returned
+    pub error: BoxedError,
+}
+
+impl fmt::Display for SystemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "System `{}` failed: {}", self.system, self.error)
+    }
+}
+
+impl std::error::Error for SystemError {}
+
+/// The errors stored by the `collect` handler, oldest first
+#[derive(Debug, Default)]
+pub struct SystemErrors(pub Vec<SystemError>);
+
+impl Resource for SystemErrors {}
+
+/// Drops the error
+pub fn ignore(_registry: &mut Registry, _error: SystemError) {}
+
+/// Prints the error to stderr. The default handler.
+pub fn log(_registry: &mut Registry, error: SystemError) {
+    eprintln!("{}", error);
+}
+
+/// Stores the error in the `SystemErrors` resource, inserted if missing
+pub fn collect(registry: &mut Registry, error: SystemError) {
+    if !registry.has_resource::<SystemErrors>() {
+        registry.insert_resource(SystemErrors::default());
+    }
+    if let Some(errors) = registry.get_resource_mut::<SystemErrors>() {
+        errors.0.push(error);
+    }
+}
+
+/// Panics with the error
+pub fn panic(_registry: &mut Registry, error: SystemError) {
+    panic!("{}", error);
+}
+
+/// Takes the result out of the output of a system, if the system is
+/// fallible. `output` holds an `Option` of the system's output type.
+pub(crate) fn take_result(output: &mut dyn Any) -> Option<Result<(), BoxedError>> {
+    if let Some(result) = output.downcast_mut::<Option<Result<(), RecsError>>>() {
+        return result.take().map(|result| result.map_err(BoxedError::from));
+    }
+    #[cfg(feature = "anyhow")]
+    if let Some(result) = output.downcast_mut::<Option<Result<(), anyhow::Error>>>() {
+        return result.take().map(|result| result.map_err(BoxedError::from));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entity::Entity, resource::ResMut, system::executor::ExecutorKind};
+
+    #[derive(Default)]
+    struct Attempts(u32);
+    impl Resource for Attempts {}
+
+    fn load(mut attempts: ResMut<Attempts>) -> Result<(), RecsError> {
+        attempts.0 += 1;
+        Err(RecsError::InvalidEntity(Entity::new(7, 0)))
+    }
+
+    #[test]
+    fn test_system_errors_are_routed_to_the_handler() {
+        for executor in [ExecutorKind::Sequential, ExecutorKind::Parallel] {
+            let mut registry = Registry::new();
+            registry.set_executor(executor);
+            registry.set_system_error_handler(collect);
+            registry.init_resource::<Attempts>();
+            registry.add_system(load);
+            registry.run_systems();
+            registry.run_systems();
+
+            let errors = &registry.get_resource::<SystemErrors>().unwrap().0;
+            assert_eq!(errors.len(), 2);
+            assert!(errors[0].system.contains("load"));
+            assert!(matches!(
+                errors[0].error.downcast_ref::<RecsError>(),
+                Some(RecsError::InvalidEntity(_))
+            ));
+            assert_eq!(registry.get_resource::<Attempts>().unwrap().0, 2);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "anyhow")]
+    fn test_anyhow_errors_are_routed_to_the_handler() {
+        fn connect() -> anyhow::Result<()> {
+            anyhow::bail!("connection refused")
+        }
+
+        let mut registry = Registry::new();
+        registry.set_system_error_handler(collect);
+        registry.add_system(connect);
+        registry.run_systems();
+
+        let errors = &registry.get_resource::<SystemErrors>().unwrap().0;
+        assert_eq!(errors[0].error.to_string(), "connection refused");
+    }
+
+    #[test]
+    #[should_panic(expected = "failed")]
+    fn test_panic_handler_panics() {
+        let mut registry = Registry::new();
+        registry.set_system_error_handler(panic);
+        registry.init_resource::<Attempts>();
+        registry.add_system(load);
+        registry.run_systems();
+    }
+}
@@ -0,0 +1,299 @@
+use std::ops::Range;
+
+use crate::{
+    registry::cell::UnsafeRegistryCell,
+    system::{BoxedSystem, access::Access},
+    tick::Tick,
+};
+
+/// How `Registry::run_systems` executes the registered systems
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutorKind {
+    /// Runs systems one after another in insertion order
+    #[default]
+    Sequential,
+    /// Runs consecutive systems with compatible accesses concurrently.
+    ///
+    /// Systems are grouped into batches in insertion order, so two conflicting
+    /// systems always run in the order they were added. Commands recorded by a
+    /// batch are applied once the whole batch has finished. Uses the rayon
+    /// thread pool when the `rayon` feature is enabled, and scoped threads
+    /// otherwise.
+    Parallel,
+}
+
+/// Splits systems into consecutive batches whose members have pairwise
+/// compatible accesses and don't depend on each other through ordering
+/// constraints. Exclusive systems always end up in a batch of their own.
+///
+/// `dependencies[i]` lists the positions of the systems system `i` must run
+/// after, all of which come before `i`.
+pub(crate) fn batches(accesses: &[Access], dependencies: &[&[usize]]) -> Vec<Range<usize>> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+
+    for (i, access) in accesses.iter().enumerate() {
+        let compatible = accesses[start..i]
+            .iter()
+            .all(|other| access.is_compatible(other))
+            && dependencies[i].iter().all(|&dependency| dependency < start);
+        if !compatible {
+            batches.push(start..i);
+            start = i;
+        }
+    }
+
+    if start < accesses.len() {
+        batches.push(start..accesses.len());
+    }
+    batches
+}
+
+/// Runs every system of a batch concurrently and waits for all of them. The
+/// system that must run on the calling thread, if any, runs there.
+///
+/// # Safety
+/// The systems must have pairwise compatible accesses.
+pub(crate) unsafe fn run_concurrently<'a>(
+    registry: UnsafeRegistryCell<'_>,
+    systems: impl IntoIterator<Item = &'a mut BoxedSystem>,
+    this_run: Tick,
+) {
+    let mut systems: Vec<_> = systems.into_iter().collect();
+    if let Some(main) = systems
+        .iter()
+        .position(|system| system.access().requires_main_thread())
+    {
+        systems.swap(0, main);
+    }
+    let Some((first, rest)) = systems.split_first_mut() else {
+        return;
+    };
+
+    #[cfg(feature = "rayon")]
+    rayon::in_place_scope(|scope| {
+        for system in rest {
+            scope.spawn(move |_| unsafe { system.run_unsafe((), registry, this_run) });
+        }
+        unsafe { first.run_unsafe((), registry, this_run) };
+    });
+
+    #[cfg(not(feature = "rayon"))]
+    std::thread::scope(|scope| {
+        for system in rest {
+            scope.spawn(move || unsafe { system.run_unsafe((), registry, this_run) });
+        }
+        unsafe { first.run_unsafe((), registry, this_run) };
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+
+    use super::*;
+    use crate::{
+        component::Component,
+        query::Query,
+        registry::Registry,
+        resource::{ResMut, Resource},
+        system::commands::Commands,
+    };
+
+    struct A;
+    struct B;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    impl Component for Position {}
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+    impl Component for Velocity {}
+
+    #[derive(Default)]
+    struct Spawned(usize);
+    impl Resource for Spawned {}
+
+    fn movement(query: Query<(&mut Position, &Velocity)>) {
+        for (mut pos, vel) in query {
+            pos.0 += vel.0;
+        }
+    }
+
+    fn accelerate(query: Query<(&mut Velocity,)>) {
+        for (mut vel,) in query {
+            vel.0 += 1;
+        }
+    }
+
+    fn spawner(mut commands: Commands, mut spawned: ResMut<Spawned>) {
+        commands.spawn((Position(0),));
+        spawned.0 += 1;
+    }
+
+    fn run_world(executor: ExecutorKind) -> Registry {
+        let mut registry = Registry::new();
+        registry.set_executor(executor);
+        registry.init_resource::<Spawned>();
+        registry.spawn((Position(0), Velocity(1)));
+        registry.add_system(movement);
+        registry.add_system(accelerate);
+        registry.add_system(movement);
+        registry.add_system(spawner);
+
+        for _ in 0..3 {
+            registry.run_systems();
+        }
+        registry
+    }
+
+    #[test]
+    fn test_parallel_executor_matches_sequential() {
+        let mut sequential = run_world(ExecutorKind::Sequential);
+        let mut parallel = run_world(ExecutorKind::Parallel);
+
+        let collect = |registry: &mut Registry| {
+            let mut positions: Vec<i32> =
+                registry.query::<(&Position,)>().map(|(p,)| p.0).collect();
+            positions.sort();
+            positions
+        };
+        assert_eq!(collect(&mut sequential), collect(&mut parallel));
+        assert_eq!(parallel.get_resource::<Spawned>().unwrap().0, 3);
+    }
+
+    // The rayon pool may have a single worker, which runs a batch sequentially
+    #[cfg(not(feature = "rayon"))]
+    #[test]
+    fn test_compatible_systems_run_concurrently() {
+        use std::{
+            sync::atomic::{AtomicUsize, Ordering},
+            time::{Duration, Instant},
+        };
+
+        use crate::resource::Res;
+
+        #[derive(Default)]
+        struct Rendezvous(AtomicUsize);
+        impl Resource for Rendezvous {}
+
+        #[derive(Default)]
+        struct MetA(bool);
+        impl Resource for MetA {}
+
+        #[derive(Default)]
+        struct MetB(bool);
+        impl Resource for MetB {}
+
+        /// Waits until both systems arrived, returning false on timeout
+        fn meet(rendezvous: &Rendezvous) -> bool {
+            rendezvous.0.fetch_add(1, Ordering::SeqCst);
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while rendezvous.0.load(Ordering::SeqCst) < 2 {
+                if Instant::now() > deadline {
+                    return false;
+                }
+                std::thread::yield_now();
+            }
+            true
+        }
+
+        fn meet_a(rendezvous: Res<Rendezvous>, mut met: ResMut<MetA>) {
+            met.0 = meet(&rendezvous);
+        }
+
+        fn meet_b(rendezvous: Res<Rendezvous>, mut met: ResMut<MetB>) {
+            met.0 = meet(&rendezvous);
+        }
+
+        let mut registry = Registry::new();
+        registry.set_executor(ExecutorKind::Parallel);
+        registry.init_resource::<Rendezvous>();
+        registry.init_resource::<MetA>();
+        registry.init_resource::<MetB>();
+        registry.add_system(meet_a);
+        registry.add_system(meet_b);
+
+        registry.run_systems();
+
+        assert!(registry.get_resource::<MetA>().unwrap().0);
+        assert!(registry.get_resource::<MetB>().unwrap().0);
+    }
+
+    #[test]
+    fn test_non_send_systems_run_on_the_calling_thread() {
+        use std::{marker::PhantomData, thread::ThreadId};
+
+        use crate::resource::non_send::{NonSend, NonSendMut};
+
+        struct Window {
+            thread: ThreadId,
+            frames: u32,
+            _not_send: PhantomData<*const ()>,
+        }
+
+        fn draw(mut window: NonSendMut<Window>) {
+            assert_eq!(std::thread::current().id(), window.thread);
+            window.frames += 1;
+        }
+
+        fn present(window: NonSend<Window>) {
+            assert_eq!(std::thread::current().id(), window.thread);
+        }
+
+        let mut registry = Registry::new();
+        registry.set_executor(ExecutorKind::Parallel);
+        registry.insert_non_send_resource(Window {
+            thread: std::thread::current().id(),
+            frames: 0,
+            _not_send: PhantomData,
+        });
+        registry.spawn((Position(0), Velocity(1)));
+        registry.add_system(accelerate);
+        registry.add_system(draw);
+        registry.add_system(movement);
+        registry.add_system(present);
+
+        registry.run_systems();
+        assert_eq!(
+            registry.get_non_send_resource::<Window>().unwrap().frames,
+            1
+        );
+        assert!(registry.validate().missing_resources.is_empty());
+    }
+
+    #[test]
+    fn test_batches_keep_conflicting_systems_in_order() {
+        let mut read_a = Access::new();
+        read_a.read_component(TypeId::of::<A>());
+        let mut write_a = Access::new();
+        write_a.write_component(TypeId::of::<A>());
+        let mut write_b = Access::new();
+        write_b.write_component(TypeId::of::<B>());
+
+        let accesses = [
+            read_a.clone(),
+            read_a.clone(),
+            write_b,
+            write_a,
+            read_a,
+            Access::exclusive(),
+            Access::new(),
+        ];
+
+        let no_dependencies = [&[][..]; 7];
+        assert_eq!(
+            batches(&accesses, &no_dependencies),
+            vec![0..3, 3..4, 4..5, 5..6, 6..7]
+        );
+        assert!(batches(&[], &[]).is_empty());
+
+        // Ordering constraints split otherwise compatible systems
+        let dependencies = [&[][..], &[0][..]];
+        assert_eq!(
+            batches(&[Access::new(), Access::new()], &dependencies),
+            vec![0..1, 1..2]
+        );
+    }
+}
@@ -1,94 +1,476 @@
+pub mod access;
+pub mod commands;
+pub mod condition;
+pub mod error;
+pub mod executor;
+pub mod output;
+pub mod param_set;
+pub mod pipe;
+pub mod quota;
+pub mod schedule;
+
+use std::{
+    any::{Any, TypeId},
+    borrow::Cow,
+};
+
 use crate::{
-    query::{Query, QueryParam},
-    registry::Registry,
-    resource::{OptionalRes, OptionalResMut, Res, ResMut, Resource},
+    component::{Component, removed::RemovedComponents},
+    event::{EventReader, EventWriter, Events, missing_events, trace::SystemScope},
+    query::{Query, QueryFilter, QueryParam, QueryState},
+    registry::{Registry, cell::UnsafeRegistryCell},
+    resource::{
+        Res, ResMut, Resource,
+        audit::{self, ResourceUse, UsageLog},
+        non_send::{NonSend, NonSendMut},
+    },
+    system::{
+        access::Access,
+        commands::Commands,
+        output::SystemOutput,
+        pipe::{In, Pipe},
+    },
+    tick::Tick,
 };
 
 /// A trait representing a system that can be executed in the ECS.
-pub trait System {
+///
+/// Systems run by the schedule take no input, and their output is published
+/// for `SystemOutput`. Systems taking an input, see `In`, only run as the
+/// second half of a `pipe`.
+pub trait System: Send {
+    /// The value the system is run with, `()` unless its function takes an
+    /// `In` parameter
+    type In;
+    /// The value returned by each run
+    type Out;
+
     /// Execute the system logic
-    fn run(&mut self, registry: &mut Registry);
+    fn run(&mut self, input: Self::In, registry: &mut Registry) -> Self::Out;
+
+    /// Executes the system logic through a registry cell shared with the
+    /// systems running at the same time, with change detection comparing
+    /// against the system's last run and writes stamped with `this_run`.
+    ///
+    /// The parallel executor uses this to run systems with compatible accesses
+    /// concurrently. The default implementation forwards to `run`, which is only
+    /// sound for systems running on their own, as exclusive systems do.
+    ///
+    /// # Safety
+    /// Every system running at the same time must have an access compatible
+    /// with `self.access()`.
+    unsafe fn run_unsafe(
+        &mut self,
+        input: Self::In,
+        registry: UnsafeRegistryCell<'_>,
+        this_run: Tick,
+    ) -> Self::Out {
+        let _ = this_run;
+        unsafe { self.run(input, registry.registry_mut()) }
+    }
+
+    /// Applies work the system deferred until it finished running, such as
+    /// publishing its output. Called with exclusive access to the registry.
+    fn apply_deferred(&mut self, registry: &mut Registry) {
+        let _ = registry;
+    }
+
+    /// Returns the name of this system, used in diagnostics and as its
+    /// default label
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed(std::any::type_name::<Self>())
+    }
+
+    /// Returns the registry data this system reads and writes.
+    ///
+    /// Defaults to exclusive access, so systems that can't describe their
+    /// access are never run concurrently with others.
+    fn access(&self) -> Access {
+        Access::exclusive()
+    }
+
+    /// Returns the change tick at which this system last ran
+    fn last_run(&self) -> Tick;
+
+    /// Records the change tick at which this system last ran
+    fn set_last_run(&mut self, tick: Tick);
+
+    /// Returns the resources this system declared and used over its runs, see
+    /// `Registry::resource_audit`. Only recorded in debug builds.
+    fn resource_usage(&self) -> UsageLog {
+        UsageLog::default()
+    }
 }
 
 /// A boxed system that can be stored in the Registry's system list
-pub type BoxedSystem = Box<dyn System>;
+pub type BoxedSystem = Box<dyn System<In = (), Out = ()>>;
 
 /// Trait for creating systems from functions
 pub trait IntoSystem<Params> {
     type System: System;
 
     fn into_system(self) -> Self::System;
+
+    /// Creates a system running this one, then `next` with its output as
+    /// input, see `Pipe`
+    fn pipe<Next, NextParams>(self, next: Next) -> Pipe<Self::System, Next::System>
+    where
+        Self: Sized,
+        Next: IntoSystem<NextParams>,
+        Next::System: System<In = <Self::System as System>::Out>,
+    {
+        Pipe::new(self.into_system(), next.into_system())
+    }
+}
+
+/// The change ticks of a single system run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SystemTicks {
+    /// The tick at which the system last ran, which change detection compares against
+    pub last_run: Tick,
+    /// The tick of the current run, which writes are stamped with
+    pub this_run: Tick,
 }
 
 /// Trait for system parameters that can be extracted from the Registry
 pub trait SystemParam {
     /// Extract this parameter from the registry
     ///
+    /// Implementations only fetch the data they declare in `access`, through
+    /// the accessors of the cell.
+    ///
+    /// # Safety
+    /// The registry must outlive the returned parameter, and nothing else may
+    /// access the data declared in `access` in a conflicting way meanwhile.
+    unsafe fn from_registry(registry: UnsafeRegistryCell<'_>, ticks: SystemTicks) -> Self;
+
+    /// Extracts this parameter like `from_registry`, with `state` kept by the
+    /// system across its runs, e.g. to cache lookups. Defaults to
+    /// `from_registry`.
+    ///
     /// # Safety
-    /// This function uses raw pointers to work around lifetime issues.
-    /// The caller must ensure that the registry remains valid for the
-    /// lifetime of the returned parameter.
-    unsafe fn from_registry(registry: *mut Registry) -> Self;
+    /// Same as `from_registry`.
+    unsafe fn from_registry_with_state(
+        registry: UnsafeRegistryCell<'_>,
+        ticks: SystemTicks,
+        state: &mut ParamState,
+    ) -> Self
+    where
+        Self: Sized,
+    {
+        let _ = state;
+        unsafe { Self::from_registry(registry, ticks) }
+    }
+
+    /// Records the registry data this parameter reads and writes.
+    ///
+    /// Defaults to exclusive access, so parameters that don't describe their
+    /// access keep their systems from running concurrently with others.
+    fn access(access: &mut Access) {
+        access.set_exclusive();
+    }
 }
 
-impl<'q, Q: QueryParam<'q>> SystemParam for Query<'q, Q> {
-    unsafe fn from_registry(registry: *mut Registry) -> Self {
-        unsafe { Query::new(&mut *registry) }
+/// The state a system keeps for one of its parameters, see
+/// `SystemParam::from_registry_with_state`
+#[derive(Default)]
+pub struct ParamState(Option<Box<dyn Any + Send>>);
+
+impl ParamState {
+    /// Returns the state, initializing it with `init` on first use
+    pub fn get_or_insert_with<T: Any + Send>(&mut self, init: impl FnOnce() -> T) -> &mut T {
+        if !self.0.as_ref().is_some_and(|state| state.is::<T>()) {
+            self.0 = Some(Box::new(init()));
+        }
+        self.0
+            .as_mut()
+            .and_then(|state| state.downcast_mut())
+            .expect("The state was just initialized")
+    }
+}
+
+impl<'q, Q: QueryParam<'q> + 'static, F: QueryFilter + 'static> SystemParam for Query<'q, Q, F> {
+    unsafe fn from_registry(registry: UnsafeRegistryCell<'_>, ticks: SystemTicks) -> Self {
+        let components = unsafe { registry.components() };
+        let storages = Q::storages(components);
+        let filter = F::state(components);
+        Query::with_ticks(
+            registry.as_ptr(),
+            storages,
+            filter,
+            ticks.last_run,
+            ticks.this_run,
+        )
+    }
+
+    /// Reuses the storages resolved by the previous runs, see `QueryState`
+    unsafe fn from_registry_with_state(
+        registry: UnsafeRegistryCell<'_>,
+        ticks: SystemTicks,
+        state: &mut ParamState,
+    ) -> Self {
+        let state = state.get_or_insert_with(QueryState::<Q, F>::new);
+        // SAFETY: storages are only created or replaced with exclusive access
+        // to the registry, never while systems run
+        let (storages, filter) = unsafe { state.storages(registry.as_ptr()) };
+        Query::with_ticks(
+            registry.as_ptr(),
+            storages,
+            filter,
+            ticks.last_run,
+            ticks.this_run,
+        )
+    }
+
+    fn access(access: &mut Access) {
+        Query::<Q, F>::access(access);
     }
 }
 
 impl<R: Resource> SystemParam for Res<'_, R> {
-    unsafe fn from_registry(registry: *mut Registry) -> Self {
+    unsafe fn from_registry(registry: UnsafeRegistryCell<'_>, ticks: SystemTicks) -> Self {
+        audit::declare::<R>(ResourceUse::Read);
         unsafe {
-            let resource = (*registry).resources.get::<R>().expect(&format!(
-                "Resource {} not found. Did you forget to insert it?",
-                std::any::type_name::<R>()
-            ));
-            Res::new(resource)
+            let (resource, resource_ticks) =
+                registry.get_resource_with_ticks::<R>().unwrap_or_else(|| {
+                    panic!(
+                        "Resource {} not found. Did you forget to insert it?",
+                        std::any::type_name::<R>()
+                    )
+                });
+            Res::with_ticks(resource, resource_ticks, ticks)
         }
     }
+
+    fn access(access: &mut Access) {
+        access.read_resource(TypeId::of::<R>());
+        access.require_resource::<R>();
+    }
 }
 
 impl<R: Resource> SystemParam for ResMut<'_, R> {
-    unsafe fn from_registry(registry: *mut Registry) -> Self {
+    unsafe fn from_registry(registry: UnsafeRegistryCell<'_>, ticks: SystemTicks) -> Self {
+        audit::declare::<R>(ResourceUse::Write);
         unsafe {
-            let resource = (*registry).resources.get_mut::<R>().expect(&format!(
-                "Resource {} not found. Did you forget to insert it?",
-                std::any::type_name::<R>()
-            ));
+            let resource = registry
+                .get_resource_mut_with_tick::<R>(ticks.this_run)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Resource {} not found. Did you forget to insert it?",
+                        std::any::type_name::<R>()
+                    )
+                });
             ResMut::new(resource)
         }
     }
+
+    fn access(access: &mut Access) {
+        access.write_resource(TypeId::of::<R>());
+        access.require_resource::<R>();
+    }
+}
+
+#[allow(deprecated)]
+impl<R: Resource> SystemParam for crate::resource::OptionalRes<'_, R> {
+    unsafe fn from_registry(registry: UnsafeRegistryCell<'_>, _ticks: SystemTicks) -> Self {
+        audit::declare::<R>(ResourceUse::Read);
+        unsafe {
+            let resource = registry
+                .get_resource_with_ticks::<R>()
+                .map(|(resource, _)| resource);
+            crate::resource::OptionalRes::new(resource)
+        }
+    }
+
+    fn access(access: &mut Access) {
+        access.read_resource(TypeId::of::<R>());
+    }
+}
+
+#[allow(deprecated)]
+impl<R: Resource> SystemParam for crate::resource::OptionalResMut<'_, R> {
+    unsafe fn from_registry(registry: UnsafeRegistryCell<'_>, ticks: SystemTicks) -> Self {
+        audit::declare::<R>(ResourceUse::Write);
+        unsafe {
+            let resource = registry.get_resource_mut_with_tick::<R>(ticks.this_run);
+            crate::resource::OptionalResMut::new(resource)
+        }
+    }
+
+    fn access(access: &mut Access) {
+        access.write_resource(TypeId::of::<R>());
+    }
+}
+
+/// Optional read-only access to a resource, yielding `None` if it doesn't exist
+impl<R: Resource> SystemParam for Option<Res<'_, R>> {
+    unsafe fn from_registry(registry: UnsafeRegistryCell<'_>, ticks: SystemTicks) -> Self {
+        audit::declare::<R>(ResourceUse::Read);
+        unsafe {
+            registry
+                .get_resource_with_ticks::<R>()
+                .map(|(resource, resource_ticks)| Res::with_ticks(resource, resource_ticks, ticks))
+        }
+    }
+
+    fn access(access: &mut Access) {
+        access.read_resource(TypeId::of::<R>());
+    }
+}
+
+/// Optional mutable access to a resource, yielding `None` if it doesn't exist
+impl<R: Resource> SystemParam for Option<ResMut<'_, R>> {
+    unsafe fn from_registry(registry: UnsafeRegistryCell<'_>, ticks: SystemTicks) -> Self {
+        audit::declare::<R>(ResourceUse::Write);
+        unsafe {
+            registry
+                .get_resource_mut_with_tick::<R>(ticks.this_run)
+                .map(ResMut::new)
+        }
+    }
+
+    fn access(access: &mut Access) {
+        access.write_resource(TypeId::of::<R>());
+    }
+}
+
+impl<R: 'static> SystemParam for NonSend<'_, R> {
+    unsafe fn from_registry(registry: UnsafeRegistryCell<'_>, _ticks: SystemTicks) -> Self {
+        unsafe {
+            let resource = registry.get_non_send::<R>().unwrap_or_else(|| {
+                panic!(
+                    "Non-send resource {} not found. Did you forget to insert it?",
+                    std::any::type_name::<R>()
+                )
+            });
+            NonSend::new(resource)
+        }
+    }
+
+    fn access(access: &mut Access) {
+        access.read_resource(TypeId::of::<R>());
+        access.require_resource::<R>();
+        access.require_main_thread();
+    }
+}
+
+impl<R: 'static> SystemParam for NonSendMut<'_, R> {
+    unsafe fn from_registry(registry: UnsafeRegistryCell<'_>, _ticks: SystemTicks) -> Self {
+        unsafe {
+            let resource = registry.get_non_send_mut::<R>().unwrap_or_else(|| {
+                panic!(
+                    "Non-send resource {} not found. Did you forget to insert it?",
+                    std::any::type_name::<R>()
+                )
+            });
+            NonSendMut::new(resource)
+        }
+    }
+
+    fn access(access: &mut Access) {
+        access.write_resource(TypeId::of::<R>());
+        access.require_resource::<R>();
+        access.require_main_thread();
+    }
+}
+
+impl SystemParam for Commands<'_> {
+    unsafe fn from_registry(registry: UnsafeRegistryCell<'_>, _ticks: SystemTicks) -> Self {
+        unsafe { registry.commands() }
+    }
+
+    fn access(access: &mut Access) {
+        access.write_commands();
+    }
+}
+
+impl<C: Component> SystemParam for RemovedComponents<'_, C> {
+    unsafe fn from_registry(registry: UnsafeRegistryCell<'_>, ticks: SystemTicks) -> Self {
+        unsafe {
+            RemovedComponents::new(
+                registry.removed_components().get::<C>(),
+                ticks.last_run,
+                ticks.this_run,
+            )
+        }
+    }
+
+    fn access(_access: &mut Access) {
+        // Removals are only recorded while commands are applied, between systems
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParam for EventWriter<'_, T> {
+    unsafe fn from_registry(registry: UnsafeRegistryCell<'_>, ticks: SystemTicks) -> Self {
+        unsafe {
+            let events = registry
+                .get_resource_mut::<Events<T>>()
+                .unwrap_or_else(|| missing_events::<T>());
+            EventWriter::new(events, ticks.this_run)
+        }
+    }
+
+    fn access(access: &mut Access) {
+        access.write_resource(TypeId::of::<Events<T>>());
+        access.require_resource::<Events<T>>();
+    }
 }
 
-impl<R: Resource> SystemParam for OptionalRes<'_, R> {
-    unsafe fn from_registry(registry: *mut Registry) -> Self {
+impl<T: Send + Sync + 'static> SystemParam for EventReader<'_, T> {
+    unsafe fn from_registry(registry: UnsafeRegistryCell<'_>, ticks: SystemTicks) -> Self {
         unsafe {
-            let resource = (*registry).resources.get::<R>();
-            OptionalRes::new(resource)
+            let events = registry
+                .get_resource_with_ticks::<Events<T>>()
+                .map(|(events, _)| events)
+                .unwrap_or_else(|| missing_events::<T>());
+            EventReader::new(events, ticks.last_run, ticks.this_run)
         }
     }
+
+    fn access(access: &mut Access) {
+        access.read_resource(TypeId::of::<Events<T>>());
+        access.require_resource::<Events<T>>();
+    }
 }
 
-impl<R: Resource> SystemParam for OptionalResMut<'_, R> {
-    unsafe fn from_registry(registry: *mut Registry) -> Self {
+impl<T: Send + Sync + 'static> SystemParam for SystemOutput<'_, T> {
+    unsafe fn from_registry(registry: UnsafeRegistryCell<'_>, _ticks: SystemTicks) -> Self {
         unsafe {
-            let resource = (*registry).resources.get_mut::<R>();
-            OptionalResMut::new(resource)
+            let latest = registry.get_resource_with_ticks::<output::LatestOutput<T>>();
+            SystemOutput::new(latest.map(|(latest, _)| &latest.0))
         }
     }
+
+    fn access(access: &mut Access) {
+        output::access::<T>(access);
+    }
 }
 
-/// A system that wraps a function taking system parameters
-pub struct FunctionSystem<F, Params> {
+/// A system that wraps a function taking system parameters.
+///
+/// `Marker` is the signature of the function, e.g. `fn(Res<Time>) -> Path`.
+pub struct FunctionSystem<F, Marker> {
     func: F,
-    _phantom: std::marker::PhantomData<Params>,
+    /// The change tick at which this system last ran
+    last_run: Tick,
+    /// Resources declared and used over every run, in debug builds
+    resource_usage: UsageLog,
+    /// The state of each parameter, kept across runs
+    param_states: Vec<ParamState>,
+    /// The union of the parameters' accesses, computed on first use and
+    /// checked for conflicts between parameters when created by `into_system`
+    access: Option<Access>,
+    _phantom: std::marker::PhantomData<fn() -> Marker>,
 }
 
-impl<F, Params> FunctionSystem<F, Params> {
+impl<F, Marker> FunctionSystem<F, Marker> {
     pub fn new(func: F) -> Self {
         Self {
             func,
+            last_run: Tick::default(),
+            resource_usage: UsageLog::default(),
+            param_states: Vec::new(),
+            access: None,
             _phantom: std::marker::PhantomData,
         }
     }
@@ -96,31 +478,117 @@ impl<F, Params> FunctionSystem<F, Params> {
 
 macro_rules! impl_system {
     ($($param:ident),*) => {
+        impl_system!(@impl []; $($param),*);
+        impl_system!(@impl [Input]; $($param),*);
+    };
+    (@input) => { () };
+    (@input $input:ident) => { $input };
+    (@impl [$($input:ident)?]; $($param:ident),*) => {
         #[allow(non_snake_case)]
-        impl<F, $($param: SystemParam),*> System for FunctionSystem<F, ($($param,)*)>
+        impl<F, Out, $($input: 'static,)? $($param: SystemParam),*> System
+            for FunctionSystem<F, fn($(In<$input>,)? $($param),*) -> Out>
         where
-            F: FnMut($($param),*) + 'static,
+            F: FnMut($(In<$input>,)? $($param),*) -> Out + Send + 'static,
+            Out: 'static,
         {
-            fn run(&mut self, registry: &mut Registry) {
-                #[allow(unused_unsafe)]
-                unsafe {
-                    #[allow(unused_variables)]
-                    let registry_ptr = registry as *mut Registry;
-                    $(let $param = $param::from_registry(registry_ptr);)*
-                    (self.func)($($param),*);
+            type In = impl_system!(@input $($input)?);
+            type Out = Out;
+
+            fn run(&mut self, input: Self::In, registry: &mut Registry) -> Out {
+                let this_run = registry.change_tick();
+                // SAFETY: the system has exclusive access to the registry
+                unsafe { self.run_unsafe(input, UnsafeRegistryCell::new(registry), this_run) }
+            }
+
+            #[allow(unused_variables, unused_mut)]
+            unsafe fn run_unsafe(
+                &mut self,
+                input: Self::In,
+                registry: UnsafeRegistryCell<'_>,
+                this_run: Tick,
+            ) -> Out {
+                let access = self.access.get_or_insert_with(|| {
+                    let mut access = Access::new();
+                    $($param::access(&mut access);)*
+                    access
+                });
+                let registry = registry.with_access(access);
+                let ticks = SystemTicks {
+                    last_run: self.last_run,
+                    this_run,
+                };
+
+                audit::begin();
+                let _scope = SystemScope::enter(std::any::type_name::<F>());
+                let params = <[&str]>::len(&[$(stringify!($param)),*]);
+                if self.param_states.len() < params {
+                    self.param_states.resize_with(params, ParamState::default);
                 }
+                #[allow(unused_mut)]
+                let mut states = self.param_states.iter_mut();
+                #[allow(unused_unsafe)]
+                let output = unsafe {
+                    $(
+                        let state = states.next().expect("There is a state for every parameter");
+                        let $param = $param::from_registry_with_state(registry, ticks, state);
+                    )*
+                    (self.func)($(In::<$input>(input),)? $($param),*)
+                };
+                self.resource_usage.merge(audit::end());
+                output
+            }
+
+            fn name(&self) -> Cow<'static, str> {
+                Cow::Borrowed(std::any::type_name::<F>())
+            }
+
+            #[allow(unused_mut)]
+            fn access(&self) -> Access {
+                self.access.clone().unwrap_or_else(|| {
+                    let mut access = Access::new();
+                    $($param::access(&mut access);)*
+                    access
+                })
+            }
+
+            fn last_run(&self) -> Tick {
+                self.last_run
+            }
+
+            fn set_last_run(&mut self, tick: Tick) {
+                self.last_run = tick;
+            }
+
+            fn resource_usage(&self) -> UsageLog {
+                self.resource_usage.clone()
             }
         }
 
         #[allow(non_snake_case)]
-        impl<F, $($param: SystemParam),*> IntoSystem<($($param,)*)> for F
+        impl<F, Out, $($input: 'static,)? $($param: SystemParam),*>
+            IntoSystem<fn($(In<$input>,)? $($param),*) -> Out> for F
         where
-            F: FnMut($($param),*) + 'static,
+            F: FnMut($(In<$input>,)? $($param),*) -> Out + Send + 'static,
+            Out: 'static,
         {
-            type System = FunctionSystem<F, ($($param,)*)>;
+            type System = FunctionSystem<F, fn($(In<$input>,)? $($param),*) -> Out>;
 
+            #[allow(unused_mut)]
             fn into_system(self) -> Self::System {
-                FunctionSystem::new(self)
+                let params = [$((std::any::type_name::<$param>(), {
+                    let mut access = Access::new();
+                    $param::access(&mut access);
+                    access
+                })),*];
+                assert_params_compatible(std::any::type_name::<F>(), &params);
+                let mut access = Access::new();
+                for (_, param_access) in &params {
+                    access.extend(param_access);
+                }
+                FunctionSystem {
+                    access: Some(access),
+                    ..FunctionSystem::new(self)
+                }
             }
         }
     };
@@ -148,11 +616,36 @@ impl_system!(
     P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13, P14, P15
 );
 
+/// Panics if two parameters of the system `system`, or two parts of a single
+/// one, access the same component or resource with at least one of them
+/// writing it, since both would then hand out references to the same data.
+///
+/// `params` pairs the type name of each parameter with its access.
+fn assert_params_compatible(system: &str, params: &[(&'static str, Access)]) {
+    for (index, (name, access)) in params.iter().enumerate() {
+        if access.conflicts_with_itself() {
+            panic!(
+                "System {system} has conflicting parameters within {name}: \
+                 it writes data it also accesses elsewhere. \
+                 Name each component once instead."
+            );
+        }
+        for (other_name, other_access) in &params[index + 1..] {
+            if access.conflicts_over_data(other_access) {
+                panic!(
+                    "System {system} has conflicting parameters {name} and {other_name}: \
+                     one of them writes data the other one accesses. \
+                     Fetch the data through a single parameter instead."
+                );
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::component::Component;
-
     use super::*;
+    use crate::system::schedule::IntoSystemConfig;
 
     #[derive(Debug, PartialEq)]
     struct Position {
@@ -183,7 +676,7 @@ mod tests {
     impl Resource for Counter {}
 
     fn movement_system(query: Query<(&mut Position, &Velocity)>) {
-        for (pos, vel) in query {
+        for (mut pos, vel) in query {
             pos.x += vel.dx;
         }
     }
@@ -194,7 +687,11 @@ mod tests {
         }
     }
 
-    fn optional_resource_system(time: OptionalRes<Time>, mut counter: ResMut<Counter>) {
+    #[allow(deprecated)]
+    fn optional_resource_system(
+        time: crate::resource::OptionalRes<Time>,
+        mut counter: ResMut<Counter>,
+    ) {
         if time.is_some() {
             counter.value = 10;
         } else {
@@ -202,6 +699,62 @@ mod tests {
         }
     }
 
+    fn count_changed_system(
+        query: Query<(&Position,), crate::query::Changed<Position>>,
+        mut counter: ResMut<Counter>,
+    ) {
+        counter.value = query.into_iter().count() as i32;
+    }
+
+    fn count_added_system(
+        query: Query<(&Velocity,), crate::query::Added<Velocity>>,
+        mut counter: ResMut<Counter>,
+    ) {
+        counter.value = query.into_iter().count() as i32;
+    }
+
+    fn std_option_resource_system(time: Option<Res<Time>>, counter: Option<ResMut<Counter>>) {
+        if let Some(mut counter) = counter {
+            counter.value = match time {
+                Some(time) if time.delta > 0.0 => 1,
+                Some(_) => 0,
+                None => -1,
+            };
+        }
+    }
+
+    fn count_removed_system(removed: RemovedComponents<Velocity>, mut counter: ResMut<Counter>) {
+        counter.value = removed.into_iter().count() as i32;
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Target(i32);
+
+    fn produce_target(counter: Res<Counter>) -> Target {
+        Target(counter.value + 1)
+    }
+
+    fn consume_target(target: SystemOutput<Target>, mut counter: ResMut<Counter>) {
+        if let Some(target) = target.get() {
+            counter.value = target.0;
+        }
+    }
+
+    #[test]
+    fn test_system_output_is_visible_to_later_systems() {
+        let mut registry = Registry::new();
+        registry.init_resource::<Counter>();
+        registry.add_system(consume_target.after(produce_target));
+        registry.add_system(produce_target);
+
+        registry.run_systems();
+        assert_eq!(registry.system_output::<Target>(), Some(&Target(1)));
+        assert_eq!(registry.get_resource::<Counter>().unwrap().value, 1);
+
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Counter>().unwrap().value, 2);
+    }
+
     #[test]
     fn test_system_with_query() {
         let mut registry = Registry::new();
@@ -214,6 +767,19 @@ mod tests {
         assert_eq!(pos.x, 15.0);
     }
 
+    #[test]
+    fn test_system_query_sees_storages_created_between_runs() {
+        let mut registry = Registry::new();
+        registry.add_system(movement_system);
+        registry.run_systems();
+
+        // The query state of the system was resolved before the storages existed
+        let entity = registry.spawn((Position { x: 1.0 }, Velocity { dx: 2.0 }));
+        registry.run_systems();
+        registry.run_systems();
+        assert_eq!(registry.get_component::<Position>(entity).unwrap().x, 5.0);
+    }
+
     #[test]
     fn test_system_with_resources() {
         let mut registry = Registry::new();
@@ -261,4 +827,136 @@ mod tests {
         registry.add_system(time_reader_system);
         registry.run_systems();
     }
+
+    #[test]
+    fn test_changed_filter_only_sees_changes_since_last_run() {
+        let mut registry = Registry::new();
+        registry.init_resource::<Counter>();
+        let e1 = registry.spawn(Position { x: 0.0 });
+        registry.spawn(Position { x: 1.0 });
+
+        registry.add_system(count_changed_system);
+
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Counter>().unwrap().value, 2);
+
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Counter>().unwrap().value, 0);
+
+        registry.get_component_mut::<Position>(e1).unwrap().x = 5.0;
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Counter>().unwrap().value, 1);
+    }
+
+    #[test]
+    fn test_changed_filter_sees_writes_from_other_systems() {
+        let mut registry = Registry::new();
+        registry.init_resource::<Counter>();
+        registry.spawn((Position { x: 0.0 }, Velocity { dx: 1.0 }));
+        registry.spawn(Position { x: 1.0 });
+
+        registry.add_system(movement_system);
+        registry.add_system(count_changed_system);
+
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Counter>().unwrap().value, 2);
+
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Counter>().unwrap().value, 1);
+    }
+
+    #[test]
+    fn test_added_filter_sees_factory_initialized_components() {
+        let mut registry = Registry::new();
+        registry.init_resource::<Counter>();
+        registry.register_component_factory(|_, _| Velocity { dx: 1.0 });
+        let entity = registry.spawn(Position { x: 0.0 });
+
+        registry.add_system(count_added_system);
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Counter>().unwrap().value, 0);
+
+        registry.init_component::<Velocity>(entity).unwrap();
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Counter>().unwrap().value, 1);
+
+        registry.get_component_mut::<Velocity>(entity).unwrap().dx = 2.0;
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Counter>().unwrap().value, 0);
+    }
+
+    #[test]
+    fn test_system_with_std_option_resources() {
+        let mut registry = Registry::new();
+        registry.add_system(std_option_resource_system);
+
+        registry.run_systems();
+        assert!(!registry.has_resource::<Counter>());
+
+        registry.init_resource::<Counter>();
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Counter>().unwrap().value, -1);
+
+        registry.insert_resource(Time { delta: 0.1 });
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Counter>().unwrap().value, 1);
+    }
+
+    #[test]
+    fn test_removed_components_seen_once_per_system() {
+        let mut registry = Registry::new();
+        registry.init_resource::<Counter>();
+        let e1 = registry.spawn((Position { x: 0.0 }, Velocity { dx: 1.0 }));
+        let e2 = registry.spawn((Position { x: 0.0 }, Velocity { dx: 1.0 }));
+
+        registry.add_system(count_removed_system);
+
+        registry.remove_component::<Velocity>(e1).unwrap();
+        registry.destroy_entity(e2).unwrap();
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Counter>().unwrap().value, 2);
+
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Counter>().unwrap().value, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "conflicting parameters")]
+    fn test_conflicting_queries_are_rejected() {
+        fn aliasing(_writer: Query<(&mut Position,)>, _reader: Query<(&Position,)>) {}
+
+        let mut registry = Registry::new();
+        registry.add_system(aliasing);
+    }
+
+    #[test]
+    #[should_panic(expected = "conflicting parameters")]
+    fn test_query_naming_a_component_twice_is_rejected() {
+        fn aliasing(_positions: Query<(&mut Position, &mut Position)>) {}
+
+        let mut registry = Registry::new();
+        registry.add_system(aliasing);
+    }
+
+    #[test]
+    fn test_compatible_params_are_accepted() {
+        fn disjoint(
+            _positions: Query<(&mut Position,)>,
+            _velocities: Query<(&Velocity,)>,
+            _counter: Res<Counter>,
+            _reader: Res<Counter>,
+        ) {
+        }
+
+        let mut registry = Registry::new();
+        registry.add_system(disjoint);
+
+        let result = std::panic::catch_unwind(|| {
+            let mut registry = Registry::new();
+            registry.add_system(|_: ResMut<Counter>, _: Res<Counter>| {});
+        });
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("ResMut<"));
+        assert!(message.contains("Counter"));
+    }
 }
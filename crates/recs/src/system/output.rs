@@ -0,0 +1,163 @@
+use std::{
+    any::{Any, TypeId},
+    borrow::Cow,
+};
+
+use crate::{
+    registry::{Registry, cell::UnsafeRegistryCell},
+    resource::{Resource, audit::UsageLog},
+    system::{
+        System,
+        access::Access,
+        commands::Command,
+        error::{self, SystemError},
+    },
+    tick::Tick,
+};
+
+/// The latest output of a system returning `T`, stored as a resource
+pub(crate) struct LatestOutput<T>(pub(crate) T);
+
+impl<T: Send + Sync + 'static> Resource for LatestOutput<T> {}
+
+/// Returns the command storing the `output` of `system` in the registry, or
+/// handing it to the system error handler for fallible systems. Returns None
+/// for systems that don't return anything or succeeded.
+pub(crate) fn publish<T: Send + Sync + 'static>(
+    system: Cow<'static, str>,
+    output: T,
+) -> Option<Command> {
+    if TypeId::of::<T>() == TypeId::of::<()>() {
+        return None;
+    }
+    let mut output = Some(output);
+    if let Some(result) = error::take_result(&mut output as &mut dyn Any) {
+        let error = result.err()?;
+        return Some(Box::new(move |registry: &mut Registry| {
+            registry.handle_system_error(SystemError { system, error })
+        }));
+    }
+    let output = output.expect("Only results are taken out of outputs");
+    Some(Box::new(move |registry: &mut Registry| {
+        registry.insert_resource(LatestOutput(output))
+    }))
+}
+
+/// A system added to a schedule, whose outputs are published once it
+/// finishes, see `SystemOutput`
+pub(crate) struct PublishOutput<S> {
+    system: S,
+    /// Publishes the output of the last run
+    pending_output: Option<Command>,
+}
+
+impl<S> PublishOutput<S> {
+    pub(crate) fn new(system: S) -> Self {
+        Self {
+            system,
+            pending_output: None,
+        }
+    }
+}
+
+impl<S> System for PublishOutput<S>
+where
+    S: System<In = ()>,
+    S::Out: Send + Sync + 'static,
+{
+    type In = ();
+    type Out = ();
+
+    fn run(&mut self, _input: (), registry: &mut Registry) {
+        let output = self.system.run((), registry);
+        self.pending_output = publish(self.system.name(), output);
+    }
+
+    unsafe fn run_unsafe(&mut self, _input: (), registry: UnsafeRegistryCell<'_>, this_run: Tick) {
+        // SAFETY: forwarded from the caller
+        let output = unsafe { self.system.run_unsafe((), registry, this_run) };
+        self.pending_output = publish(self.system.name(), output);
+    }
+
+    fn apply_deferred(&mut self, registry: &mut Registry) {
+        self.system.apply_deferred(registry);
+        if let Some(publish) = self.pending_output.take() {
+            publish(registry);
+        }
+    }
+
+    fn name(&self) -> Cow<'static, str> {
+        self.system.name()
+    }
+
+    fn access(&self) -> Access {
+        self.system.access()
+    }
+
+    fn last_run(&self) -> Tick {
+        self.system.last_run()
+    }
+
+    fn set_last_run(&mut self, tick: Tick) {
+        self.system.set_last_run(tick);
+    }
+
+    fn resource_usage(&self) -> UsageLog {
+        self.system.resource_usage()
+    }
+}
+
+/// Records a read of the stored output of type `T`
+pub(crate) fn access<T: Send + Sync + 'static>(access: &mut Access) {
+    access.read_resource(TypeId::of::<LatestOutput<T>>());
+}
+
+/// A system parameter giving access to the latest value returned by a system
+/// whose return type is `T`.
+///
+/// Outputs are published once the producing system finishes, like its
+/// commands. If several systems return the same type, the one that ran last
+/// wins, so producer/consumer pipelines usually wrap results in a dedicated type.
+/// The results of fallible systems aren't published but handed to the system
+/// error handler, see `Registry::set_system_error_handler`.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// struct Path(Vec<(i32, i32)>);
+///
+/// fn pathfinding() -> Path {
+///     Path(vec![(0, 0), (1, 0), (1, 1)])
+/// }
+///
+/// fn movement(path: SystemOutput<Path>) {
+///     if let Some(path) = path.get() {
+///         println!("following {} waypoints", path.0.len());
+///     }
+/// }
+///
+/// let mut registry = Registry::new();
+/// registry.add_system(pathfinding);
+/// registry.add_system(movement.after(pathfinding));
+/// registry.run_systems();
+/// # assert_eq!(registry.system_output::<Path>().unwrap().0.len(), 3);
+/// ```
+pub struct SystemOutput<'a, T> {
+    value: Option<&'a T>,
+}
+
+impl<'a, T> SystemOutput<'a, T> {
+    pub fn new(value: Option<&'a T>) -> Self {
+        Self { value }
+    }
+
+    /// Returns the latest output, or None if no system produced one yet
+    pub fn get(&self) -> Option<&'a T> {
+        self.value
+    }
+
+    /// Returns true if a system produced an output of this type
+    pub fn is_some(&self) -> bool {
+        self.value.is_some()
+    }
+}
@@ -0,0 +1,298 @@
+use crate::{
+    component::{Component, removed::RemovedComponents},
+    event::{EventReader, EventWriter},
+    query::{Query, QueryFilter, QueryStorage},
+    registry::cell::UnsafeRegistryCell,
+    resource::{
+        Res, ResMut, Resource,
+        non_send::{NonSend, NonSendMut},
+    },
+    system::{Commands, SystemParam, SystemTicks, access::Access, output::SystemOutput},
+};
+
+/// A system parameter grouping parameters whose accesses conflict, such as a
+/// query writing `Transform` and another reading it, of which only one can be
+/// used at a time.
+///
+/// Members are fetched when requested with `p0`, `p1`, ... and borrow the set
+/// mutably, so the borrow checker keeps them from being used together. The
+/// set declares the accesses of all its members: it still conflicts with the
+/// other parameters of the system touching the same data.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// #[derive(Component)]
+/// struct Transform(f32);
+///
+/// #[derive(Component)]
+/// struct Player;
+///
+/// #[derive(Component)]
+/// struct Camera;
+///
+/// fn follow_player(
+///     mut set: ParamSet<(Query<(&mut Transform, &Camera)>, Query<(&Transform, &Player)>)>,
+/// ) {
+///     let target = set.p1().iter().map(|(transform, _)| transform.0).next();
+///     if let Some(target) = target {
+///         for (mut transform, _) in set.p0().iter_mut() {
+///             transform.0 = target;
+///         }
+///     }
+/// }
+///
+/// let mut registry = Registry::new();
+/// registry.spawn((Transform(4.0), Player));
+/// let camera = registry.spawn((Transform(0.0), Camera));
+/// registry.add_system(follow_player);
+/// registry.run_systems();
+/// assert_eq!(registry.get_component::<Transform>(camera).unwrap().0, 4.0);
+/// ```
+///
+/// Members borrow the set for as long as anything read through them is used,
+/// so a resource read through one can't outlive the fetch of another:
+/// ```compile_fail,E0499
+/// # #![allow(deprecated)]
+/// # use recs::prelude::*;
+/// # use recs::resource::{OptionalRes, OptionalResMut};
+/// #[derive(Resource)]
+/// struct Health(u32);
+///
+/// fn alias(mut set: ParamSet<(OptionalRes<Health>, OptionalResMut<Health>)>) {
+///     let health = set.p0();
+///     let shared = health.as_ref().unwrap();
+///     set.p1().as_mut().unwrap().0 += 1;
+///     println!("{}", shared.0);
+/// }
+/// ```
+pub struct ParamSet<'w, T: ParamSetMembers> {
+    registry: UnsafeRegistryCell<'w>,
+    ticks: SystemTicks,
+    /// The function fetching each member for the borrow of the set
+    fetch: T::Fetch,
+}
+
+/// The tuples of parameters a `ParamSet` can hold
+pub trait ParamSetMembers {
+    /// A tuple of the functions fetching each member
+    type Fetch: Copy;
+}
+
+/// A system parameter that can be a member of a `ParamSet`
+pub trait ParamSetMember {
+    /// This parameter borrowing the registry for `'w` instead, as which the
+    /// set hands it out so that it can't outlive the borrow of the set
+    type Item<'w>;
+}
+
+/// Fetches the member `P` for the borrow `'s` of its set
+///
+/// # Safety
+/// Same as `SystemParam::from_registry`
+unsafe fn fetch<'s, P>(registry: UnsafeRegistryCell<'s>, ticks: SystemTicks) -> P::Item<'s>
+where
+    P: ParamSetMember,
+    for<'x> P::Item<'x>: SystemParam,
+{
+    unsafe { <P::Item<'s> as SystemParam>::from_registry(registry, ticks) }
+}
+
+impl<Q: QueryStorage, F: QueryFilter> ParamSetMember for Query<'_, Q, F> {
+    type Item<'w> = Query<'w, Q, F>;
+}
+
+impl<R: Resource> ParamSetMember for Res<'_, R> {
+    type Item<'w> = Res<'w, R>;
+}
+
+impl<R: Resource> ParamSetMember for ResMut<'_, R> {
+    type Item<'w> = ResMut<'w, R>;
+}
+
+#[allow(deprecated)]
+impl<R: Resource> ParamSetMember for crate::resource::OptionalRes<'_, R> {
+    type Item<'w> = crate::resource::OptionalRes<'w, R>;
+}
+
+#[allow(deprecated)]
+impl<R: Resource> ParamSetMember for crate::resource::OptionalResMut<'_, R> {
+    type Item<'w> = crate::resource::OptionalResMut<'w, R>;
+}
+
+impl<R: Resource> ParamSetMember for Option<Res<'_, R>> {
+    type Item<'w> = Option<Res<'w, R>>;
+}
+
+impl<R: Resource> ParamSetMember for Option<ResMut<'_, R>> {
+    type Item<'w> = Option<ResMut<'w, R>>;
+}
+
+impl<R: 'static> ParamSetMember for NonSend<'_, R> {
+    type Item<'w> = NonSend<'w, R>;
+}
+
+impl<R: 'static> ParamSetMember for NonSendMut<'_, R> {
+    type Item<'w> = NonSendMut<'w, R>;
+}
+
+impl ParamSetMember for Commands<'_> {
+    type Item<'w> = Commands<'w>;
+}
+
+impl<C: Component> ParamSetMember for RemovedComponents<'_, C> {
+    type Item<'w> = RemovedComponents<'w, C>;
+}
+
+impl<T: Send + Sync + 'static> ParamSetMember for EventWriter<'_, T> {
+    type Item<'w> = EventWriter<'w, T>;
+}
+
+impl<T: Send + Sync + 'static> ParamSetMember for EventReader<'_, T> {
+    type Item<'w> = EventReader<'w, T>;
+}
+
+impl<T: Send + Sync + 'static> ParamSetMember for SystemOutput<'_, T> {
+    type Item<'w> = SystemOutput<'w, T>;
+}
+
+macro_rules! impl_param_set {
+    ($(($param:ident, $getter:ident, $index:tt)),*) => {
+        impl<$($param: ParamSetMember),*> ParamSetMembers for ($($param,)*) {
+            type Fetch = ($(for<'s> unsafe fn(UnsafeRegistryCell<'s>, SystemTicks) -> $param::Item<'s>,)*);
+        }
+
+        impl<$($param: ParamSetMember),*> ParamSet<'_, ($($param,)*)> {
+            $(
+                #[doc = concat!("Fetches the `", stringify!($param), "` member of the set")]
+                pub fn $getter(&mut self) -> $param::Item<'_> {
+                    // SAFETY: the set declared the access of every member, and
+                    // the member borrows the set mutably, so no other member
+                    // is alive
+                    unsafe { (self.fetch.$index)(self.registry, self.ticks) }
+                }
+            )*
+        }
+
+        impl<$($param: ParamSetMember),*> ParamSetMember for ParamSet<'_, ($($param,)*)> {
+            type Item<'w> = ParamSet<'w, ($($param,)*)>;
+        }
+
+        impl<$($param),*> SystemParam for ParamSet<'_, ($($param,)*)>
+        where
+            $($param: SystemParam + ParamSetMember, for<'x> $param::Item<'x>: SystemParam,)*
+        {
+            unsafe fn from_registry(registry: UnsafeRegistryCell<'_>, ticks: SystemTicks) -> Self {
+                ParamSet {
+                    // SAFETY: the set is dropped when the system returns, while
+                    // the registry and the system's access are still alive
+                    registry: unsafe { registry.with_lifetime() },
+                    ticks,
+                    fetch: ($(fetch::<$param>,)*),
+                }
+            }
+
+            fn access(access: &mut Access) {
+                $($param::access(access);)*
+            }
+        }
+    };
+}
+
+impl_param_set!((P0, p0, 0), (P1, p1, 1));
+impl_param_set!((P0, p0, 0), (P1, p1, 1), (P2, p2, 2));
+impl_param_set!((P0, p0, 0), (P1, p1, 1), (P2, p2, 2), (P3, p3, 3));
+impl_param_set!(
+    (P0, p0, 0),
+    (P1, p1, 1),
+    (P2, p2, 2),
+    (P3, p3, 3),
+    (P4, p4, 4)
+);
+impl_param_set!(
+    (P0, p0, 0),
+    (P1, p1, 1),
+    (P2, p2, 2),
+    (P3, p3, 3),
+    (P4, p4, 4),
+    (P5, p5, 5)
+);
+impl_param_set!(
+    (P0, p0, 0),
+    (P1, p1, 1),
+    (P2, p2, 2),
+    (P3, p3, 3),
+    (P4, p4, 4),
+    (P5, p5, 5),
+    (P6, p6, 6)
+);
+impl_param_set!(
+    (P0, p0, 0),
+    (P1, p1, 1),
+    (P2, p2, 2),
+    (P3, p3, 3),
+    (P4, p4, 4),
+    (P5, p5, 5),
+    (P6, p6, 6),
+    (P7, p7, 7)
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        component::Component,
+        query::Query,
+        registry::Registry,
+        resource::{Res, ResMut, Resource},
+    };
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+    impl Component for Health {}
+
+    struct Heal(u32);
+    impl Resource for Heal {}
+
+    type HealSet<'w> = ParamSet<
+        'w,
+        (
+            Query<'w, (&'w mut Health,)>,
+            Query<'w, (&'w Health,)>,
+            ResMut<'w, Heal>,
+            Res<'w, Heal>,
+        ),
+    >;
+
+    fn heal(mut set: HealSet) {
+        let total: u32 = set.p1().iter().map(|(health,)| health.0).sum();
+        set.p2().0 = total;
+        let amount = set.p3().0;
+        for (mut health,) in set.p0().iter_mut() {
+            health.0 += amount;
+        }
+    }
+
+    #[test]
+    fn test_param_set_members_conflict_with_each_other() {
+        let mut registry = Registry::new();
+        let first = registry.spawn((Health(1),));
+        let second = registry.spawn((Health(2),));
+        registry.insert_resource(Heal(0));
+        registry.add_system(heal);
+        registry.run_systems();
+
+        assert_eq!(registry.get_resource::<Heal>().unwrap().0, 3);
+        assert_eq!(registry.get_component::<Health>(first), Some(&Health(4)));
+        assert_eq!(registry.get_component::<Health>(second), Some(&Health(5)));
+    }
+
+    #[test]
+    #[should_panic(expected = "conflicting parameters")]
+    fn test_param_set_conflicts_with_other_params() {
+        fn aliasing(_set: ParamSet<(Query<(&mut Health,)>, Res<Heal>)>, _heal: ResMut<Heal>) {}
+
+        let mut registry = Registry::new();
+        registry.add_system(aliasing);
+    }
+}
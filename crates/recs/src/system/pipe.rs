@@ -0,0 +1,190 @@
+use std::borrow::Cow;
+
+use crate::{
+    registry::{Registry, cell::UnsafeRegistryCell},
+    resource::audit::UsageLog,
+    system::{IntoSystem, System, access::Access},
+    tick::Tick,
+};
+
+/// The input of a system, given by the system piped into it.
+///
+/// Must be the first parameter of the system's function. Systems taking an
+/// input can't be added to a schedule on their own, only through `pipe`.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// #[derive(Component)]
+/// struct Health(u32);
+///
+/// #[derive(Component)]
+/// struct Burning;
+///
+/// fn collect_damage(burning: Query<(&Burning,)>) -> u32 {
+///     burning.iter().count() as u32 * 5
+/// }
+///
+/// fn apply_damage(In(damage): In<u32>, mut health: Query<(&mut Health,)>) {
+///     for (mut health,) in health.iter_mut() {
+///         health.0 = health.0.saturating_sub(damage);
+///     }
+/// }
+///
+/// let mut registry = Registry::new();
+/// let entity = registry.spawn((Health(20), Burning));
+/// registry.add_system(collect_damage.pipe(apply_damage));
+/// registry.run_systems();
+/// assert_eq!(registry.get_component::<Health>(entity).unwrap().0, 15);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct In<T>(pub T);
+
+/// A system running `first`, then `second` with the output of `first` as
+/// input, created by `IntoSystem::pipe`.
+///
+/// The two systems share a single slot in the schedule: the pipe accesses the
+/// data of both, but they never run at the same time, so their parameters
+/// may overlap.
+pub struct Pipe<A, B> {
+    first: A,
+    second: B,
+    name: Cow<'static, str>,
+}
+
+impl<A: System, B: System<In = A::Out>> Pipe<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        let name = Cow::Owned(format!("Pipe({}, {})", first.name(), second.name()));
+        Self {
+            first,
+            second,
+            name,
+        }
+    }
+}
+
+impl<A: System, B: System<In = A::Out>> System for Pipe<A, B> {
+    type In = A::In;
+    type Out = B::Out;
+
+    fn run(&mut self, input: A::In, registry: &mut Registry) -> B::Out {
+        let output = self.first.run(input, registry);
+        self.second.run(output, registry)
+    }
+
+    unsafe fn run_unsafe(
+        &mut self,
+        input: A::In,
+        registry: UnsafeRegistryCell<'_>,
+        this_run: Tick,
+    ) -> B::Out {
+        // SAFETY: the access of the pipe covers the accesses of both systems,
+        // which run one after the other
+        unsafe {
+            let output = self.first.run_unsafe(input, registry, this_run);
+            self.second.run_unsafe(output, registry, this_run)
+        }
+    }
+
+    fn apply_deferred(&mut self, registry: &mut Registry) {
+        self.first.apply_deferred(registry);
+        self.second.apply_deferred(registry);
+    }
+
+    fn name(&self) -> Cow<'static, str> {
+        self.name.clone()
+    }
+
+    fn access(&self) -> Access {
+        let mut access = self.first.access();
+        access.extend(&self.second.access());
+        access
+    }
+
+    fn last_run(&self) -> Tick {
+        self.first.last_run()
+    }
+
+    fn set_last_run(&mut self, tick: Tick) {
+        self.first.set_last_run(tick);
+        self.second.set_last_run(tick);
+    }
+
+    fn resource_usage(&self) -> UsageLog {
+        let mut usage = self.first.resource_usage();
+        usage.merge(self.second.resource_usage());
+        usage
+    }
+}
+
+impl<A: System, B: System<In = A::Out>> IntoSystem<()> for Pipe<A, B> {
+    type System = Self;
+
+    fn into_system(self) -> Self::System {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        resource::{Res, ResMut, Resource},
+        system::{executor::ExecutorKind, output::SystemOutput, schedule::IntoSystemConfig},
+    };
+
+    #[derive(Default)]
+    struct Total(u32);
+    impl Resource for Total {}
+
+    struct Step(u32);
+    impl Resource for Step {}
+
+    fn read_step(step: Res<Step>) -> u32 {
+        step.0
+    }
+
+    fn double(In(value): In<u32>) -> u32 {
+        value * 2
+    }
+
+    fn add_to_total(In(value): In<u32>, mut total: ResMut<Total>) -> u32 {
+        total.0 += value;
+        total.0
+    }
+
+    #[test]
+    fn test_piped_systems_pass_outputs_along() {
+        for executor in [ExecutorKind::Sequential, ExecutorKind::Parallel] {
+            let mut registry = Registry::new();
+            registry.set_executor(executor);
+            registry.insert_resource(Step(3));
+            registry.init_resource::<Total>();
+            registry.add_system(read_step.pipe(double).pipe(add_to_total));
+            registry.run_systems();
+            registry.run_systems();
+
+            assert_eq!(registry.get_resource::<Total>().unwrap().0, 12);
+            assert_eq!(registry.system_output::<u32>(), Some(&12));
+        }
+    }
+
+    #[test]
+    fn test_pipe_output_is_visible_to_later_systems() {
+        fn report(total: SystemOutput<u32>, mut seen: ResMut<Total>) {
+            seen.0 += total.get().copied().unwrap_or_default();
+        }
+
+        let mut registry = Registry::new();
+        registry.insert_resource(Step(5));
+        registry.init_resource::<Total>();
+        let piped = read_step.pipe(double);
+        let access = piped.access();
+        registry.add_system(piped.label("doubled"));
+        registry.add_system(report.after("doubled"));
+        registry.run_systems();
+
+        assert!(access.reads_resource(std::any::TypeId::of::<Step>()));
+        assert_eq!(registry.get_resource::<Total>().unwrap().0, 10);
+    }
+}
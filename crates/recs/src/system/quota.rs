@@ -0,0 +1,354 @@
+use std::{any::TypeId, collections::BTreeMap, collections::HashMap};
+
+use crate::{
+    registry::Registry,
+    system::{
+        BoxedSystem,
+        schedule::{IntoSystemLabel, SystemLabel},
+    },
+};
+
+/// What happens when a system emits more than its `SystemQuota` allows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuotaAction {
+    /// Prints a warning to stderr naming the system
+    #[default]
+    Warn,
+    /// Drops the commands and events beyond the quota. The entities reserved
+    /// by dropped spawn commands are destroyed.
+    Cap,
+}
+
+/// Limits on how many commands and events a system may emit per run.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use recs::system::quota::SystemQuota;
+/// #[derive(Component)]
+/// struct Particle;
+///
+/// fn burst(mut commands: Commands) {
+///     for _ in 0..1000 {
+///         commands.spawn((Particle,));
+///     }
+/// }
+///
+/// let mut registry = Registry::new();
+/// registry.add_system(burst);
+/// registry.set_system_quota(burst, SystemQuota::new().max_commands(100).cap());
+/// registry.run_systems();
+///
+/// assert_eq!(registry.query::<(&Particle,)>().count(), 100);
+/// let emissions = registry.system_emissions().get(std::any::type_name_of_val(&burst));
+/// assert_eq!(emissions.unwrap().dropped_commands, 900);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SystemQuota {
+    /// Maximum number of commands recorded per run
+    pub max_commands: Option<usize>,
+    /// Maximum number of events sent per run, across all event types
+    pub max_events: Option<usize>,
+    /// What happens when a limit is exceeded
+    pub action: QuotaAction,
+}
+
+impl SystemQuota {
+    /// Creates a quota without any limit, warning once limits are set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the number of commands recorded per run
+    pub fn max_commands(mut self, max: usize) -> Self {
+        self.max_commands = Some(max);
+        self
+    }
+
+    /// Limits the number of events sent per run
+    pub fn max_events(mut self, max: usize) -> Self {
+        self.max_events = Some(max);
+        self
+    }
+
+    /// Drops whatever exceeds the limits instead of only warning
+    pub fn cap(mut self) -> Self {
+        self.action = QuotaAction::Cap;
+        self
+    }
+}
+
+/// What a system emitted during a frame, summed over all of its runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SystemEmissions {
+    /// Number of commands recorded
+    pub commands: usize,
+    /// Number of events sent
+    pub events: usize,
+    /// Number of commands dropped by a capping quota
+    pub dropped_commands: usize,
+    /// Number of events dropped by a capping quota
+    pub dropped_events: usize,
+}
+
+/// The command queue length and current events per event type before a
+/// system or batch ran
+pub(crate) struct EmissionSnapshot {
+    commands: usize,
+    events: HashMap<TypeId, usize>,
+}
+
+/// Per-system emission counters and quotas
+#[derive(Default)]
+pub(crate) struct EmissionTracker {
+    quotas: HashMap<SystemLabel, SystemQuota>,
+    default_quota: Option<SystemQuota>,
+    /// Emissions of the frame in progress, by system name
+    current: BTreeMap<String, SystemEmissions>,
+    /// Emissions of the last completed frame, by system name
+    last_frame: BTreeMap<String, SystemEmissions>,
+}
+
+impl EmissionTracker {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publishes the emissions of the frame that just ended
+    pub(crate) fn end_frame(&mut self) {
+        self.last_frame = std::mem::take(&mut self.current);
+    }
+
+    /// Returns true if a quota is configured, without which nothing is tracked
+    fn is_active(&self) -> bool {
+        !self.quotas.is_empty() || self.default_quota.is_some()
+    }
+}
+
+impl Registry {
+    /// Sets the emission quota of the systems labelled `label`, e.g. a system
+    /// function, overriding the default quota
+    pub fn set_system_quota<M>(&mut self, label: impl IntoSystemLabel<M>, quota: SystemQuota) {
+        self.emissions.quotas.insert(label.into_label(), quota);
+    }
+
+    /// Sets the emission quota of every system without its own quota
+    pub fn set_default_system_quota(&mut self, quota: Option<SystemQuota>) {
+        self.emissions.default_quota = quota;
+    }
+
+    /// Returns how many commands and events each system emitted during the
+    /// last completed frame, by system name. Systems that emitted nothing are
+    /// left out.
+    ///
+    /// Emissions are only tracked while a quota is configured, so that
+    /// registries without quotas don't pay for it. A default quota without
+    /// limits, `SystemQuota::new()`, tracks every system without warning.
+    ///
+    /// Sorting by `commands` points to the system responsible for a frame
+    /// spike caused by mass spawning.
+    pub fn system_emissions(&self) -> &BTreeMap<String, SystemEmissions> {
+        &self.emissions.last_frame
+    }
+
+    /// Records what was emitted so far, or returns None if no quota is
+    /// configured
+    pub(crate) fn emission_snapshot(&self) -> Option<EmissionSnapshot> {
+        if !self.emissions.is_active() {
+            return None;
+        }
+        Some(EmissionSnapshot {
+            commands: self.command_queue.len(),
+            events: self.event_types.sent_this_frame(&self.resources).collect(),
+        })
+    }
+
+    /// Attributes what was emitted since `before` to `systems`, which ran
+    /// since then, and enforces their quotas.
+    ///
+    /// Systems of a concurrent batch have compatible accesses, so at most one
+    /// of them records commands and at most one writes each event type.
+    pub(crate) fn record_emissions<'s>(
+        &mut self,
+        systems: impl ExactSizeIterator<Item = &'s BoxedSystem>,
+        before: &Option<EmissionSnapshot>,
+    ) {
+        let Some(before) = before else {
+            return;
+        };
+        let alone = systems.len() == 1;
+        let sent: Vec<(TypeId, usize)> =
+            self.event_types.sent_this_frame(&self.resources).collect();
+
+        for system in systems {
+            let access = system.access();
+            let name = system.name();
+            let quota = self
+                .emissions
+                .quotas
+                .iter()
+                .find(|(label, _)| label.as_str() == name)
+                .map(|(_, quota)| *quota)
+                .or(self.emissions.default_quota);
+
+            let mut emitted = SystemEmissions::default();
+            if alone || access.uses_commands() {
+                emitted.commands = self.command_queue.len().saturating_sub(before.commands);
+                if let Some(max) = quota.and_then(|quota| quota.max_commands)
+                    && emitted.commands > max
+                    && quota.is_some_and(|quota| quota.action == QuotaAction::Cap)
+                {
+                    for entity in self.command_queue.truncate(before.commands + max) {
+                        // Reserved entities have no components yet
+                        let _ = self.entity_manager.destroy_entity(entity);
+                    }
+                    emitted.dropped_commands = emitted.commands - max;
+                }
+            }
+
+            let mut event_budget = quota.and_then(|quota| quota.max_events);
+            for &(resource, len) in &sent {
+                if !(alone || access.writes_resource(resource)) {
+                    continue;
+                }
+                let previous = before.events.get(&resource).copied().unwrap_or(0);
+                let count = len.saturating_sub(previous);
+                emitted.events += count;
+
+                if let Some(budget) = event_budget.as_mut() {
+                    let kept = count.min(*budget);
+                    *budget -= kept;
+                    if kept < count && quota.is_some_and(|quota| quota.action == QuotaAction::Cap) {
+                        self.event_types.truncate_sent(
+                            &mut self.resources,
+                            resource,
+                            previous + kept,
+                        );
+                        emitted.dropped_events += count - kept;
+                    }
+                }
+            }
+
+            if emitted == SystemEmissions::default() {
+                continue;
+            }
+
+            if let Some(quota) = quota
+                && quota.action == QuotaAction::Warn
+            {
+                if quota.max_commands.is_some_and(|max| emitted.commands > max) {
+                    eprintln!(
+                        "System {} recorded {} commands, over its quota of {}",
+                        name,
+                        emitted.commands,
+                        quota.max_commands.unwrap()
+                    );
+                }
+                if quota.max_events.is_some_and(|max| emitted.events > max) {
+                    eprintln!(
+                        "System {} sent {} events, over its quota of {}",
+                        name,
+                        emitted.events,
+                        quota.max_events.unwrap()
+                    );
+                }
+            }
+
+            let total = self.emissions.current.entry(name.into_owned()).or_default();
+            total.commands += emitted.commands;
+            total.events += emitted.events;
+            total.dropped_commands += emitted.dropped_commands;
+            total.dropped_events += emitted.dropped_events;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        component::Component,
+        event::EventWriter,
+        system::{commands::Commands, executor::ExecutorKind},
+    };
+
+    struct Spark;
+    impl Component for Spark {}
+
+    struct Boom;
+
+    fn sparks(mut commands: Commands) {
+        for _ in 0..10 {
+            commands.spawn((Spark,));
+        }
+    }
+
+    fn booms(mut booms: EventWriter<Boom>) {
+        for _ in 0..10 {
+            booms.send(Boom);
+        }
+    }
+
+    fn quiet() {}
+
+    fn emissions_of<M>(registry: &Registry, system: impl IntoSystemLabel<M>) -> SystemEmissions {
+        registry
+            .system_emissions()
+            .get(system.into_label().as_str())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_emissions_are_tracked_per_system() {
+        for executor in [ExecutorKind::Sequential, ExecutorKind::Parallel] {
+            let mut registry = Registry::new();
+            registry.set_executor(executor);
+            registry.set_default_system_quota(Some(SystemQuota::new()));
+            registry.add_event::<Boom>();
+            registry.add_system(sparks);
+            registry.add_system(booms);
+            registry.add_system(quiet);
+            registry.run_systems();
+
+            assert_eq!(emissions_of(&registry, sparks).commands, 10);
+            assert_eq!(emissions_of(&registry, sparks).events, 0);
+            assert_eq!(emissions_of(&registry, booms).events, 10);
+            assert_eq!(emissions_of(&registry, booms).commands, 0);
+            assert_eq!(registry.system_emissions().len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_capping_quota_drops_the_excess() {
+        let mut registry = Registry::new();
+        registry.add_event::<Boom>();
+        registry.add_system(sparks);
+        registry.add_system(booms);
+        registry.set_default_system_quota(Some(SystemQuota::new().max_events(4).cap()));
+        registry.set_system_quota(sparks, SystemQuota::new().max_commands(3).cap());
+        registry.run_systems();
+
+        assert_eq!(registry.query::<(&Spark,)>().count(), 3);
+        assert_eq!(
+            registry
+                .get_resource::<crate::event::Events<Boom>>()
+                .unwrap()
+                .len(),
+            4
+        );
+        assert_eq!(emissions_of(&registry, sparks).dropped_commands, 7);
+        assert_eq!(emissions_of(&registry, booms).dropped_events, 6);
+        // The entities reserved by the dropped spawns were released
+        assert_eq!(registry.entities().count(), 3);
+    }
+
+    #[test]
+    fn test_nothing_is_tracked_without_quotas() {
+        let mut registry = Registry::new();
+        registry.add_system(sparks);
+        registry.run_systems();
+        assert!(registry.system_emissions().is_empty());
+        assert_eq!(registry.query::<(&Spark,)>().count(), 10);
+    }
+}
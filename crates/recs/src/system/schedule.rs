@@ -0,0 +1,560 @@
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fmt,
+    ops::Range,
+};
+
+use crate::{
+    error::RecsError,
+    registry::Registry,
+    system::{
+        BoxedSystem, IntoSystem, System, SystemTicks,
+        access::Access,
+        condition::{BoxedCondition, Condition},
+        executor,
+        output::PublishOutput,
+    },
+    tick::Tick,
+};
+
+/// A name identifying one or more systems in ordering constraints.
+///
+/// Every function system is labelled with its function's path, so it can be
+/// referred to by passing the function itself. Additional labels can be shared
+/// by several systems to order them as a group.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SystemLabel(Cow<'static, str>);
+
+impl SystemLabel {
+    /// Creates a new label with the given name
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        Self(name.into())
+    }
+
+    /// Returns the name of this label
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for SystemLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Conversion into a `SystemLabel`.
+///
+/// Implemented for labels, strings, and system functions, which convert into
+/// the label every system built from them carries. The `Marker` parameter only
+/// exists to keep these implementations apart.
+pub trait IntoSystemLabel<Marker> {
+    fn into_label(self) -> SystemLabel;
+}
+
+impl IntoSystemLabel<()> for SystemLabel {
+    fn into_label(self) -> SystemLabel {
+        self
+    }
+}
+
+impl IntoSystemLabel<()> for &'static str {
+    fn into_label(self) -> SystemLabel {
+        SystemLabel::new(self)
+    }
+}
+
+impl IntoSystemLabel<()> for String {
+    fn into_label(self) -> SystemLabel {
+        SystemLabel::new(self)
+    }
+}
+
+/// Marker for the `IntoSystemLabel` implementation of system functions
+pub struct SystemFunctionLabel;
+
+impl<F: IntoSystem<Params>, Params> IntoSystemLabel<(SystemFunctionLabel, Params)> for F {
+    fn into_label(self) -> SystemLabel {
+        SystemLabel::new(std::any::type_name::<F>())
+    }
+}
+
+/// A system together with its labels, ordering constraints and run conditions
+pub struct SystemConfig {
+    system: BoxedSystem,
+    labels: Vec<SystemLabel>,
+    before: Vec<SystemLabel>,
+    after: Vec<SystemLabel>,
+    conditions: Vec<BoxedCondition>,
+}
+
+/// Conversion into a `SystemConfig`, with builder methods for labelling and
+/// ordering systems.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// fn input() {}
+/// fn movement() {}
+/// fn collisions() {}
+///
+/// let mut registry = Registry::new();
+/// registry.add_system(collisions.label("physics"));
+/// registry.add_system(movement.after(input).before("physics"));
+/// registry.add_system(input);
+///
+/// // Runs input, movement, then collisions
+/// registry.run_systems();
+/// ```
+pub trait IntoSystemConfig<Params>: Sized {
+    /// Converts into a system configuration
+    fn into_config(self) -> SystemConfig;
+
+    /// Adds a label other systems can refer to in ordering constraints
+    fn label<M>(self, label: impl IntoSystemLabel<M>) -> SystemConfig {
+        let mut config = self.into_config();
+        config.labels.push(label.into_label());
+        config
+    }
+
+    /// Runs this system before every system carrying `label`
+    fn before<M>(self, label: impl IntoSystemLabel<M>) -> SystemConfig {
+        let mut config = self.into_config();
+        config.before.push(label.into_label());
+        config
+    }
+
+    /// Runs this system after every system carrying `label`
+    fn after<M>(self, label: impl IntoSystemLabel<M>) -> SystemConfig {
+        let mut config = self.into_config();
+        config.after.push(label.into_label());
+        config
+    }
+
+    /// Only runs this system when `condition` holds, e.g.
+    /// `condition::resource_changed::<Config>()`. Conditions are evaluated
+    /// right before the system would run, and all of them must hold.
+    ///
+    /// A skipped system keeps the tick of its last actual run, so change
+    /// detection covers every change since then once it runs again.
+    fn run_if(self, condition: impl Condition) -> SystemConfig {
+        let mut config = self.into_config();
+        config.conditions.push(Box::new(condition));
+        config
+    }
+}
+
+impl IntoSystemConfig<()> for SystemConfig {
+    fn into_config(self) -> SystemConfig {
+        self
+    }
+}
+
+impl<S, Params> IntoSystemConfig<Params> for S
+where
+    S: IntoSystem<Params>,
+    S::System: System<In = ()> + 'static,
+    <S::System as System>::Out: Send + Sync + 'static,
+{
+    fn into_config(self) -> SystemConfig {
+        let system = self.into_system();
+        SystemConfig {
+            labels: vec![SystemLabel::new(system.name())],
+            system: Box::new(PublishOutput::new(system)),
+            before: Vec::new(),
+            after: Vec::new(),
+            conditions: Vec::new(),
+        }
+    }
+}
+
+/// Identifies one of the Registry's schedules.
+///
+/// `Registry::run_systems()` runs `Startup` once, then `PreUpdate`,
+/// `FixedUpdate` (zero or more times, see `FixedTime`), `Update` and
+/// `PostUpdate` every frame. Custom stages only run when requested through
+/// `Registry::run_schedule()`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// One-time setup, run before the first frame
+    Startup,
+    /// Runs at the start of every frame, e.g. to gather input
+    PreUpdate,
+    /// Logic running at a fixed rate independent of the frame rate, such as
+    /// physics. Runs zero or more times per frame, see `FixedTime`.
+    FixedUpdate,
+    /// Per-frame game logic. `Registry::add_system` adds systems here.
+    Update,
+    /// Runs at the end of every frame, e.g. for cleanup
+    PostUpdate,
+    /// A schedule run explicitly by the user
+    Custom(Cow<'static, str>),
+}
+
+impl Stage {
+    /// The stages run exactly once every frame, in order. `FixedUpdate` runs
+    /// between `PreUpdate` and `Update`.
+    pub const FRAME: [Stage; 3] = [Stage::PreUpdate, Stage::Update, Stage::PostUpdate];
+
+    /// Creates a custom stage with the given name
+    pub fn custom(name: impl Into<Cow<'static, str>>) -> Self {
+        Stage::Custom(name.into())
+    }
+}
+
+/// A registered system and the positions of the systems it must run after
+struct SystemNode {
+    config: SystemConfig,
+    dependencies: Vec<usize>,
+}
+
+/// An ordered collection of systems.
+///
+/// Systems run in insertion order unless ordering constraints say otherwise.
+/// The order is resolved lazily whenever systems were added since the last run.
+#[derive(Default)]
+pub struct Schedule {
+    nodes: Vec<SystemNode>,
+    /// Set when systems were added since the order was last resolved
+    dirty: bool,
+}
+
+impl Schedule {
+    /// Creates a new empty Schedule
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            dirty: false,
+        }
+    }
+
+    /// Adds a system, optionally configured with labels and ordering constraints
+    pub fn add_system<Params>(&mut self, system: impl IntoSystemConfig<Params>) {
+        self.nodes.push(SystemNode {
+            config: system.into_config(),
+            dependencies: Vec::new(),
+        });
+        self.dirty = true;
+    }
+
+    /// Returns the number of systems
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if the schedule contains no systems
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Moves every system of `other` to the end of this schedule
+    pub fn append(&mut self, mut other: Schedule) {
+        self.dirty |= !other.nodes.is_empty();
+        self.nodes.append(&mut other.nodes);
+    }
+
+    /// Removes every system
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.dirty = false;
+    }
+
+    /// Returns the names of the systems in execution order.
+    ///
+    /// Fails with `SystemOrderCycle` if the ordering constraints contradict
+    /// each other.
+    pub fn system_names(&mut self) -> Result<Vec<String>, RecsError> {
+        self.build()?;
+        Ok(self
+            .nodes
+            .iter()
+            .map(|node| node.config.system.name().into_owned())
+            .collect())
+    }
+
+    /// Resolves the execution order from the ordering constraints.
+    ///
+    /// Systems are sorted topologically, keeping insertion order between
+    /// unconstrained systems. Constraints naming labels no system carries are
+    /// ignored.
+    pub fn build(&mut self) -> Result<(), RecsError> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let mut by_label: HashMap<&SystemLabel, Vec<usize>> = HashMap::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            for label in &node.config.labels {
+                by_label.entry(label).or_default().push(i);
+            }
+        }
+
+        // successors[i] holds the systems that must run after system i
+        let mut successors = vec![Vec::new(); self.nodes.len()];
+        for (i, node) in self.nodes.iter().enumerate() {
+            for label in &node.config.before {
+                for &j in by_label.get(label).into_iter().flatten() {
+                    if i != j {
+                        successors[i].push(j);
+                    }
+                }
+            }
+            for label in &node.config.after {
+                for &j in by_label.get(label).into_iter().flatten() {
+                    if i != j {
+                        successors[j].push(i);
+                    }
+                }
+            }
+        }
+
+        let mut in_degree = vec![0; self.nodes.len()];
+        for &j in successors.iter().flatten() {
+            in_degree[j] += 1;
+        }
+
+        // Always pick the earliest inserted ready system to keep insertion order
+        let mut ready: BinaryHeap<Reverse<usize>> = (0..self.nodes.len())
+            .filter(|&i| in_degree[i] == 0)
+            .map(Reverse)
+            .collect();
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(Reverse(i)) = ready.pop() {
+            order.push(i);
+            for &j in &successors[i] {
+                in_degree[j] -= 1;
+                if in_degree[j] == 0 {
+                    ready.push(Reverse(j));
+                }
+            }
+        }
+
+        if order.len() < self.nodes.len() {
+            let cycle = (0..self.nodes.len())
+                .filter(|&i| in_degree[i] > 0)
+                .map(|i| self.nodes[i].config.system.name().into_owned())
+                .collect();
+            return Err(RecsError::SystemOrderCycle(cycle));
+        }
+
+        let mut position = vec![0; self.nodes.len()];
+        for (pos, &i) in order.iter().enumerate() {
+            position[i] = pos;
+        }
+        let mut dependencies = vec![Vec::new(); self.nodes.len()];
+        for (i, succ) in successors.iter().enumerate() {
+            for &j in succ {
+                dependencies[position[j]].push(position[i]);
+            }
+        }
+
+        let mut nodes: Vec<Option<SystemNode>> = self.nodes.drain(..).map(Some).collect();
+        self.nodes = order
+            .iter()
+            .zip(dependencies)
+            .map(|(&i, dependencies)| {
+                let mut node = nodes[i].take().expect("each system is ordered once");
+                node.dependencies = dependencies;
+                node
+            })
+            .collect();
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Returns the systems in execution order, as of the last `build`
+    pub(crate) fn systems(&self) -> impl Iterator<Item = &BoxedSystem> {
+        self.nodes.iter().map(|node| &node.config.system)
+    }
+
+    /// Returns the pairs of systems that conflict over a component or resource
+    /// (see `Access::conflicts_over_data`) without any ordering constraint,
+    /// direct or transitive, between them. Their relative order then only
+    /// follows insertion order, which is easy to break by accident.
+    ///
+    /// Fails with `SystemOrderCycle` if the order can't be resolved.
+    pub fn ambiguities(&mut self) -> Result<Vec<(String, String)>, RecsError> {
+        self.build()?;
+
+        // runs_after[i][k] is set if system k must run before system i; every
+        // dependency comes earlier in execution order
+        let mut runs_after = vec![vec![false; self.nodes.len()]; self.nodes.len()];
+        for (i, node) in self.nodes.iter().enumerate() {
+            let (earlier, rest) = runs_after.split_at_mut(i);
+            let reach = &mut rest[0];
+            for &dependency in &node.dependencies {
+                reach[dependency] = true;
+                for (reached, &before) in reach.iter_mut().zip(&earlier[dependency]) {
+                    *reached |= before;
+                }
+            }
+        }
+
+        let accesses: Vec<Access> = self.systems().map(|system| system.access()).collect();
+        let mut ambiguities = Vec::new();
+        for j in 0..self.nodes.len() {
+            for i in 0..j {
+                if !runs_after[j][i] && accesses[i].conflicts_over_data(&accesses[j]) {
+                    ambiguities.push((
+                        self.nodes[i].config.system.name().into_owned(),
+                        self.nodes[j].config.system.name().into_owned(),
+                    ));
+                }
+            }
+        }
+        Ok(ambiguities)
+    }
+
+    /// Evaluates the run conditions of the system at `index` in execution
+    /// order, for a run at the current change tick of `registry`
+    pub(crate) fn should_run(&mut self, index: usize, registry: &Registry) -> bool {
+        let config = &mut self.nodes[index].config;
+        let ticks = SystemTicks {
+            last_run: config.system.last_run(),
+            this_run: registry.change_tick(),
+        };
+        config
+            .conditions
+            .iter_mut()
+            .all(|condition| condition.evaluate(registry, ticks))
+    }
+
+    /// Returns the system at `index` in execution order
+    pub(crate) fn system_mut(&mut self, index: usize) -> &mut BoxedSystem {
+        &mut self.nodes[index].config.system
+    }
+
+    /// Clamps the last run tick of every system, see `Tick::check_tick`
+    pub(crate) fn check_change_ticks(&mut self, this_run: Tick) {
+        for node in &mut self.nodes {
+            let system = &mut node.config.system;
+            let mut last_run = system.last_run();
+            if last_run.check_tick(this_run) {
+                system.set_last_run(last_run);
+            }
+        }
+    }
+
+    /// Returns the systems in `range` of the execution order
+    pub(crate) fn systems_mut(
+        &mut self,
+        range: Range<usize>,
+    ) -> impl Iterator<Item = &mut BoxedSystem> {
+        self.nodes[range]
+            .iter_mut()
+            .map(|node| &mut node.config.system)
+    }
+
+    /// Returns the systems in `range` of the execution order
+    pub(crate) fn systems_range(
+        &self,
+        range: Range<usize>,
+    ) -> impl ExactSizeIterator<Item = &BoxedSystem> {
+        self.nodes[range].iter().map(|node| &node.config.system)
+    }
+
+    /// Splits the execution order into batches of systems that can run
+    /// concurrently, see `executor::batches`
+    pub(crate) fn batches(&self) -> Vec<Range<usize>> {
+        let accesses: Vec<Access> = self
+            .nodes
+            .iter()
+            .map(|node| node.config.system.access())
+            .collect();
+        let dependencies: Vec<&[usize]> = self
+            .nodes
+            .iter()
+            .map(|node| node.dependencies.as_slice())
+            .collect();
+        executor::batches(&accesses, &dependencies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input() {}
+    fn movement() {}
+    fn collisions() {}
+    fn render() {}
+
+    fn short_names(schedule: &mut Schedule) -> Vec<String> {
+        schedule
+            .system_names()
+            .unwrap()
+            .into_iter()
+            .map(|name| name.rsplit("::").next().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_unconstrained_systems_keep_insertion_order() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(render);
+        schedule.add_system(input);
+        schedule.add_system(movement);
+
+        assert_eq!(short_names(&mut schedule), ["render", "input", "movement"]);
+    }
+
+    #[test]
+    fn test_before_and_after_constraints() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(render.after("physics"));
+        schedule.add_system(collisions.label("physics"));
+        schedule.add_system(movement.after(input).before(collisions));
+        schedule.add_system(input);
+
+        assert_eq!(
+            short_names(&mut schedule),
+            ["input", "movement", "collisions", "render"]
+        );
+    }
+
+    #[test]
+    fn test_cycle_is_reported() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(input.after(movement));
+        schedule.add_system(movement.after(input));
+        schedule.add_system(render);
+
+        match schedule.build() {
+            Err(RecsError::SystemOrderCycle(systems)) => {
+                assert_eq!(systems.len(), 2);
+                assert!(!systems.iter().any(|name| name.ends_with("render")));
+            }
+            _ => panic!("expected a cycle"),
+        }
+    }
+
+    #[test]
+    fn test_unordered_conflicting_systems_are_ambiguous() {
+        use crate::resource::{Res, ResMut, Resource};
+
+        struct Score;
+        impl Resource for Score {}
+
+        fn read_score(_score: Res<Score>) {}
+        fn write_score(_score: ResMut<Score>) {}
+        fn reset_score(_score: ResMut<Score>) {}
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(read_score);
+        schedule.add_system(write_score.after(read_score));
+        schedule.add_system(reset_score.after(write_score));
+        schedule.add_system(input);
+        assert!(schedule.ambiguities().unwrap().is_empty());
+
+        // Ordered after write_score only, so unordered with read_score
+        let mut schedule = Schedule::new();
+        schedule.add_system(read_score);
+        schedule.add_system(write_score);
+        schedule.add_system(reset_score.after(write_score));
+        let ambiguities = schedule.ambiguities().unwrap();
+        assert_eq!(ambiguities.len(), 2);
+        assert!(ambiguities.iter().all(|(a, _)| a.ends_with("read_score")));
+    }
+}
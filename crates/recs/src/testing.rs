@@ -0,0 +1,352 @@
+//! A harness for running scenario programs deterministically under every
+//! registry configuration and asserting that they all end in the same state.
+//!
+//! Unit tests usually exercise one feature under the default configuration,
+//! so regressions that only show up when features interact (e.g. commands
+//! under the parallel executor) slip through. A `Scenario` describes a whole
+//! program instead: how the registry is set up, what happens at which frame
+//! and how many frames run. `Scenario::assert_consistent` then runs it under
+//! each `ScenarioConfig` and compares the resulting registries.
+
+use std::fmt::Debug;
+
+use crate::{
+    registry::{Registry, stats::RegistryStats},
+    system::executor::ExecutorKind,
+};
+
+/// A registry configuration a scenario can run under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScenarioConfig {
+    /// How the registry runs its systems
+    pub executor: ExecutorKind,
+}
+
+impl ScenarioConfig {
+    /// Returns every configuration scenarios are checked against
+    pub fn all() -> Vec<ScenarioConfig> {
+        [ExecutorKind::Sequential, ExecutorKind::Parallel]
+            .into_iter()
+            .map(|executor| ScenarioConfig { executor })
+            .collect()
+    }
+
+    /// Applies this configuration to `registry`
+    pub fn apply(&self, registry: &mut Registry) {
+        registry.set_executor(self.executor);
+    }
+}
+
+type Step = Box<dyn Fn(&mut Registry)>;
+
+/// The frames a scenario step runs before
+#[derive(Debug, Clone, Copy)]
+enum Trigger {
+    /// The frame with this index
+    At(u64),
+    /// Every frame whose index is a multiple of the period
+    Every(u64),
+}
+
+impl Trigger {
+    fn fires(self, frame: u64) -> bool {
+        match self {
+            Trigger::At(at) => at == frame,
+            Trigger::Every(period) => frame.is_multiple_of(period),
+        }
+    }
+}
+
+/// A deterministic program run against a fresh registry.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use recs::testing::Scenario;
+/// #[derive(Component)]
+/// struct Enemy;
+///
+/// Scenario::new("enemy waves")
+///     .frames(10)
+///     .every(3, |registry| {
+///         for _ in 0..5 {
+///             registry.spawn((Enemy,));
+///         }
+///     })
+///     .assert_consistent(|registry| registry.query::<(&Enemy,)>().count());
+/// ```
+pub struct Scenario {
+    name: &'static str,
+    frames: u64,
+    setup: Vec<Step>,
+    /// Steps run before the frames their trigger fires on, resolved when the
+    /// scenario runs so that the builder calls can come in any order
+    steps: Vec<(Trigger, Step)>,
+}
+
+impl Scenario {
+    /// Creates an empty scenario running a single frame
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            frames: 1,
+            setup: Vec::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Sets the number of frames to run
+    pub fn frames(mut self, frames: u64) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    /// Adds a step run once on the fresh registry, before the first frame
+    pub fn setup(mut self, step: impl Fn(&mut Registry) + 'static) -> Self {
+        self.setup.push(Box::new(step));
+        self
+    }
+
+    /// Adds a step run right before frame `frame` (counting from 0)
+    pub fn at_frame(mut self, frame: u64, step: impl Fn(&mut Registry) + 'static) -> Self {
+        self.steps.push((Trigger::At(frame), Box::new(step)));
+        self
+    }
+
+    /// Adds a step run before every `period`-th frame, starting with the first
+    pub fn every(mut self, period: u64, step: impl Fn(&mut Registry) + 'static) -> Self {
+        self.steps
+            .push((Trigger::Every(period.max(1)), Box::new(step)));
+        self
+    }
+
+    /// Runs the scenario on a fresh registry configured with `config`, and
+    /// returns the registry in its final state
+    pub fn run(&self, config: ScenarioConfig) -> Registry {
+        let mut registry = Registry::new();
+        config.apply(&mut registry);
+
+        for step in &self.setup {
+            step(&mut registry);
+        }
+
+        for frame in 0..self.frames {
+            for (_, step) in self
+                .steps
+                .iter()
+                .filter(|(trigger, _)| trigger.fires(frame))
+            {
+                step(&mut registry);
+            }
+            registry.run_systems();
+        }
+        registry
+    }
+
+    /// Runs the scenario under every `ScenarioConfig` and panics if the final
+    /// registries differ in shape (see `RegistryStats`) or in what `observe`
+    /// returns for them. Returns the observation.
+    pub fn assert_consistent<T: PartialEq + Debug>(
+        &self,
+        observe: impl Fn(&mut Registry) -> T,
+    ) -> T {
+        let mut expected: Option<(ScenarioConfig, RegistryStats, T)> = None;
+
+        for config in ScenarioConfig::all() {
+            let mut registry = self.run(config);
+            let stats = registry.stats();
+            let observed = observe(&mut registry);
+
+            match &expected {
+                None => expected = Some((config, stats, observed)),
+                Some((expected_config, expected_stats, expected_observed)) => {
+                    assert_eq!(
+                        &stats, expected_stats,
+                        "scenario `{}`: registry shape under {:?} differs from {:?}",
+                        self.name, config, expected_config
+                    );
+                    assert_eq!(
+                        &observed, expected_observed,
+                        "scenario `{}`: observed state under {:?} differs from {:?}",
+                        self.name, config, expected_config
+                    );
+                }
+            }
+        }
+
+        expected
+            .map(|(_, _, observed)| observed)
+            .expect("there is at least one scenario configuration")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        component::Component,
+        entity::Entity,
+        query::Query,
+        resource::{ResMut, Resource},
+        system::{commands::Commands, schedule::IntoSystemConfig},
+    };
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Health(i32);
+    impl Component for Health {}
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Poisoned;
+    impl Component for Poisoned {}
+
+    #[derive(Default)]
+    struct Wave(u32);
+    impl Resource for Wave {}
+
+    #[derive(Default)]
+    struct Log(Vec<&'static str>);
+    impl Resource for Log {}
+
+    fn spawn_wave(mut commands: Commands, mut wave: ResMut<Wave>) {
+        wave.0 += 1;
+        for i in 0..wave.0 * 10 {
+            let entity = commands.spawn((Health(100),));
+            if i % 3 == 0 {
+                commands.insert(entity, Poisoned);
+            }
+        }
+    }
+
+    fn poison(query: Query<(&mut Health, &Poisoned)>) {
+        for (mut health, _) in query {
+            health.0 -= 30;
+        }
+    }
+
+    fn total_health(registry: &mut Registry) -> i64 {
+        registry
+            .query::<(&Health,)>()
+            .map(|(health,)| health.0 as i64)
+            .sum()
+    }
+
+    #[test]
+    fn test_spawn_waves() {
+        let total = Scenario::new("spawn waves")
+            .frames(6)
+            .setup(|registry| {
+                registry.init_resource::<Wave>();
+                registry.add_system(spawn_wave);
+                registry.add_system(poison);
+            })
+            .assert_consistent(total_health);
+        assert!(total > 0);
+    }
+
+    #[test]
+    fn test_periodic_steps_follow_the_frame_count_set_afterwards() {
+        let spawned = Scenario::new("periodic steps")
+            .every(3, |registry| {
+                registry.spawn((Poisoned,));
+            })
+            .frames(10)
+            .assert_consistent(|registry| registry.query::<(&Poisoned,)>().count());
+        // Frames 0, 3, 6 and 9
+        assert_eq!(spawned, 4);
+    }
+
+    #[test]
+    fn test_destroy_huge_sets() {
+        let remaining = Scenario::new("destroy huge sets")
+            .frames(3)
+            .setup(|registry| {
+                for i in 0..20_000 {
+                    registry.spawn((Health(i),));
+                }
+            })
+            .at_frame(1, |registry| {
+                let doomed: Vec<Entity> = registry
+                    .entities()
+                    .filter(|entity| entity.id() % 2 == 0)
+                    .collect();
+                for entity in doomed {
+                    registry.commands().despawn(entity);
+                }
+            })
+            .at_frame(2, |registry| {
+                // Recycled ids must start out empty
+                for _ in 0..100 {
+                    registry.spawn((Poisoned,));
+                }
+            })
+            .assert_consistent(|registry| {
+                (
+                    registry.query::<(&Health,)>().count(),
+                    registry.query::<(&Health, &Poisoned)>().count(),
+                )
+            });
+        assert_eq!(remaining, (10_000, 0));
+    }
+
+    #[test]
+    fn test_reordered_systems() {
+        fn first(mut log: ResMut<Log>) {
+            log.0.push("first");
+        }
+        fn second(mut log: ResMut<Log>) {
+            log.0.push("second");
+        }
+
+        let in_order = Scenario::new("systems in order")
+            .setup(|registry| {
+                registry.init_resource::<Log>();
+                registry.add_system(first);
+                registry.add_system(second);
+            })
+            .assert_consistent(|registry| registry.get_resource::<Log>().unwrap().0.clone());
+        let reordered = Scenario::new("systems reordered by constraints")
+            .setup(|registry| {
+                registry.init_resource::<Log>();
+                registry.add_system(second.after(first));
+                registry.add_system(first);
+            })
+            .assert_consistent(|registry| registry.get_resource::<Log>().unwrap().0.clone());
+        assert_eq!(in_order, reordered);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        fn register_codecs(registry: &mut Registry) {
+            registry.register_component_codec(
+                |health: &Health, bytes: &mut Vec<u8>| bytes.extend(health.0.to_le_bytes()),
+                |bytes| Some(Health(i32::from_le_bytes(bytes.try_into().ok()?))),
+            );
+        }
+
+        let scenario = Scenario::new("save and load").frames(4).setup(|registry| {
+            register_codecs(registry);
+            registry.init_resource::<Wave>();
+            registry.add_system(spawn_wave);
+            registry.add_system(poison);
+        });
+
+        scenario.assert_consistent(|registry| {
+            // Save every entity, load into a fresh registry and compare
+            let saved: Vec<_> = registry
+                .entities()
+                .map(|entity| registry.pack_entity(entity).unwrap())
+                .collect();
+
+            let mut loaded = Registry::new();
+            register_codecs(&mut loaded);
+            for packed in &saved {
+                let entity = loaded.create_entity();
+                for component in packed {
+                    loaded.unpack_component(entity, component).unwrap();
+                }
+            }
+            assert_eq!(total_health(&mut loaded), total_health(registry));
+            saved.len()
+        });
+    }
+}
@@ -0,0 +1,92 @@
+/// How many ticks may pass between two clamps of every stored tick, see
+/// `Registry::check_change_ticks`
+pub const CHECK_TICK_THRESHOLD: u32 = 518_400_000;
+
+/// The maximum age of a stored tick relative to the current one. Older ticks
+/// are clamped to it, so that they are never mistaken for recent ones once the
+/// counter wraps around.
+pub const MAX_CHANGE_AGE: u32 = u32::MAX - (2 * CHECK_TICK_THRESHOLD - 1);
+
+/// A point in time used for change detection.
+///
+/// The Registry advances its change tick every time a system runs. Component
+/// writes are stamped with the current tick so that systems can later ask
+/// whether a value was changed since they last ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Tick(u32);
+
+impl Tick {
+    /// Creates a new Tick from a raw counter value
+    pub const fn new(tick: u32) -> Self {
+        Self(tick)
+    }
+
+    /// Returns the raw counter value
+    pub fn get(self) -> u32 {
+        self.0
+    }
+
+    /// Returns the tick that comes after this one, wrapping on overflow
+    pub fn next(self) -> Self {
+        Self(self.0.wrapping_add(1))
+    }
+
+    /// Returns true if this tick happened after `last_run`, as seen from `this_run`.
+    ///
+    /// The comparison is done relative to `this_run` so that it keeps working
+    /// when the underlying counter wraps around.
+    pub fn is_newer_than(self, last_run: Tick, this_run: Tick) -> bool {
+        let ticks_since_self = this_run.0.wrapping_sub(self.0);
+        let ticks_since_last_run = this_run.0.wrapping_sub(last_run.0);
+        ticks_since_last_run > ticks_since_self
+    }
+
+    /// Clamps this tick to at most `MAX_CHANGE_AGE` ticks before `this_run`.
+    /// Returns true if it was older.
+    pub fn check_tick(&mut self, this_run: Tick) -> bool {
+        if this_run.0.wrapping_sub(self.0) > MAX_CHANGE_AGE {
+            self.0 = this_run.0.wrapping_sub(MAX_CHANGE_AGE);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer_than() {
+        let last_run = Tick::new(5);
+        let this_run = Tick::new(10);
+
+        assert!(Tick::new(6).is_newer_than(last_run, this_run));
+        assert!(Tick::new(10).is_newer_than(last_run, this_run));
+        assert!(!Tick::new(5).is_newer_than(last_run, this_run));
+        assert!(!Tick::new(2).is_newer_than(last_run, this_run));
+    }
+
+    #[test]
+    fn test_is_newer_than_wrapping() {
+        let last_run = Tick::new(u32::MAX - 1);
+        let this_run = Tick::new(3);
+
+        assert!(Tick::new(u32::MAX).is_newer_than(last_run, this_run));
+        assert!(Tick::new(1).is_newer_than(last_run, this_run));
+        assert!(!Tick::new(u32::MAX - 2).is_newer_than(last_run, this_run));
+    }
+
+    #[test]
+    fn test_check_tick_clamps_old_ticks() {
+        let this_run = Tick::new(10);
+        let mut recent = Tick::new(3);
+        assert!(!recent.check_tick(this_run));
+        assert_eq!(recent, Tick::new(3));
+
+        let mut old = Tick::new(this_run.get().wrapping_sub(MAX_CHANGE_AGE + 5));
+        assert!(old.check_tick(this_run));
+        assert_eq!(this_run.get().wrapping_sub(old.get()), MAX_CHANGE_AGE);
+    }
+}
@@ -0,0 +1,320 @@
+use std::time::{Duration, Instant};
+
+use crate::{error::RecsError, resource::Resource};
+
+/// How `Time` advances every frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimeSource {
+    /// Measures wall-clock time between frames
+    Real { last_update: Option<Instant> },
+    /// Advances by a fixed delta every frame
+    Manual { delta: Duration },
+}
+
+/// Frame timing, updated by the Registry at the start of every `run_systems`.
+///
+/// The Registry inserts a real-time clock the first time it runs; insert
+/// `Time::manual` beforehand to step time deterministically, e.g. in tests.
+/// `delta` and `elapsed` are scaled by `scale`, which allows slow motion or
+/// pausing the game. `FixedUpdate` follows the scaled time as well.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use std::time::Duration;
+/// #[derive(Component)]
+/// struct Position { x: f32 }
+///
+/// fn movement(time: Res<Time>, query: Query<(&mut Position,)>) {
+///     for (mut pos,) in query {
+///         pos.x += 10.0 * time.delta_secs();
+///     }
+/// }
+///
+/// let mut registry = Registry::new();
+/// registry.insert_resource(Time::manual(Duration::from_millis(100)));
+/// let entity = registry.spawn(Position { x: 0.0 });
+/// registry.add_system(movement);
+///
+/// registry.run_systems();
+/// registry.run_systems();
+/// # let x = registry.get_component::<Position>(entity).unwrap().x;
+/// # assert!((x - 2.0).abs() < 1e-5);
+/// assert_eq!(registry.get_resource::<Time>().unwrap().elapsed(), Duration::from_millis(200));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Time {
+    source: TimeSource,
+    delta: Duration,
+    elapsed: Duration,
+    scale: f64,
+}
+
+impl Resource for Time {}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::real()
+    }
+}
+
+impl Time {
+    /// Creates a clock measuring wall-clock time between frames. The first
+    /// frame has a zero delta.
+    pub fn real() -> Self {
+        Self::with_source(TimeSource::Real { last_update: None })
+    }
+
+    /// Creates a clock advancing by exactly `delta` every frame
+    pub fn manual(delta: Duration) -> Self {
+        Self::with_source(TimeSource::Manual { delta })
+    }
+
+    fn with_source(source: TimeSource) -> Self {
+        Self {
+            source,
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            scale: 1.0,
+        }
+    }
+
+    /// Returns true if this clock advances by a fixed delta every frame
+    pub fn is_manual(&self) -> bool {
+        matches!(self.source, TimeSource::Manual { .. })
+    }
+
+    /// Changes the delta a manual clock advances by every frame, switching a
+    /// real-time clock to manual mode
+    pub fn set_manual_delta(&mut self, delta: Duration) {
+        self.source = TimeSource::Manual { delta };
+    }
+
+    /// Returns the scaled time elapsed during the last frame
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// Returns the scaled time elapsed during the last frame, in seconds
+    pub fn delta_secs(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Returns the scaled time elapsed since the first frame
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the scaled time elapsed since the first frame, in seconds
+    pub fn elapsed_secs(&self) -> f64 {
+        self.elapsed.as_secs_f64()
+    }
+
+    /// Returns the factor frame durations are multiplied by
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Sets the factor frame durations are multiplied by, e.g. 0.5 for slow
+    /// motion or 0 to pause.
+    ///
+    /// Returns `InvalidTimeScale` and keeps the current scale if `scale` is
+    /// negative, infinite or NaN.
+    pub fn set_scale(&mut self, scale: f64) -> Result<(), RecsError> {
+        if !scale.is_finite() || scale < 0.0 {
+            return Err(RecsError::InvalidTimeScale(scale));
+        }
+        self.scale = scale;
+        Ok(())
+    }
+
+    /// Advances the clock by one frame ending at `now`
+    pub(crate) fn update(&mut self, now: Instant) {
+        let raw_delta = match &mut self.source {
+            TimeSource::Real { last_update } => last_update
+                .replace(now)
+                .map_or(Duration::ZERO, |last| now.saturating_duration_since(last)),
+            TimeSource::Manual { delta } => *delta,
+        };
+
+        // Saturates rather than panicking when a huge scale overflows
+        self.delta = Duration::try_from_secs_f64(raw_delta.as_secs_f64() * self.scale)
+            .unwrap_or(Duration::MAX);
+        self.elapsed = self.elapsed.saturating_add(self.delta);
+    }
+}
+
+/// Drives the `FixedUpdate` schedule, which runs at a fixed rate independent
+/// of the frame rate, e.g. for physics.
+///
+/// Every frame, the scaled `Time::delta` is added to an accumulator, and the
+/// `FixedUpdate` schedule runs once per whole `step` it contains, i.e. zero or
+/// more times. The Registry inserts this resource with the default step of
+/// 1/64 s the first time it runs `FixedUpdate`; insert it yourself to pick
+/// another step.
+///
+/// # Example
+/// ```rust
+/// # use recs::prelude::*;
+/// # use std::time::Duration;
+/// #[derive(Component)]
+/// struct Body { y: f32, vy: f32 }
+///
+/// fn gravity(time: Res<FixedTime>, query: Query<(&mut Body,)>) {
+///     let dt = time.step().as_secs_f32();
+///     for (mut body,) in query {
+///         body.vy -= 9.81 * dt;
+///         body.y += body.vy * dt;
+///     }
+/// }
+///
+/// let mut registry = Registry::new();
+/// registry.insert_resource(FixedTime::new(Duration::from_millis(20)));
+/// registry.add_system_to(Stage::FixedUpdate, gravity);
+/// registry.run_systems();
+/// ```
+#[derive(Debug, Clone)]
+pub struct FixedTime {
+    step: Duration,
+    accumulator: Duration,
+    max_steps: u32,
+}
+
+impl Resource for FixedTime {}
+
+impl Default for FixedTime {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_STEP)
+    }
+}
+
+impl FixedTime {
+    /// The default step duration, 1/64 s
+    pub const DEFAULT_STEP: Duration = Duration::from_micros(15_625);
+
+    /// Creates a fixed timestep running `FixedUpdate` once per `step`, at most
+    /// 8 times per frame. A zero step is clamped to 1 ns.
+    pub fn new(step: Duration) -> Self {
+        Self {
+            step: step.max(Duration::from_nanos(1)),
+            accumulator: Duration::ZERO,
+            max_steps: 8,
+        }
+    }
+
+    /// Limits how many times `FixedUpdate` runs in a single frame. Time left
+    /// over after the last allowed step is dropped, so that a slow frame can't
+    /// make the following frames slower and slower.
+    pub fn with_max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps.max(1);
+        self
+    }
+
+    /// Returns the duration of one fixed step
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// Changes the duration of one fixed step. A zero step is clamped to 1 ns.
+    pub fn set_step(&mut self, step: Duration) {
+        self.step = step.max(Duration::from_nanos(1));
+    }
+
+    /// Returns the maximum number of steps run in a single frame
+    pub fn max_steps(&self) -> u32 {
+        self.max_steps
+    }
+
+    /// Adds elapsed time to the accumulator
+    pub fn accumulate(&mut self, delta: Duration) {
+        self.accumulator += delta;
+    }
+
+    /// Returns the time accumulated towards the next step
+    pub fn accumulator(&self) -> Duration {
+        self.accumulator
+    }
+
+    /// Returns how far the accumulator is into the next step, between 0 and 1.
+    /// Useful to interpolate rendering between two fixed steps.
+    pub fn overstep_fraction(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+
+    /// Takes as many whole steps out of the accumulator as should run this
+    /// frame, returning their number
+    pub(crate) fn expend(&mut self) -> u32 {
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            if steps == self.max_steps {
+                let step_nanos = self.step.as_nanos();
+                let left_nanos = self.accumulator.as_nanos() % step_nanos;
+                self.accumulator = Duration::from_nanos(left_nanos as u64);
+                break;
+            }
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_scale_and_manual_delta() {
+        let start = Instant::now();
+        let mut time = Time::manual(Duration::from_millis(10));
+        time.update(start);
+        time.set_scale(2.0).unwrap();
+        time.update(start);
+        assert_eq!(time.delta(), Duration::from_millis(20));
+        assert_eq!(time.elapsed(), Duration::from_millis(30));
+
+        let mut time = Time::real();
+        time.update(start);
+        assert_eq!(time.delta(), Duration::ZERO);
+        time.update(start + Duration::from_millis(5));
+        assert_eq!(time.delta(), Duration::from_millis(5));
+        assert!(!time.is_manual());
+    }
+
+    #[test]
+    fn test_invalid_time_scales_are_rejected() {
+        let mut time = Time::manual(Duration::from_millis(10));
+        time.set_scale(0.5).unwrap();
+        for scale in [f64::INFINITY, f64::NAN, -1.0] {
+            assert!(matches!(
+                time.set_scale(scale),
+                Err(RecsError::InvalidTimeScale(_))
+            ));
+        }
+        assert_eq!(time.scale(), 0.5);
+
+        time.set_scale(f64::MAX).unwrap();
+        time.update(Instant::now());
+        assert_eq!(time.delta(), Duration::MAX);
+    }
+
+    #[test]
+    fn test_expend_whole_steps() {
+        let mut fixed = FixedTime::new(Duration::from_millis(10));
+        fixed.accumulate(Duration::from_millis(25));
+        assert_eq!(fixed.expend(), 2);
+        assert_eq!(fixed.accumulator(), Duration::from_millis(5));
+        assert_eq!(fixed.overstep_fraction(), 0.5);
+
+        fixed.accumulate(Duration::from_millis(4));
+        assert_eq!(fixed.expend(), 0);
+    }
+
+    #[test]
+    fn test_expend_drops_time_beyond_max_steps() {
+        let mut fixed = FixedTime::new(Duration::from_millis(10)).with_max_steps(3);
+        fixed.accumulate(Duration::from_millis(107));
+        assert_eq!(fixed.expend(), 3);
+        assert_eq!(fixed.accumulator(), Duration::from_millis(7));
+    }
+}
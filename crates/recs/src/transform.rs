@@ -0,0 +1,343 @@
+//! Local and world transforms of entities, propagated through the hierarchy.
+//!
+//! Entities carry a `Transform` relative to their parent (see
+//! `Registry::set_parent`), and `Registry::propagate_transforms` computes
+//! their `GlobalTransform` in world space. `TransformPlugin` runs the
+//! propagation at the end of every frame.
+//!
+//! Only available with the `transform` feature. Math types come from `glam`,
+//! which is re-exported so applications use the same version.
+
+pub use glam;
+use glam::{Affine3A, Mat4, Quat, Vec3};
+
+use crate::{
+    component::Component,
+    entity::Entity,
+    plugin::Plugin,
+    registry::Registry,
+    system::{IntoSystem, System, schedule::IntoSystemConfig, schedule::Stage},
+    tick::Tick,
+};
+
+/// The label of the propagation system added by `TransformPlugin`, for
+/// ordering systems that read `GlobalTransform` after it
+pub const PROPAGATE_TRANSFORMS: &str = "propagate_transforms";
+
+/// The position, rotation and scale of an entity relative to its parent, or
+/// to the world for entities without a parent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Transform {
+    /// A transform that changes nothing
+    pub const IDENTITY: Self = Self {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    /// Creates a transform translating by `(x, y, z)`
+    pub fn from_xyz(x: f32, y: f32, z: f32) -> Self {
+        Self::from_translation(Vec3::new(x, y, z))
+    }
+
+    /// Creates a transform translating by `translation`
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Creates a transform rotating by `rotation`
+    pub fn from_rotation(rotation: Quat) -> Self {
+        Self {
+            rotation,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Creates a transform scaling by `scale`
+    pub fn from_scale(scale: Vec3) -> Self {
+        Self {
+            scale,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Replaces the translation
+    pub fn with_translation(mut self, translation: Vec3) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    /// Replaces the rotation
+    pub fn with_rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Replaces the scale
+    pub fn with_scale(mut self, scale: Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Returns the transform as an affine transformation, applying scale,
+    /// then rotation, then translation
+    pub fn compute_affine(&self) -> Affine3A {
+        Affine3A::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    /// Returns the transform as a 4x4 matrix
+    pub fn compute_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Component for Transform {
+    const CLONE: Option<fn(&Self) -> Self> = Some(Self::clone);
+}
+
+/// The transform of an entity in world space, computed from its `Transform`
+/// and those of its ancestors by `Registry::propagate_transforms`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct GlobalTransform(Affine3A);
+
+impl GlobalTransform {
+    /// Returns the world transform as an affine transformation
+    pub fn affine(&self) -> Affine3A {
+        self.0
+    }
+
+    /// Returns the world transform as a 4x4 matrix
+    pub fn compute_matrix(&self) -> Mat4 {
+        Mat4::from(self.0)
+    }
+
+    /// Returns the position in world space
+    pub fn translation(&self) -> Vec3 {
+        self.0.translation.into()
+    }
+
+    /// Decomposes the world transform into a `Transform`
+    pub fn compute_transform(&self) -> Transform {
+        let (scale, rotation, translation) = self.0.to_scale_rotation_translation();
+        Transform {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    /// Transforms `point` from the entity's local space into world space
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.0.transform_point3(point)
+    }
+}
+
+impl Component for GlobalTransform {
+    const CLONE: Option<fn(&Self) -> Self> = Some(Self::clone);
+}
+
+impl Registry {
+    /// Computes the `GlobalTransform` of every entity with a `Transform`,
+    /// inserting it where missing.
+    ///
+    /// Walks the hierarchy from the entities whose parent has no `Transform`.
+    /// A child without a `Transform` cuts its subtree off from propagation.
+    /// Global transforms are only written when they change, so `Changed`
+    /// filters see moved entities only.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use recs::prelude::*;
+    /// # use recs::transform::{GlobalTransform, Transform, glam::Vec3};
+    /// let mut registry = Registry::new();
+    /// let ship = registry.spawn(Transform::from_xyz(10.0, 0.0, 0.0));
+    /// let turret = registry.spawn(Transform::from_xyz(0.0, 2.0, 0.0));
+    /// registry.set_parent(turret, ship).unwrap();
+    ///
+    /// registry.propagate_transforms();
+    /// let global = registry.get_component::<GlobalTransform>(turret).unwrap();
+    /// assert_eq!(global.translation(), Vec3::new(10.0, 2.0, 0.0));
+    /// ```
+    pub fn propagate_transforms(&mut self) {
+        let mut pending: Vec<(Entity, Affine3A)> = self
+            .entities_with::<Transform>()
+            .iter()
+            .filter(|&&entity| {
+                self.parent(entity)
+                    .is_none_or(|parent| !self.has_component::<Transform>(parent))
+            })
+            .map(|&entity| (entity, Affine3A::IDENTITY))
+            .collect();
+
+        while let Some((entity, parent)) = pending.pop() {
+            let Some(local) = self.get_component::<Transform>(entity) else {
+                continue;
+            };
+            let global = parent * local.compute_affine();
+
+            match self.get_component::<GlobalTransform>(entity) {
+                Some(current) if current.0 == global => {}
+                Some(_) => {
+                    *self.get_component_mut::<GlobalTransform>(entity).unwrap() =
+                        GlobalTransform(global)
+                }
+                None => {
+                    let _ = self.add_component(entity, GlobalTransform(global));
+                }
+            }
+
+            pending.extend(self.children(entity).iter().map(|&child| (child, global)));
+        }
+    }
+}
+
+/// The system added by `TransformPlugin`, running `propagate_transforms`
+#[derive(Default)]
+pub struct PropagateTransforms {
+    last_run: Tick,
+}
+
+impl System for PropagateTransforms {
+    type In = ();
+    type Out = ();
+
+    fn run(&mut self, _input: (), registry: &mut Registry) {
+        registry.propagate_transforms();
+    }
+
+    fn last_run(&self) -> Tick {
+        self.last_run
+    }
+
+    fn set_last_run(&mut self, tick: Tick) {
+        self.last_run = tick;
+    }
+}
+
+impl IntoSystem<()> for PropagateTransforms {
+    type System = Self;
+
+    fn into_system(self) -> Self::System {
+        self
+    }
+}
+
+/// Propagates transforms in `PostUpdate` every frame, after the systems that
+/// move entities in `Update`. The system is labelled `PROPAGATE_TRANSFORMS`.
+pub struct TransformPlugin;
+
+impl Plugin for TransformPlugin {
+    fn build(&self, registry: &mut Registry) {
+        registry.add_system_to(
+            Stage::PostUpdate,
+            PropagateTransforms::default().label(PROPAGATE_TRANSFORMS),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{query::Changed, system::schedule::IntoSystemConfig};
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{a} != {b}");
+    }
+
+    #[test]
+    fn test_propagation_through_the_hierarchy() {
+        let mut registry = Registry::new();
+        registry.add_plugin(TransformPlugin).unwrap();
+
+        let root = registry.spawn(
+            Transform::from_xyz(1.0, 0.0, 0.0)
+                .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2))
+                .with_scale(Vec3::splat(2.0)),
+        );
+        let child = registry.spawn(Transform::from_xyz(1.0, 0.0, 0.0));
+        let grandchild = registry.spawn(Transform::from_xyz(0.0, 1.0, 0.0));
+        let cut_off = registry.create_entity();
+        let below_cut = registry.spawn(Transform::IDENTITY);
+        registry.set_parent(child, root).unwrap();
+        registry.set_parent(grandchild, child).unwrap();
+        registry.set_parent(cut_off, root).unwrap();
+        registry.set_parent(below_cut, cut_off).unwrap();
+
+        registry.run_systems();
+        let global = |registry: &Registry, entity| {
+            registry
+                .get_component::<GlobalTransform>(entity)
+                .map(GlobalTransform::translation)
+        };
+        assert_near(global(&registry, root).unwrap(), Vec3::new(1.0, 0.0, 0.0));
+        assert_near(global(&registry, child).unwrap(), Vec3::new(1.0, 2.0, 0.0));
+        assert_near(
+            global(&registry, grandchild).unwrap(),
+            Vec3::new(-1.0, 2.0, 0.0),
+        );
+        // Parents without a Transform start new roots
+        assert_near(global(&registry, below_cut).unwrap(), Vec3::ZERO);
+
+        registry.set_parent(grandchild, root).unwrap();
+        registry.run_systems();
+        assert_near(
+            global(&registry, grandchild).unwrap(),
+            Vec3::new(-1.0, 0.0, 0.0),
+        );
+        let decomposed = registry
+            .get_component::<GlobalTransform>(grandchild)
+            .unwrap()
+            .compute_transform();
+        assert_near(decomposed.scale, Vec3::splat(2.0));
+    }
+
+    #[test]
+    fn test_unmoved_entities_are_not_marked_changed() {
+        use crate::{query::Query, resource::ResMut, resource::Resource};
+
+        #[derive(Default)]
+        struct Moved(usize);
+        impl Resource for Moved {}
+
+        fn count_moved(
+            moved: Query<(&GlobalTransform,), Changed<GlobalTransform>>,
+            mut count: ResMut<Moved>,
+        ) {
+            count.0 = moved.into_iter().count();
+        }
+
+        let mut registry = Registry::new();
+        registry.init_resource::<Moved>();
+        registry.add_plugin(TransformPlugin).unwrap();
+        registry.add_system_to(Stage::PostUpdate, count_moved.after(PROPAGATE_TRANSFORMS));
+        let still = registry.spawn(Transform::IDENTITY);
+        let moving = registry.spawn(Transform::IDENTITY);
+
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Moved>().unwrap().0, 2);
+
+        registry
+            .get_component_mut::<Transform>(moving)
+            .unwrap()
+            .translation
+            .x += 1.0;
+        registry.run_systems();
+        assert_eq!(registry.get_resource::<Moved>().unwrap().0, 1);
+        assert!(registry.is_valid(still));
+    }
+}
@@ -0,0 +1,99 @@
+//! Hashers replacing SipHash for internal maps. Maps keyed by `TypeId` skip
+//! hashing: type ids are already random, so their bits are used as the hash
+//! directly. Maps keyed by entity ids use a single multiply, as FxHash does.
+
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    hash::{BuildHasherDefault, Hasher},
+};
+
+/// A `HashMap` keyed by `TypeId`, without the cost of SipHash
+pub(crate) type TypeIdMap<V> = HashMap<TypeId, V, BuildHasherDefault<TypeIdHasher>>;
+
+/// Hashes a `TypeId` by passing its bits through
+#[derive(Default)]
+pub(crate) struct TypeIdHasher(u64);
+
+impl Hasher for TypeIdHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = self.0.rotate_left(8) ^ u64::from(byte);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.0 ^= value;
+    }
+}
+
+/// Hashes integer ids with one multiply, like FxHash. Ids are small and
+/// dense, which the multiply spreads over the high bits the map looks at.
+#[derive(Default)]
+pub(crate) struct IdHasher(u64);
+
+impl IdHasher {
+    const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+    fn add(&mut self, value: u64) {
+        self.0 = (self.0.rotate_left(5) ^ value).wrapping_mul(Self::SEED);
+    }
+}
+
+impl Hasher for IdHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.add(u64::from(byte));
+        }
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.add(u64::from(value));
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.add(value);
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.add(value as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_type_id_map_finds_every_type() {
+        let mut map = TypeIdMap::default();
+        map.insert(TypeId::of::<u8>(), "u8");
+        map.insert(TypeId::of::<u16>(), "u16");
+        map.insert(TypeId::of::<String>(), "String");
+
+        assert_eq!(map[&TypeId::of::<u8>()], "u8");
+        assert_eq!(map[&TypeId::of::<u16>()], "u16");
+        assert_eq!(map[&TypeId::of::<String>()], "String");
+        assert!(!map.contains_key(&TypeId::of::<u32>()));
+    }
+
+    #[test]
+    fn test_id_hasher_separates_close_ids() {
+        let hash = |id: usize| {
+            let mut hasher = IdHasher::default();
+            hasher.write_usize(id);
+            hasher.finish()
+        };
+        assert_ne!(hash(1), hash(2));
+        // The map picks buckets from the top bits
+        assert_ne!(hash(1) >> 57, hash(2) >> 57);
+    }
+}
@@ -1,15 +1,112 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{DeriveInput, parse_macro_input};
+use syn::{
+    Data, DeriveInput, Fields, LitStr, Member, Path, Token, Type, parse::Parse, parse_macro_input,
+    parse_quote,
+};
 
-#[proc_macro_derive(Component)]
+/// Implements `Component`. The storage of the component type can be picked
+/// with `#[component(storage = "table")]`,
+/// `#[component(storage = "sparse_map")]` or
+/// `#[component(storage = "sparse")]`, the default. Lifecycle hooks are
+/// declared with `#[component(on_add = path)]` and
+/// `#[component(on_remove = path)]`, components inserted with their default
+/// value alongside this one with `#[component(requires(A, B))]`, and
+/// `#[component(clone)]` registers the `Clone` impl as the clone function of
+/// the component type.
+#[proc_macro_derive(Component, attributes(component))]
 pub fn derive_component(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
+    let mut storage = None;
+    let mut on_add = None;
+    let mut on_remove = None;
+    let mut requires = Vec::new();
+    let mut clone = false;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("component"))
+    {
+        let parsed = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("on_add") {
+                on_add = Some(meta.value()?.parse::<Path>()?);
+                return Ok(());
+            }
+            if meta.path.is_ident("on_remove") {
+                on_remove = Some(meta.value()?.parse::<Path>()?);
+                return Ok(());
+            }
+            if meta.path.is_ident("clone") {
+                clone = true;
+                return Ok(());
+            }
+            if meta.path.is_ident("requires") {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                requires.extend(content.parse_terminated(Type::parse, Token![,])?);
+                return Ok(());
+            }
+            if !meta.path.is_ident("storage") {
+                return Err(
+                    meta.error("expected `storage`, `on_add`, `on_remove`, `requires` or `clone`")
+                );
+            }
+            let value: LitStr = meta.value()?.parse()?;
+            storage = Some(match value.value().as_str() {
+                "sparse" => quote! { recs::component::StorageType::SparseSet },
+                "table" => quote! { recs::component::StorageType::Table },
+                "sparse_map" => quote! { recs::component::StorageType::SparseMap },
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        value,
+                        "expected \"sparse\", \"sparse_map\" or \"table\"",
+                    ));
+                }
+            });
+            Ok(())
+        });
+        if let Err(error) = parsed {
+            return error.to_compile_error().into();
+        }
+    }
+
     let name = input.ident;
+    let storage = storage.map(|storage| {
+        quote! { const STORAGE: recs::component::StorageType = #storage; }
+    });
+    let on_add = on_add.map(|hook| {
+        quote! { const ON_ADD: Option<recs::component::hooks::ComponentHook<Self>> = Some(#hook); }
+    });
+    let on_remove = on_remove.map(|hook| {
+        quote! { const ON_REMOVE: Option<recs::component::hooks::ComponentHook<Self>> = Some(#hook); }
+    });
+
+    let requires = (!requires.is_empty()).then(|| {
+        quote! {
+            const REQUIRES: &'static [recs::component::required::RequiredComponent] = &[
+                #(recs::component::required::RequiredComponent::of::<#requires>(),)*
+            ];
+        }
+    });
+    let clone = clone.then(|| {
+        quote! { const CLONE: Option<fn(&Self) -> Self> = Some(<Self as ::core::clone::Clone>::clone); }
+    });
 
     let expanded = quote! {
-        impl recs::component::Component for #name {}
+        impl recs::component::Component for #name {
+            #storage
+            #on_add
+            #on_remove
+            #requires
+            #clone
+
+            fn storage_cache() -> Option<&'static recs::component::storages::StorageCache> {
+                static CACHE: recs::component::storages::StorageCache =
+                    recs::component::storages::StorageCache::new();
+                Some(&CACHE)
+            }
+        }
     };
 
     TokenStream::from(expanded)
@@ -27,3 +124,137 @@ pub fn derive_resource(input: TokenStream) -> TokenStream {
 
     TokenStream::from(expanded)
 }
+
+/// Implements `ComponentBundle` for a struct by adding every field to the
+/// entity. Fields may be components or other bundles, which are flattened
+/// recursively.
+#[proc_macro_derive(Bundle)]
+pub fn derive_bundle(input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+
+    let Data::Struct(data) = &input.data else {
+        return syn::Error::new_spanned(&input.ident, "Bundle can only be derived for structs")
+            .to_compile_error()
+            .into();
+    };
+
+    let members: Vec<Member> = data.fields.members().collect();
+    let indices = (0..data.fields.len()).map(syn::Index::from);
+    let construct = match &data.fields {
+        Fields::Named(_) => quote! { { #(#members: fields.#indices?,)* } },
+        Fields::Unnamed(_) => quote! { ( #(fields.#indices?,)* ) },
+        Fields::Unit => quote! {},
+    };
+    let field_types: Vec<Type> = data.fields.iter().map(|field| field.ty.clone()).collect();
+
+    let where_clause = input.generics.make_where_clause();
+    for ty in &field_types {
+        where_clause
+            .predicates
+            .push(parse_quote!(#ty: recs::registry::bundle::ComponentBundle));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let expanded = quote! {
+        impl #impl_generics recs::registry::bundle::ComponentBundle for #name #ty_generics #where_clause {
+            fn add_to_entity(
+                self,
+                registry: &mut recs::registry::Registry,
+                entity: recs::entity::Entity,
+            ) -> Result<(), recs::error::RecsError> {
+                #(
+                    recs::registry::bundle::ComponentBundle::add_to_entity(self.#members, registry, entity)?;
+                )*
+                Ok(())
+            }
+
+            fn remove_from_entity(
+                registry: &mut recs::registry::Registry,
+                entity: recs::entity::Entity,
+            ) -> Option<Self> {
+                // Every field is removed before checking that all were present
+                #[allow(unused_variables)]
+                let fields = (#(
+                    <#field_types as recs::registry::bundle::ComponentBundle>::remove_from_entity(registry, entity),
+                )*);
+                Some(#name #construct)
+            }
+
+            #[allow(unused_variables)]
+            fn reserve(registry: &mut recs::registry::Registry, additional: usize) {
+                #(
+                    <#field_types as recs::registry::bundle::ComponentBundle>::reserve(registry, additional);
+                )*
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+/// Implements `Reflect` for a struct, exposing every field by name. Fields of
+/// tuple structs are named by their index.
+#[proc_macro_derive(Reflect)]
+pub fn derive_reflect(input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+
+    let Data::Struct(data) = &input.data else {
+        return syn::Error::new_spanned(&input.ident, "Reflect can only be derived for structs")
+            .to_compile_error()
+            .into();
+    };
+
+    let members: Vec<Member> = data.fields.members().collect();
+    let names: Vec<String> = members
+        .iter()
+        .map(|member| match member {
+            Member::Named(ident) => ident.to_string(),
+            Member::Unnamed(index) => index.index.to_string(),
+        })
+        .collect();
+    let field_types: Vec<Type> = data.fields.iter().map(|field| field.ty.clone()).collect();
+
+    let where_clause = input.generics.make_where_clause();
+    for ty in &field_types {
+        where_clause
+            .predicates
+            .push(parse_quote!(#ty: recs::reflect::Reflect));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let expanded = quote! {
+        impl #impl_generics recs::reflect::Reflect for #name #ty_generics #where_clause {
+            fn field_names(&self) -> &'static [&'static str] {
+                &[#(#names),*]
+            }
+
+            fn field(&self, name: &str) -> Option<&dyn recs::reflect::Reflect> {
+                match name {
+                    #(#names => Some(&self.#members),)*
+                    _ => None,
+                }
+            }
+
+            fn field_mut(&mut self, name: &str) -> Option<&mut dyn recs::reflect::Reflect> {
+                match name {
+                    #(#names => Some(&mut self.#members),)*
+                    _ => None,
+                }
+            }
+
+            fn as_any(&self) -> &dyn std::any::Any {
+                self
+            }
+
+            fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+                self
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}